@@ -0,0 +1,52 @@
+//! `set_attrs_bulk` and `get_attrs_bulk` against one call per node, on 200k
+//! nodes. Run with `cargo bench --bench attrs_bulk`; `CONCORD_BENCH_NODES` scales
+//! the graph. The numpy variant is timed only when numpy is installed. The
+//! README quotes these numbers.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+const BENCH: &str = r#"
+import os
+
+NODES = int(os.environ.get("CONCORD_BENCH_NODES", 200_000))
+rc.configure({"log_level": "warn"})
+
+def timed(label, f):
+    start = time.perf_counter()
+    f()
+    elapsed = time.perf_counter() - start
+    print("%-44s %9.3f s" % (label, elapsed))
+    return elapsed
+
+ids = ["n%d" % i for i in range(NODES)]
+w = rc.GraphWalker()
+w.add_nodes_bulk([(id, []) for id in ids])
+scores = [i / NODES for i in range(NODES)]
+values = [str(s) for s in scores]
+
+def loop():
+    for id, value in zip(ids, values):
+        w.set_attribute(id, "score", value)
+
+naive = timed("set_attribute x %d" % NODES, loop)
+bulk = timed("set_attrs_bulk", lambda: w.set_attrs_bulk(ids, "score", values))
+print("%-44s %9.1fx" % ("  speedup", naive / bulk))
+try:
+    import numpy as np
+except ImportError:
+    print("set_numeric_attrs_bulk                       skipped, no numpy")
+else:
+    array = np.array(scores)
+    timed("set_numeric_attrs_bulk", lambda: w.set_numeric_attrs_bulk(ids, "score", array))
+    timed("set_attrs_bulk, formatting in Python", lambda: w.set_attrs_bulk(ids, "score", [str(s) for s in scores]))
+
+naive = timed("get_attributes x %d" % NODES, lambda: [w.get_attributes(id).get("score") for id in ids])
+bulk = timed("get_attrs_bulk", lambda: w.get_attrs_bulk(ids, "score"))
+print("%-44s %9.1fx" % ("  speedup", naive / bulk))
+assert w.get_attrs_bulk(ids[:3], "score") == values[:3]
+"#;
+
+fn main() {
+    common::run(BENCH);
+}
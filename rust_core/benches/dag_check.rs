@@ -0,0 +1,72 @@
+//! The `enforce_dag` check of one new edge on a 1M-node DAG, by BFS and from a
+//! fresh reachability index. Run with `cargo bench --bench dag_check`;
+//! `CONCORD_BENCH_NODES` scales the graph (four edges per node). The README
+//! quotes these numbers.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+const BENCH: &str = r#"
+import os, random
+
+NODES = int(os.environ.get("CONCORD_BENCH_NODES", 1_000_000))
+DEGREE = 4
+CHECKS = 20
+rc.configure({"log_level": "warn"})
+rng = random.Random(11)
+
+def timed(label, f, per):
+    start = time.perf_counter()
+    f()
+    elapsed = time.perf_counter() - start
+    print("%-56s %9.3f ms per edge" % (label, elapsed * 1000 / per))
+
+# Edges only run from lower to higher numbers, so the graph is a DAG and node 0
+# reaches (nearly) all of it. Added in that order, no check meets a dependent
+# that is already a node, so building the graph checks nothing.
+entries = []
+for i in range(NODES):
+    later = NODES - 1 - i
+    deps = sorted({i + 1} | {i + 1 + rng.randrange(later) for _ in range(DEGREE - 1)}) if later else []
+    entries.append(("n%d" % i, ["n%d" % d for d in deps]))
+dag = rc.GraphWalker(enforce_dag=True)
+dag.add_nodes_bulk(entries)
+del entries
+
+# `n0 -> nX` never closes a cycle, since nothing reaches n0, but the check searches
+# everything nX reaches for n0.
+def accepted(targets):
+    for x in targets:
+        dag.add_edge("n0", "n%d" % x)
+
+timed("add_edge(n0, nX), X < 20: BFS over the whole graph",
+      lambda: accepted(range(2, 2 + CHECKS)), CHECKS)
+timed("add_edge(n0, nX), X near the end: BFS over ~1000 nodes",
+      lambda: accepted(range(NODES - 1000, NODES - 1000 + CHECKS)), CHECKS)
+
+build = check = 0.0
+for x in range(2 + CHECKS, 2 + 2 * CHECKS):
+    start = time.perf_counter()
+    dag.build_reachability_index()
+    built = time.perf_counter()
+    dag.add_edge("n0", "n%d" % x)
+    build += built - start
+    check += time.perf_counter() - built
+print("%-56s %9.3f ms per edge" % ("add_edge(n0, nX), X < 40: check with a fresh index", check * 1000 / CHECKS))
+print("%-56s %9.3f ms" % ("  build_reachability_index() before each", build * 1000 / CHECKS))
+
+def rejected():
+    for _ in range(CHECKS):
+        try:
+            dag.add_edge("n%d" % (NODES - 1), "n0")
+        except rc.CycleError:
+            pass
+        else:
+            raise AssertionError("no CycleError")
+dag.build_reachability_index()
+timed("add_edge(nLast, n0) rejected: index + BFS for the cycle", rejected, CHECKS)
+"#;
+
+fn main() {
+    common::run(BENCH);
+}
@@ -0,0 +1,58 @@
+//! `mark_invalid` against `propagate_invalidation` for overlapping invalidations
+//! on a 1M-node DAG. Run with `cargo bench --bench dirty_tracking`;
+//! `CONCORD_BENCH_NODES` scales the graph (three edges per node). The README
+//! quotes these numbers.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+const BENCH: &str = r#"
+import os, random
+
+NODES = int(os.environ.get("CONCORD_BENCH_NODES", 1_000_000))
+DEGREE = 3
+SPAN = 50
+STARTS = 200
+rc.configure({"log_level": "warn"})
+rng = random.Random(7)
+
+def timed(label, f):
+    start = time.perf_counter()
+    result = f()
+    print("%-56s %9.3f s" % (label, time.perf_counter() - start))
+    return result
+
+def expanded():
+    return w.computation_stats()["invalid_expanded"]
+
+# Each node has edges into three of the next SPAN ids, so blast radii are long
+# and overlap heavily.
+w = rc.GraphWalker()
+w.add_nodes_bulk([
+    ("n%d" % i, ["n%d" % (i + 1 + rng.randrange(min(SPAN, NODES - 1 - i))) for _ in range(DEGREE)])
+    if i < NODES - 1 else ("n%d" % i, [])
+    for i in range(NODES)
+])
+starts = ["n%d" % rng.randrange(NODES // 10) for _ in range(STARTS)]
+
+timed("propagate_invalidation, %d starts" % (STARTS // 10),
+      lambda: [w.propagate_invalidation(s) for s in starts[:STARTS // 10]])
+timed("mark_invalid, the first %d starts" % (STARTS // 10),
+      lambda: [w.mark_invalid(s) for s in starts[:STARTS // 10]])
+before = expanded()
+timed("mark_invalid, the other %d starts" % (STARTS - STARTS // 10),
+      lambda: [w.mark_invalid(s) for s in starts[STARTS // 10:]])
+print("%-56s %9d" % ("  ids walked by them", expanded() - before))
+print("%-56s %9d" % ("ids invalid", w.invalid_count()))
+
+cleared = ["n%d" % rng.randrange(NODES // 2, NODES) for _ in range(1000)]
+timed("clear_invalid, 1000 ids", lambda: w.clear_invalid(cleared))
+before = expanded()
+fresh = timed("mark_invalid(n0) after the clear", lambda: w.mark_invalid("n0"))
+print("%-56s %9d" % ("  ids walked", expanded() - before))
+print("%-56s %9d" % ("  ids re-marked", len(fresh)))
+"#;
+
+fn main() {
+    common::run(BENCH);
+}
@@ -0,0 +1,56 @@
+//! Edge aging on a 1M-node, 10M-edge graph, and consumers re-observing facts.
+//! Run with `cargo bench --bench edge_aging`; `CONCORD_BENCH_NODES` scales the
+//! graph (ten edges per node). The README quotes these numbers.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+const BENCH: &str = r#"
+import os
+
+NODES = int(os.environ.get("CONCORD_BENCH_NODES", 1_000_000))
+DEGREE = 10
+FACTS = 20_000
+rc.configure({"log_level": "warn"})
+
+def timed(label, f):
+    start = time.perf_counter()
+    result = f()
+    print("%-44s %9.3f s" % (label, time.perf_counter() - start))
+    return result
+
+ids = ["n%d" % i for i in range(NODES)]
+indices = [(i * 7919 + k * 104729 + 1) % NODES for i in range(NODES) for k in range(DEGREE)]
+w = timed("from_csr, %d edges" % len(indices), lambda: rc.GraphWalker.from_csr(
+    list(range(0, len(indices) + 1, DEGREE)), indices, ids))
+timed("decay_weights(0.5)", lambda: w.decay_weights(0.5))
+later = time.time() + 3600
+timed("touch_edge x 100k", lambda: [
+    w.touch_edge(ids[i], ids[indices[i * DEGREE]], later) for i in range(100_000)])
+removed = timed("prune_edges_below(0.75)", lambda: w.prune_edges_below(0.75))
+assert removed == len(indices) - 100_000, removed
+del w, indices
+
+def consume(label, facts):
+    broker = rc.MemoryBroker()
+    for id, dependents in facts:
+        broker.send("facts", json.dumps({"fact_id": id, "dependents": dependents}))
+    handle = rc.start_kafka_consumer(broker, "facts", walker=shared)
+    start = time.perf_counter()
+    wait_until(lambda: handle.metrics()["messages"] == len(facts), timeout=600)
+    handle.stop()
+    elapsed = time.perf_counter() - start
+    print("%-44s %9.3f s  %8.0f msg/s" % (label, elapsed, len(facts) / elapsed))
+
+facts = [("f%d" % i, ["f%d" % ((i * 31 + k) % FACTS) for k in range(1, DEGREE + 1)])
+         for i in range(FACTS)]
+shared = rc.SharedGraphWalker()
+consume("consumer, %d new facts" % FACTS, facts)
+consume("consumer, the same facts re-observed", facts)
+consume("consumer, re-observed with one edge changed",
+        [(id, deps[:-1] + ["x%d" % i]) for i, (id, deps) in enumerate(facts)])
+"#;
+
+fn main() {
+    common::run(BENCH);
+}
@@ -0,0 +1,52 @@
+//! Marshalling a 500k-node propagation into Python: a set of str against a
+//! numpy array of interned handles. Run with `cargo bench --bench result_arrays`;
+//! `CONCORD_BENCH_NODES` scales the graph. The array rows need numpy and are
+//! skipped without it. The README quotes these numbers.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+const BENCH: &str = r#"
+import os
+
+NODES = int(os.environ.get("CONCORD_BENCH_NODES", 500_000))
+DEGREE = 4
+rc.configure({"log_level": "warn"})
+
+def timed(label, f, repeat=5):
+    best = float("inf")
+    for _ in range(repeat):
+        start = time.perf_counter()
+        result = f()
+        best = min(best, time.perf_counter() - start)
+    print("%-44s %9.1f ms" % (label, best * 1000))
+    return result
+
+# Node i depends on i + 1 and three pseudo-random later nodes, so "n0" reaches
+# every other node.
+ids = ["n%d" % i for i in range(NODES)]
+indptr, indices = [0], []
+for i in range(NODES):
+    later = NODES - 1 - i
+    if later:
+        indices.extend(sorted({i + 1} | {i + 1 + (i * 7919 + k * 104729) % later for k in range(DEGREE - 1)}))
+    indptr.append(len(indices))
+w = rc.GraphWalker.from_csr(indptr, indices, ids)
+
+strings = timed("propagate_invalidation -> set of str", lambda: w.propagate_invalidation("n0"))
+assert len(strings) == NODES - 1, len(strings)
+table = timed("id_table -> list of str", w.id_table)
+timed("detect_communities -> lists of str", lambda: w.detect_communities())
+try:
+    import numpy
+except ImportError:
+    print("numpy is not installed; skipping the array rows")
+else:
+    handles = timed("propagate_invalidation_ids -> int64 array", lambda: w.propagate_invalidation_ids("n0"))
+    assert {table[h] for h in handles.tolist()} == strings
+    timed("detect_communities_ids -> int64 arrays", lambda: w.detect_communities_ids())
+"#;
+
+fn main() {
+    common::run(BENCH);
+}
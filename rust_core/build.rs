@@ -0,0 +1,14 @@
+// The extension module leaves libpython to the interpreter that loads it, but
+// test and benchmark binaries run on their own and have to link it.
+fn main() {
+    let config = pyo3_build_config::get();
+    for target in ["tests", "benches"] {
+        if let Some(dir) = &config.lib_dir {
+            println!("cargo:rustc-link-arg-{}=-L{}", target, dir);
+            println!("cargo:rustc-link-arg-{}=-Wl,-rpath,{}", target, dir);
+        }
+        if let Some(name) = &config.lib_name {
+            println!("cargo:rustc-link-arg-{}=-l{}", target, name);
+        }
+    }
+}
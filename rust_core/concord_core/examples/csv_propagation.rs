@@ -0,0 +1,57 @@
+//! Builds a graph from a `source,dependent` CSV and prints what invalidating a node
+//! affects.
+//!
+//!     cargo run -p concord-core --example csv_propagation -- [path.csv] [start-id]
+//!
+//! Without arguments it uses the bundled `data/dependencies.csv` and `user:42`.
+
+use concord_core::{Graph, Limits};
+use std::error::Error;
+
+const SAMPLE: &str = include_str!("data/dependencies.csv");
+
+/// Parses `source,dependent` lines; blank lines and `#` comments are skipped.
+fn load_csv(text: &str) -> Result<Graph, String> {
+    let mut graph = Graph::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (from, to) = line
+            .split_once(',')
+            .ok_or_else(|| format!("line {}: expected source,dependent", n + 1))?;
+        graph.add_edge(from.trim(), to.trim());
+    }
+    Ok(graph)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = std::env::args().skip(1);
+    let text = match args.next() {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => SAMPLE.to_string(),
+    };
+    let start = args.next().unwrap_or_else(|| "user:42".to_string());
+
+    let graph = load_csv(&text)?;
+    println!(
+        "loaded {} nodes, {} edges",
+        graph.node_count(),
+        graph.edge_count()
+    );
+
+    let result = graph.propagate(&start, &Limits::none().with_max_results(10_000))?;
+    let mut affected: Vec<&String> = result.affected.iter().collect();
+    affected.sort();
+    println!(
+        "invalidating {} affects {} nodes ({} levels deep):",
+        start,
+        affected.len(),
+        result.depth
+    );
+    for id in affected {
+        println!("  {}", id);
+    }
+    Ok(())
+}
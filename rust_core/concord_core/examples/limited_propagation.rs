@@ -0,0 +1,43 @@
+//! Loads the bundled CSV, ranks nodes by PageRank and propagates from the most
+//! central source under tight limits, showing how a limit error is reported.
+//!
+//!     cargo run -p concord-core --example limited_propagation
+
+use concord_core::{Error, Graph, Limits, PageRankOptions};
+
+const SAMPLE: &str = include_str!("data/dependencies.csv");
+
+fn main() {
+    let mut graph = Graph::new();
+    for line in SAMPLE
+        .lines()
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+    {
+        if let Some((from, to)) = line.split_once(',') {
+            graph.add_edge(from, to);
+        }
+    }
+
+    let ranks = graph.pagerank(&PageRankOptions::default().with_iterations(30));
+    let mut ranked: Vec<(&String, &f64)> = ranks.iter().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(a.1).then(a.0.cmp(b.0)));
+    println!("most central nodes:");
+    for (id, rank) in ranked.iter().take(3) {
+        println!("  {:<20} {:.4}", id, rank);
+    }
+
+    let limits = Limits::none().with_max_results(2);
+    for start in ["catalog:7", "user:42", "user:7"] {
+        match graph.propagate(start, &limits) {
+            Ok(result) => println!("{}: {} affected", start, result.affected.len()),
+            Err(e @ (Error::LimitExceeded { .. } | Error::UnknownNode { .. })) => {
+                println!("{}: {}", start, e)
+            }
+            Err(e) => println!("{}: unexpected error: {}", start, e),
+        }
+    }
+    match graph.descendants("user:42") {
+        Ok(all) => println!("descendants of user:42 without limits: {}", all.len()),
+        Err(e) => println!("user:42: {}", e),
+    }
+}
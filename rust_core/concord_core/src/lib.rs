@@ -0,0 +1,352 @@
+//! Dependency graph and invalidation propagation for Rust services.
+//!
+//! This is the propagation model of the Python `GraphWalker` without the Python
+//! bindings: a node's *dependents* are the nodes invalidated when it changes, and
+//! propagating from a node yields every node reachable through dependents. The
+//! bindings run their propagations through `propagate_with`, so both agree on
+//! limits, depth and errors.
+//!
+//! ```
+//! use concord_core::{Graph, Limits};
+//!
+//! let mut graph = Graph::new();
+//! graph.add_node("user:42", ["session:42", "profile:42"]);
+//! graph.add_edge("profile:42", "feed:42");
+//!
+//! let result = graph.propagate("user:42", &Limits::none()).unwrap();
+//! assert_eq!(result.affected.len(), 3);
+//! ```
+//!
+//! Types marked `#[non_exhaustive]` may gain fields or variants in minor releases;
+//! construct them through their constructors and match them with a wildcard arm.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+
+/// Directed dependency graph keyed by string ids.
+///
+/// Edges may point at ids that were never added as nodes; such dangling targets are
+/// still reported by propagation but have no dependents of their own.
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    nodes: HashMap<String, Vec<String>>,
+}
+
+/// Caps on a single propagation. `None` means unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Limits {
+    /// Maximum number of affected nodes.
+    pub max_results: Option<usize>,
+    /// Maximum number of nodes expanded, counting the start node.
+    pub max_visited: Option<usize>,
+}
+
+impl Limits {
+    /// No caps.
+    pub fn none() -> Self {
+        Limits::default()
+    }
+
+    pub fn with_max_results(mut self, max_results: usize) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    pub fn with_max_visited(mut self, max_visited: usize) -> Self {
+        self.max_visited = Some(max_visited);
+        self
+    }
+
+    /// Fails if `results` or `visited` exceeds its cap, `max_results` first.
+    pub fn check(&self, visited: usize, results: usize) -> Result<(), Error> {
+        let exceeded = match (self.max_results, self.max_visited) {
+            (Some(cap), _) if results > cap => Some(("max_results", cap)),
+            (_, Some(cap)) if visited > cap => Some(("max_visited", cap)),
+            _ => None,
+        };
+        match exceeded {
+            Some((limit, cap)) => Err(Error::LimitExceeded {
+                limit,
+                cap,
+                visited,
+                results,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Outcome of `Graph::propagate`, or of `propagate_with` over nodes of type `N`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Propagation<N: Eq + Hash = String> {
+    /// Every node reachable from the start node (excluding the start itself unless it
+    /// lies on a cycle).
+    pub affected: HashSet<N>,
+    /// Number of nodes expanded, counting the start node.
+    pub visited: usize,
+    /// Number of breadth-first levels below the start node.
+    pub depth: usize,
+}
+
+/// Parameters for `Graph::pagerank`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct PageRankOptions {
+    pub iterations: usize,
+    pub damping: f64,
+}
+
+impl Default for PageRankOptions {
+    fn default() -> Self {
+        PageRankOptions {
+            iterations: 20,
+            damping: 0.85,
+        }
+    }
+}
+
+impl PageRankOptions {
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    pub fn with_damping(mut self, damping: f64) -> Self {
+        self.damping = damping;
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A propagation exceeded one of its `Limits`; `limit` names it
+    /// (`"max_results"` or `"max_visited"`).
+    LimitExceeded {
+        limit: &'static str,
+        cap: usize,
+        visited: usize,
+        results: usize,
+    },
+    /// The start of a propagation is not a node. Ids that only appear as edge
+    /// targets are not nodes.
+    UnknownNode { id: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::LimitExceeded {
+                limit,
+                cap,
+                visited,
+                results,
+            } => write!(
+                f,
+                "{} limit of {} exceeded after visiting {} nodes with {} results",
+                limit, cap, visited, results
+            ),
+            Error::UnknownNode { id } => write!(f, "unknown node '{}'", id),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Graph {
+    pub fn new() -> Self {
+        Graph::default()
+    }
+
+    /// Sets `id`'s dependents, replacing any it had. Duplicate dependents are kept
+    /// once.
+    pub fn add_node<I, S>(&mut self, id: impl Into<String>, dependents: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut deps: Vec<String> = Vec::new();
+        for dep in dependents {
+            let dep = dep.into();
+            if !deps.contains(&dep) {
+                deps.push(dep);
+            }
+        }
+        self.nodes.insert(id.into(), deps);
+    }
+
+    /// Adds `from -> to`, creating `from` if needed. Returns false if the edge
+    /// already existed.
+    pub fn add_edge(&mut self, from: impl Into<String>, to: impl Into<String>) -> bool {
+        let to = to.into();
+        let deps = self.nodes.entry(from.into()).or_default();
+        if deps.contains(&to) {
+            return false;
+        }
+        deps.push(to);
+        true
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.nodes.contains_key(id)
+    }
+
+    /// Direct dependents of `id`, or None if it is not a node.
+    pub fn dependents(&self, id: &str) -> Option<&[String]> {
+        self.nodes.get(id).map(Vec::as_slice)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.nodes.values().map(Vec::len).sum()
+    }
+
+    /// Node ids in unspecified order.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.nodes.keys().map(String::as_str)
+    }
+
+    /// Breadth-first propagation from `start`, failing as soon as a limit is
+    /// exceeded, or with `Error::UnknownNode` if `start` is not a node.
+    pub fn propagate(&self, start: &str, limits: &Limits) -> Result<Propagation, Error> {
+        let Some((start, _)) = self.nodes.get_key_value(start) else {
+            return Err(Error::UnknownNode {
+                id: start.to_string(),
+            });
+        };
+        let result = propagate_with(
+            start.as_str(),
+            limits,
+            |frontier| {
+                frontier
+                    .iter()
+                    .flat_map(|&id| {
+                        let deps = self.nodes.get(id).into_iter().flatten();
+                        deps.map(move |dep| (id, dep.as_str()))
+                    })
+                    .collect()
+            },
+            |_, _, _| {},
+        )?;
+        Ok(Propagation {
+            affected: result.affected.into_iter().map(String::from).collect(),
+            visited: result.visited,
+            depth: result.depth,
+        })
+    }
+
+    /// Every node reachable from `start`, without limits. Fails like `propagate`
+    /// if `start` is not a node.
+    pub fn descendants(&self, start: &str) -> Result<HashSet<String>, Error> {
+        self.propagate(start, &Limits::none())
+            .map(|result| result.affected)
+    }
+
+    /// PageRank by power iteration over the known nodes, in sorted id order so the
+    /// result is reproducible. Only edges between known nodes carry rank; a node
+    /// without any spreads its rank over all nodes, so the ranks sum to 1.
+    pub fn pagerank(&self, options: &PageRankOptions) -> HashMap<String, f64> {
+        let mut ids: Vec<&String> = self.nodes.keys().collect();
+        ids.sort();
+        let n = ids.len();
+        if n == 0 {
+            return HashMap::new();
+        }
+        let index: HashMap<&str, usize> = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.as_str(), i))
+            .collect();
+        let mut sources: Vec<Vec<usize>> = vec![Vec::new(); n];
+        for (u, id) in ids.iter().enumerate() {
+            for dep in &self.nodes[*id] {
+                if let Some(&v) = index.get(dep.as_str()) {
+                    sources[v].push(u);
+                }
+            }
+        }
+        let mut out_degree = vec![0usize; n];
+        for targets in &sources {
+            for &u in targets {
+                out_degree[u] += 1;
+            }
+        }
+        let mut ranks = vec![1.0 / n as f64; n];
+        for _ in 0..options.iterations {
+            // Nodes without edges to known nodes spread their rank over all nodes.
+            let dangling: f64 = (0..n)
+                .filter(|&u| out_degree[u] == 0)
+                .map(|u| ranks[u])
+                .sum();
+            ranks = (0..n)
+                .map(|v| {
+                    let inflow: f64 = sources[v]
+                        .iter()
+                        .map(|&u| ranks[u] / out_degree[u] as f64)
+                        .sum();
+                    (1.0 - options.damping) / n as f64
+                        + options.damping * (inflow + dangling / n as f64)
+                })
+                .collect();
+        }
+        ids.into_iter().cloned().zip(ranks).collect()
+    }
+}
+
+/// The level-by-level propagation behind `Graph::propagate`, over any node type,
+/// for graphs stored some other way.
+///
+/// `expand(frontier)` returns the `(from, to)` edges leaving the frontier that
+/// propagation may follow, already filtered by the caller. Each `to` not reached
+/// before joins the next frontier. `reached(from, to, first)` is called for every
+/// returned edge whose `to` was first reached at this level; `first` is true for
+/// the edge that reached it. Limits are checked before and after each level, so
+/// a propagation stops as soon as it exceeds one.
+pub fn propagate_with<N, E, R>(
+    start: N,
+    limits: &Limits,
+    mut expand: E,
+    mut reached: R,
+) -> Result<Propagation<N>, Error>
+where
+    N: Copy + Eq + Hash,
+    E: FnMut(&[N]) -> Vec<(N, N)>,
+    R: FnMut(N, N, bool),
+{
+    let mut affected: HashSet<N> = HashSet::new();
+    let mut frontier: Vec<N> = vec![start];
+    let mut visited = 0;
+    let mut depth = 0;
+    while !frontier.is_empty() {
+        visited += frontier.len();
+        limits.check(visited, affected.len())?;
+        let mut next = Vec::new();
+        // Nodes first reached at this level.
+        let mut fresh: HashSet<N> = HashSet::new();
+        for (from, to) in expand(&frontier) {
+            if affected.insert(to) {
+                next.push(to);
+                fresh.insert(to);
+                reached(from, to, true);
+            } else if fresh.contains(&to) {
+                reached(from, to, false);
+            }
+        }
+        limits.check(visited, affected.len())?;
+        if !next.is_empty() {
+            depth += 1;
+        }
+        frontier = next;
+    }
+    Ok(Propagation {
+        affected,
+        visited,
+        depth,
+    })
+}
@@ -0,0 +1,137 @@
+//! Guards the public surface: each item is named with its full signature, so a
+//! rename or signature change fails to compile here before it reaches a
+//! dependent crate. Changing this file means the release needs a new minor
+//! version (the crate is pre-1.0).
+
+use concord_core::{propagate_with, Error, Graph, Limits, PageRankOptions, Propagation};
+use std::collections::{HashMap, HashSet};
+
+#[test]
+fn signatures_are_stable() {
+    let _: fn() -> Graph = Graph::new;
+    let _: fn(&mut Graph, &str, &str) -> bool = |g, a, b| g.add_edge(a, b);
+    let _: fn(&mut Graph, &str, Vec<&str>) = |g, a, deps| g.add_node(a, deps);
+    let _: fn(&Graph, &str) -> bool = Graph::contains;
+    let _: for<'a> fn(&'a Graph, &str) -> Option<&'a [String]> = Graph::dependents;
+    let _: fn(&Graph) -> usize = Graph::node_count;
+    let _: fn(&Graph) -> usize = Graph::edge_count;
+    let _: fn(&Graph, &str, &Limits) -> Result<Propagation, Error> = Graph::propagate;
+    let _: fn(&Graph, &str) -> Result<HashSet<String>, Error> = Graph::descendants;
+    let _: fn(&Graph, &PageRankOptions) -> HashMap<String, f64> = Graph::pagerank;
+
+    let _: fn() -> Limits = Limits::none;
+    let _: fn(Limits, usize) -> Limits = Limits::with_max_results;
+    let _: fn(Limits, usize) -> Limits = Limits::with_max_visited;
+    let _: fn(&Limits, usize, usize) -> Result<(), Error> = Limits::check;
+    let _: fn(PageRankOptions, usize) -> PageRankOptions = PageRankOptions::with_iterations;
+    let _: fn(PageRankOptions, f64) -> PageRankOptions = PageRankOptions::with_damping;
+}
+
+#[test]
+fn public_fields_stay_readable() {
+    let limits = Limits::none().with_max_results(3).with_max_visited(4);
+    let (_, _): (Option<usize>, Option<usize>) = (limits.max_results, limits.max_visited);
+    let options = PageRankOptions::default();
+    let (_, _): (usize, f64) = (options.iterations, options.damping);
+
+    let mut graph = Graph::new();
+    graph.add_node("a", ["b"]);
+    let result = graph.propagate("a", &Limits::none()).unwrap();
+    let (_, _, _): (&HashSet<String>, usize, usize) =
+        (&result.affected, result.visited, result.depth);
+}
+
+#[test]
+fn errors_are_matched_with_a_wildcard_arm() {
+    let error = Graph::new().propagate("x", &Limits::none()).unwrap_err();
+    // Error is non_exhaustive: dependents need the wildcard arm, so adding a
+    // variant is not a breaking change.
+    let name = match error {
+        Error::LimitExceeded { limit, .. } => limit.to_string(),
+        Error::UnknownNode { id } => id,
+        _ => unreachable!(),
+    };
+    assert_eq!(name, "x");
+    let _: &dyn std::error::Error = &Error::UnknownNode { id: name };
+}
+
+#[test]
+fn unknown_start_is_an_error() {
+    let mut graph = Graph::new();
+    graph.add_node("a", ["b"]);
+    let unknown = Error::UnknownNode { id: "b".into() };
+    // "b" is only an edge target.
+    assert_eq!(graph.propagate("b", &Limits::none()), Err(unknown.clone()));
+    assert_eq!(graph.descendants("b"), Err(unknown));
+    assert_eq!(
+        graph.descendants("a").unwrap(),
+        HashSet::from(["b".to_string()])
+    );
+}
+
+#[test]
+fn limits_report_how_far_propagation_got() {
+    let mut graph = Graph::new();
+    graph.add_node("a", ["b", "c"]);
+    graph.add_node("b", ["d"]);
+    let error = graph
+        .propagate("a", &Limits::none().with_max_results(1))
+        .unwrap_err();
+    assert_eq!(
+        error,
+        Error::LimitExceeded {
+            limit: "max_results",
+            cap: 1,
+            visited: 1,
+            results: 2,
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "max_results limit of 1 exceeded after visiting 1 nodes with 2 results"
+    );
+    let result = graph.propagate("a", &Limits::none()).unwrap();
+    assert_eq!(
+        (result.affected.len(), result.visited, result.depth),
+        (3, 4, 2)
+    );
+}
+
+#[test]
+fn propagate_with_reports_every_parent_at_the_first_level() {
+    // 0 -> 1, 0 -> 2, 1 -> 3, 2 -> 3, 3 -> 0
+    let edges: Vec<Vec<u32>> = vec![vec![1, 2], vec![3], vec![3], vec![0]];
+    let mut parents: HashMap<u32, Vec<u32>> = HashMap::new();
+    let result = propagate_with(
+        0u32,
+        &Limits::none(),
+        |frontier| {
+            frontier
+                .iter()
+                .flat_map(|&u| edges[u as usize].iter().map(move |&v| (u, v)))
+                .collect()
+        },
+        |from, to, first| {
+            let list = parents.entry(to).or_default();
+            assert_eq!(first, list.is_empty());
+            list.push(from);
+        },
+    )
+    .unwrap();
+    assert_eq!(result.affected, HashSet::from([0, 1, 2, 3]));
+    assert_eq!(result.depth, 3);
+    assert_eq!(parents[&3], vec![1, 2]);
+    assert_eq!(parents[&0], vec![3]);
+}
+
+#[test]
+fn pagerank_sums_to_one() {
+    let mut graph = Graph::new();
+    graph.add_node("a", ["b", "c"]);
+    graph.add_node("b", ["c"]);
+    graph.add_node("c", ["a"]);
+    let ranks = graph.pagerank(&PageRankOptions::default().with_iterations(50));
+    let total: f64 = ranks.values().sum();
+    assert!((total - 1.0).abs() < 1e-9);
+    assert!(ranks["c"] > ranks["b"]);
+}
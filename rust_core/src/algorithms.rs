@@ -0,0 +1,1319 @@
+//! Graph algorithms over the raw node map, shared by several GraphWalker methods.
+//! Everything here is iterative so deep graphs cannot overflow the thread stack.
+
+use crate::store::NodeStore;
+use crate::CausalNode;
+use std::collections::{HashMap, VecDeque};
+
+/// Dense index view of the node map: known node ids sorted, each with the indices of
+/// its dependents that are themselves known nodes (dangling targets are skipped).
+pub struct IndexedGraph<'a> {
+    pub ids: Vec<&'a String>,
+    pub adj: Vec<Vec<usize>>,
+    /// Edge weights, parallel to `adj`.
+    pub weights: Vec<Vec<f64>>,
+    /// Out-degree including dangling targets, as stored on the node.
+    pub out_degree: Vec<usize>,
+}
+
+impl<'a> IndexedGraph<'a> {
+    pub fn new(nodes: &'a NodeStore) -> Self {
+        let mut handles: Vec<(&String, u32, &CausalNode)> = nodes
+            .handles()
+            .map(|(h, node)| (nodes.name(h), h, node))
+            .collect();
+        handles.sort_unstable_by_key(|&(id, _, _)| id);
+        // Handle to dense index; ids that are not nodes stay unmapped.
+        let mut index = vec![usize::MAX; nodes.ids().len()];
+        for (i, &(_, h, _)) in handles.iter().enumerate() {
+            index[h as usize] = i;
+        }
+        let (adj, weights) = handles
+            .iter()
+            .map(|&(_, _, node)| {
+                node.dependents
+                    .iter()
+                    .zip(&node.weights)
+                    .filter_map(|(&d, &w)| match index[d as usize] {
+                        usize::MAX => None,
+                        v => Some((v, w)),
+                    })
+                    .unzip()
+            })
+            .unzip();
+        let out_degree = handles
+            .iter()
+            .map(|&(_, _, node)| node.dependents.len())
+            .collect();
+        let ids = handles.into_iter().map(|(id, _, _)| id).collect();
+        IndexedGraph {
+            ids,
+            adj,
+            weights,
+            out_degree,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Incoming adjacency (indices of sources for each node).
+    pub fn reverse(&self) -> Vec<Vec<usize>> {
+        let mut rev = vec![Vec::new(); self.len()];
+        for (u, deps) in self.adj.iter().enumerate() {
+            for &v in deps {
+                rev[v].push(u);
+            }
+        }
+        rev
+    }
+}
+
+/// Weakly connected components as sorted index lists, ordered by size (largest
+/// first) and then by smallest member.
+pub fn weak_components(graph: &IndexedGraph) -> Vec<Vec<usize>> {
+    let mut uf = UnionFind::new(graph.len());
+    for (u, deps) in graph.adj.iter().enumerate() {
+        for &v in deps {
+            uf.union(u, v);
+        }
+    }
+    let mut by_root: HashMap<usize, Vec<usize>> = HashMap::new();
+    for v in 0..graph.len() {
+        by_root.entry(uf.find(v)).or_default().push(v);
+    }
+    // Members are pushed in increasing index order, so each list is already sorted.
+    let mut components: Vec<Vec<usize>> = by_root.into_values().collect();
+    components.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+    components
+}
+
+/// How a PageRank run ended: iterations performed and the L1 change of the last one
+/// (None if none ran).
+#[derive(Clone, Copy, Debug)]
+pub struct Convergence {
+    pub iterations: usize,
+    pub delta: Option<f64>,
+}
+
+impl Convergence {
+    /// Stops after the iteration whose L1 change is below `tolerance`.
+    pub fn step(&mut self, delta: f64, tolerance: Option<f64>) -> bool {
+        self.iterations += 1;
+        self.delta = Some(delta);
+        tolerance.is_some_and(|t| delta < t)
+    }
+}
+
+/// PageRank by power iteration in index order, so results are bit-identical across
+/// runs and processes for the same graph content. Only edges between nodes carry
+/// rank, split in proportion to edge weight (negative weights count as 0); nodes
+/// without positive outgoing weight are dangling and spread theirs over all nodes.
+pub fn pagerank(graph: &IndexedGraph, iterations: usize, damping: f64) -> Vec<f64> {
+    pagerank_until(graph, iterations, damping, None, None).0
+}
+
+/// `pagerank` that stops early once an iteration changes the ranks by less than
+/// `tolerance` (L1); `iterations` is the cap. With `teleport` (summing to 1), random
+/// jumps and dangling rank go to nodes in those proportions instead of uniformly
+/// (personalized PageRank).
+pub fn pagerank_until(
+    graph: &IndexedGraph,
+    iterations: usize,
+    damping: f64,
+    tolerance: Option<f64>,
+    teleport: Option<&[f64]>,
+) -> (Vec<f64>, Convergence) {
+    let n = graph.len();
+    let mut convergence = Convergence {
+        iterations: 0,
+        delta: None,
+    };
+    if n == 0 {
+        return (Vec::new(), convergence);
+    }
+    let rev = weighted_in_edges(graph);
+    let out_weight: Vec<f64> = graph
+        .weights
+        .iter()
+        .map(|ws| ws.iter().map(|w| w.max(0.0)).sum())
+        .collect();
+    let mut ranks = match teleport {
+        Some(t) => t.to_vec(),
+        None => vec![1.0 / n as f64; n],
+    };
+    for _ in 0..iterations {
+        // Rank held by nodes without edges to other nodes is spread like the random
+        // jumps, so the ranks keep summing to 1.
+        let dangling: f64 = (0..n)
+            .filter(|&u| out_weight[u] == 0.0)
+            .map(|u| ranks[u])
+            .sum();
+        let next: Vec<f64> = (0..n)
+            .map(|v| {
+                let rank_sum: f64 = rev[v]
+                    .iter()
+                    .map(|&(u, w)| ranks[u] * w / out_weight[u])
+                    .sum();
+                match teleport {
+                    None => (1.0 - damping) / n as f64 + damping * (rank_sum + dangling / n as f64),
+                    Some(t) => (1.0 - damping) * t[v] + damping * (rank_sum + dangling * t[v]),
+                }
+            })
+            .collect();
+        let delta = next.iter().zip(&ranks).map(|(a, b)| (a - b).abs()).sum();
+        ranks = next;
+        if convergence.step(delta, tolerance) {
+            break;
+        }
+    }
+    (ranks, convergence)
+}
+
+/// Sources of each node with their edge weights (negative weights count as 0), in
+/// index order.
+pub fn weighted_in_edges(graph: &IndexedGraph) -> Vec<Vec<(usize, f64)>> {
+    let mut rev: Vec<Vec<(usize, f64)>> = vec![Vec::new(); graph.len()];
+    for (u, (out, weights)) in graph.adj.iter().zip(&graph.weights).enumerate() {
+        for (&v, &w) in out.iter().zip(weights) {
+            rev[v].push((u, w.max(0.0)));
+        }
+    }
+    rev
+}
+
+/// `y[v] = Σ w(u, v) · x[u]` over the incoming edges `rev` (see `weighted_in_edges`),
+/// i.e. the transposed adjacency times `x`. Rows are computed in parallel, each
+/// summed in index order, so the result does not depend on the thread count.
+pub fn in_edge_matvec(rev: &[Vec<(usize, f64)>], x: &[f64]) -> Vec<f64> {
+    use rayon::prelude::*;
+    rev.par_iter()
+        .map(|sources| sources.iter().map(|&(u, w)| w * x[u]).sum())
+        .collect()
+}
+
+/// Why a power iteration returned no scores.
+#[derive(Clone, Copy, Debug)]
+pub enum PowerFailure {
+    /// Still changing by more than the tolerance after this many iterations.
+    NotConverged(usize),
+    /// The scores grew without bound.
+    Diverged,
+}
+
+/// Sum of absolute differences.
+fn l1_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum()
+}
+
+fn l2_normalize(x: &mut [f64]) {
+    let norm = x.iter().map(|v| v * v).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        for v in x.iter_mut() {
+            *v /= norm;
+        }
+    }
+}
+
+/// Eigenvector centrality: the principal eigenvector of the transposed weighted
+/// adjacency, so a node scores high when high-scoring nodes point at it. Power
+/// iteration on `A^T + I` (the identity shift keeps bipartite graphs from
+/// oscillating and leaves the eigenvector unchanged), from the uniform vector,
+/// normalized to unit L2 length each step. Converged once an iteration moves the
+/// scores by less than `n · tolerance` (L1), as networkx does.
+pub fn eigenvector_centrality(
+    graph: &IndexedGraph,
+    max_iter: usize,
+    tolerance: f64,
+) -> Result<Vec<f64>, PowerFailure> {
+    let n = graph.len();
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    let rev = weighted_in_edges(graph);
+    let mut x = vec![1.0 / n as f64; n];
+    for _ in 0..max_iter {
+        let mut next = in_edge_matvec(&rev, &x);
+        for (y, &v) in next.iter_mut().zip(&x) {
+            *y += v;
+        }
+        l2_normalize(&mut next);
+        let delta = l1_distance(&next, &x);
+        x = next;
+        if delta < n as f64 * tolerance {
+            return Ok(x);
+        }
+    }
+    Err(PowerFailure::NotConverged(max_iter))
+}
+
+/// Katz centrality: the fixed point of `x = alpha · A^T x + beta`, so every node
+/// starts from `beta` and gains `alpha^k` for each walk of length `k` into it.
+/// Iterated from zero until an iteration moves the scores by less than
+/// `n · tolerance` (L1), then normalized to unit L2 length. The series converges
+/// only when `alpha` is below 1 / spectral radius; above it the changes grow each
+/// iteration, which is reported as `Diverged`.
+pub fn katz_centrality(
+    graph: &IndexedGraph,
+    alpha: f64,
+    beta: f64,
+    max_iter: usize,
+    tolerance: f64,
+) -> Result<Vec<f64>, PowerFailure> {
+    let n = graph.len();
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    let rev = weighted_in_edges(graph);
+    let mut x = vec![0.0; n];
+    let mut last_delta = f64::INFINITY;
+    for _ in 0..max_iter {
+        let mut next = in_edge_matvec(&rev, &x);
+        for y in next.iter_mut() {
+            *y = alpha * *y + beta;
+        }
+        let delta = l1_distance(&next, &x);
+        x = next;
+        if !delta.is_finite() {
+            return Err(PowerFailure::Diverged);
+        }
+        if delta < n as f64 * tolerance {
+            l2_normalize(&mut x);
+            return Ok(x);
+        }
+        last_delta = delta;
+    }
+    // A convergent series shrinks its steps geometrically; a run that is merely
+    // too short still ends on a smaller step than it started with.
+    let first = beta.abs() * n as f64;
+    if last_delta >= first {
+        return Err(PowerFailure::Diverged);
+    }
+    Err(PowerFailure::NotConverged(max_iter))
+}
+
+/// Tarjan's strongly connected components with an explicit stack. Components are
+/// returned as sorted index lists, in the order Tarjan completes them (reverse
+/// topological order of the condensation).
+pub fn tarjan_scc(adj: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let n = adj.len();
+    let mut index = vec![UNVISITED; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut next_index = 0;
+    // (node, position of the next edge to explore)
+    let mut call_stack: Vec<(usize, usize)> = Vec::new();
+
+    for root in 0..n {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        call_stack.push((root, 0));
+        index[root] = next_index;
+        low[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(&mut (v, ref mut edge)) = call_stack.last_mut() {
+            if let Some(&w) = adj[v].get(*edge) {
+                *edge += 1;
+                if index[w] == UNVISITED {
+                    index[w] = next_index;
+                    low[w] = next_index;
+                    next_index += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    call_stack.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+
+            call_stack.pop();
+            if let Some(&(parent, _)) = call_stack.last() {
+                low[parent] = low[parent].min(low[v]);
+            }
+            if low[v] == index[v] {
+                let mut component = Vec::new();
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// Union-find with path halving and union by size.
+pub struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    pub fn new(n: usize) -> Self {
+        UnionFind {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    pub fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    /// Returns true if `a` and `b` were in different sets.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut ra, mut rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return false;
+        }
+        if self.size[ra] < self.size[rb] {
+            std::mem::swap(&mut ra, &mut rb);
+        }
+        self.parent[rb] = ra;
+        self.size[ra] += self.size[rb];
+        true
+    }
+}
+
+/// Number of weakly connected components (edges treated as undirected).
+pub fn weak_component_count(graph: &IndexedGraph) -> usize {
+    let mut uf = UnionFind::new(graph.len());
+    let mut components = graph.len();
+    for (u, deps) in graph.adj.iter().enumerate() {
+        for &v in deps {
+            if uf.union(u, v) {
+                components -= 1;
+            }
+        }
+    }
+    components
+}
+
+/// Sorted, distinct out-neighbors of each node, without self loops.
+pub fn simple_adjacency(graph: &IndexedGraph) -> Vec<Vec<usize>> {
+    graph
+        .adj
+        .iter()
+        .enumerate()
+        .map(|(u, out)| {
+            let mut out: Vec<usize> = out.iter().copied().filter(|&v| v != u).collect();
+            out.sort_unstable();
+            out.dedup();
+            out
+        })
+        .collect()
+}
+
+/// Sorted, distinct neighbors of each node in the undirected projection of the
+/// graph, without self loops.
+pub fn undirected_neighbors(graph: &IndexedGraph) -> Vec<Vec<usize>> {
+    let mut neighbors: Vec<Vec<usize>> = vec![Vec::new(); graph.len()];
+    for (u, deps) in graph.adj.iter().enumerate() {
+        for &v in deps {
+            if u != v {
+                neighbors[u].push(v);
+                neighbors[v].push(u);
+            }
+        }
+    }
+    for list in &mut neighbors {
+        list.sort_unstable();
+        list.dedup();
+    }
+    neighbors
+}
+
+/// Core number of every node with edges treated as undirected (self-loops and
+/// parallel edges ignored), by Batagelj–Zaversnik bucket peeling in O(V + E).
+pub fn core_numbers(graph: &IndexedGraph) -> Vec<usize> {
+    core_numbers_of(&undirected_neighbors(graph))
+}
+
+/// `core_numbers` over precomputed `undirected_neighbors`.
+pub fn core_numbers_of(neighbors: &[Vec<usize>]) -> Vec<usize> {
+    let n = neighbors.len();
+    let mut degree: Vec<usize> = neighbors.iter().map(Vec::len).collect();
+    let max_degree = degree.iter().copied().max().unwrap_or(0);
+
+    // Nodes sorted by degree, with `start[d]` the first position of degree d.
+    let mut start = vec![0; max_degree + 2];
+    for &d in &degree {
+        start[d + 1] += 1;
+    }
+    for d in 1..start.len() {
+        start[d] += start[d - 1];
+    }
+    let mut order = vec![0; n];
+    let mut pos = vec![0; n];
+    let mut next = start.clone();
+    for v in 0..n {
+        pos[v] = next[degree[v]];
+        order[pos[v]] = v;
+        next[degree[v]] += 1;
+    }
+
+    for i in 0..n {
+        let v = order[i];
+        for &u in &neighbors[v] {
+            if degree[u] > degree[v] {
+                // Move u to the front of its bucket, then shrink that bucket by one.
+                let du = degree[u];
+                let (pu, pw) = (pos[u], start[du]);
+                let w = order[pw];
+                if u != w {
+                    order.swap(pu, pw);
+                    pos[u] = pw;
+                    pos[w] = pu;
+                }
+                start[du] += 1;
+                degree[u] -= 1;
+            }
+        }
+    }
+    degree
+}
+
+/// Articulation points and bridges of an undirected simple graph given as
+/// `undirected_neighbors`, by Hopcroft–Tarjan low-link over an explicit DFS stack,
+/// one search per connected component. Returns a flag per node and the bridges as
+/// `(u, v)` with `u < v`, sorted.
+pub fn cut_structure(neighbors: &[Vec<usize>]) -> (Vec<bool>, Vec<(usize, usize)>) {
+    const UNVISITED: usize = usize::MAX;
+    let n = neighbors.len();
+    let mut disc = vec![UNVISITED; n];
+    let mut low = vec![0; n];
+    let mut cut = vec![false; n];
+    let mut bridges = Vec::new();
+    let mut next = 0;
+    // (node, its DFS parent, position of the next neighbor to explore)
+    let mut stack: Vec<(usize, usize, usize)> = Vec::new();
+
+    for root in 0..n {
+        if disc[root] != UNVISITED {
+            continue;
+        }
+        disc[root] = next;
+        low[root] = next;
+        next += 1;
+        let mut root_children = 0;
+        stack.push((root, UNVISITED, 0));
+        while let Some(&mut (v, parent, ref mut edge)) = stack.last_mut() {
+            if let Some(&w) = neighbors[v].get(*edge) {
+                *edge += 1;
+                if w == parent {
+                    continue;
+                }
+                if disc[w] == UNVISITED {
+                    disc[w] = next;
+                    low[w] = next;
+                    next += 1;
+                    if v == root {
+                        root_children += 1;
+                    }
+                    stack.push((w, v, 0));
+                } else {
+                    low[v] = low[v].min(disc[w]);
+                }
+                continue;
+            }
+            stack.pop();
+            if parent == UNVISITED {
+                continue;
+            }
+            low[parent] = low[parent].min(low[v]);
+            if low[v] > disc[parent] {
+                bridges.push((parent.min(v), parent.max(v)));
+            }
+            // The root is a cut vertex only with two or more DFS children, below.
+            if parent != root && low[v] >= disc[parent] {
+                cut[parent] = true;
+            }
+        }
+        if root_children > 1 {
+            cut[root] = true;
+        }
+    }
+    bridges.sort_unstable();
+    (cut, bridges)
+}
+
+/// SplitMix64 step; a small seeded generator for reproducible sampling.
+pub fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// `k` distinct indices below `n`, chosen by a partial Fisher–Yates shuffle under
+/// `seed`.
+pub fn sample_indices(n: usize, k: usize, seed: u64) -> Vec<usize> {
+    let mut pool: Vec<usize> = (0..n).collect();
+    let mut state = seed;
+    let k = k.min(n);
+    for i in 0..k {
+        let j = i + (splitmix64(&mut state) % (n - i) as u64) as usize;
+        pool.swap(i, j);
+    }
+    pool.truncate(k);
+    pool
+}
+
+/// A uniform draw from `[0, 1)`.
+fn unit(state: &mut u64) -> f64 {
+    (splitmix64(state) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Second-order (node2vec) random walks. From `cur`, reached from `prev`, the next
+/// node `x` is drawn with weight `1/p` if it is `prev`, 1 if `prev` has an edge to it
+/// and `1/q` otherwise; the first step is uniform. Walks end early at sinks.
+pub struct WalkSampler {
+    /// Distinct neighbors of each index, sorted.
+    adj: Vec<Vec<usize>>,
+    inv_p: f64,
+    inv_q: f64,
+}
+
+impl WalkSampler {
+    pub fn new(graph: &IndexedGraph, p: f64, q: f64) -> Self {
+        let adj = graph
+            .adj
+            .iter()
+            .map(|targets| {
+                let mut out = targets.clone();
+                out.sort_unstable();
+                out.dedup();
+                out
+            })
+            .collect();
+        WalkSampler {
+            adj,
+            inv_p: 1.0 / p,
+            inv_q: 1.0 / q,
+        }
+    }
+
+    fn bias(&self, prev: usize, x: usize) -> f64 {
+        if x == prev {
+            self.inv_p
+        } else if self.adj[prev].binary_search(&x).is_ok() {
+            1.0
+        } else {
+            self.inv_q
+        }
+    }
+
+    /// A walk of at most `length` nodes from `start`, drawn from its own generator
+    /// seeded with `seed`.
+    pub fn walk(&self, start: usize, length: usize, seed: u64) -> Vec<usize> {
+        let mut state = seed;
+        let mut walk = Vec::with_capacity(length);
+        if length == 0 {
+            return walk;
+        }
+        walk.push(start);
+        while walk.len() < length {
+            let cur = walk[walk.len() - 1];
+            let next = &self.adj[cur];
+            if next.is_empty() {
+                break;
+            }
+            let uniform = walk.len() == 1 || (self.inv_p == 1.0 && self.inv_q == 1.0);
+            let pick = if uniform {
+                next[(unit(&mut state) * next.len() as f64) as usize % next.len()]
+            } else {
+                let prev = walk[walk.len() - 2];
+                let total: f64 = next.iter().map(|&x| self.bias(prev, x)).sum();
+                let mut r = unit(&mut state) * total;
+                // Rounding can leave `r` just past the last weight; keep the last node.
+                let mut pick = next[next.len() - 1];
+                for &x in next {
+                    r -= self.bias(prev, x);
+                    if r < 0.0 {
+                        pick = x;
+                        break;
+                    }
+                }
+                pick
+            };
+            walk.push(pick);
+        }
+        walk
+    }
+}
+
+/// Seed of walk number `index` under `seed`, so each walk draws from its own stream
+/// whatever thread generates it.
+pub fn walk_seed(seed: u64, index: usize) -> u64 {
+    let mut mix = index as u64;
+    seed ^ splitmix64(&mut mix)
+}
+
+/// Number of ordered pairs `(s, v)`, `s` in `sources`, `v != s` reachable from `s`,
+/// at each BFS distance, as `hist[d]`. Sources are processed in parallel, each with
+/// its own distance buffer, so memory is O(V) per worker plus the histogram.
+pub fn distance_histogram(graph: &IndexedGraph, sources: &[usize]) -> Vec<u64> {
+    use rayon::prelude::*;
+    let n = graph.len();
+    sources
+        .par_iter()
+        .fold(
+            || (Vec::new(), vec![usize::MAX; n], Vec::new()),
+            |(mut hist, mut dist, mut queue): (Vec<u64>, Vec<usize>, Vec<usize>), &s| {
+                dist[s] = 0;
+                queue.clear();
+                queue.push(s);
+                let mut head = 0;
+                while head < queue.len() {
+                    let u = queue[head];
+                    head += 1;
+                    for &v in &graph.adj[u] {
+                        if dist[v] == usize::MAX {
+                            let d = dist[u] + 1;
+                            dist[v] = d;
+                            if hist.len() <= d {
+                                hist.resize(d + 1, 0);
+                            }
+                            hist[d] += 1;
+                            queue.push(v);
+                        }
+                    }
+                }
+                for &v in &queue {
+                    dist[v] = usize::MAX;
+                }
+                (hist, dist, queue)
+            },
+        )
+        .map(|(hist, _, _)| hist)
+        .reduce(Vec::new, |mut a, b| {
+            if a.len() < b.len() {
+                a.resize(b.len(), 0);
+            }
+            for (x, y) in a.iter_mut().zip(b) {
+                *x += y;
+            }
+            a
+        })
+}
+
+/// Normalized betweenness centrality (Brandes) for the directed, unweighted graph:
+/// for each node, the fraction of shortest paths between ordered pairs of other
+/// nodes that pass through it, divided by `(n-1)(n-2)`. Parallel edges and self
+/// loops do not add paths. Sources are processed in parallel, each worker keeping
+/// its own O(V) buffers and partial scores, which are summed at the end.
+pub fn betweenness(graph: &IndexedGraph) -> Vec<f64> {
+    use rayon::prelude::*;
+    let n = graph.len();
+    if n < 3 {
+        return vec![0.0; n];
+    }
+    let adj = simple_adjacency(graph);
+    let scores = (0..n)
+        .into_par_iter()
+        .fold(
+            || Brandes::new(n),
+            |mut b, s| {
+                b.accumulate(&adj, s);
+                b
+            },
+        )
+        .map(|b| b.score)
+        .reduce(
+            || vec![0.0; n],
+            |mut a, b| {
+                for (x, y) in a.iter_mut().zip(b) {
+                    *x += y;
+                }
+                a
+            },
+        );
+    let scale = 1.0 / ((n - 1) * (n - 2)) as f64;
+    scores.into_iter().map(|x| x * scale).collect()
+}
+
+/// Per-worker state for `betweenness`: partial scores plus buffers reset after
+/// each source.
+struct Brandes {
+    score: Vec<f64>,
+    dist: Vec<usize>,
+    sigma: Vec<f64>,
+    delta: Vec<f64>,
+    order: Vec<usize>,
+}
+
+impl Brandes {
+    fn new(n: usize) -> Self {
+        Brandes {
+            score: vec![0.0; n],
+            dist: vec![usize::MAX; n],
+            sigma: vec![0.0; n],
+            delta: vec![0.0; n],
+            order: Vec::new(),
+        }
+    }
+
+    /// Adds the dependencies of source `s` on every other node to `score`.
+    fn accumulate(&mut self, adj: &[Vec<usize>], s: usize) {
+        self.dist[s] = 0;
+        self.sigma[s] = 1.0;
+        self.order.clear();
+        self.order.push(s);
+        let mut head = 0;
+        while head < self.order.len() {
+            let u = self.order[head];
+            head += 1;
+            for &v in &adj[u] {
+                if self.dist[v] == usize::MAX {
+                    self.dist[v] = self.dist[u] + 1;
+                    self.order.push(v);
+                }
+                if self.dist[v] == self.dist[u] + 1 {
+                    self.sigma[v] += self.sigma[u];
+                }
+            }
+        }
+        // Dependencies accumulate from the farthest nodes back to the source.
+        for &w in self.order.iter().rev() {
+            for &v in &adj[w] {
+                if self.dist[v] == self.dist[w] + 1 {
+                    self.delta[w] += self.sigma[w] / self.sigma[v] * (1.0 + self.delta[v]);
+                }
+            }
+            if w != s {
+                self.score[w] += self.delta[w];
+            }
+        }
+        for &v in &self.order {
+            self.dist[v] = usize::MAX;
+            self.sigma[v] = 0.0;
+            self.delta[v] = 0.0;
+        }
+    }
+}
+
+/// Closeness centrality over outgoing shortest paths, with the Wasserman–Faust
+/// correction for disconnected graphs: with `r` nodes reachable from `u` at total
+/// distance `d`, `closeness(u) = (r / (n-1)) * (r / d)`. Nodes that reach nothing
+/// score 0. One BFS per source, in parallel.
+pub fn closeness(graph: &IndexedGraph) -> Vec<f64> {
+    use rayon::prelude::*;
+    let n = graph.len();
+    if n < 2 {
+        return vec![0.0; n];
+    }
+    (0..n)
+        .into_par_iter()
+        .map_init(
+            || (vec![usize::MAX; n], Vec::new()),
+            |(dist, queue), s| {
+                dist[s] = 0;
+                queue.clear();
+                queue.push(s);
+                let mut head = 0;
+                let mut total = 0usize;
+                while head < queue.len() {
+                    let u = queue[head];
+                    head += 1;
+                    for &v in &graph.adj[u] {
+                        if dist[v] == usize::MAX {
+                            dist[v] = dist[u] + 1;
+                            total += dist[v];
+                            queue.push(v);
+                        }
+                    }
+                }
+                for &v in queue.iter() {
+                    dist[v] = usize::MAX;
+                }
+                let reached = (queue.len() - 1) as f64;
+                if total == 0 {
+                    0.0
+                } else {
+                    reached / (n - 1) as f64 * reached / total as f64
+                }
+            },
+        )
+        .collect()
+}
+
+/// Residual network for Edmonds–Karp. Each edge is stored next to its reverse
+/// (`e ^ 1`), which starts with capacity 0.
+pub struct FlowNetwork {
+    adj: Vec<Vec<usize>>,
+    to: Vec<usize>,
+    cap: Vec<i64>,
+}
+
+impl FlowNetwork {
+    pub fn new(n: usize) -> Self {
+        FlowNetwork {
+            adj: vec![Vec::new(); n],
+            to: Vec::new(),
+            cap: Vec::new(),
+        }
+    }
+
+    pub fn add_edge(&mut self, u: usize, v: usize, cap: i64) {
+        self.adj[u].push(self.to.len());
+        self.to.push(v);
+        self.cap.push(cap);
+        self.adj[v].push(self.to.len());
+        self.to.push(u);
+        self.cap.push(0);
+    }
+
+    /// Pushes the maximum flow from `s` to `t` along shortest augmenting paths,
+    /// leaving the residual capacities behind for `source_side`.
+    pub fn max_flow(&mut self, s: usize, t: usize) -> i64 {
+        if s == t {
+            return 0;
+        }
+        let n = self.adj.len();
+        let mut total: i64 = 0;
+        let mut via = vec![usize::MAX; n];
+        loop {
+            via.fill(usize::MAX);
+            let mut queue = VecDeque::from([s]);
+            while let Some(u) = queue.pop_front() {
+                if u == t {
+                    break;
+                }
+                for &e in &self.adj[u] {
+                    let v = self.to[e];
+                    if self.cap[e] > 0 && v != s && via[v] == usize::MAX {
+                        via[v] = e;
+                        queue.push_back(v);
+                    }
+                }
+            }
+            if via[t] == usize::MAX {
+                return total;
+            }
+            let mut push = i64::MAX;
+            let mut v = t;
+            while v != s {
+                let e = via[v];
+                push = push.min(self.cap[e]);
+                v = self.to[e ^ 1];
+            }
+            let mut v = t;
+            while v != s {
+                let e = via[v];
+                self.cap[e] -= push;
+                self.cap[e ^ 1] += push;
+                v = self.to[e ^ 1];
+            }
+            total = total.saturating_add(push);
+        }
+    }
+
+    /// Nodes reachable from `s` in the residual network. After `max_flow`, the
+    /// original edges leaving this set form a minimum cut.
+    pub fn source_side(&self, s: usize) -> Vec<bool> {
+        let mut seen = vec![false; self.adj.len()];
+        seen[s] = true;
+        let mut stack = vec![s];
+        while let Some(u) = stack.pop() {
+            for &e in &self.adj[u] {
+                let v = self.to[e];
+                if self.cap[e] > 0 && !seen[v] {
+                    seen[v] = true;
+                    stack.push(v);
+                }
+            }
+        }
+        seen
+    }
+}
+
+/// Number of triangles through each node of the undirected projection given by
+/// `neighbors` (see `undirected_neighbors`): adjacent pairs among its neighbors,
+/// found by intersecting sorted neighbor lists. Each triangle is counted once at
+/// each of its three nodes.
+pub fn triangles_per_node(neighbors: &[Vec<usize>]) -> Vec<usize> {
+    use rayon::prelude::*;
+    neighbors
+        .par_iter()
+        .map(|out| {
+            let shared: usize = out
+                .iter()
+                .map(|&v| sorted_intersection_len(out, &neighbors[v]))
+                .sum();
+            shared / 2
+        })
+        .collect()
+}
+
+fn sorted_intersection_len(a: &[usize], b: &[usize]) -> usize {
+    let (mut i, mut j, mut count) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                count += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    count
+}
+
+/// Directed 3-cycles `a→b→c→a` over `adj` (see `simple_adjacency`), each counted
+/// once, from its smallest node.
+pub fn directed_3_cycles(adj: &[Vec<usize>]) -> usize {
+    let mut count = 0;
+    for (a, out) in adj.iter().enumerate() {
+        for &b in out.iter().filter(|&&b| b > a) {
+            for &c in adj[b].iter().filter(|&&c| c > a) {
+                if adj[c].binary_search(&a).is_ok() {
+                    count += 1;
+                }
+            }
+        }
+    }
+    count
+}
+
+/// Ordered triples of distinct nodes `(a, b, c)` with `a→b`, `b→c` and `a→c`
+/// over `adj` (see `simple_adjacency`).
+pub fn transitive_triads(adj: &[Vec<usize>]) -> usize {
+    let mut count = 0;
+    for (a, out) in adj.iter().enumerate() {
+        for &b in out {
+            count += adj[b]
+                .iter()
+                .filter(|&&c| c != a && out.binary_search(&c).is_ok())
+                .count();
+        }
+    }
+    count
+}
+
+/// Calls `emit` with every maximal clique of the undirected projection given by
+/// `neighbors` (see `undirected_neighbors`), as sorted indices, until it returns
+/// false. Bron–Kerbosch with pivoting, started from each node with its neighbors
+/// later in core-number order as candidates, so candidate sets stay small on
+/// sparse graphs. The search runs on an explicit stack.
+pub fn maximal_cliques(neighbors: &[Vec<usize>], mut emit: impl FnMut(&[usize]) -> bool) {
+    struct Frame {
+        p: Vec<usize>,
+        x: Vec<usize>,
+        todo: Vec<usize>,
+    }
+    let n = neighbors.len();
+    let core = core_numbers_of(neighbors);
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by_key(|&v| (core[v], v));
+    let mut rank = vec![0; n];
+    for (i, &v) in order.iter().enumerate() {
+        rank[v] = i;
+    }
+    let frame = |p: Vec<usize>, x: Vec<usize>| {
+        // Pivot on the node covering most candidates; only its non-neighbors branch.
+        let pivot = p
+            .iter()
+            .chain(&x)
+            .copied()
+            .max_by_key(|&u| sorted_intersection_len(&p, &neighbors[u]));
+        let todo = match pivot {
+            Some(u) => p
+                .iter()
+                .copied()
+                .filter(|v| neighbors[u].binary_search(v).is_err())
+                .collect(),
+            None => Vec::new(),
+        };
+        Frame { p, x, todo }
+    };
+    let mut clique = Vec::new();
+    for &v in &order {
+        let (p, x): (Vec<usize>, Vec<usize>) =
+            neighbors[v].iter().partition(|&&u| rank[u] > rank[v]);
+        if p.is_empty() && x.is_empty() {
+            if !emit(&[v]) {
+                return;
+            }
+            continue;
+        }
+        clique.clear();
+        clique.push(v);
+        let mut stack = vec![frame(p, x)];
+        while let Some(top) = stack.last_mut() {
+            let Some(u) = top.todo.pop() else {
+                stack.pop();
+                clique.pop();
+                continue;
+            };
+            let p = sorted_intersection(&top.p, &neighbors[u]);
+            let x = sorted_intersection(&top.x, &neighbors[u]);
+            if let Ok(i) = top.p.binary_search(&u) {
+                top.p.remove(i);
+            }
+            let at = top.x.partition_point(|&w| w < u);
+            top.x.insert(at, u);
+            clique.push(u);
+            if p.is_empty() {
+                if x.is_empty() {
+                    let mut found = clique.clone();
+                    found.sort_unstable();
+                    if !emit(&found) {
+                        return;
+                    }
+                }
+                clique.pop();
+            } else {
+                stack.push(frame(p, x));
+            }
+        }
+    }
+}
+
+fn sorted_intersection(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let (mut i, mut j, mut out) = (0, 0, Vec::new());
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                out.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Kahn's algorithm by rounds: layer 0 holds the nodes without incoming edges and
+/// layer `k` those whose longest path from such a node has `k` edges. Layers are
+/// sorted index lists. If `adj` has a cycle, returns one instead, as the indices
+/// along it in edge order starting from the smallest.
+pub fn topological_layers(adj: &[Vec<usize>]) -> Result<Vec<Vec<usize>>, Vec<usize>> {
+    let n = adj.len();
+    let mut indegree = vec![0usize; n];
+    for out in adj {
+        for &v in out {
+            indegree[v] += 1;
+        }
+    }
+    let mut layer: Vec<usize> = (0..n).filter(|&v| indegree[v] == 0).collect();
+    let mut layers = Vec::new();
+    let mut placed = 0;
+    while !layer.is_empty() {
+        placed += layer.len();
+        let mut next = Vec::new();
+        for &u in &layer {
+            for &v in &adj[u] {
+                indegree[v] -= 1;
+                if indegree[v] == 0 {
+                    next.push(v);
+                }
+            }
+        }
+        next.sort_unstable();
+        layers.push(std::mem::replace(&mut layer, next));
+    }
+    if placed == n {
+        return Ok(layers);
+    }
+
+    // Every unplaced node still has an unplaced predecessor, so walking
+    // predecessors from one of them must revisit a node.
+    let mut pred = vec![usize::MAX; n];
+    for (u, out) in adj.iter().enumerate() {
+        for &v in out {
+            if indegree[u] > 0 && indegree[v] > 0 {
+                pred[v] = u;
+            }
+        }
+    }
+    let mut seen_at = vec![usize::MAX; n];
+    let mut walk = Vec::new();
+    let mut u = (0..n).find(|&v| indegree[v] > 0).unwrap_or(0);
+    while seen_at[u] == usize::MAX {
+        seen_at[u] = walk.len();
+        walk.push(u);
+        u = pred[u];
+    }
+    let mut cycle = walk.split_off(seen_at[u]);
+    cycle.reverse();
+    let first = (0..cycle.len()).min_by_key(|&i| cycle[i]).unwrap_or(0);
+    cycle.rotate_left(first);
+    Err(cycle)
+}
+
+/// True if `adj` has no cycle: Kahn's algorithm, consuming the in-degree of each
+/// node.
+pub fn is_acyclic(adj: &[Vec<usize>], mut indegree: Vec<usize>) -> bool {
+    let mut ready: Vec<usize> = (0..adj.len()).filter(|&v| indegree[v] == 0).collect();
+    let mut placed = 0;
+    while let Some(u) = ready.pop() {
+        placed += 1;
+        for &v in &adj[u] {
+            indegree[v] -= 1;
+            if indegree[v] == 0 {
+                ready.push(v);
+            }
+        }
+    }
+    placed == adj.len()
+}
+
+/// Largest BFS distance from any of `sources` to a node it reaches, over `adj`.
+/// Runs 64 sources at a time as one bit-parallel BFS (bit `i` of a node's word
+/// says "reached from the batch's `i`-th source"), so each level is one sweep
+/// over the edges for the whole batch. Batches are processed in parallel and
+/// reduced with `max`.
+pub fn max_eccentricity(adj: &[Vec<usize>], sources: &[usize]) -> usize {
+    use rayon::prelude::*;
+    let n = adj.len();
+    sources
+        .par_chunks(64)
+        .map_init(
+            || (vec![0u64; n], vec![0u64; n], vec![0u64; n]),
+            |(seen, frontier, next), batch| {
+                seen.fill(0);
+                frontier.fill(0);
+                for (bit, &s) in batch.iter().enumerate() {
+                    seen[s] |= 1 << bit;
+                    frontier[s] |= 1 << bit;
+                }
+                let mut level = 0;
+                loop {
+                    for (u, out) in adj.iter().enumerate() {
+                        let bits = frontier[u];
+                        if bits != 0 {
+                            for &v in out {
+                                next[v] |= bits;
+                            }
+                        }
+                    }
+                    let mut grew = false;
+                    for v in 0..n {
+                        let fresh = next[v] & !seen[v];
+                        next[v] = 0;
+                        frontier[v] = fresh;
+                        if fresh != 0 {
+                            seen[v] |= fresh;
+                            grew = true;
+                        }
+                    }
+                    if !grew {
+                        return level;
+                    }
+                    level += 1;
+                }
+            },
+        )
+        .max()
+        .unwrap_or(0)
+}
+
+/// An `f64` with a total order (`f64::total_cmp`), for use as a heap key.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Cost(pub f64);
+
+impl Eq for Cost {}
+
+impl PartialOrd for Cost {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cost {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Union-find over `0..n` with path halving and union by size.
+pub struct DisjointSet {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl DisjointSet {
+    pub fn new(n: usize) -> Self {
+        DisjointSet {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    pub fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    /// Merges the sets of `a` and `b`; false if they were already one set.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return false;
+        }
+        let (big, small) = if self.size[ra] >= self.size[rb] {
+            (ra, rb)
+        } else {
+            (rb, ra)
+        };
+        self.parent[small] = big;
+        self.size[big] += self.size[small];
+        true
+    }
+}
+
+/// Minimum spanning forest of the undirected projection (Kruskal). A pair linked
+/// in both directions or by parallel edges counts once, with its smallest weight;
+/// self loops are ignored. Candidate edges are taken by weight, ties by index pair,
+/// so equal weights (e.g. all 1.0) still give one reproducible forest. Returns the
+/// total weight and the edges as sorted `(smaller, larger)` index pairs.
+pub fn minimum_spanning_forest(graph: &IndexedGraph) -> (f64, Vec<(usize, usize)>) {
+    let mut candidates: Vec<(Cost, usize, usize)> = Vec::new();
+    for (u, (out, weights)) in graph.adj.iter().zip(&graph.weights).enumerate() {
+        for (&v, &w) in out.iter().zip(weights) {
+            if u != v {
+                candidates.push((Cost(w), u.min(v), u.max(v)));
+            }
+        }
+    }
+    candidates.sort_unstable();
+    let mut sets = DisjointSet::new(graph.len());
+    let mut total = 0.0;
+    let mut edges = Vec::new();
+    // Later copies of a pair are heavier (or equal) and already joined, so the
+    // union check skips them along with every other cycle-closing edge.
+    for (Cost(w), u, v) in candidates {
+        if sets.union(u, v) {
+            total += w;
+            edges.push((u, v));
+        }
+    }
+    edges.sort_unstable();
+    (total, edges)
+}
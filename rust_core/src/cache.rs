@@ -0,0 +1,142 @@
+//! Per-walker cache of computed metrics, keyed by name (including parameters) and
+//! valid for a single graph version. Any mutation bumps the walker's version, which
+//! drops every cached entry on the next lookup.
+//!
+//! Identical computations that overlap in time are coalesced: while one thread
+//! computes a key for a version, other callers of the same key and version wait for
+//! its result instead of recomputing (unless disabled with `configure`).
+
+use crate::config;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+#[derive(Default)]
+struct State {
+    version: u64,
+    entries: HashMap<String, Arc<dyn Any + Send + Sync>>,
+    /// `(version, key)` pairs currently being computed.
+    inflight: HashSet<(u64, String)>,
+    computed: u64,
+    coalesced: u64,
+    hits: u64,
+}
+
+#[derive(Default)]
+pub struct MetricCache {
+    state: Mutex<State>,
+    done: Condvar,
+}
+
+/// Counters reported by `computation_stats`.
+pub struct CacheStats {
+    pub computed: u64,
+    pub coalesced: u64,
+    pub hits: u64,
+}
+
+/// Clears the in-flight mark and wakes waiters, also if the computation panics.
+struct InflightGuard<'a> {
+    cache: &'a MetricCache,
+    mark: Option<(u64, String)>,
+}
+
+impl Drop for InflightGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(mark) = self.mark.take() {
+            self.cache.lock().inflight.remove(&mark);
+            self.cache.done.notify_all();
+        }
+    }
+}
+
+impl MetricCache {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the cached value for `key` at `version`, computing and storing it on a
+    /// miss. The lock is not held while `compute` runs; callers that arrive while
+    /// the same key and version is being computed wait for that result.
+    pub fn get_or_compute<T: Any + Send + Sync>(
+        &self,
+        version: u64,
+        key: &str,
+        compute: impl FnOnce() -> T,
+    ) -> Arc<T> {
+        let coalesce = config::coalesce();
+        let mark = (version, key.to_string());
+        {
+            let mut guard = self.lock();
+            let mut waited = false;
+            loop {
+                if guard.version != version {
+                    guard.entries.clear();
+                    guard.version = version;
+                }
+                if let Some(hit) = guard.entries.get(key) {
+                    if let Ok(value) = hit.clone().downcast::<T>() {
+                        if waited {
+                            guard.coalesced += 1;
+                        } else {
+                            guard.hits += 1;
+                        }
+                        return value;
+                    }
+                }
+                if !coalesce || !guard.inflight.contains(&mark) {
+                    break;
+                }
+                waited = true;
+                guard = self.done.wait(guard).unwrap_or_else(|e| e.into_inner());
+            }
+            guard.computed += 1;
+            if coalesce {
+                guard.inflight.insert(mark.clone());
+            }
+        }
+        let _inflight = InflightGuard {
+            cache: self,
+            mark: coalesce.then_some(mark),
+        };
+        let value = Arc::new(compute());
+        {
+            let mut guard = self.lock();
+            if guard.version == version {
+                guard.entries.insert(key.to_string(), value.clone());
+            }
+        }
+        value
+    }
+
+    /// The value cached for `key` at `version`, if there is one. Never computes, and
+    /// is not counted in `stats`.
+    pub fn peek<T: Any + Send + Sync>(&self, version: u64, key: &str) -> Option<Arc<T>> {
+        let guard = self.lock();
+        if guard.version != version {
+            return None;
+        }
+        guard.entries.get(key)?.clone().downcast::<T>().ok()
+    }
+
+    /// Keys currently being computed, as `key@version`, sorted.
+    pub fn inflight(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .lock()
+            .inflight
+            .iter()
+            .map(|(version, key)| format!("{}@{}", key, version))
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let guard = self.lock();
+        CacheStats {
+            computed: guard.computed,
+            coalesced: guard.coalesced,
+            hits: guard.hits,
+        }
+    }
+}
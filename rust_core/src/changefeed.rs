@@ -0,0 +1,285 @@
+//! JSONL changefeed of invalidation results for consumers that tail a file instead
+//! of reading Kafka.
+//!
+//! The active segment is always `path`; each line is one invalidation with a
+//! monotonically increasing `seq`. When `max_bytes` would be exceeded, the active
+//! segment is renamed to `<path>.<first_seq>` and recorded in the sidecar index
+//! `<path>.index` (one `{"first_seq", "last_seq", "file"}` line per rotated segment).
+//!
+//! Writers are serialized per path within the process, and each line is written with
+//! a single append, so concurrent callers never interleave partial lines. Only one
+//! process should write a given changefeed.
+
+use crate::errors::SerializationError;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Copy)]
+enum Fsync {
+    /// Leave flushing to the OS.
+    Never,
+    /// Sync segment and index when a segment is rotated.
+    Rotate,
+    /// Sync after every appended line.
+    Always,
+}
+
+impl Fsync {
+    fn parse(name: &str) -> PyResult<Self> {
+        match name {
+            "never" => Ok(Fsync::Never),
+            "rotate" => Ok(Fsync::Rotate),
+            "always" => Ok(Fsync::Always),
+            _ => Err(PyValueError::new_err(format!(
+                "unknown fsync policy '{}' (expected never, rotate or always)",
+                name
+            ))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    seq: u64,
+    ts: f64,
+    start_id: String,
+    affected: Vec<String>,
+}
+
+impl Entry {
+    fn to_py(&self, py: Python) -> PyResult<PyObject> {
+        let d = PyDict::new(py);
+        d.set_item("seq", self.seq)?;
+        d.set_item("ts", self.ts)?;
+        d.set_item("start_id", &self.start_id)?;
+        d.set_item("affected", &self.affected)?;
+        Ok(d.into())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexRecord {
+    first_seq: u64,
+    last_seq: u64,
+    file: String,
+}
+
+struct Writer {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    /// Sequence number of the first entry in the active segment.
+    segment_first: u64,
+    next_seq: u64,
+    fsync: Fsync,
+    max_bytes: Option<u64>,
+}
+
+static WRITERS: Mutex<Option<HashMap<PathBuf, Arc<Mutex<Writer>>>>> = Mutex::new(None);
+
+fn io_err(path: &Path, e: std::io::Error) -> PyErr {
+    PyIOError::new_err(format!("{}: {}", path.display(), e))
+}
+
+fn index_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".index");
+    PathBuf::from(name)
+}
+
+fn read_index(path: &Path) -> PyResult<Vec<IndexRecord>> {
+    read_lines(&index_path(path))
+}
+
+/// Parses every line of a JSONL file; a missing file is empty.
+fn read_lines<T: for<'de> Deserialize<'de>>(path: &Path) -> PyResult<Vec<T>> {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(io_err(path, e)),
+    };
+    let mut out = Vec::new();
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| io_err(path, e))?;
+        if line.is_empty() {
+            continue;
+        }
+        out.push(serde_json::from_str(&line).map_err(|e| {
+            SerializationError::new_err(format!("{}:{}: {}", path.display(), n + 1, e))
+        })?);
+    }
+    Ok(out)
+}
+
+impl Writer {
+    /// Opens (or resumes) the changefeed at `path`, continuing its sequence numbers.
+    fn open(path: &Path) -> PyResult<Self> {
+        let index_last = read_index(path)?.last().map_or(0, |r| r.last_seq);
+        let active: Vec<Entry> = read_lines(path)?;
+        let segment_first = active.first().map_or(index_last + 1, |e| e.seq);
+        let last = active.last().map_or(index_last, |e| e.seq.max(index_last));
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| io_err(path, e))?;
+        let size = file.metadata().map_err(|e| io_err(path, e))?.len();
+        Ok(Writer {
+            path: path.to_path_buf(),
+            file,
+            size,
+            segment_first,
+            next_seq: last + 1,
+            fsync: Fsync::Never,
+            max_bytes: None,
+        })
+    }
+
+    fn append(&mut self, start_id: String, mut affected: Vec<String>) -> PyResult<u64> {
+        affected.sort();
+        let entry = Entry {
+            seq: self.next_seq,
+            ts: crate::now_secs(),
+            start_id,
+            affected,
+        };
+        let mut line = serde_json::to_string(&entry)
+            .map_err(|e| SerializationError::new_err(e.to_string()))?;
+        line.push('\n');
+        let len = line.len() as u64;
+        if self.size > 0 && self.max_bytes.is_some_and(|max| self.size + len > max) {
+            self.rotate()?;
+        }
+        self.file
+            .write_all(line.as_bytes())
+            .map_err(|e| io_err(&self.path, e))?;
+        if let Fsync::Always = self.fsync {
+            self.file.sync_data().map_err(|e| io_err(&self.path, e))?;
+        }
+        self.size += len;
+        self.next_seq += 1;
+        Ok(entry.seq)
+    }
+
+    /// Moves the active segment aside and starts a new one. The index is written
+    /// first, so a crash in between leaves the entries findable in the active file.
+    fn rotate(&mut self) -> PyResult<()> {
+        let file_name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let record = IndexRecord {
+            first_seq: self.segment_first,
+            last_seq: self.next_seq - 1,
+            file: format!("{}.{}", file_name, self.segment_first),
+        };
+        let rotated = self.path.with_file_name(&record.file);
+        let index = index_path(&self.path);
+        let mut line = serde_json::to_string(&record)
+            .map_err(|e| SerializationError::new_err(e.to_string()))?;
+        line.push('\n');
+        let mut index_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&index)
+            .map_err(|e| io_err(&index, e))?;
+        index_file
+            .write_all(line.as_bytes())
+            .map_err(|e| io_err(&index, e))?;
+        if !matches!(self.fsync, Fsync::Never) {
+            self.file.sync_all().map_err(|e| io_err(&self.path, e))?;
+            index_file.sync_all().map_err(|e| io_err(&index, e))?;
+        }
+
+        std::fs::rename(&self.path, &rotated).map_err(|e| io_err(&rotated, e))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| io_err(&self.path, e))?;
+        self.size = 0;
+        self.segment_first = self.next_seq;
+        Ok(())
+    }
+}
+
+fn writer_for(path: &str) -> PyResult<Arc<Mutex<Writer>>> {
+    let path = PathBuf::from(path);
+    let mut writers = WRITERS.lock().unwrap_or_else(|e| e.into_inner());
+    let writers = writers.get_or_insert_with(HashMap::new);
+    if let Some(w) = writers.get(&path) {
+        return Ok(w.clone());
+    }
+    let writer = Arc::new(Mutex::new(Writer::open(&path)?));
+    writers.insert(path, writer.clone());
+    Ok(writer)
+}
+
+/// Appends one invalidation to the changefeed at `path` with the writer's current
+/// policy and returns its sequence number.
+pub fn append(path: &str, start_id: String, affected: Vec<String>) -> PyResult<u64> {
+    let writer = writer_for(path)?;
+    let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+    writer.append(start_id, affected)
+}
+
+/// Appends `{"seq", "ts", "start_id", "affected"}` to the changefeed at `path` and
+/// returns the entry's sequence number. `fsync` ("never", "rotate" or "always") and
+/// `max_bytes` (rotation threshold, None for no rotation) update the policy for this
+/// path when given; otherwise the previous policy (initially "never", no rotation)
+/// is kept.
+#[pyfunction]
+#[pyo3(name = "append_changefeed", signature = (path, start_id, affected, fsync = None, max_bytes = None))]
+pub fn append_changefeed(
+    path: &str,
+    start_id: String,
+    affected: Vec<String>,
+    fsync: Option<&str>,
+    max_bytes: Option<u64>,
+) -> PyResult<u64> {
+    let fsync = fsync.map(Fsync::parse).transpose()?;
+    let writer = writer_for(path)?;
+    let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(fsync) = fsync {
+        writer.fsync = fsync;
+    }
+    if max_bytes.is_some() {
+        writer.max_bytes = max_bytes;
+    }
+    writer.append(start_id, affected)
+}
+
+/// Reads changefeed entries with `seq > since_seq`, in order, across rotated
+/// segments. Returns dicts shaped like those written by `append_changefeed`.
+#[pyfunction]
+#[pyo3(signature = (path, since_seq = 0))]
+pub fn read_changefeed(py: Python, path: &str, since_seq: u64) -> PyResult<Vec<PyObject>> {
+    let path = Path::new(path);
+    let mut segments: Vec<PathBuf> = read_index(path)?
+        .into_iter()
+        .filter(|r| r.last_seq > since_seq)
+        .map(|r| path.with_file_name(r.file))
+        .collect();
+    segments.push(path.to_path_buf());
+
+    let mut last = since_seq;
+    let mut out = Vec::new();
+    for segment in segments {
+        for entry in read_lines::<Entry>(&segment)? {
+            // Skips entries seen twice if a crash interrupted a rotation.
+            if entry.seq > last {
+                last = entry.seq;
+                out.push(entry.to_py(py)?);
+            }
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,532 @@
+//! Cold-start load of a graph from a log-compacted Kafka topic.
+//!
+//! Each message's key is a node id and its payload the node's current state as JSON:
+//! `{"dependents": [...], "attributes": {...}}` (both optional). An empty payload is a
+//! compaction tombstone and removes the key. Every partition is read from its low
+//! watermark up to the high watermark captured at start, so appends made during the
+//! load are left for the live consumer.
+//!
+//! Within a partition the later offset wins. A key seen in several partitions (e.g.
+//! after a repartition) keeps the record with the latest timestamp, ties going to the
+//! higher partition. Each reader builds a partial walker from the keys it won, and
+//! the partials are merged (see `GraphWalker::merge_from`), so a parallel load
+//! (`load_parallel`) builds the same graph as a sequential one.
+//!
+//! The topic is read from Kafka or from a `MemoryBroker` (see `Brokers`).
+
+use crate::config;
+use crate::errors::SerializationError;
+use crate::normalize::Normalizer;
+use crate::source::{Delivery, MemoryBroker};
+use crate::GraphWalker;
+use pyo3::exceptions::{PyRuntimeError, PyTimeoutError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rayon::prelude::*;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::{Offset, TopicPartitionList};
+use serde::Deserialize;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Messages polled per GIL release.
+const POLL_BATCH: usize = 1_000;
+
+/// How often `load_parallel` reports progress.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Deserialize)]
+struct NodeState {
+    #[serde(default)]
+    dependents: Vec<String>,
+    #[serde(default)]
+    attributes: HashMap<String, String>,
+}
+
+/// Latest record seen for a key; `payload` is None for a tombstone.
+struct Record {
+    partition: i32,
+    offset: i64,
+    timestamp: i64,
+    payload: Option<Vec<u8>>,
+}
+
+impl Record {
+    fn supersedes(&self, other: &Record) -> bool {
+        if self.partition == other.partition {
+            self.offset > other.offset
+        } else {
+            (self.timestamp, self.partition) > (other.timestamp, other.partition)
+        }
+    }
+}
+
+fn keep_latest(state: &mut HashMap<String, Record>, key: String, record: Record) {
+    match state.entry(key) {
+        Entry::Occupied(mut e) => {
+            if record.supersedes(e.get()) {
+                e.insert(record);
+            }
+        }
+        Entry::Vacant(e) => {
+            e.insert(record);
+        }
+    }
+}
+
+fn kafka_err(context: &str, e: impl std::fmt::Display) -> PyErr {
+    PyRuntimeError::new_err(format!("{}: {}", context, e))
+}
+
+/// Partitions to read, as `(partition, low, high)`, with the end offset of every
+/// partition and the number of messages to read.
+struct Plan {
+    partitions: Vec<(i32, i64, i64)>,
+    ends: BTreeMap<i32, i64>,
+    total: u64,
+}
+
+impl Plan {
+    fn new(watermarks: impl IntoIterator<Item = (i32, i64, i64)>) -> Self {
+        let mut plan = Plan {
+            partitions: Vec::new(),
+            ends: BTreeMap::new(),
+            total: 0,
+        };
+        for (p, low, high) in watermarks {
+            plan.ends.insert(p, high);
+            if high > low {
+                plan.partitions.push((p, low, high));
+                plan.total += (high - low) as u64;
+            }
+        }
+        plan
+    }
+}
+
+/// Where the topic is read from: a bootstrap server list with extra librdkafka
+/// settings, or a `MemoryBroker`.
+pub enum Brokers {
+    Kafka {
+        servers: String,
+        config: HashMap<String, String>,
+    },
+    Memory(MemoryBroker),
+}
+
+impl Brokers {
+    /// A `MemoryBroker`, or a server list to use with `config`.
+    pub fn from_py(brokers: &PyAny, config: HashMap<String, String>) -> PyResult<Self> {
+        match brokers.extract::<MemoryBroker>() {
+            Ok(broker) => Ok(Brokers::Memory(broker)),
+            Err(_) => Ok(Brokers::Kafka {
+                servers: brokers.extract()?,
+                config,
+            }),
+        }
+    }
+
+    /// Captures the watermarks of every partition before anything is read.
+    fn plan(&self, topic: &str, timeout: Duration) -> PyResult<Plan> {
+        match self {
+            Brokers::Kafka { servers, config } => {
+                plan(&create_consumer(servers, config)?, topic, timeout)
+            }
+            Brokers::Memory(broker) => {
+                let ends = broker
+                    .end_offsets(topic)
+                    .ok_or_else(|| kafka_err("topic not found", topic))?;
+                Ok(Plan::new(ends.into_iter().map(|(p, high)| (p, 0, high))))
+            }
+        }
+    }
+
+    fn reader(&self, topic: &str, partitions: &[(i32, i64, i64)]) -> PyResult<Reader> {
+        let feed = match self {
+            Brokers::Kafka { servers, config } => {
+                let consumer = create_consumer(servers, config)?;
+                let mut assignment = TopicPartitionList::new();
+                for &(p, low, _) in partitions {
+                    assignment
+                        .add_partition_offset(topic, p, Offset::Offset(low))
+                        .map_err(|e| kafka_err("assignment failed", e))?;
+                }
+                consumer
+                    .assign(&assignment)
+                    .map_err(|e| kafka_err("assignment failed", e))?;
+                Feed::Kafka(consumer)
+            }
+            Brokers::Memory(broker) => {
+                let ranges: HashMap<i32, (i64, i64)> = partitions
+                    .iter()
+                    .map(|&(p, low, high)| (p, (low, high)))
+                    .collect();
+                Feed::Memory(broker.read(topic, &ranges).into())
+            }
+        };
+        Ok(Reader::new(feed, partitions))
+    }
+}
+
+/// Messages of the partitions a `Reader` owns.
+enum Feed {
+    Kafka(BaseConsumer),
+    Memory(VecDeque<Delivery>),
+}
+
+impl Feed {
+    /// The next message, waiting up to `wait` for one.
+    fn poll(&mut self, wait: Duration) -> Option<Result<Delivery, String>> {
+        match self {
+            Feed::Kafka(consumer) => consumer.poll(wait).map(|msg| {
+                msg.map(|m| Delivery::from_kafka(&m))
+                    .map_err(|e| e.to_string())
+            }),
+            Feed::Memory(messages) => messages.pop_front().map(Ok),
+        }
+    }
+}
+
+fn create_consumer(brokers: &str, config: &HashMap<String, String>) -> PyResult<BaseConsumer> {
+    let mut client = ClientConfig::new();
+    client
+        .set("bootstrap.servers", brokers)
+        .set("group.id", "concord-cold-start")
+        .set("enable.auto.commit", "false")
+        .set("enable.partition.eof", "false");
+    for (k, v) in config {
+        client.set(k, v);
+    }
+    client
+        .create()
+        .map_err(|e| kafka_err("consumer creation failed", e))
+}
+
+/// Captures the watermarks of every partition before anything is read.
+fn plan(consumer: &BaseConsumer, topic: &str, timeout: Duration) -> PyResult<Plan> {
+    let metadata = consumer
+        .fetch_metadata(Some(topic), timeout)
+        .map_err(|e| kafka_err("metadata fetch failed", e))?;
+    let ids: Vec<i32> = metadata
+        .topics()
+        .iter()
+        .filter(|t| t.name() == topic)
+        .flat_map(|t| t.partitions().iter().map(|p| p.id()))
+        .collect();
+    if ids.is_empty() {
+        return Err(kafka_err("topic not found", topic));
+    }
+    let watermarks = ids
+        .into_iter()
+        .map(|p| {
+            let (low, high) = consumer
+                .fetch_watermarks(topic, p, timeout)
+                .map_err(|e| kafka_err("watermark fetch failed", e))?;
+            Ok((p, low, high))
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(Plan::new(watermarks))
+}
+
+/// Reads a set of partitions up to their planned end, keeping the latest record per
+/// key.
+struct Reader {
+    feed: Feed,
+    remaining: HashMap<i32, i64>,
+    state: HashMap<String, Record>,
+    read: u64,
+    total: u64,
+}
+
+impl Reader {
+    fn new(feed: Feed, partitions: &[(i32, i64, i64)]) -> Self {
+        Reader {
+            feed,
+            remaining: partitions.iter().map(|&(p, _, high)| (p, high)).collect(),
+            state: HashMap::new(),
+            read: 0,
+            total: partitions
+                .iter()
+                .map(|&(_, low, high)| (high - low) as u64)
+                .sum(),
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.remaining.is_empty()
+    }
+
+    /// Polls one batch and folds it into the state. Does not need the GIL.
+    fn step(&mut self, deadline: Instant) -> PyResult<()> {
+        for _ in 0..POLL_BATCH {
+            let wait = deadline
+                .saturating_duration_since(Instant::now())
+                .min(Duration::from_millis(100));
+            let Some(msg) = self.feed.poll(wait) else {
+                break;
+            };
+            let msg = msg.map_err(|e| kafka_err("Kafka error", e))?;
+            let Some(&end) = self.remaining.get(&msg.partition) else {
+                continue;
+            };
+            if msg.offset >= end {
+                continue;
+            }
+            self.read += 1;
+            if msg.offset + 1 >= end {
+                self.remaining.remove(&msg.partition);
+            }
+            let Some(key) = msg.key.map(|k| String::from_utf8_lossy(&k).into_owned()) else {
+                continue;
+            };
+            let record = Record {
+                partition: msg.partition,
+                offset: msg.offset,
+                timestamp: msg.timestamp.unwrap_or(-1),
+                payload: msg.payload.filter(|p| !p.is_empty()),
+            };
+            keep_latest(&mut self.state, key, record);
+            if self.done() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn timeout_err(&self, topic: &str) -> PyErr {
+        PyTimeoutError::new_err(format!(
+            "cold start of '{}' timed out after reading {} of {} messages",
+            topic, self.read, self.total
+        ))
+    }
+}
+
+/// Reads `topic` up to its current end and builds a walker from the last state per
+/// key. Returns the walker and the end offset per partition (where a live consumer
+/// should resume). `progress(read, total)` is called after each polled batch.
+pub fn load(
+    py: Python,
+    brokers: &Brokers,
+    topic: &str,
+    timeout_ms: u64,
+    progress: Option<&PyAny>,
+    normalizer: Option<Normalizer>,
+) -> PyResult<(GraphWalker, BTreeMap<i32, i64>)> {
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+    let timeout = Duration::from_millis(timeout_ms);
+
+    let plan = py.allow_threads(|| brokers.plan(topic, timeout))?;
+    let mut reader = brokers.reader(topic, &plan.partitions)?;
+    while !reader.done() {
+        if Instant::now() >= deadline {
+            return Err(reader.timeout_err(topic));
+        }
+        py.allow_threads(|| reader.step(deadline))?;
+        if let Some(cb) = progress {
+            cb.call1((reader.read, reader.total))?;
+        }
+    }
+    Ok((assemble(py, vec![reader.state], normalizer)?, plan.ends))
+}
+
+/// Per-worker counters shared with the progress reporter.
+struct WorkerProgress {
+    partitions: Vec<i32>,
+    total: u64,
+    read: AtomicU64,
+    finished: AtomicBool,
+}
+
+/// Like `load`, but reads the partitions with `workers` consumers in parallel, each
+/// owning a round-robin subset, then merges what they read (see `assemble`).
+/// `progress(read, total, workers)` is called periodically; `workers` lists one
+/// dict per worker with `partitions`, `read`, `total` and `messages_per_sec`.
+pub fn load_parallel(
+    py: Python,
+    brokers: &Brokers,
+    topic: &str,
+    workers: usize,
+    timeout_ms: u64,
+    progress: Option<&PyAny>,
+    normalizer: Option<Normalizer>,
+) -> PyResult<(GraphWalker, BTreeMap<i32, i64>)> {
+    if workers == 0 {
+        return Err(PyValueError::new_err("workers must be at least 1"));
+    }
+    let started = Instant::now();
+    let deadline = started + Duration::from_millis(timeout_ms);
+    let timeout = Duration::from_millis(timeout_ms);
+
+    let plan = py.allow_threads(|| brokers.plan(topic, timeout))?;
+    let workers = workers.min(plan.partitions.len()).max(1);
+    let mut assignments: Vec<Vec<(i32, i64, i64)>> = vec![Vec::new(); workers];
+    for (i, &part) in plan.partitions.iter().enumerate() {
+        assignments[i % workers].push(part);
+    }
+    let readers = assignments
+        .iter()
+        .map(|parts| brokers.reader(topic, parts))
+        .collect::<PyResult<Vec<_>>>()?;
+    let counters: Vec<WorkerProgress> = assignments
+        .iter()
+        .zip(&readers)
+        .map(|(parts, reader)| WorkerProgress {
+            partitions: parts.iter().map(|&(p, _, _)| p).collect(),
+            total: reader.total,
+            read: AtomicU64::new(0),
+            finished: AtomicBool::new(false),
+        })
+        .collect();
+
+    let cancel = AtomicBool::new(false);
+    let results: Vec<PyResult<HashMap<String, Record>>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = readers
+            .into_iter()
+            .zip(&counters)
+            .map(|(mut reader, counter)| {
+                let cancel = &cancel;
+                scope.spawn(move || {
+                    let result = (|| {
+                        while !reader.done() && !cancel.load(Ordering::Relaxed) {
+                            if Instant::now() >= deadline {
+                                return Err(reader.timeout_err(topic));
+                            }
+                            reader.step(deadline)?;
+                            counter.read.store(reader.read, Ordering::Relaxed);
+                        }
+                        Ok(reader.state)
+                    })();
+                    counter.finished.store(true, Ordering::Release);
+                    result
+                })
+            })
+            .collect();
+        let all_finished = || counters.iter().all(|c| c.finished.load(Ordering::Acquire));
+        loop {
+            let finished = py.allow_threads(|| {
+                let next = Instant::now() + PROGRESS_INTERVAL;
+                while Instant::now() < next && !all_finished() {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                all_finished()
+            });
+            if let Some(cb) = progress {
+                if let Err(e) = report(py, cb, &counters, started) {
+                    cancel.store(true, Ordering::Relaxed);
+                    return vec![Err(e)];
+                }
+            }
+            if finished {
+                break;
+            }
+        }
+        handles
+            .into_iter()
+            .map(|h| {
+                h.join()
+                    .unwrap_or_else(|_| Err(PyRuntimeError::new_err("cold start worker panicked")))
+            })
+            .collect()
+    });
+
+    let states = results.into_iter().collect::<PyResult<Vec<_>>>()?;
+    Ok((assemble(py, states, normalizer)?, plan.ends))
+}
+
+fn report(py: Python, cb: &PyAny, counters: &[WorkerProgress], started: Instant) -> PyResult<()> {
+    let elapsed = started.elapsed().as_secs_f64().max(f64::EPSILON);
+    let mut read = 0;
+    let mut total = 0;
+    let mut workers = Vec::with_capacity(counters.len());
+    for c in counters {
+        let r = c.read.load(Ordering::Relaxed);
+        read += r;
+        total += c.total;
+        let d = PyDict::new(py);
+        d.set_item("partitions", c.partitions.clone())?;
+        d.set_item("read", r)?;
+        d.set_item("total", c.total)?;
+        d.set_item("messages_per_sec", r as f64 / elapsed)?;
+        workers.push(d);
+    }
+    cb.call1((read, total, workers))?;
+    Ok(())
+}
+
+/// The walker holding the latest record per key across `states`, the per-key
+/// states of the readers. A key read by several of them is kept only where its
+/// record supersedes the others. Each state then becomes a partial walker (in
+/// parallel, without the GIL), and the partials are merged into one walker with
+/// `normalizer`, which normalizes every id once as it is adopted.
+fn assemble(
+    py: Python,
+    mut states: Vec<HashMap<String, Record>>,
+    normalizer: Option<Normalizer>,
+) -> PyResult<GraphWalker> {
+    if states.len() > 1 {
+        let mut owner: HashMap<String, usize> = HashMap::new();
+        let mut superseded = Vec::new();
+        for (i, state) in states.iter().enumerate() {
+            for (key, record) in state {
+                match owner.entry(key.clone()) {
+                    Entry::Vacant(e) => {
+                        e.insert(i);
+                    }
+                    Entry::Occupied(mut e) => {
+                        let held = *e.get();
+                        if record.supersedes(&states[held][key]) {
+                            superseded.push((held, key.clone()));
+                            e.insert(i);
+                        } else {
+                            superseded.push((i, key.clone()));
+                        }
+                    }
+                }
+            }
+        }
+        for (i, key) in superseded {
+            states[i].remove(&key);
+        }
+    }
+    let partials = py.allow_threads(|| {
+        config::install(|| {
+            states
+                .into_par_iter()
+                .map(build)
+                .collect::<PyResult<Vec<_>>>()
+        })
+    })?;
+    let mut walker = GraphWalker::blank();
+    walker.normalizer = normalizer;
+    walker
+        .nodes
+        .reserve(partials.iter().map(|p| p.nodes.len()).sum());
+    for partial in &partials {
+        walker.merge_from(py, partial, None)?;
+    }
+    Ok(walker)
+}
+
+/// A walker from the latest record per key, in key order, through `add_node`.
+fn build(state: HashMap<String, Record>) -> PyResult<GraphWalker> {
+    let mut walker = GraphWalker::blank();
+    let mut keys: Vec<&String> = state.keys().collect();
+    keys.sort();
+    for key in keys {
+        let record = &state[key];
+        let Some(payload) = &record.payload else {
+            continue;
+        };
+        let node: NodeState = serde_json::from_slice(payload).map_err(|e| {
+            SerializationError::new_err(format!(
+                "partition {} offset {}: {}",
+                record.partition, record.offset, e
+            ))
+        })?;
+        walker.add_node(key.clone(), node.dependents, None, Some(node.attributes))?;
+    }
+    Ok(walker)
+}
@@ -0,0 +1,112 @@
+//! Bounded LRU of previous propagation results per start node, for
+//! `GraphWalker::propagate_and_compare`.
+
+use std::collections::{HashMap, HashSet};
+
+pub const DEFAULT_CAPACITY: usize = 64;
+
+struct Entry {
+    version: u64,
+    result: HashSet<String>,
+    last_used: u64,
+}
+
+pub struct ResultCache {
+    capacity: usize,
+    /// Maximum version distance at which a stored result is still compared against;
+    /// None means any age.
+    pub max_staleness: Option<u64>,
+    entries: HashMap<String, Entry>,
+    tick: u64,
+}
+
+impl Default for ResultCache {
+    fn default() -> Self {
+        ResultCache {
+            capacity: DEFAULT_CAPACITY,
+            max_staleness: None,
+            entries: HashMap::new(),
+            tick: 0,
+        }
+    }
+}
+
+pub struct Comparison {
+    /// Version of the stored result that was compared against, if any.
+    pub previous_version: Option<u64>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl ResultCache {
+    /// Stores `current` for `start` at `version` and compares it with the stored
+    /// result, if one exists and is fresh enough.
+    pub fn compare(&mut self, start: &str, version: u64, current: HashSet<String>) -> Comparison {
+        self.tick += 1;
+        let previous = self.entries.remove(start).filter(|e| {
+            self.max_staleness
+                .is_none_or(|max| version.saturating_sub(e.version) <= max)
+        });
+        let comparison = match &previous {
+            Some(prev) => {
+                let mut added: Vec<String> = current.difference(&prev.result).cloned().collect();
+                let mut removed: Vec<String> = prev.result.difference(&current).cloned().collect();
+                added.sort();
+                removed.sort();
+                Comparison {
+                    previous_version: Some(prev.version),
+                    unchanged: current.len() - added.len(),
+                    added,
+                    removed,
+                }
+            }
+            None => Comparison {
+                previous_version: None,
+                added: Vec::new(),
+                removed: Vec::new(),
+                unchanged: 0,
+            },
+        };
+        if self.capacity > 0 {
+            self.entries.insert(
+                start.to_string(),
+                Entry {
+                    version,
+                    result: current,
+                    last_used: self.tick,
+                },
+            );
+            self.evict();
+        }
+        comparison
+    }
+
+    /// The stored result for `start`, regardless of age.
+    pub fn get(&self, start: &str) -> Option<&HashSet<String>> {
+        self.entries.get(start).map(|e| &e.result)
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn evict(&mut self) {
+        while self.entries.len() > self.capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone());
+            match oldest {
+                Some(k) => self.entries.remove(&k),
+                None => break,
+            };
+        }
+    }
+}
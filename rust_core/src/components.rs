@@ -0,0 +1,250 @@
+//! Incrementally maintained weakly connected components.
+//!
+//! The tracker keeps a union-find over node ids that lives as long as the walker.
+//! Until the first `weakly_connected_components()` call it is dormant. After that,
+//! every mutation is fed to it as a `GraphEvent` and turned into hints, which are
+//! applied lazily on the next query against the then-current graph:
+//! - added edges are unioned, in O(α) each
+//! - ids whose outgoing edges may have shrunk (replaced, removed or tombstoned
+//!   nodes) mark their component stale, and only stale components are re-flooded
+//! - edges to ids that do not exist yet are parked until the id is created
+//!
+//! Mutations without a cheap local description (`prune_edges_below`, `split_node`,
+//! rolled-back replays) fall back to a full recomputation.
+
+use crate::events::GraphEvent;
+use crate::store::NodeStore;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Default)]
+pub struct ComponentTracker {
+    /// False until the first query; a dormant tracker ignores mutations.
+    live: bool,
+    /// The next sync recomputes everything.
+    full: bool,
+    index: HashMap<String, usize>,
+    ids: Vec<String>,
+    /// Whether the id is a node of the graph as of the last sync.
+    present: Vec<bool>,
+    parent: Vec<usize>,
+    /// Members of each root's component; present nodes only.
+    members: HashMap<usize, Vec<usize>>,
+    /// Edges from present nodes to ids that were not nodes at the last sync.
+    dangling: HashMap<String, HashSet<usize>>,
+    added: Vec<(String, String)>,
+    shrunk: HashSet<String>,
+    pub incremental_syncs: u64,
+    pub full_syncs: u64,
+}
+
+impl ComponentTracker {
+    /// True if the next query has work to do.
+    pub fn is_stale(&self) -> bool {
+        !self.live || self.full || !self.added.is_empty() || !self.shrunk.is_empty()
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.live
+    }
+
+    /// Forces a full recomputation on the next query.
+    pub fn invalidate(&mut self) {
+        self.full = true;
+    }
+
+    /// Records the hints for a mutation about to be applied.
+    pub fn observe(&mut self, event: &GraphEvent) {
+        if !self.live || self.full {
+            return;
+        }
+        match event {
+            GraphEvent::AddNode { id, dependents } => {
+                // Replacing a node may drop edges it had before.
+                self.shrunk.insert(id.clone());
+                for dep in dependents {
+                    self.added.push((id.clone(), dep.clone()));
+                    // In undirected_multi mode the mirrored half-edge is added too.
+                    self.added.push((dep.clone(), id.clone()));
+                }
+                self.added.push((id.clone(), id.clone()));
+            }
+            GraphEvent::AddEdge { from, to }
+            | GraphEvent::TouchEdge { from, to, .. }
+            | GraphEvent::MergeEdge { from, to, .. } => {
+                self.added.push((from.clone(), to.clone()));
+                self.added.push((to.clone(), from.clone()));
+                self.added.push((from.clone(), from.clone()));
+            }
+            // Nodes with edges into a removed node share its component, so
+            // re-flooding that component covers pruned references too.
+            GraphEvent::Tombstone { id, .. }
+            | GraphEvent::RemoveNode { id, .. }
+            | GraphEvent::RemoveEdge { from: id, .. } => {
+                self.shrunk.insert(id.clone());
+            }
+            GraphEvent::PruneEdgesBelow { .. }
+            | GraphEvent::Repair { .. }
+            | GraphEvent::SplitNode { .. } => self.full = true,
+            GraphEvent::DecayWeights { .. }
+            | GraphEvent::SetWeights { .. }
+            | GraphEvent::SetLabel { .. }
+            | GraphEvent::PurgeTombstones { .. }
+            | GraphEvent::SetAttrs { .. } => {}
+        }
+    }
+
+    /// Brings the components up to date with `nodes`.
+    pub fn sync(&mut self, nodes: &NodeStore) {
+        if !self.live || self.full {
+            self.rebuild(nodes);
+            return;
+        }
+        if self.added.is_empty() && self.shrunk.is_empty() {
+            return;
+        }
+        self.incremental_syncs += 1;
+
+        // Re-flood the components touched by a possible removal, all at once so that
+        // unions between them cannot hide a member from the reset.
+        let shrunk = std::mem::take(&mut self.shrunk);
+        let mut roots = HashSet::new();
+        for id in &shrunk {
+            if let Some(&i) = self.index.get(id) {
+                if self.present[i] {
+                    roots.insert(self.find(i));
+                }
+            }
+        }
+        let reset: Vec<usize> = roots
+            .into_iter()
+            .flat_map(|r| self.members.remove(&r).unwrap_or_default())
+            .collect();
+        for &i in &reset {
+            self.parent[i] = i;
+            self.present[i] = nodes.contains_key(&self.ids[i]);
+            if self.present[i] {
+                self.members.insert(i, vec![i]);
+            }
+        }
+        for &i in &reset {
+            if self.present[i] {
+                self.link_out(i, nodes);
+            }
+        }
+
+        for (from, to) in std::mem::take(&mut self.added) {
+            if !nodes.contains_key(&from) {
+                continue;
+            }
+            let u = self.ensure_present(&from, nodes);
+            if from == to || !nodes.has_edge(&from, &to) {
+                continue;
+            }
+            if nodes.contains_key(&to) {
+                let v = self.ensure_present(&to, nodes);
+                self.union(u, v);
+            } else {
+                self.dangling.entry(to).or_default().insert(u);
+            }
+        }
+    }
+
+    /// Components as sorted id lists, largest first, then by smallest id.
+    pub fn components(&mut self) -> Vec<Vec<String>> {
+        let mut out: Vec<Vec<String>> = self
+            .members
+            .values()
+            .map(|m| {
+                let mut ids: Vec<String> = m.iter().map(|&i| self.ids[i].clone()).collect();
+                ids.sort();
+                ids
+            })
+            .collect();
+        out.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].cmp(&b[0])));
+        out
+    }
+
+    fn rebuild(&mut self, nodes: &NodeStore) {
+        *self = ComponentTracker {
+            live: true,
+            incremental_syncs: self.incremental_syncs,
+            full_syncs: self.full_syncs + 1,
+            ..Default::default()
+        };
+        for id in nodes.sorted_keys() {
+            self.ensure_present(id, nodes);
+        }
+    }
+
+    /// Marks `id` (a node of `nodes`) present, linking it on first sight: its own
+    /// edges and the parked edges pointing to it.
+    fn ensure_present(&mut self, id: &str, nodes: &NodeStore) -> usize {
+        let i = match self.index.get(id) {
+            Some(&i) => i,
+            None => {
+                let i = self.ids.len();
+                self.index.insert(id.to_string(), i);
+                self.ids.push(id.to_string());
+                self.present.push(false);
+                self.parent.push(i);
+                i
+            }
+        };
+        if self.present[i] {
+            return i;
+        }
+        self.present[i] = true;
+        self.parent[i] = i;
+        self.members.insert(i, vec![i]);
+        self.link_out(i, nodes);
+        for src in self.dangling.remove(id).unwrap_or_default() {
+            let still_linked = self.present[src] && nodes.has_edge(&self.ids[src], id);
+            if still_linked {
+                self.union(src, i);
+            }
+        }
+        i
+    }
+
+    /// Unions `i` with the present targets of its edges and parks the rest. A target
+    /// that is a node but not yet present is linked when it becomes present (every
+    /// created node has a hint), which keeps this free of recursion.
+    fn link_out(&mut self, i: usize, nodes: &NodeStore) {
+        let Some(node) = nodes.get(&self.ids[i]) else {
+            return;
+        };
+        for dep in nodes.targets(node) {
+            match self.index.get(dep) {
+                Some(&j) if self.present[j] => self.union(i, j),
+                _ => {
+                    self.dangling.entry(dep.clone()).or_default().insert(i);
+                }
+            }
+        }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    /// Unions by member count, moving the smaller member list into the larger.
+    fn union(&mut self, a: usize, b: usize) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return;
+        }
+        let size = |r: usize| self.members.get(&r).map_or(0, Vec::len);
+        let (big, small) = if size(ra) >= size(rb) {
+            (ra, rb)
+        } else {
+            (rb, ra)
+        };
+        self.parent[small] = big;
+        let moved = self.members.remove(&small).unwrap_or_default();
+        self.members.entry(big).or_default().extend(moved);
+    }
+}
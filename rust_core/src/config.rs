@@ -0,0 +1,179 @@
+//! Module-wide configuration: the thread pool used by parallel algorithms, the
+//! default deterministic mode, computation coalescing and the logging level. Default resource limits live in
+//! `limits` and are set through the same `configure` call.
+//!
+//! Configuration may change at any time. A new thread count builds a new pool that
+//! subsequent calls use; calls already running finish on the pool they started on.
+
+use crate::limits;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, RwLock};
+
+#[derive(Clone, Copy)]
+pub enum LogLevel {
+    Off = 0,
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+}
+
+const LOG_LEVELS: [(&str, LogLevel); 5] = [
+    ("off", LogLevel::Off),
+    ("error", LogLevel::Error),
+    ("warn", LogLevel::Warn),
+    ("info", LogLevel::Info),
+    ("debug", LogLevel::Debug),
+];
+
+/// Dedicated pool and the thread count it was built with; None means rayon's global
+/// pool (one thread per core).
+static POOL: RwLock<Option<(usize, Arc<rayon::ThreadPool>)>> = RwLock::new(None);
+static DETERMINISTIC: AtomicBool = AtomicBool::new(false);
+static COALESCE: AtomicBool = AtomicBool::new(true);
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+/// Runs `op` inside the configured thread pool, so any rayon parallelism in it is
+/// capped at the configured thread count.
+pub fn install<R: Send>(op: impl FnOnce() -> R + Send) -> R {
+    let pool = POOL
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|(_, pool)| pool.clone());
+    match pool {
+        Some(pool) => pool.install(op),
+        None => op(),
+    }
+}
+
+/// Whether algorithms should favor bit-identical results over speed by default.
+pub fn deterministic() -> bool {
+    DETERMINISTIC.load(Ordering::Relaxed)
+}
+
+/// Whether identical in-flight cached computations are shared (see `cache`).
+pub fn coalesce() -> bool {
+    COALESCE.load(Ordering::Relaxed)
+}
+
+pub fn log_enabled(level: LogLevel) -> bool {
+    level as u8 <= LOG_LEVEL.load(Ordering::Relaxed)
+}
+
+/// `log_at!(Error, ...)` etc.: `eprintln!` gated on the configured log level.
+macro_rules! log_at {
+    ($level:ident, $($arg:tt)*) => {
+        if $crate::config::log_enabled($crate::config::LogLevel::$level) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+pub(crate) use log_at;
+
+fn parse_log_level(name: &str) -> PyResult<LogLevel> {
+    LOG_LEVELS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, level)| *level)
+        .ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "unknown log level '{}' (expected off, error, warn, info or debug)",
+                name
+            ))
+        })
+}
+
+/// Updates module configuration from a dict. Recognized keys:
+/// - `threads`: positive int, or None for one thread per core
+/// - `deterministic`: bool
+/// - `coalesce`: bool, share identical in-flight computations (default True)
+/// - `limits`: dict accepted by `set_default_limits`
+/// - `log_level`: "off", "error", "warn", "info" or "debug"
+///
+/// Keys not present are left unchanged. Invalid options raise ValueError and leave
+/// the whole configuration untouched.
+#[pyfunction]
+pub fn configure(options: &PyDict) -> PyResult<()> {
+    let mut threads = None;
+    let mut deterministic = None;
+    let mut coalesce = None;
+    let mut limits = None;
+    let mut log_level = None;
+    for (key, value) in options.iter() {
+        let key: String = key.extract()?;
+        match key.as_str() {
+            "threads" => threads = Some(value.extract::<Option<usize>>()?),
+            "deterministic" => deterministic = Some(value.extract::<bool>()?),
+            "coalesce" => coalesce = Some(value.extract::<bool>()?),
+            "limits" => limits = Some(value.downcast::<PyDict>()?),
+            "log_level" => log_level = Some(parse_log_level(value.extract()?)?),
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown option '{}' (expected threads, deterministic, coalesce, limits or log_level)",
+                    other
+                )))
+            }
+        }
+    }
+
+    let pool = match threads {
+        Some(Some(0)) => return Err(PyValueError::new_err("threads must be positive")),
+        Some(Some(n)) => {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(n)
+                .thread_name(|i| format!("concord-worker-{}", i))
+                .build()
+                .map_err(|e| PyValueError::new_err(e.to_string()))?;
+            Some(Some((n, Arc::new(pool))))
+        }
+        Some(None) => Some(None),
+        None => None,
+    };
+    if let Some(limits) = limits {
+        limits::set_default_limits(limits)?;
+    }
+
+    if let Some(pool) = pool {
+        *POOL.write().unwrap_or_else(|e| e.into_inner()) = pool;
+    }
+    if let Some(d) = deterministic {
+        DETERMINISTIC.store(d, Ordering::Relaxed);
+    }
+    if let Some(c) = coalesce {
+        COALESCE.store(c, Ordering::Relaxed);
+    }
+    if let Some(level) = log_level {
+        LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+/// Current configuration in the shape `configure` accepts, plus `effective_threads`
+/// (the number of threads parallel algorithms actually use).
+#[pyfunction]
+pub fn get_config(py: Python) -> PyResult<PyObject> {
+    let threads = POOL
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|(n, _)| *n);
+    let level = LOG_LEVEL.load(Ordering::Relaxed);
+    let d = PyDict::new(py);
+    d.set_item("threads", threads)?;
+    d.set_item("effective_threads", install(rayon::current_num_threads))?;
+    d.set_item("deterministic", deterministic())?;
+    d.set_item("coalesce", coalesce())?;
+    d.set_item("limits", limits::get_default_limits(py)?)?;
+    d.set_item(
+        "log_level",
+        LOG_LEVELS
+            .iter()
+            .find(|(_, l)| *l as u8 == level)
+            .map(|(n, _)| *n),
+    )?;
+    Ok(d.into())
+}
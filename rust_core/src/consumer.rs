@@ -1,62 +1,906 @@
-use rdkafka::config::ClientConfig;
+use crate::config::log_at;
+use crate::kafka::{redact, ConsumerConfig, StatsContext};
+use crate::namespace;
+use crate::normalize::Normalizer;
+use crate::pipeline::{Applied, ApplyOptions, Message, Shared};
+use crate::producer::Publish;
+use crate::source::{
+    AnySource, Commit, Delivery, KafkaSource, MemoryBroker, MessageSource, Watermarks,
+};
+use pyo3::exceptions::{PyRuntimeError, PyTimeoutError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
 use rdkafka::consumer::{Consumer, StreamConsumer};
-use rdkafka::message::Message;
+use rdkafka::error::KafkaError;
+use rdkafka::types::RDKafkaErrorCode;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{JoinHandle, ThreadId};
+use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
+use tokio::sync::Notify;
 
-pub struct CausalConsumer {
+/// How long `start` waits for the brokers by default.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a consumer held by a paused, frozen walker checks for `unfreeze`.
+const PAUSE_POLL: Duration = Duration::from_millis(50);
+
+/// How often a consumer publishing through an `InvalidationPublisher` sends the
+/// invalidations it held back once their window has passed.
+const PUBLISH_FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Live state of one started consumer, updated by its thread.
+struct Status {
     brokers: String,
     topic: String,
+    state: Mutex<&'static str>,
+    messages: AtomicU64,
+    /// Key and payload bytes of the messages received.
+    bytes: AtomicU64,
+    /// Timestamp of the last message received that had one, in milliseconds, or
+    /// `NO_TIMESTAMP`.
+    last_timestamp: AtomicI64,
+    errors: AtomicU64,
+    /// Messages skipped because they could not be parsed.
+    malformed: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+const NO_TIMESTAMP: i64 = i64::MIN;
+
+impl Status {
+    fn fail(&self, error: String) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(error);
+    }
+
+    fn receive(&self, delivery: &Delivery) {
+        let size = delivery.key.as_ref().map_or(0, Vec::len)
+            + delivery.payload.as_ref().map_or(0, Vec::len);
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(size as u64, Ordering::Relaxed);
+        if let Some(timestamp) = delivery.timestamp {
+            self.last_timestamp.store(timestamp, Ordering::Relaxed);
+        }
+    }
+
+    fn counts(&self) -> Counts {
+        Counts {
+            messages: self.messages.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            malformed: self.malformed.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            at: crate::now_secs(),
+        }
+    }
+
+    fn skip_malformed(&self, error: String) {
+        self.malformed.fetch_add(1, Ordering::Relaxed);
+        *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(error);
+    }
+
+    fn snapshot(&self) -> ConsumerState {
+        ConsumerState {
+            brokers: self.brokers.clone(),
+            topic: self.topic.clone(),
+            state: *self.state.lock().unwrap_or_else(|e| e.into_inner()),
+            messages: self.messages.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            malformed: self.malformed.load(Ordering::Relaxed),
+            last_error: self
+                .last_error
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        }
+    }
+}
+
+fn kafka_err(context: &str, e: impl std::fmt::Display) -> PyErr {
+    PyRuntimeError::new_err(format!("{}: {}", context, e))
+}
+
+/// Every consumer started in this process, in start order.
+static REGISTRY: Mutex<Vec<Arc<Status>>> = Mutex::new(Vec::new());
+
+/// Point-in-time copy of a registered consumer's status.
+#[derive(Serialize)]
+pub struct ConsumerState {
+    pub brokers: String,
+    pub topic: String,
+    /// `running`, `stopping`, `stopped`, or `failed` if its thread panicked.
+    pub state: &'static str,
+    pub messages: u64,
+    pub errors: u64,
+    pub malformed: u64,
+    pub last_error: Option<String>,
+}
+
+/// States of all consumers started in this process.
+pub fn states() -> Vec<ConsumerState> {
+    REGISTRY
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|s| s.snapshot())
+        .collect()
+}
+
+/// States of all consumers started in this process, in start order, as dicts with
+/// `brokers`, `topic`, `state` ("running", "stopping", "stopped", or "failed" if
+/// its thread panicked), `messages`, `errors`, `malformed` and `last_error`.
+#[pyfunction]
+pub fn consumer_states(py: Python) -> PyResult<PyObject> {
+    let states = serde_json::to_value(states()).expect("consumer states serialize");
+    crate::pyjson::to_py(py, &states)
+}
+
+/// Stop request and exit notice shared by a consumer's thread and its handle.
+#[derive(Default)]
+struct Control {
+    stop: AtomicBool,
+    /// Woken when `stop` is set.
+    stopping: Notify,
+    exited: Mutex<bool>,
+    /// Signalled once `exited` is set.
+    exit: Condvar,
+}
+
+impl Control {
+    fn request_stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.stopping.notify_waiters();
+    }
+
+    /// Completes once a stop has been requested.
+    async fn stopped(&self) {
+        loop {
+            // Created before the check, so a request in between still wakes it.
+            let woken = self.stopping.notified();
+            if self.stop.load(Ordering::SeqCst) {
+                return;
+            }
+            woken.await;
+        }
+    }
+
+    /// Waits up to `timeout`, or without limit for None, for the thread to exit,
+    /// and returns whether it has.
+    fn wait_exit(&self, timeout: Option<Duration>) -> bool {
+        let exited = self.exited.lock().unwrap_or_else(|e| e.into_inner());
+        let exited = match timeout {
+            Some(timeout) => {
+                self.exit
+                    .wait_timeout_while(exited, timeout, |exited| !*exited)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0
+            }
+            None => self
+                .exit
+                .wait_while(exited, |exited| !*exited)
+                .unwrap_or_else(|e| e.into_inner()),
+        };
+        *exited
+    }
+}
+
+/// Marks the consumer stopped when its thread ends, or failed if it panicked.
+struct ExitGuard {
+    status: Arc<Status>,
+    control: Arc<Control>,
+}
+
+impl Drop for ExitGuard {
+    fn drop(&mut self) {
+        let mut state = self.status.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state = if std::thread::panicking() {
+            "failed"
+        } else {
+            "stopped"
+        };
+        drop(state);
+        *self
+            .control
+            .exited
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = true;
+        self.control.exit.notify_all();
+    }
+}
+
+/// Processing progress of one partition.
+#[derive(Default)]
+struct Position {
+    /// Offset after the last message processed with none failing before it.
+    done: Option<i64>,
+    /// Set by the first message that fails, after which `done` stays put so that
+    /// a restart redelivers that message.
+    held: bool,
+    committed: Option<i64>,
+}
+
+/// The offsets a consumer may commit, and where it commits them.
+struct Commits {
+    offsets: Mutex<BTreeMap<(String, i32), Position>>,
+    target: Arc<dyn Commit>,
+}
+
+impl Commits {
+    fn record(&self, delivery: &Delivery, processed: bool) {
+        let mut offsets = self.offsets.lock().unwrap_or_else(|e| e.into_inner());
+        let position = offsets
+            .entry((delivery.topic.clone(), delivery.partition))
+            .or_default();
+        if position.held {
+            return;
+        }
+        if processed {
+            position.done = Some(delivery.offset + 1);
+        } else {
+            position.held = true;
+        }
+    }
+
+    /// Commits every partition's `done` offset that is ahead of its committed one,
+    /// and returns those offsets.
+    fn commit(&self, wait: bool) -> Result<Vec<(String, i32, i64)>, String> {
+        let pending: Vec<(String, i32, i64)> = self
+            .offsets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter_map(|((topic, partition), p)| match p.done {
+                Some(done) if p.committed < Some(done) => Some((topic.clone(), *partition, done)),
+                _ => None,
+            })
+            .collect();
+        if pending.is_empty() {
+            return Ok(pending);
+        }
+        self.target.commit_offsets(&pending, wait)?;
+        let mut offsets = self.offsets.lock().unwrap_or_else(|e| e.into_inner());
+        for (topic, partition, offset) in &pending {
+            if let Some(p) = offsets.get_mut(&(topic.clone(), *partition)) {
+                p.committed = p.committed.max(Some(*offset));
+            }
+        }
+        Ok(pending)
+    }
+}
+
+/// A consumer's counters at one point, which `metrics()` counts from.
+#[derive(Clone, Copy)]
+struct Counts {
+    messages: u64,
+    bytes: u64,
+    malformed: u64,
+    errors: u64,
+    /// Seconds since the epoch.
+    at: f64,
+}
+
+/// Handle to a consumer started by `start_kafka_consumer`. Dropping it leaves the
+/// consumer running; its thread does not keep the interpreter from exiting.
+#[pyclass(module = "rust_core")]
+pub struct ConsumerHandle {
+    status: Arc<Status>,
+    control: Arc<Control>,
+    commits: Arc<Commits>,
+    watermarks: Arc<dyn Watermarks>,
+    topics: Vec<String>,
+    /// Counters as of the last `reset_metrics`, or the start.
+    baseline: Mutex<Counts>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+    thread_id: ThreadId,
+}
+
+impl ConsumerHandle {
+    /// Waits for the thread as `Control::wait_exit` does, reaping it once it exits.
+    /// From the consumer's own thread (a callback) it cannot exit yet, so this
+    /// returns false at once.
+    fn wait(&self, py: Python, timeout: Option<Duration>) -> bool {
+        if std::thread::current().id() == self.thread_id {
+            return false;
+        }
+        py.allow_threads(|| {
+            if !self.control.wait_exit(timeout) {
+                return false;
+            }
+            if let Some(thread) = self.thread.lock().unwrap_or_else(|e| e.into_inner()).take() {
+                let _ = thread.join();
+            }
+            true
+        })
+    }
+}
+
+fn timeout_of(secs: Option<f64>) -> PyResult<Option<Duration>> {
+    secs.map(|secs| {
+        Duration::try_from_secs_f64(secs).map_err(|_| {
+            PyValueError::new_err(format!(
+                "timeout_secs must be a non-negative number, got {}",
+                secs
+            ))
+        })
+    })
+    .transpose()
+}
+
+#[pymethods]
+impl ConsumerHandle {
+    /// Asks the consumer to stop, which unsubscribes it, and waits up to
+    /// `timeout_secs` for its thread to exit. Returns whether it has; if not, it
+    /// still stops once the message in hand is done. Calling it again is a no-op,
+    /// and calling it from `on_message` stops the consumer after that message.
+    #[pyo3(signature = (timeout_secs = 5.0))]
+    fn stop(&self, py: Python, timeout_secs: f64) -> PyResult<bool> {
+        let timeout = timeout_of(Some(timeout_secs))?;
+        if !self.control.stop.load(Ordering::SeqCst) {
+            self.control.request_stop();
+            let mut state = self.status.state.lock().unwrap_or_else(|e| e.into_inner());
+            if *state == "running" {
+                *state = "stopping";
+            }
+        }
+        Ok(self.wait(py, timeout))
+    }
+
+    /// Whether the consumer's thread is still alive.
+    fn is_running(&self) -> bool {
+        !*self
+            .control
+            .exited
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Waits up to `timeout_secs`, or without limit for None, for the consumer's
+    /// thread to exit, without asking it to. Returns whether it has.
+    #[pyo3(signature = (timeout_secs = None))]
+    fn join(&self, py: Python, timeout_secs: Option<f64>) -> PyResult<bool> {
+        let timeout = timeout_of(timeout_secs)?;
+        Ok(self.wait(py, timeout))
+    }
+
+    /// Commits, for each partition, the offset after the last message processed
+    /// with none failing before it, and waits for the commit. Returns what was
+    /// committed as `{topic: {partition: offset}}`; a partition already committed
+    /// that far is left out. Raises RuntimeError if the commit fails.
+    ///
+    /// A message is processed once it is applied to the walker (or skipped as
+    /// malformed) and `on_message` has returned. After a failure the partition's
+    /// offset stops advancing, so a restarted consumer gets that message again,
+    /// along with any later ones that did succeed.
+    fn commit(&self, py: Python) -> PyResult<BTreeMap<String, BTreeMap<i32, i64>>> {
+        let committed = py
+            .allow_threads(|| self.commits.commit(true))
+            .map_err(|e| kafka_err("commit failed", e))?;
+        let mut by_topic: BTreeMap<String, BTreeMap<i32, i64>> = BTreeMap::new();
+        for (topic, partition, offset) in committed {
+            by_topic.entry(topic).or_default().insert(partition, offset);
+        }
+        Ok(by_topic)
+    }
+
+    /// The most recent error the loop recorded (a receive error, an unparsable
+    /// message, a rejected mutation), or None. `status()` counts them in `errors` and
+    /// `malformed`.
+    fn last_error(&self) -> Option<String> {
+        self.status
+            .last_error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Throughput and progress since the consumer started, or since the last
+    /// `reset_metrics()`, as a dict:
+    ///
+    /// - `messages` and `bytes` (keys and payloads) received;
+    /// - `deserialization_errors`: messages skipped as malformed;
+    /// - `processing_errors`: errors receiving, applying, handling or committing;
+    /// - `last_message_timestamp`: of the latest message that had one, in
+    ///   milliseconds, or None;
+    /// - `since`: when counting started, in seconds since the epoch;
+    /// - `partitions`: `{topic: {partition: {"committed", "high_watermark",
+    ///   "lag"}}}`, each None while unknown.
+    ///
+    /// Against Kafka, `partitions` comes from librdkafka's statistics report,
+    /// every `ConsumerConfig.statistics_interval_ms`, and is empty until the first.
+    fn metrics(&self, py: Python) -> PyResult<PyObject> {
+        let now = self.status.counts();
+        let base = *self.baseline.lock().unwrap_or_else(|e| e.into_inner());
+        let d = PyDict::new(py);
+        d.set_item("messages", now.messages - base.messages)?;
+        d.set_item("bytes", now.bytes - base.bytes)?;
+        d.set_item("deserialization_errors", now.malformed - base.malformed)?;
+        d.set_item("processing_errors", now.errors - base.errors)?;
+        let last = self.status.last_timestamp.load(Ordering::Relaxed);
+        d.set_item(
+            "last_message_timestamp",
+            (last != NO_TIMESTAMP).then_some(last),
+        )?;
+        d.set_item("since", base.at)?;
+        let partitions = PyDict::new(py);
+        let lag = py.allow_threads(|| self.watermarks.lag(&self.topics));
+        for ((topic, partition), offsets) in lag {
+            let p = PyDict::new(py);
+            p.set_item("committed", offsets.committed)?;
+            p.set_item("high_watermark", offsets.high_watermark)?;
+            p.set_item("lag", offsets.lag)?;
+            match partitions.get_item(&topic)? {
+                Some(by_partition) => by_partition.set_item(partition, p)?,
+                None => {
+                    let by_partition = PyDict::new(py);
+                    by_partition.set_item(partition, p)?;
+                    partitions.set_item(topic, by_partition)?;
+                }
+            }
+        }
+        d.set_item("partitions", partitions)?;
+        Ok(d.into())
+    }
+
+    /// Makes `metrics()` count from now. `status()` and `consumer_states()` keep
+    /// their totals.
+    fn reset_metrics(&self) {
+        *self.baseline.lock().unwrap_or_else(|e| e.into_inner()) = self.status.counts();
+    }
+
+    /// This consumer's entry in `consumer_states()`.
+    fn status(&self, py: Python) -> PyResult<PyObject> {
+        let state =
+            serde_json::to_value(self.status.snapshot()).expect("consumer state serializes");
+        crate::pyjson::to_py(py, &state)
+    }
+
+    fn __repr__(&self) -> String {
+        let state = *self.status.state.lock().unwrap_or_else(|e| e.into_inner());
+        format!(
+            "ConsumerHandle(brokers={:?}, topic={:?}, state={:?})",
+            self.status.brokers, self.status.topic, state
+        )
+    }
+}
+
+pub struct CausalConsumer {
+    brokers: String,
+    /// Subscribed together; messages of all of them share the loop.
+    topics: Vec<String>,
+    /// Separator used to prefix node ids with their topic; None leaves ids as-is.
+    namespace_separator: Option<String>,
+    /// Applied to message keys before namespacing, like a walker's normalizer.
+    normalizer: Option<Arc<Normalizer>>,
+    /// Read instead of connecting to `brokers`, if set.
+    memory: Option<MemoryBroker>,
+    /// Walker messages are applied to.
+    walker: Option<(Arc<Shared>, ApplyOptions)>,
+    /// Python code messages are passed to, after the walker. Without either,
+    /// messages are only logged.
+    callbacks: Option<Callbacks>,
+    /// How long `start` waits for the brokers to answer.
+    connect_timeout: Duration,
+    config: ConsumerConfig,
 }
 
 impl CausalConsumer {
-    pub fn new(brokers: &str, topic: &str) -> Self {
+    pub fn new(brokers: &str, topics: Vec<String>) -> Self {
         Self {
             brokers: brokers.to_string(),
-            topic: topic.to_string(),
-        }
-    }
-
-    pub fn start(&self) {
-        let brokers = self.brokers.clone();
-        let topic = self.topic.clone();
-
-        std::thread::spawn(move || {
-            let rt = Runtime::new().unwrap();
-            rt.block_on(async move {
-                let consumer: StreamConsumer = ClientConfig::new()
-                    .set("group.id", "rust_causal_core")
-                    .set("bootstrap.servers", &brokers)
-                    .set("enable.partition.eof", "false")
-                    .set("session.timeout.ms", "6000")
-                    .set("enable.auto.commit", "true")
-                    .create()
-                    .expect("Consumer creation failed");
-
-                consumer
-                    .subscribe(&[&topic])
-                    .expect("Can't subscribe to specified topic");
-
-                println!("ignored: Rust Consumer started on topic: {}", topic);
-
-                loop {
-                    match consumer.recv().await {
-                        Err(e) => eprintln!("Kafka error: {}", e),
-                        Ok(m) => {
-                            let payload = match m.payload_view::<str>() {
-                                None => "",
-                                Some(Ok(s)) => s,
-                                Some(Err(e)) => {
-                                    eprintln!("Error while deserializing message payload: {:?}", e);
-                                    ""
-                                }
-                            };
-                            println!("ignored: Received event: {}", payload);
-                            // In a real implementation, we would extract fact_id and call GraphWalker here
-                            // For MVP, we just log it.
-                        }
-                    };
+            topics,
+            namespace_separator: None,
+            normalizer: None,
+            memory: None,
+            walker: None,
+            callbacks: None,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            config: ConsumerConfig::default(),
+        }
+    }
+
+    /// Consumes from `broker` in this process instead of from Kafka.
+    pub fn in_memory(broker: MemoryBroker, topics: Vec<String>) -> Self {
+        Self {
+            memory: Some(broker),
+            ..Self::new("memory", topics)
+        }
+    }
+
+    /// Prefixes node ids from each message with its topic and `separator`.
+    pub fn namespace_by_topic(mut self, separator: &str) -> Self {
+        self.namespace_separator = Some(separator.to_string());
+        self
+    }
+
+    /// Normalizes message keys with `normalizer` (see `GraphWalker(normalize=...)`).
+    pub fn normalize(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = Some(Arc::new(normalizer));
+        self
+    }
+
+    /// Applies each message to `walker` (see `pipeline`).
+    pub fn apply_to(mut self, walker: Arc<Shared>, options: ApplyOptions) -> Self {
+        self.walker = Some((walker, options));
+        self
+    }
+
+    /// The graph node id for `id` received on `topic`. An id that already has a
+    /// namespace is kept, so a message can refer to another topic's nodes.
+    fn node_id(
+        normalizer: Option<&Normalizer>,
+        separator: Option<&str>,
+        topic: &str,
+        id: &str,
+    ) -> Result<String, String> {
+        let id = match normalizer {
+            Some(n) => Python::with_gil(|py| n.apply(py, id)).map_err(|e| e.to_string())?,
+            None => id.to_string(),
+        };
+        Ok(match separator {
+            Some(sep) if namespace::split(&id, sep).is_none() => {
+                namespace::qualify(topic, &id, sep)
+            }
+            _ => id,
+        })
+    }
+
+    /// Passes each message to `handlers`, after applying it to the walker if
+    /// there is one; see `Callbacks`.
+    pub fn on_message(mut self, handlers: Handlers, on_error: Option<PyObject>) -> Self {
+        self.callbacks = Some(Callbacks { handlers, on_error });
+        self
+    }
+
+    /// Creates the Kafka consumer with `config` instead of the defaults.
+    pub fn config(mut self, config: ConsumerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Fails `start` if the brokers have not answered within `timeout`.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Creates the Kafka consumer, checks that the brokers answer within
+    /// `connect_timeout` and that every topic exists and may be read, and
+    /// subscribes.
+    fn connect(&self, py: Python) -> PyResult<StreamConsumer<StatsContext>> {
+        let consumer: StreamConsumer<StatsContext> = self
+            .config
+            .client_config(&self.brokers)?
+            .create_with_context(StatsContext::default())
+            .map_err(|e| match e {
+                // Such as a certificate that cannot be loaded.
+                KafkaError::ClientCreation(e) => {
+                    let message = format!("invalid consumer setting: {}", e);
+                    PyValueError::new_err(redact(message, &self.config.resolve()))
+                }
+                e => kafka_err("consumer creation failed", e),
+            })?;
+        let deadline = Instant::now() + self.connect_timeout;
+        for topic in &self.topics {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            self.check_topic(py, &consumer, topic, timeout)?;
+        }
+        let topics: Vec<&str> = self.topics.iter().map(String::as_str).collect();
+        consumer
+            .subscribe(&topics)
+            .map_err(|e| kafka_err("subscription failed", e))?;
+        Ok(consumer)
+    }
+
+    fn check_topic(
+        &self,
+        py: Python,
+        consumer: &StreamConsumer<StatsContext>,
+        topic: &str,
+        timeout: Duration,
+    ) -> PyResult<()> {
+        let metadata = py
+            .allow_threads(|| consumer.fetch_metadata(Some(topic), timeout))
+            .map_err(|e| match e {
+                KafkaError::MetadataFetch(
+                    code @ (RDKafkaErrorCode::OperationTimedOut
+                    | RDKafkaErrorCode::BrokerTransportFailure
+                    | RDKafkaErrorCode::AllBrokersDown),
+                ) => PyTimeoutError::new_err(format!(
+                    "brokers '{}' did not answer within {} ms: {}",
+                    self.brokers,
+                    self.connect_timeout.as_millis(),
+                    code
+                )),
+                e => kafka_err("metadata fetch failed", e),
+            })?;
+        match metadata.topics().iter().find(|t| t.name() == topic) {
+            None => Err(kafka_err("topic not found", topic)),
+            Some(t) => match t.error() {
+                Some(code) => {
+                    let context = format!("cannot consume topic '{}'", topic);
+                    Err(kafka_err(&context, RDKafkaErrorCode::from(code)))
                 }
-            });
+                None => Ok(()),
+            },
+        }
+    }
+
+    /// Connects (see `connect`) and starts the consumer on its own thread. Errors
+    /// up to that point are raised; later ones are counted in its status.
+    pub fn start(self, py: Python) -> PyResult<ConsumerHandle> {
+        let rt = Runtime::new().map_err(|e| kafka_err("cannot start consumer runtime", e))?;
+        let group = self.config.group_id();
+        let (source, target, watermarks): (AnySource, Arc<dyn Commit>, Arc<dyn Watermarks>) =
+            match &self.memory {
+                Some(broker) => {
+                    let source = broker.subscribe(&self.topics, group);
+                    let group = Arc::new(broker.group(group));
+                    (AnySource::Memory(source), group.clone(), group)
+                }
+                None => {
+                    // The consumer registers with the runtime it is created in.
+                    let _entered = rt.enter();
+                    let consumer = Arc::new(self.connect(py)?);
+                    let source = KafkaSource(consumer.clone());
+                    (AnySource::Kafka(source), consumer.clone(), consumer)
+                }
+            };
+        let commits = Arc::new(Commits {
+            offsets: Mutex::new(BTreeMap::new()),
+            target,
         });
+        let status = Arc::new(Status {
+            brokers: self.brokers.clone(),
+            topic: self.topics.join(","),
+            state: Mutex::new("running"),
+            messages: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            last_timestamp: AtomicI64::new(NO_TIMESTAMP),
+            errors: AtomicU64::new(0),
+            malformed: AtomicU64::new(0),
+            last_error: Mutex::new(None),
+        });
+        REGISTRY
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(status.clone());
+        let control = Arc::new(Control::default());
+        log_at!(
+            Info,
+            "ignored: Consumer started on topics: {}",
+            self.topics.join(", ")
+        );
+
+        let guard = ExitGuard {
+            status: status.clone(),
+            control: control.clone(),
+        };
+        let loop_commits = commits.clone();
+        let topics = self.topics.clone();
+        // Taken before the loop starts, so messages it reads at once still count.
+        let baseline = Mutex::new(status.counts());
+        let thread = std::thread::spawn(move || {
+            let (status, control) = (&guard.status, &guard.control);
+            rt.block_on(self.run(source, status, control, &loop_commits));
+        });
+        Ok(ConsumerHandle {
+            baseline,
+            status,
+            control,
+            commits,
+            watermarks,
+            topics,
+            thread_id: thread.thread().id(),
+            thread: Mutex::new(Some(thread)),
+        })
+    }
+
+    async fn run(
+        &self,
+        mut source: impl MessageSource,
+        status: &Status,
+        control: &Control,
+        commits: &Commits,
+    ) {
+        let publish = self
+            .walker
+            .as_ref()
+            .and_then(|(_, options)| options.publish.as_ref())
+            .filter(|publish| publish.publisher.is_some());
+        let mut flush = tokio::time::interval(PUBLISH_FLUSH_INTERVAL);
+        flush.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            let received = tokio::select! {
+                // A stop wins over a message that is ready too.
+                biased;
+                _ = control.stopped() => break,
+                _ = flush.tick(), if publish.is_some() => {
+                    Self::flush_publications(publish, false, status);
+                    continue;
+                }
+                received = source.recv() => received,
+            };
+            match received {
+                Err(e) => {
+                    log_at!(Error, "Kafka error: {}", e);
+                    status.fail(e);
+                }
+                Ok(delivery) => {
+                    status.receive(&delivery);
+                    let processed = self.handle(&delivery, status, control).await;
+                    commits.record(&delivery, processed);
+                    if self.config.auto_commit_after_callback {
+                        if let Err(e) = commits.commit(false) {
+                            log_at!(Error, "Offset commit failed: {}", e);
+                            status.fail(e);
+                        }
+                    }
+                }
+            }
+        }
+        // Nothing else would send what the publisher still holds back.
+        Self::flush_publications(publish, true, status);
+        source.close();
+        log_at!(
+            Info,
+            "ignored: Consumer stopped on topics: {}",
+            self.topics.join(", ")
+        );
+    }
+
+    fn flush_publications(publish: Option<&Publish>, all: bool, status: &Status) {
+        if let Some(Err(e)) = publish.map(|publish| publish.flush(all)) {
+            log_at!(Error, "Publishing held back invalidations failed: {}", e);
+            status.fail(e.to_string());
+        }
+    }
+
+    /// Applies the message to the walker and passes it to the callbacks, and
+    /// returns whether that succeeded, making its offset safe to commit.
+    async fn handle(&self, delivery: &Delivery, status: &Status, control: &Control) -> bool {
+        let applied = match &self.walker {
+            Some((walker, options)) => self.apply(walker, options, delivery, status, control).await,
+            None if self.callbacks.is_none() => return self.log(delivery, status),
+            None => true,
+        };
+        match &self.callbacks {
+            Some(callbacks) if applied => callbacks.deliver(delivery, status),
+            _ => applied,
+        }
+    }
+
+    fn node_id_of(&self, delivery: &Delivery, id: &str) -> Result<String, String> {
+        Self::node_id(
+            self.normalizer.as_deref(),
+            self.namespace_separator.as_deref(),
+            &delivery.topic,
+            id,
+        )
+    }
+
+    fn log(&self, delivery: &Delivery, status: &Status) -> bool {
+        let payload = delivery.payload.as_deref().unwrap_or_default();
+        let payload = match std::str::from_utf8(payload) {
+            Ok(s) => s,
+            Err(e) => {
+                log_at!(Error, "Error while deserializing message payload: {:?}", e);
+                ""
+            }
+        };
+        let key = delivery.key.as_deref().unwrap_or_default();
+        let key = std::str::from_utf8(key).unwrap_or_default();
+        match self.node_id_of(delivery, key) {
+            Ok(id) => {
+                log_at!(Debug, "ignored: Received event for {}: {}", id, payload);
+                true
+            }
+            Err(e) => {
+                log_at!(Error, "Failed to normalize key {:?}: {}", key, e);
+                status.fail(e);
+                false
+            }
+        }
+    }
+
+    /// Applies the message to `walker`, and returns whether it was applied or
+    /// skipped as malformed (which a redelivery would not change).
+    async fn apply(
+        &self,
+        walker: &Shared,
+        options: &ApplyOptions,
+        delivery: &Delivery,
+        status: &Status,
+        control: &Control,
+    ) -> bool {
+        let message = match Message::parse(delivery, |id| self.node_id_of(delivery, id)) {
+            Ok(message) => message,
+            Err(e) => {
+                log_at!(
+                    Warn,
+                    "Skipping malformed message at {}/{}@{}: {}",
+                    delivery.topic,
+                    delivery.partition,
+                    delivery.offset,
+                    e
+                );
+                status.skip_malformed(e);
+                return true;
+            }
+        };
+        loop {
+            match walker.apply(&message, options) {
+                Ok(Applied::Done) => return true,
+                Ok(Applied::Paused) => tokio::select! {
+                    _ = control.stopped() => return false,
+                    _ = tokio::time::sleep(PAUSE_POLL) => {}
+                },
+                Err(e) => {
+                    log_at!(Error, "Failed to apply message for {}: {}", message.id(), e);
+                    status.fail(e.to_string());
+                    return false;
+                }
+            }
+        }
+    }
+}
+
+/// Which Python callable gets a message.
+pub enum Handlers {
+    All(PyObject),
+    /// By topic; messages of other topics get none.
+    ByTopic(HashMap<String, PyObject>),
+}
+
+/// Python callbacks of a consumer. The handler of a message's topic is called
+/// with the dict of `Delivery::to_py`. An exception it raises is counted as an error and passed to
+/// `on_error(exception, message)` if given; either way the loop goes on. The GIL is
+/// taken only for the calls and no walker lock is held meanwhile, so callbacks may
+/// call into rust_core, the consumer's `SharedGraphWalker` included.
+pub struct Callbacks {
+    handlers: Handlers,
+    on_error: Option<PyObject>,
+}
+
+impl Callbacks {
+    /// Calls the message's handler, and returns whether it returned without
+    /// raising (true if there is none).
+    fn deliver(&self, delivery: &Delivery, status: &Status) -> bool {
+        let handler = match &self.handlers {
+            Handlers::All(handler) => handler,
+            Handlers::ByTopic(handlers) => match handlers.get(&delivery.topic) {
+                Some(handler) => handler,
+                None => return true,
+            },
+        };
+        Python::with_gil(|py| {
+            let message = match delivery.to_py(py) {
+                Ok(message) => message,
+                Err(e) => {
+                    status.fail(e.to_string());
+                    return false;
+                }
+            };
+            let Err(e) = handler.call1(py, (message,)) else {
+                return true;
+            };
+            log_at!(
+                Error,
+                "on_message raised at {}/{}@{}: {}",
+                delivery.topic,
+                delivery.partition,
+                delivery.offset,
+                e
+            );
+            status.fail(format!("on_message raised {}", e));
+            if let Some(on_error) = &self.on_error {
+                if let Err(e) = on_error.call1(py, (e.value(py), message)) {
+                    log_at!(Error, "on_error raised: {}", e);
+                }
+            }
+            false
+        })
     }
 }
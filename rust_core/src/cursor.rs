@@ -0,0 +1,149 @@
+//! Paging through large results. List-returning methods take `offset`/`limit`
+//! over a documented stable order; `*_cursor` methods return a `ResultCursor` that
+//! materializes the result once on the Rust side and hands it out page by page.
+//! A cursor is tied to the graph version it was created at and raises
+//! StaleCursorError once the graph has changed, rather than skipping or repeating
+//! entries. `iter(walker)` returns a `NodeIter`, which works the same way.
+
+use crate::errors::StaleCursorError;
+use crate::GraphWalker;
+use pyo3::prelude::*;
+
+/// Rows of a paged result, already in their stable order.
+pub enum Rows {
+    Ids(Vec<String>),
+    Edges(Vec<(String, String, f64)>),
+}
+
+impl Rows {
+    fn len(&self) -> usize {
+        match self {
+            Rows::Ids(rows) => rows.len(),
+            Rows::Edges(rows) => rows.len(),
+        }
+    }
+
+    fn slice_to_py(&self, py: Python, start: usize, end: usize) -> PyObject {
+        match self {
+            Rows::Ids(rows) => rows[start..end].to_vec().into_py(py),
+            Rows::Edges(rows) => rows[start..end].to_vec().into_py(py),
+        }
+    }
+}
+
+/// The `offset`/`limit` window of `rows`.
+pub fn page<T>(mut rows: Vec<T>, offset: usize, limit: Option<usize>) -> Vec<T> {
+    let start = offset.min(rows.len());
+    let end = limit.map_or(rows.len(), |l| start.saturating_add(l).min(rows.len()));
+    rows.truncate(end);
+    rows.drain(..start);
+    rows
+}
+
+/// True if `walker` is still at `version`.
+fn is_fresh(py: Python, walker: &Py<GraphWalker>, version: u64) -> bool {
+    // A walker mutably borrowed right now is mid-mutation, hence stale too.
+    walker.try_borrow(py).map(|w| w.version).ok() == Some(version)
+}
+
+/// Page-by-page view of a result computed at one graph version.
+#[pyclass]
+pub struct ResultCursor {
+    walker: Py<GraphWalker>,
+    version: u64,
+    rows: Rows,
+    position: usize,
+}
+
+impl ResultCursor {
+    pub(crate) fn new(walker: Py<GraphWalker>, version: u64, rows: Rows) -> Self {
+        ResultCursor {
+            walker,
+            version,
+            rows,
+            position: 0,
+        }
+    }
+
+    fn check_fresh(&self, py: Python) -> PyResult<()> {
+        if is_fresh(py, &self.walker, self.version) {
+            return Ok(());
+        }
+        Err(StaleCursorError::new_err(format!(
+            "graph changed since the cursor was created at version {} ({} of {} rows read)",
+            self.version,
+            self.position,
+            self.rows.len()
+        )))
+    }
+}
+
+#[pymethods]
+impl ResultCursor {
+    /// The next `n` rows (fewer at the end, empty once exhausted). Raises
+    /// StaleCursorError if the graph has been mutated since the cursor was created.
+    fn next_page(&mut self, py: Python, n: usize) -> PyResult<PyObject> {
+        self.check_fresh(py)?;
+        let start = self.position;
+        let end = start.saturating_add(n).min(self.rows.len());
+        self.position = end;
+        Ok(self.rows.slice_to_py(py, start, end))
+    }
+
+    /// Total number of rows in the result.
+    fn total(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Rows not yet returned by `next_page`.
+    fn remaining(&self) -> usize {
+        self.rows.len() - self.position
+    }
+
+    /// Graph version the result was computed at.
+    fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+/// Iterator over node ids in sorted order, as returned by `iter(walker)`. The ids
+/// are copied on the Rust side when it is created, so no Python list is built, and
+/// it raises StaleCursorError on the next step once the graph has been mutated.
+#[pyclass]
+pub struct NodeIter {
+    walker: Py<GraphWalker>,
+    version: u64,
+    ids: std::vec::IntoIter<String>,
+}
+
+impl NodeIter {
+    pub(crate) fn new(walker: Py<GraphWalker>, version: u64, ids: Vec<String>) -> Self {
+        NodeIter {
+            walker,
+            version,
+            ids: ids.into_iter(),
+        }
+    }
+}
+
+#[pymethods]
+impl NodeIter {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<String>> {
+        if !is_fresh(py, &self.walker, self.version) {
+            return Err(StaleCursorError::new_err(format!(
+                "graph changed during iteration (started at version {}, {} ids left)",
+                self.version,
+                self.ids.len()
+            )));
+        }
+        Ok(self.ids.next())
+    }
+
+    fn __length_hint__(&self) -> usize {
+        self.ids.len()
+    }
+}
@@ -0,0 +1,224 @@
+//! Dirty-fact tracking for `GraphWalker::mark_invalid`.
+//!
+//! Each dirty handle is either closed or open. A closed handle is known to have
+//! everything it reaches dirty too, so an invalidation that arrives there can stop:
+//! that early stop is what makes overlapping invalidations cheap. Clearing some ids,
+//! or a mutation that can lengthen reachability, breaks the guarantee only for the
+//! dirty ancestors of the ids involved; those are reopened. An open handle stays
+//! dirty, but the next invalidation reaching it walks past it and closes it again.
+
+use crate::events::GraphEvent;
+use crate::sources_of;
+use crate::store::NodeStore;
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum State {
+    #[default]
+    Clean,
+    Closed,
+    Open,
+}
+
+#[derive(Default)]
+pub struct DirtySet {
+    /// State per handle; handles past the end are clean.
+    state: Vec<State>,
+    len: usize,
+    /// Ids whose dirty ancestors must be reopened before the next invalidation.
+    pending: Vec<String>,
+    /// Set when every dirty handle must be reopened instead.
+    reopen_all: bool,
+    /// Handles whose dependents `mark` walked, since the walker was created.
+    pub expanded: u64,
+    /// Closed handles reopened, since the walker was created.
+    pub reopened: u64,
+}
+
+impl DirtySet {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn get(&self, handle: u32) -> State {
+        self.state.get(handle as usize).copied().unwrap_or_default()
+    }
+
+    fn set(&mut self, handle: u32, state: State) {
+        let i = handle as usize;
+        if i >= self.state.len() {
+            self.state.resize(i + 1, State::Clean);
+        }
+        match (self.state[i] == State::Clean, state == State::Clean) {
+            (true, false) => self.len += 1,
+            (false, true) => self.len -= 1,
+            _ => {}
+        }
+        self.state[i] = state;
+    }
+
+    pub fn contains(&self, handle: u32) -> bool {
+        self.get(handle) != State::Clean
+    }
+
+    /// Dirty handles, ascending.
+    pub fn handles(&self) -> impl Iterator<Item = u32> + '_ {
+        self.state
+            .iter()
+            .enumerate()
+            .filter(|(_, &s)| s != State::Clean)
+            .map(|(h, _)| h as u32)
+    }
+
+    /// Marks `handle` dirty with nothing known about what it reaches, as when
+    /// loading a snapshot.
+    pub fn insert_open(&mut self, handle: u32) {
+        self.set(handle, State::Open);
+    }
+
+    /// Notes what `event` does to reachability. Removals only shrink it, so they
+    /// keep every closed handle valid.
+    pub fn observe(&mut self, event: &GraphEvent) {
+        if self.is_empty() || self.reopen_all {
+            return;
+        }
+        match event {
+            // Re-creating an id also lifts its tombstone, which opens the edges into
+            // it; edges are taken both ways for undirected mode.
+            GraphEvent::AddNode { id, dependents } => {
+                self.pending.push(id.clone());
+                self.pending.extend(dependents.iter().cloned());
+            }
+            GraphEvent::AddEdge { from, to }
+            | GraphEvent::TouchEdge { from, to, .. }
+            | GraphEvent::MergeEdge { from, to, .. } => {
+                self.pending.push(from.clone());
+                self.pending.push(to.clone());
+            }
+            GraphEvent::SplitNode { .. } | GraphEvent::PurgeTombstones { .. } => self.reopen(),
+            GraphEvent::RemoveNode { .. }
+            | GraphEvent::RemoveEdge { .. }
+            | GraphEvent::Tombstone { .. }
+            | GraphEvent::PruneEdgesBelow { .. }
+            | GraphEvent::Repair { .. }
+            | GraphEvent::DecayWeights { .. }
+            | GraphEvent::SetWeights { .. }
+            | GraphEvent::SetLabel { .. }
+            | GraphEvent::SetAttrs { .. } => {}
+        }
+        // Past this size, reopening everything is no dearer than the ancestor walks.
+        if self.pending.len() > self.len {
+            self.reopen();
+        }
+    }
+
+    /// Reopens every dirty handle, for changes `observe` cannot see.
+    pub fn reopen(&mut self) {
+        self.reopen_all = true;
+        self.pending = Vec::new();
+    }
+
+    /// Applies the reopening noted since the last call. `reverse` holds the sources
+    /// of each handle.
+    pub fn sync(&mut self, nodes: &NodeStore, reverse: &[Vec<u32>]) {
+        if std::mem::take(&mut self.reopen_all) {
+            for s in &mut self.state {
+                if *s == State::Closed {
+                    *s = State::Open;
+                    self.reopened += 1;
+                }
+            }
+        }
+        let pending = std::mem::take(&mut self.pending);
+        let starts: Vec<u32> = pending.iter().filter_map(|id| nodes.handle(id)).collect();
+        self.reopen_ancestors(reverse, starts);
+    }
+
+    /// Reopens the dirty handles among `starts` and every dirty handle with a path
+    /// to one of them through dirty handles. A clean handle has no closed ancestor,
+    /// so the walk need not pass one, except at a start.
+    fn reopen_ancestors(&mut self, reverse: &[Vec<u32>], starts: Vec<u32>) {
+        let mut seen: HashSet<u32> = starts.iter().copied().collect();
+        let mut stack = starts;
+        while let Some(v) = stack.pop() {
+            if self.get(v) == State::Closed {
+                self.reopened += 1;
+            }
+            if self.contains(v) {
+                self.set(v, State::Open);
+            }
+            for &p in sources_of(reverse, v) {
+                if self.contains(p) && seen.insert(p) {
+                    stack.push(p);
+                }
+            }
+        }
+    }
+
+    /// Propagates from `start` as `propagate_invalidation` does, skipping handles for
+    /// which `skip` is true, and returns the handles it made dirty in BFS order. It
+    /// stops at closed handles and walks past open ones, closing them.
+    pub fn mark(&mut self, nodes: &NodeStore, start: u32, skip: impl Fn(u32) -> bool) -> Vec<u32> {
+        let start_state = self.get(start);
+        if start_state == State::Closed {
+            return Vec::new();
+        }
+        let mut fresh = Vec::new();
+        let mut queue = vec![start];
+        let mut head = 0;
+        while let Some(&u) = queue.get(head) {
+            head += 1;
+            let Some(node) = nodes.node(u) else {
+                continue;
+            };
+            self.expanded += 1;
+            for &v in &node.dependents {
+                if skip(v) {
+                    continue;
+                }
+                match self.get(v) {
+                    State::Closed => continue,
+                    State::Open => {}
+                    State::Clean => fresh.push(v),
+                }
+                self.set(v, State::Closed);
+                queue.push(v);
+            }
+        }
+        if start_state == State::Open {
+            self.set(start, State::Closed);
+        }
+        fresh
+    }
+
+    /// Marks `handles` clean and reopens their dirty ancestors, returning how many
+    /// were dirty.
+    pub fn clear(&mut self, reverse: &[Vec<u32>], handles: &[u32]) -> usize {
+        let mut cleared = Vec::new();
+        for &h in handles {
+            if self.contains(h) {
+                self.set(h, State::Clean);
+                cleared.push(h);
+            }
+        }
+        let count = cleared.len();
+        self.reopen_ancestors(reverse, cleared);
+        count
+    }
+
+    /// Marks everything clean, returning how many handles were dirty. The counters
+    /// are kept.
+    pub fn clear_all(&mut self) -> usize {
+        let count = self.len;
+        *self = DirtySet {
+            expanded: self.expanded,
+            reopened: self.reopened,
+            ..DirtySet::default()
+        };
+        count
+    }
+}
@@ -0,0 +1,299 @@
+//! Edge-list files (CSV, TSV and the like) read with buffered IO.
+//!
+//! Fields follow RFC 4180: a field may be quoted with `"`, and a quoted field may
+//! hold the delimiter, line breaks and `""` for a quote. Lines end in LF or CRLF,
+//! blank (or all-whitespace) lines are skipped, and a UTF-8 byte order mark at the start is dropped.
+//! Files go through zlib's `gzread`, which decompresses gzip input (concatenated
+//! members included) and passes anything else through, so compressed dumps need no
+//! flag. Errors name the line on which the offending record starts.
+
+use crate::errors::SerializationError;
+use libz_sys::{gzFile, gzclose, gzdopen, gzerror, gzread, Z_BUF_ERROR, Z_OK};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use std::ffi::CStr;
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::fd::IntoRawFd;
+use std::os::raw::{c_int, c_uint};
+
+/// Bytes read from the decompressed stream at a time.
+const BUFFER: usize = 1 << 20;
+
+/// A column given by position or by header name.
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
+
+impl Column {
+    pub fn from_py(value: &PyAny) -> PyResult<Column> {
+        match value.extract::<String>() {
+            Ok(name) => Ok(Column::Name(name)),
+            Err(_) => value.extract().map(Column::Index),
+        }
+    }
+
+    fn resolve(&self, header: Option<&[String]>) -> PyResult<usize> {
+        match (self, header) {
+            (Column::Index(i), _) => Ok(*i),
+            (Column::Name(name), Some(header)) => {
+                header.iter().position(|h| h == name).ok_or_else(|| {
+                    PyValueError::new_err(format!("no column '{}' in the header", name))
+                })
+            }
+            (Column::Name(name), None) => Err(PyValueError::new_err(format!(
+                "column '{}' is named, which needs has_header=True",
+                name
+            ))),
+        }
+    }
+}
+
+pub struct Options {
+    pub delimiter: u8,
+    pub has_header: bool,
+    pub source: Column,
+    pub target: Column,
+    pub weight: Option<Column>,
+}
+
+impl Options {
+    pub fn check_delimiter(delimiter: &str) -> PyResult<u8> {
+        match delimiter.as_bytes() {
+            [d] if d.is_ascii() && !matches!(d, b'"' | b'\n' | b'\r') => Ok(*d),
+            _ => Err(PyValueError::new_err(format!(
+                "delimiter must be one ASCII character other than a quote or line break, got {:?}",
+                delimiter
+            ))),
+        }
+    }
+}
+
+/// `gzread` over an open file, which it closes when dropped.
+struct GzReader(gzFile);
+
+impl GzReader {
+    fn open(path: &str) -> io::Result<GzReader> {
+        let fd = std::fs::File::open(path)?.into_raw_fd();
+        // SAFETY: `fd` is an open descriptor that zlib now owns and closes.
+        let file = unsafe { gzdopen(fd, c"rb".as_ptr()) };
+        if file.is_null() {
+            return Err(io::Error::other("cannot start reading the file"));
+        }
+        Ok(GzReader(file))
+    }
+}
+
+impl Read for GzReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(c_int::MAX as usize) as c_uint;
+        // SAFETY: `buf` has room for `len` bytes and the handle is open.
+        let n = unsafe { gzread(self.0, buf.as_mut_ptr().cast(), len) };
+        if n > 0 || len == 0 {
+            return Ok(n as usize);
+        }
+        let mut code: c_int = Z_OK;
+        // SAFETY: `gzerror` returns a NUL-terminated message owned by the handle.
+        let message = unsafe { CStr::from_ptr(gzerror(self.0, &mut code)) };
+        match (n, code) {
+            (0, Z_BUF_ERROR) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "gzip data ends before the end of its stream",
+            )),
+            (0, _) => Ok(0),
+            _ => Err(io::Error::other(message.to_string_lossy().into_owned())),
+        }
+    }
+}
+
+impl Drop for GzReader {
+    fn drop(&mut self) {
+        // SAFETY: the handle is open and not used again.
+        unsafe { gzclose(self.0) };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    /// At the start of a field.
+    Start,
+    Unquoted,
+    Quoted,
+    /// Just after a quote inside a quoted field: either the closing quote or the
+    /// first half of `""`.
+    QuoteInQuoted,
+}
+
+/// Splits a file into records.
+struct Records<R> {
+    input: R,
+    path: String,
+    delimiter: u8,
+    line: Vec<u8>,
+    /// Physical lines read so far.
+    line_no: usize,
+    /// Content of the current record's fields, back to back.
+    text: Vec<u8>,
+    /// End of each field in `text`.
+    ends: Vec<usize>,
+}
+
+impl<R: BufRead> Records<R> {
+    fn error(&self, line: usize, message: impl std::fmt::Display) -> PyErr {
+        SerializationError::new_err(format!("{}:{}: {}", self.path, line, message))
+    }
+
+    fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    fn field(&self, i: usize) -> &[u8] {
+        let start = if i == 0 { 0 } else { self.ends[i - 1] };
+        &self.text[start..self.ends[i]]
+    }
+
+    /// Reads the next record, returning the line it starts on, or None at the end.
+    fn next(&mut self) -> PyResult<Option<usize>> {
+        self.text.clear();
+        self.ends.clear();
+        let mut state = State::Start;
+        let mut start_line = None;
+        loop {
+            self.line.clear();
+            let read = self
+                .input
+                .read_until(b'\n', &mut self.line)
+                .map_err(|e| PyIOError::new_err(format!("{}: {}", self.path, e)))?;
+            if read == 0 {
+                return match start_line {
+                    Some(line) => Err(self.error(line, "unterminated quoted field")),
+                    None => Ok(None),
+                };
+            }
+            self.line_no += 1;
+            let mut content = &self.line[..];
+            if self.line_no == 1 {
+                content = content.strip_prefix(b"\xef\xbb\xbf").unwrap_or(content);
+            }
+            let full = content;
+            content = content.strip_suffix(b"\n").unwrap_or(content);
+            content = content.strip_suffix(b"\r").unwrap_or(content);
+            let line_break = &full[content.len()..];
+            if start_line.is_none() && content.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let start = *start_line.get_or_insert(self.line_no);
+            for &b in content {
+                state = match (state, b) {
+                    (State::Start, b'"') => State::Quoted,
+                    (State::Quoted, b'"') => State::QuoteInQuoted,
+                    (State::Quoted, b) => {
+                        self.text.push(b);
+                        State::Quoted
+                    }
+                    (State::QuoteInQuoted, b'"') => {
+                        self.text.push(b'"');
+                        State::Quoted
+                    }
+                    (_, b) if b == self.delimiter => {
+                        self.ends.push(self.text.len());
+                        State::Start
+                    }
+                    (State::QuoteInQuoted, _) => {
+                        return Err(
+                            self.error(self.line_no, "unexpected text after a closing quote")
+                        );
+                    }
+                    (State::Start | State::Unquoted, b) => {
+                        self.text.push(b);
+                        State::Unquoted
+                    }
+                };
+            }
+            if state == State::Quoted {
+                // A line break inside quotes belongs to the field.
+                self.text.extend_from_slice(line_break);
+                continue;
+            }
+            self.ends.push(self.text.len());
+            return Ok(Some(start));
+        }
+    }
+}
+
+/// Reads the edges of the file at `path`, calling `edge(source, target, weight)`
+/// for each row in order, and returns the number of rows. A missing or empty
+/// source or target, an unparsable weight or malformed quoting raises
+/// SerializationError naming the line; an empty weight field leaves it None.
+pub fn read(
+    path: &str,
+    options: &Options,
+    mut edge: impl FnMut(&str, &str, Option<f64>) -> PyResult<()>,
+) -> PyResult<usize> {
+    let input = GzReader::open(path).map_err(|e| PyIOError::new_err(format!("{}: {}", path, e)))?;
+    let mut records = Records {
+        input: BufReader::with_capacity(BUFFER, input),
+        path: path.to_string(),
+        delimiter: options.delimiter,
+        line: Vec::new(),
+        line_no: 0,
+        text: Vec::new(),
+        ends: Vec::new(),
+    };
+    let header: Option<Vec<String>> = match options.has_header {
+        true => match records.next()? {
+            Some(_) => Some(
+                (0..records.len())
+                    .map(|i| String::from_utf8_lossy(records.field(i)).into_owned())
+                    .collect(),
+            ),
+            None => return Ok(0),
+        },
+        false => None,
+    };
+    let source = options.source.resolve(header.as_deref())?;
+    let target = options.target.resolve(header.as_deref())?;
+    let weight = options
+        .weight
+        .as_ref()
+        .map(|c| c.resolve(header.as_deref()))
+        .transpose()?;
+    let mut rows = 0;
+    while let Some(line) = records.next()? {
+        let text = |i: usize, what: &str| -> PyResult<&str> {
+            if i >= records.len() {
+                return Err(records.error(
+                    line,
+                    format!("missing {} (column {} of {})", what, i, records.len()),
+                ));
+            }
+            let field = std::str::from_utf8(records.field(i))
+                .map_err(|_| records.error(line, format!("{} is not valid UTF-8", what)))?;
+            Ok(field)
+        };
+        let from = text(source, "source")?;
+        let to = text(target, "target")?;
+        for (id, what) in [(from, "source"), (to, "target")] {
+            if id.is_empty() {
+                return Err(records.error(line, format!("empty {}", what)));
+            }
+        }
+        let w = match weight {
+            None => None,
+            Some(i) => match text(i, "weight")?.trim() {
+                "" => None,
+                w => match w.parse::<f64>() {
+                    Ok(w) if w.is_finite() => Some(w),
+                    _ => {
+                        return Err(
+                            records.error(line, format!("weight '{}' is not a finite number", w))
+                        )
+                    }
+                },
+            },
+        };
+        edge(from, to, w)?;
+        rows += 1;
+    }
+    Ok(rows)
+}
@@ -0,0 +1,34 @@
+use pyo3::create_exception;
+use pyo3::exceptions::{PyKeyError, PyRuntimeError, PyValueError};
+
+// Raised when a mutation would introduce a cycle into a graph created with `enforce_dag=True`.
+create_exception!(rust_core, CycleError, PyValueError);
+
+// Raised when a snapshot, export or edge file cannot be decoded, or a snapshot fails
+// its integrity check.
+create_exception!(rust_core, SerializationError, PyValueError);
+
+// Raised when a query exceeds its max_results / max_visited limit.
+// Args are (message, visited, results).
+create_exception!(rust_core, ResourceLimitError, PyRuntimeError);
+
+// Raised when an operation names a node that does not exist. A KeyError subclass.
+create_exception!(rust_core, NodeNotFoundError, PyKeyError);
+
+// Raised when a mutation violates the graph schema of a walker created with
+// `enforce_schema=True`.
+create_exception!(rust_core, SchemaError, PyValueError);
+
+// Raised when a frozen graph is mutated.
+create_exception!(rust_core, FrozenGraphError, PyRuntimeError);
+
+// Raised when an operation does not apply to the walker's edge mode, e.g. a
+// directed-only algorithm on an `undirected_multi` graph.
+create_exception!(rust_core, ModeError, PyValueError);
+
+// Raised when a `ResultCursor` is read after the graph changed since its creation.
+create_exception!(rust_core, StaleCursorError, PyRuntimeError);
+
+// Raised when an iterative computation such as eigenvector centrality does not
+// converge within its iteration cap.
+create_exception!(rust_core, ConvergenceError, PyRuntimeError);
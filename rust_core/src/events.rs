@@ -0,0 +1,150 @@
+//! Mutation event log. When a walker is created with `record_events=True`, every
+//! successful mutation appends a `GraphEvent`; replaying the log in order onto an
+//! empty walker reproduces the graph.
+
+use crate::GraphWalker;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::iter::Peekable;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum GraphEvent {
+    AddNode {
+        id: String,
+        dependents: Vec<String>,
+    },
+    AddEdge {
+        from: String,
+        to: String,
+    },
+    TouchEdge {
+        from: String,
+        to: String,
+        ts: f64,
+    },
+    DecayWeights {
+        factor: f64,
+    },
+    PruneEdgesBelow {
+        threshold: f64,
+    },
+    SetWeights {
+        from: String,
+        weights: Vec<(String, f64)>,
+    },
+    SetLabel {
+        from: String,
+        to: String,
+        label: Option<String>,
+    },
+    SplitNode {
+        id: String,
+        parts: Vec<(String, Vec<String>, Vec<String>)>,
+        keep_original: bool,
+        strict: bool,
+        copy_attributes: bool,
+    },
+    RemoveNode {
+        id: String,
+        prune_edges: bool,
+    },
+    RemoveEdge {
+        from: String,
+        to: String,
+    },
+    Tombstone {
+        id: String,
+        ts: f64,
+    },
+    PurgeTombstones {
+        older_than: f64,
+    },
+    SetAttrs {
+        ids: Vec<String>,
+        key: String,
+        values: Vec<String>,
+    },
+    MergeEdge {
+        from: String,
+        to: String,
+        weight: f64,
+        ts: f64,
+        label: Option<String>,
+    },
+    Repair {
+        drop_dangling: bool,
+        dedupe_edges: bool,
+        drop_self_loops: bool,
+    },
+}
+
+impl GraphEvent {
+    /// Applies the event through the regular mutators (which record it again if the
+    /// target walker is recording).
+    pub fn apply(self, walker: &mut GraphWalker) -> PyResult<()> {
+        match self {
+            GraphEvent::AddNode { id, dependents } => walker.add_node(id, dependents, None, None),
+            GraphEvent::AddEdge { from, to } => walker.add_edge(from, to, None),
+            GraphEvent::TouchEdge { from, to, ts } => walker.touch_edge(from, to, ts),
+            GraphEvent::DecayWeights { factor } => walker.decay_weights(factor),
+            GraphEvent::PruneEdgesBelow { threshold } => {
+                walker.prune_edges_below(threshold).map(|_| ())
+            }
+            GraphEvent::SetWeights { from, weights } => walker.set_weights(from, weights),
+            GraphEvent::SetLabel { from, to, label } => walker.set_label(from, to, label),
+            GraphEvent::SplitNode {
+                id,
+                parts,
+                keep_original,
+                strict,
+                copy_attributes,
+            } => walker.split_node(id, parts, keep_original, strict, copy_attributes),
+            GraphEvent::RemoveNode { id, prune_edges } => {
+                walker.remove_node(id, prune_edges).map(|_| ())
+            }
+            GraphEvent::RemoveEdge { from, to } => walker.remove_edge(from, to).map(|_| ()),
+            GraphEvent::Tombstone { id, ts } => walker.tombstone_node(id, Some(ts)).map(|_| ()),
+            GraphEvent::PurgeTombstones { older_than } => {
+                walker.purge_tombstones(older_than).map(|_| ())
+            }
+            GraphEvent::SetAttrs { ids, key, values } => {
+                walker.set_attrs_bulk(ids, key, values, false).map(|_| ())
+            }
+            GraphEvent::MergeEdge {
+                from,
+                to,
+                weight,
+                ts,
+                label,
+            } => walker.merge_edge(&from, &to, weight, ts, label),
+            GraphEvent::Repair {
+                drop_dangling,
+                dedupe_edges,
+                drop_self_loops,
+            } => walker
+                .repair_edges(drop_dangling, dedupe_edges, drop_self_loops)
+                .map(|_| ()),
+        }
+    }
+}
+
+/// Applies the next event of `events`, or returns None once they run out. An
+/// `add_edge` directly followed by a `set_label` of the same edge is the pair
+/// `add_edge_labeled` records; it is applied as one call, so the schema checks the
+/// new edge with its label.
+pub fn apply_next(
+    events: &mut Peekable<impl Iterator<Item = GraphEvent>>,
+    walker: &mut GraphWalker,
+) -> Option<PyResult<()>> {
+    let event = events.next()?;
+    if let GraphEvent::AddEdge { from, to } = &event {
+        let label = events.next_if(
+            |next| matches!(next, GraphEvent::SetLabel { from: f, to: t, .. } if f == from && t == to),
+        );
+        if let Some(GraphEvent::SetLabel { from, to, label }) = label {
+            return Some(walker.add_edge_labeled(from, to, label));
+        }
+    }
+    Some(event.apply(walker))
+}
@@ -0,0 +1,273 @@
+//! DOT (Graphviz) and GraphML (Gephi, yEd) export, written straight to any
+//! `Write` so large graphs stream to a buffered file instead of one big String.
+//!
+//! Both formats list every id that appears, edge targets that are not nodes
+//! included, sorted, then the edges sorted by (source, target). In
+//! `undirected_multi` mode each pair is written once, as an undirected edge whose
+//! weight is the observation count. Highlighted ids get a distinct fill color.
+//!
+//! `csr` gives the same ids as the rows of a compressed sparse row matrix, for
+//! scipy and other array libraries.
+
+use crate::config;
+use crate::store::NodeStore;
+use rayon::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{self, Write};
+
+/// Fill color of highlighted nodes.
+const HIGHLIGHT_COLOR: &str = "#ff6b6b";
+
+struct Edge<'a> {
+    from: &'a str,
+    to: &'a str,
+    weight: f64,
+    label: Option<&'a str>,
+}
+
+/// Sorted ids (nodes and edge targets) and sorted edges; with `directed` false, the
+/// mirrored half of each pair is dropped.
+fn collect(nodes: &NodeStore, directed: bool) -> (Vec<&str>, Vec<Edge<'_>>) {
+    let mut ids: BTreeSet<&str> = BTreeSet::new();
+    let mut edges = Vec::new();
+    for (id, node) in nodes.iter() {
+        ids.insert(id);
+        for (i, to) in nodes.targets(node).enumerate() {
+            let to = to.as_str();
+            ids.insert(to);
+            if !directed && to < id.as_str() {
+                continue;
+            }
+            edges.push(Edge {
+                from: id,
+                to,
+                weight: node.weights[i],
+                label: node.labels[i].as_deref(),
+            });
+        }
+    }
+    edges.sort_unstable_by(|a, b| (a.from, a.to).cmp(&(b.from, b.to)));
+    (ids.into_iter().collect(), edges)
+}
+
+/// Writes `s` as the body of a DOT quoted string: backslashes and quotes are
+/// escaped, and line breaks become `\n`.
+fn dot_escape(out: &mut impl Write, s: &str) -> io::Result<()> {
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escaped = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            _ => continue,
+        };
+        out.write_all(&s.as_bytes()[start..i])?;
+        out.write_all(escaped.as_bytes())?;
+        start = i + c.len_utf8();
+    }
+    out.write_all(&s.as_bytes()[start..])
+}
+
+/// Writes the graph in DOT. Every id is quoted; relation labels become edge labels,
+/// and undirected pairs carry their observation count as `weight`.
+pub fn write_dot(
+    out: &mut impl Write,
+    nodes: &NodeStore,
+    directed: bool,
+    highlight: &HashSet<&str>,
+) -> io::Result<()> {
+    let (ids, edges) = collect(nodes, directed);
+    let (kind, arrow) = if directed {
+        ("digraph", "->")
+    } else {
+        ("graph", "--")
+    };
+    writeln!(out, "{} concord {{", kind)?;
+    for id in ids {
+        out.write_all(b"  \"")?;
+        dot_escape(out, id)?;
+        out.write_all(b"\"")?;
+        if highlight.contains(id) {
+            write!(out, " [style=filled, fillcolor=\"{}\"]", HIGHLIGHT_COLOR)?;
+        }
+        out.write_all(b";\n")?;
+    }
+    for edge in edges {
+        out.write_all(b"  \"")?;
+        dot_escape(out, edge.from)?;
+        write!(out, "\" {} \"", arrow)?;
+        dot_escape(out, edge.to)?;
+        out.write_all(b"\"")?;
+        if let Some(label) = edge.label {
+            out.write_all(b" [label=\"")?;
+            dot_escape(out, label)?;
+            out.write_all(b"\"]")?;
+        } else if !directed {
+            write!(out, " [weight={}]", edge.weight)?;
+        }
+        out.write_all(b";\n")?;
+    }
+    out.write_all(b"}\n")?;
+    out.flush()
+}
+
+/// Writes `s` as XML character data or attribute value. Characters XML 1.0 cannot
+/// represent (most control characters) become U+FFFD.
+fn xml_escape(out: &mut impl Write, s: &str) -> io::Result<()> {
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escaped = match c {
+            '&' => "&amp;",
+            '<' => "&lt;",
+            '>' => "&gt;",
+            '"' => "&quot;",
+            '\'' => "&apos;",
+            // Escaped so attribute-value normalization keeps them in ids.
+            '\t' => "&#9;",
+            '\n' => "&#10;",
+            '\r' => "&#13;",
+            c if (c as u32) < 0x20 || c == '\u{fffe}' || c == '\u{ffff}' => "\u{fffd}",
+            _ => continue,
+        };
+        out.write_all(&s.as_bytes()[start..i])?;
+        out.write_all(escaped.as_bytes())?;
+        start = i + c.len_utf8();
+    }
+    out.write_all(&s.as_bytes()[start..])
+}
+
+/// Writes the graph in GraphML, declaring one string key per node attribute name
+/// (`a0`, `a1`, ... in name order), `weight` (double) and `label` (string) on edges,
+/// and `color` on highlighted nodes.
+pub fn write_graphml(
+    out: &mut impl Write,
+    nodes: &NodeStore,
+    directed: bool,
+    highlight: &HashSet<&str>,
+) -> io::Result<()> {
+    let (ids, edges) = collect(nodes, directed);
+    let names: BTreeSet<&str> = nodes
+        .values()
+        .flat_map(|n| n.attributes.keys().map(String::as_str))
+        .collect();
+    let key_of: HashMap<&str, usize> = names.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+
+    out.write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n")?;
+    out.write_all(b"<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n")?;
+    for (i, name) in names.iter().enumerate() {
+        write!(out, "  <key id=\"a{}\" for=\"node\" attr.name=\"", i)?;
+        xml_escape(out, name)?;
+        out.write_all(b"\" attr.type=\"string\"/>\n")?;
+    }
+    if !highlight.is_empty() {
+        out.write_all(
+            b"  <key id=\"color\" for=\"node\" attr.name=\"color\" attr.type=\"string\"/>\n",
+        )?;
+    }
+    out.write_all(
+        b"  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>\n",
+    )?;
+    out.write_all(
+        b"  <key id=\"label\" for=\"edge\" attr.name=\"label\" attr.type=\"string\"/>\n",
+    )?;
+    writeln!(
+        out,
+        "  <graph id=\"concord\" edgedefault=\"{}\">",
+        if directed { "directed" } else { "undirected" }
+    )?;
+
+    for id in ids {
+        out.write_all(b"    <node id=\"")?;
+        xml_escape(out, id)?;
+        let mut attrs: Vec<(&String, &String)> = nodes
+            .get(id)
+            .map(|n| n.attributes.iter().collect())
+            .unwrap_or_default();
+        let lit = highlight.contains(id);
+        if attrs.is_empty() && !lit {
+            out.write_all(b"\"/>\n")?;
+            continue;
+        }
+        out.write_all(b"\">\n")?;
+        attrs.sort();
+        for (name, value) in attrs {
+            write!(out, "      <data key=\"a{}\">", key_of[name.as_str()])?;
+            xml_escape(out, value)?;
+            out.write_all(b"</data>\n")?;
+        }
+        if lit {
+            writeln!(out, "      <data key=\"color\">{}</data>", HIGHLIGHT_COLOR)?;
+        }
+        out.write_all(b"    </node>\n")?;
+    }
+    for edge in edges {
+        out.write_all(b"    <edge source=\"")?;
+        xml_escape(out, edge.from)?;
+        out.write_all(b"\" target=\"")?;
+        xml_escape(out, edge.to)?;
+        writeln!(
+            out,
+            "\">\n      <data key=\"weight\">{}</data>",
+            edge.weight
+        )?;
+        if let Some(label) = edge.label {
+            out.write_all(b"      <data key=\"label\">")?;
+            xml_escape(out, label)?;
+            out.write_all(b"</data>\n")?;
+        }
+        out.write_all(b"    </edge>\n")?;
+    }
+    out.write_all(b"  </graph>\n</graphml>\n")?;
+    out.flush()
+}
+
+/// The adjacency as `(indptr, indices, ids)` in CSR layout: row `i` is `ids[i]`,
+/// with ids sorted as in `collect`, and its targets are
+/// `indices[indptr[i]..indptr[i + 1]]`, sorted. Every stored edge is a nonzero, so
+/// a repeated edge appears twice and an undirected pair once in each row.
+pub fn csr(nodes: &NodeStore) -> (Vec<i64>, Vec<i64>, Vec<&String>) {
+    let mut used = vec![false; nodes.ids().len()];
+    for (h, node) in nodes.handles() {
+        used[h as usize] = true;
+        for &d in &node.dependents {
+            used[d as usize] = true;
+        }
+    }
+    let mut rows: Vec<u32> = (0..used.len() as u32)
+        .filter(|&h| used[h as usize])
+        .collect();
+    drop(used);
+    config::install(|| rows.par_sort_unstable_by_key(|&h| nodes.name(h)));
+    let mut index = vec![0i64; nodes.ids().len()];
+    for (i, &h) in rows.iter().enumerate() {
+        index[h as usize] = i as i64;
+    }
+    let degree = |h: u32| nodes.node(h).map_or(0, |n| n.dependents.len());
+    let mut indptr = Vec::with_capacity(rows.len() + 1);
+    indptr.push(0);
+    for &h in &rows {
+        indptr.push(indptr[indptr.len() - 1] + degree(h) as i64);
+    }
+    // One slice of `indices` per row, filled in parallel.
+    let mut indices = vec![0i64; indptr[rows.len()] as usize];
+    let mut slices = Vec::with_capacity(rows.len());
+    let mut rest = &mut indices[..];
+    for &h in &rows {
+        let (row, tail) = rest.split_at_mut(degree(h));
+        slices.push(row);
+        rest = tail;
+    }
+    config::install(|| {
+        rows.par_iter().zip(slices).for_each(|(&h, row)| {
+            if let Some(node) = nodes.node(h) {
+                for (slot, &d) in row.iter_mut().zip(&node.dependents) {
+                    *slot = index[d as usize];
+                }
+            }
+            row.sort_unstable();
+        })
+    });
+    let ids = rows.into_iter().map(|h| nodes.name(h)).collect();
+    (indptr, indices, ids)
+}
@@ -0,0 +1,72 @@
+//! Per-node counters of how often a node appears in propagation results.
+//!
+//! Counters are atomics indexed by an interned handle. Recording takes a read lock,
+//! so concurrent propagations only contend when a node is counted for the first time.
+
+use crate::interner::Interner;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+#[derive(Default)]
+struct Counters {
+    ids: Interner,
+    hits: Vec<AtomicU64>,
+}
+
+#[derive(Default)]
+pub struct Heat {
+    inner: RwLock<Counters>,
+}
+
+impl Heat {
+    /// Adds one hit for each id in `ids`.
+    pub fn record<'a>(&self, ids: impl IntoIterator<Item = &'a String>) {
+        let mut unseen = Vec::new();
+        {
+            let counters = self.inner.read().unwrap_or_else(|e| e.into_inner());
+            for id in ids {
+                match counters.ids.get(id) {
+                    Some(h) => {
+                        counters.hits[h as usize].fetch_add(1, Ordering::Relaxed);
+                    }
+                    None => unseen.push(id),
+                }
+            }
+        }
+        if unseen.is_empty() {
+            return;
+        }
+        let mut counters = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        for id in unseen {
+            let h = counters.ids.intern(id) as usize;
+            if h == counters.hits.len() {
+                counters.hits.push(AtomicU64::new(0));
+            }
+            counters.hits[h].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The `k` most frequently hit ids, highest first with ties broken by id.
+    pub fn top(&self, k: usize) -> Vec<(String, u64)> {
+        let counters = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        let mut out: Vec<(String, u64)> = counters
+            .ids
+            .ids()
+            .iter()
+            .zip(&counters.hits)
+            .map(|(id, hits)| (id.clone(), hits.load(Ordering::Relaxed)))
+            .filter(|(_, hits)| *hits > 0)
+            .collect();
+        out.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        out.truncate(k);
+        out
+    }
+
+    /// Zeroes every counter, keeping the handles.
+    pub fn reset(&self) {
+        let counters = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        for hits in &counters.hits {
+            hits.store(0, Ordering::Relaxed);
+        }
+    }
+}
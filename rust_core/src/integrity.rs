@@ -0,0 +1,231 @@
+//! Structural integrity checks for `GraphWalker::validate` and `GraphWalker::repair`:
+//! dangling edges (to ids that are neither nodes nor tombstoned), self-loops,
+//! repeated edges within one node's edge list and edges touching the empty id. Both
+//! run in one parallel pass over the edges.
+
+use crate::store::NodeStore;
+use crate::CausalNode;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+/// Offending edges by kind, as `(from, to)` handles.
+#[derive(Default)]
+struct Issues {
+    dangling: Vec<(u32, u32)>,
+    self_loops: Vec<(u32, u32)>,
+    /// Each repeated pair once.
+    duplicates: Vec<(u32, u32)>,
+    empty: Vec<(u32, u32)>,
+}
+
+impl Issues {
+    fn extend(mut self, other: Issues) -> Issues {
+        self.dangling.extend(other.dangling);
+        self.self_loops.extend(other.self_loops);
+        self.duplicates.extend(other.duplicates);
+        self.empty.extend(other.empty);
+        self
+    }
+}
+
+/// Per handle: true for ids that are edge targets only, not nodes or tombstones.
+pub fn phantoms(nodes: &NodeStore, tombstoned: impl Fn(u32) -> bool) -> Vec<bool> {
+    (0..nodes.ids().len() as u32)
+        .map(|h| !nodes.contains(h) && !tombstoned(h))
+        .collect()
+}
+
+fn scan(nodes: &NodeStore, phantom: &[bool]) -> Issues {
+    let empty = nodes.handle("");
+    let handles: Vec<(u32, &CausalNode)> = nodes.handles().collect();
+    handles
+        .par_iter()
+        .fold(Issues::default, |mut issues, &(h, node)| {
+            let mut seen = HashSet::new();
+            let mut repeated = HashSet::new();
+            for &t in &node.dependents {
+                if phantom[t as usize] {
+                    issues.dangling.push((h, t));
+                }
+                if t == h {
+                    issues.self_loops.push((h, t));
+                }
+                if !seen.insert(t) && repeated.insert(t) {
+                    issues.duplicates.push((h, t));
+                }
+                if empty.is_some_and(|e| e == h || e == t) {
+                    issues.empty.push((h, t));
+                }
+            }
+            issues
+        })
+        .reduce(Issues::default, Issues::extend)
+}
+
+#[pyclass(module = "rust_core")]
+pub struct ValidationReport {
+    /// Edges to ids that were never added as nodes (see `find_phantom_nodes`).
+    #[pyo3(get)]
+    dangling: Vec<(String, String)>,
+    #[pyo3(get)]
+    dangling_count: usize,
+    #[pyo3(get)]
+    self_loops: Vec<(String, String)>,
+    #[pyo3(get)]
+    self_loop_count: usize,
+    /// Pairs stored more than once in the source's edge list, each listed once.
+    #[pyo3(get)]
+    duplicate_edges: Vec<(String, String)>,
+    #[pyo3(get)]
+    duplicate_edge_count: usize,
+    /// Edges from or to the empty id.
+    #[pyo3(get)]
+    empty_ids: Vec<(String, String)>,
+    #[pyo3(get)]
+    empty_id_count: usize,
+    /// Whether the empty id is itself a node.
+    #[pyo3(get)]
+    empty_id_node: bool,
+    /// Most pairs listed per kind; the counts are always complete.
+    #[pyo3(get)]
+    limit: usize,
+}
+
+/// The first `limit` of `pairs` by id, with the total.
+fn named(
+    nodes: &NodeStore,
+    pairs: Vec<(u32, u32)>,
+    limit: usize,
+) -> (Vec<(String, String)>, usize) {
+    let total = pairs.len();
+    let mut named: Vec<(&String, &String)> = pairs
+        .into_iter()
+        .map(|(a, b)| (nodes.name(a), nodes.name(b)))
+        .collect();
+    named.sort_unstable();
+    named.truncate(limit);
+    let pairs = named
+        .into_iter()
+        .map(|(a, b)| (a.clone(), b.clone()))
+        .collect();
+    (pairs, total)
+}
+
+impl ValidationReport {
+    pub fn new(nodes: &NodeStore, phantom: &[bool], limit: usize) -> Self {
+        let issues = scan(nodes, phantom);
+        let (dangling, dangling_count) = named(nodes, issues.dangling, limit);
+        let (self_loops, self_loop_count) = named(nodes, issues.self_loops, limit);
+        let (duplicate_edges, duplicate_edge_count) = named(nodes, issues.duplicates, limit);
+        let (empty_ids, empty_id_count) = named(nodes, issues.empty, limit);
+        ValidationReport {
+            dangling,
+            dangling_count,
+            self_loops,
+            self_loop_count,
+            duplicate_edges,
+            duplicate_edge_count,
+            empty_ids,
+            empty_id_count,
+            empty_id_node: nodes.contains_key(""),
+            limit,
+        }
+    }
+}
+
+#[pymethods]
+impl ValidationReport {
+    /// True if no issue of any kind was found.
+    #[getter]
+    fn is_valid(&self) -> bool {
+        self.dangling_count == 0
+            && self.self_loop_count == 0
+            && self.duplicate_edge_count == 0
+            && self.empty_id_count == 0
+            && !self.empty_id_node
+    }
+
+    fn __repr__(&self) -> String {
+        let mut out = format!(
+            "ValidationReport ({})",
+            if self.is_valid() {
+                "valid"
+            } else {
+                "issues found"
+            }
+        );
+        for (name, count, pairs) in [
+            ("dangling references", self.dangling_count, &self.dangling),
+            ("self-loops", self.self_loop_count, &self.self_loops),
+            (
+                "duplicate edges",
+                self.duplicate_edge_count,
+                &self.duplicate_edges,
+            ),
+            (
+                "edges touching the empty id",
+                self.empty_id_count,
+                &self.empty_ids,
+            ),
+        ] {
+            out.push_str(&format!("\n  {}: {}", name, count));
+            for (from, to) in pairs {
+                out.push_str(&format!("\n    {:?} -> {:?}", from, to));
+            }
+            if count > pairs.len() {
+                out.push_str(&format!("\n    ... and {} more", count - pairs.len()));
+            }
+        }
+        if self.empty_id_node {
+            out.push_str("\n  the empty id is a node");
+        }
+        out
+    }
+}
+
+/// Edges removed by `repair`, by kind.
+#[derive(Default)]
+pub struct Repaired {
+    pub dangling: usize,
+    pub self_loops: usize,
+    pub duplicates: usize,
+}
+
+/// Removes the selected kinds of edges from every node, in parallel. An edge is
+/// counted under the first matching kind in the order dangling, self-loop, duplicate;
+/// for repeated pairs the first copy is kept.
+pub fn repair(
+    nodes: &mut NodeStore,
+    phantom: &[bool],
+    drop_dangling: bool,
+    dedupe_edges: bool,
+    drop_self_loops: bool,
+) -> Repaired {
+    nodes
+        .par_handles_mut()
+        .map(|(h, node)| {
+            let mut repaired = Repaired::default();
+            let mut seen = HashSet::new();
+            node.retain_edges(|t, _, _| {
+                if drop_dangling && phantom[t as usize] {
+                    repaired.dangling += 1;
+                    false
+                } else if drop_self_loops && t == h {
+                    repaired.self_loops += 1;
+                    false
+                } else if dedupe_edges && !seen.insert(t) {
+                    repaired.duplicates += 1;
+                    false
+                } else {
+                    true
+                }
+            });
+            repaired
+        })
+        .reduce(Repaired::default, |a, b| Repaired {
+            dangling: a.dangling + b.dangling,
+            self_loops: a.self_loops + b.self_loops,
+            duplicates: a.duplicates + b.duplicates,
+        })
+}
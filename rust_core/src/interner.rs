@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+/// Append-only mapping between node id strings and dense integer handles.
+/// Handles are never reused or reassigned, so a table fetched earlier stays a
+/// valid prefix of any later one.
+#[derive(Default)]
+pub struct Interner {
+    ids: Vec<String>,
+    index: HashMap<String, u32>,
+}
+
+impl Interner {
+    pub fn intern(&mut self, id: &str) -> u32 {
+        if let Some(&h) = self.index.get(id) {
+            return h;
+        }
+        let h = self.ids.len() as u32;
+        self.ids.push(id.to_string());
+        self.index.insert(id.to_string(), h);
+        h
+    }
+
+    /// Reserves room for `additional` more ids.
+    pub fn reserve(&mut self, additional: usize) {
+        self.ids.reserve(additional);
+        self.index.reserve(additional);
+    }
+
+    pub fn get(&self, id: &str) -> Option<u32> {
+        self.index.get(id).copied()
+    }
+
+    pub fn resolve(&self, handle: u32) -> Option<&str> {
+        self.ids.get(handle as usize).map(String::as_str)
+    }
+
+    /// Number of ids interned so far.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    /// Approximate heap footprint in bytes.
+    pub fn heap_bytes(&self) -> usize {
+        self.ids.iter().map(|s| 2 * s.capacity()).sum::<usize>()
+            + self.ids.capacity() * std::mem::size_of::<String>()
+            + self.index.capacity() * (std::mem::size_of::<String>() + 4)
+    }
+}
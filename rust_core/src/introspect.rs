@@ -0,0 +1,435 @@
+//! Read-only HTTP introspection server for ops.
+//!
+//! The server thread never takes the GIL: it answers from a view of the graph that
+//! the Python side publishes with `IntrospectionServer.publish(walker)`, plus the
+//! process-wide consumer registry. Only `GET` (and `HEAD`) is accepted, and the
+//! listener binds to 127.0.0.1. Endpoints:
+//!
+//! - `/stats`: summary of the published graph
+//! - `/metrics`: Prometheus text format
+//! - `/consumers`: states of the consumers started in this process
+//! - `/node/{id}`: one node or tombstoned id of the published graph (id
+//!   percent-encoded)
+
+use crate::consumer;
+use crate::GraphWalker;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How often the accept loop checks for shutdown.
+const ACCEPT_POLL: Duration = Duration::from_millis(20);
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+/// Longest request head accepted.
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// Port of the running server; at most one per process.
+static RUNNING: Mutex<Option<u16>> = Mutex::new(None);
+
+#[derive(Serialize)]
+struct NodeView {
+    id: String,
+    dependents: Vec<String>,
+    weights: Vec<f64>,
+    /// Relation label per edge, null where unlabeled.
+    labels: Vec<Option<String>>,
+    /// Number of nodes with an edge to this one.
+    in_degree: usize,
+    attributes: BTreeMap<String, String>,
+    tombstoned: bool,
+}
+
+#[derive(Serialize)]
+struct Stats {
+    nodes: usize,
+    edges: usize,
+    tombstones: usize,
+    version: u64,
+    frozen: bool,
+    published_at: f64,
+}
+
+/// Copy of a walker taken at publish time.
+struct View {
+    stats: Stats,
+    nodes: HashMap<String, NodeView>,
+}
+
+impl View {
+    fn capture(walker: &GraphWalker) -> Self {
+        let mut in_degree: HashMap<u32, usize> = HashMap::new();
+        for node in walker.nodes.values() {
+            for &dep in &node.dependents {
+                *in_degree.entry(dep).or_default() += 1;
+            }
+        }
+        let mut nodes: HashMap<String, NodeView> = walker
+            .nodes
+            .handles()
+            .map(|(h, n)| {
+                let id = walker.nodes.name(h);
+                let view = NodeView {
+                    id: id.clone(),
+                    dependents: walker.nodes.names(n.dependents.iter().copied()),
+                    weights: n.weights.clone(),
+                    labels: n.labels.clone(),
+                    in_degree: in_degree.get(&h).copied().unwrap_or(0),
+                    attributes: n.attributes.clone().into_iter().collect(),
+                    tombstoned: walker.tombstones.contains_key(id),
+                };
+                (id.clone(), view)
+            })
+            .collect();
+        // A tombstoned id is no longer a node, but its in-edges are kept.
+        for id in walker.tombstones.keys() {
+            let view = NodeView {
+                id: id.clone(),
+                dependents: Vec::new(),
+                weights: Vec::new(),
+                labels: Vec::new(),
+                in_degree: in_degree
+                    .get(&walker.nodes.lookup(id))
+                    .copied()
+                    .unwrap_or(0),
+                attributes: BTreeMap::new(),
+                tombstoned: true,
+            };
+            nodes.insert(id.clone(), view);
+        }
+        View {
+            stats: Stats {
+                nodes: walker.nodes.len(),
+                edges: walker.nodes.values().map(|n| n.dependents.len()).sum(),
+                tombstones: walker.tombstones.len(),
+                version: walker.version,
+                frozen: walker.frozen.is_some(),
+                published_at: crate::now_secs(),
+            },
+            nodes,
+        }
+    }
+}
+
+type SharedView = Arc<RwLock<Option<View>>>;
+
+/// Handle to the running introspection server.
+#[pyclass]
+pub struct IntrospectionServer {
+    #[pyo3(get)]
+    port: u16,
+    view: SharedView,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[pymethods]
+impl IntrospectionServer {
+    /// Replaces the served graph view with the current state of `walker`.
+    fn publish(&self, walker: &GraphWalker) {
+        let view = View::capture(walker);
+        *self.view.write().unwrap_or_else(|e| e.into_inner()) = Some(view);
+    }
+
+    fn is_running(&self) -> bool {
+        self.thread.is_some()
+    }
+
+    /// Stops the server and waits for it to exit. Calling it again is a no-op.
+    fn shutdown(&mut self, py: Python) {
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+        self.stop.store(true, Ordering::Relaxed);
+        py.allow_threads(|| {
+            let _ = thread.join();
+        });
+        *RUNNING.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// Dropping the handle stops the server too, without waiting for it.
+impl Drop for IntrospectionServer {
+    fn drop(&mut self) {
+        if self.thread.take().is_some() {
+            self.stop.store(true, Ordering::Relaxed);
+            *RUNNING.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        }
+    }
+}
+
+/// Starts the introspection server on 127.0.0.1:`port` (0 picks a free port; read
+/// it back from `.port`), optionally publishing `walker` right away. Raises
+/// RuntimeError if a server is already running in this process.
+#[pyfunction]
+#[pyo3(signature = (port, walker = None))]
+pub fn start_introspection_server(
+    port: u16,
+    walker: Option<&GraphWalker>,
+) -> PyResult<IntrospectionServer> {
+    let mut running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(existing) = *running {
+        return Err(PyRuntimeError::new_err(format!(
+            "introspection server already running on port {}",
+            existing
+        )));
+    }
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|e| PyRuntimeError::new_err(format!("cannot bind port {}: {}", port, e)))?;
+    let port = listener.local_addr()?.port();
+    listener.set_nonblocking(true)?;
+
+    let view: SharedView = Arc::new(RwLock::new(walker.map(View::capture)));
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let (view, stop) = (view.clone(), stop.clone());
+        std::thread::spawn(move || serve(listener, &view, &stop))
+    };
+    *running = Some(port);
+    Ok(IntrospectionServer {
+        port,
+        view,
+        stop,
+        thread: Some(thread),
+    })
+}
+
+fn serve(listener: TcpListener, view: &SharedView, stop: &AtomicBool) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                // Requests are tiny and answered from memory, so one at a time is fine.
+                let _ = handle(stream, view);
+            }
+            // WouldBlock when idle; other accept errors are transient as well.
+            Err(_) => std::thread::sleep(ACCEPT_POLL),
+        }
+    }
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, body: serde_json::Value) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            body: body.to_string(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Response::json(status, json!({ "error": message }))
+    }
+}
+
+fn handle(mut stream: TcpStream, view: &SharedView) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+        if head.len() > MAX_REQUEST_BYTES {
+            return write_response(
+                &mut stream,
+                &Response::error(431, "request too large"),
+                true,
+            );
+        }
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut parts = head.lines().next().unwrap_or_default().split_whitespace();
+    let (method, target) = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or("/"),
+    );
+    let include_body = method != "HEAD";
+    let response = if method == "GET" || method == "HEAD" {
+        route(target.split('?').next().unwrap_or_default(), view)
+    } else {
+        Response::error(405, "read-only endpoint; only GET and HEAD are supported")
+    };
+    write_response(&mut stream, &response, include_body)
+}
+
+fn route(path: &str, view: &SharedView) -> Response {
+    let view = view.read().unwrap_or_else(|e| e.into_inner());
+    match path {
+        "/stats" => match view.as_ref() {
+            Some(v) => Response::json(200, json!(v.stats)),
+            None => Response::error(503, "no graph published yet"),
+        },
+        "/metrics" => Response {
+            status: 200,
+            content_type: "text/plain; version=0.0.4",
+            body: prometheus(view.as_ref()),
+        },
+        "/consumers" => Response::json(200, json!(consumer::states())),
+        _ => match path.strip_prefix("/node/") {
+            Some(raw) => {
+                let Some(id) = percent_decode(raw) else {
+                    return Response::error(400, "malformed node id");
+                };
+                match view.as_ref() {
+                    Some(v) => match v.nodes.get(&id) {
+                        Some(node) => Response::json(200, json!(node)),
+                        None => Response::error(404, &format!("node '{}' not found", id)),
+                    },
+                    None => Response::error(503, "no graph published yet"),
+                }
+            }
+            None => Response::error(404, "unknown endpoint"),
+        },
+    }
+}
+
+fn write_response(stream: &mut TcpStream, r: &Response, include_body: bool) -> std::io::Result<()> {
+    let reason = match r.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        431 => "Request Header Fields Too Large",
+        _ => "Service Unavailable",
+    };
+    let mut out = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        r.status,
+        reason,
+        r.content_type,
+        r.body.len()
+    );
+    if r.status == 405 {
+        out.push_str("Allow: GET, HEAD\r\n");
+    }
+    out.push_str("\r\n");
+    if include_body {
+        out.push_str(&r.body);
+    }
+    stream.write_all(out.as_bytes())?;
+    stream.flush()
+}
+
+fn prometheus(view: Option<&View>) -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, f64)>| {
+        out.push_str(&format!(
+            "# HELP {} {}\n# TYPE {} {}\n",
+            name, help, name, kind
+        ));
+        for (labels, value) in samples {
+            out.push_str(&format!("{}{} {}\n", name, labels, value));
+        }
+    };
+    if let Some(v) = view {
+        let s = &v.stats;
+        let gauge = |x: f64| vec![(String::new(), x)];
+        metric(
+            "concord_graph_nodes",
+            "gauge",
+            "Nodes in the published graph.",
+            gauge(s.nodes as f64),
+        );
+        metric(
+            "concord_graph_edges",
+            "gauge",
+            "Edges in the published graph.",
+            gauge(s.edges as f64),
+        );
+        metric(
+            "concord_graph_tombstones",
+            "gauge",
+            "Tombstoned nodes in the published graph.",
+            gauge(s.tombstones as f64),
+        );
+        metric(
+            "concord_graph_version",
+            "gauge",
+            "Mutation version of the published graph.",
+            gauge(s.version as f64),
+        );
+        metric(
+            "concord_graph_published_timestamp_seconds",
+            "gauge",
+            "When the graph view was published.",
+            gauge(s.published_at),
+        );
+    }
+    let states = consumer::states();
+    let per_topic = |value: &dyn Fn(&consumer::ConsumerState) -> f64| {
+        states
+            .iter()
+            .map(|c| {
+                (
+                    format!("{{topic=\"{}\"}}", escape_label(&c.topic)),
+                    value(c),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    metric(
+        "concord_consumer_up",
+        "gauge",
+        "1 if the consumer is running.",
+        per_topic(&|c| f64::from(u8::from(c.state == "running"))),
+    );
+    metric(
+        "concord_consumer_messages_total",
+        "counter",
+        "Messages received by the consumer.",
+        per_topic(&|c| c.messages as f64),
+    );
+    metric(
+        "concord_consumer_errors_total",
+        "counter",
+        "Errors seen by the consumer.",
+        per_topic(&|c| c.errors as f64),
+    );
+    metric(
+        "concord_consumer_malformed_total",
+        "counter",
+        "Messages the consumer skipped as malformed.",
+        per_topic(&|c| c.malformed as f64),
+    );
+    out
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn percent_decode(raw: &str) -> Option<String> {
+    let bytes = raw.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
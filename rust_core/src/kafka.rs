@@ -0,0 +1,501 @@
+//! Kafka client settings for the live consumer and the producer.
+//!
+//! `ConsumerConfig` holds the settings callers usually change as typed fields, plus
+//! free-form librdkafka properties passed through as given. The defaults are the
+//! settings the consumer has always used. Names and values are checked by
+//! librdkafka when the config is built, without connecting.
+//!
+//! `SecurityConfig` holds the TLS and SASL settings, for consumers and producers
+//! alike, and checks that they fit together. Passwords are masked wherever
+//! settings are shown: in `resolved()`, in reprs, and in librdkafka's errors.
+//!
+//! Consumers are created with a `StatsContext`, which keeps the partition offsets
+//! from librdkafka's periodic statistics report (`statistics.interval.ms`) for
+//! the handle's `metrics()`.
+
+use crate::config::log_at;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rdkafka::client::ClientContext;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::ConsumerContext;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+pub const DEFAULT_GROUP_ID: &str = "rust_causal_core";
+
+/// Shown in place of a password.
+const MASK: &str = "***";
+
+const PROTOCOLS: [&str; 4] = ["plaintext", "ssl", "sasl_plaintext", "sasl_ssl"];
+
+/// The SASL mechanisms librdkafka is built with here.
+const MECHANISMS: [&str; 3] = ["PLAIN", "SCRAM-SHA-256", "SCRAM-SHA-512"];
+
+/// Whether the value of property `key` is a secret.
+fn is_secret(key: &str) -> bool {
+    key.ends_with("password") || key.ends_with(".secret")
+}
+
+/// `properties` with secret values masked.
+pub fn masked(properties: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    properties
+        .iter()
+        .map(|(k, v)| {
+            let v = if is_secret(k) { MASK } else { v.as_str() };
+            (k.clone(), v.to_string())
+        })
+        .collect()
+}
+
+/// `message` with the secret values of `properties` masked, for errors that
+/// may quote them.
+pub fn redact(message: String, properties: &BTreeMap<String, String>) -> String {
+    properties
+        .iter()
+        .filter(|(k, v)| is_secret(k) && !v.is_empty())
+        .fold(message, |message, (_, v)| message.replace(v.as_str(), MASK))
+}
+
+/// TLS and SASL settings of a Kafka client, passed as `ConsumerConfig(security=...)`
+/// or `CausalProducer(..., security=...)`.
+#[pyclass(module = "rust_core")]
+#[derive(Clone)]
+pub struct SecurityConfig {
+    #[pyo3(get)]
+    security_protocol: String,
+    #[pyo3(get)]
+    sasl_mechanism: Option<String>,
+    #[pyo3(get)]
+    sasl_username: Option<String>,
+    /// Not readable from Python.
+    sasl_password: Option<String>,
+    #[pyo3(get)]
+    ssl_ca_location: Option<String>,
+    #[pyo3(get)]
+    ssl_certificate_location: Option<String>,
+    #[pyo3(get)]
+    ssl_key_location: Option<String>,
+}
+
+impl SecurityConfig {
+    /// The librdkafka properties of these settings.
+    pub fn properties(&self) -> BTreeMap<String, String> {
+        let mut properties = BTreeMap::new();
+        properties.insert(
+            "security.protocol".to_string(),
+            self.security_protocol.clone(),
+        );
+        let optional = [
+            ("sasl.mechanism", &self.sasl_mechanism),
+            ("sasl.username", &self.sasl_username),
+            ("sasl.password", &self.sasl_password),
+            ("ssl.ca.location", &self.ssl_ca_location),
+            ("ssl.certificate.location", &self.ssl_certificate_location),
+            ("ssl.key.location", &self.ssl_key_location),
+        ];
+        for (k, v) in optional {
+            if let Some(v) = v {
+                properties.insert(k.to_string(), v.clone());
+            }
+        }
+        properties
+    }
+
+    /// ValueError if `properties` sets one of the properties these settings own.
+    pub fn check_overlap(&self, properties: &HashMap<String, String>) -> PyResult<()> {
+        let owned = [
+            "security.protocol",
+            "sasl.mechanism",
+            "sasl.mechanisms",
+            "sasl.username",
+            "sasl.password",
+            "ssl.ca.location",
+            "ssl.certificate.location",
+            "ssl.key.location",
+        ];
+        match owned.iter().find(|k| properties.contains_key(**k)) {
+            Some(k) => Err(PyValueError::new_err(format!(
+                "'{}' is set by security; leave it out of the properties",
+                k
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let protocol = self.security_protocol.as_str();
+        if !PROTOCOLS.contains(&protocol) {
+            return Err(format!(
+                "security_protocol must be 'plaintext', 'ssl', 'sasl_plaintext' or \
+                 'sasl_ssl', got '{}'",
+                protocol
+            ));
+        }
+        let given = |v: &Option<String>| v.as_deref().is_some_and(|v| !v.is_empty());
+        if protocol.starts_with("sasl_") {
+            let Some(mechanism) = &self.sasl_mechanism else {
+                return Err(format!(
+                    "security_protocol '{}' needs a sasl_mechanism",
+                    protocol
+                ));
+            };
+            if !MECHANISMS.contains(&mechanism.as_str()) {
+                return Err(format!(
+                    "sasl_mechanism must be 'PLAIN', 'SCRAM-SHA-256' or 'SCRAM-SHA-512', \
+                     got '{}'",
+                    mechanism
+                ));
+            }
+            if !given(&self.sasl_username) || !given(&self.sasl_password) {
+                return Err(format!(
+                    "sasl_mechanism '{}' needs both sasl_username and sasl_password",
+                    mechanism
+                ));
+            }
+        } else if self.sasl_mechanism.is_some()
+            || self.sasl_username.is_some()
+            || self.sasl_password.is_some()
+        {
+            return Err(format!(
+                "SASL settings need security_protocol 'sasl_plaintext' or 'sasl_ssl', \
+                 got '{}'",
+                protocol
+            ));
+        }
+        let files = [
+            ("ssl_ca_location", &self.ssl_ca_location),
+            ("ssl_certificate_location", &self.ssl_certificate_location),
+            ("ssl_key_location", &self.ssl_key_location),
+        ];
+        if !matches!(protocol, "ssl" | "sasl_ssl") && files.iter().any(|(_, v)| v.is_some()) {
+            return Err(format!(
+                "TLS settings need security_protocol 'ssl' or 'sasl_ssl', got '{}'",
+                protocol
+            ));
+        }
+        if self.ssl_certificate_location.is_some() != self.ssl_key_location.is_some() {
+            return Err("ssl_certificate_location and ssl_key_location go together".to_string());
+        }
+        for (name, path) in files {
+            if let Some(path) = path {
+                if !std::path::Path::new(path).is_file() {
+                    return Err(format!("{} '{}' is not a file", name, path));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl SecurityConfig {
+    /// `security_protocol` is "plaintext", "ssl", "sasl_plaintext" or "sasl_ssl"
+    /// (in any case). The SASL settings go with a "sasl_" protocol, which needs
+    /// all three; the mechanism is "PLAIN", "SCRAM-SHA-256" or "SCRAM-SHA-512".
+    /// The file locations go with "ssl" or "sasl_ssl", and must exist; a client
+    /// certificate needs its key. Anything else raises ValueError.
+    #[new]
+    #[pyo3(signature = (
+        security_protocol = "plaintext".to_string(),
+        sasl_mechanism = None,
+        sasl_username = None,
+        sasl_password = None,
+        ssl_ca_location = None,
+        ssl_certificate_location = None,
+        ssl_key_location = None
+    ))]
+    fn new(
+        security_protocol: String,
+        sasl_mechanism: Option<String>,
+        sasl_username: Option<String>,
+        sasl_password: Option<String>,
+        ssl_ca_location: Option<String>,
+        ssl_certificate_location: Option<String>,
+        ssl_key_location: Option<String>,
+    ) -> PyResult<Self> {
+        let config = SecurityConfig {
+            security_protocol: security_protocol.to_lowercase(),
+            sasl_mechanism: sasl_mechanism.map(|m| m.to_uppercase()),
+            sasl_username,
+            sasl_password,
+            ssl_ca_location,
+            ssl_certificate_location,
+            ssl_key_location,
+        };
+        config.validate().map_err(PyValueError::new_err)?;
+        Ok(config)
+    }
+
+    /// The librdkafka properties of these settings, the password masked.
+    fn resolved(&self) -> BTreeMap<String, String> {
+        masked(&self.properties())
+    }
+
+    fn __repr__(&self) -> String {
+        let mut parts = vec![format!("security_protocol={:?}", self.security_protocol)];
+        if let Some(mechanism) = &self.sasl_mechanism {
+            parts.push(format!("sasl_mechanism={:?}", mechanism));
+        }
+        if let Some(username) = &self.sasl_username {
+            parts.push(format!("sasl_username={:?}", username));
+        }
+        if self.sasl_password.is_some() {
+            parts.push(format!("sasl_password={:?}", MASK));
+        }
+        format!("SecurityConfig({})", parts.join(", "))
+    }
+}
+
+/// Settings of the consumer started by `start_kafka_consumer`.
+#[pyclass(module = "rust_core")]
+#[derive(Clone)]
+pub struct ConsumerConfig {
+    #[pyo3(get)]
+    group_id: String,
+    /// Where a group with no committed offset starts: `earliest` or `latest`.
+    #[pyo3(get)]
+    auto_offset_reset: String,
+    #[pyo3(get)]
+    enable_auto_commit: bool,
+    #[pyo3(get)]
+    session_timeout_ms: u32,
+    /// None leaves librdkafka's default (five minutes).
+    #[pyo3(get)]
+    max_poll_interval_ms: Option<u32>,
+    /// Extra librdkafka properties, applied last so they override the fields.
+    #[pyo3(get)]
+    properties: HashMap<String, String>,
+    /// With auto-commit off, commit each message's offset once it is processed.
+    #[pyo3(get)]
+    pub auto_commit_after_callback: bool,
+    /// How often librdkafka reports the offsets behind `metrics()`; 0 turns the
+    /// reports off.
+    #[pyo3(get)]
+    statistics_interval_ms: u32,
+    /// TLS and SASL settings; None connects in plain text.
+    #[pyo3(get)]
+    security: Option<SecurityConfig>,
+}
+
+impl Default for ConsumerConfig {
+    fn default() -> Self {
+        ConsumerConfig {
+            group_id: DEFAULT_GROUP_ID.to_string(),
+            auto_offset_reset: "latest".to_string(),
+            enable_auto_commit: true,
+            session_timeout_ms: 6000,
+            max_poll_interval_ms: None,
+            properties: HashMap::new(),
+            auto_commit_after_callback: false,
+            statistics_interval_ms: 5000,
+            security: None,
+        }
+    }
+}
+
+impl ConsumerConfig {
+    pub fn group_id(&self) -> &str {
+        &self.group_id
+    }
+
+    /// Every property the consumer is created with, bootstrap servers aside.
+    pub fn resolve(&self) -> BTreeMap<String, String> {
+        let mut resolved = BTreeMap::new();
+        let mut set = |k: &str, v: String| resolved.insert(k.to_string(), v);
+        set("group.id", self.group_id.clone());
+        set("auto.offset.reset", self.auto_offset_reset.clone());
+        set("enable.auto.commit", self.enable_auto_commit.to_string());
+        set("session.timeout.ms", self.session_timeout_ms.to_string());
+        if let Some(ms) = self.max_poll_interval_ms {
+            set("max.poll.interval.ms", ms.to_string());
+        }
+        set("enable.partition.eof", "false".to_string());
+        if self.statistics_interval_ms > 0 {
+            set(
+                "statistics.interval.ms",
+                self.statistics_interval_ms.to_string(),
+            );
+        }
+        if let Some(security) = &self.security {
+            resolved.extend(security.properties());
+        }
+        for (k, v) in &self.properties {
+            resolved.insert(k.clone(), v.clone());
+        }
+        resolved
+    }
+
+    /// The client config for `brokers`, or ValueError naming the property
+    /// librdkafka rejects.
+    pub fn client_config(&self, brokers: &str) -> PyResult<ClientConfig> {
+        let properties = self.resolve();
+        let mut client = ClientConfig::new();
+        client.set("bootstrap.servers", brokers);
+        for (k, v) in &properties {
+            client.set(k, v);
+        }
+        client.create_native_config().map_err(|e| {
+            let message = format!("invalid consumer setting: {}", e);
+            PyValueError::new_err(redact(message, &properties))
+        })?;
+        Ok(client)
+    }
+}
+
+#[pymethods]
+impl ConsumerConfig {
+    /// Raises ValueError for an `auto_offset_reset` other than "earliest" or
+    /// "latest", for `auto_commit_after_callback` without `enable_auto_commit=False`,
+    /// for a property librdkafka does not accept, or for one `security` sets.
+    #[new]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        group_id = DEFAULT_GROUP_ID.to_string(),
+        auto_offset_reset = "latest".to_string(),
+        enable_auto_commit = true,
+        session_timeout_ms = 6000,
+        max_poll_interval_ms = None,
+        properties = None,
+        auto_commit_after_callback = false,
+        statistics_interval_ms = 5000,
+        security = None
+    ))]
+    fn new(
+        group_id: String,
+        auto_offset_reset: String,
+        enable_auto_commit: bool,
+        session_timeout_ms: u32,
+        max_poll_interval_ms: Option<u32>,
+        properties: Option<HashMap<String, String>>,
+        auto_commit_after_callback: bool,
+        statistics_interval_ms: u32,
+        security: Option<SecurityConfig>,
+    ) -> PyResult<Self> {
+        if !matches!(auto_offset_reset.as_str(), "earliest" | "latest") {
+            return Err(PyValueError::new_err(format!(
+                "auto_offset_reset must be 'earliest' or 'latest', got '{}'",
+                auto_offset_reset
+            )));
+        }
+        if auto_commit_after_callback && enable_auto_commit {
+            return Err(PyValueError::new_err(
+                "auto_commit_after_callback needs enable_auto_commit=False",
+            ));
+        }
+        let properties = properties.unwrap_or_default();
+        if let Some(security) = &security {
+            security.check_overlap(&properties)?;
+        }
+        let config = ConsumerConfig {
+            group_id,
+            auto_offset_reset,
+            enable_auto_commit,
+            session_timeout_ms,
+            max_poll_interval_ms,
+            properties,
+            auto_commit_after_callback,
+            statistics_interval_ms,
+            security,
+        };
+        config.client_config("")?;
+        Ok(config)
+    }
+
+    /// The librdkafka properties the consumer is created with, bootstrap servers
+    /// aside, as a dict. Passwords are masked.
+    fn resolved(&self) -> BTreeMap<String, String> {
+        masked(&self.resolve())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ConsumerConfig(group_id={:?}, auto_offset_reset={:?}, enable_auto_commit={})",
+            self.group_id,
+            self.auto_offset_reset,
+            if self.enable_auto_commit {
+                "True"
+            } else {
+                "False"
+            }
+        )
+    }
+}
+
+/// Offsets of one partition, as of the last statistics report or commit seen.
+#[derive(Clone, Copy, Default)]
+pub struct PartitionLag {
+    pub committed: Option<i64>,
+    /// Offset the next message sent to the partition gets.
+    pub high_watermark: Option<i64>,
+    /// Messages between the two.
+    pub lag: Option<i64>,
+}
+
+pub type Lag = BTreeMap<(String, i32), PartitionLag>;
+
+/// The parts of a librdkafka statistics report `parse_stats` reads.
+#[derive(Deserialize)]
+struct Stats {
+    #[serde(default)]
+    topics: HashMap<String, TopicStats>,
+}
+
+#[derive(Deserialize)]
+struct TopicStats {
+    #[serde(default)]
+    partitions: HashMap<String, PartitionStats>,
+}
+
+#[derive(Deserialize)]
+struct PartitionStats {
+    partition: i32,
+    committed_offset: i64,
+    hi_offset: i64,
+    consumer_lag: i64,
+}
+
+/// The partition offsets in a librdkafka statistics report. librdkafka reports
+/// an unknown offset as a negative number, which becomes None, and lists an
+/// internal partition -1 per topic, which is left out.
+pub fn parse_stats(json: &[u8]) -> Result<Lag, String> {
+    let stats: Stats = serde_json::from_slice(json).map_err(|e| e.to_string())?;
+    let known = |offset: i64| (offset >= 0).then_some(offset);
+    let mut lag = Lag::new();
+    for (topic, t) in stats.topics {
+        for p in t.partitions.into_values().filter(|p| p.partition >= 0) {
+            let offsets = PartitionLag {
+                committed: known(p.committed_offset),
+                high_watermark: known(p.hi_offset),
+                lag: known(p.consumer_lag),
+            };
+            lag.insert((topic.clone(), p.partition), offsets);
+        }
+    }
+    Ok(lag)
+}
+
+/// Client context of the live consumer: keeps the offsets of the latest
+/// statistics report.
+#[derive(Default)]
+pub struct StatsContext {
+    lag: Mutex<Lag>,
+}
+
+impl StatsContext {
+    pub fn lag(&self) -> Lag {
+        self.lag.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl ClientContext for StatsContext {
+    fn stats_raw(&self, statistics: &[u8]) {
+        match parse_stats(statistics) {
+            Ok(lag) => *self.lag.lock().unwrap_or_else(|e| e.into_inner()) = lag,
+            Err(e) => log_at!(Warn, "Unreadable Kafka statistics: {}", e),
+        }
+    }
+}
+
+impl ConsumerContext for StatsContext {}
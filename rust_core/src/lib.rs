@@ -1,331 +1,6977 @@
 #![allow(non_local_definitions)] // Suppress pyo3 macro warning
+use numpy::{PyArray1, PyReadonlyArray1};
+use pyo3::exceptions::{PyIOError, PyKeyError, PyValueError};
+use pyo3::intern;
 use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList, PyTuple};
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 #[pyclass]
 #[derive(Clone, Debug)]
 #[allow(dead_code)]
-struct CausalNode {
-    id: String,
-    dependents: Vec<String>,
+pub struct CausalNode {
+    /// Handles (see `store`) of the edge targets, in insertion order.
+    dependents: Vec<u32>,
+    /// Per-edge weights, parallel to `dependents`.
+    weights: Vec<f64>,
+    /// Per-edge last-observation timestamps (seconds since the epoch), parallel to `dependents`.
+    observed_at: Vec<f64>,
+    /// Per-edge relation labels (e.g. "derives_from"), parallel to `dependents`;
+    /// None for unlabeled edges.
+    labels: Vec<Option<String>>,
+    /// Free-form metadata attached to the node.
+    attributes: HashMap<String, String>,
 }
 
-#[pyclass]
+impl CausalNode {
+    fn new() -> Self {
+        CausalNode {
+            dependents: Vec::new(),
+            weights: Vec::new(),
+            observed_at: Vec::new(),
+            labels: Vec::new(),
+            attributes: HashMap::new(),
+        }
+    }
+
+    fn with_dependents(dependents: Vec<u32>) -> Self {
+        let n = dependents.len();
+        let now = now_secs();
+        CausalNode {
+            dependents,
+            weights: vec![1.0; n],
+            observed_at: vec![now; n],
+            labels: vec![None; n],
+            attributes: HashMap::new(),
+        }
+    }
+
+    fn edge_index(&self, to: u32) -> Option<usize> {
+        self.dependents.iter().position(|&d| d == to)
+    }
+
+    fn push_edge(&mut self, to: u32, weight: f64, ts: f64) {
+        self.push_labeled_edge(to, weight, ts, None);
+    }
+
+    fn push_labeled_edge(&mut self, to: u32, weight: f64, ts: f64, label: Option<String>) {
+        self.dependents.push(to);
+        self.weights.push(weight);
+        self.observed_at.push(ts);
+        self.labels.push(label);
+    }
+
+    /// Merges repeated edges into the first copy, which takes the weight, timestamp
+    /// and label of the last.
+    fn collapse_repeated_edges(&mut self) {
+        let n = self.dependents.len();
+        let repeated = if n <= 16 {
+            (1..n).any(|i| self.dependents[..i].contains(&self.dependents[i]))
+        } else {
+            let mut seen = HashSet::with_capacity(n);
+            !self.dependents.iter().all(|&d| seen.insert(d))
+        };
+        if !repeated {
+            return;
+        }
+        let mut first: HashMap<u32, usize> = HashMap::with_capacity(n);
+        let mut keep = vec![true; n];
+        for (i, &d) in self.dependents.iter().enumerate() {
+            match first.entry(d) {
+                Entry::Vacant(e) => {
+                    e.insert(i);
+                }
+                Entry::Occupied(e) => {
+                    let j = *e.get();
+                    self.weights[j] = self.weights[i];
+                    self.observed_at[j] = self.observed_at[i];
+                    self.labels[j] = self.labels[i].take();
+                    keep[i] = false;
+                }
+            }
+        }
+        let mut next = keep.into_iter();
+        self.retain_edges(|_, _, _| next.next().unwrap_or(true));
+    }
+
+    /// Keeps only edges for which `keep(target, weight, observed_at)` is true, preserving
+    /// order; returns how many were removed.
+    fn retain_edges(&mut self, mut keep: impl FnMut(u32, f64, f64) -> bool) -> usize {
+        let before = self.dependents.len();
+        let mut write = 0;
+        for read in 0..before {
+            if keep(
+                self.dependents[read],
+                self.weights[read],
+                self.observed_at[read],
+            ) {
+                self.dependents.swap(write, read);
+                self.weights.swap(write, read);
+                self.observed_at.swap(write, read);
+                self.labels.swap(write, read);
+                write += 1;
+            }
+        }
+        self.dependents.truncate(write);
+        self.weights.truncate(write);
+        self.observed_at.truncate(write);
+        self.labels.truncate(write);
+        before - write
+    }
+}
+
+/// A node id with its attributes, as `to_networkx_edges` returns nodes.
+type AttributedNode = (String, HashMap<String, String>);
+
+/// Edges for a walker being built, added without the checks and bookkeeping of
+/// `add_edge`. They are buffered and grouped by source, so each edge list is
+/// allocated once.
+#[derive(Default)]
+struct EdgeBatch {
+    edges: Vec<(u32, u32, f64, f64, Option<String>)>,
+}
+
+impl EdgeBatch {
+    fn push(
+        &mut self,
+        nodes: &mut NodeStore,
+        from: &str,
+        to: &str,
+        weight: f64,
+        label: Option<String>,
+        ts: f64,
+    ) {
+        let (from, to) = (nodes.intern(from), nodes.intern(to));
+        self.edges.push((from, to, weight, ts, label));
+    }
+
+    /// Adds the edges in order. A repeated edge keeps the position of its first
+    /// copy and the weight, timestamp and label of its last.
+    fn finish(mut self, nodes: &mut NodeStore) {
+        config::install(|| self.edges.par_sort_by_key(|e| e.0));
+        for group in self.edges.chunk_by_mut(|a, b| a.0 == b.0) {
+            let node = nodes.get_or_insert_at(group[0].0);
+            node.dependents.reserve(group.len());
+            node.weights.reserve(group.len());
+            node.observed_at.reserve(group.len());
+            node.labels.reserve(group.len());
+            for (_, to, weight, ts, label) in group {
+                node.push_labeled_edge(*to, *weight, *ts, label.take());
+            }
+            node.collapse_repeated_edges();
+        }
+    }
+}
+
+/// `algorithms::topological_layers` over `adj`, indexing `ids`, as id layers;
+/// a cycle becomes CycleError.
+fn layered_ids(ids: &[&str], adj: &[Vec<usize>]) -> PyResult<Vec<Vec<String>>> {
+    match algorithms::topological_layers(adj) {
+        Ok(layers) => Ok(layers
+            .into_iter()
+            .map(|layer| layer.into_iter().map(|i| ids[i].to_string()).collect())
+            .collect()),
+        Err(cycle) => {
+            let mut path: Vec<&str> = cycle.iter().map(|&i| ids[i]).collect();
+            path.push(path[0]);
+            Err(CycleError::new_err(format!(
+                "graph has a cycle: {}",
+                path.join(" -> ")
+            )))
+        }
+    }
+}
+
+/// Sources of `h` in a `reverse_adjacency_cached` table; empty for unseen ids.
+fn sources_of(reverse: &[Vec<u32>], h: u32) -> &[u32] {
+    reverse.get(h as usize).map_or(&[], Vec::as_slice)
+}
+
+/// Checks random walk options, and picks a seed from the clock if none is given.
+fn walk_seed_for(walk_length: usize, p: f64, q: f64, seed: Option<u64>) -> PyResult<u64> {
+    if walk_length == 0 {
+        return Err(PyValueError::new_err("walk_length must be at least 1"));
+    }
+    for (name, value) in [("p", p), ("q", q)] {
+        if !(value.is_finite() && value > 0.0) {
+            return Err(PyValueError::new_err(format!(
+                "{} must be positive and finite, got {}",
+                name, value
+            )));
+        }
+    }
+    Ok(seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    }))
+}
+
+/// Creates (or truncates) `path` for buffered writing.
+fn create_buffered(path: &str) -> PyResult<std::io::BufWriter<std::fs::File>> {
+    std::fs::File::create(path)
+        .map(std::io::BufWriter::new)
+        .map_err(|e| PyIOError::new_err(e.to_string()))
+}
+
+/// The ImportError of `import numpy`, if it fails. The numpy crate panics when
+/// numpy is missing, so every array conversion checks this first.
+fn require_numpy(py: Python) -> PyResult<()> {
+    py.import("numpy").map(|_| ())
+}
+
+/// Integers from an int64 or int32 numpy array, read without converting each
+/// element to a Python int, or from any integer sequence.
+fn int_vec(values: &PyAny) -> PyResult<Vec<i64>> {
+    if values.is_instance_of::<PyList>() || values.is_instance_of::<PyTuple>() {
+        return values.extract();
+    }
+    // Without numpy, `values` cannot be an array.
+    if require_numpy(values.py()).is_err() {
+        return values.extract();
+    }
+    if let Ok(array) = values.downcast::<PyArray1<i64>>() {
+        return Ok(array.readonly().as_array().to_vec());
+    }
+    if let Ok(array) = values.downcast::<PyArray1<i32>>() {
+        return Ok(array
+            .readonly()
+            .as_array()
+            .iter()
+            .map(|&v| v as i64)
+            .collect());
+    }
+    values.extract()
+}
+
+/// The options of `from_edge_file` and `append_edge_file`; columns default to the
+/// first two.
+fn edge_file_options(
+    delimiter: &str,
+    has_header: bool,
+    source_col: Option<&PyAny>,
+    target_col: Option<&PyAny>,
+    weight_col: Option<&PyAny>,
+) -> PyResult<edgefile::Options> {
+    let column = |value: Option<&PyAny>, default| {
+        value.map_or(
+            Ok(edgefile::Column::Index(default)),
+            edgefile::Column::from_py,
+        )
+    };
+    Ok(edgefile::Options {
+        delimiter: edgefile::Options::check_delimiter(delimiter)?,
+        has_header,
+        source: column(source_col, 0)?,
+        target: column(target_col, 1)?,
+        weight: weight_col.map(edgefile::Column::from_py).transpose()?,
+    })
+}
+
+/// Edge weights must be finite; negative weights are stored, though some algorithms
+/// reject or clamp them.
+fn check_weight(weight: f64) -> PyResult<()> {
+    if !weight.is_finite() {
+        return Err(PyValueError::new_err(format!(
+            "edge weight must be finite, got {}",
+            weight
+        )));
+    }
+    Ok(())
+}
+
+/// The value `merge` keeps where two walkers disagree: `theirs`, or what
+/// `on_conflict(kind, key, ours, theirs)` returns.
+fn resolve<T>(
+    py: Python,
+    on_conflict: Option<&PyAny>,
+    kind: &str,
+    key: (&str, &str),
+    ours: T,
+    theirs: T,
+) -> PyResult<T>
+where
+    T: ToPyObject + for<'a> FromPyObject<'a>,
+{
+    match on_conflict {
+        Some(callback) => callback
+            .call1((kind, key, ours.to_object(py), theirs.to_object(py)))?
+            .extract(),
+        None => Ok(theirs),
+    }
+}
+
+/// Scores indexed like `graph`, keyed by id.
+fn by_id(graph: &algorithms::IndexedGraph, scores: Vec<f64>) -> HashMap<String, f64> {
+    graph
+        .ids
+        .iter()
+        .map(|id| (*id).clone())
+        .zip(scores)
+        .collect()
+}
+
+/// The scores of a power iteration, or the error for how it failed.
+fn power_scores(
+    what: &str,
+    result: &Result<HashMap<String, f64>, algorithms::PowerFailure>,
+) -> PyResult<HashMap<String, f64>> {
+    match result {
+        Ok(scores) => Ok(scores.clone()),
+        Err(algorithms::PowerFailure::NotConverged(iterations)) => Err(ConvergenceError::new_err(
+            format!("{} did not converge within {} iterations", what, iterations),
+        )),
+        Err(algorithms::PowerFailure::Diverged) => {
+            Err(ConvergenceError::new_err(format!("{} diverged", what)))
+        }
+    }
+}
+
+/// PageRank convergence tolerances must be non-negative numbers.
+fn check_tolerance(tolerance: Option<f64>) -> PyResult<()> {
+    if tolerance.is_some_and(|t| t.is_nan() || t < 0.0) {
+        return Err(PyValueError::new_err(
+            "tolerance must be a non-negative number",
+        ));
+    }
+    Ok(())
+}
+
+fn now_secs() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+#[pyclass(module = "rust_core")]
 struct GraphWalker {
-    nodes: HashMap<String, CausalNode>,
+    nodes: NodeStore,
+    /// Directed graph, or undirected pairs with observation counts (see `mode`).
+    mode: GraphMode,
+    /// Applied to every id entering through a mutator, if set.
+    normalizer: Option<Normalizer>,
+    /// First-seen original form of each normalized id.
+    original_ids: HashMap<String, String>,
+    /// When set, mutations that would introduce a cycle are rejected with CycleError.
+    enforce_dag: bool,
+    /// When set, edges violating `schema` are rejected with SchemaError.
+    enforce_schema: bool,
+    /// Declared node kinds and allowed edge types.
+    schema: Schema,
+    /// Step budget for the incremental cycle check run after each new edge; None
+    /// disables it.
+    cycle_check_steps: Option<usize>,
+    /// Cycles confirmed by the incremental check, oldest first.
+    new_cycles: Vec<Vec<String>>,
+    /// New edges whose incremental check ran out of budget.
+    pending_cycle_edges: Vec<(String, String)>,
+    /// Propagation hit counters, present only when constructed with `track_heat=True`.
+    heat: Option<Heat>,
+    /// Set while the graph is frozen (see `freeze`).
+    frozen: Option<Freeze>,
+    /// Removed node ids and the time (seconds since the epoch) they were tombstoned.
+    tombstones: HashMap<String, f64>,
+    /// Mutation log, present only when constructed with `record_events=True`.
+    events: Option<Vec<GraphEvent>>,
+    /// Bumped by every successful mutation.
+    version: u64,
+    /// Metrics computed for the current `version`.
+    metric_cache: MetricCache,
+    /// PageRank iterations actually run for `calculate_pagerank`, across versions.
+    pagerank_iterations: AtomicUsize,
+    /// Previous result per start node for `propagate_and_compare`.
+    comparisons: Mutex<ResultCache>,
+    /// Weakly connected components, maintained incrementally once queried.
+    components: Mutex<ComponentTracker>,
+    /// Per-edge origin, present only when constructed with `track_provenance=True`.
+    provenance: Option<ProvenanceStore>,
+    /// Facts marked by `mark_invalid` and not cleared since.
+    dirty: DirtySet,
+    /// Undo state of the open transaction (see `begin_transaction`).
+    transaction: Option<Transaction>,
+    /// Message a consumer is applying (see `pipeline`), recorded as the provenance of
+    /// the edges it creates in place of "api".
+    origin: Option<provenance::Origin>,
+}
+
+/// Walker state outside the node store saved by `begin_transaction`; the nodes and
+/// provenance records keep their own journals.
+struct Transaction {
+    tombstones: HashMap<String, f64>,
+    /// Ids given an `original_ids` entry since the transaction began.
+    original_ids: Vec<String>,
+    schema: Schema,
+    new_cycles: Vec<Vec<String>>,
+    pending_cycle_edges: Vec<(String, String)>,
+    /// Event log length, if recording.
+    logged: Option<usize>,
+    version: u64,
+}
+
+/// Graph state saved before an atomic batch (see `GraphWalker::checkpoint`).
+struct Checkpoint {
+    nodes: Slots,
+    tombstones: HashMap<String, f64>,
+    /// Event log length, if recording.
+    logged: Option<usize>,
 }
 
-#[pymethods]
-impl GraphWalker {
-    #[new]
-    fn new() -> Self {
-        GraphWalker {
-            nodes: HashMap::new(),
+#[pymethods]
+impl GraphWalker {
+    #[new]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        enforce_dag = false,
+        record_events = false,
+        track_provenance = false,
+        enforce_schema = false,
+        cycle_check_steps = None,
+        track_heat = false,
+        mode = "directed",
+        normalize = None
+    ))]
+    fn new(
+        enforce_dag: bool,
+        record_events: bool,
+        track_provenance: bool,
+        enforce_schema: bool,
+        cycle_check_steps: Option<usize>,
+        track_heat: bool,
+        mode: &str,
+        normalize: Option<&PyAny>,
+    ) -> PyResult<Self> {
+        let mode = GraphMode::parse(mode)?;
+        let normalizer = normalize.map(Normalizer::from_py).transpose()?;
+        if enforce_dag {
+            mode.require_directed("enforce_dag")?;
+        }
+        if cycle_check_steps.is_some() {
+            mode.require_directed("cycle_check_steps")?;
+        }
+        Ok(GraphWalker {
+            nodes: NodeStore::default(),
+            mode,
+            normalizer,
+            original_ids: HashMap::new(),
+            enforce_dag,
+            enforce_schema,
+            schema: Schema::default(),
+            cycle_check_steps,
+            new_cycles: Vec::new(),
+            pending_cycle_edges: Vec::new(),
+            heat: track_heat.then(Heat::default),
+            frozen: None,
+            tombstones: HashMap::new(),
+            events: record_events.then(Vec::new),
+            version: 0,
+            metric_cache: MetricCache::default(),
+            pagerank_iterations: AtomicUsize::new(0),
+            comparisons: Mutex::new(ResultCache::default()),
+            components: Mutex::default(),
+            provenance: track_provenance.then(ProvenanceStore::default),
+            dirty: DirtySet::default(),
+            transaction: None,
+            origin: None,
+        })
+    }
+
+    /// Replaces `id`'s dependents. `weights`, parallel to `dependents`, sets the new
+    /// edges' weights (default 1.0). In `undirected_multi` mode it instead records one
+    /// observation of each pair `(id, dep)`, keeping the node's other pairs; weights
+    /// are observation counts there and cannot be given. `attributes` are set on the
+    /// node as by `set_attribute`, keeping attributes it already had.
+    #[pyo3(signature = (id, dependents, weights = None, attributes = None))]
+    fn add_node(
+        &mut self,
+        id: String,
+        dependents: Vec<String>,
+        weights: Option<Vec<f64>>,
+        attributes: Option<HashMap<String, String>>,
+    ) -> PyResult<()> {
+        self.ensure_mutable()?;
+        let id = self.normalize_id(id)?;
+        let dependents = self.normalize_ids(dependents)?;
+        let weighted = match weights {
+            Some(weights) => {
+                self.mode.require_directed("add_node with weights")?;
+                if weights.len() != dependents.len() {
+                    return Err(PyValueError::new_err(format!(
+                        "got {} weights for {} dependents",
+                        weights.len(),
+                        dependents.len()
+                    )));
+                }
+                for &w in &weights {
+                    check_weight(w)?;
+                }
+                Some(dependents.iter().cloned().zip(weights).collect())
+            }
+            None => None,
+        };
+        if !self.mode.is_directed() {
+            for dep in &dependents {
+                self.check_schema(&id, dep, None)?;
+            }
+            self.record_mutation(|| GraphEvent::AddNode {
+                id: id.clone(),
+                dependents: dependents.clone(),
+            });
+            self.tombstones.remove(&id);
+            self.nodes.get_or_insert(&id);
+            let now = now_secs();
+            for dep in &dependents {
+                self.observe_pair(&id, dep, now);
+            }
+            return self.set_node_attributes(id, attributes);
+        }
+        if self.enforce_dag {
+            for dep in &dependents {
+                self.check_acyclic(&id, dep)?;
+            }
+        }
+        for dep in &dependents {
+            self.check_schema(&id, dep, None)?;
+        }
+        self.record_mutation(|| GraphEvent::AddNode {
+            id: id.clone(),
+            dependents: dependents.clone(),
+        });
+        let handles = dependents.iter().map(|d| self.nodes.intern(d)).collect();
+        let mut node = CausalNode::with_dependents(handles);
+        if let Some(old) = self.nodes.remove(&id) {
+            self.forget_edges(&id, &old, Some(&node));
+            node.attributes = old.attributes;
+        }
+        let now = now_secs();
+        for dep in &dependents {
+            self.record_provenance(&id, dep, API_SOURCE, None, None, now);
+        }
+        self.tombstones.remove(&id);
+        self.nodes.insert(&id, node);
+        for dep in &dependents {
+            self.check_new_edge_cycle(&id, dep);
+        }
+        if let Some(weighted) = weighted {
+            self.set_weights(id.clone(), weighted)?;
+        }
+        self.set_node_attributes(id, attributes)
+    }
+
+    /// Adds a single edge `from_id -> to_id`, creating the source node if needed.
+    /// Adding an edge that already exists is a no-op, except that a given `weight`
+    /// is applied to it; new edges default to 1.0. In `undirected_multi` mode it
+    /// records one more observation of the pair, creating both nodes if needed, and
+    /// `weight` cannot be given.
+    #[pyo3(signature = (from_id, to_id, weight = None))]
+    fn add_edge(&mut self, from_id: String, to_id: String, weight: Option<f64>) -> PyResult<()> {
+        self.ensure_mutable()?;
+        let from_id = self.normalize_id(from_id)?;
+        let to_id = self.normalize_id(to_id)?;
+        let label = self.edge_label(&from_id, &to_id).map(str::to_owned);
+        self.insert_edge(from_id, to_id, weight, label.as_deref())
+    }
+
+    /// Sets the weight of the existing edge `from_id -> to_id`; raises KeyError if
+    /// there is no such edge. Directed graphs only (in `undirected_multi` mode the
+    /// weight is an observation count).
+    fn set_edge_weight(&mut self, from_id: String, to_id: String, weight: f64) -> PyResult<()> {
+        self.ensure_mutable()?;
+        self.mode.require_directed("set_edge_weight")?;
+        check_weight(weight)?;
+        let from_id = self.normalize_id(from_id)?;
+        let to_id = self.normalize_id(to_id)?;
+        if !self.nodes.has_edge(&from_id, &to_id) {
+            return Err(PyKeyError::new_err(format!(
+                "no edge {} -> {}",
+                from_id, to_id
+            )));
+        }
+        self.set_weights(from_id, vec![(to_id, weight)])
+    }
+
+    /// Adds the edge `from_id -> to_id` as `add_edge` does and sets its relation
+    /// label, replacing any previous one; `label=None` makes it unlabeled again.
+    /// Directed graphs only.
+    fn add_edge_labeled(
+        &mut self,
+        from_id: String,
+        to_id: String,
+        label: Option<String>,
+    ) -> PyResult<()> {
+        self.ensure_mutable()?;
+        self.mode.require_directed("add_edge_labeled")?;
+        let from_id = self.normalize_id(from_id)?;
+        let to_id = self.normalize_id(to_id)?;
+        self.insert_edge(from_id.clone(), to_id.clone(), None, label.as_deref())?;
+        self.set_label(from_id, to_id, label)
+    }
+
+    /// Inserts many nodes in one call: `nodes` is a dict `{id: [dependents]}` or a
+    /// list of `(id, dependents)` pairs. An existing node is replaced, as with
+    /// `add_node`, unless `merge` is set, in which case its new dependents are
+    /// appended (each once) and existing edges keep their weights. Returns `{"created",
+    /// "updated"}` node counts. With `enforce_dag` or `enforce_schema` the batch is
+    /// atomic: if any node is rejected, the graph is left as it was.
+    #[pyo3(signature = (nodes, merge = false))]
+    fn add_nodes_bulk(
+        &mut self,
+        nodes: &PyAny,
+        merge: bool,
+    ) -> PyResult<HashMap<&'static str, usize>> {
+        self.ensure_mutable()?;
+        let entries: Vec<(String, Vec<String>)> = match nodes.downcast::<PyDict>() {
+            // In the dict's own order, so handles and logged events follow it.
+            Ok(dict) => dict
+                .iter()
+                .map(|(id, deps)| Ok((id.extract()?, deps.extract()?)))
+                .collect::<PyResult<_>>()?,
+            Err(_) => nodes.extract()?,
+        };
+        let entries = entries
+            .into_iter()
+            .map(|(id, deps)| Ok((self.normalize_id(id)?, self.normalize_ids(deps)?)))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let checked = self.enforce_dag
+            || self.enforce_schema
+            || self.cycle_check_steps.is_some()
+            || !self.mode.is_directed();
+        if checked {
+            let checkpoint = self.checkpoint();
+            let result = self.add_nodes_checked(entries, merge);
+            if let (Err(_), Some(checkpoint)) = (&result, checkpoint) {
+                self.restore_checkpoint(checkpoint);
+            }
+            return result;
+        }
+
+        // Unchecked: intern the ids, build the nodes in parallel, then insert them in
+        // one pass.
+        self.nodes.reserve(entries.len());
+        // Node ids first, so that new nodes get consecutive handles.
+        let handles: Vec<u32> = entries
+            .iter()
+            .map(|(id, _)| self.nodes.intern(id))
+            .collect();
+        let interned: Vec<(u32, Vec<u32>)> = handles
+            .into_iter()
+            .zip(&entries)
+            .map(|(h, (_, deps))| (h, deps.iter().map(|d| self.nodes.intern(d)).collect()))
+            .collect();
+        let built: Vec<(u32, CausalNode)> = config::install(|| {
+            interned
+                .into_par_iter()
+                .map(|(h, deps)| (h, CausalNode::with_dependents(deps)))
+                .collect()
+        });
+        let (mut created, mut updated) = (0, 0);
+        for ((id, deps), (h, mut node)) in entries.into_iter().zip(built) {
+            self.tombstones.remove(&id);
+            let Some(existing) = self.nodes.node_mut(h) else {
+                self.record_mutation(|| GraphEvent::AddNode {
+                    id: id.clone(),
+                    dependents: deps.clone(),
+                });
+                for (dep, &ts) in deps.iter().zip(&node.observed_at) {
+                    self.record_provenance(&id, dep, API_SOURCE, None, None, ts);
+                }
+                self.nodes.insert_at(h, node);
+                created += 1;
+                continue;
+            };
+            updated += 1;
+            if merge {
+                // A dependent listed twice is added once, as by repeated `add_edge`.
+                let mut new = Vec::new();
+                for i in 0..node.dependents.len() {
+                    if existing.edge_index(node.dependents[i]).is_none() {
+                        existing.push_edge(node.dependents[i], 1.0, node.observed_at[i]);
+                        new.push(i);
+                    }
+                }
+                for &i in &new {
+                    let (dep, ts) = (&deps[i], node.observed_at[i]);
+                    self.record_mutation(|| GraphEvent::AddEdge {
+                        from: id.clone(),
+                        to: dep.clone(),
+                    });
+                    self.record_provenance(&id, dep, API_SOURCE, None, None, ts);
+                }
+                continue;
+            }
+            self.record_mutation(|| GraphEvent::AddNode {
+                id: id.clone(),
+                dependents: deps.clone(),
+            });
+            if let Some(old) = self.nodes.remove(&id) {
+                self.forget_edges(&id, &old, Some(&node));
+                node.attributes = old.attributes;
+            }
+            for (dep, &ts) in deps.iter().zip(&node.observed_at) {
+                self.record_provenance(&id, dep, API_SOURCE, None, None, ts);
+            }
+            self.nodes.insert_at(h, node);
+        }
+        Ok(HashMap::from([("created", created), ("updated", updated)]))
+    }
+
+    /// Deletes a node and its outgoing edges. Returns false if it did not exist.
+    /// Edges from other nodes into it are kept unless `prune_edges` is set, which
+    /// scans every node. A kept reference behaves like an id that was never added as
+    /// a node: propagation reports it but does not cross it. In `undirected_multi`
+    /// mode the node's pairs are always removed from both sides.
+    #[pyo3(signature = (id, prune_edges = false))]
+    fn remove_node(&mut self, id: String, prune_edges: bool) -> PyResult<bool> {
+        self.ensure_mutable()?;
+        let id = self.normalize_id(id)?;
+        if !self.nodes.contains_key(&id) {
+            return Ok(false);
+        }
+        self.record_mutation(|| GraphEvent::RemoveNode {
+            id: id.clone(),
+            prune_edges,
+        });
+        let h = self.nodes.intern(&id);
+        if let Some(node) = self.nodes.remove(&id) {
+            self.forget_edges(&id, &node, None);
+        }
+        if prune_edges || !self.mode.is_directed() {
+            let nodes = &mut self.nodes;
+            let removed: usize = config::install(|| {
+                nodes
+                    .par_values_mut()
+                    .map(|node| node.retain_edges(|to, _, _| to != h))
+                    .sum()
+            });
+            if removed > 0 {
+                self.sync_provenance();
+            }
+        }
+        Ok(true)
+    }
+
+    /// Deletes the edge `from_id -> to_id` (in `undirected_multi` mode, the pair with
+    /// all its observations). Returns false if there was no such edge.
+    fn remove_edge(&mut self, from_id: String, to_id: String) -> PyResult<bool> {
+        self.ensure_mutable()?;
+        let from_id = self.normalize_id(from_id)?;
+        let to_id = self.normalize_id(to_id)?;
+        if !self.nodes.has_edge(&from_id, &to_id) {
+            return Ok(false);
+        }
+        self.record_mutation(|| GraphEvent::RemoveEdge {
+            from: from_id.clone(),
+            to: to_id.clone(),
+        });
+        let halves = if self.mode.is_directed() {
+            vec![(&from_id, &to_id)]
+        } else {
+            vec![(&from_id, &to_id), (&to_id, &from_id)]
+        };
+        for (from, to) in halves {
+            let to_h = self.nodes.intern(to);
+            let Some(node) = self.nodes.get_mut(from) else {
+                continue;
+            };
+            if node.retain_edges(|t, _, _| t != to_h) > 0 {
+                if let Some(store) = self.provenance.as_mut() {
+                    store.forget(self.nodes.ids(), from, to);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Merges `other`'s nodes, edges and attributes into this walker and returns
+    /// `{"nodes_added", "edges_added", "conflicts"}`. An edge present in both keeps
+    /// one copy with the later timestamp. Where the two disagree on an edge weight,
+    /// an edge label or a node attribute, `other` wins, unless `on_conflict` is given:
+    /// it is called as `on_conflict(kind, key, ours, theirs)` with kind "weight" or
+    /// "label" and key `(from, to)`, or kind "attribute" and key `(id, name)`, and
+    /// returns the value to keep. A missing label or attribute is no conflict. In
+    /// `undirected_multi` mode the observation counts of a shared pair are added.
+    /// Both walkers must have the same mode. Under `enforce_dag` or `enforce_schema`
+    /// the merge is atomic. Each merged edge is logged as a `merge_edge` event.
+    #[pyo3(signature = (other, on_conflict = None))]
+    fn merge(
+        &mut self,
+        py: Python,
+        other: &PyCell<GraphWalker>,
+        on_conflict: Option<&PyAny>,
+    ) -> PyResult<HashMap<&'static str, usize>> {
+        self.ensure_mutable()?;
+        let other = other
+            .try_borrow()
+            .map_err(|_| PyValueError::new_err("cannot merge a walker into itself"))?;
+        let checkpoint = self.checkpoint();
+        let result = self.merge_from(py, &other, on_conflict);
+        if let (Err(_), Some(checkpoint)) = (&result, checkpoint) {
+            self.restore_checkpoint(checkpoint);
+        }
+        result
+    }
+
+    /// A new walker merging `graphs` in order, as successive `merge` calls onto a
+    /// walker with the first graph's mode, DAG and schema enforcement, schema and
+    /// provenance tracking. Capacity for every input node is reserved up front.
+    #[staticmethod]
+    #[pyo3(signature = (graphs, on_conflict = None))]
+    fn union(
+        py: Python,
+        graphs: Vec<PyRef<GraphWalker>>,
+        on_conflict: Option<&PyAny>,
+    ) -> PyResult<GraphWalker> {
+        let mut walker = GraphWalker::blank();
+        let Some(first) = graphs.first() else {
+            return Ok(walker);
+        };
+        walker.mode = first.mode;
+        walker.enforce_dag = first.enforce_dag;
+        walker.enforce_schema = first.enforce_schema;
+        walker.schema = first.schema.clone();
+        if first.provenance.is_some() {
+            walker.provenance = Some(ProvenanceStore::default());
+        }
+        walker
+            .nodes
+            .reserve(graphs.iter().map(|g| g.nodes.len()).sum());
+        for graph in &graphs {
+            walker.merge_from(py, graph, on_conflict)?;
+        }
+        Ok(walker)
+    }
+
+    // ------------------------------------------------------------------------
+    // Edge Weights & Aging
+    // ------------------------------------------------------------------------
+
+    /// Multiplies every edge weight by `factor` (in parallel).
+    fn decay_weights(&mut self, factor: f64) -> PyResult<()> {
+        self.ensure_mutable()?;
+        if !factor.is_finite() || factor < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "decay factor must be a finite non-negative number, got {}",
+                factor
+            )));
+        }
+        self.record_mutation(|| GraphEvent::DecayWeights { factor });
+        let nodes = &mut self.nodes;
+        config::install(|| {
+            nodes.par_values_mut().for_each(|node| {
+                for w in node.weights.iter_mut() {
+                    *w *= factor;
+                }
+            })
+        });
+        Ok(())
+    }
+
+    /// Removes every edge whose weight is below `threshold` and returns how many were
+    /// removed. A NaN or infinite threshold raises ValueError.
+    fn prune_edges_below(&mut self, threshold: f64) -> PyResult<usize> {
+        self.ensure_mutable()?;
+        if !threshold.is_finite() {
+            return Err(PyValueError::new_err(format!(
+                "prune threshold must be a finite number, got {}",
+                threshold
+            )));
+        }
+        self.record_mutation(|| GraphEvent::PruneEdgesBelow { threshold });
+        let nodes = &mut self.nodes;
+        let removed = config::install(|| {
+            nodes
+                .par_values_mut()
+                .map(|node| node.retain_edges(|_, w, _| w >= threshold))
+                .sum()
+        });
+        if removed > 0 {
+            self.sync_provenance();
+        }
+        Ok(removed)
+    }
+
+    /// Removes the selected kinds of edges found by `validate`: edges to ids that are
+    /// neither nodes nor tombstoned, self-loops, and repeated copies of an edge in one
+    /// node's edge list (the first copy is kept). Edges touching the empty id are left
+    /// alone. Returns the number of edges removed per kind; an edge matching several
+    /// kinds counts under the first selected one.
+    #[pyo3(signature = (drop_dangling = false, dedupe_edges = false, drop_self_loops = false))]
+    fn repair(
+        &mut self,
+        py: Python,
+        drop_dangling: bool,
+        dedupe_edges: bool,
+        drop_self_loops: bool,
+    ) -> PyResult<HashMap<&'static str, usize>> {
+        let repaired =
+            py.allow_threads(|| self.repair_edges(drop_dangling, dedupe_edges, drop_self_loops))?;
+        Ok(HashMap::from([
+            ("dangling", repaired.dangling),
+            ("self_loops", repaired.self_loops),
+            ("duplicate_edges", repaired.duplicates),
+        ]))
+    }
+
+    /// Records a re-observation of `from_id -> to_id` at `ts`: the edge's weight is
+    /// restored to 1.0 and its timestamp advanced to `ts` (never moved backwards, so
+    /// out-of-order events are harmless). A previously unseen edge is created. In
+    /// `undirected_multi` mode the weight is an observation count, so it is
+    /// incremented instead of reset.
+    fn touch_edge(&mut self, from_id: String, to_id: String, ts: f64) -> PyResult<()> {
+        self.ensure_mutable()?;
+        let from_id = self.normalize_id(from_id)?;
+        let to_id = self.normalize_id(to_id)?;
+        if !self.mode.is_directed() {
+            self.check_schema(&from_id, &to_id, None)?;
+            self.record_mutation(|| GraphEvent::TouchEdge {
+                from: from_id.clone(),
+                to: to_id.clone(),
+                ts,
+            });
+            self.observe_pair(&from_id, &to_id, ts);
+            return Ok(());
+        }
+        let existing = self.nodes.edge_index(&from_id, &to_id);
+        if existing.is_none() {
+            if self.enforce_dag {
+                self.check_acyclic(&from_id, &to_id)?;
+            }
+            self.check_schema(&from_id, &to_id, None)?;
+        }
+        self.record_mutation(|| GraphEvent::TouchEdge {
+            from: from_id.clone(),
+            to: to_id.clone(),
+            ts,
+        });
+        if let (Some(i), Some(node)) = (existing, self.nodes.get_mut(&from_id)) {
+            node.weights[i] = 1.0;
+            node.observed_at[i] = node.observed_at[i].max(ts);
+            return Ok(());
+        }
+        self.tombstones.remove(&from_id);
+        self.record_provenance(&from_id, &to_id, API_SOURCE, None, None, ts);
+        let to = self.nodes.intern(&to_id);
+        self.nodes.get_or_insert(&from_id).push_edge(to, 1.0, ts);
+        self.check_new_edge_cycle(&from_id, &to_id);
+        Ok(())
+    }
+
+    /// Feeds back which nodes actually changed after invalidating `start_id`. Each
+    /// first-hop edge `start_id -> d` in the last `propagate_and_compare` result for
+    /// `start_id` moves toward 1.0 if `d` changed and toward 0.0 if it did not, by
+    /// `learning_rate` times the distance, and stays within [0, 1]. Returns
+    /// `{"adjusted", "unpredicted"}`: the number of edges updated and the changed ids
+    /// that were not in the predicted set. Raises ValueError if there is no stored
+    /// result for `start_id`.
+    #[pyo3(signature = (start_id, actually_changed, learning_rate = 0.1))]
+    fn record_outcome(
+        &mut self,
+        py: Python,
+        start_id: String,
+        actually_changed: Vec<String>,
+        learning_rate: f64,
+    ) -> PyResult<PyObject> {
+        self.ensure_mutable()?;
+        self.mode.require_directed("record_outcome")?;
+        if !(0.0..=1.0).contains(&learning_rate) {
+            return Err(PyValueError::new_err(format!(
+                "learning_rate must be in [0, 1], got {}",
+                learning_rate
+            )));
+        }
+        let start_id = self.lookup_id(start_id)?;
+        let changed: HashSet<String> = self.lookup_ids(actually_changed)?.into_iter().collect();
+        let (weights, mut unpredicted) = {
+            let cache = self.comparisons.lock().unwrap_or_else(|e| e.into_inner());
+            let predicted = cache.get(&start_id).ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "no stored propagation result for '{}'; call propagate_and_compare first",
+                    start_id
+                ))
+            })?;
+            let weights: Vec<(String, f64)> = self
+                .nodes
+                .get(&start_id)
+                .map(|node| {
+                    self.nodes
+                        .targets(node)
+                        .zip(&node.weights)
+                        .filter(|(d, _)| predicted.contains(*d))
+                        .map(|(d, &w)| {
+                            let target = if changed.contains(d) { 1.0 } else { 0.0 };
+                            (
+                                d.clone(),
+                                (w + learning_rate * (target - w)).clamp(0.0, 1.0),
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let unpredicted: Vec<String> = changed
+                .iter()
+                .filter(|id| !predicted.contains(*id))
+                .cloned()
+                .collect();
+            (weights, unpredicted)
+        };
+        unpredicted.sort();
+        let adjusted = weights.len();
+        if adjusted > 0 {
+            self.set_weights(start_id, weights)?;
+        }
+        let out = PyDict::new(py);
+        out.set_item("adjusted", adjusted)?;
+        out.set_item("unpredicted", unpredicted)?;
+        Ok(out.into())
+    }
+
+    /// Summary of all edge weights: `{"edges", "min", "max", "mean", "p50", "p90",
+    /// "histogram"}`, where `histogram` counts weights in ten equal buckets over
+    /// [0, 1] (values outside fall into the end buckets). The statistics are None
+    /// for a graph without edges.
+    fn weight_stats(&self, py: Python) -> PyResult<PyObject> {
+        let mut weights: Vec<f64> = self
+            .nodes
+            .values()
+            .flat_map(|n| n.weights.iter().copied())
+            .collect();
+        weights.sort_by(f64::total_cmp);
+        let mut histogram = [0usize; 10];
+        for &w in &weights {
+            histogram[((w * 10.0).floor().max(0.0) as usize).min(9)] += 1;
+        }
+        let quantile = |q: f64| {
+            (!weights.is_empty())
+                .then(|| weights[((weights.len() - 1) as f64 * q).round() as usize])
+        };
+        let out = PyDict::new(py);
+        out.set_item("edges", weights.len())?;
+        out.set_item("min", weights.first())?;
+        out.set_item("max", weights.last())?;
+        out.set_item(
+            "mean",
+            (!weights.is_empty()).then(|| weights.iter().sum::<f64>() / weights.len() as f64),
+        )?;
+        out.set_item("p50", quantile(0.5))?;
+        out.set_item("p90", quantile(0.9))?;
+        out.set_item("histogram", histogram.to_vec())?;
+        Ok(out.into())
+    }
+
+    /// Returns `(weight, last_observed_ts)` for an edge, or None if it does not exist.
+    fn get_edge(&self, from_id: String, to_id: String) -> PyResult<Option<(f64, f64)>> {
+        let (from_id, to_id) = (self.lookup_id(from_id)?, self.lookup_id(to_id)?);
+        let edge = self.nodes.edge_index(&from_id, &to_id);
+        let Some((node, i)) = self.nodes.get(&from_id).zip(edge) else {
+            return Ok(None);
+        };
+        Ok(Some((node.weights[i], node.observed_at[i])))
+    }
+
+    /// Where `from_id -> to_id` came from: `{"source", "partition", "offset",
+    /// "timestamp"}`. `source` is the Kafka topic, or "api" for direct calls (which
+    /// have no partition or offset). None if the edge does not exist or provenance
+    /// tracking is disabled.
+    fn edge_provenance(
+        &self,
+        py: Python,
+        from_id: String,
+        to_id: String,
+    ) -> PyResult<Option<PyObject>> {
+        let Some(store) = self.provenance.as_ref() else {
+            return Ok(None);
+        };
+        let (from_id, to_id) = (self.lookup_id(from_id)?, self.lookup_id(to_id)?);
+        match store.get(self.nodes.ids(), &from_id, &to_id) {
+            Some(p) => store.to_py(py, &p).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Estimated heap usage in bytes, broken down by component, plus `total`. Id
+    /// strings are stored once, in the interner; nodes and edges refer to them by
+    /// handle.
+    fn memory_usage(&self, py: Python) -> PyResult<PyObject> {
+        use std::mem::size_of;
+        let nodes = self.nodes.capacity() * size_of::<Option<CausalNode>>();
+        let (mut edges, mut attributes) = (0, 0);
+        for node in self.nodes.values() {
+            edges += node.dependents.capacity() * size_of::<u32>()
+                + (node.weights.capacity() + node.observed_at.capacity()) * size_of::<f64>()
+                + node.labels.capacity() * size_of::<Option<String>>()
+                + node
+                    .labels
+                    .iter()
+                    .flatten()
+                    .map(String::capacity)
+                    .sum::<usize>();
+            attributes += node.attributes.capacity() * 2 * size_of::<String>()
+                + node
+                    .attributes
+                    .iter()
+                    .map(|(k, v)| k.capacity() + v.capacity())
+                    .sum::<usize>();
+        }
+        let tombstones = self.tombstones.capacity() * (size_of::<String>() + size_of::<f64>())
+            + self.tombstones.keys().map(String::capacity).sum::<usize>();
+        let provenance = self
+            .provenance
+            .as_ref()
+            .map_or(0, ProvenanceStore::heap_bytes);
+        let interner = self.nodes.ids().heap_bytes();
+        let events = self
+            .events
+            .as_ref()
+            .map_or(0, |log| log.capacity() * size_of::<GraphEvent>());
+
+        let d = PyDict::new(py);
+        d.set_item("nodes", nodes)?;
+        d.set_item("edges", edges)?;
+        d.set_item("attributes", attributes)?;
+        d.set_item("tombstones", tombstones)?;
+        d.set_item("provenance", provenance)?;
+        d.set_item("interner", interner)?;
+        d.set_item("event_log", events)?;
+        d.set_item(
+            "total",
+            nodes + edges + attributes + tombstones + provenance + interner + events,
+        )?;
+        Ok(d.into())
+    }
+
+    /// `id` as the walker's normalizer would store it (unchanged without one). Use it
+    /// to look up nodes by a raw id.
+    fn normalize(&self, id: String) -> PyResult<String> {
+        self.lookup_id(id)
+    }
+
+    /// The first-seen original form of the normalized id `id`, or None if no id
+    /// was normalized to it.
+    fn original_id(&self, id: &str) -> Option<String> {
+        self.original_ids.get(id).cloned()
+    }
+
+    /// `"directed"` or `"undirected_multi"`.
+    fn mode(&self) -> &'static str {
+        self.mode.name()
+    }
+
+    fn is_dag_enforced(&self) -> bool {
+        self.enforce_dag
+    }
+
+    fn is_schema_enforced(&self) -> bool {
+        self.enforce_schema
+    }
+
+    /// Monotonic counter bumped by every successful mutation.
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn has_node(&self, id: &str) -> PyResult<bool> {
+        Ok(self.nodes.contains_key(&self.lookup_str(id)?))
+    }
+
+    /// The dependents of `id`, in insertion order. Raises NodeNotFoundError if the
+    /// node does not exist.
+    fn get_dependents(&self, id: &str) -> PyResult<Vec<String>> {
+        let id = self.lookup_str(id)?;
+        self.nodes
+            .get(&id)
+            .map(|node| self.nodes.targets(node).cloned().collect())
+            .ok_or_else(|| NodeNotFoundError::new_err(id.to_string()))
+    }
+
+    /// The dependents of `id` with their relation labels (None where unlabeled), in
+    /// insertion order. Raises NodeNotFoundError if the node does not exist.
+    fn get_dependents_labeled(&self, id: &str) -> PyResult<Vec<(String, Option<String>)>> {
+        let id = self.lookup_str(id)?;
+        self.nodes
+            .get(&id)
+            .map(|node| {
+                self.nodes
+                    .targets(node)
+                    .cloned()
+                    .zip(node.labels.iter().cloned())
+                    .collect()
+            })
+            .ok_or_else(|| NodeNotFoundError::new_err(id.to_string()))
+    }
+
+    /// All node ids, sorted.
+    fn node_ids(&self) -> Vec<String> {
+        self.sorted_ids()
+    }
+
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn __len__(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// False for non-str keys, as for a dict of str keys.
+    fn __contains__(&self, id: &PyAny) -> PyResult<bool> {
+        match id.extract::<&str>() {
+            Ok(id) => self.has_node(id),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Node ids in sorted order (see `cursor::NodeIter`); mutating the graph while
+    /// iterating raises StaleCursorError on the next step.
+    fn __iter__(slf: PyRef<Self>) -> NodeIter {
+        let ids = slf.sorted_ids();
+        let version = slf.version;
+        NodeIter::new(slf.into(), version, ids)
+    }
+
+    /// `walker[id]` is `get_dependents(id)`.
+    fn __getitem__(&self, id: &str) -> PyResult<Vec<String>> {
+        self.get_dependents(id)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "GraphWalker(nodes={}, edges={})",
+            self.nodes.len(),
+            self.edge_count_total()
+        )
+    }
+
+    /// Number of entries across all dependents lists, or of distinct `(from, to)`
+    /// edges with `unique`. In `undirected_multi` mode, the number of pairs.
+    #[pyo3(signature = (unique = false))]
+    fn edge_count(&self, unique: bool) -> usize {
+        if !self.mode.is_directed() {
+            return self.edge_count_total();
+        }
+        if !unique {
+            return self.nodes.values().map(|n| n.dependents.len()).sum();
+        }
+        self.nodes
+            .values()
+            .map(|n| n.dependents.iter().collect::<HashSet<_>>().len())
+            .sum()
+    }
+
+    /// Node ids sorted by id, windowed by `offset` and `limit`.
+    #[pyo3(signature = (offset = 0, limit = None))]
+    fn nodes(&self, offset: usize, limit: Option<usize>) -> Vec<String> {
+        cursor::page(self.sorted_ids(), offset, limit)
+    }
+
+    /// Edges as `(from, to, weight)` sorted by `from`, then `to`, windowed by
+    /// `offset` and `limit`. In `undirected_multi` mode both halves of each pair are
+    /// listed.
+    #[pyo3(signature = (offset = 0, limit = None))]
+    fn edges(&self, offset: usize, limit: Option<usize>) -> Vec<(String, String, f64)> {
+        cursor::page(self.sorted_edges(), offset, limit)
+    }
+
+    /// Cursor over `nodes()`.
+    fn nodes_cursor(slf: PyRef<Self>) -> ResultCursor {
+        let rows = Rows::Ids(slf.sorted_ids());
+        let version = slf.version;
+        ResultCursor::new(slf.into(), version, rows)
+    }
+
+    /// Cursor over `edges()`.
+    fn edges_cursor(slf: PyRef<Self>) -> ResultCursor {
+        let rows = Rows::Edges(slf.sorted_edges());
+        let version = slf.version;
+        ResultCursor::new(slf.into(), version, rows)
+    }
+
+    /// Propagates invalidation from a starting node to all affected dependents.
+    /// Uses parallel iteration for improved performance on large graphs.
+    /// Raises ResourceLimitError if `max_results` / `max_visited` (or the module
+    /// defaults) are exceeded. With `changefeed`, the result is also appended to the
+    /// changefeed file at that path (see `append_changefeed`). Raises
+    /// NodeNotFoundError if `start_id` is not a node, unless `strict=False`, which
+    /// returns an empty set instead. The methods built on it are strict.
+    ///
+    /// With `attribute_filter`, only nodes whose attributes include every given
+    /// key/value pair are invalidated; any other node reached (edge targets that are
+    /// not nodes included) is left out and not expanded, so propagation stops there.
+    /// The start node itself is not filtered.
+    ///
+    /// With `follow_labels`, only edges whose label is in the list are traversed;
+    /// unlabeled edges are not followed then. Without it every edge is followed.
+    #[pyo3(signature = (
+        start_id,
+        max_results = None,
+        max_visited = None,
+        changefeed = None,
+        strict = true,
+        attribute_filter = None,
+        follow_labels = None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn propagate_invalidation(
+        &self,
+        py: Python,
+        start_id: String,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+        changefeed: Option<&str>,
+        strict: bool,
+        attribute_filter: Option<HashMap<String, String>>,
+        follow_labels: Option<Vec<String>>,
+    ) -> PyResult<HashSet<String>> {
+        let start_id = self.lookup_id(start_id)?;
+        py.allow_threads(|| {
+            if strict && !self.nodes.contains_key(&start_id) {
+                return Err(NodeNotFoundError::new_err(start_id));
+            }
+            let limits = Limits::resolve(max_results, max_visited);
+            let follow: Option<HashSet<&str>> = follow_labels
+                .as_ref()
+                .map(|labels| labels.iter().map(String::as_str).collect());
+            let invalid_set = self.propagate(
+                &start_id,
+                &limits,
+                follow.as_ref(),
+                attribute_filter.as_ref(),
+            )?;
+            if let Some(path) = changefeed {
+                changefeed::append(path, start_id, invalid_set.iter().cloned().collect())?;
+            }
+            Ok(invalid_set)
+        })
+    }
+
+    /// Union of `propagate_invalidation` over every id in `start_ids`, in a single
+    /// traversal that expands each node at most once. A start node is in the result
+    /// only if it is reachable from some start node. Limits and `strict` behave as in
+    /// `propagate_invalidation` (an unknown start raises unless `strict=False`).
+    #[pyo3(signature = (start_ids, max_results = None, max_visited = None, strict = true))]
+    fn propagate_invalidation_multi(
+        &self,
+        py: Python,
+        start_ids: Vec<String>,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+        strict: bool,
+    ) -> PyResult<HashSet<String>> {
+        let start_ids = self.lookup_ids(start_ids)?;
+        py.allow_threads(|| {
+            self.require_starts(&start_ids, strict)?;
+            let limits = Limits::resolve(max_results, max_visited);
+            let seeds: HashSet<u32> = start_ids.iter().map(|id| self.nodes.lookup(id)).collect();
+            let mut invalid_set: HashSet<u32> = HashSet::new();
+            let mut frontier: Vec<u32> = seeds.iter().copied().collect();
+            let mut visited = 0;
+
+            while !frontier.is_empty() {
+                visited += frontier.len();
+                limits.check(visited, invalid_set.len())?;
+                let reached: Vec<u32> = config::install(|| {
+                    frontier
+                        .par_iter()
+                        .filter_map(|&h| self.nodes.node(h))
+                        .flat_map_iter(|node| node.dependents.iter().copied())
+                        .collect()
+                });
+
+                frontier = Vec::new();
+                for item in reached {
+                    if self.is_tombstoned(item) {
+                        continue;
+                    }
+                    // Seeds were expanded in the first round already.
+                    if invalid_set.insert(item) && !seeds.contains(&item) {
+                        frontier.push(item);
+                    }
+                }
+                limits.check(visited, invalid_set.len())?;
+            }
+
+            let invalid_set: HashSet<String> = self.nodes.names(invalid_set);
+            if let Some(heat) = &self.heat {
+                heat.record(&invalid_set);
+            }
+            Ok(invalid_set)
+        })
+    }
+
+    /// `propagate_invalidation_multi` with attribution: maps each affected node to
+    /// the start ids whose single-source propagation reaches it, in `start_ids`
+    /// order. A node may be expanded once per start that reaches it.
+    #[pyo3(signature = (start_ids, max_results = None, max_visited = None, strict = true))]
+    fn propagate_invalidation_attributed(
+        &self,
+        py: Python,
+        start_ids: Vec<String>,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+        strict: bool,
+    ) -> PyResult<HashMap<String, Vec<String>>> {
+        let start_ids = self.lookup_ids(start_ids)?;
+        py.allow_threads(|| {
+            self.require_starts(&start_ids, strict)?;
+            let limits = Limits::resolve(max_results, max_visited);
+            let mut sources: Vec<&str> = Vec::with_capacity(start_ids.len());
+            for id in &start_ids {
+                if !sources.contains(&id.as_str()) {
+                    sources.push(id);
+                }
+            }
+            // Source indices reaching each node; the frontier carries only the newly
+            // arrived ones.
+            let mut labels: HashMap<u32, BTreeSet<usize>> = HashMap::new();
+            let mut frontier: Vec<(u32, Vec<usize>)> = sources
+                .iter()
+                .enumerate()
+                .map(|(i, id)| (self.nodes.lookup(id), vec![i]))
+                .collect();
+            let mut visited = 0;
+
+            while !frontier.is_empty() {
+                visited += frontier.len();
+                limits.check(visited, labels.len())?;
+                let arrivals: Vec<(u32, usize)> = config::install(|| {
+                    frontier
+                        .par_iter()
+                        .filter_map(|(h, delta)| self.nodes.node(*h).map(|node| (node, delta)))
+                        .flat_map_iter(|(node, delta)| {
+                            node.dependents
+                                .iter()
+                                .flat_map(move |&dep| delta.iter().map(move |&l| (dep, l)))
+                        })
+                        .collect()
+                });
+
+                let mut next: HashMap<u32, Vec<usize>> = HashMap::new();
+                for (dep, label) in arrivals {
+                    if self.is_tombstoned(dep) {
+                        continue;
+                    }
+                    if labels.entry(dep).or_default().insert(label) {
+                        next.entry(dep).or_default().push(label);
+                    }
+                }
+                frontier = next.into_iter().collect();
+                limits.check(visited, labels.len())?;
+            }
+
+            let attributed: HashMap<String, Vec<String>> = labels
+                .into_iter()
+                .map(|(h, ls)| {
+                    let by: Vec<String> = ls.into_iter().map(|l| sources[l].to_string()).collect();
+                    (self.nodes.name(h).clone(), by)
+                })
+                .collect();
+            if let Some(heat) = &self.heat {
+                heat.record(attributed.keys());
+            }
+            Ok(attributed)
+        })
+    }
+
+    /// Every node from which `node_id` is reachable, i.e. everything whose change
+    /// could invalidate it: `propagate_invalidation` against edge direction. The
+    /// node itself is excluded, even on a cycle. Raises NodeNotFoundError if
+    /// `node_id` is neither a node nor an edge target. Uses the cached reverse
+    /// adjacency, rebuilt lazily after mutations.
+    #[pyo3(signature = (node_id, max_results = None, max_visited = None))]
+    fn find_ancestors(
+        &self,
+        py: Python,
+        node_id: String,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+    ) -> PyResult<HashSet<String>> {
+        let node_id = self.lookup_id(node_id)?;
+        py.allow_threads(|| {
+            let reverse = self.reverse_adjacency_cached();
+            let target = self.nodes.lookup(&node_id);
+            if !self.nodes.contains(target) && sources_of(&reverse, target).is_empty() {
+                return Err(NodeNotFoundError::new_err(node_id));
+            }
+            let limits = Limits::resolve(max_results, max_visited);
+            let mut ancestors: HashSet<u32> = HashSet::new();
+            let mut frontier = vec![target];
+            let mut visited = 0;
+            while !frontier.is_empty() {
+                visited += frontier.len();
+                limits.check(visited, ancestors.len())?;
+                let mut next = Vec::new();
+                for h in frontier {
+                    for &source in sources_of(&reverse, h) {
+                        if source != target && ancestors.insert(source) {
+                            next.push(source);
+                        }
+                    }
+                }
+                frontier = next;
+                limits.check(visited, ancestors.len())?;
+            }
+            Ok(self.nodes.names(ancestors))
+        })
+    }
+
+    /// Builds the reachability index behind `is_reachable` and `reachable_count`
+    /// (see `reachability`) and reports its size: `{"components", "intervals",
+    /// "memory_bytes"}`. The index is cached like other computed metrics. Any
+    /// mutation marks it stale, and the next query rebuilds it lazily, so calling
+    /// this first only moves the build cost out of the first query.
+    fn build_reachability_index(&self, py: Python) -> HashMap<&'static str, usize> {
+        let index = py.allow_threads(|| self.reachability_cached());
+        HashMap::from([
+            ("components", index.component_count()),
+            ("intervals", index.interval_count()),
+            ("memory_bytes", index.heap_bytes()),
+        ])
+    }
+
+    /// True if `to_id` is in `propagate_invalidation(from_id)`: reachable through at
+    /// least one edge, skipping tombstoned ids. Answered from the reachability index
+    /// by a binary search. Raises NodeNotFoundError if `from_id` is not a node.
+    fn is_reachable(&self, py: Python, from_id: &str, to_id: &str) -> PyResult<bool> {
+        let (from_id, to_id) = (self.lookup_str(from_id)?, self.lookup_str(to_id)?);
+        let Some(from) = self
+            .nodes
+            .handle(&from_id)
+            .filter(|&h| self.nodes.contains(h))
+        else {
+            return Err(NodeNotFoundError::new_err(from_id.to_string()));
+        };
+        let to = self.nodes.lookup(&to_id);
+        Ok(py
+            .allow_threads(|| self.reachability_cached())
+            .reaches(from, to))
+    }
+
+    /// The size of `propagate_invalidation(from_id)`, from the reachability index in
+    /// time linear in the number of intervals stored for `from_id`. Raises
+    /// NodeNotFoundError if `from_id` is not a node, and ResourceLimitError as
+    /// `propagate_invalidation` would, counting every reached node as visited.
+    #[pyo3(signature = (from_id, max_results = None, max_visited = None))]
+    fn reachable_count(
+        &self,
+        py: Python,
+        from_id: &str,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+    ) -> PyResult<usize> {
+        let from_id = self.lookup_str(from_id)?;
+        let Some(from) = self
+            .nodes
+            .handle(&from_id)
+            .filter(|&h| self.nodes.contains(h))
+        else {
+            return Err(NodeNotFoundError::new_err(from_id.to_string()));
+        };
+        let count = py.allow_threads(|| self.reachability_cached()).count(from);
+        Limits::resolve(max_results, max_visited).check(count, count)?;
+        Ok(count)
+    }
+
+    /// `len(propagate_invalidation(id))` for every node: the size of its invalidation
+    /// blast radius. Counted from the reachability index, so the members of a cycle
+    /// share one reachable set and each count is a sum over that node's intervals;
+    /// counts run in parallel. With `top_k`, only the `k` highest-impact nodes are
+    /// returned, ties broken by id.
+    #[pyo3(signature = (top_k = None))]
+    fn impact_scores(&self, py: Python, top_k: Option<usize>) -> HashMap<String, usize> {
+        py.allow_threads(|| {
+            let index = self.reachability_cached();
+            let handles: Vec<u32> = self.nodes.handles().map(|(h, _)| h).collect();
+            let mut scores: Vec<(u32, usize)> =
+                config::install(|| handles.par_iter().map(|&h| (h, index.count(h))).collect());
+            if let Some(k) = top_k.filter(|&k| k < scores.len()) {
+                let names = &self.nodes;
+                if k > 0 {
+                    scores.select_nth_unstable_by(k - 1, |a, b| {
+                        b.1.cmp(&a.1)
+                            .then_with(|| names.name(a.0).cmp(names.name(b.0)))
+                    });
+                }
+                scores.truncate(k);
+            }
+            scores
+                .into_iter()
+                .map(|(h, count)| (self.nodes.name(h).clone(), count))
+                .collect()
+        })
+    }
+
+    /// `propagate_invalidation` with provenance: maps each affected node to the nodes
+    /// through which invalidation first reached it, i.e. its predecessors one hop
+    /// closer to `start_id`, sorted. Following any parent back leads to `start_id`
+    /// along a shortest path. Runs the same traversal, so limits and `strict` behave
+    /// as there.
+    #[pyo3(signature = (start_id, max_results = None, max_visited = None, strict = true))]
+    fn propagate_invalidation_with_parents(
+        &self,
+        py: Python,
+        start_id: String,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+        strict: bool,
+    ) -> PyResult<HashMap<String, Vec<String>>> {
+        let start_id = self.lookup_id(start_id)?;
+        py.allow_threads(|| {
+            if strict && !self.nodes.contains_key(&start_id) {
+                return Err(NodeNotFoundError::new_err(start_id));
+            }
+            let limits = Limits::resolve(max_results, max_visited);
+            let mut parents = HashMap::new();
+            self.invalidation_bfs(
+                self.nodes.lookup(&start_id),
+                &limits,
+                None,
+                None,
+                Some(&mut parents),
+            )?;
+            let parents: HashMap<String, Vec<String>> = parents
+                .into_iter()
+                .map(|(h, from)| (self.nodes.name(h).clone(), self.nodes.names(from)))
+                .collect();
+            if let Some(heat) = &self.heat {
+                heat.record(parents.keys());
+            }
+            Ok(parents)
+        })
+    }
+
+    /// Why `target_id` is in `propagate_invalidation(start_id)`: up to `max_paths`
+    /// distinct paths `start_id -> ... -> target_id`, both ends included, shortest
+    /// first and then by ids. Paths never repeat a node (except `start_id` closing a
+    /// cycle back to itself) and never cross tombstoned ids, so there are finitely
+    /// many. Empty if `target_id` is not invalidated. Raises NodeNotFoundError as
+    /// `shortest_path` does, and ResourceLimitError once more than `max_visited`
+    /// partial paths (or the module default) have been expanded.
+    #[pyo3(signature = (start_id, target_id, max_paths = 5, max_visited = None))]
+    fn explain_invalidation(
+        &self,
+        py: Python,
+        start_id: String,
+        target_id: String,
+        max_paths: usize,
+        max_visited: Option<usize>,
+    ) -> PyResult<Vec<Vec<String>>> {
+        use std::cmp::Reverse;
+        use std::collections::BinaryHeap;
+        let (start_id, target_id) = (self.lookup_id(start_id)?, self.lookup_id(target_id)?);
+        py.allow_threads(|| {
+            self.require_path_ends(&start_id, &target_id)?;
+            let limits = Limits::resolve(None, max_visited);
+            let (start, goal) = (self.nodes.lookup(&start_id), self.nodes.lookup(&target_id));
+            if max_paths == 0 || self.is_tombstoned(goal) {
+                return Ok(Vec::new());
+            }
+            // Hops left to the target, by a backward BFS; unlisted handles cannot
+            // reach it.
+            let reverse = self.reverse_adjacency_cached();
+            let mut to_goal: HashMap<u32, usize> = HashMap::from([(goal, 0)]);
+            let mut queue = VecDeque::from([goal]);
+            while let Some(v) = queue.pop_front() {
+                let d = to_goal[&v] + 1;
+                for &u in sources_of(&reverse, v) {
+                    if let Entry::Vacant(slot) = to_goal.entry(u) {
+                        slot.insert(d);
+                        queue.push_back(u);
+                    }
+                }
+            }
+            // Best-first over partial paths by length plus the hops still needed, so
+            // complete paths come out shortest first.
+            type Partial<'a> = Reverse<(usize, Vec<&'a String>, Vec<u32>)>;
+            let mut heap: BinaryHeap<Partial> = BinaryHeap::new();
+            heap.push(Reverse((0, vec![self.nodes.name(start)], vec![start])));
+            let mut paths: Vec<Vec<String>> = Vec::new();
+            let mut visited = 0;
+            while let Some(Reverse((_, names, handles))) = heap.pop() {
+                let last = handles[handles.len() - 1];
+                if handles.len() > 1 && last == goal {
+                    paths.push(names.into_iter().cloned().collect());
+                    if paths.len() == max_paths {
+                        break;
+                    }
+                    continue;
+                }
+                visited += 1;
+                limits.check(visited, paths.len())?;
+                let Some(node) = self.nodes.node(last) else {
+                    continue;
+                };
+                let mut next: Vec<u32> = node.dependents.clone();
+                next.sort_unstable();
+                next.dedup();
+                for v in next {
+                    let Some(&left) = to_goal.get(&v) else {
+                        continue;
+                    };
+                    if self.is_tombstoned(v) || (v != goal && handles.contains(&v)) {
+                        continue;
+                    }
+                    let mut names = names.clone();
+                    names.push(self.nodes.name(v));
+                    let mut handles = handles.clone();
+                    handles.push(v);
+                    heap.push(Reverse((handles.len() - 1 + left, names, handles)));
+                }
+            }
+            Ok(paths)
+        })
+    }
+
+    /// Marks everything `propagate_invalidation(start_id)` reaches as invalid and
+    /// returns, sorted, the ids that were not invalid already. The walk stops at ids
+    /// invalidated earlier, whose dependents are invalid too, so repeated
+    /// overlapping invalidations only pay for what is new. The state persists until
+    /// `clear_invalid` and survives pickling and snapshots. Raises
+    /// NodeNotFoundError if `start_id` is not a node.
+    fn mark_invalid(&mut self, py: Python, start_id: String) -> PyResult<Vec<String>> {
+        let start_id = self.lookup_id(start_id)?;
+        let start = match self.nodes.handle(&start_id) {
+            Some(h) if self.nodes.contains(h) => h,
+            _ => return Err(NodeNotFoundError::new_err(start_id)),
+        };
+        py.allow_threads(|| {
+            self.sync_dirty();
+            let GraphWalker {
+                nodes,
+                dirty,
+                tombstones,
+                ..
+            } = self;
+            let fresh = dirty.mark(nodes, start, |h| {
+                !tombstones.is_empty() && tombstones.contains_key(nodes.name(h))
+            });
+            let mut names: Vec<String> = self.nodes.names(fresh);
+            names.sort_unstable();
+            Ok(names)
+        })
+    }
+
+    /// True if `id` was marked by `mark_invalid` and not cleared since.
+    fn is_invalid(&self, id: &str) -> PyResult<bool> {
+        Ok(self
+            .nodes
+            .handle(&self.lookup_str(id)?)
+            .is_some_and(|h| self.dirty.contains(h)))
+    }
+
+    /// Number of ids currently marked invalid.
+    fn invalid_count(&self) -> usize {
+        self.dirty.len()
+    }
+
+    /// The ids currently marked invalid.
+    fn get_invalid(&self) -> HashSet<String> {
+        self.nodes.names(self.dirty.handles())
+    }
+
+    /// Marks `ids`, or every id when None, as recomputed and returns how many of
+    /// them were invalid. Unknown ids are ignored. A later `mark_invalid` walks past
+    /// invalid ids that have a cleared id downstream, so clearing part of a blast
+    /// radius and invalidating again re-marks the cleared part.
+    #[pyo3(signature = (ids = None))]
+    fn clear_invalid(&mut self, py: Python, ids: Option<Vec<String>>) -> PyResult<usize> {
+        let Some(ids) = ids else {
+            return Ok(self.dirty.clear_all());
+        };
+        let ids = self.lookup_ids(ids)?;
+        Ok(py.allow_threads(|| {
+            self.sync_dirty();
+            let handles: Vec<u32> = ids.iter().filter_map(|id| self.nodes.handle(id)).collect();
+            let reverse = self.reverse_adjacency_cached();
+            self.dirty.clear(&reverse, &handles)
+        }))
+    }
+
+    /// Like `propagate_invalidation`, but maps each affected node to its hop distance
+    /// from `start_id` (the shortest, when it is reachable several ways), expanding no
+    /// further than `max_depth` hops. The start itself appears only if it lies on a
+    /// cycle, at that cycle's length. `max_depth=0` returns an empty map. Limits and
+    /// `strict` behave as in `propagate_invalidation`.
+    #[pyo3(signature = (
+        start_id,
+        max_depth = None,
+        max_results = None,
+        max_visited = None,
+        strict = true
+    ))]
+    fn propagate_invalidation_with_depth(
+        &self,
+        py: Python,
+        start_id: String,
+        max_depth: Option<usize>,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+        strict: bool,
+    ) -> PyResult<HashMap<String, usize>> {
+        let start_id = self.lookup_id(start_id)?;
+        py.allow_threads(|| {
+            if strict && !self.nodes.contains_key(&start_id) {
+                return Err(NodeNotFoundError::new_err(start_id));
+            }
+            let limits = Limits::resolve(max_results, max_visited);
+            let max_depth = max_depth.unwrap_or(usize::MAX);
+            let start = self.nodes.lookup(&start_id);
+            let mut depths: HashMap<u32, usize> = HashMap::new();
+            let mut queue = VecDeque::from([(start, 0)]);
+            let mut visited = 0;
+            // Depths leave the queue in non-decreasing order, so the first time a node
+            // is reached is at its shortest distance.
+            while let Some((h, depth)) = queue.pop_front() {
+                visited += 1;
+                limits.check(visited, depths.len())?;
+                if depth >= max_depth {
+                    continue;
+                }
+                let Some(node) = self.nodes.node(h) else {
+                    continue;
+                };
+                for &dep in &node.dependents {
+                    if self.is_tombstoned(dep) || depths.contains_key(&dep) {
+                        continue;
+                    }
+                    depths.insert(dep, depth + 1);
+                    if dep != start {
+                        queue.push_back((dep, depth + 1));
+                    }
+                }
+                limits.check(visited, depths.len())?;
+            }
+            let depths: HashMap<String, usize> = depths
+                .into_iter()
+                .map(|(h, depth)| (self.nodes.name(h).clone(), depth))
+                .collect();
+            if let Some(heat) = &self.heat {
+                heat.record(depths.keys());
+            }
+            Ok(depths)
+        })
+    }
+
+    /// `propagate_invalidation` as a list sorted by id, windowed by `offset` and
+    /// `limit`. Paging re-runs the propagation; use `propagate_cursor` to compute it
+    /// once.
+    #[pyo3(signature = (start_id, offset = 0, limit = None, max_results = None, max_visited = None))]
+    fn propagate_invalidation_sorted(
+        &self,
+        py: Python,
+        start_id: String,
+        offset: usize,
+        limit: Option<usize>,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+    ) -> PyResult<Vec<String>> {
+        let mut ids: Vec<String> = self
+            .propagate_invalidation(
+                py,
+                start_id,
+                max_results,
+                max_visited,
+                None,
+                true,
+                None,
+                None,
+            )?
+            .into_iter()
+            .collect();
+        ids.sort();
+        Ok(cursor::page(ids, offset, limit))
+    }
+
+    /// Cursor over `propagate_invalidation_sorted(start_id)`.
+    #[pyo3(signature = (start_id, max_results = None, max_visited = None))]
+    fn propagate_cursor(
+        slf: PyRef<Self>,
+        start_id: String,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+    ) -> PyResult<ResultCursor> {
+        let ids = slf.propagate_invalidation_sorted(
+            slf.py(),
+            start_id,
+            0,
+            None,
+            max_results,
+            max_visited,
+        )?;
+        let version = slf.version;
+        Ok(ResultCursor::new(slf.into(), version, Rows::Ids(ids)))
+    }
+
+    /// Runs `propagate_invalidation` with a trace of how the result came about, for
+    /// debugging surprising results. Returns `{"affected": [...sorted], "trace":
+    /// {...}}`, where the trace is a dict of lists (JSON-ready): per frontier level
+    /// `frontier_sizes`, `new_nodes`, `tombstone_hits` (edges into tombstoned
+    /// nodes), `revisits` (edges into already affected nodes) and `ms`, plus
+    /// `levels` and `paths`: for each id in `nodes_of_interest`, the first path found
+    /// from `start_id`, or None if it is not affected. Does not count toward heat.
+    #[pyo3(signature = (start_id, nodes_of_interest = None, max_results = None, max_visited = None))]
+    fn explain_propagation(
+        &self,
+        py: Python,
+        start_id: String,
+        nodes_of_interest: Option<Vec<String>>,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+    ) -> PyResult<PyObject> {
+        let start_id = self.lookup_id(start_id)?;
+        if !self.nodes.contains_key(&start_id) {
+            return Err(NodeNotFoundError::new_err(start_id));
+        }
+        let limits = Limits::resolve(max_results, max_visited);
+        let start = self.nodes.lookup(&start_id);
+        let mut parent: HashMap<u32, u32> = HashMap::new();
+        let mut affected: HashSet<u32> = HashSet::new();
+        let mut frontier: Vec<u32> = vec![start];
+        let mut visited = 0;
+        let (mut frontier_sizes, mut new_nodes, mut tombstone_hits, mut revisits, mut ms) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new());
+
+        while !frontier.is_empty() {
+            let started = std::time::Instant::now();
+            visited += frontier.len();
+            limits.check(visited, affected.len())?;
+            frontier.sort_unstable_by_key(|&h| self.nodes.name(h));
+            let (mut tombstoned, mut seen) = (0, 0);
+            let mut next = Vec::new();
+            for &h in &frontier {
+                let Some(node) = self.nodes.node(h) else {
+                    continue;
+                };
+                for &dep in &node.dependents {
+                    if self.is_tombstoned(dep) {
+                        tombstoned += 1;
+                    } else if affected.insert(dep) {
+                        parent.insert(dep, h);
+                        next.push(dep);
+                    } else {
+                        seen += 1;
+                    }
+                }
+            }
+            frontier_sizes.push(frontier.len());
+            new_nodes.push(next.len());
+            tombstone_hits.push(tombstoned);
+            revisits.push(seen);
+            ms.push(started.elapsed().as_secs_f64() * 1000.0);
+            frontier = next;
+            limits.check(visited, affected.len())?;
+        }
+
+        let paths = PyDict::new(py);
+        for target in nodes_of_interest.unwrap_or_default() {
+            let path = self
+                .nodes
+                .handle(&self.lookup_str(&target)?)
+                .filter(|h| affected.contains(h))
+                .map(|h| {
+                    let mut path = vec![h];
+                    let mut at = h;
+                    // The start is reached again only if it lies on a cycle, so stop there.
+                    while let Some(&p) = parent.get(&at) {
+                        path.push(p);
+                        if p == start {
+                            break;
+                        }
+                        at = p;
+                    }
+                    path.reverse();
+                    self.nodes.names::<Vec<String>>(path)
+                });
+            paths.set_item(&target, path)?;
+        }
+        let trace = PyDict::new(py);
+        trace.set_item("levels", frontier_sizes.len())?;
+        trace.set_item("frontier_sizes", frontier_sizes)?;
+        trace.set_item("new_nodes", new_nodes)?;
+        trace.set_item("tombstone_hits", tombstone_hits)?;
+        trace.set_item("revisits", revisits)?;
+        trace.set_item("ms", ms)?;
+        trace.set_item("paths", paths)?;
+        let mut sorted: Vec<&String> = affected.into_iter().map(|h| self.nodes.name(h)).collect();
+        sorted.sort_unstable();
+        let out = PyDict::new(py);
+        out.set_item("affected", sorted)?;
+        out.set_item("trace", trace)?;
+        Ok(out.into())
+    }
+
+    /// Propagates from `start_id` and compares the result with the previous
+    /// propagation from the same node. Returns `added` and `removed` (sorted lists),
+    /// the `unchanged` count, `version` and `previous_version`, plus `current` (sorted)
+    /// with `include_current`. When there is no usable previous result — first call,
+    /// evicted, or older than the staleness set by `configure_comparisons` — it sets
+    /// `baseline_reset` and reports empty deltas.
+    #[pyo3(signature = (start_id, include_current = false, max_results = None, max_visited = None))]
+    fn propagate_and_compare(
+        &self,
+        py: Python,
+        start_id: String,
+        include_current: bool,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+    ) -> PyResult<PyObject> {
+        let start_id = self.lookup_id(start_id)?;
+        let current = self.propagate_invalidation(
+            py,
+            start_id.clone(),
+            max_results,
+            max_visited,
+            None,
+            true,
+            None,
+            None,
+        )?;
+        let sorted_current = include_current.then(|| {
+            let mut ids: Vec<String> = current.iter().cloned().collect();
+            ids.sort();
+            ids
+        });
+        let cmp = self
+            .comparisons
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .compare(&start_id, self.version, current);
+        let out = PyDict::new(py);
+        out.set_item("added", cmp.added)?;
+        out.set_item("removed", cmp.removed)?;
+        out.set_item("unchanged", cmp.unchanged)?;
+        out.set_item("baseline_reset", cmp.previous_version.is_none())?;
+        out.set_item("version", self.version)?;
+        out.set_item("previous_version", cmp.previous_version)?;
+        if let Some(ids) = sorted_current {
+            out.set_item("current", ids)?;
+        }
+        Ok(out.into())
+    }
+
+    /// Sets how many start nodes `propagate_and_compare` remembers (least recently
+    /// used are dropped first) and the maximum number of versions a stored result may
+    /// lag before the next comparison resets its baseline. Omitted values are kept;
+    /// `max_staleness=-1` removes the staleness bound.
+    #[pyo3(signature = (capacity = None, max_staleness = None))]
+    fn configure_comparisons(
+        &self,
+        capacity: Option<usize>,
+        max_staleness: Option<i64>,
+    ) -> PyResult<()> {
+        let mut cache = self.comparisons.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(capacity) = capacity {
+            cache.set_capacity(capacity);
+        }
+        match max_staleness {
+            None => {}
+            Some(-1) => cache.max_staleness = None,
+            Some(n) if n >= 0 => cache.max_staleness = Some(n as u64),
+            Some(n) => {
+                return Err(PyValueError::new_err(format!(
+                    "max_staleness must be >= 0 or -1, got {}",
+                    n
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// Forgets every stored result, so the next comparison per node is a baseline.
+    fn clear_comparisons(&self) {
+        self.comparisons
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// Same traversal as `propagate_invalidation`, but returns a sorted int64 numpy
+    /// array of interned handles instead of a set of strings; map them back with
+    /// `id_table()`.
+    #[pyo3(signature = (start_id, max_results = None, max_visited = None))]
+    fn propagate_invalidation_ids<'py>(
+        &mut self,
+        py: Python<'py>,
+        start_id: String,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+    ) -> PyResult<&'py PyArray1<i64>> {
+        let affected = self.propagate_invalidation(
+            py,
+            start_id,
+            max_results,
+            max_visited,
+            None,
+            true,
+            None,
+            None,
+        )?;
+        self.intern_array(py, affected.iter())
+    }
+
+    /// Handle-to-id lookup table for the `*_ids` APIs: `id_table()[h]` is the id of
+    /// handle `h`. The table only grows, so it can be fetched once after the calls.
+    fn id_table(&self) -> Vec<String> {
+        self.nodes.ids().ids().to_vec()
+    }
+
+    /// Splits node `id` into several new nodes. Each part is `(new_id, in_sources,
+    /// out_targets)`: the in-edges from `in_sources` and the out-edges to `out_targets`
+    /// move to `new_id` (an edge listed on several parts is duplicated onto each).
+    /// With `keep_original`, `id` stays and keeps every unassigned edge; otherwise it is
+    /// removed and unassigned edges are dropped, or rejected with ValueError if `strict`.
+    /// Weights, labels and timestamps travel with their edges; attributes are copied to every
+    /// part unless `copy_attributes` is false.
+    #[pyo3(signature = (id, parts, keep_original = false, strict = false, copy_attributes = true))]
+    fn split_node(
+        &mut self,
+        id: String,
+        parts: Vec<(String, Vec<String>, Vec<String>)>,
+        keep_original: bool,
+        strict: bool,
+        copy_attributes: bool,
+    ) -> PyResult<()> {
+        self.ensure_mutable()?;
+        self.mode.require_directed("split_node")?;
+        let id = self.normalize_id(id)?;
+        let parts = parts
+            .into_iter()
+            .map(|(pid, a, b)| {
+                Ok((
+                    self.normalize_id(pid)?,
+                    self.normalize_ids(a)?,
+                    self.normalize_ids(b)?,
+                ))
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        let original = self
+            .nodes
+            .get(&id)
+            .ok_or_else(|| PyKeyError::new_err(id.clone()))?;
+
+        let mut part_ids = HashSet::new();
+        for (pid, _, _) in &parts {
+            if *pid == id || self.nodes.contains_key(pid) || !part_ids.insert(pid) {
+                return Err(PyValueError::new_err(format!(
+                    "split part id '{}' is not a new, unique node id",
+                    pid
+                )));
+            }
+        }
+
+        let h = self.nodes.lookup(&id);
+        let sources: HashSet<String> = self
+            .nodes
+            .iter()
+            .filter(|(_, n)| n.edge_index(h).is_some())
+            .map(|(k, _)| k.clone())
+            .collect();
+        let mut assigned_in = HashSet::new();
+        let mut assigned_out = HashSet::new();
+        for (pid, ins, outs) in &parts {
+            for src in ins {
+                if !sources.contains(src) {
+                    return Err(PyValueError::new_err(format!(
+                        "part '{}': {} -> {} is not an existing in-edge",
+                        pid, src, id
+                    )));
+                }
+                assigned_in.insert(src.as_str());
+            }
+            for dst in outs {
+                if original.edge_index(self.nodes.lookup(dst)).is_none() {
+                    return Err(PyValueError::new_err(format!(
+                        "part '{}': {} -> {} is not an existing out-edge",
+                        pid, id, dst
+                    )));
+                }
+                assigned_out.insert(dst.as_str());
+            }
+        }
+        if strict && !keep_original {
+            let mut unassigned: Vec<String> = sources
+                .iter()
+                .filter(|s| !assigned_in.contains(s.as_str()))
+                .map(|s| format!("{} -> {}", s, id))
+                .chain(
+                    self.nodes
+                        .targets(original)
+                        .filter(|d| !assigned_out.contains(d.as_str()))
+                        .map(|d| format!("{} -> {}", id, d)),
+                )
+                .collect();
+            if !unassigned.is_empty() {
+                unassigned.sort();
+                return Err(PyValueError::new_err(format!(
+                    "split of '{}' leaves edges unassigned: {}",
+                    id,
+                    unassigned.join(", ")
+                )));
+            }
+        }
+
+        if self.enforce_schema {
+            for (pid, ins, outs) in &parts {
+                let kind = self
+                    .schema
+                    .kind_of(pid, copy_attributes.then_some(&original.attributes));
+                for src in ins {
+                    let label = self.edge_label(src, &id);
+                    self.check_schema_kinds(src, self.kind_of(src), pid, kind, label)?;
+                }
+                for dst in outs {
+                    let label = self.edge_label(&id, dst);
+                    self.check_schema_kinds(pid, kind, dst, self.kind_of(dst), label)?;
+                }
+            }
+        }
+
+        self.record_mutation(|| GraphEvent::SplitNode {
+            id: id.clone(),
+            parts: parts.clone(),
+            keep_original,
+            strict,
+            copy_attributes,
+        });
+
+        if let Some(store) = self.provenance.as_mut() {
+            for (pid, ins, outs) in &parts {
+                for src in ins {
+                    store.copy(self.nodes.ids_mut(), src, &id, src, pid);
+                }
+                for dst in outs {
+                    store.copy(self.nodes.ids_mut(), &id, dst, pid, dst);
+                }
+            }
+        }
+
+        for (pid, _, _) in &parts {
+            self.nodes.intern(pid);
+        }
+        let Some(original) = self.nodes.get(&id) else {
+            return Err(PyKeyError::new_err(id));
+        };
+        let mut new_nodes = Vec::with_capacity(parts.len());
+        for (_, _, outs) in &parts {
+            let mut node = CausalNode::new();
+            for dst in outs {
+                let to = self.nodes.lookup(dst);
+                let i = original.edge_index(to).unwrap_or_default();
+                node.push_labeled_edge(
+                    to,
+                    original.weights[i],
+                    original.observed_at[i],
+                    original.labels[i].clone(),
+                );
+            }
+            if copy_attributes {
+                node.attributes = original.attributes.clone();
+            }
+            new_nodes.push(node);
+        }
+
+        // Re-point in-edges at their parts before the original is touched.
+        for src in &sources {
+            let receivers: Vec<u32> = parts
+                .iter()
+                .filter(|(_, ins, _)| ins.contains(src))
+                .map(|(pid, _, _)| self.nodes.lookup(pid))
+                .collect();
+            let Some(node) = self.nodes.get_mut(src) else {
+                continue;
+            };
+            let Some(i) = node.edge_index(h) else {
+                continue;
+            };
+            let (w, ts, label) = (node.weights[i], node.observed_at[i], node.labels[i].clone());
+            for &pid in &receivers {
+                node.push_labeled_edge(pid, w, ts, label.clone());
+            }
+            if !keep_original || !receivers.is_empty() {
+                node.retain_edges(|to, _, _| to != h);
+            }
+        }
+
+        if keep_original {
+            let assigned_out: HashSet<u32> =
+                assigned_out.iter().map(|d| self.nodes.lookup(d)).collect();
+            if let Some(node) = self.nodes.get_mut(&id) {
+                node.retain_edges(|to, _, _| !assigned_out.contains(&to));
+            }
+        } else {
+            self.nodes.remove(&id);
+        }
+        for ((pid, _, _), node) in parts.iter().zip(new_nodes) {
+            self.tombstones.remove(pid);
+            self.nodes.insert(pid, node);
+        }
+        self.sync_provenance();
+        Ok(())
+    }
+
+    // ------------------------------------------------------------------------
+    // Freezing
+    // ------------------------------------------------------------------------
+
+    /// Makes the graph read-only: every mutator raises FrozenGraphError until
+    /// `unfreeze()`, while reads work normally. `consumer_policy` decides what happens
+    /// to events arriving through `submit_events` (the consumer path) meanwhile:
+    /// "buffer" queues them for `unfreeze`, "pause" rejects them so the consumer
+    /// holds its position and retries.
+    #[pyo3(signature = (consumer_policy = "buffer"))]
+    fn freeze(&mut self, consumer_policy: &str) -> PyResult<()> {
+        let buffer = match consumer_policy {
+            "buffer" => true,
+            "pause" => false,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown consumer policy '{}' (expected buffer or pause)",
+                    other
+                )))
+            }
+        };
+        if self.frozen.is_some() {
+            return Err(PyValueError::new_err("graph is already frozen"));
+        }
+        self.frozen = Some(Freeze {
+            version: self.version,
+            buffer,
+            buffered: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Lifts the freeze and applies buffered events in arrival order. Returns
+    /// `{"frozen_version", "buffered", "applied", "failed"}`; events that fail are
+    /// dropped after the others have been applied.
+    fn unfreeze(&mut self, py: Python) -> PyResult<PyObject> {
+        let Some(freeze) = self.frozen.take() else {
+            return Err(PyValueError::new_err("graph is not frozen"));
+        };
+        let buffered = freeze.buffered.len();
+        let mut failed = 0;
+        let mut events = freeze.buffered.into_iter().peekable();
+        while let Some(result) = events::apply_next(&mut events, self) {
+            if result.is_err() {
+                failed += 1;
+            }
+        }
+        let d = PyDict::new(py);
+        d.set_item("frozen_version", freeze.version)?;
+        d.set_item("buffered", buffered)?;
+        d.set_item("applied", buffered - failed)?;
+        d.set_item("failed", failed)?;
+        Ok(d.into())
+    }
+
+    fn is_frozen(&self) -> bool {
+        self.frozen.is_some()
+    }
+
+    /// Entry point for streamed events (`event_log` format). Applies them like
+    /// `replay_events`, unless the graph is frozen: then they are buffered, or
+    /// rejected with FrozenGraphError under the "pause" policy. Returns how many
+    /// events were applied now.
+    fn submit_events(&mut self, events: Vec<&PyAny>) -> PyResult<usize> {
+        let parsed = parse_events(&events)?;
+        self.submit(parsed)
+    }
+
+    // ------------------------------------------------------------------------
+    // Transactions
+    // ------------------------------------------------------------------------
+
+    /// Opens a transaction: every mutation from here on is kept by `commit()` or
+    /// undone together by `rollback()`. Each node saves its old state the first time
+    /// it changes, so a transaction costs what it touches rather than a copy of the
+    /// graph. Operations that rewrite every node (`decay_weights`,
+    /// `prune_edges_below`, `repair`, `remove_node` with `prune_edges`) save all of
+    /// them once. Transactions do not nest: raises ValueError if one is already open.
+    fn begin_transaction(&mut self) -> PyResult<()> {
+        if self.transaction.is_some() {
+            return Err(PyValueError::new_err("a transaction is already open"));
+        }
+        self.nodes.begin_journal();
+        if let Some(store) = self.provenance.as_mut() {
+            store.begin_journal();
+        }
+        self.transaction = Some(Transaction {
+            tombstones: self.tombstones.clone(),
+            original_ids: Vec::new(),
+            schema: self.schema.clone(),
+            new_cycles: self.new_cycles.clone(),
+            pending_cycle_edges: self.pending_cycle_edges.clone(),
+            logged: self.events.as_ref().map(Vec::len),
+            version: self.version,
+        });
+        Ok(())
+    }
+
+    /// Keeps the mutations of the open transaction and closes it. Raises ValueError
+    /// if no transaction is open.
+    fn commit(&mut self) -> PyResult<()> {
+        if self.transaction.take().is_none() {
+            return Err(PyValueError::new_err("no transaction is open"));
+        }
+        self.nodes.commit_journal();
+        if let Some(store) = self.provenance.as_mut() {
+            store.commit_journal();
+        }
+        Ok(())
+    }
+
+    /// Undoes every mutation since `begin_transaction()` and closes the transaction:
+    /// nodes, edges with their weights, labels and timestamps, attributes,
+    /// tombstones, provenance, original ids, schema declarations, cycle reports and
+    /// the event log are as they were then. The version still moves forward, so
+    /// cursors opened inside the transaction go stale. Invalid-fact marks and heat
+    /// counters are not graph state and are kept. Raises FrozenGraphError while
+    /// frozen (the transaction stays open) and ValueError if no transaction is open.
+    fn rollback(&mut self) -> PyResult<()> {
+        self.ensure_mutable()?;
+        let Some(transaction) = self.transaction.take() else {
+            return Err(PyValueError::new_err("no transaction is open"));
+        };
+        self.nodes.rollback_journal();
+        if let Some(store) = self.provenance.as_mut() {
+            store.rollback_journal();
+        }
+        self.tombstones = transaction.tombstones;
+        for id in transaction.original_ids {
+            self.original_ids.remove(&id);
+        }
+        self.schema = transaction.schema;
+        self.new_cycles = transaction.new_cycles;
+        self.pending_cycle_edges = transaction.pending_cycle_edges;
+        if let (Some(log), Some(len)) = (self.events.as_mut(), transaction.logged) {
+            log.truncate(len);
+        }
+        if self.version != transaction.version {
+            self.version += 1;
+            self.components
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner())
+                .invalidate();
+            self.dirty.reopen();
+        }
+        Ok(())
+    }
+
+    fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    // ------------------------------------------------------------------------
+    // Heat Counters
+    // ------------------------------------------------------------------------
+
+    /// The `k` nodes that appeared most often in `propagate_invalidation` results,
+    /// as `(id, hits)` sorted by hits then id. Empty unless `track_heat` is on.
+    fn hot_nodes(&self, k: usize) -> Vec<(String, u64)> {
+        self.heat.as_ref().map_or_else(Vec::new, |h| h.top(k))
+    }
+
+    fn reset_heat(&self) {
+        if let Some(heat) = &self.heat {
+            heat.reset();
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Tombstones
+    // ------------------------------------------------------------------------
+
+    /// Removes a node from traversal and analysis while remembering that it was deleted.
+    /// Edges from other nodes are kept (so re-adding the id restores them) but are not
+    /// followed while the tombstone exists. Returns false if the node did not exist.
+    #[pyo3(signature = (id, ts = None))]
+    fn tombstone_node(&mut self, id: String, ts: Option<f64>) -> PyResult<bool> {
+        self.ensure_mutable()?;
+        let id = self.normalize_id(id)?;
+        let Some(node) = self.nodes.remove(&id) else {
+            return Ok(false);
+        };
+        self.forget_edges(&id, &node, None);
+        let ts = ts.unwrap_or_else(now_secs);
+        self.record_mutation(|| GraphEvent::Tombstone { id: id.clone(), ts });
+        self.tombstones.insert(id, ts);
+        Ok(true)
+    }
+
+    /// Tombstoned ids with their deletion timestamps, sorted by id.
+    fn tombstones(&self) -> Vec<(String, f64)> {
+        let mut out: Vec<(String, f64)> = self
+            .tombstones
+            .iter()
+            .map(|(id, ts)| (id.clone(), *ts))
+            .collect();
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        out
+    }
+
+    /// Forgets tombstones recorded before `older_than`; returns how many were purged.
+    fn purge_tombstones(&mut self, older_than: f64) -> PyResult<usize> {
+        self.ensure_mutable()?;
+        self.record_mutation(|| GraphEvent::PurgeTombstones { older_than });
+        let before = self.tombstones.len();
+        self.tombstones.retain(|_, ts| *ts >= older_than);
+        Ok(before - self.tombstones.len())
+    }
+
+    // ------------------------------------------------------------------------
+    // Node Attributes
+    // ------------------------------------------------------------------------
+
+    /// Sets attribute `key` on each node in `ids` to the aligned entry of `values`.
+    /// Unknown ids raise KeyError (listing the first few) when `strict`, otherwise
+    /// they are skipped. Returns the number of skipped ids.
+    #[pyo3(signature = (ids, key, values, strict = true))]
+    fn set_attrs_bulk(
+        &mut self,
+        ids: Vec<String>,
+        key: String,
+        values: Vec<String>,
+        strict: bool,
+    ) -> PyResult<usize> {
+        if ids.len() != values.len() {
+            return Err(PyValueError::new_err(format!(
+                "ids and values must have the same length ({} != {})",
+                ids.len(),
+                values.len()
+            )));
+        }
+        self.apply_attrs(ids, key, values, strict)
+    }
+
+    /// Numeric variant of `set_attrs_bulk` taking a float64 numpy array aligned with `ids`.
+    #[pyo3(signature = (ids, key, values, strict = true))]
+    fn set_numeric_attrs_bulk(
+        &mut self,
+        ids: Vec<String>,
+        key: String,
+        values: &PyAny,
+        strict: bool,
+    ) -> PyResult<usize> {
+        require_numpy(values.py())?;
+        let values: PyReadonlyArray1<f64> = values.extract()?;
+        let values = values.as_array();
+        if ids.len() != values.len() {
+            return Err(PyValueError::new_err(format!(
+                "ids and values must have the same length ({} != {})",
+                ids.len(),
+                values.len()
+            )));
+        }
+        let values = values.iter().map(|v| v.to_string()).collect();
+        self.apply_attrs(ids, key, values, strict)
+    }
+
+    /// Sets attribute `key` of node `id` to `value`. Raises NodeNotFoundError if the
+    /// node does not exist.
+    fn set_attribute(&mut self, id: String, key: String, value: String) -> PyResult<()> {
+        let id = self.normalize_id(id)?;
+        if !self.nodes.contains_key(&id) {
+            return Err(NodeNotFoundError::new_err(id));
+        }
+        self.apply_normalized_attrs(vec![id], key, vec![value], true)
+            .map(|_| ())
+    }
+
+    /// All attributes of node `id`. Raises NodeNotFoundError if the node does not
+    /// exist.
+    fn get_attributes(&self, id: &str) -> PyResult<HashMap<String, String>> {
+        let id = self.lookup_str(id)?;
+        self.nodes
+            .get(&id)
+            .map(|node| node.attributes.clone())
+            .ok_or_else(|| NodeNotFoundError::new_err(id.to_string()))
+    }
+
+    /// Returns attribute `key` for each id, aligned with `ids`; None where the node
+    /// or the attribute is absent.
+    fn get_attrs_bulk(&self, ids: Vec<String>, key: String) -> PyResult<Vec<Option<String>>> {
+        Ok(self
+            .lookup_ids(ids)?
+            .iter()
+            .map(|id| self.nodes.get(id)?.attributes.get(&key).cloned())
+            .collect())
+    }
+
+    // ------------------------------------------------------------------------
+    // Event Log
+    // ------------------------------------------------------------------------
+
+    /// The recorded mutation events as dicts tagged by `op`. Raises ValueError unless
+    /// the walker was created with `record_events=True`.
+    fn event_log(&self, py: Python) -> PyResult<Vec<PyObject>> {
+        self.recorded_events()?
+            .iter()
+            .map(|ev| {
+                let value = serde_json::to_value(ev)
+                    .map_err(|e| SerializationError::new_err(e.to_string()))?;
+                pyjson::to_py(py, &value)
+            })
+            .collect()
+    }
+
+    /// Applies a list of event dicts (in `event_log` format) in order. All events are
+    /// parsed before any is applied. With `enforce_dag` or `enforce_schema` the batch
+    /// is atomic: if any event fails, the graph is restored to its state before the
+    /// call. Otherwise events before the failing one remain applied.
+    fn replay_events(&mut self, events: Vec<&PyAny>) -> PyResult<()> {
+        self.ensure_mutable()?;
+        let parsed = parse_events(&events)?;
+
+        let checkpoint = self.checkpoint();
+        let mut parsed = parsed.into_iter().peekable();
+        while let Some(result) = events::apply_next(&mut parsed, self) {
+            if let Err(e) = result {
+                if let Some(checkpoint) = checkpoint {
+                    self.restore_checkpoint(checkpoint);
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replays the event log into a single evolving walker, sampling `metrics` every
+    /// `interval_events` events and once more at the end. Returns one dict per sample
+    /// with an `events` key plus the requested metrics. Cheap metrics (the default
+    /// set): `nodes`, `edges`, `avg_degree`, `components` (weakly connected).
+    /// Expensive, opt-in: `largest_scc`.
+    #[pyo3(signature = (interval_events, metrics = None))]
+    fn history_metrics(
+        &self,
+        py: Python,
+        interval_events: usize,
+        metrics: Option<Vec<String>>,
+    ) -> PyResult<Vec<PyObject>> {
+        const SUPPORTED: [&str; 5] = ["nodes", "edges", "avg_degree", "components", "largest_scc"];
+        if interval_events == 0 {
+            return Err(PyValueError::new_err("interval_events must be positive"));
+        }
+        let metrics =
+            metrics.unwrap_or_else(|| SUPPORTED[..4].iter().map(|m| m.to_string()).collect());
+        if let Some(bad) = metrics.iter().find(|m| !SUPPORTED.contains(&m.as_str())) {
+            return Err(PyValueError::new_err(format!(
+                "unknown metric '{}' (supported: {})",
+                bad,
+                SUPPORTED.join(", ")
+            )));
+        }
+
+        if metrics.iter().any(|m| m == "largest_scc") {
+            self.mode.require_directed("largest_scc")?;
+        }
+        let log = self.recorded_events()?;
+        let mut replica = GraphWalker::blank();
+        replica.mode = self.mode;
+        replica.enforce_dag = self.enforce_dag;
+        let mut samples = Vec::new();
+        for (i, ev) in log.iter().enumerate() {
+            ev.clone().apply(&mut replica)?;
+            let applied = i + 1;
+            if applied % interval_events == 0 || applied == log.len() {
+                samples.push(replica.sample_metrics(py, applied, &metrics)?);
+            }
+        }
+        Ok(samples)
+    }
+
+    // ------------------------------------------------------------------------
+    // Schema
+    // ------------------------------------------------------------------------
+
+    /// Declares node kind `kind` for ids matching `id_pattern` (`*` and `?` globs).
+    /// Kinds are tried in declaration order; a node's `kind` attribute overrides them.
+    fn define_node_kind(&mut self, kind: String, id_pattern: String) -> PyResult<()> {
+        self.ensure_mutable()?;
+        self.schema.define_kind(kind, id_pattern);
+        Ok(())
+    }
+
+    /// Allows edges from `from_kind` nodes to `to_kind` nodes. Once any rule exists,
+    /// edges between kinded nodes must match one. `label=None` matches any label.
+    #[pyo3(signature = (from_kind, to_kind, label = None))]
+    fn allow_edge(
+        &mut self,
+        from_kind: String,
+        to_kind: String,
+        label: Option<String>,
+    ) -> PyResult<()> {
+        self.ensure_mutable()?;
+        self.schema.allow(from_kind, to_kind, label);
+        Ok(())
+    }
+
+    /// Audits the current graph against the schema, enforced or not. Returns one
+    /// `{"from", "to", "from_kind", "to_kind", "reason"}` dict per violating edge,
+    /// sorted by (from, to). Edges into tombstoned ids are skipped.
+    fn validate_schema(&self, py: Python) -> PyResult<Vec<PyObject>> {
+        let mut out = Vec::new();
+        for (id, node) in self.nodes.sorted_nodes() {
+            let from_kind = self.kind_of(id);
+            let mut deps: Vec<(&String, Option<&str>)> = self
+                .nodes
+                .targets(node)
+                .zip(node.labels.iter().map(Option::as_deref))
+                .filter(|(d, _)| !self.tombstones.contains_key(*d))
+                .collect();
+            deps.sort();
+            for (dep, label) in deps {
+                let to_kind = self.kind_of(dep);
+                if let Err(reason) = self.schema.check_edge(from_kind, to_kind, label) {
+                    let d = PyDict::new(py);
+                    d.set_item("from", id)?;
+                    d.set_item("to", dep)?;
+                    d.set_item("from_kind", from_kind)?;
+                    d.set_item("to_kind", to_kind)?;
+                    d.set_item("reason", reason)?;
+                    out.push(d.into());
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    // ------------------------------------------------------------------------
+    // Snapshots
+    // ------------------------------------------------------------------------
+
+    /// Order-independent digest of everything a snapshot persists (see
+    /// `snapshot::content_digest`). With `structural`, edge timestamps, tombstone
+    /// times, provenance and original ids are left out, so two processes that built
+    /// the same graph at different times can compare digests cheaply.
+    #[pyo3(signature = (structural = false))]
+    fn content_digest(&self, structural: bool) -> String {
+        let covered = if structural {
+            snapshot::Covered::structure()
+        } else {
+            snapshot::Covered::all(self)
+        };
+        snapshot::content_digest(self, &covered)
+    }
+
+    /// Writes a checksummed JSON snapshot to `path`. With `include_order`, each node
+    /// record carries its `stable_order()` position as `order`; with
+    /// `include_provenance`, each edge carries its `edge_provenance()` record; with
+    /// `include_original_ids`, nodes whose id was normalized carry the first-seen
+    /// original as `original`.
+    #[pyo3(signature = (
+        path,
+        include_order = false,
+        include_provenance = false,
+        include_original_ids = false
+    ))]
+    fn save_snapshot(
+        &self,
+        path: &str,
+        include_order: bool,
+        include_provenance: bool,
+        include_original_ids: bool,
+    ) -> PyResult<()> {
+        snapshot::write(
+            self,
+            create_buffered(path)?,
+            include_order,
+            include_provenance,
+            include_original_ids,
+        )
+    }
+
+    /// Loads a snapshot written by `save_snapshot`, raising SerializationError if it is
+    /// malformed or its checksum does not match the decoded content. `normalize`
+    /// configures the returned walker's id normalizer (as in the constructor) and
+    /// applies to the loaded ids.
+    #[staticmethod]
+    #[pyo3(signature = (path, normalize = None))]
+    fn load_snapshot(py: Python, path: &str, normalize: Option<&PyAny>) -> PyResult<GraphWalker> {
+        let normalizer = normalize.map(Normalizer::from_py).transpose()?;
+        let data = std::fs::read_to_string(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        GraphWalker::renormalized(py, snapshot::decode(&data)?, normalizer)
+    }
+
+    /// The snapshot of `save_snapshot` (with default options) as a JSON string.
+    fn to_json(&self, py: Python) -> PyResult<String> {
+        py.allow_threads(|| snapshot::encode(self, false, false, false))
+    }
+
+    /// Parses a string produced by `to_json`. Malformed input, an unknown `version`
+    /// or a checksum mismatch raise SerializationError, a ValueError. `normalize` is
+    /// as in `load_snapshot`.
+    #[staticmethod]
+    #[pyo3(signature = (data, normalize = None))]
+    fn from_json(py: Python, data: &str, normalize: Option<&PyAny>) -> PyResult<GraphWalker> {
+        let normalizer = normalize.map(Normalizer::from_py).transpose()?;
+        let raw = py.allow_threads(|| snapshot::decode(data))?;
+        GraphWalker::renormalized(py, raw, normalizer)
+    }
+
+    /// Same as `save_snapshot(path)`.
+    fn save(&self, path: &str) -> PyResult<()> {
+        self.save_snapshot(path, false, false, false)
+    }
+
+    /// Same as `load_snapshot(path, normalize)`.
+    #[staticmethod]
+    #[pyo3(signature = (path, normalize = None))]
+    fn load(py: Python, path: &str, normalize: Option<&PyAny>) -> PyResult<GraphWalker> {
+        GraphWalker::load_snapshot(py, path, normalize)
+    }
+
+    /// Pickle state: the snapshot with provenance and original ids, as bytes, and
+    /// the settings it does not carry (see `settings`). A normalizer with custom
+    /// callables pickles only if they do.
+    fn __getstate__(&self, py: Python) -> PyResult<PyObject> {
+        let data = py.allow_threads(|| snapshot::encode(self, false, true, true))?;
+        Ok((PyBytes::new(py, data.as_bytes()), self.settings(py)?).into_py(py))
+    }
+
+    /// Restores `__getstate__`'s state, or the snapshot bytes alone as written
+    /// before settings were pickled.
+    fn __setstate__(&mut self, py: Python, state: &PyAny) -> PyResult<()> {
+        let (data, settings): (&PyBytes, Option<&PyDict>) = match state.downcast::<PyBytes>() {
+            Ok(data) => (data, None),
+            Err(_) => state.extract()?,
+        };
+        let data = std::str::from_utf8(data.as_bytes())
+            .map_err(|e| SerializationError::new_err(format!("malformed snapshot: {}", e)))?;
+        let mut walker = py.allow_threads(|| snapshot::decode(data))?;
+        if let Some(settings) = settings {
+            walker.apply_settings(settings)?;
+        }
+        *self = walker;
+        Ok(())
+    }
+
+    /// An independent copy, made through the pickle state; the node map cannot be
+    /// shared, so this is a deep copy too. Heat counters start over.
+    fn __copy__(&self, py: Python) -> PyResult<GraphWalker> {
+        let mut copy =
+            py.allow_threads(|| snapshot::decode(&snapshot::encode(self, false, true, true)?))?;
+        copy.apply_settings(self.settings(py)?)?;
+        Ok(copy)
+    }
+
+    fn __deepcopy__(&self, py: Python, _memo: &PyAny) -> PyResult<GraphWalker> {
+        self.__copy__(py)
+    }
+
+    // ------------------------------------------------------------------------
+    // Export
+    // ------------------------------------------------------------------------
+
+    /// The graph in Graphviz DOT (see `export`). With `path`, it is streamed to that
+    /// file and None is returned; otherwise the DOT text is returned. Ids in
+    /// `highlight` are filled with a distinct color.
+    #[pyo3(signature = (path = None, highlight = None))]
+    fn to_dot(
+        &self,
+        py: Python,
+        path: Option<&str>,
+        highlight: Option<Vec<String>>,
+    ) -> PyResult<Option<String>> {
+        let highlight = self.lookup_ids(highlight.unwrap_or_default())?;
+        let highlight: HashSet<&str> = highlight.iter().map(String::as_str).collect();
+        let directed = self.mode.is_directed();
+        py.allow_threads(|| match path {
+            Some(path) => {
+                let mut out = create_buffered(path)?;
+                export::write_dot(&mut out, &self.nodes, directed, &highlight)
+                    .map_err(|e| PyIOError::new_err(e.to_string()))?;
+                Ok(None)
+            }
+            None => {
+                let mut out = Vec::new();
+                export::write_dot(&mut out, &self.nodes, directed, &highlight)
+                    .map_err(|e| PyIOError::new_err(e.to_string()))?;
+                String::from_utf8(out)
+                    .map(Some)
+                    .map_err(|e| PyIOError::new_err(e.to_string()))
+            }
+        })
+    }
+
+    /// Streams the graph as GraphML to `path`, with node attributes, edge weights and
+    /// labels (see `export`). Ids in `highlight` get a `color` attribute.
+    #[pyo3(signature = (path, highlight = None))]
+    fn to_graphml(&self, py: Python, path: &str, highlight: Option<Vec<String>>) -> PyResult<()> {
+        let highlight = self.lookup_ids(highlight.unwrap_or_default())?;
+        let highlight: HashSet<&str> = highlight.iter().map(String::as_str).collect();
+        let directed = self.mode.is_directed();
+        py.allow_threads(|| {
+            let mut out = create_buffered(path)?;
+            export::write_graphml(&mut out, &self.nodes, directed, &highlight)
+                .map_err(|e| PyIOError::new_err(e.to_string()))
+        })
+    }
+
+    /// The adjacency in CSR layout as `(indptr, indices, ids)`, two int64 numpy
+    /// arrays and the id of each row, so `scipy.sparse.csr_matrix((numpy.ones(
+    /// len(indices)), indices, indptr), shape=(len(ids), len(ids)))` is the
+    /// adjacency matrix. Rows are every node and edge target, sorted by id, and
+    /// each row's targets are sorted. A repeated edge is listed twice, and an
+    /// undirected walker lists each edge in both rows.
+    fn to_csr<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<(&'py PyArray1<i64>, &'py PyArray1<i64>, Vec<String>)> {
+        require_numpy(py)?;
+        let (indptr, indices, ids) = py.allow_threads(|| {
+            let (indptr, indices, ids) = export::csr(&self.nodes);
+            (indptr, indices, ids.into_iter().cloned().collect())
+        });
+        Ok((
+            PyArray1::from_vec(py, indptr),
+            PyArray1::from_vec(py, indices),
+            ids,
+        ))
+    }
+
+    /// A new directed walker from CSR arrays as returned by `to_csr`: every id of
+    /// `node_ids` becomes a node, and row `i` gets an edge to `node_ids[j]` for
+    /// each `j` in `indices[indptr[i]:indptr[i + 1]]`, in that order, with weight
+    /// 1. The arrays may be int32 or int64 numpy arrays or integer sequences.
+    /// Raises ValueError unless `indptr` has `len(node_ids) + 1` non-decreasing
+    /// entries from 0 to `len(indices)`, every index is a row, and the ids are
+    /// unique. `normalize` is as in `load_snapshot`; ids that normalize alike are
+    /// merged.
+    #[staticmethod]
+    #[pyo3(signature = (indptr, indices, node_ids, normalize = None))]
+    fn from_csr(
+        py: Python,
+        indptr: &PyAny,
+        indices: &PyAny,
+        node_ids: Vec<String>,
+        normalize: Option<&PyAny>,
+    ) -> PyResult<GraphWalker> {
+        let normalizer = normalize.map(Normalizer::from_py).transpose()?;
+        let indptr = int_vec(indptr)?;
+        let indices = int_vec(indices)?;
+        let raw = py.allow_threads(|| {
+            let n = node_ids.len();
+            if indptr.len() != n + 1 {
+                return Err(PyValueError::new_err(format!(
+                    "indptr has {} entries, expected len(node_ids) + 1 = {}",
+                    indptr.len(),
+                    n + 1
+                )));
+            }
+            if indptr[0] != 0
+                || indptr[n] != indices.len() as i64
+                || indptr.windows(2).any(|w| w[0] > w[1])
+            {
+                return Err(PyValueError::new_err(format!(
+                    "indptr must be non-decreasing from 0 to len(indices) = {}",
+                    indices.len()
+                )));
+            }
+            if let Some(&bad) = indices.iter().find(|&&j| j < 0 || j >= n as i64) {
+                return Err(PyValueError::new_err(format!(
+                    "index {} is out of range for {} rows",
+                    bad, n
+                )));
+            }
+            let mut walker = GraphWalker::blank();
+            walker.nodes.reserve(n);
+            // Interned in order, so row `i` gets handle `i`.
+            for (i, id) in node_ids.iter().enumerate() {
+                if walker.nodes.intern(id) as usize != i {
+                    return Err(PyValueError::new_err(format!("duplicate node id '{}'", id)));
+                }
+            }
+            let built: Vec<CausalNode> = config::install(|| {
+                indptr
+                    .par_windows(2)
+                    .map(|w| {
+                        let row = &indices[w[0] as usize..w[1] as usize];
+                        CausalNode::with_dependents(row.iter().map(|&j| j as u32).collect())
+                    })
+                    .collect()
+            });
+            for (h, node) in built.into_iter().enumerate() {
+                walker.nodes.insert_at(h as u32, node);
+            }
+            Ok(walker)
+        })?;
+        GraphWalker::renormalized(py, raw, normalizer)
+    }
+
+    /// The graph as `(nodes, edges)` for networkx: `G.add_nodes_from(nodes)` and
+    /// `G.add_edges_from(edges)` rebuild it in an `nx.DiGraph`. Nodes are
+    /// `(id, attributes)` pairs sorted by id, isolated nodes included. Edges are
+    /// `(from, to, data)` sorted by endpoints, where `data` holds `weight`,
+    /// `observed_at` and, on labeled edges, `label`. Edge targets that are not
+    /// nodes appear only in edges. An undirected walker lists each edge both ways.
+    fn to_networkx_edges(&self, py: Python) -> PyResult<(Vec<AttributedNode>, Vec<PyObject>)> {
+        let ids = self.nodes.sorted_keys();
+        let nodes = ids
+            .iter()
+            .map(|&id| {
+                let attributes = self.nodes.get(id).map(|n| n.attributes.clone());
+                (id.clone(), attributes.unwrap_or_default())
+            })
+            .collect();
+        let mut edges = Vec::with_capacity(
+            ids.iter()
+                .filter_map(|&id| self.nodes.get(id))
+                .map(|n| n.dependents.len())
+                .sum(),
+        );
+        // One Python string per id, shared by every edge that names it.
+        let mut names: Vec<Option<PyObject>> = vec![None; self.nodes.ids().len()];
+        let mut name = |h: u32| {
+            names[h as usize]
+                .get_or_insert_with(|| self.nodes.name(h).to_object(py))
+                .clone_ref(py)
+        };
+        for &id in &ids {
+            let h = self.nodes.lookup(id);
+            let Some(node) = self.nodes.node(h) else {
+                continue;
+            };
+            let mut order: Vec<usize> = (0..node.dependents.len()).collect();
+            order.sort_by_key(|&i| self.nodes.name(node.dependents[i]));
+            for i in order {
+                let data = PyDict::new(py);
+                data.set_item(intern!(py, "weight"), node.weights[i])?;
+                data.set_item(intern!(py, "observed_at"), node.observed_at[i])?;
+                if let Some(label) = &node.labels[i] {
+                    data.set_item(intern!(py, "label"), label)?;
+                }
+                edges.push((name(h), name(node.dependents[i]), data).to_object(py));
+            }
+        }
+        Ok((nodes, edges))
+    }
+
+    /// A new directed walker from networkx-style lists, so
+    /// `GraphWalker.from_edge_list(list(G.nodes(data=True)), list(G.edges(data=True)))`
+    /// copies `G`; plain `G.nodes` and `G.edges` work too. A node is an id or an
+    /// `(id, attributes)` pair, and becomes a node even without edges. Attribute
+    /// values that are not strings are stored as `str(value)`. An edge is a
+    /// `(from, to)` tuple, optionally with a third element: a weight, None, or a dict
+    /// whose `weight`, `label` and `observed_at` entries are kept (other keys are
+    /// ignored). Edge sources become nodes; targets become nodes only if listed in
+    /// `nodes`. A repeated edge updates the first one, as in networkx. `normalize`
+    /// is as in `load_snapshot`.
+    #[staticmethod]
+    #[pyo3(signature = (nodes, edges, normalize = None))]
+    fn from_edge_list(
+        nodes: &PyAny,
+        edges: &PyAny,
+        normalize: Option<&PyAny>,
+    ) -> PyResult<GraphWalker> {
+        let normalizer = normalize.map(Normalizer::from_py).transpose()?;
+        let mut walker = GraphWalker::blank();
+        for item in nodes.iter()? {
+            let item = item?;
+            let (id, attributes) = match item.extract::<&str>() {
+                Ok(id) => (id, None),
+                Err(_) => {
+                    let (id, attributes): (&str, &PyDict) = item.extract()?;
+                    (id, Some(attributes))
+                }
+            };
+            let h = walker.nodes.intern(id);
+            let node = walker.nodes.get_or_insert_at(h);
+            for (key, value) in attributes.into_iter().flatten() {
+                node.attributes
+                    .insert(key.str()?.to_string(), value.str()?.to_string());
+            }
+        }
+        let now = now_secs();
+        let mut batch = EdgeBatch::default();
+        for item in edges.iter()? {
+            let item: &PyTuple = item?.downcast()?;
+            if item.len() != 2 && item.len() != 3 {
+                return Err(PyValueError::new_err(format!(
+                    "an edge is (from, to) or (from, to, data), got {} elements",
+                    item.len()
+                )));
+            }
+            let from: &str = item.get_item(0)?.extract()?;
+            let to: &str = item.get_item(1)?.extract()?;
+            let (mut weight, mut label, mut ts) = (1.0, None, now);
+            if item.len() == 3 {
+                let data = item.get_item(2)?;
+                if let Ok(data) = data.downcast::<PyDict>() {
+                    if let Some(w) = data.get_item("weight")? {
+                        weight = w.extract::<Option<f64>>()?.unwrap_or(1.0);
+                    }
+                    if let Some(l) = data.get_item("label")? {
+                        label = l.extract()?;
+                    }
+                    if let Some(t) = data.get_item("observed_at")? {
+                        ts = t.extract()?;
+                    }
+                } else if let Some(w) = data.extract::<Option<f64>>()? {
+                    weight = w;
+                }
+            }
+            check_weight(weight)?;
+            batch.push(&mut walker.nodes, from, to, weight, label, ts);
+        }
+        batch.finish(&mut walker.nodes);
+        GraphWalker::renormalized(nodes.py(), walker, normalizer)
+    }
+
+    /// A new directed walker from an edge-list file such as a CSV dump, read in Rust
+    /// (see `edgefile` for the format). Each row adds the edge `source_col ->
+    /// target_col`; columns are 0-based positions or, with a header, names.
+    /// `weight_col` sets the edge weight, 1 when absent or empty; a repeated edge
+    /// takes the last weight. Gzip-compressed files are detected from their
+    /// content. A malformed row raises SerializationError with the file and line.
+    /// `normalize` is as in `load_snapshot`.
+    #[staticmethod]
+    #[pyo3(signature = (
+        path,
+        delimiter = ",",
+        has_header = true,
+        source_col = None,
+        target_col = None,
+        weight_col = None,
+        normalize = None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn from_edge_file(
+        py: Python,
+        path: &str,
+        delimiter: &str,
+        has_header: bool,
+        source_col: Option<&PyAny>,
+        target_col: Option<&PyAny>,
+        weight_col: Option<&PyAny>,
+        normalize: Option<&PyAny>,
+    ) -> PyResult<GraphWalker> {
+        let options = edge_file_options(delimiter, has_header, source_col, target_col, weight_col)?;
+        let normalizer = normalize.map(Normalizer::from_py).transpose()?;
+        let raw = py.allow_threads(|| -> PyResult<GraphWalker> {
+            let mut walker = GraphWalker::blank();
+            let now = now_secs();
+            let mut batch = EdgeBatch::default();
+            edgefile::read(path, &options, |from, to, weight| {
+                batch.push(
+                    &mut walker.nodes,
+                    from,
+                    to,
+                    weight.unwrap_or(1.0),
+                    None,
+                    now,
+                );
+                Ok(())
+            })?;
+            batch.finish(&mut walker.nodes);
+            Ok(walker)
+        })?;
+        GraphWalker::renormalized(py, raw, normalizer)
+    }
+
+    /// Adds the edges of an edge-list file to this walker, as `add_edge(source,
+    /// target, weight)` per row, so normalization, DAG and schema checks, events and
+    /// provenance apply. Options are those of `from_edge_file`. The whole file is
+    /// parsed first, so a malformed row changes nothing, and with `enforce_dag` or
+    /// `enforce_schema` a rejected edge leaves the graph as it was. Returns the
+    /// number of rows.
+    #[pyo3(signature = (
+        path,
+        delimiter = ",",
+        has_header = true,
+        source_col = None,
+        target_col = None,
+        weight_col = None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn append_edge_file(
+        &mut self,
+        py: Python,
+        path: &str,
+        delimiter: &str,
+        has_header: bool,
+        source_col: Option<&PyAny>,
+        target_col: Option<&PyAny>,
+        weight_col: Option<&PyAny>,
+    ) -> PyResult<usize> {
+        self.ensure_mutable()?;
+        let options = edge_file_options(delimiter, has_header, source_col, target_col, weight_col)?;
+        py.allow_threads(|| {
+            let mut rows = Vec::new();
+            edgefile::read(path, &options, |from, to, weight| {
+                rows.push((from.to_string(), to.to_string(), weight));
+                Ok(())
+            })?;
+            let count = rows.len();
+            let checkpoint = self.checkpoint();
+            let result = rows
+                .into_iter()
+                .try_for_each(|(from, to, weight)| self.add_edge(from, to, weight));
+            if let (Err(_), Some(checkpoint)) = (&result, checkpoint) {
+                self.restore_checkpoint(checkpoint);
+            }
+            result.map(|_| count)
+        })
+    }
+
+    /// Builds a graph from a log-compacted topic (see `coldstart` for the message
+    /// format), reading each partition only up to the high watermark captured at
+    /// start. `brokers` is a bootstrap server list, or a `MemoryBroker`. Returns
+    /// `(walker, {partition: end_offset})`; a live consumer should resume from those
+    /// offsets. `config` adds librdkafka settings and `progress(read, total)` is
+    /// called as messages arrive. Raises TimeoutError if the load does not finish
+    /// within `timeout_ms`. `normalize` configures the returned walker's id
+    /// normalizer (as in the constructor) and applies to the loaded ids.
+    #[staticmethod]
+    #[pyo3(signature = (
+        brokers,
+        topic,
+        config = None,
+        timeout_ms = 60_000,
+        progress = None,
+        normalize = None
+    ))]
+    fn from_compacted_topic(
+        py: Python,
+        brokers: &PyAny,
+        topic: &str,
+        config: Option<HashMap<String, String>>,
+        timeout_ms: u64,
+        progress: Option<&PyAny>,
+        normalize: Option<&PyAny>,
+    ) -> PyResult<(GraphWalker, std::collections::BTreeMap<i32, i64>)> {
+        coldstart::load(
+            py,
+            &coldstart::Brokers::from_py(brokers, config.unwrap_or_default())?,
+            topic,
+            timeout_ms,
+            progress,
+            normalize.map(Normalizer::from_py).transpose()?,
+        )
+    }
+
+    /// Parallel `from_compacted_topic`: `workers` consumers each read a round-robin
+    /// subset of the partitions, keys read by several workers keep the latest
+    /// record by message timestamp, and the partial graphs are merged, so the
+    /// result matches a sequential load. Returns `(walker, {partition: end_offset})`. `progress(read, total,
+    /// workers)` is called about every 250ms with one dict per worker (`partitions`,
+    /// `read`, `total`, `messages_per_sec`). `workers` is capped at the number of
+    /// non-empty partitions. Raises TimeoutError if the load does not finish within
+    /// `timeout_ms`.
+    #[staticmethod]
+    #[pyo3(signature = (
+        brokers,
+        topic,
+        config = None,
+        workers = 4,
+        timeout_ms = 600_000,
+        progress = None,
+        normalize = None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn parallel_cold_start(
+        py: Python,
+        brokers: &PyAny,
+        topic: &str,
+        config: Option<HashMap<String, String>>,
+        workers: usize,
+        timeout_ms: u64,
+        progress: Option<&PyAny>,
+        normalize: Option<&PyAny>,
+    ) -> PyResult<(GraphWalker, std::collections::BTreeMap<i32, i64>)> {
+        coldstart::load_parallel(
+            py,
+            &coldstart::Brokers::from_py(brokers, config.unwrap_or_default())?,
+            topic,
+            workers,
+            timeout_ms,
+            progress,
+            normalize.map(Normalizer::from_py).transpose()?,
+        )
+    }
+
+    // ------------------------------------------------------------------------
+    // Graph Analysis Algorithms
+    // ------------------------------------------------------------------------
+
+    /// Detects circular dependencies using DFS: one cycle per back edge found,
+    /// each rotated to start at its smallest id, deduplicated and sorted. With
+    /// `max_cycles`, finding more distinct cycles than that raises
+    /// ResourceLimitError with args `(message, max_cycles, max_cycles)`.
+    #[pyo3(signature = (max_cycles = None))]
+    fn detect_cycles(&self, py: Python, max_cycles: Option<usize>) -> PyResult<Vec<Vec<String>>> {
+        py.allow_threads(|| {
+            self.mode.require_directed("detect_cycles")?;
+            let cap = max_cycles.unwrap_or(usize::MAX);
+            let mut cycles = BTreeSet::new();
+            let mut visited = HashSet::new();
+            let mut recursion_stack = HashSet::new();
+            let mut path = Vec::new();
+
+            let mut starts: Vec<(&String, u32)> = self
+                .nodes
+                .handles()
+                .map(|(h, _)| (self.nodes.name(h), h))
+                .collect();
+            starts.sort_unstable();
+            for (_, node_id) in starts {
+                if !visited.contains(&node_id) {
+                    let within_cap = self.dfs_cycle(
+                        node_id,
+                        &mut visited,
+                        &mut recursion_stack,
+                        &mut path,
+                        &mut cycles,
+                        cap,
+                    );
+                    if !within_cap {
+                        return Err(ResourceLimitError::new_err((
+                            format!("max_cycles limit of {} exceeded", cap),
+                            cap,
+                            cap,
+                        )));
+                    }
+                }
+            }
+            Ok(cycles.into_iter().collect())
+        })
+    }
+
+    /// Nodes in dependency order: every node comes after all nodes with an edge to
+    /// it (Kahn's algorithm). Ties are broken by id, so the order is reproducible;
+    /// it is `topological_layers` flattened. Edges to ids that are not nodes are
+    /// ignored. Raises CycleError (a ValueError) naming one cycle if there is any.
+    fn topological_sort(&self, py: Python) -> PyResult<Vec<String>> {
+        self.mode.require_directed("topological_sort")?;
+        Ok(self.topological_layers(py)?.into_iter().flatten().collect())
+    }
+
+    /// Nodes grouped by longest-path depth: layer 0 holds the nodes without
+    /// incoming edges, and a node is in layer `k` if the longest path reaching it
+    /// has `k` edges. Nodes within a layer do not depend on each other and can be
+    /// recomputed in parallel. Layers are sorted; errors as in `topological_sort`.
+    fn topological_layers(&self, py: Python) -> PyResult<Vec<Vec<String>>> {
+        self.mode.require_directed("topological_layers")?;
+        py.allow_threads(|| {
+            let graph = algorithms::IndexedGraph::new(&self.nodes);
+            let ids: Vec<&str> = graph.ids.iter().map(|id| id.as_str()).collect();
+            layered_ids(&ids, &graph.adj)
+        })
+    }
+
+    /// The `propagate_invalidation` result in dependency order, ready for a
+    /// scheduler: each affected node comes after the affected nodes it depends on,
+    /// ties broken by id. Only edges between affected nodes count. Raises CycleError
+    /// if the affected nodes contain a cycle (including one back to `start_id`);
+    /// limits and `strict` as in `propagate_invalidation`.
+    #[pyo3(signature = (start_id, max_results = None, max_visited = None, strict = true))]
+    fn propagate_invalidation_ordered(
+        &self,
+        py: Python,
+        start_id: String,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+        strict: bool,
+    ) -> PyResult<Vec<String>> {
+        self.mode
+            .require_directed("propagate_invalidation_ordered")?;
+        let affected = self.propagate_invalidation(
+            py,
+            start_id,
+            max_results,
+            max_visited,
+            None,
+            strict,
+            None,
+            None,
+        )?;
+        py.allow_threads(|| {
+            let mut ids: Vec<&str> = affected.iter().map(String::as_str).collect();
+            ids.sort_unstable();
+            let index: HashMap<u32, usize> = ids
+                .iter()
+                .enumerate()
+                .map(|(i, id)| (self.nodes.lookup(id), i))
+                .collect();
+            let adj: Vec<Vec<usize>> = ids
+                .iter()
+                .map(|id| {
+                    self.nodes.get(id).map_or_else(Vec::new, |node| {
+                        node.dependents
+                            .iter()
+                            .filter_map(|d| index.get(d).copied())
+                            .collect()
+                    })
+                })
+                .collect();
+            Ok(layered_ids(&ids, &adj)?.into_iter().flatten().collect())
+        })
+    }
+
+    /// Cycles confirmed by the incremental check (see `cycle_check_steps`) since the
+    /// buffer was last cleared, in `detect_cycles` format. With `clear`, the buffer
+    /// is emptied.
+    #[pyo3(signature = (clear = false))]
+    fn new_cycles(&mut self, clear: bool) -> Vec<Vec<String>> {
+        if clear {
+            std::mem::take(&mut self.new_cycles)
+        } else {
+            self.new_cycles.clone()
+        }
+    }
+
+    /// Edges whose incremental cycle check ran out of budget, awaiting
+    /// `verify_pending_cycles`.
+    fn pending_cycle_edges(&self) -> Vec<(String, String)> {
+        self.pending_cycle_edges.clone()
+    }
+
+    /// Checks every pending edge that still exists without a step budget, adds each
+    /// confirmed cycle to `new_cycles()`, clears the pending list and returns how many
+    /// cycles were found.
+    fn verify_pending_cycles(&mut self) -> usize {
+        let pending = std::mem::take(&mut self.pending_cycle_edges);
+        let mut found = 0;
+        for (from, to) in pending {
+            if !self.nodes.has_edge(&from, &to) {
+                continue;
+            }
+            if let PathSearch::Found(cycle) = self.find_path_within(&to, &from, usize::MAX) {
+                self.push_new_cycle(cycle);
+                found += 1;
+            }
+        }
+        found
+    }
+
+    /// Identifies isolated communities using connected components: each id (nodes
+    /// and edge targets) with everything linked to it by edges in either direction,
+    /// found through the reverse adjacency. Members are sorted, communities ordered
+    /// largest first, then by smallest id. With `directed`, the previous behavior:
+    /// from each id not yet assigned, in sorted order, only what its outgoing edges
+    /// reach, so communities can overlap.
+    #[pyo3(signature = (directed = false))]
+    fn detect_communities(&self, py: Python, directed: bool) -> Vec<Vec<String>> {
+        py.allow_threads(|| {
+            let reverse = (!directed).then(|| self.reverse_adjacency_cached());
+            let mut communities = Vec::new();
+            let mut global_visited = HashSet::new();
+
+            let mut starts: Vec<(&String, u32)> = self
+                .nodes
+                .handles()
+                .map(|(h, _)| (self.nodes.name(h), h))
+                .collect();
+            starts.sort_unstable();
+            for (_, node_id) in starts {
+                if !global_visited.contains(&node_id) {
+                    let mut community = HashSet::new();
+                    let mut stack = vec![node_id];
+
+                    while let Some(current) = stack.pop() {
+                        if community.insert(current) {
+                            global_visited.insert(current);
+
+                            let outgoing = self.nodes.node(current).map(|n| &n.dependents[..]);
+                            let incoming = reverse.as_ref().map(|r| sources_of(r, current));
+                            for &next in outgoing.into_iter().chain(incoming).flatten() {
+                                if !community.contains(&next) {
+                                    stack.push(next);
+                                }
+                            }
+                        }
+                    }
+                    let mut members: Vec<String> = self.nodes.names(community);
+                    members.sort();
+                    communities.push(members);
+                }
+            }
+            communities.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].cmp(&b[0])));
+            communities
+        })
+    }
+
+    /// Strongly connected components (iterative Tarjan) as sorted id lists, ordered
+    /// by smallest member. Nodes on no cycle form components of one. Cached until
+    /// the next mutation. Directed graphs only.
+    fn strongly_connected_components(&self, py: Python) -> PyResult<Vec<Vec<String>>> {
+        self.mode
+            .require_directed("strongly_connected_components")?;
+        Ok((*self.scc_cached(py)).clone())
+    }
+
+    /// The condensation: a new walker with one node per strongly connected
+    /// component, named after its smallest member and carrying a `scc_size`
+    /// attribute, and an edge between two components wherever any edge joins
+    /// them. The result is a DAG. Edges to ids that are not nodes are dropped.
+    /// Directed graphs only.
+    fn condensation(&self, py: Python) -> PyResult<GraphWalker> {
+        self.mode.require_directed("condensation")?;
+        let components = self.scc_cached(py);
+        let mut component_of: HashMap<u32, usize> = HashMap::new();
+        for (c, members) in components.iter().enumerate() {
+            for id in members {
+                component_of.insert(self.nodes.lookup(id), c);
+            }
+        }
+        let mut edges: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); components.len()];
+        for (h, node) in self.nodes.handles() {
+            let from = component_of[&h];
+            for dep in &node.dependents {
+                if let Some(&to) = component_of.get(dep) {
+                    if to != from {
+                        edges[from].insert(to);
+                    }
+                }
+            }
+        }
+        let mut walker = GraphWalker::blank();
+        for (members, targets) in components.iter().zip(edges) {
+            let dependents = targets
+                .into_iter()
+                .map(|c| components[c][0].clone())
+                .collect();
+            walker.add_node(members[0].clone(), dependents, None, None)?;
+            if let Some(node) = walker.nodes.get_mut(&members[0]) {
+                node.attributes
+                    .insert("scc_size".to_string(), members.len().to_string());
+            }
+        }
+        Ok(walker)
+    }
+
+    /// A new walker with only the nodes in `node_ids` and the edges between them;
+    /// edges leaving the set are dropped. Weights, labels, timestamps, attributes and
+    /// (if tracked) provenance are copied, as are the mode, DAG and schema
+    /// enforcement and the schema. Ids that are not nodes raise NodeNotFoundError
+    /// with `strict`, and are ignored otherwise. The result shares nothing with this
+    /// walker.
+    #[pyo3(signature = (node_ids, strict = true))]
+    fn subgraph(&self, node_ids: Vec<String>, strict: bool) -> PyResult<GraphWalker> {
+        let node_ids = self.lookup_ids(node_ids)?;
+        self.require_starts(&node_ids, strict)?;
+        let keep = node_ids
+            .iter()
+            .filter_map(|id| self.nodes.handle(id))
+            .collect();
+        Ok(self.induced(&keep))
+    }
+
+    /// The subgraph (as `subgraph`) of every node within `radius` hops of `center`,
+    /// `center` included. `direction` is "out" (following edges), "in" (against
+    /// them) or "both". Hops may pass through edge targets that are not nodes, but
+    /// only nodes are kept. Raises NodeNotFoundError if `center` is not a node, and
+    /// ResourceLimitError once more than `max_results` ids have been reached or
+    /// more than `max_visited` expanded (or the module defaults).
+    #[pyo3(signature = (center, radius, direction = "both", max_results = None, max_visited = None))]
+    fn ego_graph(
+        &self,
+        py: Python,
+        center: String,
+        radius: usize,
+        direction: &str,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+    ) -> PyResult<GraphWalker> {
+        let (out, inward) = match direction {
+            "out" => (true, false),
+            "in" => (false, true),
+            "both" => (true, true),
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown direction '{}' (expected out, in or both)",
+                    other
+                )))
+            }
+        };
+        let center = self.lookup_id(center)?;
+        let Some(start) = self
+            .nodes
+            .handle(&center)
+            .filter(|&h| self.nodes.contains(h))
+        else {
+            return Err(NodeNotFoundError::new_err(center));
+        };
+        py.allow_threads(|| {
+            let limits = Limits::resolve(max_results, max_visited);
+            let reverse = inward.then(|| self.reverse_adjacency_cached());
+            let mut reached = HashSet::from([start]);
+            let mut frontier = vec![start];
+            let mut visited = 0;
+            for _ in 0..radius {
+                visited += frontier.len();
+                limits.check(visited, reached.len())?;
+                let mut next = Vec::new();
+                for &h in &frontier {
+                    let outgoing = self
+                        .nodes
+                        .node(h)
+                        .filter(|_| out)
+                        .map(|n| &n.dependents[..]);
+                    let incoming = reverse.as_ref().map(|r| sources_of(r, h));
+                    for &v in outgoing.into_iter().chain(incoming).flatten() {
+                        if reached.insert(v) {
+                            next.push(v);
+                        }
+                    }
+                }
+                limits.check(visited, reached.len())?;
+                if next.is_empty() {
+                    break;
+                }
+                frontier = next;
+            }
+            Ok(self.induced(&reached))
+        })
+    }
+
+    /// `detect_communities` as one sorted int64 handle array per community.
+    #[pyo3(signature = (directed = false))]
+    fn detect_communities_ids<'py>(
+        &mut self,
+        py: Python<'py>,
+        directed: bool,
+    ) -> PyResult<Vec<&'py PyArray1<i64>>> {
+        require_numpy(py)?;
+        self.detect_communities(py, directed)
+            .iter()
+            .map(|community| self.intern_array(py, community.iter()))
+            .collect()
+    }
+
+    /// Weakly connected components as sorted id lists, largest first, then by
+    /// smallest id. Maintained incrementally after the first call: edge additions
+    /// are unioned as they happen and removals re-flood only the touched component
+    /// (see `components`).
+    fn weakly_connected_components(&self, py: Python) -> Vec<Vec<String>> {
+        py.allow_threads(|| {
+            let mut components = self.components.lock().unwrap_or_else(|e| e.into_inner());
+            components.sync(&self.nodes);
+            components.components()
+        })
+    }
+
+    /// True if the next `weakly_connected_components` call has pending mutations to
+    /// apply (or has never run).
+    fn components_stale(&self) -> bool {
+        self.components
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_stale()
+    }
+
+    /// Deterministic node ordering for stable visual layouts: weakly connected
+    /// components (largest first, then by smallest id), and within each component
+    /// descending PageRank, ties broken by id. Identical across runs and processes
+    /// for the same graph content; cached until the next mutation.
+    fn stable_order(&self) -> Vec<String> {
+        self.stable_order_cached().as_ref().clone()
+    }
+
+    /// Budgeted plain-text summary for LLM context: the focus nodes' neighborhoods
+    /// first, then a header line, the most central nodes (by PageRank), cyclic
+    /// groups and the largest communities. Entries are `node -> [deps]` lines;
+    /// headers and cycle/community lines start with `#`. Output stops at the last
+    /// whole line that fits in `max_chars`. Deterministic.
+    #[pyo3(signature = (max_chars, focus = None))]
+    fn summarize_text(&self, max_chars: usize, focus: Option<Vec<String>>) -> PyResult<String> {
+        summary::summarize(
+            self,
+            max_chars,
+            &self.lookup_ids(focus.unwrap_or_default())?,
+        )
+    }
+
+    /// Per-node metrics as one table: a dict of equal-length columns with `id` (the
+    /// node ids, sorted) followed by the requested `metrics`, or a `pyarrow.Table` of
+    /// the same columns with `format="arrow"`. Supported: `pagerank` (20 iterations,
+    /// damping 0.85, deterministic), `degree` (with multiplicities in
+    /// `undirected_multi` mode), `in_degree`, `out_degree`, `core_number`
+    /// (undirected) and `community` (weakly connected component, largest first).
+    /// Columns are cached until the next mutation.
+    #[pyo3(signature = (metrics, format = "dict"))]
+    fn metrics_table(&self, py: Python, metrics: Vec<String>, format: &str) -> PyResult<PyObject> {
+        if let Some(bad) = metrics
+            .iter()
+            .find(|m| !table::SUPPORTED.contains(&m.as_str()))
+        {
+            return Err(PyValueError::new_err(format!(
+                "unknown metric '{}' (supported: {})",
+                bad,
+                table::SUPPORTED.join(", ")
+            )));
+        }
+        if format != "dict" && format != "arrow" {
+            return Err(PyValueError::new_err(format!(
+                "format must be 'dict' or 'arrow', got '{}'",
+                format
+            )));
+        }
+        let graph = algorithms::IndexedGraph::new(&self.nodes);
+        let computed: Vec<Arc<table::Column>> = py.allow_threads(|| {
+            metrics
+                .iter()
+                .map(|metric| {
+                    let key = format!("metrics_table:{}", metric);
+                    self.metric_cache
+                        .get_or_compute(self.version, &key, || match metric.as_str() {
+                            "pagerank" => {
+                                table::Column::Float(self.indexed_pagerank_cached(&graph).to_vec())
+                            }
+                            "degree" => table::Column::Float(self.degree_column(&graph)),
+                            name => table::compute(&graph, name),
+                        })
+                })
+                .collect()
+        });
+        let columns = PyDict::new(py);
+        columns.set_item("id", &graph.ids)?;
+        for (metric, column) in metrics.iter().zip(&computed) {
+            columns.set_item(metric, column.to_py(py))?;
+        }
+        if format == "arrow" {
+            return Ok(py
+                .import("pyarrow")?
+                .call_method1("table", (columns,))?
+                .into());
+        }
+        Ok(columns.into())
+    }
+
+    /// In deterministic mode (see `configure`) ranks are computed in sorted id order,
+    /// so they are bit-identical across runs and processes. Runs without the GIL and
+    /// is cached until the next mutation; concurrent identical calls share one run.
+    /// With `tolerance`, iteration stops early once an iteration changes the ranks
+    /// by less than that (L1 distance), and `iterations` only caps the run.
+    #[pyo3(signature = (iterations, damping, tolerance = None))]
+    fn calculate_pagerank(
+        &self,
+        py: Python,
+        iterations: usize,
+        damping: f64,
+        tolerance: Option<f64>,
+    ) -> PyResult<HashMap<String, f64>> {
+        Ok(self
+            .pagerank_run(py, iterations, damping, tolerance)?
+            .0
+            .clone())
+    }
+
+    /// PageRank relative to `seeds`: random jumps (and the rank of dangling nodes)
+    /// go only to the seeds, uniformly, so the ranks measure relevance to them
+    /// rather than global importance. Computed in sorted id order like
+    /// deterministic `calculate_pagerank`, and cached until the next mutation.
+    /// Unknown seeds raise NodeNotFoundError; an empty seed list raises
+    /// ValueError (use `calculate_pagerank` for global ranks).
+    #[pyo3(signature = (seeds, iterations, damping, tolerance = None))]
+    fn calculate_personalized_pagerank(
+        &self,
+        py: Python,
+        seeds: Vec<String>,
+        iterations: usize,
+        damping: f64,
+        tolerance: Option<f64>,
+    ) -> PyResult<HashMap<String, f64>> {
+        if seeds.is_empty() {
+            return Err(PyValueError::new_err(
+                "personalized PageRank needs at least one seed",
+            ));
+        }
+        let seeds = self.lookup_ids(seeds)?;
+        self.require_starts(&seeds, true)?;
+        check_tolerance(tolerance)?;
+        let seeds: BTreeSet<String> = seeds.into_iter().collect();
+        let key = format!(
+            "personalized_pagerank:{}:{:?}:{:?}:{:?}",
+            iterations, damping, tolerance, seeds
+        );
+        let ranks = py.allow_threads(|| {
+            self.metric_cache.get_or_compute(self.version, &key, || {
+                let graph = algorithms::IndexedGraph::new(&self.nodes);
+                let share = 1.0 / seeds.len() as f64;
+                let teleport: Vec<f64> = graph
+                    .ids
+                    .iter()
+                    .map(|id| if seeds.contains(*id) { share } else { 0.0 })
+                    .collect();
+                let (ranks, _) = algorithms::pagerank_until(
+                    &graph,
+                    iterations,
+                    damping,
+                    tolerance,
+                    Some(&teleport),
+                );
+                graph
+                    .ids
+                    .iter()
+                    .map(|id| (*id).clone())
+                    .zip(ranks)
+                    .collect::<HashMap<String, f64>>()
+            })
+        });
+        Ok(ranks.as_ref().clone())
+    }
+
+    /// How the `calculate_pagerank` run with the same arguments ended: `{"iterations"
+    /// (performed), "delta" (L1 change of the last iteration, None if none ran),
+    /// "converged" (delta below `tolerance`)}`. Shares the cached run, so calling it
+    /// after `calculate_pagerank` costs nothing.
+    #[pyo3(signature = (iterations, damping, tolerance = None))]
+    fn pagerank_convergence(
+        &self,
+        py: Python,
+        iterations: usize,
+        damping: f64,
+        tolerance: Option<f64>,
+    ) -> PyResult<PyObject> {
+        let run = self.pagerank_run(py, iterations, damping, tolerance)?;
+        let convergence = run.1;
+        let d = PyDict::new(py);
+        d.set_item("iterations", convergence.iterations)?;
+        d.set_item("delta", convergence.delta)?;
+        let converged = match (tolerance, convergence.delta) {
+            (Some(t), Some(delta)) => delta < t,
+            // An empty graph has nothing to converge.
+            (Some(_), None) => self.nodes.is_empty(),
+            (None, _) => false,
+        };
+        d.set_item("converged", converged)?;
+        Ok(d.into())
+    }
+
+    /// Eigenvector centrality: a node is central when central nodes point at it,
+    /// weighted by edge weight (see `algorithms::eigenvector_centrality`). Unlike
+    /// PageRank, a source's influence is not divided among its edges. Scores have
+    /// unit L2 norm. Raises ConvergenceError if the scores still move by `n ·
+    /// tolerance` or more after `max_iter` iterations. Runs without the GIL and is
+    /// cached until the next mutation.
+    #[pyo3(signature = (max_iter = 100, tolerance = 1e-6))]
+    fn calculate_eigenvector_centrality(
+        &self,
+        py: Python,
+        max_iter: usize,
+        tolerance: f64,
+    ) -> PyResult<HashMap<String, f64>> {
+        check_tolerance(Some(tolerance))?;
+        let key = format!("eigenvector:{}:{:?}", max_iter, tolerance);
+        let scores = py.allow_threads(|| {
+            self.metric_cache.get_or_compute(self.version, &key, || {
+                let graph = algorithms::IndexedGraph::new(&self.nodes);
+                config::install(|| algorithms::eigenvector_centrality(&graph, max_iter, tolerance))
+                    .map(|scores| by_id(&graph, scores))
+            })
+        });
+        power_scores("eigenvector centrality", &scores)
+    }
+
+    /// Katz centrality: `beta` for every node plus `alpha^k` for each walk of length
+    /// `k` into it, weighted by edge weight (see `algorithms::katz_centrality`).
+    /// Scores have unit L2 norm. Raises ValueError if `alpha` is too large for the
+    /// series to converge (at least 1 / spectral radius), and ConvergenceError if it
+    /// does not settle within `max_iter` iterations. Runs without the GIL and is
+    /// cached until the next mutation.
+    #[pyo3(signature = (alpha = 0.1, beta = 1.0, max_iter = 1000, tolerance = 1e-6))]
+    fn calculate_katz_centrality(
+        &self,
+        py: Python,
+        alpha: f64,
+        beta: f64,
+        max_iter: usize,
+        tolerance: f64,
+    ) -> PyResult<HashMap<String, f64>> {
+        check_tolerance(Some(tolerance))?;
+        if !alpha.is_finite() || !beta.is_finite() {
+            return Err(PyValueError::new_err("alpha and beta must be finite"));
+        }
+        let key = format!("katz:{:?}:{:?}:{}:{:?}", alpha, beta, max_iter, tolerance);
+        let scores = py.allow_threads(|| {
+            self.metric_cache.get_or_compute(self.version, &key, || {
+                let graph = algorithms::IndexedGraph::new(&self.nodes);
+                config::install(|| {
+                    algorithms::katz_centrality(&graph, alpha, beta, max_iter, tolerance)
+                })
+                .map(|scores| by_id(&graph, scores))
+            })
+        });
+        match scores.as_ref() {
+            Err(algorithms::PowerFailure::Diverged) => Err(PyValueError::new_err(format!(
+                "Katz centrality diverges for alpha={}; alpha must be below 1 / spectral radius",
+                alpha
+            ))),
+            _ => power_scores("Katz centrality", &scores),
+        }
+    }
+
+    /// Cached computations currently running on this walker, as `key@version`.
+    fn inflight_computations(&self) -> Vec<String> {
+        self.metric_cache.inflight()
+    }
+
+    /// Cached-computation counters since the walker was created: `computed` (actual
+    /// runs), `coalesced` (calls that waited for an identical in-flight run) and
+    /// `hits` (calls answered from the cache), plus `components_incremental` and
+    /// `components_full`: how often `weakly_connected_components` applied pending
+    /// mutations locally or recomputed from scratch, and `invalid_expanded` and
+    /// `invalid_reopened`: ids whose dependents `mark_invalid` walked, and invalid
+    /// ids reopened so that a later `mark_invalid` walks past them, and
+    /// `pagerank_iterations`: iterations `calculate_pagerank` actually ran.
+    fn computation_stats(&self) -> HashMap<&'static str, u64> {
+        let stats = self.metric_cache.stats();
+        let components = self.components.lock().unwrap_or_else(|e| e.into_inner());
+        HashMap::from([
+            ("computed", stats.computed),
+            ("coalesced", stats.coalesced),
+            ("hits", stats.hits),
+            ("components_incremental", components.incremental_syncs),
+            ("components_full", components.full_syncs),
+            ("invalid_expanded", self.dirty.expanded),
+            ("invalid_reopened", self.dirty.reopened),
+            (
+                "pagerank_iterations",
+                self.pagerank_iterations.load(Ordering::Relaxed) as u64,
+            ),
+        ])
+    }
+
+    /// Normalized betweenness centrality (Brandes) over the directed edges: the
+    /// fraction of shortest paths between other node pairs that pass through each
+    /// node. Exact, so O(V·E); runs in parallel without the GIL and is cached until
+    /// the next mutation.
+    fn calculate_betweenness(&self, py: Python) -> HashMap<String, f64> {
+        let scores = py.allow_threads(|| {
+            self.metric_cache
+                .get_or_compute(self.version, "betweenness", || {
+                    let graph = algorithms::IndexedGraph::new(&self.nodes);
+                    let scores = config::install(|| algorithms::betweenness(&graph));
+                    graph
+                        .ids
+                        .iter()
+                        .map(|id| (*id).clone())
+                        .zip(scores)
+                        .collect::<HashMap<String, f64>>()
+                })
+        });
+        (*scores).clone()
+    }
+
+    /// Number of nodes with each out-degree. Out-degrees count stored edges, including
+    /// edges to ids that are not nodes, as in `metrics_table`.
+    fn degree_distribution(&self) -> HashMap<usize, usize> {
+        let mut histogram = HashMap::new();
+        for node in self.nodes.values() {
+            *histogram.entry(node.dependents.len()).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Number of edges into `id` from nodes. Raises NodeNotFoundError if `id` is not a
+    /// node.
+    fn in_degree(&self, id: String) -> PyResult<usize> {
+        let id = self.lookup_id(id)?;
+        let Some(h) = self.nodes.handle(&id).filter(|&h| self.nodes.contains(h)) else {
+            return Err(NodeNotFoundError::new_err(id));
+        };
+        Ok(sources_of(&self.reverse_adjacency_cached(), h).len())
+    }
+
+    /// Number of edges out of `id`, as in `degree_distribution`. Raises
+    /// NodeNotFoundError if `id` is not a node.
+    fn out_degree(&self, id: String) -> PyResult<usize> {
+        let id = self.lookup_id(id)?;
+        match self.nodes.get(&id) {
+            Some(node) => Ok(node.dependents.len()),
+            None => Err(NodeNotFoundError::new_err(id)),
+        }
+    }
+
+    /// Sorted nodes without incoming edges from nodes (in-degree zero).
+    fn find_roots(&self, py: Python) -> Vec<String> {
+        let reverse = self.reverse_adjacency_cached();
+        py.allow_threads(|| {
+            self.sorted_names(
+                self.nodes
+                    .handles()
+                    .filter(|&(h, _)| sources_of(&reverse, h).is_empty()),
+            )
+        })
+    }
+
+    /// Sorted nodes without outgoing edges. An edge to an id that is not a node still
+    /// counts.
+    fn find_leaves(&self, py: Python) -> Vec<String> {
+        py.allow_threads(|| {
+            self.sorted_names(
+                self.nodes
+                    .handles()
+                    .filter(|(_, node)| node.dependents.is_empty()),
+            )
+        })
+    }
+
+    /// Sorted nodes that are both roots and leaves.
+    fn find_isolated(&self, py: Python) -> Vec<String> {
+        let reverse = self.reverse_adjacency_cached();
+        py.allow_threads(|| {
+            self.sorted_names(self.nodes.handles().filter(|&(h, node)| {
+                node.dependents.is_empty() && sources_of(&reverse, h).is_empty()
+            }))
+        })
+    }
+
+    /// Sorted ids that are edge targets but were never added as nodes, typically
+    /// because an edge arrived before its target. These are left out of the
+    /// roots, leaves and isolated nodes, which only cover nodes. Tombstoned ids
+    /// keep their incoming edges but are deleted facts, not phantoms, and are left
+    /// out here too.
+    fn find_phantom_nodes(&self, py: Python) -> Vec<String> {
+        let reverse = self.reverse_adjacency_cached();
+        py.allow_threads(|| {
+            let mut out: Vec<String> = (0..reverse.len() as u32)
+                .filter(|&h| {
+                    !sources_of(&reverse, h).is_empty()
+                        && !self.nodes.contains(h)
+                        && !self.is_tombstoned(h)
+                })
+                .map(|h| self.nodes.name(h).clone())
+                .collect();
+            out.sort_unstable();
+            out
+        })
+    }
+
+    /// Integrity report (see `integrity::ValidationReport`): dangling edges (to ids
+    /// that are neither nodes nor tombstoned), self-loops, pairs repeated within one
+    /// node's edge list and edges touching the empty id, each as sorted `(from, to)`
+    /// pairs capped at `limit` with complete counts. One parallel O(V+E) pass without
+    /// the GIL; `repair` fixes what it finds.
+    #[pyo3(signature = (limit = 100))]
+    fn validate(&self, py: Python, limit: usize) -> integrity::ValidationReport {
+        py.allow_threads(|| {
+            let phantom = integrity::phantoms(&self.nodes, |h| self.is_tombstoned(h));
+            config::install(|| integrity::ValidationReport::new(&self.nodes, &phantom, limit))
+        })
+    }
+
+    /// Structural overview (see `stats::GraphSummary`): node and edge counts (as
+    /// `edge_count`), density, weakly connected components, isolated nodes, the
+    /// largest in- and out-degrees (as `in_degree` and `out_degree`) with the nodes
+    /// reaching them, and whether the graph is acyclic. Edges to ids that are not
+    /// nodes count toward out-degrees only. In `undirected_multi` mode every edge is
+    /// a two-way cycle, so `is_dag` is false once there is one.
+    fn summary(&self, py: Python) -> stats::GraphSummary {
+        let edges = self.edge_count_total();
+        let directed = self.mode.is_directed();
+        py.allow_threads(|| {
+            let graph = algorithms::IndexedGraph::new(&self.nodes);
+            stats::GraphSummary::new(&graph, edges, directed)
+        })
+    }
+
+    /// Out-degree of each node, the cheap stand-in `calculate_betweenness` used to
+    /// return.
+    fn calculate_degree_centrality(&self) -> HashMap<String, f64> {
+        self.nodes
+            .iter()
+            .map(|(id, node)| (id.clone(), node.dependents.len() as f64))
+            .collect()
+    }
+
+    /// Closeness centrality over outgoing shortest paths, scaled by the fraction of
+    /// nodes reachable (Wasserman–Faust) so disconnected graphs stay comparable.
+    /// Nodes that reach nothing score 0. O(V·(V+E)); runs in parallel without the
+    /// GIL and is cached until the next mutation.
+    fn calculate_closeness(&self, py: Python) -> HashMap<String, f64> {
+        let scores = py.allow_threads(|| {
+            self.metric_cache
+                .get_or_compute(self.version, "closeness", || {
+                    let graph = algorithms::IndexedGraph::new(&self.nodes);
+                    let scores = config::install(|| algorithms::closeness(&graph));
+                    graph
+                        .ids
+                        .iter()
+                        .map(|id| (*id).clone())
+                        .zip(scores)
+                        .collect::<HashMap<String, f64>>()
+                })
+        });
+        (*scores).clone()
+    }
+
+    /// Number of triangles, each unordered triple of mutually linked nodes counted
+    /// once regardless of edge directions. In `undirected_multi` mode: the number
+    /// of triangles counted with multiplicity, i.e. the sum over node triples of
+    /// the product of their three pair counts.
+    fn count_triangles(&self, py: Python) -> usize {
+        if !self.mode.is_directed() {
+            return self.multigraph_triangles();
+        }
+        let triangles = self.triangles_cached(py);
+        triangles.values().map(|&(t, _)| t).sum::<usize>() / 3
+    }
+
+    /// Directed cycles `a→b→c→a` through three distinct nodes, each counted once.
+    fn count_directed_cycles_of_length_3(&self, py: Python) -> usize {
+        py.allow_threads(|| {
+            let graph = algorithms::IndexedGraph::new(&self.nodes);
+            algorithms::directed_3_cycles(&algorithms::simple_adjacency(&graph))
+        })
+    }
+
+    /// Ordered triples of distinct nodes `(a, b, c)` with edges `a→b`, `b→c` and
+    /// the shortcut `a→c`.
+    fn count_transitive_triads(&self, py: Python) -> usize {
+        py.allow_threads(|| {
+            let graph = algorithms::IndexedGraph::new(&self.nodes);
+            algorithms::transitive_triads(&algorithms::simple_adjacency(&graph))
+        })
+    }
+
+    /// Local clustering coefficient of `node_id` over the undirected projection:
+    /// the fraction of pairs of its neighbors that are linked themselves, 0 with
+    /// fewer than two neighbors. Raises NodeNotFoundError for an unknown node.
+    fn clustering_coefficient(&self, py: Python, node_id: String) -> PyResult<f64> {
+        let node_id = self.lookup_id(node_id)?;
+        let triangles = self.triangles_cached(py);
+        let Some(&(t, d)) = triangles.get(&node_id) else {
+            return Err(NodeNotFoundError::new_err(node_id));
+        };
+        Ok(if d < 2 {
+            0.0
+        } else {
+            2.0 * t as f64 / (d * (d - 1)) as f64
+        })
+    }
+
+    /// Global clustering coefficient (transitivity) over the undirected
+    /// projection: three times the triangles over the connected triples, 0 when
+    /// there are none.
+    fn global_clustering_coefficient(&self, py: Python) -> f64 {
+        let triangles = self.triangles_cached(py);
+        let (closed, triples) = triangles.values().fold((0, 0), |(c, p), &(t, d)| {
+            (c + t, p + d * d.saturating_sub(1) / 2)
+        });
+        if triples == 0 {
+            0.0
+        } else {
+            closed as f64 / triples as f64
+        }
+    }
+
+    /// Maximal cliques of the undirected projection (Bron–Kerbosch with pivoting),
+    /// each as sorted ids, ordered largest first and then by ids. Isolated nodes
+    /// are cliques of one. Enumeration can blow up on dense graphs: with
+    /// `max_cliques`, finding one more clique than that raises ResourceLimitError
+    /// with args `(message, max_cliques, max_cliques)`.
+    #[pyo3(signature = (max_cliques = None))]
+    fn find_cliques(&self, py: Python, max_cliques: Option<usize>) -> PyResult<Vec<Vec<String>>> {
+        py.allow_threads(|| {
+            let graph = algorithms::IndexedGraph::new(&self.nodes);
+            let neighbors = algorithms::undirected_neighbors(&graph);
+            let cap = max_cliques.unwrap_or(usize::MAX);
+            let mut cliques: Vec<Vec<usize>> = Vec::new();
+            let mut truncated = false;
+            algorithms::maximal_cliques(&neighbors, |clique| {
+                if cliques.len() == cap {
+                    truncated = true;
+                    return false;
+                }
+                cliques.push(clique.to_vec());
+                true
+            });
+            if truncated {
+                return Err(ResourceLimitError::new_err((
+                    format!("max_cliques limit of {} exceeded", cap),
+                    cap,
+                    cap,
+                )));
+            }
+            cliques.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+            Ok(cliques
+                .into_iter()
+                .map(|c| c.into_iter().map(|i| graph.ids[i].clone()).collect())
+                .collect())
+        })
+    }
+
+    /// Size of the largest clique of the undirected projection, 0 for an empty
+    /// graph. Enumerates maximal cliques like `find_cliques` without storing them.
+    fn max_clique_size(&self, py: Python) -> usize {
+        py.allow_threads(|| {
+            let graph = algorithms::IndexedGraph::new(&self.nodes);
+            let neighbors = algorithms::undirected_neighbors(&graph);
+            let mut best = 0;
+            algorithms::maximal_cliques(&neighbors, |clique| {
+                best = best.max(clique.len());
+                true
+            });
+            best
+        })
+    }
+
+    /// Nodes whose removal splits their connected component of the undirected
+    /// projection (self-loops and edges to ids that are not nodes ignored), sorted.
+    /// Each component is searched separately.
+    fn articulation_points(&self, py: Python) -> Vec<String> {
+        py.allow_threads(|| {
+            let graph = algorithms::IndexedGraph::new(&self.nodes);
+            let (cut, _) = algorithms::cut_structure(&algorithms::undirected_neighbors(&graph));
+            (0..graph.len())
+                .filter(|&i| cut[i])
+                .map(|i| graph.ids[i].clone())
+                .collect()
+        })
+    }
+
+    /// Edges of the undirected projection whose removal splits their component, as
+    /// `(id_a, id_b)` pairs with `id_a < id_b`, sorted. Edges are taken as in
+    /// `articulation_points`, so `a -> b` together with `b -> a` is one edge and can
+    /// be a bridge.
+    fn bridges(&self, py: Python) -> Vec<(String, String)> {
+        py.allow_threads(|| {
+            let graph = algorithms::IndexedGraph::new(&self.nodes);
+            let (_, bridges) = algorithms::cut_structure(&algorithms::undirected_neighbors(&graph));
+            bridges
+                .into_iter()
+                .map(|(u, v)| (graph.ids[u].clone(), graph.ids[v].clone()))
+                .collect()
+        })
+    }
+
+    /// Longest shortest-path distance from a node to any id it reaches (edge
+    /// targets included), with edges followed forward; 0 for a graph without
+    /// edges. One BFS per node, in parallel without the GIL, cached until the next
+    /// mutation. With `sample=k`, only `k` sources picked under `seed` are used and
+    /// the result is a dict `{"diameter", "approximate", "sources"}`: the diameter
+    /// is then a lower bound, flagged approximate unless every node was a source.
+    #[pyo3(signature = (sample = None, seed = 0))]
+    fn find_diameter(&self, py: Python, sample: Option<usize>, seed: u64) -> PyResult<PyObject> {
+        if sample == Some(0) {
+            return Err(PyValueError::new_err("sample must be positive"));
+        }
+        let key = format!("diameter:{:?}:{}", sample, seed);
+        let result = py.allow_threads(|| {
+            self.metric_cache.get_or_compute(self.version, &key, || {
+                let (n, adj) = self.adjacency_with_targets();
+                let sources = match sample {
+                    Some(k) if k < n => algorithms::sample_indices(n, k, seed),
+                    _ => (0..n).collect(),
+                };
+                let diameter = config::install(|| algorithms::max_eccentricity(&adj, &sources));
+                (diameter, sources.len(), n)
+            })
+        });
+        let (diameter, sources, n) = *result;
+        if sample.is_none() {
+            return Ok(diameter.into_py(py));
+        }
+        let d = PyDict::new(py);
+        d.set_item("diameter", diameter)?;
+        d.set_item("approximate", sources < n)?;
+        d.set_item("sources", sources)?;
+        Ok(d.into())
+    }
+
+    /// A shortest path `from_id -> ... -> to_id`, both included, or None if `to_id`
+    /// is unreachable. Raises NodeNotFoundError if `from_id` is not a node or
+    /// `to_id` is neither a node nor an edge target.
+    fn shortest_path(
+        &self,
+        py: Python,
+        from_id: String,
+        to_id: String,
+    ) -> PyResult<Option<Vec<String>>> {
+        let (from_id, to_id) = (self.lookup_id(from_id)?, self.lookup_id(to_id)?);
+        py.allow_threads(|| {
+            self.require_path_ends(&from_id, &to_id)?;
+            Ok(self.find_path(&from_id, &to_id))
+        })
+    }
+
+    /// Number of edges on a shortest path, or None if unreachable. Errors as in
+    /// `shortest_path`.
+    fn shortest_path_length(
+        &self,
+        py: Python,
+        from_id: String,
+        to_id: String,
+    ) -> PyResult<Option<usize>> {
+        let (from_id, to_id) = (self.lookup_id(from_id)?, self.lookup_id(to_id)?);
+        py.allow_threads(|| {
+            self.require_path_ends(&from_id, &to_id)?;
+            let goal = self.nodes.lookup(&to_id);
+            Ok(self
+                .bfs_distances(self.nodes.lookup(&from_id), Some(goal))
+                .get(&goal)
+                .copied())
+        })
+    }
+
+    /// Every shortest path from `from_id` to `to_id`, sorted, or the first `limit`
+    /// of them in that order; empty if unreachable. Errors as in `shortest_path`,
+    /// and raises ResourceLimitError once more than `max_results` paths have been
+    /// found or more than `max_visited` nodes expanded (or the module defaults).
+    #[pyo3(signature = (from_id, to_id, limit = None, max_results = None, max_visited = None))]
+    fn all_shortest_paths(
+        &self,
+        py: Python,
+        from_id: String,
+        to_id: String,
+        limit: Option<usize>,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+    ) -> PyResult<Vec<Vec<String>>> {
+        let (from_id, to_id) = (self.lookup_id(from_id)?, self.lookup_id(to_id)?);
+        py.allow_threads(|| {
+            self.require_path_ends(&from_id, &to_id)?;
+            let limit = limit.unwrap_or(usize::MAX);
+            let limits = Limits::resolve(max_results, max_visited);
+            let (start, goal) = (self.nodes.lookup(&from_id), self.nodes.lookup(&to_id));
+            let dist = self.bfs_distances(start, Some(goal));
+            let mut visited = dist.len();
+            limits.check(visited, 0)?;
+            if limit == 0 || !dist.contains_key(&goal) {
+                return Ok(Vec::new());
+            }
+            // Nodes on some shortest path: walk back from `to_id` through
+            // predecessors one step closer to `from_id`.
+            let reverse = self.reverse_adjacency_cached();
+            let mut on_path = HashSet::from([goal]);
+            let mut queue = vec![goal];
+            while let Some(v) = queue.pop() {
+                let d = dist[&v];
+                for &p in sources_of(&reverse, v) {
+                    if d > 0 && dist.get(&p) == Some(&(d - 1)) && on_path.insert(p) {
+                        queue.push(p);
+                    }
+                }
+            }
+            visited += on_path.len();
+            limits.check(visited, 0)?;
+            // Successors of `u` one step closer to `to_id`, sorted by id and distinct.
+            let further = |u: u32| -> Vec<u32> {
+                let d = dist[&u];
+                let mut next: Vec<u32> = self
+                    .nodes
+                    .node(u)
+                    .map(|n| &n.dependents[..])
+                    .unwrap_or_default()
+                    .iter()
+                    .copied()
+                    .filter(|v| on_path.contains(v) && dist.get(v) == Some(&(d + 1)))
+                    .collect();
+                next.sort_unstable_by_key(|&v| self.nodes.name(v));
+                next.dedup();
+                next
+            };
+            // Walk forward from `from_id` with an explicit stack of (node, its
+            // successors, next successor to try). Trying successors in id order
+            // yields the paths already sorted, so stopping at `limit` keeps the
+            // first ones in sorted order.
+            let mut paths: Vec<Vec<String>> = Vec::new();
+            let mut trail: Vec<u32> = vec![start];
+            let mut stack = vec![(further(start), 0)];
+            while let Some((succs, next)) = stack.last_mut() {
+                let current = trail[trail.len() - 1];
+                if current == goal {
+                    paths.push(self.nodes.names(trail.iter().copied()));
+                    limits.check(visited, paths.len())?;
+                    if paths.len() == limit {
+                        break;
+                    }
+                }
+                match succs.get(*next) {
+                    Some(&v) if current != goal => {
+                        *next += 1;
+                        visited += 1;
+                        limits.check(visited, paths.len())?;
+                        trail.push(v);
+                        stack.push((further(v), 0));
+                    }
+                    _ => {
+                        stack.pop();
+                        trail.pop();
+                    }
+                }
+            }
+            Ok(paths)
+        })
+    }
+
+    /// Shortest path lengths from each of `sources` to each of `targets` (every id
+    /// reached, edge targets included, if None) as `{(source, target): length}`,
+    /// with one BFS per source in parallel without the GIL. Unreachable pairs are
+    /// absent, and a source reaches itself at length 0. Sources must be nodes and
+    /// targets nodes or edge targets, or NodeNotFoundError is raised. More than
+    /// `max_pairs` results raise ResourceLimitError with args
+    /// `(message, max_pairs, pairs_found)`; pass the sources in batches instead.
+    #[pyo3(signature = (sources, targets = None, max_pairs = Some(DEFAULT_MAX_PAIRS)))]
+    fn shortest_path_lengths(
+        &self,
+        py: Python,
+        sources: Vec<String>,
+        targets: Option<Vec<String>>,
+        max_pairs: Option<usize>,
+    ) -> PyResult<HashMap<(String, String), usize>> {
+        let sources = self.lookup_ids(sources)?;
+        let targets = targets.map(|t| self.lookup_ids(t)).transpose()?;
+        py.allow_threads(|| {
+            let starts = self.require_sources(&sources)?;
+            let goals = match &targets {
+                Some(targets) => {
+                    let mut goals = vec![false; self.nodes.ids().len()];
+                    for t in targets {
+                        self.require_path_target(t)?;
+                        goals[self.nodes.lookup(t) as usize] = true;
+                    }
+                    Some(goals)
+                }
+                None => None,
+            };
+            let cap = max_pairs.unwrap_or(usize::MAX);
+            let found = AtomicUsize::new(0);
+            let rows = self.bfs_rows(&starts, goals.as_deref(), |start, dist, reached| {
+                let row: Vec<(u32, u32, u32)> = reached
+                    .iter()
+                    .filter(|&&v| goals.as_ref().is_none_or(|g| g[v as usize]))
+                    .map(|&v| (start, v, dist[v as usize]))
+                    .collect();
+                let total = found.fetch_add(row.len(), Ordering::Relaxed) + row.len();
+                (total <= cap).then_some(row)
+            });
+            let Some(rows) = rows else {
+                let found = found.load(Ordering::Relaxed);
+                return Err(ResourceLimitError::new_err((
+                    format!(
+                        "shortest_path_lengths found more than max_pairs={} pairs; \
+                         pass fewer sources at a time",
+                        cap
+                    ),
+                    cap,
+                    found,
+                )));
+            };
+            Ok(rows
+                .into_iter()
+                .flatten()
+                .map(|(s, t, d)| {
+                    let pair = (self.nodes.name(s).clone(), self.nodes.name(t).clone());
+                    (pair, d as usize)
+                })
+                .collect())
+        })
+    }
+
+    /// Shortest path lengths between every pair of `node_ids`: row `i`, column `j`
+    /// is the length from `node_ids[i]` to `node_ids[j]`, or -1 if unreachable, so
+    /// the result converts directly with `numpy.array`. Ids must be nodes, or
+    /// NodeNotFoundError is raised. More than `max_pairs` cells raise
+    /// ResourceLimitError with args `(message, max_pairs, cells)` before any search.
+    #[pyo3(signature = (node_ids, max_pairs = Some(DEFAULT_MAX_PAIRS)))]
+    fn distance_matrix(
+        &self,
+        py: Python,
+        node_ids: Vec<String>,
+        max_pairs: Option<usize>,
+    ) -> PyResult<Vec<Vec<i64>>> {
+        let node_ids = self.lookup_ids(node_ids)?;
+        py.allow_threads(|| {
+            let cells = node_ids.len().saturating_mul(node_ids.len());
+            let cap = max_pairs.unwrap_or(usize::MAX);
+            if cells > cap {
+                return Err(ResourceLimitError::new_err((
+                    format!(
+                        "distance_matrix of {} ids has {} cells, more than max_pairs={}",
+                        node_ids.len(),
+                        cells,
+                        cap
+                    ),
+                    cap,
+                    cells,
+                )));
+            }
+            let handles = self.require_sources(&node_ids)?;
+            let mut goals = vec![false; self.nodes.ids().len()];
+            for &h in &handles {
+                goals[h as usize] = true;
+            }
+            let rows = self.bfs_rows(&handles, Some(&goals), |_, dist, _| {
+                Some(
+                    handles
+                        .iter()
+                        .map(|&h| match dist[h as usize] {
+                            u32::MAX => -1,
+                            d => i64::from(d),
+                        })
+                        .collect(),
+                )
+            });
+            Ok(rows.unwrap_or_default())
+        })
+    }
+
+    /// Cheapest path by total edge weight (Dijkstra): `(cost, path)` with both
+    /// endpoints in the path, or None if `to_id` is unreachable. Raises ValueError
+    /// on reaching an edge with a negative weight; other errors as in
+    /// `shortest_path`.
+    fn shortest_path_weighted(
+        &self,
+        py: Python,
+        from_id: String,
+        to_id: String,
+    ) -> PyResult<Option<(f64, Vec<String>)>> {
+        use algorithms::Cost;
+        use std::cmp::Reverse;
+        use std::collections::BinaryHeap;
+        let (from_id, to_id) = (self.lookup_id(from_id)?, self.lookup_id(to_id)?);
+        self.require_path_ends(&from_id, &to_id)?;
+        py.allow_threads(|| {
+            let (start, goal) = (self.nodes.lookup(&from_id), self.nodes.lookup(&to_id));
+            let mut best: HashMap<u32, (f64, u32)> = HashMap::from([(start, (0.0, start))]);
+            // Ties in cost pop in id order.
+            let mut heap = BinaryHeap::from([Reverse((Cost(0.0), from_id.as_str(), start))]);
+            while let Some(Reverse((Cost(cost), _, u))) = heap.pop() {
+                if cost > best[&u].0 {
+                    continue;
+                }
+                if u == goal {
+                    let mut path = vec![u];
+                    let mut cur = u;
+                    while cur != start {
+                        cur = best[&cur].1;
+                        path.push(cur);
+                    }
+                    path.reverse();
+                    return Ok(Some((cost, self.nodes.names(path))));
+                }
+                let Some(node) = self.nodes.node(u) else {
+                    continue;
+                };
+                for (&v, &w) in node.dependents.iter().zip(&node.weights) {
+                    if w < 0.0 {
+                        return Err(PyValueError::new_err(format!(
+                            "edge {} -> {} has negative weight {}",
+                            self.nodes.name(u),
+                            self.nodes.name(v),
+                            w
+                        )));
+                    }
+                    let next = cost + w;
+                    if best.get(&v).is_none_or(|&(c, _)| next < c) {
+                        best.insert(v, (next, u));
+                        heap.push(Reverse((Cost(next), self.nodes.name(v).as_str(), v)));
+                    }
+                }
+            }
+            Ok(None)
+        })
+    }
+
+    /// Number of node pairs `(u, v)` with `v` reachable from `u` at each shortest-path
+    /// distance (edges followed forward, dangling targets excluded). Exact over all
+    /// sources by default; with `sample_sources=k`, BFS runs from `k` sources picked
+    /// under `seed` and counts are scaled by `nodes / k`. Runs in parallel and keeps
+    /// only the histogram, never the pair matrix. Cached until the next mutation.
+    #[pyo3(signature = (sample_sources = None, seed = 0))]
+    fn distance_distribution(
+        &self,
+        py: Python,
+        sample_sources: Option<usize>,
+        seed: u64,
+    ) -> PyResult<HashMap<usize, u64>> {
+        let hist = self.distance_histogram_cached(py, sample_sources, seed)?;
+        Ok(hist
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(d, &count)| (d, count))
+            .collect())
+    }
+
+    /// Distance within which `percentile` (0 < p <= 1) of the reachable pairs lie,
+    /// linearly interpolated between integer distances as is usual for the effective
+    /// diameter. Uses `distance_distribution` with the same sampling arguments; 0.0
+    /// for a graph without reachable pairs.
+    #[pyo3(signature = (percentile = 0.9, sample_sources = None, seed = 0))]
+    fn effective_diameter(
+        &self,
+        py: Python,
+        percentile: f64,
+        sample_sources: Option<usize>,
+        seed: u64,
+    ) -> PyResult<f64> {
+        if !(percentile > 0.0 && percentile <= 1.0) {
+            return Err(PyValueError::new_err(format!(
+                "percentile must be in (0, 1], got {}",
+                percentile
+            )));
+        }
+        let hist = self.distance_histogram_cached(py, sample_sources, seed)?;
+        let total: u64 = hist.iter().sum();
+        if total == 0 {
+            return Ok(0.0);
+        }
+        let target = percentile * total as f64;
+        let mut cumulative = 0.0;
+        for (d, &count) in hist.iter().enumerate().filter(|(_, &c)| c > 0) {
+            let next = cumulative + count as f64;
+            if next >= target {
+                return Ok((d - 1) as f64 + (target - cumulative) / count as f64);
+            }
+            cumulative = next;
+        }
+        Ok((hist.len() - 1) as f64)
+    }
+
+    /// Node ids grouped by namespace prefix (see `namespace_of`), each list sorted;
+    /// ids without a namespace are grouped under "".
+    #[pyo3(signature = (separator = namespace::DEFAULT_SEPARATOR))]
+    fn nodes_by_namespace(&self, separator: &str) -> HashMap<String, Vec<String>> {
+        let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+        for id in self.nodes.keys() {
+            let ns = namespace::split(id, separator).map_or("", |(ns, _)| ns);
+            groups.entry(ns.to_string()).or_default().push(id.clone());
+        }
+        for ids in groups.values_mut() {
+            ids.sort();
+        }
+        groups
+    }
+
+    /// The `k` nodes most similar to `id`, as `(id, score)` sorted by descending
+    /// score with ties broken by id; nodes scoring zero are left out. Methods
+    /// "jaccard", "overlap" (shared over the smaller neighborhood), "cosine" and
+    /// "adamic_adar" compare neighborhoods along `direction`: "out" (dependents),
+    /// "in" (sources) or "both". Candidates are the nodes that can share such a
+    /// neighbor. "simrank_1hop" (one SimRank iteration over sources, C = 0.8) and
+    /// "co_invalidation" (Jaccard of the downstream propagation sets) ignore
+    /// `direction` and consider nodes within two hops, edges taken either way.
+    /// Raises NodeNotFoundError if `id` does not exist.
+    #[pyo3(signature = (id, k, method = "jaccard", direction = "both"))]
+    fn most_similar(
+        &self,
+        py: Python,
+        id: String,
+        k: usize,
+        method: &str,
+        direction: &str,
+    ) -> PyResult<Vec<(String, f64)>> {
+        let method = similarity::Method::parse(method)?;
+        let direction = similarity::Direction::parse(direction)?;
+        let id = self.lookup_id(id)?;
+        if !self.nodes.contains_key(&id) {
+            return Err(NodeNotFoundError::new_err(id));
+        }
+        let reverse = self.reverse_adjacency_cached();
+        let sim = similarity::Similarity {
+            nodes: &self.nodes,
+            reverse: &reverse,
+            tombstones: &self.tombstones,
+            direction,
+        };
+        Ok(py.allow_threads(|| sim.most_similar(&id, k, method)))
+    }
+
+    /// Pairwise `most_similar` scores of `node_ids`: row `i` holds the scores of
+    /// `node_ids[i]` against each id in order, zeros included. The diagonal holds each
+    /// node scored against itself. Raises NodeNotFoundError for an id that is not a
+    /// node.
+    #[pyo3(signature = (node_ids, method = "jaccard", direction = "both"))]
+    fn similarity_matrix(
+        &self,
+        py: Python,
+        node_ids: Vec<String>,
+        method: &str,
+        direction: &str,
+    ) -> PyResult<Vec<Vec<f64>>> {
+        let method = similarity::Method::parse(method)?;
+        let direction = similarity::Direction::parse(direction)?;
+        let node_ids = self.lookup_ids(node_ids)?;
+        if let Some(id) = node_ids.iter().find(|id| !self.nodes.contains_key(id)) {
+            return Err(NodeNotFoundError::new_err(id.clone()));
+        }
+        let reverse = self.reverse_adjacency_cached();
+        let sim = similarity::Similarity {
+            nodes: &self.nodes,
+            reverse: &reverse,
+            tombstones: &self.tombstones,
+            direction,
+        };
+        Ok(py.allow_threads(|| sim.matrix(&node_ids, method)))
+    }
+
+    /// The `k` most likely missing edges, as `(id_a, id_b, score)` with `id_a < id_b`,
+    /// best first with ties broken by the id pair. Scoring runs on the undirected
+    /// projection and covers every pair of nodes that shares a neighbor and has no edge
+    /// in either direction. Metrics: "common_neighbors", "adamic_adar" (shared
+    /// neighbors weighted by 1/ln(degree)) and "preferential_attachment" (product of
+    /// the degrees).
+    #[pyo3(signature = (k, metric = "common_neighbors"))]
+    fn predict_links(
+        &self,
+        py: Python,
+        k: usize,
+        metric: &str,
+    ) -> PyResult<Vec<(String, String, f64)>> {
+        let metric = linkpred::Metric::parse(metric)?;
+        let reverse = self.reverse_adjacency_cached();
+        Ok(py.allow_threads(|| {
+            linkpred::predict(&self.nodes, &reverse, |h| self.is_tombstoned(h), k, metric)
+        }))
+    }
+
+    /// `walks_per_node` node2vec walks from every node, as id lists of at most
+    /// `walk_length` nodes each (start included); a walk ends early at a node whose
+    /// edges all leave the graph. Each step follows an out-edge to another node, biased
+    /// by the return parameter `p` and the in-out parameter `q` (see
+    /// `algorithms::WalkSampler`); `p = q = 1` gives uniform walks. Walks come in rounds
+    /// over the nodes in id order, and walk `i` draws from its own generator derived
+    /// from `seed` and `i`, so a seed fixes the output whatever the thread count.
+    /// Without a seed one is taken from the clock. Raises ValueError if `walk_length` is
+    /// 0 or `p` or `q` is not positive and finite.
+    #[pyo3(signature = (walks_per_node, walk_length, p = 1.0, q = 1.0, seed = None))]
+    fn generate_random_walks(
+        &self,
+        py: Python,
+        walks_per_node: usize,
+        walk_length: usize,
+        p: f64,
+        q: f64,
+        seed: Option<u64>,
+    ) -> PyResult<Vec<Vec<String>>> {
+        let seed = walk_seed_for(walk_length, p, q, seed)?;
+        py.allow_threads(|| {
+            let graph = algorithms::IndexedGraph::new(&self.nodes);
+            let sampler = algorithms::WalkSampler::new(&graph, p, q);
+            let n = graph.len();
+            Ok(config::install(|| {
+                (0..walks_per_node * n)
+                    .into_par_iter()
+                    .map(|i| {
+                        let walk = sampler.walk(i % n, walk_length, algorithms::walk_seed(seed, i));
+                        walk.into_iter().map(|v| graph.ids[v].clone()).collect()
+                    })
+                    .collect()
+            }))
+        })
+    }
+
+    /// `generate_random_walks` streamed to `path`, one walk per line with ids
+    /// separated by single spaces (the format word2vec tools read). Walks are
+    /// generated in parallel batches and written in the same order with the same
+    /// seed handling. Returns the number of walks written. Raises ValueError if a node
+    /// id contains whitespace, and IOError if the file cannot be written.
+    #[pyo3(signature = (path, walks_per_node, walk_length, p = 1.0, q = 1.0, seed = None))]
+    #[allow(clippy::too_many_arguments)]
+    fn write_random_walks(
+        &self,
+        py: Python,
+        path: &str,
+        walks_per_node: usize,
+        walk_length: usize,
+        p: f64,
+        q: f64,
+        seed: Option<u64>,
+    ) -> PyResult<usize> {
+        use std::io::Write;
+        const BATCH: usize = 1 << 14;
+        let seed = walk_seed_for(walk_length, p, q, seed)?;
+        py.allow_threads(|| {
+            let graph = algorithms::IndexedGraph::new(&self.nodes);
+            if let Some(id) = graph.ids.iter().find(|id| id.contains(char::is_whitespace)) {
+                return Err(PyValueError::new_err(format!(
+                    "node id {:?} contains whitespace and cannot be written as a walk token",
+                    id
+                )));
+            }
+            let sampler = algorithms::WalkSampler::new(&graph, p, q);
+            let n = graph.len();
+            let total = walks_per_node * n;
+            let io = |e: std::io::Error| PyIOError::new_err(e.to_string());
+            let mut out = create_buffered(path)?;
+            for first in (0..total).step_by(BATCH) {
+                let batch: Vec<Vec<usize>> = config::install(|| {
+                    (first..total.min(first + BATCH))
+                        .into_par_iter()
+                        .map(|i| sampler.walk(i % n, walk_length, algorithms::walk_seed(seed, i)))
+                        .collect()
+                });
+                for walk in batch {
+                    for (j, &v) in walk.iter().enumerate() {
+                        if j > 0 {
+                            out.write_all(b" ").map_err(io)?;
+                        }
+                        out.write_all(graph.ids[v].as_bytes()).map_err(io)?;
+                    }
+                    out.write_all(b"\n").map_err(io)?;
+                }
+            }
+            out.flush().map_err(io)?;
+            Ok(total)
+        })
+    }
+
+    /// Jaccard similarity of the two nodes' dependents. In `undirected_multi` mode it
+    /// is the weighted form over neighbors: sum of the smaller pair counts over sum
+    /// of the larger. Raises NodeNotFoundError for an unknown node, unless
+    /// `strict=False`, which treats it as having no dependents.
+    #[pyo3(signature = (node_a, node_b, strict = true))]
+    fn calculate_jaccard_similarity(
+        &self,
+        node_a: String,
+        node_b: String,
+        strict: bool,
+    ) -> PyResult<f64> {
+        let (node_a, node_b) = (self.lookup_id(node_a)?, self.lookup_id(node_b)?);
+        if strict {
+            for id in [&node_a, &node_b] {
+                if !self.nodes.contains_key(id) {
+                    return Err(NodeNotFoundError::new_err(id.clone()));
+                }
+            }
+        }
+        if !self.mode.is_directed() {
+            return Ok(self.weighted_jaccard(&node_a, &node_b));
+        }
+        let empty = Vec::new();
+        let deps_a: HashSet<_> = self
+            .nodes
+            .get(&node_a)
+            .map(|n| &n.dependents)
+            .unwrap_or(&empty)
+            .iter()
+            .collect();
+        let deps_b: HashSet<_> = self
+            .nodes
+            .get(&node_b)
+            .map(|n| &n.dependents)
+            .unwrap_or(&empty)
+            .iter()
+            .collect();
+
+        let intersection = deps_a.intersection(&deps_b).count();
+        let union = deps_a.union(&deps_b).count();
+
+        if union == 0 {
+            Ok(0.0)
+        } else {
+            Ok(intersection as f64 / union as f64)
+        }
+    }
+
+    /// Maximum flow from `source` to `sink` (Edmonds–Karp). Every edge between
+    /// nodes has capacity 1, counted once per parallel edge, unless `capacities`
+    /// gives a `(from, to)` edge its own. Raises ValueError if either end is not a
+    /// node, or for a negative capacity or one given for a pair that is not an
+    /// edge. `source == sink` gives 0.
+    #[pyo3(signature = (source, sink, capacities = None))]
+    fn max_flow(
+        &self,
+        py: Python,
+        source: String,
+        sink: String,
+        capacities: Option<HashMap<(String, String), i64>>,
+    ) -> PyResult<i64> {
+        let (source, sink) = (self.lookup_id(source)?, self.lookup_id(sink)?);
+        let capacities = capacities.map(|c| self.lookup_capacities(c)).transpose()?;
+        py.allow_threads(|| Ok(self.solve_flow(&source, &sink, capacities)?.0))
+    }
+
+    /// Edges of a minimum `source`-`sink` cut, taken from the residual network
+    /// `max_flow` leaves behind: every original edge from the side still reachable
+    /// from `source` to the other. Their capacities sum to the max flow. Same
+    /// arguments and errors as `max_flow`; `source == sink` gives an empty set.
+    #[pyo3(signature = (source, sink, capacities = None))]
+    fn min_cut(
+        &self,
+        py: Python,
+        source: String,
+        sink: String,
+        capacities: Option<HashMap<(String, String), i64>>,
+    ) -> PyResult<HashSet<(String, String)>> {
+        let (source, sink) = (self.lookup_id(source)?, self.lookup_id(sink)?);
+        let capacities = capacities.map(|c| self.lookup_capacities(c)).transpose()?;
+        py.allow_threads(|| Ok(self.solve_flow(&source, &sink, capacities)?.1))
+    }
+
+    /// Minimum spanning forest over edge weights, edges taken as undirected: one
+    /// tree per connected component, so disconnected parts are covered too. Returns
+    /// `(total_weight, edges)` with each edge as `(smaller id, larger id)`, sorted.
+    /// Pairs linked in both directions or several times count once, with their
+    /// smallest weight. Edges to ids that are not nodes are ignored.
+    fn minimum_spanning_tree(&self, py: Python) -> (f64, Vec<(String, String)>) {
+        py.allow_threads(|| {
+            let graph = algorithms::IndexedGraph::new(&self.nodes);
+            let (total, edges) = algorithms::minimum_spanning_forest(&graph);
+            let edges = edges
+                .into_iter()
+                .map(|(u, v)| (graph.ids[u].clone(), graph.ids[v].clone()))
+                .collect();
+            (total, edges)
+        })
+    }
+
+    /// Sorted ids of the k-core: what remains after repeatedly removing nodes with
+    /// fewer than `k` neighbors, edges taken as undirected. That is exactly the
+    /// nodes whose core number is at least `k`, so this reads the cached
+    /// `core_numbers`.
+    fn k_core_decomposition(&self, py: Python, k: usize) -> Vec<String> {
+        let cores = self.core_numbers_cached(py);
+        let mut ids: Vec<String> = cores
+            .iter()
+            .filter(|&(_, &core)| core >= k)
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Core number of every node: the largest k whose k-core contains it, with
+    /// edges taken as undirected and parallel edges and self loops ignored.
+    /// Computed in one O(V + E) pass (Batagelj–Zaversnik) and cached until the
+    /// next mutation.
+    fn core_numbers(&self, py: Python) -> HashMap<String, usize> {
+        (*self.core_numbers_cached(py)).clone()
+    }
+}
+
+struct Freeze {
+    /// `version` when the graph was frozen.
+    version: u64,
+    /// Buffer consumer events (true) or reject them (false).
+    buffer: bool,
+    buffered: Vec<GraphEvent>,
+}
+
+fn parse_events(events: &[&PyAny]) -> PyResult<Vec<GraphEvent>> {
+    events
+        .iter()
+        .enumerate()
+        .map(|(i, ev)| {
+            serde_json::from_value::<GraphEvent>(pyjson::to_value(ev)?)
+                .map_err(|e| PyValueError::new_err(format!("event {}: {}", i, e)))
+        })
+        .collect()
+}
+
+enum PathSearch {
+    Found(Vec<String>),
+    Absent,
+    /// The step budget ran out before the search finished.
+    Exhausted,
+}
+
+/// How many missing ids a strict bulk operation lists in its error message.
+const MISSING_IDS_IN_ERROR: usize = 10;
+
+/// Default `max_pairs` of `shortest_path_lengths` and `distance_matrix`: room for a
+/// matrix over about 3,000 ids.
+const DEFAULT_MAX_PAIRS: usize = 10_000_000;
+
+impl GraphWalker {
+    /// The body of `submit_events`, also used by consumers (see `pipeline`).
+    fn submit(&mut self, events: Vec<GraphEvent>) -> PyResult<usize> {
+        match self.frozen.as_mut() {
+            Some(freeze) if freeze.buffer => {
+                freeze.buffered.extend(events);
+                Ok(0)
+            }
+            Some(_) => Err(FrozenGraphError::new_err(
+                "graph is frozen; consumer paused until unfreeze()",
+            )),
+            None => {
+                let n = events.len();
+                let mut events = events.into_iter().peekable();
+                while let Some(result) = events::apply_next(&mut events, self) {
+                    result?;
+                }
+                Ok(n)
+            }
+        }
+    }
+
+    /// The traversal of `propagate_invalidation`, recording heat.
+    fn propagate(
+        &self,
+        start_id: &str,
+        limits: &Limits,
+        follow: Option<&HashSet<&str>>,
+        attribute_filter: Option<&HashMap<String, String>>,
+    ) -> PyResult<HashSet<String>> {
+        let invalid_set = self.invalidation_bfs(
+            self.nodes.lookup(start_id),
+            limits,
+            follow,
+            attribute_filter,
+            None,
+        )?;
+        let invalid_set: HashSet<String> = self.nodes.names(invalid_set);
+        if let Some(heat) = &self.heat {
+            heat.record(&invalid_set);
+        }
+        Ok(invalid_set)
+    }
+
+    /// An empty walker with every optional feature off.
+    fn blank() -> Self {
+        GraphWalker::new(false, false, false, false, None, false, "directed", None)
+            .expect("default walker options are valid")
+    }
+
+    /// Constructor settings and state that the snapshot format leaves out: the
+    /// normalizer, `cycle_check_steps`, whether heat, provenance and events are
+    /// tracked, and the event log itself.
+    fn settings<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let settings = PyDict::new(py);
+        let normalize = self.normalizer.as_ref().map(|n| n.to_py(py));
+        settings.set_item("normalize", normalize)?;
+        settings.set_item("cycle_check_steps", self.cycle_check_steps)?;
+        settings.set_item("track_heat", self.heat.is_some())?;
+        settings.set_item("track_provenance", self.provenance.is_some())?;
+        let events = match &self.events {
+            Some(events) => Some(
+                serde_json::to_string(events)
+                    .map_err(|e| SerializationError::new_err(e.to_string()))?,
+            ),
+            None => None,
+        };
+        settings.set_item("events", events)?;
+        Ok(settings)
+    }
+
+    /// Applies `settings` to a walker just decoded from a snapshot.
+    fn apply_settings(&mut self, settings: &PyDict) -> PyResult<()> {
+        let get = |key: &str| -> PyResult<&PyAny> {
+            settings
+                .get_item(key)?
+                .ok_or_else(|| SerializationError::new_err(format!("pickle state lacks '{}'", key)))
+        };
+        let normalize = get("normalize")?;
+        self.normalizer = if normalize.is_none() {
+            None
+        } else {
+            Some(Normalizer::from_py(normalize)?)
+        };
+        self.cycle_check_steps = get("cycle_check_steps")?.extract()?;
+        self.heat = get("track_heat")?.extract::<bool>()?.then(Heat::default);
+        if !get("track_provenance")?.extract::<bool>()? {
+            self.provenance = None;
+        } else if self.provenance.is_none() {
+            self.provenance = Some(ProvenanceStore::default());
+        }
+        let events: Option<String> = get("events")?.extract()?;
+        self.events =
+            match events {
+                Some(json) => Some(serde_json::from_str(&json).map_err(|e| {
+                    SerializationError::new_err(format!("malformed event log: {}", e))
+                })?),
+                None => None,
+            };
+        Ok(())
+    }
+
+    /// The body of `merge`, without the rollback.
+    fn merge_from(
+        &mut self,
+        py: Python,
+        other: &GraphWalker,
+        on_conflict: Option<&PyAny>,
+    ) -> PyResult<HashMap<&'static str, usize>> {
+        if other.mode != self.mode {
+            return Err(ModeError::new_err(format!(
+                "cannot merge a '{}' walker into a '{}' walker",
+                other.mode.name(),
+                self.mode.name()
+            )));
+        }
+        let directed = self.mode.is_directed();
+        let (mut nodes_added, mut edges_added, mut conflicts) = (0, 0, 0);
+        self.nodes.reserve(other.nodes.len());
+        // `other`'s handles translated into ours, filled in as ids are met.
+        let mut handles = vec![UNSEEN; other.nodes.ids().len()];
+
+        // Nodes and attributes first, so that schema checks on the edges see the
+        // merged kinds.
+        for (other_h, node) in other.nodes.handles() {
+            let h = self.adopt(other, &mut handles, other_h)?;
+            if !self.nodes.contains(h) {
+                let id = self.nodes.name(h).clone();
+                self.record_mutation(|| GraphEvent::AddNode {
+                    id: id.clone(),
+                    dependents: Vec::new(),
+                });
+                self.tombstones.remove(&id);
+                self.nodes.insert_at(h, CausalNode::new());
+                nodes_added += 1;
+            }
+            let mut attributes: Vec<(&String, &String)> = node.attributes.iter().collect();
+            attributes.sort();
+            for (key, theirs) in attributes {
+                let id = self.nodes.name(h).clone();
+                let value = match self.nodes.node(h).and_then(|n| n.attributes.get(key)) {
+                    Some(ours) if ours == theirs => continue,
+                    Some(ours) => {
+                        conflicts += 1;
+                        let ours = ours.clone();
+                        let kept = resolve(
+                            py,
+                            on_conflict,
+                            "attribute",
+                            (&id, key),
+                            ours.clone(),
+                            theirs.clone(),
+                        )?;
+                        if kept == ours {
+                            continue;
+                        }
+                        kept
+                    }
+                    None => theirs.clone(),
+                };
+                self.apply_normalized_attrs(vec![id], key.clone(), vec![value], true)?;
+            }
+        }
+
+        for (other_from, node) in other.nodes.handles() {
+            let from = handles[other_from as usize];
+            for (i, &other_to) in node.dependents.iter().enumerate() {
+                let to = self.adopt(other, &mut handles, other_to)?;
+                // Each undirected pair is stored as two halves; fold it in once.
+                if !directed && self.nodes.name(from) > self.nodes.name(to) {
+                    continue;
+                }
+                let (theirs, ts) = (node.weights[i], node.observed_at[i]);
+                let existing = self.nodes.node(from).and_then(|ours| {
+                    let j = ours.edge_index(to)?;
+                    Some((ours.weights[j], ours.observed_at[j], ours.labels[j].clone()))
+                });
+                let (weight, label) = match existing {
+                    None => {
+                        edges_added += 1;
+                        (theirs, node.labels[i].clone())
+                    }
+                    Some((ours, _, _)) if !directed => (ours + theirs, None),
+                    Some((ours, ours_ts, ours_label)) => {
+                        let key = (self.nodes.name(from).as_str(), self.nodes.name(to).as_str());
+                        let weight = if ours == theirs {
+                            ours
+                        } else {
+                            conflicts += 1;
+                            let kept = resolve(py, on_conflict, "weight", key, ours, theirs)?;
+                            check_weight(kept)?;
+                            kept
+                        };
+                        let label = match (&ours_label, &node.labels[i]) {
+                            (Some(a), Some(b)) if a != b => {
+                                conflicts += 1;
+                                Some(resolve(
+                                    py,
+                                    on_conflict,
+                                    "label",
+                                    key,
+                                    a.clone(),
+                                    b.clone(),
+                                )?)
+                            }
+                            (a, b) => b.clone().or_else(|| a.clone()),
+                        };
+                        if weight == ours && label == ours_label && ts <= ours_ts {
+                            continue;
+                        }
+                        (weight, label)
+                    }
+                };
+                let origin = other.provenance.as_ref().and_then(|store| {
+                    let (f, t) = (other.nodes.name(other_from), other.nodes.name(other_to));
+                    Some(store.to_record(&store.get(other.nodes.ids(), f, t)?))
+                });
+                self.merge_edge_at(from, to, weight, ts, label, origin)?;
+            }
+        }
+        Ok(HashMap::from([
+            ("nodes_added", nodes_added),
+            ("edges_added", edges_added),
+            ("conflicts", conflicts),
+        ]))
+    }
+
+    /// Our handle for `other`'s handle `h`, looked up in `handles` or adopted: the
+    /// id is normalized by this walker's steps, starting from `other`'s original
+    /// form of it if it kept one, and interned.
+    fn adopt(&mut self, other: &GraphWalker, handles: &mut [u32], h: u32) -> PyResult<u32> {
+        if handles[h as usize] != UNSEEN {
+            return Ok(handles[h as usize]);
+        }
+        let id = other.nodes.name(h);
+        let id = match (&self.normalizer, other.original_ids.get(id)) {
+            (Some(_), Some(original)) => self.normalize_id(original.clone())?,
+            _ => self.normalize_id(id.clone())?,
+        };
+        handles[h as usize] = self.nodes.intern(&id);
+        Ok(handles[h as usize])
+    }
+
+    /// `raw`, built by a constructor that takes ids as given, with its ids
+    /// normalized by `normalizer`: merged into a new walker with it, so each id is
+    /// normalized once from its original form and ids that normalize alike become
+    /// one node. Settings, tombstones and invalid marks carry over.
+    fn renormalized(
+        py: Python,
+        raw: GraphWalker,
+        normalizer: Option<Normalizer>,
+    ) -> PyResult<GraphWalker> {
+        let Some(normalizer) = normalizer else {
+            return Ok(raw);
+        };
+        let mut walker = GraphWalker::blank();
+        walker.mode = raw.mode;
+        walker.normalizer = Some(normalizer);
+        walker.provenance = raw.provenance.as_ref().map(|_| ProvenanceStore::default());
+        walker.merge_from(py, &raw, None)?;
+        for (id, ts) in &raw.tombstones {
+            let original = raw.original_ids.get(id).unwrap_or(id);
+            let id = walker.normalize_id(original.clone())?;
+            walker.tombstones.insert(id, ts.to_owned());
+        }
+        for h in raw.dirty.handles() {
+            let original = raw.nodes.name(h);
+            let original = raw.original_ids.get(original).unwrap_or(original);
+            let id = walker.normalize_id(original.clone())?;
+            let h = walker.nodes.intern(&id);
+            walker.dirty.insert_open(h);
+        }
+        walker.enforce_dag = raw.enforce_dag;
+        walker.enforce_schema = raw.enforce_schema;
+        walker.schema = raw.schema;
+        Ok(walker)
+    }
+
+    /// Sets `from -> to` to `weight` and `label` and advances its timestamp to `ts`,
+    /// creating the edge (and `from`) if needed; in `undirected_multi` mode both
+    /// halves of the pair get `weight` as their count. Used by replay of `merge_edge`
+    /// events.
+    fn merge_edge(
+        &mut self,
+        from: &str,
+        to: &str,
+        weight: f64,
+        ts: f64,
+        label: Option<String>,
+    ) -> PyResult<()> {
+        let (from, to) = (self.nodes.intern(from), self.nodes.intern(to));
+        self.merge_edge_at(from, to, weight, ts, label, None)
+    }
+
+    /// `merge_edge` by handle. A new edge records `origin` as its provenance, or the
+    /// API if None.
+    fn merge_edge_at(
+        &mut self,
+        from: u32,
+        to: u32,
+        weight: f64,
+        ts: f64,
+        label: Option<String>,
+        origin: Option<ProvenanceRecord>,
+    ) -> PyResult<()> {
+        self.ensure_mutable()?;
+        let (from_id, to_id) = (self.nodes.name(from).clone(), self.nodes.name(to).clone());
+        let current = self
+            .nodes
+            .node(from)
+            .and_then(|n| n.edge_index(to).map(|i| n.labels[i].as_deref()));
+        let is_new = current.is_none();
+        if is_new && self.enforce_dag {
+            self.check_acyclic(&from_id, &to_id)?;
+        }
+        if current != Some(label.as_deref()) {
+            self.check_schema(&from_id, &to_id, label.as_deref())?;
+        }
+        self.record_mutation(|| GraphEvent::MergeEdge {
+            from: from_id.clone(),
+            to: to_id.clone(),
+            weight,
+            ts,
+            label: label.clone(),
+        });
+        let halves = if self.mode.is_directed() || from == to {
+            vec![(from, to, &from_id, &to_id)]
+        } else {
+            vec![(from, to, &from_id, &to_id), (to, from, &to_id, &from_id)]
+        };
+        for (a, b, a_id, b_id) in halves {
+            self.tombstones.remove(a_id);
+            let node = self.nodes.get_or_insert_at(a);
+            match node.edge_index(b) {
+                Some(i) => {
+                    node.weights[i] = weight;
+                    node.observed_at[i] = node.observed_at[i].max(ts);
+                    node.labels[i] = label.clone();
+                }
+                None => {
+                    node.push_labeled_edge(b, weight, ts, label.clone());
+                    match &origin {
+                        Some(p) => self.record_provenance(
+                            a_id,
+                            b_id,
+                            &p.source,
+                            p.partition,
+                            p.offset,
+                            p.timestamp,
+                        ),
+                        None => self.record_provenance(a_id, b_id, API_SOURCE, None, None, ts),
+                    }
+                }
+            }
+        }
+        if is_new {
+            self.check_new_edge_cycle(&from_id, &to_id);
+        }
+        Ok(())
+    }
+
+    /// Sets the weights of existing edges out of `from`; edges that do not exist are
+    /// skipped. Used by `record_outcome` and by replay of its events.
+    fn set_weights(&mut self, from: String, weights: Vec<(String, f64)>) -> PyResult<()> {
+        self.ensure_mutable()?;
+        self.record_mutation(|| GraphEvent::SetWeights {
+            from: from.clone(),
+            weights: weights.clone(),
+        });
+        for (to, w) in weights {
+            if let Some(i) = self.nodes.edge_index(&from, &to) {
+                let node = self
+                    .nodes
+                    .get_mut(&from)
+                    .ok_or_else(|| NodeNotFoundError::new_err(from.clone()))?;
+                node.weights[i] = w;
+            }
+        }
+        Ok(())
+    }
+
+    /// The body of `add_edge` for normalized ids. `label` is the label the edge
+    /// carries afterwards, which the schema checks it with.
+    fn insert_edge(
+        &mut self,
+        from_id: String,
+        to_id: String,
+        weight: Option<f64>,
+        label: Option<&str>,
+    ) -> PyResult<()> {
+        if let Some(w) = weight {
+            self.mode.require_directed("add_edge with a weight")?;
+            check_weight(w)?;
+        }
+        if !self.mode.is_directed() {
+            self.check_schema(&from_id, &to_id, label)?;
+            self.record_mutation(|| GraphEvent::AddEdge {
+                from: from_id.clone(),
+                to: to_id.clone(),
+            });
+            self.observe_pair(&from_id, &to_id, now_secs());
+            return Ok(());
+        }
+        if self.enforce_dag {
+            self.check_acyclic(&from_id, &to_id)?;
+        }
+        self.check_schema(&from_id, &to_id, label)?;
+        self.record_mutation(|| GraphEvent::AddEdge {
+            from: from_id.clone(),
+            to: to_id.clone(),
+        });
+        self.tombstones.remove(&from_id);
+        let to = self.nodes.intern(&to_id);
+        let node = self.nodes.get_or_insert(&from_id);
+        if node.edge_index(to).is_none() {
+            let now = now_secs();
+            node.push_edge(to, 1.0, now);
+            self.record_provenance(&from_id, &to_id, API_SOURCE, None, None, now);
+            self.check_new_edge_cycle(&from_id, &to_id);
+        }
+        if let Some(w) = weight {
+            self.set_weights(from_id, vec![(to_id, w)])?;
+        }
+        Ok(())
+    }
+
+    /// Label of the existing edge `from -> to`, if it has one.
+    fn edge_label(&self, from: &str, to: &str) -> Option<&str> {
+        let i = self.nodes.edge_index(from, to)?;
+        self.nodes.get(from)?.labels[i].as_deref()
+    }
+
+    /// Sets the label of the existing edge `from -> to`; a missing edge is skipped.
+    /// Used by `add_edge_labeled` and by replay of its events.
+    fn set_label(&mut self, from: String, to: String, label: Option<String>) -> PyResult<()> {
+        self.ensure_mutable()?;
+        if self.nodes.has_edge(&from, &to) {
+            self.check_schema(&from, &to, label.as_deref())?;
+        }
+        self.record_mutation(|| GraphEvent::SetLabel {
+            from: from.clone(),
+            to: to.clone(),
+            label: label.clone(),
+        });
+        if let Some(i) = self.nodes.edge_index(&from, &to) {
+            let node = self
+                .nodes
+                .get_mut(&from)
+                .ok_or_else(|| NodeNotFoundError::new_err(from.clone()))?;
+            node.labels[i] = label;
+        }
+        Ok(())
+    }
+
+    fn record_mutation(&mut self, make: impl FnOnce() -> GraphEvent) {
+        self.version += 1;
+        let components = self.components.get_mut().unwrap_or_else(|e| e.into_inner());
+        if self.events.is_none() && !components.is_live() && self.dirty.is_empty() {
+            return;
+        }
+        let event = make();
+        components.observe(&event);
+        self.dirty.observe(&event);
+        if let Some(log) = self.events.as_mut() {
+            log.push(event);
+        }
+    }
+
+    /// Reopens the invalid ids that mutations since the last call may have left
+    /// with clean ids downstream (see `dirty`).
+    fn sync_dirty(&mut self) {
+        if self.dirty.is_empty() {
+            return;
+        }
+        let reverse = self.reverse_adjacency_cached();
+        self.dirty.sync(&self.nodes, &reverse);
+    }
+
+    fn recorded_events(&self) -> PyResult<&Vec<GraphEvent>> {
+        self.events.as_ref().ok_or_else(|| {
+            PyValueError::new_err(
+                "event recording is disabled; create the walker with record_events=True",
+            )
+        })
+    }
+
+    /// Records where `from -> to` came from, if provenance tracking is enabled.
+    fn record_provenance(
+        &mut self,
+        from: &str,
+        to: &str,
+        source: &str,
+        partition: Option<u32>,
+        offset: Option<u64>,
+        ts: f64,
+    ) {
+        let Some(store) = self.provenance.as_mut() else {
+            return;
+        };
+        let (source, partition, offset) = match &self.origin {
+            Some(origin) if source == API_SOURCE => {
+                (origin.topic.as_str(), origin.partition, origin.offset)
+            }
+            _ => (source, partition, offset),
+        };
+        store.record(
+            self.nodes.ids_mut(),
+            from,
+            to,
+            source,
+            partition,
+            offset,
+            ts,
+        );
+    }
+
+    /// A new walker with the nodes of `keep` (handles that are not nodes are
+    /// skipped) and the edges between them, for `subgraph` and `ego_graph`.
+    fn induced(&self, keep: &HashSet<u32>) -> GraphWalker {
+        let mut walker = GraphWalker::blank();
+        walker.mode = self.mode;
+        walker.enforce_dag = self.enforce_dag;
+        walker.enforce_schema = self.enforce_schema;
+        walker.schema = self.schema.clone();
+        if self.provenance.is_some() {
+            walker.provenance = Some(ProvenanceStore::default());
+        }
+        // Handle order, so the new walker interns ids in the same relative order.
+        let mut handles: Vec<(u32, &CausalNode)> = keep
+            .iter()
+            .filter_map(|&h| Some((h, self.nodes.node(h)?)))
+            .collect();
+        handles.sort_unstable_by_key(|&(h, _)| h);
+        for (h, node) in handles {
+            let id = self.nodes.name(h);
+            let mut copy = CausalNode::new();
+            copy.attributes = node.attributes.clone();
+            for (i, &dep) in node.dependents.iter().enumerate() {
+                if !keep.contains(&dep) || !self.nodes.contains(dep) {
+                    continue;
+                }
+                let to = self.nodes.name(dep);
+                copy.push_labeled_edge(
+                    walker.nodes.intern(to),
+                    node.weights[i],
+                    node.observed_at[i],
+                    node.labels[i].clone(),
+                );
+                let origin = self.provenance.as_ref().and_then(|store| {
+                    let p = store.get(self.nodes.ids(), id, to)?;
+                    Some(store.to_record(&p))
+                });
+                if let Some(p) = origin {
+                    walker.record_provenance(id, to, &p.source, p.partition, p.offset, p.timestamp);
+                }
+            }
+            walker.nodes.insert(id, copy);
+            if let Some(original) = self.original_ids.get(id) {
+                walker.original_ids.insert(id.clone(), original.clone());
+            }
+        }
+        walker
+    }
+
+    /// Drops the provenance of `old`'s edges out of `id`, except those `kept` also has.
+    fn forget_edges(&mut self, id: &str, old: &CausalNode, kept: Option<&CausalNode>) {
+        let Some(store) = self.provenance.as_mut() else {
+            return;
+        };
+        for &dep in &old.dependents {
+            if kept.is_some_and(|k| k.edge_index(dep).is_some()) {
+                continue;
+            }
+            store.forget(self.nodes.ids(), id, self.nodes.name(dep));
+        }
+    }
+
+    fn is_tombstoned(&self, h: u32) -> bool {
+        !self.tombstones.is_empty() && self.tombstones.contains_key(self.nodes.name(h))
+    }
+
+    /// `add_nodes_bulk` through the regular mutators, so every DAG, schema and
+    /// cycle check (and `undirected_multi` pairing) applies.
+    fn add_nodes_checked(
+        &mut self,
+        entries: Vec<(String, Vec<String>)>,
+        merge: bool,
+    ) -> PyResult<HashMap<&'static str, usize>> {
+        let (mut created, mut updated) = (0, 0);
+        for (id, deps) in entries {
+            if !self.nodes.contains_key(&id) {
+                created += 1;
+                self.add_node(id, deps, None, None)?;
+            } else if merge {
+                updated += 1;
+                for dep in deps {
+                    // Re-adding an existing pair would count another observation.
+                    if self.nodes.edge_index(&id, &dep).is_none() {
+                        self.add_edge(id.clone(), dep, None)?;
+                    }
+                }
+            } else {
+                updated += 1;
+                self.add_node(id, deps, None, None)?;
+            }
+        }
+        Ok(HashMap::from([("created", created), ("updated", updated)]))
+    }
+
+    fn core_numbers_cached(&self, py: Python) -> Arc<HashMap<String, usize>> {
+        py.allow_threads(|| {
+            self.metric_cache
+                .get_or_compute(self.version, "core_numbers", || {
+                    let graph = algorithms::IndexedGraph::new(&self.nodes);
+                    let cores = algorithms::core_numbers(&graph);
+                    graph
+                        .ids
+                        .iter()
+                        .map(|id| (*id).clone())
+                        .zip(cores)
+                        .collect::<HashMap<String, usize>>()
+                })
+        })
+    }
+
+    /// Triangles through each node and its neighbor count, both over the
+    /// undirected projection.
+    fn triangles_cached(&self, py: Python) -> Arc<HashMap<String, (usize, usize)>> {
+        py.allow_threads(|| {
+            self.metric_cache
+                .get_or_compute(self.version, "triangles", || {
+                    let graph = algorithms::IndexedGraph::new(&self.nodes);
+                    let neighbors = algorithms::undirected_neighbors(&graph);
+                    let triangles = config::install(|| algorithms::triangles_per_node(&neighbors));
+                    graph
+                        .ids
+                        .iter()
+                        .zip(triangles.into_iter().zip(neighbors.iter().map(Vec::len)))
+                        .map(|(id, counts)| ((*id).clone(), counts))
+                        .collect::<HashMap<String, (usize, usize)>>()
+                })
+        })
+    }
+
+    fn scc_cached(&self, py: Python) -> Arc<Vec<Vec<String>>> {
+        py.allow_threads(|| {
+            self.metric_cache.get_or_compute(self.version, "scc", || {
+                let graph = algorithms::IndexedGraph::new(&self.nodes);
+                // Members come back as sorted indices into sorted ids, so sorting
+                // by first index orders components by smallest member.
+                let mut components = algorithms::tarjan_scc(&graph.adj);
+                components.sort_unstable_by_key(|members| members[0]);
+                components
+                    .into_iter()
+                    .map(|members| members.into_iter().map(|i| graph.ids[i].clone()).collect())
+                    .collect::<Vec<Vec<String>>>()
+            })
+        })
+    }
+
+    /// Raises NodeNotFoundError unless `from` is a node and `to` is a node or an
+    /// edge target.
+    fn require_path_ends(&self, from: &str, to: &str) -> PyResult<()> {
+        if !self.nodes.contains_key(from) {
+            return Err(NodeNotFoundError::new_err(from.to_string()));
+        }
+        self.require_path_target(to)
+    }
+
+    /// Handles of `ids`, raising NodeNotFoundError for the first that is not a node.
+    fn require_sources(&self, ids: &[String]) -> PyResult<Vec<u32>> {
+        ids.iter()
+            .map(|id| match self.nodes.handle(id) {
+                Some(h) if self.nodes.contains(h) => Ok(h),
+                _ => Err(NodeNotFoundError::new_err(id.clone())),
+            })
+            .collect()
+    }
+
+    /// Raises NodeNotFoundError unless `to` is a node or an edge target.
+    fn require_path_target(&self, to: &str) -> PyResult<()> {
+        let is_target = self
+            .nodes
+            .handle(to)
+            .is_some_and(|h| !sources_of(&self.reverse_adjacency_cached(), h).is_empty());
+        if !self.nodes.contains_key(to) && !is_target {
+            return Err(NodeNotFoundError::new_err(to.to_string()));
         }
+        Ok(())
     }
 
-    fn add_node(&mut self, id: String, dependents: Vec<String>) {
-        let node = CausalNode {
-            id: id.clone(),
-            dependents,
-        };
-        self.nodes.insert(id, node);
+    /// Adjacency over indices: nodes first, in sorted id order (`0..n`, with `n`
+    /// returned alongside), then every edge target that is not a node.
+    fn adjacency_with_targets(&self) -> (usize, Vec<Vec<usize>>) {
+        let nodes = self.nodes.sorted_nodes();
+        let n = nodes.len();
+        let mut index: HashMap<u32, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, &(id, _))| (self.nodes.lookup(id), i))
+            .collect();
+        let mut adj: Vec<Vec<usize>> = Vec::with_capacity(n);
+        for (_, node) in &nodes {
+            let out = node
+                .dependents
+                .iter()
+                .map(|&dep| {
+                    let next = index.len();
+                    *index.entry(dep).or_insert(next)
+                })
+                .collect();
+            adj.push(out);
+        }
+        adj.resize(index.len(), Vec::new());
+        (n, adj)
     }
 
-    /// Propagates invalidation from a starting node to all affected dependents.
-    /// Uses parallel iteration for improved performance on large graphs.
-    fn propagate_invalidation(&self, start_id: String) -> HashSet<String> {
-        let mut invalid_set = HashSet::new();
-        let mut frontier = vec![start_id];
+    /// BFS distances from `start` to every id it reaches (itself at 0), following
+    /// edges forward. With `goal`, stops once the goal's distance is known.
+    fn bfs_distances(&self, start: u32, goal: Option<u32>) -> HashMap<u32, usize> {
+        let mut dist = HashMap::from([(start, 0)]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(u) = queue.pop_front() {
+            if goal == Some(u) {
+                break;
+            }
+            let Some(node) = self.nodes.node(u) else {
+                continue;
+            };
+            let d = dist[&u] + 1;
+            for &v in &node.dependents {
+                if let Entry::Vacant(slot) = dist.entry(v) {
+                    slot.insert(d);
+                    queue.push_back(v);
+                }
+            }
+        }
+        dist
+    }
 
-        while !frontier.is_empty() {
-            let next_frontier: HashSet<String> = frontier
+    /// The traversal behind `propagate_invalidation`: `concord_core::propagate_with`
+    /// from `start`, gathering each frontier's edges in parallel. With `parents`, also records for
+    /// every invalidated handle the handles of the previous level with a followed
+    /// edge to it, sorted by id and distinct.
+    fn invalidation_bfs(
+        &self,
+        start: u32,
+        limits: &Limits,
+        follow: Option<&HashSet<&str>>,
+        attribute_filter: Option<&HashMap<String, String>>,
+        mut parents: Option<&mut HashMap<u32, Vec<u32>>>,
+    ) -> PyResult<HashSet<u32>> {
+        let expand = |frontier: &[u32]| -> Vec<(u32, u32)> {
+            config::install(|| {
+                frontier
+                    .par_iter()
+                    .filter_map(|&h| self.nodes.node(h).map(|node| (h, node)))
+                    .flat_map_iter(|(h, node)| {
+                        node.dependents
+                            .iter()
+                            .zip(&node.labels)
+                            .filter(|(_, label)| match follow {
+                                Some(follow) => {
+                                    label.as_deref().is_some_and(|label| follow.contains(label))
+                                }
+                                None => true,
+                            })
+                            .map(move |(&dep, _)| (h, dep))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .into_iter()
+            .filter(|&(_, item)| {
+                !self.is_tombstoned(item)
+                    && attribute_filter.is_none_or(|filter| self.matches_attributes(item, filter))
+            })
+            .collect()
+        };
+        let reached = |from: u32, item: u32, first: bool| {
+            if let Some(parents) = parents.as_deref_mut() {
+                if first {
+                    parents.insert(item, vec![from]);
+                } else {
+                    parents.entry(item).or_default().push(from);
+                }
+            }
+        };
+        let invalid_set = concord_core::propagate_with(start, &limits.core(), expand, reached)
+            .map_err(limits::core_error)?
+            .affected;
+        if let Some(parents) = parents {
+            for list in parents.values_mut() {
+                list.sort_unstable_by_key(|&h| self.nodes.name(h));
+                list.dedup();
+            }
+        }
+        Ok(invalid_set)
+    }
+
+    /// One BFS per source as in `bfs_distances`, run in parallel. `row` gets the
+    /// source, the distance of every handle (`u32::MAX` where unreached) and the
+    /// handles reached in BFS order, source first; returning None stops the
+    /// remaining searches and makes the whole result None. With `goals`, a search
+    /// ends once it has reached every handle flagged there.
+    fn bfs_rows<R: Send>(
+        &self,
+        sources: &[u32],
+        goals: Option<&[bool]>,
+        row: impl Fn(u32, &[u32], &[u32]) -> Option<R> + Sync,
+    ) -> Option<Vec<R>> {
+        let n = self.nodes.ids().len();
+        let is_goal = |h: u32| goals.is_some_and(|g| g[h as usize]);
+        let goal_count = goals.map(|g| g.iter().filter(|&&x| x).count());
+        config::install(|| {
+            sources
                 .par_iter()
-                .filter_map(|node_id| self.nodes.get(node_id))
-                .flat_map(|node| node.dependents.clone())
-                .collect();
+                .map_init(
+                    || (vec![u32::MAX; n], Vec::new()),
+                    |(dist, queue), &start| {
+                        dist[start as usize] = 0;
+                        queue.push(start);
+                        let mut found = usize::from(is_goal(start));
+                        let mut head = 0;
+                        while head < queue.len() && goal_count != Some(found) {
+                            let u = queue[head];
+                            head += 1;
+                            let Some(node) = self.nodes.node(u) else {
+                                continue;
+                            };
+                            let d = dist[u as usize] + 1;
+                            for &v in &node.dependents {
+                                if dist[v as usize] == u32::MAX {
+                                    dist[v as usize] = d;
+                                    queue.push(v);
+                                    found += usize::from(is_goal(v));
+                                }
+                            }
+                        }
+                        let out = row(start, dist, queue);
+                        for &v in queue.iter() {
+                            dist[v as usize] = u32::MAX;
+                        }
+                        queue.clear();
+                        out
+                    },
+                )
+                .collect()
+        })
+    }
+
+    /// `capacities` of `max_flow` keyed by normalized ids.
+    fn lookup_capacities(
+        &self,
+        capacities: HashMap<(String, String), i64>,
+    ) -> PyResult<HashMap<(String, String), i64>> {
+        if self.normalizer.is_none() {
+            return Ok(capacities);
+        }
+        capacities
+            .into_iter()
+            .map(|((from, to), c)| Ok(((self.lookup_id(from)?, self.lookup_id(to)?), c)))
+            .collect()
+    }
 
-            frontier = Vec::new();
-            for item in next_frontier {
-                if invalid_set.insert(item.clone()) {
-                    frontier.push(item);
+    /// Max flow value and minimum cut edges for `max_flow` and `min_cut`.
+    fn solve_flow(
+        &self,
+        source: &str,
+        sink: &str,
+        capacities: Option<HashMap<(String, String), i64>>,
+    ) -> PyResult<(i64, HashSet<(String, String)>)> {
+        for (role, id) in [("source", source), ("sink", sink)] {
+            if !self.nodes.contains_key(id) {
+                return Err(PyValueError::new_err(format!(
+                    "flow {} '{}' is not a node",
+                    role, id
+                )));
+            }
+        }
+        let graph = algorithms::IndexedGraph::new(&self.nodes);
+        let index = |id: &str| graph.ids.binary_search_by(|x| x.as_str().cmp(id)).ok();
+        let mut edges: BTreeMap<(usize, usize), i64> = BTreeMap::new();
+        for (u, out) in graph.adj.iter().enumerate() {
+            for &v in out {
+                if u != v {
+                    *edges.entry((u, v)).or_default() += 1;
+                }
+            }
+        }
+        for ((from, to), cap) in capacities.unwrap_or_default() {
+            if cap < 0 {
+                return Err(PyValueError::new_err(format!(
+                    "capacity of ({}, {}) is negative",
+                    from, to
+                )));
+            }
+            match index(&from).zip(index(&to)).and_then(|e| edges.get_mut(&e)) {
+                Some(slot) => *slot = cap,
+                None => {
+                    return Err(PyValueError::new_err(format!(
+                        "capacity given for ({}, {}), which is not an edge",
+                        from, to
+                    )))
                 }
             }
         }
+        let (s, t) = (index(source).unwrap(), index(sink).unwrap());
+        if s == t {
+            return Ok((0, HashSet::new()));
+        }
+        let mut network = algorithms::FlowNetwork::new(graph.len());
+        for (&(u, v), &cap) in &edges {
+            network.add_edge(u, v, cap);
+        }
+        let flow = network.max_flow(s, t);
+        let side = network.source_side(s);
+        let cut = edges
+            .iter()
+            .filter(|&(&(u, v), &cap)| cap > 0 && side[u] && !side[v])
+            .map(|(&(u, v), _)| (graph.ids[u].clone(), graph.ids[v].clone()))
+            .collect();
+        Ok((flow, cut))
+    }
 
-        invalid_set
+    /// Raises NodeNotFoundError for the first of `ids` that is not a node, when
+    /// `strict`.
+    fn require_starts(&self, ids: &[String], strict: bool) -> PyResult<()> {
+        match ids.iter().find(|id| !self.nodes.contains_key(id)) {
+            Some(id) if strict => Err(NodeNotFoundError::new_err(id.clone())),
+            _ => Ok(()),
+        }
     }
 
-    // ------------------------------------------------------------------------
-    // Graph Analysis Algorithms
-    // ------------------------------------------------------------------------
+    /// State to restore if a batch fails, taken only when batches are atomic (with
+    /// `enforce_dag` or `enforce_schema`).
+    fn checkpoint(&self) -> Option<Checkpoint> {
+        (self.enforce_dag || self.enforce_schema).then(|| Checkpoint {
+            nodes: self.nodes.checkpoint(),
+            tombstones: self.tombstones.clone(),
+            logged: self.events.as_ref().map(Vec::len),
+        })
+    }
 
-    /// Detects circular dependencies using DFS.
-    fn detect_cycles(&self) -> Vec<Vec<String>> {
-        let mut cycles = Vec::new();
-        let mut visited = HashSet::new();
-        let mut recursion_stack = HashSet::new();
-        let mut path = Vec::new();
-
-        for node_id in self.nodes.keys() {
-            if !visited.contains(node_id) {
-                self.dfs_cycle(
-                    node_id,
-                    &mut visited,
-                    &mut recursion_stack,
-                    &mut path,
-                    &mut cycles,
-                );
+    fn restore_checkpoint(&mut self, checkpoint: Checkpoint) {
+        self.nodes.restore(checkpoint.nodes);
+        self.tombstones = checkpoint.tombstones;
+        self.components
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .invalidate();
+        self.dirty.reopen();
+        if let (Some(log), Some(len)) = (self.events.as_mut(), checkpoint.logged) {
+            log.truncate(len);
+        }
+        self.sync_provenance();
+    }
+
+    /// `repair` without the GIL handling, for replay.
+    fn repair_edges(
+        &mut self,
+        drop_dangling: bool,
+        dedupe_edges: bool,
+        drop_self_loops: bool,
+    ) -> PyResult<integrity::Repaired> {
+        self.ensure_mutable()?;
+        self.record_mutation(|| GraphEvent::Repair {
+            drop_dangling,
+            dedupe_edges,
+            drop_self_loops,
+        });
+        let phantom = integrity::phantoms(&self.nodes, |h| self.is_tombstoned(h));
+        let nodes = &mut self.nodes;
+        let repaired = config::install(|| {
+            integrity::repair(
+                nodes,
+                &phantom,
+                drop_dangling,
+                dedupe_edges,
+                drop_self_loops,
+            )
+        });
+        if repaired.dangling + repaired.self_loops + repaired.duplicates > 0 {
+            self.sync_provenance();
+        }
+        Ok(repaired)
+    }
+
+    /// Drops provenance for edges that no longer exist, after bulk edge removal.
+    fn sync_provenance(&mut self) {
+        let Some(store) = self.provenance.as_mut() else {
+            return;
+        };
+        let nodes = &self.nodes;
+        store.retain(nodes.ids(), |from, to| nodes.has_edge(from, to));
+    }
+
+    /// Degree per `IndexedGraph` index: incident edges (in plus out, dangling
+    /// targets included) when directed; the sum of pair counts in `undirected_multi`
+    /// mode, with a self-loop counted twice.
+    fn degree_column(&self, graph: &algorithms::IndexedGraph) -> Vec<f64> {
+        if self.mode.is_directed() {
+            let incoming = graph.reverse();
+            return (0..graph.len())
+                .map(|v| (graph.out_degree[v] + incoming[v].len()) as f64)
+                .collect();
+        }
+        graph
+            .ids
+            .iter()
+            .map(|id| {
+                let h = self.nodes.lookup(id);
+                self.nodes.get(id).map_or(0.0, |node| {
+                    node.dependents
+                        .iter()
+                        .zip(&node.weights)
+                        .map(|(&d, w)| if d == h { 2.0 * w } else { *w })
+                        .sum()
+                })
+            })
+            .collect()
+    }
+
+    /// The ids of `nodes`, sorted.
+    fn sorted_names<'a>(&self, nodes: impl Iterator<Item = (u32, &'a CausalNode)>) -> Vec<String> {
+        let mut out: Vec<String> = nodes.map(|(h, _)| self.nodes.name(h).clone()).collect();
+        out.sort_unstable();
+        out
+    }
+
+    fn sorted_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.nodes.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    fn sorted_edges(&self) -> Vec<(String, String, f64)> {
+        let mut edges: Vec<(String, String, f64)> = self
+            .nodes
+            .iter()
+            .flat_map(|(id, n)| {
+                self.nodes
+                    .targets(n)
+                    .zip(&n.weights)
+                    .map(move |(to, w)| (id.clone(), to.clone(), *w))
+            })
+            .collect();
+        edges.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        edges
+    }
+
+    /// Number of edges; in `undirected_multi` mode, of distinct pairs.
+    fn edge_count_total(&self) -> usize {
+        let halves: usize = self.nodes.values().map(|n| n.dependents.len()).sum();
+        if self.mode.is_directed() {
+            return halves;
+        }
+        let self_loops = self
+            .nodes
+            .handles()
+            .filter(|(h, n)| n.edge_index(*h).is_some())
+            .count();
+        (halves + self_loops) / 2
+    }
+
+    /// Normalizes an incoming id and remembers its first-seen original form.
+    fn normalize_id(&mut self, id: String) -> PyResult<String> {
+        let Some(normalizer) = &self.normalizer else {
+            return Ok(id);
+        };
+        let normalized = Python::with_gil(|py| normalizer.apply(py, &id))?;
+        if !self.original_ids.contains_key(&normalized) {
+            if let Some(transaction) = self.transaction.as_mut() {
+                transaction.original_ids.push(normalized.clone());
             }
+            self.original_ids.insert(normalized.clone(), id);
+        }
+        Ok(normalized)
+    }
+
+    fn normalize_ids(&mut self, ids: Vec<String>) -> PyResult<Vec<String>> {
+        if self.normalizer.is_none() {
+            return Ok(ids);
+        }
+        ids.into_iter().map(|id| self.normalize_id(id)).collect()
+    }
+
+    /// Normalizes an id a query was given. Unlike `normalize_id`, it records no
+    /// original form, so looking up an absent id leaves no trace.
+    pub(crate) fn lookup_id(&self, id: String) -> PyResult<String> {
+        match &self.normalizer {
+            Some(normalizer) => Python::with_gil(|py| normalizer.apply(py, &id)),
+            None => Ok(id),
         }
-        cycles
     }
 
-    /// Identifies isolated communities using Connected Components.
-    fn detect_communities(&self) -> Vec<HashSet<String>> {
-        let mut communities = Vec::new();
-        let mut global_visited = HashSet::new();
+    /// `lookup_id` for a borrowed id, copying it only when a normalizer is set.
+    pub(crate) fn lookup_str<'a>(&self, id: &'a str) -> PyResult<Cow<'a, str>> {
+        match &self.normalizer {
+            Some(normalizer) => Python::with_gil(|py| normalizer.apply(py, id)).map(Cow::Owned),
+            None => Ok(Cow::Borrowed(id)),
+        }
+    }
 
-        for node_id in self.nodes.keys() {
-            if !global_visited.contains(node_id) {
-                let mut community = HashSet::new();
-                let mut stack = vec![node_id.clone()];
+    pub(crate) fn lookup_ids(&self, ids: Vec<String>) -> PyResult<Vec<String>> {
+        if self.normalizer.is_none() {
+            return Ok(ids);
+        }
+        ids.into_iter().map(|id| self.lookup_id(id)).collect()
+    }
 
-                while let Some(current) = stack.pop() {
-                    if community.insert(current.clone()) {
-                        global_visited.insert(current.clone());
+    /// `lookup_ids` for borrowed ids.
+    pub(crate) fn lookup_slice<'a>(&self, ids: &'a [String]) -> PyResult<Cow<'a, [String]>> {
+        if self.normalizer.is_none() {
+            return Ok(Cow::Borrowed(ids));
+        }
+        ids.iter()
+            .map(|id| self.lookup_id(id.clone()))
+            .collect::<PyResult<_>>()
+            .map(Cow::Owned)
+    }
 
-                        if let Some(node) = self.nodes.get(&current) {
-                            for dep in &node.dependents {
-                                if !community.contains(dep) {
-                                    stack.push(dep.clone());
-                                }
-                            }
-                        }
+    /// Records one observation of the unordered pair `(a, b)` in `undirected_multi`
+    /// mode: both half-edges gain 1 on their count and advance to `ts`.
+    fn observe_pair(&mut self, a: &str, b: &str, ts: f64) {
+        let halves = if a == b {
+            vec![(a, b)]
+        } else {
+            vec![(a, b), (b, a)]
+        };
+        for (from, to) in halves {
+            self.tombstones.remove(from);
+            let to_handle = self.nodes.intern(to);
+            let node = self.nodes.get_or_insert(from);
+            match node.edge_index(to_handle) {
+                Some(i) => {
+                    node.weights[i] += 1.0;
+                    node.observed_at[i] = node.observed_at[i].max(ts);
+                }
+                None => {
+                    node.push_edge(to_handle, 1.0, ts);
+                    self.record_provenance(from, to, API_SOURCE, None, None, ts);
+                }
+            }
+        }
+    }
+
+    /// Pair counts of `id`'s neighbors in `undirected_multi` mode.
+    fn pair_counts(&self, id: &str) -> HashMap<&str, f64> {
+        self.nodes
+            .get(id)
+            .map(|n| {
+                self.nodes
+                    .targets(n)
+                    .map(String::as_str)
+                    .zip(n.weights.iter().copied())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn multigraph_triangles(&self) -> usize {
+        let mut total = 0.0;
+        for (a, node_a) in self.nodes.iter() {
+            let counts_a = self.pair_counts(a);
+            for (b, &ab) in self.nodes.targets(node_a).zip(&node_a.weights) {
+                if b <= a {
+                    continue;
+                }
+                let Some(node_b) = self.nodes.get(b) else {
+                    continue;
+                };
+                for (c, &bc) in self.nodes.targets(node_b).zip(&node_b.weights) {
+                    if c <= b {
+                        continue;
+                    }
+                    if let Some(&ca) = counts_a.get(c.as_str()) {
+                        total += ab * bc * ca;
                     }
                 }
-                communities.push(community);
             }
         }
-        communities
+        total.round() as usize
+    }
+
+    fn weighted_jaccard(&self, a: &str, b: &str) -> f64 {
+        let (counts_a, counts_b) = (self.pair_counts(a), self.pair_counts(b));
+        let (mut min_sum, mut max_sum) = (0.0, 0.0);
+        for (id, &wa) in &counts_a {
+            let wb = counts_b.get(id).copied().unwrap_or(0.0);
+            min_sum += wa.min(wb);
+            max_sum += wa.max(wb);
+        }
+        for (id, &wb) in &counts_b {
+            if !counts_a.contains_key(id) {
+                max_sum += wb;
+            }
+        }
+        if max_sum == 0.0 {
+            0.0
+        } else {
+            min_sum / max_sum
+        }
+    }
+
+    /// Default-parameter PageRank aligned with `IndexedGraph` indices (sorted ids),
+    /// shared by the layout and summary helpers.
+    fn indexed_pagerank_cached(&self, graph: &algorithms::IndexedGraph) -> Arc<Vec<f64>> {
+        self.metric_cache
+            .get_or_compute(self.version, "pagerank_indexed:20:0.85", || {
+                algorithms::pagerank(graph, 20, 0.85)
+            })
+    }
+
+    /// Cached `calculate_pagerank` run with its convergence.
+    fn pagerank_run(
+        &self,
+        py: Python,
+        iterations: usize,
+        damping: f64,
+        tolerance: Option<f64>,
+    ) -> PyResult<Arc<(HashMap<String, f64>, algorithms::Convergence)>> {
+        check_tolerance(tolerance)?;
+        let key = format!(
+            "pagerank:{}:{:?}:{:?}:{}",
+            iterations,
+            damping,
+            tolerance,
+            config::deterministic()
+        );
+        Ok(py.allow_threads(|| {
+            self.metric_cache.get_or_compute(self.version, &key, || {
+                let run = self.pagerank_map(iterations, damping, tolerance);
+                self.pagerank_iterations
+                    .fetch_add(run.1.iterations, Ordering::Relaxed);
+                run
+            })
+        }))
     }
 
-    fn calculate_pagerank(&self, iterations: usize, damping: f64) -> HashMap<String, f64> {
+    /// PageRank for `calculate_pagerank`, uncached.
+    fn pagerank_map(
+        &self,
+        iterations: usize,
+        damping: f64,
+        tolerance: Option<f64>,
+    ) -> (HashMap<String, f64>, algorithms::Convergence) {
+        let mut convergence = algorithms::Convergence {
+            iterations: 0,
+            delta: None,
+        };
         let n = self.nodes.len();
         if n == 0 {
-            return HashMap::new();
+            return (HashMap::new(), convergence);
+        }
+        if config::deterministic() {
+            let graph = algorithms::IndexedGraph::new(&self.nodes);
+            let (ranks, convergence) =
+                algorithms::pagerank_until(&graph, iterations, damping, tolerance, None);
+            let ranks = graph
+                .ids
+                .iter()
+                .map(|id| (*id).clone())
+                .zip(ranks)
+                .collect();
+            return (ranks, convergence);
         }
 
+        // Ranks are indexed by handle; ids that are not nodes keep a rank of zero
+        // and are never read.
         let initial_rank = 1.0 / n as f64;
-        let mut ranks: HashMap<String, f64> = self
-            .nodes
-            .keys()
-            .map(|k| (k.clone(), initial_rank))
-            .collect();
+        let mut ranks: Vec<f64> = vec![0.0; self.nodes.ids().len()];
+        for (h, _) in self.nodes.handles() {
+            ranks[h as usize] = initial_rank;
+        }
 
+        // Only edges between nodes carry rank, in proportion to their weight (as in
+        // the deterministic path); nodes without positive weight to other nodes are
+        // dangling and spread their rank over all nodes.
+        let reverse = self.weighted_reverse_adjacency_cached();
+        let mut out_weight: Vec<f64> = vec![0.0; ranks.len()];
+        for (h, node) in self.nodes.handles() {
+            out_weight[h as usize] = node
+                .dependents
+                .iter()
+                .zip(&node.weights)
+                .filter(|(&d, _)| self.nodes.contains(d))
+                .map(|(_, w)| w.max(0.0))
+                .sum();
+        }
         for _ in 0..iterations {
-            let mut new_ranks = HashMap::new();
-            for node_id in self.nodes.keys() {
+            let dangling: f64 = self
+                .nodes
+                .handles()
+                .filter(|&(h, _)| out_weight[h as usize] == 0.0)
+                .map(|(h, _)| ranks[h as usize])
+                .sum();
+            let mut new_ranks = vec![0.0; ranks.len()];
+            for (h, _) in self.nodes.handles() {
                 let mut rank_sum = 0.0;
-                // Note: Simplified reverse lookup for MVP. Production would use an adjacency matrix.
-                for (other_id, other_node) in &self.nodes {
-                    if other_node.dependents.contains(node_id) {
-                        let other_rank = ranks.get(other_id).unwrap_or(&0.0);
-                        let out_degree = other_node.dependents.len().max(1) as f64;
-                        rank_sum += other_rank / out_degree;
-                    }
+                for &(other, w) in &reverse[h as usize] {
+                    let other = other as usize;
+                    rank_sum += ranks[other] * w.max(0.0) / out_weight[other];
                 }
-                let new_rank = (1.0 - damping) / n as f64 + damping * rank_sum;
-                new_ranks.insert(node_id.clone(), new_rank);
+                new_ranks[h as usize] =
+                    (1.0 - damping) / n as f64 + damping * (rank_sum + dangling / n as f64);
             }
+            let delta = self
+                .nodes
+                .handles()
+                .map(|(h, _)| (new_ranks[h as usize] - ranks[h as usize]).abs())
+                .sum();
             ranks = new_ranks;
+            if convergence.step(delta, tolerance) {
+                break;
+            }
         }
-        ranks
+        let ranks = self
+            .nodes
+            .handles()
+            .map(|(h, _)| (self.nodes.name(h).clone(), ranks[h as usize]))
+            .collect();
+        (ranks, convergence)
     }
 
-    fn calculate_betweenness(&self) -> HashMap<String, f64> {
-        // Degree Centrality proxy for Betweenness
-        self.nodes
-            .iter()
-            .map(|(id, node)| (id.clone(), node.dependents.len() as f64))
-            .collect()
+    /// Scaled distance histogram for `distance_distribution`, indexed by distance.
+    fn distance_histogram_cached(
+        &self,
+        py: Python,
+        sample_sources: Option<usize>,
+        seed: u64,
+    ) -> PyResult<Arc<Vec<u64>>> {
+        if sample_sources == Some(0) {
+            return Err(PyValueError::new_err("sample_sources must be positive"));
+        }
+        let key = format!("distance_distribution:{:?}:{}", sample_sources, seed);
+        Ok(py.allow_threads(|| {
+            self.metric_cache.get_or_compute(self.version, &key, || {
+                let graph = algorithms::IndexedGraph::new(&self.nodes);
+                let n = graph.len();
+                let sources = match sample_sources {
+                    Some(k) if k < n => algorithms::sample_indices(n, k, seed),
+                    _ => (0..n).collect(),
+                };
+                let hist = config::install(|| algorithms::distance_histogram(&graph, &sources));
+                if sources.len() == n {
+                    return hist;
+                }
+                let scale = n as f64 / sources.len() as f64;
+                hist.into_iter()
+                    .map(|c| (c as f64 * scale).round() as u64)
+                    .collect()
+            })
+        }))
     }
 
-    fn calculate_closeness(&self) -> HashMap<String, f64> {
-        // Simple heuristic: Inverse of out-degree
-        self.nodes
-            .iter()
-            .map(|(id, node)| (id.clone(), 1.0 / (node.dependents.len() as f64 + 1.0)))
-            .collect()
+    fn reachability_cached(&self) -> Arc<ReachabilityIndex> {
+        self.metric_cache
+            .get_or_compute(self.version, "reachability", || {
+                ReachabilityIndex::build(&self.nodes, |h| self.is_tombstoned(h))
+            })
     }
 
-    fn count_triangles(&self) -> usize {
-        let mut count = 0;
-        for (id_a, node_a) in &self.nodes {
-            for id_b in &node_a.dependents {
-                if let Some(node_b) = self.nodes.get(id_b) {
-                    for id_c in &node_b.dependents {
-                        // Check for A->B->C->A (Directed Cycle)
-                        if id_c == id_a {
-                            count += 1;
-                        }
-                        // Check for Transitive relationship A->B->C and A->C
-                        if let Some(node_a_ref) = self.nodes.get(id_a) {
-                            if node_a_ref.dependents.contains(id_c) {
-                                count += 1;
-                            }
-                        }
+    /// Sources of each handle, indexed by handle (see `sources_of`).
+    fn reverse_adjacency_cached(&self) -> Arc<Vec<Vec<u32>>> {
+        self.metric_cache
+            .get_or_compute(self.version, "reverse_adjacency", || {
+                let mut reverse: Vec<Vec<u32>> = vec![Vec::new(); self.nodes.ids().len()];
+                for (h, node) in self.nodes.handles() {
+                    for &dep in &node.dependents {
+                        reverse[dep as usize].push(h);
+                    }
+                }
+                reverse
+            })
+    }
+
+    /// `reverse_adjacency_cached` with the weight of each incoming edge.
+    fn weighted_reverse_adjacency_cached(&self) -> Arc<Vec<Vec<(u32, f64)>>> {
+        self.metric_cache
+            .get_or_compute(self.version, "weighted_reverse_adjacency", || {
+                let mut reverse: Vec<Vec<(u32, f64)>> = vec![Vec::new(); self.nodes.ids().len()];
+                for (h, node) in self.nodes.handles() {
+                    for (&dep, &w) in node.dependents.iter().zip(&node.weights) {
+                        reverse[dep as usize].push((h, w));
                     }
                 }
+                reverse
+            })
+    }
+
+    fn stable_order_cached(&self) -> Arc<Vec<String>> {
+        self.metric_cache
+            .get_or_compute(self.version, "stable_order", || {
+                let graph = algorithms::IndexedGraph::new(&self.nodes);
+                let ranks = self.indexed_pagerank_cached(&graph);
+                let mut order = Vec::with_capacity(graph.len());
+                for mut component in algorithms::weak_components(&graph) {
+                    component.sort_by(|&a, &b| ranks[b].total_cmp(&ranks[a]).then(a.cmp(&b)));
+                    order.extend(component.into_iter().map(|i| graph.ids[i].clone()));
+                }
+                order
+            })
+    }
+
+    fn sample_metrics(&self, py: Python, events: usize, metrics: &[String]) -> PyResult<PyObject> {
+        let d = PyDict::new(py);
+        d.set_item("events", events)?;
+        let needs_index = metrics
+            .iter()
+            .any(|m| m == "components" || m == "largest_scc");
+        let indexed = needs_index.then(|| algorithms::IndexedGraph::new(&self.nodes));
+        for metric in metrics {
+            match (metric.as_str(), indexed.as_ref()) {
+                ("nodes", _) => d.set_item("nodes", self.nodes.len())?,
+                ("edges", _) => d.set_item("edges", self.edge_count_total())?,
+                ("avg_degree", _) => {
+                    let n = self.nodes.len().max(1) as f64;
+                    d.set_item("avg_degree", self.edge_count_total() as f64 / n)?
+                }
+                ("components", Some(g)) => {
+                    d.set_item("components", algorithms::weak_component_count(g))?
+                }
+                ("largest_scc", Some(g)) => {
+                    let largest = algorithms::tarjan_scc(&g.adj)
+                        .iter()
+                        .map(Vec::len)
+                        .max()
+                        .unwrap_or(0);
+                    d.set_item("largest_scc", largest)?
+                }
+                _ => {}
             }
         }
-        // approximate de-duplication
-        count / 3
+        Ok(d.into())
     }
 
-    fn find_cliques(&self) -> usize {
-        // Heuristic: Max neighborhood size + 1
-        self.nodes
-            .values()
-            .map(|n| n.dependents.len())
-            .max()
-            .unwrap_or(0)
-            + 1
-    }
-
-    fn find_diameter(&self) -> usize {
-        let mut max_dist = 0;
-        for start_node in self.nodes.keys() {
-            let mut dists = HashMap::new();
-            dists.insert(start_node, 0);
-            let mut queue = vec![start_node];
-
-            while !queue.is_empty() {
-                let u = queue.remove(0); // Pop front
-                let d = *dists.get(u).unwrap();
-                max_dist = max_dist.max(d);
-
-                if let Some(node) = self.nodes.get(u) {
-                    for v in &node.dependents {
-                        if !dists.contains_key(v) {
-                            dists.insert(v, d + 1);
-                            queue.push(v);
-                        }
+    /// Interns `ids` and returns their handles as a sorted numpy array.
+    fn intern_array<'py, 'a>(
+        &mut self,
+        py: Python<'py>,
+        ids: impl Iterator<Item = &'a String>,
+    ) -> PyResult<&'py PyArray1<i64>> {
+        require_numpy(py)?;
+        let mut handles: Vec<i64> = ids.map(|id| self.nodes.intern(id) as i64).collect();
+        handles.sort_unstable();
+        Ok(PyArray1::from_vec(py, handles))
+    }
+
+    /// Sets each of `attributes` on `id`, which is normalized already, in key order
+    /// so the recorded events are stable.
+    fn set_node_attributes(
+        &mut self,
+        id: String,
+        attributes: Option<HashMap<String, String>>,
+    ) -> PyResult<()> {
+        let mut attributes: Vec<(String, String)> =
+            attributes.unwrap_or_default().into_iter().collect();
+        attributes.sort();
+        for (key, value) in attributes {
+            self.apply_normalized_attrs(vec![id.clone()], key, vec![value], true)?;
+        }
+        Ok(())
+    }
+
+    fn apply_attrs(
+        &mut self,
+        ids: Vec<String>,
+        key: String,
+        values: Vec<String>,
+        strict: bool,
+    ) -> PyResult<usize> {
+        self.ensure_mutable()?;
+        let ids = self.normalize_ids(ids)?;
+        self.apply_normalized_attrs(ids, key, values, strict)
+    }
+
+    /// `apply_attrs` for ids that have been normalized already.
+    fn apply_normalized_attrs(
+        &mut self,
+        ids: Vec<String>,
+        key: String,
+        values: Vec<String>,
+        strict: bool,
+    ) -> PyResult<usize> {
+        if strict {
+            let missing: Vec<&String> = ids
+                .iter()
+                .filter(|id| !self.nodes.contains_key(id))
+                .collect();
+            if !missing.is_empty() {
+                let shown: Vec<&str> = missing
+                    .iter()
+                    .take(MISSING_IDS_IN_ERROR)
+                    .map(|s| s.as_str())
+                    .collect();
+                return Err(PyKeyError::new_err(format!(
+                    "{} unknown node id(s), first: {}",
+                    missing.len(),
+                    shown.join(", ")
+                )));
+            }
+        }
+        if self.enforce_schema && key == schema::KIND_ATTR {
+            let changed: HashMap<&str, &str> = ids
+                .iter()
+                .zip(&values)
+                .filter(|(id, _)| self.nodes.contains_key(id))
+                .map(|(id, v)| (id.as_str(), v.as_str()))
+                .collect();
+            let kind = |id: &str| changed.get(id).copied().or_else(|| self.kind_of(id));
+            for (from, node) in self.nodes.iter() {
+                for (to, label) in self.nodes.targets(node).zip(&node.labels) {
+                    if changed.contains_key(from.as_str()) || changed.contains_key(to.as_str()) {
+                        self.check_schema_kinds(from, kind(from), to, kind(to), label.as_deref())?;
                     }
                 }
             }
         }
-        max_dist
+        self.record_mutation(|| GraphEvent::SetAttrs {
+            ids: ids.clone(),
+            key: key.clone(),
+            values: values.clone(),
+        });
+        let mut skipped = 0;
+        for (id, value) in ids.into_iter().zip(values) {
+            match self.nodes.get_mut(&id) {
+                Some(node) => {
+                    node.attributes.insert(key.clone(), value);
+                }
+                None => skipped += 1,
+            }
+        }
+        Ok(skipped)
     }
 
-    fn calculate_jaccard_similarity(&self, node_a: String, node_b: String) -> f64 {
-        let empty = Vec::new();
-        let deps_a: HashSet<_> = self
-            .nodes
-            .get(&node_a)
-            .map(|n| &n.dependents)
-            .unwrap_or(&empty)
-            .iter()
-            .collect();
-        let deps_b: HashSet<_> = self
-            .nodes
-            .get(&node_b)
-            .map(|n| &n.dependents)
-            .unwrap_or(&empty)
-            .iter()
-            .collect();
+    fn ensure_mutable(&self) -> PyResult<()> {
+        match self.frozen {
+            Some(_) => Err(FrozenGraphError::new_err(
+                "graph is frozen; call unfreeze() before mutating",
+            )),
+            None => Ok(()),
+        }
+    }
 
-        let intersection = deps_a.intersection(&deps_b).count();
-        let union = deps_a.union(&deps_b).count();
+    /// True if node `h` has every key/value pair of `filter`.
+    fn matches_attributes(&self, h: u32, filter: &HashMap<String, String>) -> bool {
+        self.nodes.node(h).is_some_and(|node| {
+            filter
+                .iter()
+                .all(|(k, v)| node.attributes.get(k) == Some(v))
+        })
+    }
 
-        if union == 0 {
-            0.0
-        } else {
-            intersection as f64 / union as f64
+    fn kind_of(&self, id: &str) -> Option<&str> {
+        self.schema
+            .kind_of(id, self.nodes.get(id).map(|n| &n.attributes))
+    }
+
+    /// Raises SchemaError if schema enforcement is on and `from -> to`, carrying
+    /// `label`, violates it.
+    fn check_schema(&self, from: &str, to: &str, label: Option<&str>) -> PyResult<()> {
+        if !self.enforce_schema {
+            return Ok(());
         }
+        self.check_schema_kinds(from, self.kind_of(from), to, self.kind_of(to), label)
     }
 
-    fn max_flow(&self, _source: String, _sink: String) -> i32 {
-        // Placeholder for future Edmonds-Karp implementation
-        1
+    fn check_schema_kinds(
+        &self,
+        from: &str,
+        from_kind: Option<&str>,
+        to: &str,
+        to_kind: Option<&str>,
+        label: Option<&str>,
+    ) -> PyResult<()> {
+        self.schema
+            .check_edge(from_kind, to_kind, label)
+            .map_err(|reason| {
+                SchemaError::new_err(format!(
+                    "edge {} -> {} violates schema: {}",
+                    from, to, reason
+                ))
+            })
+    }
+
+    /// Returns CycleError if adding `from -> to` would close a cycle, i.e. `from` is
+    /// already reachable from `to`. The graph is never modified here.
+    ///
+    /// A reachability index built for the current version answers the check by a
+    /// binary search, and the BFS then only runs to name the cycle of a rejected
+    /// edge. The index leaves out edges into tombstoned ids, so it is not used while
+    /// there are any. Without it each check is a BFS from `to`; on the 1M-node DAG of
+    /// `benches/dag_check.rs` that takes 0.69 s when `to` reaches the whole graph and
+    /// 0.15 ms when it reaches 1000 nodes, against 0.04 ms from the index.
+    fn check_acyclic(&self, from: &str, to: &str) -> PyResult<()> {
+        if from != to && self.tombstones.is_empty() {
+            let index = self
+                .metric_cache
+                .peek::<ReachabilityIndex>(self.version, "reachability");
+            if let Some(index) = index {
+                let closes = match (self.nodes.handle(to), self.nodes.handle(from)) {
+                    (Some(to), Some(from)) => index.reaches(to, from),
+                    _ => false,
+                };
+                if !closes {
+                    return Ok(());
+                }
+            }
+        }
+        if let Some(path) = self.find_path(to, from) {
+            let mut cycle = vec![from.to_string()];
+            cycle.extend(path);
+            return Err(CycleError::new_err(format!(
+                "edge {} -> {} would create cycle: {}",
+                from,
+                to,
+                cycle.join(" -> ")
+            )));
+        }
+        Ok(())
     }
 
-    fn minimum_spanning_tree(&self) -> HashSet<(String, String)> {
-        // BFS Tree heuristic for unweighted graph
-        let mut edges = HashSet::new();
-        let mut visited = HashSet::new();
+    /// BFS path from `start` to `goal` (both included), if one exists.
+    fn find_path(&self, start: &str, goal: &str) -> Option<Vec<String>> {
+        match self.find_path_within(start, goal, usize::MAX) {
+            PathSearch::Found(path) => Some(path),
+            PathSearch::Absent | PathSearch::Exhausted => None,
+        }
+    }
 
-        if let Some(start) = self.nodes.keys().next() {
-            let mut queue = vec![start];
-            visited.insert(start);
+    /// BFS for a path `start -> ... -> goal`, expanding at most `max_steps` nodes.
+    fn find_path_within(&self, start: &str, goal: &str, max_steps: usize) -> PathSearch {
+        if start == goal {
+            return PathSearch::Found(vec![start.to_string()]);
+        }
+        let (Some(start), Some(goal)) = (self.nodes.handle(start), self.nodes.handle(goal)) else {
+            return PathSearch::Absent;
+        };
+        let mut parents: HashMap<u32, u32> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        parents.insert(start, start);
+        let mut steps = 0;
 
-            while !queue.is_empty() {
-                let u = queue.remove(0);
-                if let Some(node) = self.nodes.get(u) {
-                    for v in &node.dependents {
-                        if visited.insert(v) {
-                            edges.insert((u.clone(), v.clone()));
-                            queue.push(v);
+        while let Some(u) = queue.pop_front() {
+            if steps == max_steps {
+                return PathSearch::Exhausted;
+            }
+            steps += 1;
+            let Some(node) = self.nodes.node(u) else {
+                continue;
+            };
+            for &v in &node.dependents {
+                if parents.contains_key(&v) {
+                    continue;
+                }
+                parents.insert(v, u);
+                if v == goal {
+                    let mut path = vec![goal];
+                    let mut cur = u;
+                    while cur != start {
+                        path.push(cur);
+                        match parents.get(&cur) {
+                            Some(&parent) => cur = parent,
+                            None => break,
                         }
                     }
+                    path.push(start);
+                    path.reverse();
+                    return PathSearch::Found(self.nodes.names(path));
                 }
+                queue.push_back(v);
             }
         }
-        edges
+        PathSearch::Absent
     }
 
-    fn k_core_decomposition(&self, k: usize) -> Vec<String> {
-        self.nodes
-            .iter()
-            .filter(|(_, n)| n.dependents.len() >= k)
-            .map(|(id, _)| id.clone())
-            .collect()
+    /// Incremental cycle check for a just-added edge: a cycle exists iff `to`
+    /// reaches `from`. Confirmed cycles go to `new_cycles`; an exhausted budget
+    /// queues the edge for `verify_pending_cycles`.
+    fn check_new_edge_cycle(&mut self, from: &str, to: &str) {
+        let Some(max_steps) = self.cycle_check_steps else {
+            return;
+        };
+        if self.enforce_dag {
+            return;
+        }
+        match self.find_path_within(to, from, max_steps) {
+            PathSearch::Found(cycle) => self.push_new_cycle(cycle),
+            PathSearch::Exhausted => self
+                .pending_cycle_edges
+                .push((from.to_string(), to.to_string())),
+            PathSearch::Absent => {}
+        }
     }
-}
 
-impl GraphWalker {
+    /// Buffers a confirmed cycle for `new_cycles()`, rotated to start at its
+    /// smallest id as `detect_cycles` reports it.
+    fn push_new_cycle(&mut self, mut cycle: Vec<String>) {
+        let first = (0..cycle.len()).min_by_key(|&i| &cycle[i]).unwrap_or(0);
+        cycle.rotate_left(first);
+        self.new_cycles.push(cycle);
+    }
+
+    /// Depth-first cycle search from `start`, using an explicit stack of
+    /// (node, next dependent index) frames so deep chains cannot overflow the thread
+    /// stack. `path` mirrors the frames and `stack` holds the same ids for O(1) lookup,
+    /// so a back edge to `neighbor` closes exactly the path suffix starting there.
+    /// Cycles are added in canonical rotation; returns false once `cycles` would
+    /// grow past `cap`.
     fn dfs_cycle(
         &self,
-        current_node: &String,
-        visited: &mut HashSet<String>,
-        stack: &mut HashSet<String>,
-        path: &mut Vec<String>,
-        cycles: &mut Vec<Vec<String>>,
-    ) {
-        visited.insert(current_node.clone());
-        stack.insert(current_node.clone());
-        path.push(current_node.clone());
+        start: u32,
+        visited: &mut HashSet<u32>,
+        stack: &mut HashSet<u32>,
+        path: &mut Vec<u32>,
+        cycles: &mut BTreeSet<Vec<String>>,
+        cap: usize,
+    ) -> bool {
+        let mut frames: Vec<(u32, usize)> = vec![(start, 0)];
+        visited.insert(start);
+        stack.insert(start);
+        path.push(start);
 
-        if let Some(node) = self.nodes.get(current_node) {
-            for neighbor in &node.dependents {
-                if !visited.contains(neighbor) {
-                    self.dfs_cycle(neighbor, visited, stack, path, cycles);
-                } else if stack.contains(neighbor) {
-                    // Cycle detected: Extract the path segment involved in the cycle
-                    if let Some(pos) = path.iter().position(|x| x == neighbor) {
-                        cycles.push(path[pos..].to_vec());
+        while let Some(frame) = frames.last_mut() {
+            let (current, next) = (frame.0, frame.1);
+            let neighbor = self
+                .nodes
+                .node(current)
+                .and_then(|node| node.dependents.get(next).copied());
+            let Some(neighbor) = neighbor else {
+                frames.pop();
+                stack.remove(&current);
+                path.pop();
+                continue;
+            };
+            frame.1 += 1;
+
+            if !visited.contains(&neighbor) {
+                visited.insert(neighbor);
+                stack.insert(neighbor);
+                path.push(neighbor);
+                frames.push((neighbor, 0));
+            } else if stack.contains(&neighbor) {
+                if let Some(pos) = path.iter().position(|&x| x == neighbor) {
+                    let mut cycle: Vec<String> = self.nodes.names(path[pos..].iter().copied());
+                    let first = (0..cycle.len()).min_by_key(|&i| &cycle[i]).unwrap_or(0);
+                    cycle.rotate_left(first);
+                    if !cycles.contains(&cycle) && cycles.len() == cap {
+                        stack.clear();
+                        path.clear();
+                        return false;
                     }
+                    cycles.insert(cycle);
                 }
             }
         }
-
-        stack.remove(current_node);
-        path.pop();
+        true
     }
 }
 
+mod algorithms;
+mod cache;
+mod changefeed;
+mod coldstart;
+mod compare;
+mod components;
+mod config;
 mod consumer;
+mod cursor;
+mod dirty;
+mod edgefile;
+mod errors;
+mod events;
+mod export;
+mod heat;
+mod integrity;
+mod interner;
+mod introspect;
+mod kafka;
+mod limits;
+mod linkpred;
+mod locks;
+mod mode;
+mod namespace;
+mod normalize;
+mod pipeline;
+mod producer;
+mod provenance;
+mod publish;
+mod pyjson;
+mod reachability;
+mod schema;
+mod similarity;
+mod snapshot;
+mod source;
+mod stats;
+mod store;
+mod summary;
+mod table;
+use cache::MetricCache;
+use compare::ResultCache;
+use components::ComponentTracker;
 use consumer::CausalConsumer;
+use cursor::{NodeIter, ResultCursor, Rows};
+use dirty::DirtySet;
+use errors::{
+    ConvergenceError, CycleError, FrozenGraphError, ModeError, NodeNotFoundError,
+    ResourceLimitError, SchemaError, SerializationError, StaleCursorError,
+};
+use events::GraphEvent;
+use heat::Heat;
+use limits::Limits;
+use mode::GraphMode;
+use normalize::Normalizer;
+use provenance::{ProvenanceRecord, ProvenanceStore, API_SOURCE};
+use reachability::ReachabilityIndex;
+use schema::Schema;
+use store::{NodeStore, Slots, UNSEEN};
 
+/// Starts a background Kafka consumer. `brokers` is a bootstrap server list, or a
+/// `MemoryBroker` to consume from in this process. `topic` is one topic or a list
+/// of topics consumed together. With `walker`, a
+/// `SharedGraphWalker`, each message is applied to it (see `pipeline` for the
+/// payload format): malformed messages are counted in `consumer_states()` and
+/// skipped, "delete" tombstones the fact with `tombstone_deletes`, and invalidation
+/// results are also appended to the `changefeed` file if given.
+///
+/// `on_message(message)` is called with a dict of each message's `topic`,
+/// `partition`, `offset`, `timestamp`, `key` and `payload` (bytes or None) and
+/// `headers` (`(name, value)` pairs), after it is applied to `walker` if given. A
+/// dict of callables by topic instead gives each topic its own handler.
+/// Exceptions it raises are counted and passed to `on_error(exception, message)`
+/// instead of stopping the loop. With neither `walker` nor `on_message`, messages
+/// are only logged.
+///
+/// With `producer`, a `CausalProducer`, and `output_topic`, the result of each
+/// "invalidate" message is also sent to that topic as
+/// `CausalProducer.publish_invalidation` sends it, through `publisher` (an
+/// `InvalidationPublisher`) if given.
+///
+/// With `namespace_by_topic`, node ids are prefixed with the message's topic and
+/// `separator` (see `namespace_id`); ids that already have a namespace are kept. `normalize` takes the same steps as
+/// `GraphWalker(normalize=...)` and is applied to message ids before namespacing;
+/// pass the walker's steps so both paths agree.
+///
+/// `config`, a `ConsumerConfig`, sets the group id, offset reset, commit mode and
+/// any other librdkafka property; the memory broker ignores it.
+///
+/// Before the consumer starts, the brokers must answer within `connect_timeout_ms`
+/// (TimeoutError otherwise) and know the topic (RuntimeError with the Kafka error
+/// otherwise, e.g. for a missing topic or a failed authorization). It then returns
+/// a `ConsumerHandle`: `stop()` ends the consumer, `join()` waits for it, and
+/// `status()` and `last_error()` report errors met while running.
 #[pyfunction]
-fn start_kafka_consumer(brokers: &str, topic: &str) -> PyResult<()> {
-    let consumer = CausalConsumer::new(brokers, topic);
-    consumer.start();
-    Ok(())
+#[pyo3(signature = (
+    brokers,
+    topic,
+    namespace_by_topic = false,
+    separator = namespace::DEFAULT_SEPARATOR,
+    normalize = None,
+    walker = None,
+    tombstone_deletes = false,
+    changefeed = None,
+    connect_timeout_ms = consumer::DEFAULT_CONNECT_TIMEOUT.as_millis() as u64,
+    config = None,
+    on_message = None,
+    on_error = None,
+    producer = None,
+    output_topic = None,
+    publisher = None
+))]
+#[allow(clippy::too_many_arguments)]
+fn start_kafka_consumer(
+    py: Python,
+    brokers: &PyAny,
+    topic: &PyAny,
+    namespace_by_topic: bool,
+    separator: &str,
+    normalize: Option<&PyAny>,
+    walker: Option<PyRef<pipeline::SharedGraphWalker>>,
+    tombstone_deletes: bool,
+    changefeed: Option<String>,
+    connect_timeout_ms: u64,
+    config: Option<kafka::ConsumerConfig>,
+    on_message: Option<&PyAny>,
+    on_error: Option<PyObject>,
+    producer: Option<PyRef<producer::CausalProducer>>,
+    output_topic: Option<String>,
+    publisher: Option<Py<publish::InvalidationPublisher>>,
+) -> PyResult<consumer::ConsumerHandle> {
+    let topics: Vec<String> = match topic.extract::<String>() {
+        Ok(topic) => vec![topic],
+        Err(_) => topic.extract()?,
+    };
+    let mut unique = HashSet::new();
+    let topics: Vec<String> = topics
+        .into_iter()
+        .filter(|t| unique.insert(t.clone()))
+        .collect();
+    if topics.is_empty() {
+        return Err(PyValueError::new_err("at least one topic is needed"));
+    }
+    let handlers = match on_message {
+        None => None,
+        Some(on_message) => match on_message.downcast::<PyDict>() {
+            Err(_) => Some(consumer::Handlers::All(on_message.into())),
+            Ok(by_topic) => {
+                let by_topic: HashMap<String, PyObject> = by_topic.extract()?;
+                if let Some(other) = by_topic.keys().find(|t| !unique.contains(*t)) {
+                    return Err(PyValueError::new_err(format!(
+                        "on_message has a handler for '{}', which is not subscribed",
+                        other
+                    )));
+                }
+                Some(consumer::Handlers::ByTopic(by_topic))
+            }
+        },
+    };
+    let mut consumer = match brokers.extract::<source::MemoryBroker>() {
+        Ok(broker) => CausalConsumer::in_memory(broker, topics),
+        Err(_) => CausalConsumer::new(brokers.extract()?, topics),
+    };
+    let publish = match (producer, output_topic) {
+        (Some(producer), Some(topic)) => Some(producer::Publish {
+            output: producer.output(),
+            topic,
+            publisher,
+        }),
+        (None, None) if publisher.is_none() => None,
+        _ => {
+            return Err(PyValueError::new_err(
+                "producer and output_topic go together, and publisher needs both",
+            ))
+        }
+    };
+    if publish.is_some() && walker.is_none() {
+        return Err(PyValueError::new_err("output_topic needs walker"));
+    }
+    if let Some(walker) = walker {
+        let options = pipeline::ApplyOptions {
+            tombstone_deletes,
+            changefeed,
+            publish,
+        };
+        consumer = consumer.apply_to(walker.shared(), options);
+    }
+    if namespace_by_topic {
+        consumer = consumer.namespace_by_topic(separator);
+    }
+    if let Some(spec) = normalize {
+        consumer = consumer.normalize(Normalizer::from_py(spec)?);
+    }
+    if let Some(config) = config {
+        consumer = consumer.config(config);
+    }
+    if let Some(handlers) = handlers {
+        consumer = consumer.on_message(handlers, on_error);
+    } else if on_error.is_some() {
+        return Err(PyValueError::new_err("on_error needs on_message"));
+    }
+    consumer
+        .connect_timeout(std::time::Duration::from_millis(connect_timeout_ms))
+        .start(py)
 }
 
 #[pymodule]
-fn rust_core(_py: Python, m: &PyModule) -> PyResult<()> {
+pub fn rust_core(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<GraphWalker>()?;
+    m.add_class::<publish::InvalidationPublisher>()?;
+    m.add_class::<introspect::IntrospectionServer>()?;
+    m.add_class::<ResultCursor>()?;
+    m.add_class::<NodeIter>()?;
+    m.add_class::<stats::GraphSummary>()?;
+    m.add_class::<integrity::ValidationReport>()?;
+    m.add_class::<pipeline::SharedGraphWalker>()?;
+    m.add_class::<source::MemoryBroker>()?;
+    m.add_class::<consumer::ConsumerHandle>()?;
+    m.add_class::<kafka::ConsumerConfig>()?;
+    m.add_class::<kafka::SecurityConfig>()?;
+    m.add_class::<producer::CausalProducer>()?;
+    m.add("CycleError", py.get_type::<CycleError>())?;
+    m.add("SerializationError", py.get_type::<SerializationError>())?;
+    m.add("ResourceLimitError", py.get_type::<ResourceLimitError>())?;
+    m.add("NodeNotFoundError", py.get_type::<NodeNotFoundError>())?;
+    m.add("SchemaError", py.get_type::<SchemaError>())?;
+    m.add("FrozenGraphError", py.get_type::<FrozenGraphError>())?;
+    m.add("ModeError", py.get_type::<ModeError>())?;
+    m.add("StaleCursorError", py.get_type::<StaleCursorError>())?;
+    m.add("ConvergenceError", py.get_type::<ConvergenceError>())?;
     m.add_function(wrap_pyfunction!(start_kafka_consumer, m)?)?;
+    m.add_function(wrap_pyfunction!(consumer::consumer_states, m)?)?;
+    m.add_function(wrap_pyfunction!(introspect::start_introspection_server, m)?)?;
+    m.add_function(wrap_pyfunction!(namespace::namespace_id, m)?)?;
+    m.add_function(wrap_pyfunction!(namespace::strip_namespace, m)?)?;
+    m.add_function(wrap_pyfunction!(namespace::namespace_of, m)?)?;
+    m.add_function(wrap_pyfunction!(changefeed::append_changefeed, m)?)?;
+    m.add_function(wrap_pyfunction!(changefeed::read_changefeed, m)?)?;
+    m.add_function(wrap_pyfunction!(config::configure, m)?)?;
+    m.add_function(wrap_pyfunction!(config::get_config, m)?)?;
+    m.add_function(wrap_pyfunction!(limits::set_default_limits, m)?)?;
+    m.add_function(wrap_pyfunction!(limits::get_default_limits, m)?)?;
+    m.add_function(wrap_pyfunction!(locks::lock_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(locks::reset_lock_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(locks::stress_test, m)?)?;
     Ok(())
 }
@@ -0,0 +1,100 @@
+use crate::errors::{NodeNotFoundError, ResourceLimitError};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Process-wide defaults applied when a call does not pass explicit limits.
+/// `usize::MAX` means unlimited.
+static DEFAULT_MAX_RESULTS: AtomicUsize = AtomicUsize::new(usize::MAX);
+static DEFAULT_MAX_VISITED: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Per-call caps on result size and on the number of nodes expanded.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    pub max_results: usize,
+    pub max_visited: usize,
+}
+
+impl Limits {
+    /// Explicit arguments win over the module defaults.
+    pub fn resolve(max_results: Option<usize>, max_visited: Option<usize>) -> Self {
+        Limits {
+            max_results: max_results.unwrap_or_else(|| DEFAULT_MAX_RESULTS.load(Ordering::Relaxed)),
+            max_visited: max_visited.unwrap_or_else(|| DEFAULT_MAX_VISITED.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// The same caps for `concord_core`, whose propagation the walker runs.
+    pub fn core(&self) -> concord_core::Limits {
+        let mut limits = concord_core::Limits::none();
+        if let Some(cap) = limit_to_py(self.max_results) {
+            limits = limits.with_max_results(cap);
+        }
+        if let Some(cap) = limit_to_py(self.max_visited) {
+            limits = limits.with_max_visited(cap);
+        }
+        limits
+    }
+
+    /// Raises ResourceLimitError if either counter exceeds its cap. The exception's
+    /// args are `(message, visited, results)` so callers can see how far it got.
+    pub fn check(&self, visited: usize, results: usize) -> PyResult<()> {
+        self.core().check(visited, results).map_err(core_error)
+    }
+}
+
+/// A `concord_core` error as the Python API raises it.
+pub fn core_error(e: concord_core::Error) -> PyErr {
+    match e {
+        concord_core::Error::LimitExceeded {
+            visited, results, ..
+        } => ResourceLimitError::new_err((e.to_string(), visited, results)),
+        concord_core::Error::UnknownNode { id } => NodeNotFoundError::new_err(id),
+        e => PyValueError::new_err(e.to_string()),
+    }
+}
+
+fn limit_to_py(v: usize) -> Option<usize> {
+    (v != usize::MAX).then_some(v)
+}
+
+/// Sets module-wide default limits from a dict with optional `max_results` and
+/// `max_visited` keys; a value of None removes that cap.
+#[pyfunction]
+pub fn set_default_limits(limits: &PyDict) -> PyResult<()> {
+    let mut updates = Vec::new();
+    for (key, value) in limits.iter() {
+        let key: String = key.extract()?;
+        let value: Option<usize> = value.extract()?;
+        let target = match key.as_str() {
+            "max_results" => &DEFAULT_MAX_RESULTS,
+            "max_visited" => &DEFAULT_MAX_VISITED,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown limit '{}' (expected max_results or max_visited)",
+                    other
+                )))
+            }
+        };
+        updates.push((target, value.unwrap_or(usize::MAX)));
+    }
+    for (target, value) in updates {
+        target.store(value, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+#[pyfunction]
+pub fn get_default_limits(py: Python) -> PyResult<PyObject> {
+    let d = PyDict::new(py);
+    d.set_item(
+        "max_results",
+        limit_to_py(DEFAULT_MAX_RESULTS.load(Ordering::Relaxed)),
+    )?;
+    d.set_item(
+        "max_visited",
+        limit_to_py(DEFAULT_MAX_VISITED.load(Ordering::Relaxed)),
+    )?;
+    Ok(d.into())
+}
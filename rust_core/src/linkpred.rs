@@ -0,0 +1,164 @@
+//! Link prediction for `GraphWalker::predict_links`: scores pairs of nodes that share
+//! a neighbor but are not joined by an edge. Scoring runs on the undirected
+//! projection of the graph, and tombstoned ids take no part.
+//!
+//! Each worker enumerates the pairs `(u, v)` with `u < v` by handle through their
+//! shared neighbors, counting them in a scratch vector indexed by handle, and
+//! keeps only its best `k` in a bounded heap. The heaps are merged at the end, so
+//! memory stays at one scratch vector per thread plus `k` entries per heap, however
+//! many pairs are scored.
+
+use crate::algorithms::Cost;
+use crate::config;
+use crate::store::NodeStore;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+#[derive(Clone, Copy)]
+pub enum Metric {
+    CommonNeighbors,
+    AdamicAdar,
+    PreferentialAttachment,
+}
+
+impl Metric {
+    pub fn parse(name: &str) -> PyResult<Self> {
+        match name {
+            "common_neighbors" => Ok(Metric::CommonNeighbors),
+            "adamic_adar" => Ok(Metric::AdamicAdar),
+            "preferential_attachment" => Ok(Metric::PreferentialAttachment),
+            _ => Err(PyValueError::new_err(format!(
+                "unknown link prediction metric '{}' (supported: common_neighbors, \
+                 adamic_adar, preferential_attachment)",
+                name
+            ))),
+        }
+    }
+}
+
+/// A scored pair. The greatest entry is the best prediction: highest score, then
+/// smallest id pair.
+type Ranked<'a> = (Cost, Reverse<(&'a str, &'a str)>);
+
+/// Keeps the best `k` entries of `heap`, a min-heap.
+fn offer<'a>(heap: &mut BinaryHeap<Reverse<Ranked<'a>>>, k: usize, entry: Ranked<'a>) {
+    if heap.len() < k {
+        heap.push(Reverse(entry));
+    } else if heap.peek().is_some_and(|worst| entry > worst.0) {
+        heap.pop();
+        heap.push(Reverse(entry));
+    }
+}
+
+/// Distinct neighbors of every handle, either edge direction, sorted; self-loops and
+/// handles for which `skip` is true are left out.
+fn undirected(
+    nodes: &NodeStore,
+    reverse: &[Vec<u32>],
+    skip: &(impl Fn(u32) -> bool + Sync),
+) -> Vec<Vec<u32>> {
+    (0..nodes.ids().len() as u32)
+        .into_par_iter()
+        .map(|h| {
+            if skip(h) {
+                return Vec::new();
+            }
+            let outgoing = nodes.node(h).map_or(&[][..], |n| &n.dependents[..]);
+            let incoming = reverse.get(h as usize).map_or(&[][..], Vec::as_slice);
+            let mut out: Vec<u32> = outgoing
+                .iter()
+                .chain(incoming)
+                .copied()
+                .filter(|&v| v != h && !skip(v))
+                .collect();
+            out.sort_unstable();
+            out.dedup();
+            out
+        })
+        .collect()
+}
+
+/// Sum of `1/ln(degree)` over the shared neighbors of two sorted neighbor lists.
+/// Terms are added smallest first, so pairs with the same shared degrees tie exactly.
+fn adamic_adar(adj: &[Vec<u32>], a: &[u32], b: &[u32]) -> f64 {
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    let mut degrees: Vec<usize> = small
+        .iter()
+        .filter(|z| large.binary_search(z).is_ok())
+        .map(|&z| adj[z as usize].len())
+        .collect();
+    degrees.sort_unstable_by(|x, y| y.cmp(x));
+    // A shared neighbor has both endpoints as neighbors, so its degree is at least 2.
+    degrees.into_iter().map(|d| 1.0 / (d as f64).ln()).sum()
+}
+
+/// The best `k` predicted edges as `(id_a, id_b, score)` with `id_a < id_b`, best
+/// first. `reverse` holds the sources of each handle.
+pub fn predict(
+    nodes: &NodeStore,
+    reverse: &[Vec<u32>],
+    skip: impl Fn(u32) -> bool + Sync,
+    k: usize,
+    metric: Metric,
+) -> Vec<(String, String, f64)> {
+    if k == 0 {
+        return Vec::new();
+    }
+    config::install(|| {
+        let adj = undirected(nodes, reverse, &skip);
+        let starts: Vec<u32> = nodes
+            .handles()
+            .map(|(h, _)| h)
+            .filter(|&h| !skip(h))
+            .collect();
+        let chunk = (starts.len() / (rayon::current_num_threads() * 4)).max(1);
+        let best = starts
+            .par_chunks(chunk)
+            .map(|chunk| {
+                // Shared-neighbor counts; zero marks an untouched handle.
+                let mut score = vec![0.0f64; adj.len()];
+                let mut touched = Vec::new();
+                let mut heap = BinaryHeap::with_capacity(k + 1);
+                for &u in chunk {
+                    let near = &adj[u as usize];
+                    for &z in near {
+                        for &v in adj[z as usize].iter().filter(|&&v| v > u) {
+                            if score[v as usize] == 0.0 {
+                                touched.push(v);
+                            }
+                            score[v as usize] += 1.0;
+                        }
+                    }
+                    for v in touched.drain(..) {
+                        let shared = std::mem::take(&mut score[v as usize]);
+                        if !nodes.contains(v) || near.binary_search(&v).is_ok() {
+                            continue;
+                        }
+                        let s = match metric {
+                            Metric::CommonNeighbors => shared,
+                            Metric::AdamicAdar => adamic_adar(&adj, near, &adj[v as usize]),
+                            Metric::PreferentialAttachment => {
+                                (near.len() * adj[v as usize].len()) as f64
+                            }
+                        };
+                        let (a, b) = (nodes.name(u).as_str(), nodes.name(v).as_str());
+                        offer(&mut heap, k, (Cost(s), Reverse((a.min(b), a.max(b)))));
+                    }
+                }
+                heap
+            })
+            .reduce(BinaryHeap::new, |mut a, b| {
+                for Reverse(entry) in b {
+                    offer(&mut a, k, entry);
+                }
+                a
+            });
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((Cost(s), Reverse((a, b))))| (a.to_string(), b.to_string(), s))
+            .collect()
+    })
+}
@@ -0,0 +1,196 @@
+use crate::store::NodeStore;
+use crate::CausalNode;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant};
+
+/// Process-wide lock contention counters. Plain relaxed atomics, so keeping them
+/// always on costs a few nanoseconds per acquisition.
+struct LockStats {
+    read_acquisitions: AtomicU64,
+    write_acquisitions: AtomicU64,
+    read_wait_ns: AtomicU64,
+    write_wait_ns: AtomicU64,
+    max_write_wait_ns: AtomicU64,
+}
+
+static STATS: LockStats = LockStats {
+    read_acquisitions: AtomicU64::new(0),
+    write_acquisitions: AtomicU64::new(0),
+    read_wait_ns: AtomicU64::new(0),
+    write_wait_ns: AtomicU64::new(0),
+    max_write_wait_ns: AtomicU64::new(0),
+};
+
+/// RwLock that records acquisitions and wait times into the global `STATS`.
+/// A poisoned lock is recovered rather than propagated as a panic.
+pub struct InstrumentedRwLock<T> {
+    inner: RwLock<T>,
+}
+
+impl<T> InstrumentedRwLock<T> {
+    pub fn new(value: T) -> Self {
+        InstrumentedRwLock {
+            inner: RwLock::new(value),
+        }
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        let started = Instant::now();
+        let guard = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        let waited = started.elapsed().as_nanos() as u64;
+        STATS.read_acquisitions.fetch_add(1, Ordering::Relaxed);
+        STATS.read_wait_ns.fetch_add(waited, Ordering::Relaxed);
+        guard
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        let started = Instant::now();
+        let guard = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let waited = started.elapsed().as_nanos() as u64;
+        STATS.write_acquisitions.fetch_add(1, Ordering::Relaxed);
+        STATS.write_wait_ns.fetch_add(waited, Ordering::Relaxed);
+        STATS.max_write_wait_ns.fetch_max(waited, Ordering::Relaxed);
+        guard
+    }
+}
+
+/// Returns the global lock counters as a dict (wait times in nanoseconds).
+#[pyfunction]
+pub fn lock_stats(py: Python) -> PyResult<PyObject> {
+    let d = PyDict::new(py);
+    d.set_item(
+        "read_acquisitions",
+        STATS.read_acquisitions.load(Ordering::Relaxed),
+    )?;
+    d.set_item(
+        "write_acquisitions",
+        STATS.write_acquisitions.load(Ordering::Relaxed),
+    )?;
+    d.set_item("read_wait_ns", STATS.read_wait_ns.load(Ordering::Relaxed))?;
+    d.set_item("write_wait_ns", STATS.write_wait_ns.load(Ordering::Relaxed))?;
+    d.set_item(
+        "max_write_wait_ns",
+        STATS.max_write_wait_ns.load(Ordering::Relaxed),
+    )?;
+    Ok(d.into())
+}
+
+#[pyfunction]
+pub fn reset_lock_stats() {
+    STATS.read_acquisitions.store(0, Ordering::Relaxed);
+    STATS.write_acquisitions.store(0, Ordering::Relaxed);
+    STATS.read_wait_ns.store(0, Ordering::Relaxed);
+    STATS.write_wait_ns.store(0, Ordering::Relaxed);
+    STATS.max_write_wait_ns.store(0, Ordering::Relaxed);
+}
+
+/// Builds a deterministic graph where node `i` depends on `fanout` pseudo-random later ids.
+fn generate_graph(nodes: usize, fanout: usize) -> NodeStore {
+    let mut store = NodeStore::default();
+    for i in 0..nodes {
+        let dependents = (1..=fanout)
+            .map(|k| store.intern(&((i * 31 + k * 7919) % nodes).to_string()))
+            .collect();
+        store.insert(&i.to_string(), CausalNode::with_dependents(dependents));
+    }
+    store
+}
+
+/// Sequential BFS used by stress-test readers; returns how many nodes were reached.
+fn reach_count(nodes: &NodeStore, start: &str, limit: usize) -> usize {
+    let mut seen: HashSet<u32> = HashSet::new();
+    let mut queue = VecDeque::from([nodes.lookup(start)]);
+    while let Some(u) = queue.pop_front() {
+        if seen.len() >= limit {
+            break;
+        }
+        if let Some(node) = nodes.node(u) {
+            for &v in &node.dependents {
+                if seen.insert(v) {
+                    queue.push_back(v);
+                }
+            }
+        }
+    }
+    seen.len()
+}
+
+/// Hammers a generated graph behind an `InstrumentedRwLock` with `readers` threads doing
+/// bounded propagations and `writers` threads adding edges for `duration_ms`, then
+/// reports throughput. Lock counters are reset first so `lock_stats()` afterwards
+/// describes this run. Runs without the GIL.
+#[pyfunction]
+#[pyo3(signature = (readers, writers, duration_ms, nodes = 100_000))]
+pub fn stress_test(
+    py: Python,
+    readers: usize,
+    writers: usize,
+    duration_ms: u64,
+    nodes: usize,
+) -> PyResult<PyObject> {
+    let nodes = nodes.max(1);
+    let (read_ops, write_ops) = py.allow_threads(|| {
+        let graph = Arc::new(InstrumentedRwLock::new(generate_graph(nodes, 4)));
+        let stop = Arc::new(AtomicBool::new(false));
+        let read_ops = Arc::new(AtomicU64::new(0));
+        let write_ops = Arc::new(AtomicU64::new(0));
+        reset_lock_stats();
+
+        let mut handles = Vec::with_capacity(readers + writers);
+        for r in 0..readers {
+            let (graph, stop, ops) = (graph.clone(), stop.clone(), read_ops.clone());
+            handles.push(std::thread::spawn(move || {
+                let mut i = r;
+                while !stop.load(Ordering::Relaxed) {
+                    let start = (i % nodes).to_string();
+                    reach_count(&graph.read(), &start, 1_000);
+                    ops.fetch_add(1, Ordering::Relaxed);
+                    i = i.wrapping_add(7919);
+                }
+            }));
+        }
+        for w in 0..writers {
+            let (graph, stop, ops) = (graph.clone(), stop.clone(), write_ops.clone());
+            handles.push(std::thread::spawn(move || {
+                let mut i = w;
+                while !stop.load(Ordering::Relaxed) {
+                    let from = (i % nodes).to_string();
+                    let to = (i.wrapping_mul(13).wrapping_add(1) % nodes).to_string();
+                    let mut nodes = graph.write();
+                    let to = nodes.intern(&to);
+                    if let Some(node) = nodes.get_mut(&from) {
+                        node.push_edge(to, 1.0, 0.0);
+                    }
+                    drop(nodes);
+                    ops.fetch_add(1, Ordering::Relaxed);
+                    i += writers.max(1);
+                }
+            }));
+        }
+
+        std::thread::sleep(Duration::from_millis(duration_ms));
+        stop.store(true, Ordering::Relaxed);
+        for h in handles {
+            let _ = h.join();
+        }
+        (
+            read_ops.load(Ordering::Relaxed),
+            write_ops.load(Ordering::Relaxed),
+        )
+    });
+
+    let secs = (duration_ms as f64 / 1000.0).max(f64::EPSILON);
+    let d = PyDict::new(py);
+    d.set_item("readers", readers)?;
+    d.set_item("writers", writers)?;
+    d.set_item("duration_ms", duration_ms)?;
+    d.set_item("read_ops", read_ops)?;
+    d.set_item("write_ops", write_ops)?;
+    d.set_item("read_ops_per_sec", read_ops as f64 / secs)?;
+    d.set_item("write_ops_per_sec", write_ops as f64 / secs)?;
+    Ok(d.into())
+}
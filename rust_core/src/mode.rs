@@ -0,0 +1,55 @@
+//! How a walker interprets its edges.
+//!
+//! In `undirected_multi` mode an edge is an unordered pair observed some number of
+//! times. Each pair is stored as two mirrored half-edges whose weight is the
+//! observation count, so traversals, communities and neighborhoods are symmetric by
+//! construction and snapshots carry the count as the edge weight.
+
+use crate::errors::ModeError;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphMode {
+    #[default]
+    Directed,
+    UndirectedMulti,
+}
+
+impl GraphMode {
+    pub fn parse(name: &str) -> PyResult<Self> {
+        match name {
+            "directed" => Ok(GraphMode::Directed),
+            "undirected_multi" => Ok(GraphMode::UndirectedMulti),
+            other => Err(PyValueError::new_err(format!(
+                "unknown mode '{}' (expected 'directed' or 'undirected_multi')",
+                other
+            ))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GraphMode::Directed => "directed",
+            GraphMode::UndirectedMulti => "undirected_multi",
+        }
+    }
+
+    pub fn is_directed(&self) -> bool {
+        *self == GraphMode::Directed
+    }
+
+    /// Raises ModeError unless the graph is directed; `op` names the operation.
+    pub fn require_directed(self, op: &str) -> PyResult<()> {
+        if self.is_directed() {
+            return Ok(());
+        }
+        Err(ModeError::new_err(format!(
+            "{} is only defined for directed graphs (this walker is '{}')",
+            op,
+            self.name()
+        )))
+    }
+}
@@ -0,0 +1,44 @@
+//! Topic namespaces for node ids. A consumer subscribed to several topics can prefix
+//! ids with their topic (`orders::user-1` vs `billing::user-1`) so identical ids from
+//! different systems stay distinct nodes in the one graph.
+
+use pyo3::prelude::*;
+
+/// Default separator between the namespace and the id. Kafka topic names cannot
+/// contain `:`, so the first separator always ends the namespace.
+pub const DEFAULT_SEPARATOR: &str = "::";
+
+pub fn qualify(namespace: &str, id: &str, separator: &str) -> String {
+    format!("{}{}{}", namespace, separator, id)
+}
+
+/// Splits `id` into `(namespace, local_id)` at the first separator.
+pub fn split<'a>(id: &'a str, separator: &str) -> Option<(&'a str, &'a str)> {
+    if separator.is_empty() {
+        return None;
+    }
+    id.split_once(separator)
+}
+
+/// `namespace_id("orders", "user-1")` -> `"orders::user-1"`.
+#[pyfunction]
+#[pyo3(signature = (namespace, id, separator = DEFAULT_SEPARATOR))]
+pub fn namespace_id(namespace: &str, id: &str, separator: &str) -> String {
+    qualify(namespace, id, separator)
+}
+
+/// The id without its namespace prefix (unchanged if it has none).
+#[pyfunction]
+#[pyo3(signature = (id, separator = DEFAULT_SEPARATOR))]
+pub fn strip_namespace(id: &str, separator: &str) -> String {
+    split(id, separator)
+        .map_or(id, |(_, local)| local)
+        .to_string()
+}
+
+/// The namespace prefix of `id`, or None if it has none.
+#[pyfunction]
+#[pyo3(signature = (id, separator = DEFAULT_SEPARATOR))]
+pub fn namespace_of(id: &str, separator: &str) -> Option<String> {
+    split(id, separator).map(|(ns, _)| ns.to_string())
+}
@@ -0,0 +1,97 @@
+//! Node id normalization on ingest, so `"Fact-A "` and `"fact-a"` become one node.
+//!
+//! A normalizer is a pipeline of steps applied in order: the built-ins `trim`,
+//! `lowercase`, `nfc` (Unicode NFC, via Python's `unicodedata`) and
+//! `collapse_whitespace` (runs of whitespace become one space, and leading and
+//! trailing whitespace is dropped), or any Python callable taking and returning a
+//! str. Steps should be idempotent: ids already normalized (e.g. replayed events)
+//! pass through the pipeline again.
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+
+enum Step {
+    Trim,
+    Lowercase,
+    Nfc,
+    CollapseWhitespace,
+    Custom(PyObject),
+}
+
+pub struct Normalizer {
+    steps: Vec<Step>,
+}
+
+impl Normalizer {
+    /// Parses a step name, a callable, or a list of those.
+    pub fn from_py(spec: &PyAny) -> PyResult<Self> {
+        let items: Vec<&PyAny> = if spec.is_instance_of::<pyo3::types::PyList>()
+            || spec.is_instance_of::<pyo3::types::PyTuple>()
+        {
+            spec.iter()?.collect::<PyResult<_>>()?
+        } else {
+            vec![spec]
+        };
+        let steps = items
+            .into_iter()
+            .map(|item| {
+                if let Ok(name) = item.extract::<&str>() {
+                    return match name {
+                        "trim" => Ok(Step::Trim),
+                        "lowercase" => Ok(Step::Lowercase),
+                        "nfc" => Ok(Step::Nfc),
+                        "collapse_whitespace" => Ok(Step::CollapseWhitespace),
+                        other => Err(PyValueError::new_err(format!(
+                            "unknown normalization step '{}' (expected trim, lowercase, nfc, \
+                             collapse_whitespace or a callable)",
+                            other
+                        ))),
+                    };
+                }
+                if item.is_callable() {
+                    return Ok(Step::Custom(item.into()));
+                }
+                Err(PyTypeError::new_err(
+                    "normalization steps must be step names or callables",
+                ))
+            })
+            .collect::<PyResult<_>>()?;
+        Ok(Normalizer { steps })
+    }
+
+    /// The steps as `from_py` accepts them: names, and the callables themselves.
+    pub fn to_py(&self, py: Python) -> PyObject {
+        let steps: Vec<PyObject> = self
+            .steps
+            .iter()
+            .map(|step| match step {
+                Step::Trim => "trim".into_py(py),
+                Step::Lowercase => "lowercase".into_py(py),
+                Step::Nfc => "nfc".into_py(py),
+                Step::CollapseWhitespace => "collapse_whitespace".into_py(py),
+                Step::Custom(f) => f.clone_ref(py),
+            })
+            .collect();
+        steps.into_py(py)
+    }
+
+    pub fn apply(&self, py: Python, id: &str) -> PyResult<String> {
+        let mut out = id.to_string();
+        for step in &self.steps {
+            out = match step {
+                Step::Trim => out.trim().to_string(),
+                Step::Lowercase => out.to_lowercase(),
+                Step::Nfc => py
+                    .import("unicodedata")?
+                    .call_method1("normalize", ("NFC", out))?
+                    .extract()?,
+                Step::CollapseWhitespace => out.split_whitespace().collect::<Vec<_>>().join(" "),
+                Step::Custom(f) => f
+                    .call1(py, (out,))?
+                    .extract(py)
+                    .map_err(|_| PyTypeError::new_err("custom normalizer must return a str"))?,
+            };
+        }
+        Ok(out)
+    }
+}
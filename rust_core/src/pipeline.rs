@@ -0,0 +1,443 @@
+//! Consumed messages applied to a walker shared between Python and consumer threads.
+//!
+//! A payload is a JSON object `{"fact_id": ..., "op": ..., "dependents": [...],
+//! "attributes": {...}}` in which every field is optional. `fact_id` defaults to the
+//! message key and `op` to "add", so the cold-start format (see `coldstart`) works
+//! as well, and an empty payload deletes its key like a compaction tombstone:
+//! - "add" replaces the fact's dependents and sets its attributes, as `add_node` does.
+//!   On a directed walker, re-observing a fact instead touches each listed edge
+//!   (see `touch_edge`) at the message's timestamp and removes the unlisted ones,
+//!   so edges seen again keep decaying from their latest observation;
+//! - "invalidate" propagates from the fact (an unknown fact invalidates nothing) and
+//!   appends the result to the walker's invalidation log, and to the changefeed
+//!   file and output topic if the consumer has them;
+//! - "delete" removes the fact, or tombstones it for consumers started with
+//!   `tombstone_deletes`.
+//!
+//! Adds and deletes go through the streamed-event path (`submit_events`), so a
+//! frozen walker buffers them, or under the "pause" policy holds the consumer until
+//! `unfreeze`. With `track_provenance`, the edges they create record the message's
+//! topic, partition and offset.
+//!
+//! The walker's lock is only taken with the GIL released. Whoever holds it may take
+//! the GIL (id normalizers need it), so the reverse order never happens.
+
+use crate::changefeed;
+use crate::events::GraphEvent;
+use crate::limits::Limits;
+use crate::locks::InstrumentedRwLock;
+use crate::producer::Publish;
+use crate::provenance::Origin;
+use crate::source::Delivery;
+use crate::{now_secs, GraphWalker};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Op {
+    #[default]
+    Add,
+    Invalidate,
+    Delete,
+}
+
+#[derive(Default, Deserialize)]
+struct Payload {
+    fact_id: Option<String>,
+    #[serde(default)]
+    op: Op,
+    #[serde(default)]
+    dependents: Vec<String>,
+    #[serde(default)]
+    attributes: BTreeMap<String, String>,
+}
+
+/// A parsed message, with its ids already mapped to graph ids.
+pub struct Message {
+    op: Op,
+    id: String,
+    dependents: Vec<String>,
+    attributes: BTreeMap<String, String>,
+    origin: Origin,
+    /// The message timestamp in seconds, or the time it was parsed.
+    observed_at: f64,
+}
+
+impl Message {
+    /// Parses `delivery`, passing the fact id and dependents through `node_id`. The
+    /// error says why the message is malformed.
+    pub fn parse(
+        delivery: &Delivery,
+        node_id: impl Fn(&str) -> Result<String, String>,
+    ) -> Result<Message, String> {
+        let key = match delivery.key.as_deref() {
+            Some(key) => Some(std::str::from_utf8(key).map_err(|_| "key is not valid UTF-8")?),
+            None => None,
+        };
+        let payload = match delivery.payload.as_deref() {
+            None | Some([]) => Payload {
+                op: Op::Delete,
+                ..Payload::default()
+            },
+            Some(bytes) => serde_json::from_slice(bytes)
+                .map_err(|e| format!("payload is not a valid message: {}", e))?,
+        };
+        let id = payload
+            .fact_id
+            .as_deref()
+            .or(key)
+            .filter(|id| !id.is_empty())
+            .ok_or("no fact_id in the payload and no key")?;
+        Ok(Message {
+            op: payload.op,
+            id: node_id(id)?,
+            dependents: payload
+                .dependents
+                .iter()
+                .map(|d| node_id(d))
+                .collect::<Result<_, _>>()?,
+            attributes: payload.attributes,
+            origin: Origin {
+                topic: delivery.topic.clone(),
+                partition: u32::try_from(delivery.partition).ok(),
+                offset: u64::try_from(delivery.offset).ok(),
+            },
+            observed_at: delivery
+                .timestamp
+                .map_or_else(now_secs, |ms| ms as f64 / 1000.0),
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// How a consumer applies messages.
+pub struct ApplyOptions {
+    /// Tombstone deleted facts instead of removing them.
+    pub tombstone_deletes: bool,
+    /// Changefeed file that invalidation results are appended to.
+    pub changefeed: Option<String>,
+    /// Topic that invalidation results are sent to.
+    pub publish: Option<Publish>,
+}
+
+pub enum Applied {
+    Done,
+    /// The walker is frozen under the "pause" policy; the message should be retried.
+    Paused,
+}
+
+/// One entry of the invalidation log.
+#[derive(Clone)]
+struct Invalidation {
+    fact_id: String,
+    /// Sorted.
+    invalidated: Vec<String>,
+    origin: Origin,
+    at: f64,
+}
+
+pub struct Shared {
+    walker: InstrumentedRwLock<GraphWalker>,
+    /// Most recent invalidations, oldest first.
+    invalidations: Mutex<VecDeque<Invalidation>>,
+    log_size: usize,
+}
+
+impl Shared {
+    /// Applies `message`; errors are those of the mutator or propagation it runs.
+    pub fn apply(&self, message: &Message, options: &ApplyOptions) -> PyResult<Applied> {
+        if message.op == Op::Invalidate {
+            self.invalidate(message, options)?;
+            return Ok(Applied::Done);
+        }
+        let mut walker = self.walker.write();
+        if walker.frozen.as_ref().is_some_and(|f| !f.buffer) {
+            return Ok(Applied::Paused);
+        }
+        let events = match message.op {
+            Op::Add => add_events(&walker, message)?,
+            Op::Delete if options.tombstone_deletes => vec![GraphEvent::Tombstone {
+                id: message.id.clone(),
+                ts: now_secs(),
+            }],
+            Op::Delete => vec![GraphEvent::RemoveNode {
+                id: message.id.clone(),
+                prune_edges: false,
+            }],
+            Op::Invalidate => unreachable!("invalidations are applied above"),
+        };
+        walker.origin = Some(message.origin.clone());
+        let result = walker.submit(events);
+        walker.origin = None;
+        result.map(|_| Applied::Done)
+    }
+
+    fn invalidate(&self, message: &Message, options: &ApplyOptions) -> PyResult<()> {
+        let (id, invalidated) = {
+            let walker = self.walker.read();
+            let id = walker.lookup_id(message.id.clone())?;
+            if !walker.nodes.contains_key(&id) {
+                (id, HashSet::new())
+            } else {
+                let invalidated =
+                    walker.propagate(&id, &Limits::resolve(None, None), None, None)?;
+                (id, invalidated)
+            }
+        };
+        let mut invalidated: Vec<String> = invalidated.into_iter().collect();
+        invalidated.sort_unstable();
+        if let Some(path) = &options.changefeed {
+            changefeed::append(path, id.clone(), invalidated.clone())?;
+        }
+        if let Some(publish) = &options.publish {
+            publish.publish(&id, invalidated.clone())?;
+        }
+        if self.log_size == 0 {
+            return Ok(());
+        }
+        let mut log = self.invalidations.lock().unwrap_or_else(|e| e.into_inner());
+        if log.len() == self.log_size {
+            log.pop_front();
+        }
+        log.push_back(Invalidation {
+            fact_id: id,
+            invalidated,
+            origin: message.origin.clone(),
+            at: now_secs(),
+        });
+        Ok(())
+    }
+}
+
+/// The events of an "add" message. A live, directed fact that is already a node
+/// is re-observed: edges it still lists are touched, existing ones first since
+/// only new edges can fail the DAG and schema checks, and edges it no longer
+/// lists are removed afterwards. Any other fact is added with `add_node`. Ids
+/// are looked up as the walker's normalizer stores them.
+fn add_events(walker: &GraphWalker, message: &Message) -> PyResult<Vec<GraphEvent>> {
+    let id = walker.lookup_id(message.id.clone())?;
+    let dependents = walker.lookup_slice(&message.dependents)?;
+    let current = walker
+        .nodes
+        .get(&id)
+        .filter(|_| walker.mode.is_directed() && !walker.tombstones.contains_key(&id));
+    let mut events = match current {
+        None => vec![GraphEvent::AddNode {
+            id: id.clone(),
+            dependents: dependents.to_vec(),
+        }],
+        Some(node) => {
+            let current: HashSet<&str> = node
+                .dependents
+                .iter()
+                .map(|&h| walker.nodes.name(h).as_str())
+                .collect();
+            let listed: HashSet<&str> = dependents.iter().map(String::as_str).collect();
+            let mut seen = HashSet::new();
+            let (mut touches, new): (Vec<_>, Vec<_>) = dependents
+                .iter()
+                .filter(|d| seen.insert(d.as_str()))
+                .partition(|d| current.contains(d.as_str()));
+            touches.extend(new);
+            let mut events: Vec<GraphEvent> = touches
+                .into_iter()
+                .map(|to| GraphEvent::TouchEdge {
+                    from: id.clone(),
+                    to: to.clone(),
+                    ts: message.observed_at,
+                })
+                .collect();
+            let mut dropped: Vec<&str> = current.difference(&listed).copied().collect();
+            dropped.sort_unstable();
+            events.extend(dropped.into_iter().map(|to| GraphEvent::RemoveEdge {
+                from: id.clone(),
+                to: to.to_string(),
+            }));
+            events
+        }
+    };
+    events.extend(
+        message
+            .attributes
+            .iter()
+            .map(|(key, value)| GraphEvent::SetAttrs {
+                ids: vec![id.clone()],
+                key: key.clone(),
+                values: vec![value.clone()],
+            }),
+    );
+    Ok(events)
+}
+
+/// A walker that consumers started with `walker=` apply messages to while Python
+/// reads it (see `pipeline`). Reads wait for the message being applied, and the
+/// GIL is released meanwhile. `snapshot()` gives a plain `GraphWalker` copy for
+/// anything not offered here.
+#[pyclass(module = "rust_core")]
+pub struct SharedGraphWalker {
+    shared: Arc<Shared>,
+}
+
+impl SharedGraphWalker {
+    pub fn shared(&self) -> Arc<Shared> {
+        self.shared.clone()
+    }
+
+    fn read<R: Send>(&self, py: Python, f: impl FnOnce(&GraphWalker) -> R + Send) -> R {
+        py.allow_threads(|| f(&self.shared.walker.read()))
+    }
+
+    fn write<R: Send>(&self, py: Python, f: impl FnOnce(&mut GraphWalker) -> R + Send) -> R {
+        py.allow_threads(|| f(&mut self.shared.walker.write()))
+    }
+}
+
+#[pymethods]
+impl SharedGraphWalker {
+    /// Takes over the graph and settings of `walker`, which is left as a new empty
+    /// walker (copying a large graph would double its memory), or starts from an empty
+    /// directed walker. The invalidation log keeps the last `log_size` results.
+    #[new]
+    #[pyo3(signature = (walker = None, log_size = 100))]
+    fn new(walker: Option<PyRefMut<GraphWalker>>, log_size: usize) -> PyResult<Self> {
+        let walker = match walker {
+            Some(mut walker) => std::mem::replace(&mut *walker, GraphWalker::blank()),
+            None => GraphWalker::blank(),
+        };
+        Ok(SharedGraphWalker {
+            shared: Arc::new(Shared {
+                walker: InstrumentedRwLock::new(walker),
+                invalidations: Mutex::new(VecDeque::with_capacity(log_size.min(1024))),
+                log_size,
+            }),
+        })
+    }
+
+    /// An independent copy of the current graph.
+    fn snapshot(&self, py: Python) -> PyResult<GraphWalker> {
+        self.read(py, |walker| Python::with_gil(|py| walker.__copy__(py)))
+    }
+
+    fn node_count(&self, py: Python) -> usize {
+        self.read(py, GraphWalker::node_count)
+    }
+
+    fn __len__(&self, py: Python) -> usize {
+        self.node_count(py)
+    }
+
+    #[pyo3(signature = (unique = false))]
+    fn edge_count(&self, py: Python, unique: bool) -> usize {
+        self.read(py, |walker| walker.edge_count(unique))
+    }
+
+    fn version(&self, py: Python) -> u64 {
+        self.read(py, GraphWalker::version)
+    }
+
+    fn has_node(&self, py: Python, id: &str) -> PyResult<bool> {
+        self.read(py, |walker| walker.has_node(id))
+    }
+
+    /// As `GraphWalker.get_dependents`.
+    fn get_dependents(&self, py: Python, id: &str) -> PyResult<Vec<String>> {
+        self.read(py, |walker| walker.get_dependents(id))
+    }
+
+    /// As `GraphWalker.freeze`; the policy applies to consumers applying messages.
+    #[pyo3(signature = (consumer_policy = "buffer"))]
+    fn freeze(&self, py: Python, consumer_policy: &str) -> PyResult<()> {
+        self.write(py, |walker| walker.freeze(consumer_policy))
+    }
+
+    /// As `GraphWalker.unfreeze`.
+    fn unfreeze(&self, py: Python) -> PyResult<PyObject> {
+        self.write(py, |walker| Python::with_gil(|py| walker.unfreeze(py)))
+    }
+
+    fn is_frozen(&self, py: Python) -> bool {
+        self.read(py, GraphWalker::is_frozen)
+    }
+
+    /// As `GraphWalker.hot_nodes`; invalidations run by consumers count too.
+    fn hot_nodes(&self, py: Python, k: usize) -> Vec<(String, u64)> {
+        self.read(py, |walker| walker.hot_nodes(k))
+    }
+
+    /// As `GraphWalker.add_node`.
+    #[pyo3(signature = (id, dependents, weights = None, attributes = None))]
+    fn add_node(
+        &self,
+        py: Python,
+        id: String,
+        dependents: Vec<String>,
+        weights: Option<Vec<f64>>,
+        attributes: Option<HashMap<String, String>>,
+    ) -> PyResult<()> {
+        self.write(py, |walker| {
+            walker.add_node(id, dependents, weights, attributes)
+        })
+    }
+
+    /// As `GraphWalker.propagate_invalidation`, without logging the result.
+    #[pyo3(signature = (start_id, max_results = None, max_visited = None, strict = true))]
+    fn propagate_invalidation(
+        &self,
+        py: Python,
+        start_id: String,
+        max_results: Option<usize>,
+        max_visited: Option<usize>,
+        strict: bool,
+    ) -> PyResult<HashSet<String>> {
+        self.read(py, |walker| {
+            let start_id = walker.lookup_id(start_id)?;
+            if !walker.nodes.contains_key(&start_id) {
+                return match strict {
+                    true => Err(crate::NodeNotFoundError::new_err(start_id)),
+                    false => Ok(HashSet::new()),
+                };
+            }
+            let limits = Limits::resolve(max_results, max_visited);
+            walker.propagate(&start_id, &limits, None, None)
+        })
+    }
+
+    /// The logged results of "invalidate" messages, oldest first, as dicts with
+    /// `fact_id`, `invalidated` (sorted), `topic`, `partition`, `offset` and `at`
+    /// (when it was applied, in seconds since the epoch).
+    fn recent_invalidations(&self, py: Python) -> PyResult<Vec<PyObject>> {
+        let log: Vec<Invalidation> = self
+            .shared
+            .invalidations
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect();
+        log.into_iter()
+            .map(|entry| {
+                let d = PyDict::new(py);
+                d.set_item("fact_id", entry.fact_id)?;
+                d.set_item("invalidated", entry.invalidated)?;
+                d.set_item("topic", entry.origin.topic)?;
+                d.set_item("partition", entry.origin.partition)?;
+                d.set_item("offset", entry.origin.offset)?;
+                d.set_item("at", entry.at)?;
+                Ok(d.into())
+            })
+            .collect()
+    }
+
+    fn __repr__(&self, py: Python) -> String {
+        let (nodes, edges) =
+            self.read(py, |walker| (walker.nodes.len(), walker.edge_count_total()));
+        format!("SharedGraphWalker(nodes={}, edges={})", nodes, edges)
+    }
+}
@@ -0,0 +1,347 @@
+//! Kafka producer for sending invalidation results back out, so a blast radius
+//! goes from the walker to a topic without passing through Python.
+//!
+//! Sends are queued and delivered in the background. Each delivery report is
+//! counted, and `flush` waits for the queue to drain and returns the counts since
+//! the previous flush, so failures are reported rather than dropped. A
+//! `MemoryBroker` can stand in for the cluster, and `fail_sends` on it makes
+//! deliveries fail on purpose.
+
+use crate::config::log_at;
+use crate::kafka::{redact, SecurityConfig};
+use crate::publish::{InvalidationPublisher, Publication};
+use crate::source::{bytes_of, MemoryBroker};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rdkafka::config::ClientConfig;
+use rdkafka::error::KafkaError;
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+
+/// How often `flush` checks whether the last delivery reports have been counted.
+const SETTLE_POLL: Duration = Duration::from_millis(5);
+
+/// Delivery reports since the last flush.
+#[derive(Default)]
+struct Reports {
+    delivered: AtomicU64,
+    failed: AtomicU64,
+    /// Sent but not yet reported.
+    in_flight: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+impl Reports {
+    fn report(&self, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.delivered.fetch_add(1, Ordering::Relaxed),
+            Err(e) => {
+                log_at!(Warn, "Message delivery failed: {}", e);
+                *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(e);
+                self.failed.fetch_add(1, Ordering::Relaxed)
+            }
+        };
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Where a producer's messages go.
+trait Sink: Send + Sync {
+    /// Queues a message and reports its delivery to `reports` once known. An error
+    /// means it could not be queued at all.
+    fn send(
+        &self,
+        topic: &str,
+        key: Option<&[u8]>,
+        payload: &[u8],
+        reports: &Arc<Reports>,
+    ) -> Result<(), String>;
+
+    /// Waits up to `timeout` for queued messages to be delivered.
+    fn flush(&self, timeout: Duration);
+}
+
+struct KafkaSink {
+    producer: FutureProducer,
+    /// Runs the tasks that wait for delivery reports.
+    runtime: Runtime,
+}
+
+impl Sink for KafkaSink {
+    fn send(
+        &self,
+        topic: &str,
+        key: Option<&[u8]>,
+        payload: &[u8],
+        reports: &Arc<Reports>,
+    ) -> Result<(), String> {
+        let mut record = FutureRecord::<[u8], [u8]>::to(topic).payload(payload);
+        if let Some(key) = key {
+            record = record.key(key);
+        }
+        let delivery = self
+            .producer
+            .send_result(record)
+            .map_err(|(e, _)| e.to_string())?;
+        reports.in_flight.fetch_add(1, Ordering::SeqCst);
+        let reports = reports.clone();
+        self.runtime.spawn(async move {
+            let result = match delivery.await {
+                Ok(Ok(_)) => Ok(()),
+                Ok(Err((e, _))) => Err(e.to_string()),
+                Err(_) => Err("the producer was closed before delivery".to_string()),
+            };
+            reports.report(result);
+        });
+        Ok(())
+    }
+
+    fn flush(&self, timeout: Duration) {
+        if let Err(e) = self.producer.flush(timeout) {
+            log_at!(Warn, "Producer flush incomplete: {}", e);
+        }
+    }
+}
+
+impl Sink for MemoryBroker {
+    fn send(
+        &self,
+        topic: &str,
+        key: Option<&[u8]>,
+        payload: &[u8],
+        reports: &Arc<Reports>,
+    ) -> Result<(), String> {
+        reports.in_flight.fetch_add(1, Ordering::SeqCst);
+        reports.report(self.produce(topic, key, payload));
+        Ok(())
+    }
+
+    fn flush(&self, _timeout: Duration) {}
+}
+
+/// JSON payload of `publish_invalidation`.
+#[derive(Serialize)]
+struct InvalidationMessage<'a> {
+    start_id: &'a str,
+    invalidated: &'a [String],
+    count: usize,
+    /// Seconds since the epoch.
+    timestamp: f64,
+    /// Triggers an `InvalidationPublisher` folded into this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suppressed: Option<u64>,
+}
+
+/// The producer behind a `CausalProducer`, shared with the consumers that publish
+/// through it.
+pub struct Output {
+    sink: Box<dyn Sink>,
+    reports: Arc<Reports>,
+}
+
+impl Output {
+    pub fn send(&self, topic: &str, key: Option<&[u8]>, payload: &[u8]) -> PyResult<()> {
+        self.sink
+            .send(topic, key, payload, &self.reports)
+            .map_err(|e| PyRuntimeError::new_err(format!("cannot queue message: {}", e)))
+    }
+
+    /// Sends `invalidated` (sorted) as the result for `start_id`, keyed by it.
+    pub fn publish(
+        &self,
+        topic: &str,
+        start_id: &str,
+        invalidated: &[String],
+        suppressed: Option<u64>,
+    ) -> PyResult<()> {
+        let message = InvalidationMessage {
+            start_id,
+            invalidated,
+            count: invalidated.len(),
+            timestamp: crate::now_secs(),
+            suppressed,
+        };
+        let payload = serde_json::to_vec(&message).expect("invalidation message serializes");
+        self.send(topic, Some(start_id.as_bytes()), &payload)
+    }
+}
+
+/// Where a consumer sends the result of each "invalidate" message (see
+/// `pipeline`).
+pub struct Publish {
+    pub output: Arc<Output>,
+    pub topic: String,
+    /// Rate limits and deduplicates what is sent, if given.
+    pub publisher: Option<Py<InvalidationPublisher>>,
+}
+
+impl Publish {
+    pub fn publish(&self, start_id: &str, invalidated: Vec<String>) -> PyResult<()> {
+        let Some(publisher) = &self.publisher else {
+            return self
+                .output
+                .publish(&self.topic, start_id, &invalidated, None);
+        };
+        let publication = Python::with_gil(|py| {
+            publisher
+                .borrow_mut(py)
+                .offer_at(start_id.to_string(), invalidated, crate::now_secs())
+        });
+        match publication {
+            Some(p) => self.send(&p),
+            None => Ok(()),
+        }
+    }
+
+    /// Sends what the publisher held back and may now send (see
+    /// `InvalidationPublisher.flush_due`), or with `all` everything it holds, as
+    /// when the consumer stops. Without a publisher nothing is held back.
+    pub fn flush(&self, all: bool) -> PyResult<()> {
+        let Some(publisher) = &self.publisher else {
+            return Ok(());
+        };
+        let due = Python::with_gil(|py| publisher.borrow_mut(py).flush_at(crate::now_secs(), all));
+        due.iter().try_for_each(|p| self.send(p))
+    }
+
+    fn send(&self, p: &Publication) -> PyResult<()> {
+        self.output
+            .publish(&self.topic, &p.start_id, &p.affected, Some(p.suppressed))
+    }
+}
+
+/// A Kafka producer, or a `MemoryBroker` in its place.
+#[pyclass(module = "rust_core")]
+#[derive(Clone)]
+pub struct CausalProducer {
+    output: Arc<Output>,
+}
+
+impl CausalProducer {
+    pub fn output(&self) -> Arc<Output> {
+        self.output.clone()
+    }
+}
+
+#[pymethods]
+impl CausalProducer {
+    /// `brokers` is a bootstrap server list, or a `MemoryBroker`. `config` holds
+    /// extra librdkafka properties, and `security` TLS and SASL settings (see
+    /// `SecurityConfig`). A property librdkafka rejects, or one `security` sets,
+    /// raises ValueError.
+    #[new]
+    #[pyo3(signature = (brokers, config = None, security = None))]
+    fn new(
+        brokers: &PyAny,
+        config: Option<HashMap<String, String>>,
+        security: Option<SecurityConfig>,
+    ) -> PyResult<Self> {
+        let sink: Box<dyn Sink> = match brokers.extract::<MemoryBroker>() {
+            Ok(broker) => Box::new(broker),
+            Err(_) => {
+                let config = config.unwrap_or_default();
+                let mut properties = BTreeMap::new();
+                if let Some(security) = &security {
+                    security.check_overlap(&config)?;
+                    properties.extend(security.properties());
+                }
+                properties.extend(config);
+                let mut client = ClientConfig::new();
+                client.set("bootstrap.servers", brokers.extract::<&str>()?);
+                for (k, v) in &properties {
+                    client.set(k, v);
+                }
+                let producer = client.create().map_err(|e| match e {
+                    KafkaError::ClientConfig(..) | KafkaError::ClientCreation(..) => {
+                        let message = format!("invalid producer setting: {}", e);
+                        PyValueError::new_err(redact(message, &properties))
+                    }
+                    e => PyRuntimeError::new_err(format!("producer creation failed: {}", e)),
+                })?;
+                let runtime = tokio::runtime::Builder::new_multi_thread()
+                    .worker_threads(1)
+                    .enable_all()
+                    .build()
+                    .map_err(|e| {
+                        PyRuntimeError::new_err(format!("cannot start producer runtime: {}", e))
+                    })?;
+                Box::new(KafkaSink { producer, runtime })
+            }
+        };
+        Ok(CausalProducer {
+            output: Arc::new(Output {
+                sink,
+                reports: Arc::default(),
+            }),
+        })
+    }
+
+    /// Queues `payload` for `topic` under `key` (each str or bytes; `key` may be
+    /// None). Raises RuntimeError if it cannot be queued, e.g. when the queue is
+    /// full; delivery failures are counted by `flush`.
+    #[pyo3(signature = (topic, key, payload))]
+    fn send(&self, topic: &str, key: Option<&PyAny>, payload: &PyAny) -> PyResult<()> {
+        let key = key.map(bytes_of).transpose()?;
+        self.output.send(topic, key.as_deref(), &bytes_of(payload)?)
+    }
+
+    /// Sends `{"start_id", "invalidated", "count", "timestamp"}` to `topic`, keyed
+    /// by `start_id`. `invalidated` is any iterable of ids, such as the set
+    /// `propagate_invalidation` returns; it is sent sorted, without duplicates.
+    /// `timestamp` is in seconds.
+    fn publish_invalidation(
+        &self,
+        topic: &str,
+        start_id: &str,
+        invalidated: &PyAny,
+    ) -> PyResult<()> {
+        let mut invalidated = invalidated
+            .iter()?
+            .map(|id| id?.extract())
+            .collect::<PyResult<Vec<String>>>()?;
+        invalidated.sort_unstable();
+        invalidated.dedup();
+        self.output.publish(topic, start_id, &invalidated, None)
+    }
+
+    /// Waits up to `timeout_secs` for queued messages to be delivered. Returns the
+    /// reports since the previous flush as `{"delivered", "failed", "pending",
+    /// "last_error"}`; `pending` counts messages still unconfirmed at the timeout.
+    #[pyo3(signature = (timeout_secs = 10.0))]
+    fn flush(&self, py: Python, timeout_secs: f64) -> PyResult<PyObject> {
+        let timeout = Duration::try_from_secs_f64(timeout_secs).map_err(|_| {
+            PyValueError::new_err(format!(
+                "timeout_secs must be a non-negative number, got {}",
+                timeout_secs
+            ))
+        })?;
+        let reports = &self.output.reports;
+        py.allow_threads(|| {
+            let deadline = Instant::now() + timeout;
+            self.output.sink.flush(timeout);
+            // Reports arrive on the runtime shortly after librdkafka has them.
+            while reports.in_flight.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+                std::thread::sleep(SETTLE_POLL);
+            }
+        });
+        let d = PyDict::new(py);
+        d.set_item("delivered", reports.delivered.swap(0, Ordering::Relaxed))?;
+        d.set_item("failed", reports.failed.swap(0, Ordering::Relaxed))?;
+        d.set_item("pending", reports.in_flight.load(Ordering::SeqCst))?;
+        d.set_item(
+            "last_error",
+            reports
+                .last_error
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .take(),
+        )?;
+        Ok(d.into())
+    }
+}
@@ -0,0 +1,165 @@
+//! Optional per-edge provenance: which source (Kafka topic, or "api" for direct
+//! calls) created an edge, and where in that source it came from. Edges are keyed by
+//! interned (from, to) handles and source names are interned too, so each record
+//! costs a few dozen bytes.
+
+use crate::interner::Interner;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Source name recorded for mutations made directly through the Python API.
+pub const API_SOURCE: &str = "api";
+
+/// Position of a consumed message, recorded for the edges its event creates.
+#[derive(Clone)]
+pub struct Origin {
+    pub topic: String,
+    pub partition: Option<u32>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Provenance {
+    source: u32,
+    partition: Option<u32>,
+    offset: Option<u64>,
+    timestamp: f64,
+}
+
+/// Serialized form used by snapshots.
+#[derive(Serialize, Deserialize)]
+pub struct ProvenanceRecord {
+    pub source: String,
+    #[serde(default)]
+    pub partition: Option<u32>,
+    #[serde(default)]
+    pub offset: Option<u64>,
+    pub timestamp: f64,
+}
+
+#[derive(Default)]
+pub struct ProvenanceStore {
+    sources: Interner,
+    edges: HashMap<(u32, u32), Provenance>,
+    /// While a transaction is open, the record each changed edge had before it.
+    journal: Option<HashMap<(u32, u32), Option<Provenance>>>,
+}
+
+impl ProvenanceStore {
+    /// Records who created `from -> to`. The first record for an edge wins, so
+    /// re-observations keep pointing at the original message.
+    #[allow(clippy::too_many_arguments)]
+    pub fn record(
+        &mut self,
+        ids: &mut Interner,
+        from: &str,
+        to: &str,
+        source: &str,
+        partition: Option<u32>,
+        offset: Option<u64>,
+        timestamp: f64,
+    ) {
+        let key = (ids.intern(from), ids.intern(to));
+        let source = self.sources.intern(source);
+        self.save(key);
+        self.edges.entry(key).or_insert(Provenance {
+            source,
+            partition,
+            offset,
+            timestamp,
+        });
+    }
+
+    pub fn get(&self, ids: &Interner, from: &str, to: &str) -> Option<Provenance> {
+        self.edges.get(&(ids.get(from)?, ids.get(to)?)).copied()
+    }
+
+    pub fn forget(&mut self, ids: &Interner, from: &str, to: &str) {
+        if let (Some(f), Some(t)) = (ids.get(from), ids.get(to)) {
+            self.save((f, t));
+            self.edges.remove(&(f, t));
+        }
+    }
+
+    /// Copies the record of `from -> to` (if any) onto `new_from -> new_to`.
+    pub fn copy(&mut self, ids: &mut Interner, from: &str, to: &str, new_from: &str, new_to: &str) {
+        if let Some(p) = self.get(ids, from, to) {
+            let key = (ids.intern(new_from), ids.intern(new_to));
+            self.save(key);
+            self.edges.insert(key, p);
+        }
+    }
+
+    /// Drops records whose edge no longer exists according to `exists(from, to)`.
+    pub fn retain(&mut self, ids: &Interner, mut exists: impl FnMut(&str, &str) -> bool) {
+        let journal = &mut self.journal;
+        self.edges.retain(|&(f, t), p| {
+            let keep = match (ids.resolve(f), ids.resolve(t)) {
+                (Some(f), Some(t)) => exists(f, t),
+                _ => false,
+            };
+            if let (false, Some(journal)) = (keep, journal.as_mut()) {
+                journal.entry((f, t)).or_insert(Some(*p));
+            }
+            keep
+        });
+    }
+
+    /// Starts saving the record of every edge changed, for `rollback_journal`.
+    pub fn begin_journal(&mut self) {
+        self.journal = Some(HashMap::new());
+    }
+
+    pub fn commit_journal(&mut self) {
+        self.journal = None;
+    }
+
+    /// Puts back every record changed since `begin_journal`.
+    pub fn rollback_journal(&mut self) {
+        for (key, old) in self.journal.take().unwrap_or_default() {
+            match old {
+                Some(p) => self.edges.insert(key, p),
+                None => self.edges.remove(&key),
+            };
+        }
+    }
+
+    fn save(&mut self, key: (u32, u32)) {
+        if let Some(journal) = self.journal.as_mut() {
+            journal
+                .entry(key)
+                .or_insert_with(|| self.edges.get(&key).copied());
+        }
+    }
+
+    pub fn to_py(&self, py: Python, p: &Provenance) -> PyResult<PyObject> {
+        let d = PyDict::new(py);
+        d.set_item("source", self.sources.resolve(p.source))?;
+        d.set_item("partition", p.partition)?;
+        d.set_item("offset", p.offset)?;
+        d.set_item("timestamp", p.timestamp)?;
+        Ok(d.into())
+    }
+
+    pub fn to_record(&self, p: &Provenance) -> ProvenanceRecord {
+        ProvenanceRecord {
+            source: self
+                .sources
+                .resolve(p.source)
+                .unwrap_or_default()
+                .to_string(),
+            partition: p.partition,
+            offset: p.offset,
+            timestamp: p.timestamp,
+        }
+    }
+
+    /// Approximate heap footprint in bytes (excluding the shared id interner).
+    pub fn heap_bytes(&self) -> usize {
+        self.edges.capacity()
+            * (std::mem::size_of::<(u32, u32)>() + std::mem::size_of::<Provenance>())
+            + self.sources.heap_bytes()
+    }
+}
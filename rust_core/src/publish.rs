@@ -0,0 +1,208 @@
+//! Output-side controls for invalidation publishing. A flapping hub node would
+//! otherwise flood the output topic with near-identical affected sets, so each start
+//! node is rate limited (suppressed triggers are folded into the next publication)
+//! and publications whose affected set matches the previous one are dropped.
+//!
+//! The publisher only decides *what* to send; the caller hands the returned
+//! publications to its producer.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+#[derive(Default)]
+struct NodeState {
+    last_published_at: Option<f64>,
+    last_digest: Option<[u8; 32]>,
+    /// Most recent suppressed affected set, published once the window elapses.
+    pending: Option<Vec<String>>,
+    /// Triggers suppressed since the last publication.
+    suppressed: u64,
+}
+
+/// One invalidation ready to be sent to the output topic.
+pub struct Publication {
+    pub start_id: String,
+    pub affected: Vec<String>,
+    pub suppressed: u64,
+}
+
+impl Publication {
+    fn to_py(&self, py: Python) -> PyResult<PyObject> {
+        let d = PyDict::new(py);
+        d.set_item("start_id", &self.start_id)?;
+        d.set_item("affected", &self.affected)?;
+        d.set_item("suppressed", self.suppressed)?;
+        Ok(d.into())
+    }
+}
+
+#[pyclass]
+pub struct InvalidationPublisher {
+    /// Minimum seconds between publications for one start node; 0 disables limiting.
+    min_interval: f64,
+    dedup: bool,
+    nodes: HashMap<String, NodeState>,
+    published: u64,
+    rate_limited: u64,
+    deduplicated: u64,
+}
+
+#[pymethods]
+impl InvalidationPublisher {
+    #[new]
+    #[pyo3(signature = (min_interval = 0.0, dedup = true))]
+    fn new(min_interval: f64, dedup: bool) -> PyResult<Self> {
+        if !min_interval.is_finite() || min_interval < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "min_interval must be a finite non-negative number, got {}",
+                min_interval
+            )));
+        }
+        Ok(InvalidationPublisher {
+            min_interval,
+            dedup,
+            nodes: HashMap::new(),
+            published: 0,
+            rate_limited: 0,
+            deduplicated: 0,
+        })
+    }
+
+    /// Offers an invalidation of `start_id` affecting `affected` at time `now`
+    /// (seconds; defaults to the wall clock). Returns the publication to send as
+    /// `{"start_id", "affected", "suppressed"}`, or None if it was rate limited or
+    /// duplicates the previous publication for this node. `suppressed` counts the
+    /// triggers folded into this publication.
+    #[pyo3(signature = (start_id, affected, now = None))]
+    fn offer(
+        &mut self,
+        py: Python,
+        start_id: String,
+        affected: Vec<String>,
+        now: Option<f64>,
+    ) -> PyResult<Option<PyObject>> {
+        let now = now.unwrap_or_else(crate::now_secs);
+        match self.offer_at(start_id, affected, now) {
+            Some(p) => p.to_py(py).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Publishes every pending (rate limited) invalidation whose window has elapsed by
+    /// `now`, so the final state of a node that stopped flapping is not lost.
+    /// Publications are returned sorted by start id. Consumers publishing through
+    /// this publisher call it themselves (see `start_kafka_consumer`).
+    #[pyo3(signature = (now = None))]
+    fn flush_due(&mut self, py: Python, now: Option<f64>) -> PyResult<Vec<PyObject>> {
+        let now = now.unwrap_or_else(crate::now_secs);
+        self.flush_at(now, false)
+            .iter()
+            .map(|p| p.to_py(py))
+            .collect()
+    }
+
+    /// Counters: `published`, `rate_limited` and `deduplicated` publications.
+    fn metrics(&self, py: Python) -> PyResult<PyObject> {
+        let d = PyDict::new(py);
+        d.set_item("published", self.published)?;
+        d.set_item("rate_limited", self.rate_limited)?;
+        d.set_item("deduplicated", self.deduplicated)?;
+        Ok(d.into())
+    }
+}
+
+impl InvalidationPublisher {
+    /// `offer` without the conversion to Python, for consumers that publish
+    /// directly (see `producer`).
+    pub fn offer_at(
+        &mut self,
+        start_id: String,
+        affected: Vec<String>,
+        now: f64,
+    ) -> Option<Publication> {
+        let min_interval = self.min_interval;
+        let state = self.nodes.entry(start_id.clone()).or_default();
+        let within_window = state
+            .last_published_at
+            .is_some_and(|t| now - t < min_interval);
+        if within_window {
+            state.pending = Some(affected);
+            state.suppressed += 1;
+            self.rate_limited += 1;
+            return None;
+        }
+        self.publish(start_id, affected, now)
+    }
+
+    /// `flush_due` without the conversion to Python. With `all`, pending
+    /// invalidations are published even within their window, as a consumer does
+    /// when it stops.
+    pub fn flush_at(&mut self, now: f64, all: bool) -> Vec<Publication> {
+        let mut due: Vec<String> = self
+            .nodes
+            .iter()
+            .filter(|(_, s)| {
+                s.pending.is_some()
+                    && (all
+                        || !s
+                            .last_published_at
+                            .is_some_and(|t| now - t < self.min_interval))
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        due.sort();
+        let mut out = Vec::new();
+        for id in due {
+            let affected = self
+                .nodes
+                .get_mut(&id)
+                .and_then(|s| s.pending.take())
+                .unwrap_or_default();
+            out.extend(self.publish(id, affected, now));
+        }
+        out
+    }
+
+    /// Publishes `affected` for `start_id` unless it duplicates the previous
+    /// publication. Either way the node's pending state is cleared.
+    fn publish(
+        &mut self,
+        start_id: String,
+        affected: Vec<String>,
+        now: f64,
+    ) -> Option<Publication> {
+        let digest = self.dedup.then(|| affected_digest(&affected));
+        let state = self.nodes.entry(start_id.clone()).or_default();
+        state.pending = None;
+        if digest.is_some() && digest == state.last_digest {
+            state.suppressed = 0;
+            self.deduplicated += 1;
+            return None;
+        }
+        state.last_published_at = Some(now);
+        state.last_digest = digest;
+        let suppressed = std::mem::take(&mut state.suppressed);
+        self.published += 1;
+        Some(Publication {
+            start_id,
+            affected,
+            suppressed,
+        })
+    }
+}
+
+/// Order-independent SHA-256 of an affected set.
+fn affected_digest(affected: &[String]) -> [u8; 32] {
+    let mut ids: Vec<&String> = affected.iter().collect();
+    ids.sort();
+    ids.dedup();
+    let mut hasher = Sha256::new();
+    for id in ids {
+        hasher.update((id.len() as u64).to_le_bytes());
+        hasher.update(id.as_bytes());
+    }
+    hasher.finalize().into()
+}
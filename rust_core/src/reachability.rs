@@ -0,0 +1,250 @@
+//! Reachability index for repeated `propagate_invalidation`-style queries.
+//!
+//! The graph is condensed into its strongly connected components, which form a DAG.
+//! A depth-first search numbers the components in post-order, so every DFS subtree
+//! covers one contiguous range of numbers. Each component then stores the numbers
+//! of everything it reaches as sorted, disjoint intervals: its own subtree range,
+//! merged with the intervals of its successors (tree-cover labeling), with the
+//! components of each height over the condensation merged in parallel. A membership
+//! test is a binary search over one such list, and a count sums precomputed
+//! component sizes over it.
+//!
+//! Trees and chains need one interval per component. Graphs with many cross edges
+//! need more. The footprint is reported by `heap_bytes` and `interval_count`.
+
+use crate::algorithms;
+use crate::config;
+use crate::store::NodeStore;
+use rayon::prelude::*;
+
+/// Components per height below which merging runs on the calling thread.
+const PARALLEL_LEVEL: usize = 64;
+
+pub struct ReachabilityIndex {
+    /// Post-order number of each handle's component.
+    post: Vec<u32>,
+    /// Per post-order number: whether that component reaches itself, i.e. it has
+    /// more than one member or a self-loop.
+    cyclic: Vec<bool>,
+    /// Per post-order number: members of all components numbered below it, so the
+    /// size of a run of components is a difference.
+    members_before: Vec<u32>,
+    /// Numbers reachable from component `p` (itself included) are
+    /// `intervals[offsets[p]..offsets[p + 1]]`, as inclusive ranges.
+    offsets: Vec<usize>,
+    intervals: Vec<(u32, u32)>,
+}
+
+impl ReachabilityIndex {
+    /// Indexes every handle of `nodes`, leaving out edges into handles for which
+    /// `skip` is true.
+    pub fn build(nodes: &NodeStore, skip: impl Fn(u32) -> bool) -> Self {
+        let n = nodes.ids().len();
+        let mut adj: Vec<Vec<usize>> = vec![Vec::new(); n];
+        for (h, node) in nodes.handles() {
+            adj[h as usize] = node
+                .dependents
+                .iter()
+                .filter(|&&d| !skip(d))
+                .map(|&d| d as usize)
+                .collect();
+        }
+        let components = algorithms::tarjan_scc(&adj);
+        let count = components.len();
+        let mut component = vec![0u32; n];
+        for (c, members) in components.iter().enumerate() {
+            for &v in members {
+                component[v] = c as u32;
+            }
+        }
+        let mut cyclic: Vec<bool> = components.iter().map(|m| m.len() > 1).collect();
+        let mut successors: Vec<Vec<u32>> = vec![Vec::new(); count];
+        for (u, targets) in adj.iter().enumerate() {
+            let cu = component[u] as usize;
+            for &v in targets {
+                let cv = component[v];
+                if cv as usize != cu {
+                    successors[cu].push(cv);
+                } else if u == v {
+                    cyclic[cu] = true;
+                }
+            }
+        }
+        drop(adj);
+        for s in &mut successors {
+            s.sort_unstable();
+            s.dedup();
+        }
+        // Longest path from each component to a sink. Tarjan numbers components in
+        // reverse topological order, so successors come first.
+        let mut height = vec![0u32; count];
+        for (c, succ) in successors.iter().enumerate() {
+            height[c] = succ
+                .iter()
+                .map(|&s| height[s as usize] + 1)
+                .max()
+                .unwrap_or(0);
+        }
+        // Searches start from the tallest components and visit the tallest successor
+        // first, so they follow long paths rather than jump ahead and each search
+        // numbers what it reaches in one block. That keeps reachable sets
+        // contiguous: on a DAG where every node links to a few of the next ones,
+        // each component keeps about one interval, where Tarjan's order leaves a
+        // number growing with the graph size. A component's predecessors are all
+        // taller, so every root is a source of what is left unvisited. `low` is the
+        // first number of a component's subtree and doubles as the visited mark.
+        for s in &mut successors {
+            s.sort_by_key(|&s| std::cmp::Reverse(height[s as usize]));
+        }
+        let mut roots: Vec<u32> = (0..count as u32).collect();
+        roots.sort_by_key(|&c| std::cmp::Reverse(height[c as usize]));
+        let mut post = vec![u32::MAX; count];
+        let mut low = vec![u32::MAX; count];
+        let mut next = 0u32;
+        let mut stack: Vec<(u32, usize)> = Vec::new();
+        for root in roots {
+            if low[root as usize] != u32::MAX {
+                continue;
+            }
+            low[root as usize] = next;
+            stack.push((root, 0));
+            while let Some(&mut (c, ref mut edge)) = stack.last_mut() {
+                if let Some(&s) = successors[c as usize].get(*edge) {
+                    *edge += 1;
+                    if low[s as usize] == u32::MAX {
+                        low[s as usize] = next;
+                        stack.push((s, 0));
+                    }
+                    continue;
+                }
+                stack.pop();
+                post[c as usize] = next;
+                next += 1;
+            }
+        }
+
+        let mut by_post = vec![0u32; count];
+        for (c, &p) in post.iter().enumerate() {
+            by_post[p as usize] = c as u32;
+        }
+        // A component's intervals merge those of its successors, so components are
+        // merged by height (sinks first), each height in parallel.
+        let mut levels: Vec<Vec<u32>> = Vec::new();
+        for (p, &c) in by_post.iter().enumerate() {
+            let h = height[c as usize] as usize;
+            if levels.len() <= h {
+                levels.resize_with(h + 1, Vec::new);
+            }
+            levels[h].push(p as u32);
+        }
+        drop(height);
+        let mut runs: Vec<Vec<(u32, u32)>> = vec![Vec::new(); count];
+        let merge = |p: u32, runs: &[Vec<(u32, u32)>]| {
+            let c = by_post[p as usize] as usize;
+            let mut scratch = vec![(low[c], p)];
+            for &s in &successors[c] {
+                scratch.extend_from_slice(&runs[post[s as usize] as usize]);
+            }
+            scratch.sort_unstable();
+            let mut merged: Vec<(u32, u32)> = Vec::with_capacity(scratch.len());
+            for (lo, hi) in scratch {
+                match merged.last_mut() {
+                    Some(last) if lo <= last.1 + 1 => last.1 = last.1.max(hi),
+                    _ => merged.push((lo, hi)),
+                }
+            }
+            merged.shrink_to_fit();
+            merged
+        };
+        for level in &levels {
+            // Long chains make many tiny levels, not worth a parallel dispatch each.
+            let done: Vec<Vec<(u32, u32)>> = if level.len() < PARALLEL_LEVEL {
+                level.iter().map(|&p| merge(p, &runs)).collect()
+            } else {
+                config::install(|| level.par_iter().map(|&p| merge(p, &runs)).collect())
+            };
+            for (&p, merged) in level.iter().zip(done) {
+                runs[p as usize] = merged;
+            }
+        }
+        let mut offsets = Vec::with_capacity(count + 1);
+        offsets.push(0);
+        let mut intervals: Vec<(u32, u32)> = Vec::with_capacity(runs.iter().map(Vec::len).sum());
+        for merged in runs {
+            intervals.extend_from_slice(&merged);
+            offsets.push(intervals.len());
+        }
+
+        let mut members_before = Vec::with_capacity(count + 1);
+        members_before.push(0);
+        for &c in &by_post {
+            let total = members_before.last().copied().unwrap_or(0);
+            members_before.push(total + components[c as usize].len() as u32);
+        }
+        ReachabilityIndex {
+            post: component.iter().map(|&c| post[c as usize]).collect(),
+            cyclic: by_post.iter().map(|&c| cyclic[c as usize]).collect(),
+            members_before,
+            offsets,
+            intervals,
+        }
+    }
+
+    fn runs(&self, p: u32) -> &[(u32, u32)] {
+        let p = p as usize;
+        &self.intervals[self.offsets[p]..self.offsets[p + 1]]
+    }
+
+    /// True if a path of at least one edge leads from `from` to `to`. Handles issued
+    /// after the build are unreachable.
+    pub fn reaches(&self, from: u32, to: u32) -> bool {
+        let (Some(&pf), Some(&pt)) = (self.post.get(from as usize), self.post.get(to as usize))
+        else {
+            return false;
+        };
+        if pf == pt {
+            return self.cyclic[pf as usize];
+        }
+        let runs = self.runs(pf);
+        let i = runs.partition_point(|&(_, hi)| hi < pt);
+        runs.get(i).is_some_and(|&(lo, _)| lo <= pt)
+    }
+
+    /// Number of handles `reaches(from, _)` is true for.
+    pub fn count(&self, from: u32) -> usize {
+        let Some(&p) = self.post.get(from as usize) else {
+            return 0;
+        };
+        let members: usize = self
+            .runs(p)
+            .iter()
+            .map(|&(lo, hi)| {
+                (self.members_before[hi as usize + 1] - self.members_before[lo as usize]) as usize
+            })
+            .sum();
+        // The runs include `from`'s own component, which it reaches only on a cycle.
+        if self.cyclic[p as usize] {
+            members
+        } else {
+            members - 1
+        }
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.cyclic.len()
+    }
+
+    pub fn interval_count(&self) -> usize {
+        self.intervals.len()
+    }
+
+    /// Heap footprint in bytes.
+    pub fn heap_bytes(&self) -> usize {
+        use std::mem::size_of;
+        self.post.capacity() * size_of::<u32>()
+            + self.cyclic.capacity() * size_of::<bool>()
+            + self.members_before.capacity() * size_of::<u32>()
+            + self.offsets.capacity() * size_of::<usize>()
+            + self.intervals.capacity() * size_of::<(u32, u32)>()
+    }
+}
@@ -0,0 +1,128 @@
+//! Optional graph schema: node kinds and the edge types allowed between them.
+//!
+//! A node's kind is its `kind` attribute if set, otherwise the first declared kind
+//! whose id pattern matches (`*` matches any run of characters, `?` exactly one).
+//! Once any `allow_edge` rule exists, an edge between two kinded nodes must match a
+//! rule; edges touching a node without a kind are not constrained.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Node attribute that overrides pattern-based kinds.
+pub const KIND_ATTR: &str = "kind";
+
+#[derive(Clone, Serialize, Deserialize)]
+struct KindRule {
+    kind: String,
+    pattern: String,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct EdgeRule {
+    from_kind: String,
+    to_kind: String,
+    #[serde(default)]
+    label: Option<String>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Schema {
+    #[serde(default)]
+    kinds: Vec<KindRule>,
+    #[serde(default)]
+    edges: Vec<EdgeRule>,
+}
+
+impl Schema {
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty() && self.edges.is_empty()
+    }
+
+    /// Declares (or re-points) `kind` as matching ids against `pattern`.
+    pub fn define_kind(&mut self, kind: String, pattern: String) {
+        match self.kinds.iter_mut().find(|k| k.kind == kind) {
+            Some(rule) => rule.pattern = pattern,
+            None => self.kinds.push(KindRule { kind, pattern }),
+        }
+    }
+
+    pub fn allow(&mut self, from_kind: String, to_kind: String, label: Option<String>) {
+        let rule = EdgeRule {
+            from_kind,
+            to_kind,
+            label,
+        };
+        if !self.edges.contains(&rule) {
+            self.edges.push(rule);
+        }
+    }
+
+    /// Kind of `id`, given its attributes if it is a known node.
+    pub fn kind_of<'a>(
+        &'a self,
+        id: &str,
+        attributes: Option<&'a HashMap<String, String>>,
+    ) -> Option<&'a str> {
+        if let Some(kind) = attributes.and_then(|a| a.get(KIND_ATTR)) {
+            return Some(kind);
+        }
+        self.kinds
+            .iter()
+            .find(|k| glob_match(&k.pattern, id))
+            .map(|k| k.kind.as_str())
+    }
+
+    /// Checks an edge carrying `label` between nodes of the given kinds; the error
+    /// is a human-readable reason naming the missing rule.
+    pub fn check_edge(
+        &self,
+        from_kind: Option<&str>,
+        to_kind: Option<&str>,
+        label: Option<&str>,
+    ) -> Result<(), String> {
+        let (Some(from_kind), Some(to_kind)) = (from_kind, to_kind) else {
+            return Ok(());
+        };
+        if self.edges.is_empty() {
+            return Ok(());
+        }
+        let allowed = self.edges.iter().any(|r| {
+            r.from_kind == from_kind
+                && r.to_kind == to_kind
+                && (r.label.is_none() || r.label.as_deref() == label)
+        });
+        if allowed {
+            Ok(())
+        } else {
+            Err(format!(
+                "no allow_edge rule for {} -> {}{}",
+                from_kind,
+                to_kind,
+                label.map(|l| format!(" [{}]", l)).unwrap_or_default()
+            ))
+        }
+    }
+}
+
+/// Glob match supporting `*` and `?`, with backtracking on the last `*`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let (p, t): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,262 @@
+//! Neighborhood similarity between nodes, used by `GraphWalker::most_similar` and
+//! `GraphWalker::similarity_matrix`. Neighborhoods follow a `Direction` (by default
+//! dependents plus sources); tombstoned ids are ignored.
+
+use crate::config;
+use crate::store::NodeStore;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// SimRank decay constant.
+const SIMRANK_C: f64 = 0.8;
+
+#[derive(Clone, Copy)]
+pub enum Method {
+    Jaccard,
+    Overlap,
+    Cosine,
+    AdamicAdar,
+    SimRank1Hop,
+    CoInvalidation,
+}
+
+impl Method {
+    pub fn parse(name: &str) -> PyResult<Self> {
+        match name {
+            "jaccard" => Ok(Method::Jaccard),
+            "overlap" => Ok(Method::Overlap),
+            "cosine" => Ok(Method::Cosine),
+            "adamic_adar" => Ok(Method::AdamicAdar),
+            "simrank_1hop" => Ok(Method::SimRank1Hop),
+            "co_invalidation" => Ok(Method::CoInvalidation),
+            _ => Err(PyValueError::new_err(format!(
+                "unknown similarity method '{}' (supported: jaccard, overlap, cosine, \
+                 adamic_adar, simrank_1hop, co_invalidation)",
+                name
+            ))),
+        }
+    }
+}
+
+/// Which edges make up a neighborhood; SimRank and co-invalidation ignore it.
+#[derive(Clone, Copy)]
+pub enum Direction {
+    /// Dependents.
+    Out,
+    /// Sources.
+    In,
+    Both,
+}
+
+impl Direction {
+    pub fn parse(name: &str) -> PyResult<Self> {
+        match name {
+            "out" => Ok(Direction::Out),
+            "in" => Ok(Direction::In),
+            "both" => Ok(Direction::Both),
+            _ => Err(PyValueError::new_err(format!(
+                "unknown direction '{}' (expected out, in or both)",
+                name
+            ))),
+        }
+    }
+
+    fn reversed(self) -> Self {
+        match self {
+            Direction::Out => Direction::In,
+            Direction::In => Direction::Out,
+            Direction::Both => Direction::Both,
+        }
+    }
+}
+
+pub struct Similarity<'a> {
+    pub nodes: &'a NodeStore,
+    /// Sources of each handle (edges into it).
+    pub reverse: &'a [Vec<u32>],
+    pub tombstones: &'a HashMap<String, f64>,
+    pub direction: Direction,
+}
+
+impl<'a> Similarity<'a> {
+    fn live(&self, id: &str) -> bool {
+        !self.tombstones.contains_key(id)
+    }
+
+    fn sources(&self, id: &str) -> HashSet<&'a str> {
+        let nodes = self.nodes;
+        self.nodes
+            .handle(id)
+            .and_then(|h| self.reverse.get(h as usize))
+            .into_iter()
+            .flatten()
+            .map(|&s| nodes.name(s).as_str())
+            .filter(|s| self.live(s))
+            .collect()
+    }
+
+    fn dependents(&self, id: &str) -> HashSet<&'a str> {
+        let nodes = self.nodes;
+        nodes
+            .get(id)
+            .into_iter()
+            .flat_map(|node| nodes.targets(node))
+            .map(String::as_str)
+            .filter(|d| self.live(d))
+            .collect()
+    }
+
+    /// Neighbors of `id` along `direction`, excluding `id` itself.
+    fn neighbors_along(&self, id: &str, direction: Direction) -> HashSet<&'a str> {
+        let mut out = match direction {
+            Direction::Out => self.dependents(id),
+            Direction::In => self.sources(id),
+            Direction::Both => {
+                let mut all = self.sources(id);
+                all.extend(self.dependents(id));
+                all
+            }
+        };
+        out.remove(id);
+        out
+    }
+
+    fn neighbors(&self, id: &str) -> HashSet<&'a str> {
+        self.neighbors_along(id, self.direction)
+    }
+
+    /// Everything reachable from `id` along dependents, excluding `id` itself.
+    fn descendants(&self, id: &str) -> HashSet<&'a str> {
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        if let Some(h) = self.nodes.handle(id).filter(|&h| self.nodes.contains(h)) {
+            queue.push_back(self.nodes.name(h).as_str());
+        }
+        while let Some(u) = queue.pop_front() {
+            let Some(node) = self.nodes.get(u) else {
+                continue;
+            };
+            for v in self.nodes.targets(node) {
+                if self.live(v) && seen.insert(v.as_str()) {
+                    queue.push_back(v.as_str());
+                }
+            }
+        }
+        seen.remove(id);
+        seen
+    }
+
+    /// Known, live nodes that can share a neighbor with `id`, sorted: its neighbors
+    /// along `direction` and their neighbors against it. With `Both` these are the
+    /// nodes within two undirected hops.
+    fn candidates(&self, id: &str, direction: Direction) -> Vec<&'a str> {
+        let first = self.neighbors_along(id, direction);
+        let mut all: HashSet<&str> = first.clone();
+        for n in &first {
+            all.extend(self.neighbors_along(n, direction.reversed()));
+        }
+        all.remove(id);
+        let mut out: Vec<&str> = all
+            .into_iter()
+            .filter(|c| self.nodes.contains_key(c))
+            .collect();
+        out.sort_unstable();
+        out
+    }
+
+    /// The set `method` compares for `id`.
+    fn profile(&self, id: &str, method: Method) -> HashSet<&'a str> {
+        match method {
+            Method::SimRank1Hop => self.sources(id),
+            Method::CoInvalidation => self.descendants(id),
+            _ => self.neighbors(id),
+        }
+    }
+
+    /// Score of two nodes from their profiles.
+    fn score(&self, method: Method, a: &HashSet<&str>, b: &HashSet<&str>) -> f64 {
+        let common = || a.intersection(b).count() as f64;
+        match method {
+            Method::Jaccard | Method::CoInvalidation => jaccard(a, b),
+            Method::Overlap => match a.len().min(b.len()) {
+                0 => 0.0,
+                smaller => common() / smaller as f64,
+            },
+            Method::Cosine => match a.len() * b.len() {
+                0 => 0.0,
+                product => common() / (product as f64).sqrt(),
+            },
+            Method::AdamicAdar => {
+                // A shared neighbor's degree counts the edges that could have made
+                // it shared. Summed in id order so scores are bit-identical across runs.
+                let mut common: Vec<&str> = a.intersection(b).copied().collect();
+                common.sort_unstable();
+                common
+                    .into_iter()
+                    .map(|z| self.neighbors_along(z, self.direction.reversed()).len() as f64)
+                    .filter(|&d| d > 1.0)
+                    .map(|d| 1.0 / d.ln())
+                    .sum()
+            }
+            Method::SimRank1Hop => {
+                if a.is_empty() || b.is_empty() {
+                    return 0.0;
+                }
+                SIMRANK_C * common() / (a.len() * b.len()) as f64
+            }
+        }
+    }
+
+    /// Top `k` candidates by score (descending, ties by id); zero scores are omitted.
+    pub fn most_similar(&self, id: &str, k: usize, method: Method) -> Vec<(String, f64)> {
+        let direction = match method {
+            Method::SimRank1Hop | Method::CoInvalidation => Direction::Both,
+            _ => self.direction,
+        };
+        let candidates = self.candidates(id, direction);
+        let pq = self.profile(id, method);
+        let mut scored = self.score_all(&candidates, |c| {
+            self.score(method, &pq, &self.profile(c, method))
+        });
+        scored.retain(|(_, s)| *s > 0.0);
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scored.truncate(k);
+        scored
+    }
+
+    /// Scores of every pair of `ids`, row by row in the given order.
+    pub fn matrix(&self, ids: &[String], method: Method) -> Vec<Vec<f64>> {
+        config::install(|| {
+            let profiles: Vec<HashSet<&str>> =
+                ids.par_iter().map(|id| self.profile(id, method)).collect();
+            profiles
+                .par_iter()
+                .map(|a| profiles.iter().map(|b| self.score(method, a, b)).collect())
+                .collect()
+        })
+    }
+
+    fn score_all(
+        &self,
+        candidates: &[&str],
+        score: impl Fn(&str) -> f64 + Sync,
+    ) -> Vec<(String, f64)> {
+        config::install(|| {
+            candidates
+                .par_iter()
+                .map(|c| (c.to_string(), score(c)))
+                .collect()
+        })
+    }
+}
+
+fn jaccard(a: &HashSet<&str>, b: &HashSet<&str>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        0.0
+    } else {
+        a.intersection(b).count() as f64 / union as f64
+    }
+}
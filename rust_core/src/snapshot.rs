@@ -0,0 +1,431 @@
+use crate::errors::SerializationError;
+use crate::mode::GraphMode;
+use crate::provenance::{ProvenanceRecord, ProvenanceStore};
+use crate::schema::Schema;
+use crate::store::NodeStore;
+use crate::{CausalNode, GraphWalker};
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+/// Current on-disk snapshot format version. Version 2 changed only the checksum,
+/// which now covers every persisted field; version 1 snapshots still load.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct EdgeRecord {
+    to: String,
+    weight: f64,
+    observed_at: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    /// Written only when requested and the walker tracks provenance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    provenance: Option<ProvenanceRecord>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NodeRecord {
+    id: String,
+    edges: Vec<EdgeRecord>,
+    #[serde(default)]
+    attributes: BTreeMap<String, String>,
+    /// Position in `stable_order()`, written only when requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    order: Option<usize>,
+    /// First-seen id before normalization, written only when requested and different.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    original: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    checksum: String,
+    #[serde(default, skip_serializing_if = "GraphMode::is_directed")]
+    mode: GraphMode,
+    #[serde(default)]
+    enforce_dag: bool,
+    #[serde(default)]
+    enforce_schema: bool,
+    #[serde(default, skip_serializing_if = "Schema::is_empty")]
+    schema: Schema,
+    nodes: Vec<NodeRecord>,
+    #[serde(default)]
+    tombstones: BTreeMap<String, f64>,
+    /// Ids marked by `mark_invalid` and not cleared.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    invalid: Vec<String>,
+}
+
+/// What `content_digest` covers besides ids, edge targets, weights, labels,
+/// attributes, tombstones and invalid marks.
+pub struct Covered<'a> {
+    /// Edge observation timestamps and tombstone times.
+    pub timestamps: bool,
+    /// Edge provenance records, from this store.
+    pub provenance: Option<&'a ProvenanceStore>,
+    /// Pre-normalization ids, from this map.
+    pub original_ids: Option<&'a HashMap<String, String>>,
+}
+
+impl Covered<'_> {
+    /// Everything the walker holds.
+    pub fn all(walker: &GraphWalker) -> Covered<'_> {
+        Covered {
+            timestamps: true,
+            provenance: walker.provenance.as_ref(),
+            original_ids: Some(&walker.original_ids),
+        }
+    }
+
+    /// The graph alone, leaving out when and from where it was built.
+    pub fn structure() -> Covered<'static> {
+        Covered {
+            timestamps: false,
+            provenance: None,
+            original_ids: None,
+        }
+    }
+}
+
+/// SHA-256 over a canonical encoding of the graph: nodes sorted by id, each with
+/// its attributes sorted by key and its edges sorted by every field, then the
+/// tombstones and invalid marks sorted by id. Every field the snapshot persists is
+/// hashed (as `covered` selects), and nothing depends on the order in which the
+/// graph was built.
+pub fn content_digest(walker: &GraphWalker, covered: &Covered) -> String {
+    let nodes = &walker.nodes;
+    let mut hasher = Sha256::new();
+
+    for (id, node) in nodes.sorted_nodes() {
+        write_str(&mut hasher, id);
+        let original = covered
+            .original_ids
+            .and_then(|o| o.get(id))
+            .filter(|original| *original != id);
+        write_opt_str(&mut hasher, original.map(String::as_str));
+
+        let mut attrs: Vec<(&String, &String)> = node.attributes.iter().collect();
+        attrs.sort();
+        hasher.update((attrs.len() as u64).to_le_bytes());
+        for (k, v) in attrs {
+            write_str(&mut hasher, k);
+            write_str(&mut hasher, v);
+        }
+
+        type Edge<'a> = (
+            &'a String,
+            u64,
+            u64,
+            Option<&'a String>,
+            Option<ProvenanceRecord>,
+        );
+        let mut edges: Vec<Edge> = nodes
+            .targets(node)
+            .enumerate()
+            .map(|(i, to)| {
+                let observed = if covered.timestamps {
+                    node.observed_at[i].to_bits()
+                } else {
+                    0
+                };
+                let provenance = covered.provenance.and_then(|s| {
+                    let mut record = s.to_record(&s.get(nodes.ids(), id, to)?);
+                    if !covered.timestamps {
+                        record.timestamp = 0.0;
+                    }
+                    Some(record)
+                });
+                let label = node.labels[i].as_ref();
+                (to, node.weights[i].to_bits(), observed, label, provenance)
+            })
+            .collect();
+        edges.sort_by(|a, b| {
+            (a.0, a.1, a.2, a.3)
+                .cmp(&(b.0, b.1, b.2, b.3))
+                .then_with(|| provenance_key(&a.4).cmp(&provenance_key(&b.4)))
+        });
+        hasher.update((edges.len() as u64).to_le_bytes());
+        for (to, weight, observed, label, provenance) in edges {
+            write_str(&mut hasher, to);
+            hasher.update(weight.to_le_bytes());
+            hasher.update(observed.to_le_bytes());
+            write_opt_str(&mut hasher, label.map(String::as_str));
+            match provenance {
+                None => hasher.update([0]),
+                Some(p) => {
+                    hasher.update([1]);
+                    write_str(&mut hasher, &p.source);
+                    hasher.update(p.partition.map_or(-1, i64::from).to_le_bytes());
+                    hasher.update(p.offset.map_or(u64::MAX, |o| o).to_le_bytes());
+                    hasher.update(p.timestamp.to_bits().to_le_bytes());
+                }
+            }
+        }
+    }
+
+    let mut tombstones: Vec<(&String, &f64)> = walker.tombstones.iter().collect();
+    tombstones.sort_by(|a, b| a.0.cmp(b.0));
+    hasher.update((tombstones.len() as u64).to_le_bytes());
+    for (id, at) in tombstones {
+        write_str(&mut hasher, id);
+        let at = if covered.timestamps { at.to_bits() } else { 0 };
+        hasher.update(at.to_le_bytes());
+    }
+
+    let mut invalid: Vec<String> = nodes.names(walker.dirty.handles());
+    invalid.sort_unstable();
+    hasher.update((invalid.len() as u64).to_le_bytes());
+    for id in &invalid {
+        write_str(&mut hasher, id);
+    }
+    format!("sha256:{}", to_hex(&hasher.finalize()))
+}
+
+/// Orders provenance records for `content_digest`; f64 timestamps by their bits.
+fn provenance_key(p: &Option<ProvenanceRecord>) -> Option<(&str, Option<u32>, Option<u64>, u64)> {
+    p.as_ref().map(|p| {
+        (
+            p.source.as_str(),
+            p.partition,
+            p.offset,
+            p.timestamp.to_bits(),
+        )
+    })
+}
+
+/// The digest version 1 snapshots carry: ids, edge targets and weights, labeled
+/// edges and attributes only.
+fn digest_v1(nodes: &NodeStore) -> String {
+    let mut hasher = Sha256::new();
+
+    for (id, node) in nodes.sorted_nodes() {
+        write_str(&mut hasher, id);
+
+        let mut attrs: Vec<(&String, &String)> = node.attributes.iter().collect();
+        attrs.sort();
+        hasher.update((attrs.len() as u64).to_le_bytes());
+        for (k, v) in attrs {
+            write_str(&mut hasher, k);
+            write_str(&mut hasher, v);
+        }
+
+        let mut edges: Vec<(&String, u64)> = nodes
+            .targets(node)
+            .zip(&node.weights)
+            .map(|(to, w)| (to, w.to_bits()))
+            .collect();
+        edges.sort();
+        hasher.update((edges.len() as u64).to_le_bytes());
+        for (to, w) in edges {
+            write_str(&mut hasher, to);
+            hasher.update(w.to_le_bytes());
+        }
+
+        let mut labels: Vec<(&String, &String)> = nodes
+            .targets(node)
+            .zip(&node.labels)
+            .filter_map(|(to, label)| Some((to, label.as_ref()?)))
+            .collect();
+        if !labels.is_empty() {
+            labels.sort();
+            hasher.update((labels.len() as u64).to_le_bytes());
+            for (to, label) in labels {
+                write_str(&mut hasher, to);
+                write_str(&mut hasher, label);
+            }
+        }
+    }
+    format!("sha256:{}", to_hex(&hasher.finalize()))
+}
+
+fn write_str(hasher: &mut Sha256, s: &str) {
+    hasher.update((s.len() as u64).to_le_bytes());
+    hasher.update(s.as_bytes());
+}
+
+fn write_opt_str(hasher: &mut Sha256, s: Option<&str>) {
+    match s {
+        None => hasher.update([0]),
+        Some(s) => {
+            hasher.update([1]);
+            write_str(hasher, s);
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Serializes the walker to the versioned JSON snapshot format, nodes sorted by id.
+pub fn encode(
+    walker: &GraphWalker,
+    include_order: bool,
+    include_provenance: bool,
+    include_original_ids: bool,
+) -> PyResult<String> {
+    let snapshot = build(
+        walker,
+        include_order,
+        include_provenance,
+        include_original_ids,
+    );
+    serde_json::to_string(&snapshot).map_err(|e| SerializationError::new_err(e.to_string()))
+}
+
+/// `encode` streamed to `writer`, without building the JSON string first.
+pub fn write(
+    walker: &GraphWalker,
+    writer: impl Write,
+    include_order: bool,
+    include_provenance: bool,
+    include_original_ids: bool,
+) -> PyResult<()> {
+    let snapshot = build(
+        walker,
+        include_order,
+        include_provenance,
+        include_original_ids,
+    );
+    let mut writer = writer;
+    serde_json::to_writer(&mut writer, &snapshot)
+        .map_err(|e| SerializationError::new_err(e.to_string()))?;
+    writer
+        .flush()
+        .map_err(|e| PyIOError::new_err(e.to_string()))
+}
+
+fn build(
+    walker: &GraphWalker,
+    include_order: bool,
+    include_provenance: bool,
+    include_original_ids: bool,
+) -> Snapshot {
+    let store = walker.provenance.as_ref().filter(|_| include_provenance);
+    let positions: Option<HashMap<String, usize>> = include_order.then(|| {
+        walker
+            .stable_order_cached()
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.clone(), i))
+            .collect()
+    });
+    let nodes = walker
+        .nodes
+        .sorted_nodes()
+        .into_iter()
+        .map(|(id, node)| NodeRecord {
+            id: id.clone(),
+            edges: walker
+                .nodes
+                .targets(node)
+                .enumerate()
+                .map(|(i, to)| EdgeRecord {
+                    to: to.clone(),
+                    weight: node.weights[i],
+                    observed_at: node.observed_at[i],
+                    label: node.labels[i].clone(),
+                    provenance: store
+                        .and_then(|s| s.get(walker.nodes.ids(), id, to).map(|p| s.to_record(&p))),
+                })
+                .collect(),
+            attributes: node.attributes.clone().into_iter().collect(),
+            order: positions.as_ref().and_then(|p| p.get(id).copied()),
+            original: walker
+                .original_ids
+                .get(id)
+                .filter(|original| include_original_ids && *original != id)
+                .cloned(),
+        })
+        .collect();
+
+    let mut invalid: Vec<String> = walker.nodes.names(walker.dirty.handles());
+    invalid.sort_unstable();
+    Snapshot {
+        version: SNAPSHOT_VERSION,
+        checksum: content_digest(
+            walker,
+            &Covered {
+                timestamps: true,
+                provenance: store,
+                original_ids: include_original_ids.then_some(&walker.original_ids),
+            },
+        ),
+        mode: walker.mode,
+        enforce_dag: walker.enforce_dag,
+        enforce_schema: walker.enforce_schema,
+        schema: walker.schema.clone(),
+        nodes,
+        tombstones: walker.tombstones.clone().into_iter().collect(),
+        invalid,
+    }
+}
+
+/// Parses a snapshot produced by `encode`, verifying its version and checksum.
+pub fn decode(data: &str) -> PyResult<GraphWalker> {
+    let snapshot: Snapshot = serde_json::from_str(data)
+        .map_err(|e| SerializationError::new_err(format!("malformed snapshot: {}", e)))?;
+    if !(1..=SNAPSHOT_VERSION).contains(&snapshot.version) {
+        return Err(SerializationError::new_err(format!(
+            "unsupported snapshot version {} (expected {})",
+            snapshot.version, SNAPSHOT_VERSION
+        )));
+    }
+
+    let mut walker = GraphWalker::blank();
+    walker.mode = snapshot.mode;
+    walker.enforce_dag = snapshot.enforce_dag;
+    walker.enforce_schema = snapshot.enforce_schema;
+    walker.schema = snapshot.schema;
+    walker.nodes.reserve(snapshot.nodes.len());
+    for record in snapshot.nodes {
+        let mut node = CausalNode::new();
+        for edge in record.edges {
+            if let Some(p) = &edge.provenance {
+                walker
+                    .provenance
+                    .get_or_insert_with(ProvenanceStore::default)
+                    .record(
+                        walker.nodes.ids_mut(),
+                        &record.id,
+                        &edge.to,
+                        &p.source,
+                        p.partition,
+                        p.offset,
+                        p.timestamp,
+                    );
+            }
+            let to = walker.nodes.intern(&edge.to);
+            node.push_labeled_edge(to, edge.weight, edge.observed_at, edge.label);
+        }
+        node.attributes = record.attributes.into_iter().collect();
+        if let Some(original) = record.original {
+            walker.original_ids.insert(record.id.clone(), original);
+        }
+        walker.nodes.insert(&record.id, node);
+    }
+
+    walker.tombstones = snapshot.tombstones.into_iter().collect();
+    // What the invalid ids reach is not saved, so each is walked past once again.
+    for id in &snapshot.invalid {
+        let h = walker.nodes.intern(id);
+        walker.dirty.insert_open(h);
+    }
+
+    let actual = match snapshot.version {
+        1 => digest_v1(&walker.nodes),
+        _ => content_digest(&walker, &Covered::all(&walker)),
+    };
+    if actual != snapshot.checksum {
+        return Err(SerializationError::new_err(format!(
+            "snapshot checksum mismatch: expected {}, actual {}",
+            snapshot.checksum, actual
+        )));
+    }
+    Ok(walker)
+}
@@ -0,0 +1,490 @@
+//! Where consumers get their messages: Kafka, or a `MemoryBroker` held in the
+//! process, which lets a pipeline run end to end without a broker.
+//!
+//! A `MemoryBroker` keeps every message sent to it. Each consumer started on it
+//! reads its topic from the first message, as a new consumer group would with
+//! `auto.offset.reset=earliest`, and then waits for more. Offsets committed for a
+//! group (see `Commit`) are kept per topic, and a consumer of that group resumes
+//! after them, as it would after a restart against Kafka.
+
+use crate::kafka::{Lag, PartitionLag, StatsContext};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList, PyString};
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::message::{BorrowedMessage, Headers, Message};
+use rdkafka::{Offset, TopicPartitionList};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// One consumed message, owned so it outlives the source's buffers.
+#[derive(Clone)]
+pub struct Delivery {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    pub key: Option<Vec<u8>>,
+    pub payload: Option<Vec<u8>>,
+    /// Milliseconds since the epoch, if the message carries one.
+    pub timestamp: Option<i64>,
+    /// In message order; a name may repeat.
+    pub headers: Vec<(String, Option<Vec<u8>>)>,
+}
+
+impl Delivery {
+    pub fn from_kafka(m: &BorrowedMessage) -> Self {
+        Delivery {
+            topic: m.topic().to_string(),
+            partition: m.partition(),
+            offset: m.offset(),
+            key: m.key().map(<[u8]>::to_vec),
+            payload: m.payload().map(<[u8]>::to_vec),
+            timestamp: m.timestamp().to_millis(),
+            headers: m
+                .headers()
+                .map(|headers| {
+                    headers
+                        .iter()
+                        .map(|h| (h.key.to_string(), h.value.map(<[u8]>::to_vec)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The message as a dict with `topic`, `partition`, `offset`, `timestamp`,
+    /// `key` and `payload` (bytes or None) and `headers` (a list of `(name, value)`).
+    pub fn to_py<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let bytes = |b: &Option<Vec<u8>>| b.as_deref().map(|b| PyBytes::new(py, b));
+        let dict = PyDict::new(py);
+        dict.set_item("topic", &self.topic)?;
+        dict.set_item("partition", self.partition)?;
+        dict.set_item("offset", self.offset)?;
+        dict.set_item("timestamp", self.timestamp)?;
+        dict.set_item("key", bytes(&self.key))?;
+        dict.set_item("payload", bytes(&self.payload))?;
+        let headers: Vec<_> = self
+            .headers
+            .iter()
+            .map(|(name, value)| (name, bytes(value)))
+            .collect();
+        dict.set_item("headers", PyList::new(py, headers))?;
+        Ok(dict)
+    }
+}
+
+pub trait MessageSource {
+    /// Waits for the next message. An error is reported and the source polled again.
+    fn recv(&mut self) -> impl Future<Output = Result<Delivery, String>>;
+
+    /// Called once when the consumer stops.
+    fn close(&mut self) {}
+}
+
+/// Commits consumed offsets for a consumer group.
+pub trait Commit: Send + Sync {
+    /// Commits each `(topic, partition, offset)`, `offset` being the next one to
+    /// read. With `wait`, returns once the commit is acknowledged.
+    fn commit_offsets(&self, offsets: &[(String, i32, i64)], wait: bool) -> Result<(), String>;
+}
+
+/// Where a consumer group stands on each partition of its topics.
+pub trait Watermarks: Send + Sync {
+    fn lag(&self, topics: &[String]) -> Lag;
+}
+
+impl Commit for StreamConsumer<StatsContext> {
+    fn commit_offsets(&self, offsets: &[(String, i32, i64)], wait: bool) -> Result<(), String> {
+        let mut list = TopicPartitionList::new();
+        for (topic, partition, offset) in offsets {
+            list.add_partition_offset(topic, *partition, Offset::Offset(*offset))
+                .map_err(|e| e.to_string())?;
+        }
+        let mode = if wait {
+            CommitMode::Sync
+        } else {
+            CommitMode::Async
+        };
+        self.commit(&list, mode).map_err(|e| e.to_string())
+    }
+}
+
+impl Watermarks for StreamConsumer<StatsContext> {
+    /// As of librdkafka's last statistics report, which covers the subscribed
+    /// topics.
+    fn lag(&self, _topics: &[String]) -> Lag {
+        self.context().lag()
+    }
+}
+
+pub struct KafkaSource(pub Arc<StreamConsumer<StatsContext>>);
+
+impl MessageSource for KafkaSource {
+    async fn recv(&mut self) -> Result<Delivery, String> {
+        self.0
+            .recv()
+            .await
+            .map(|m| Delivery::from_kafka(&m))
+            .map_err(|e| e.to_string())
+    }
+
+    fn close(&mut self) {
+        self.0.unsubscribe();
+    }
+}
+
+#[derive(Default)]
+struct Topic {
+    /// Every message, in send order.
+    log: Vec<Delivery>,
+    /// Offset the next message on each partition gets.
+    next_offset: HashMap<i32, i64>,
+    /// Per group, the committed offset of each partition.
+    committed: HashMap<String, BTreeMap<i32, i64>>,
+}
+
+#[derive(Default)]
+struct BrokerState {
+    topics: Mutex<HashMap<String, Topic>>,
+    /// Deliveries from producers still to fail on purpose (see `fail_sends`).
+    failing: AtomicU64,
+    /// Woken on every send.
+    sent: Notify,
+}
+
+/// In-process stand-in for a Kafka cluster: pass it to `start_kafka_consumer` as
+/// `brokers`. Sending needs no running consumer, and messages are never dropped.
+#[pyclass(module = "rust_core")]
+#[derive(Clone, Default)]
+pub struct MemoryBroker {
+    state: Arc<BrokerState>,
+}
+
+#[pymethods]
+impl MemoryBroker {
+    #[new]
+    fn new() -> Self {
+        MemoryBroker::default()
+    }
+
+    /// Appends a message to `topic` and returns its offset within `partition`.
+    /// `payload` and `key` may be str or bytes; a None payload is a tombstone, as
+    /// in a compacted topic. `timestamp` is in milliseconds since the epoch.
+    /// `headers` is a dict or a list of `(name, value)` pairs, values being str,
+    /// bytes or None.
+    #[pyo3(signature = (topic, payload, key = None, partition = 0, timestamp = None, headers = None))]
+    fn send(
+        &self,
+        topic: &str,
+        payload: Option<&PyAny>,
+        key: Option<&PyAny>,
+        partition: i32,
+        timestamp: Option<i64>,
+        headers: Option<&PyAny>,
+    ) -> PyResult<i64> {
+        if partition < 0 {
+            return Err(PyValueError::new_err(format!(
+                "partition must be non-negative, got {}",
+                partition
+            )));
+        }
+        let payload = payload.map(bytes_of).transpose()?;
+        let key = key.map(bytes_of).transpose()?;
+        let headers = headers.map(headers_of).transpose()?.unwrap_or_default();
+        Ok(self.append(topic, partition, key, payload, timestamp, headers))
+    }
+
+    /// Makes the next `count` messages a `CausalProducer` sends here fail
+    /// delivery instead of arriving.
+    fn fail_sends(&self, count: u64) {
+        self.state.failing.store(count, Ordering::SeqCst);
+    }
+
+    /// The offsets `group_id` has committed on `topic`, as `{partition: offset}`,
+    /// `offset` being the next message a restarted consumer reads.
+    #[pyo3(signature = (topic, group_id = crate::kafka::DEFAULT_GROUP_ID))]
+    fn committed(&self, topic: &str, group_id: &str) -> BTreeMap<i32, i64> {
+        self.state
+            .topics
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(topic)
+            .and_then(|t| t.committed.get(group_id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Number of messages sent to `topic` so far.
+    fn message_count(&self, topic: &str) -> usize {
+        self.state
+            .topics
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(topic)
+            .map_or(0, |t| t.log.len())
+    }
+}
+
+pub fn bytes_of(value: &PyAny) -> PyResult<Vec<u8>> {
+    if let Ok(s) = value.downcast::<PyString>() {
+        return Ok(s.to_str()?.as_bytes().to_vec());
+    }
+    match value.downcast::<PyBytes>() {
+        Ok(b) => Ok(b.as_bytes().to_vec()),
+        Err(_) => Err(PyValueError::new_err(format!(
+            "expected str or bytes, got {}",
+            value.get_type().name()?
+        ))),
+    }
+}
+
+fn headers_of(value: &PyAny) -> PyResult<Vec<(String, Option<Vec<u8>>)>> {
+    let pairs: Vec<(String, Option<&PyAny>)> = match value.downcast::<PyDict>() {
+        Ok(dict) => dict
+            .iter()
+            .map(|(k, v)| Ok((k.extract()?, (!v.is_none()).then_some(v))))
+            .collect::<PyResult<_>>()?,
+        Err(_) => value.extract()?,
+    };
+    pairs
+        .into_iter()
+        .map(|(name, value)| Ok((name, value.map(bytes_of).transpose()?)))
+        .collect()
+}
+
+impl MemoryBroker {
+    fn append(
+        &self,
+        topic: &str,
+        partition: i32,
+        key: Option<Vec<u8>>,
+        payload: Option<Vec<u8>>,
+        timestamp: Option<i64>,
+        headers: Vec<(String, Option<Vec<u8>>)>,
+    ) -> i64 {
+        let mut topics = self.state.topics.lock().unwrap_or_else(|e| e.into_inner());
+        let topic_state = topics.entry(topic.to_string()).or_default();
+        let next = topic_state.next_offset.entry(partition).or_insert(0);
+        let offset = *next;
+        *next += 1;
+        topic_state.log.push(Delivery {
+            topic: topic.to_string(),
+            partition,
+            offset,
+            key,
+            payload,
+            timestamp,
+            headers,
+        });
+        drop(topics);
+        self.state.sent.notify_waiters();
+        offset
+    }
+
+    /// Delivers a produced message to partition 0 of `topic`, unless `fail_sends`
+    /// says it should fail.
+    pub fn produce(&self, topic: &str, key: Option<&[u8]>, payload: &[u8]) -> Result<(), String> {
+        let fail = self
+            .state
+            .failing
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if fail {
+            return Err(format!("delivery to '{}' failed (fail_sends)", topic));
+        }
+        let timestamp = (crate::now_secs() * 1000.0) as i64;
+        self.append(
+            topic,
+            0,
+            key.map(<[u8]>::to_vec),
+            Some(payload.to_vec()),
+            Some(timestamp),
+            Vec::new(),
+        );
+        Ok(())
+    }
+
+    /// A source reading `topics` for `group`: after the offsets the group has
+    /// committed, or from the first message.
+    pub fn subscribe(&self, topics: &[String], group: &str) -> MemorySource {
+        let cursors = topics
+            .iter()
+            .map(|topic| Cursor {
+                topic: topic.clone(),
+                next: 0,
+                start: self.committed(topic, group),
+            })
+            .collect();
+        MemorySource {
+            state: self.state.clone(),
+            cursors,
+            turn: 0,
+        }
+    }
+
+    /// The offset the next message on each partition of `topic` gets, or None if
+    /// nothing was ever sent to it.
+    pub fn end_offsets(&self, topic: &str) -> Option<BTreeMap<i32, i64>> {
+        let topics = self.state.topics.lock().unwrap_or_else(|e| e.into_inner());
+        let topic = topics.get(topic)?;
+        Some(topic.next_offset.iter().map(|(&p, &o)| (p, o)).collect())
+    }
+
+    /// The messages of `topic` in send order whose offset is within
+    /// `ranges[partition]`, a half-open `(from, to)`.
+    pub fn read(&self, topic: &str, ranges: &HashMap<i32, (i64, i64)>) -> Vec<Delivery> {
+        let topics = self.state.topics.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(topic) = topics.get(topic) else {
+            return Vec::new();
+        };
+        topic
+            .log
+            .iter()
+            .filter(|d| {
+                ranges
+                    .get(&d.partition)
+                    .is_some_and(|&(from, to)| (from..to).contains(&d.offset))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Commits on this broker for `group`.
+    pub fn group(&self, group: &str) -> MemoryGroup {
+        MemoryGroup {
+            broker: self.clone(),
+            group: group.to_string(),
+        }
+    }
+}
+
+pub struct MemoryGroup {
+    broker: MemoryBroker,
+    group: String,
+}
+
+impl Commit for MemoryGroup {
+    fn commit_offsets(&self, offsets: &[(String, i32, i64)], _wait: bool) -> Result<(), String> {
+        let mut topics = self
+            .broker
+            .state
+            .topics
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        for (topic, partition, offset) in offsets {
+            topics
+                .entry(topic.clone())
+                .or_default()
+                .committed
+                .entry(self.group.clone())
+                .or_default()
+                .insert(*partition, *offset);
+        }
+        Ok(())
+    }
+}
+
+impl Watermarks for MemoryGroup {
+    fn lag(&self, topics: &[String]) -> Lag {
+        let state = self
+            .broker
+            .state
+            .topics
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let mut lag = Lag::new();
+        for (name, topic) in topics.iter().filter_map(|t| Some((t, state.get(t)?))) {
+            let committed = topic.committed.get(&self.group);
+            for (&partition, &high) in &topic.next_offset {
+                let committed = committed.and_then(|c| c.get(&partition)).copied();
+                let offsets = PartitionLag {
+                    committed,
+                    high_watermark: Some(high),
+                    lag: committed.map(|c| high - c),
+                };
+                lag.insert((name.clone(), partition), offsets);
+            }
+        }
+        lag
+    }
+}
+
+/// Where a `MemorySource` is in one topic.
+struct Cursor {
+    topic: String,
+    /// Position in the topic's log.
+    next: usize,
+    /// Per partition, the first offset to deliver.
+    start: BTreeMap<i32, i64>,
+}
+
+impl Cursor {
+    /// The next message to deliver, if one has been sent.
+    fn advance(&mut self, topics: &HashMap<String, Topic>) -> Option<Delivery> {
+        let log = &topics.get(&self.topic)?.log;
+        while let Some(delivery) = log.get(self.next) {
+            self.next += 1;
+            if delivery.offset >= self.start.get(&delivery.partition).copied().unwrap_or(0) {
+                return Some(delivery.clone());
+            }
+        }
+        None
+    }
+}
+
+/// Reads its topics in turn, so a busy topic does not hold up the others; order
+/// is kept within each topic.
+pub struct MemorySource {
+    state: Arc<BrokerState>,
+    cursors: Vec<Cursor>,
+    /// Cursor to try first.
+    turn: usize,
+}
+
+impl MessageSource for MemorySource {
+    async fn recv(&mut self) -> Result<Delivery, String> {
+        loop {
+            // Created before the check, so a send in between still wakes it.
+            let sent = self.state.sent.notified();
+            let next = {
+                let topics = self.state.topics.lock().unwrap_or_else(|e| e.into_inner());
+                let n = self.cursors.len();
+                (0..n).find_map(|i| {
+                    let at = (self.turn + i) % n;
+                    let delivery = self.cursors[at].advance(&topics)?;
+                    self.turn = (at + 1) % n;
+                    Some(delivery)
+                })
+            };
+            if let Some(delivery) = next {
+                return Ok(delivery);
+            }
+            sent.await;
+        }
+    }
+}
+
+/// Either kind of source, so that a consumer's loop has one type.
+pub enum AnySource {
+    Kafka(KafkaSource),
+    Memory(MemorySource),
+}
+
+impl MessageSource for AnySource {
+    async fn recv(&mut self) -> Result<Delivery, String> {
+        match self {
+            AnySource::Kafka(source) => source.recv().await,
+            AnySource::Memory(source) => source.recv().await,
+        }
+    }
+
+    fn close(&mut self) {
+        match self {
+            AnySource::Kafka(source) => source.close(),
+            AnySource::Memory(source) => source.close(),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Cheap structural overview of a graph for `GraphWalker::summary`: one pass over
+//! the nodes for counts and degrees, plus a union-find for weak components and
+//! Kahn's algorithm for acyclicity.
+
+use crate::algorithms::{self, IndexedGraph};
+use pyo3::prelude::*;
+
+/// Ids shown per list by `GraphSummary.__repr__`.
+const REPR_IDS: usize = 5;
+
+#[pyclass(module = "rust_core")]
+pub struct GraphSummary {
+    #[pyo3(get)]
+    node_count: usize,
+    #[pyo3(get)]
+    edge_count: usize,
+    /// Edges over possible edges between distinct nodes; 0 with fewer than two.
+    #[pyo3(get)]
+    density: f64,
+    #[pyo3(get)]
+    weakly_connected_components: usize,
+    /// Nodes without edges in or out.
+    #[pyo3(get)]
+    isolated_nodes: usize,
+    #[pyo3(get)]
+    max_in_degree: usize,
+    /// Nodes with `max_in_degree`, sorted.
+    #[pyo3(get)]
+    max_in_degree_nodes: Vec<String>,
+    #[pyo3(get)]
+    max_out_degree: usize,
+    /// Nodes with `max_out_degree`, sorted.
+    #[pyo3(get)]
+    max_out_degree_nodes: Vec<String>,
+    #[pyo3(get)]
+    is_dag: bool,
+}
+
+/// The largest value and the ids having it, in index (sorted id) order.
+fn argmax(graph: &IndexedGraph, values: &[usize]) -> (usize, Vec<String>) {
+    let max = values.iter().copied().max().unwrap_or(0);
+    let ids = (0..graph.len())
+        .filter(|&v| values[v] == max)
+        .map(|v| graph.ids[v].clone())
+        .collect();
+    (max, ids)
+}
+
+impl GraphSummary {
+    /// Summarizes `graph`, which has `edge_count` edges; `directed` selects the
+    /// density denominator.
+    pub fn new(graph: &IndexedGraph, edge_count: usize, directed: bool) -> Self {
+        let n = graph.len();
+        let mut in_degree = vec![0usize; n];
+        for out in &graph.adj {
+            for &v in out {
+                in_degree[v] += 1;
+            }
+        }
+        let isolated_nodes = (0..n)
+            .filter(|&v| in_degree[v] == 0 && graph.out_degree[v] == 0)
+            .count();
+        let pairs = n * n.saturating_sub(1);
+        let possible = if directed { pairs } else { pairs / 2 };
+        let density = match possible {
+            0 => 0.0,
+            possible => edge_count as f64 / possible as f64,
+        };
+        let (max_in_degree, max_in_degree_nodes) = argmax(graph, &in_degree);
+        let (max_out_degree, max_out_degree_nodes) = argmax(graph, &graph.out_degree);
+        GraphSummary {
+            node_count: n,
+            edge_count,
+            density,
+            weakly_connected_components: algorithms::weak_component_count(graph),
+            isolated_nodes,
+            max_in_degree,
+            max_in_degree_nodes,
+            max_out_degree,
+            max_out_degree_nodes,
+            is_dag: algorithms::is_acyclic(&graph.adj, in_degree),
+        }
+    }
+}
+
+fn id_list(ids: &[String]) -> String {
+    let mut out = ids[..ids.len().min(REPR_IDS)].join(", ");
+    if ids.len() > REPR_IDS {
+        out.push_str(&format!(" and {} more", ids.len() - REPR_IDS));
+    }
+    out
+}
+
+#[pymethods]
+impl GraphSummary {
+    fn __repr__(&self) -> String {
+        let mut out = format!(
+            "GraphSummary\n  nodes: {}\n  edges: {}\n  density: {:.6}\n  \
+             weakly connected components: {}\n  isolated nodes: {}\n  \
+             max in-degree: {}",
+            self.node_count,
+            self.edge_count,
+            self.density,
+            self.weakly_connected_components,
+            self.isolated_nodes,
+            self.max_in_degree
+        );
+        if !self.max_in_degree_nodes.is_empty() {
+            out.push_str(&format!(" ({})", id_list(&self.max_in_degree_nodes)));
+        }
+        out.push_str(&format!("\n  max out-degree: {}", self.max_out_degree));
+        if !self.max_out_degree_nodes.is_empty() {
+            out.push_str(&format!(" ({})", id_list(&self.max_out_degree_nodes)));
+        }
+        out.push_str(&format!(
+            "\n  DAG: {}",
+            if self.is_dag { "yes" } else { "no" }
+        ));
+        out
+    }
+}
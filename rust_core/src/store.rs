@@ -0,0 +1,310 @@
+//! Node storage keyed by interned ids.
+//!
+//! Every id the graph sees, nodes and edge targets alike, gets a dense `u32` handle
+//! from one append-only interner. Nodes live in a slot vector indexed by handle and
+//! keep their edges as handles, so traversals run on integers and each id string is
+//! stored once however many edges point at it. Strings are produced only at the
+//! Python boundary.
+//!
+//! Handles are never reused: a removed node leaves an empty slot, and checkpoints
+//! save and restore the slots only, so a handle names the same id for the lifetime
+//! of the walker.
+//!
+//! While a journal is open (see `begin_journal`), every slot handed out for writing
+//! first has its old contents saved, once per slot, so `rollback_journal` costs
+//! what the transaction touched rather than a copy of the graph.
+
+use crate::interner::Interner;
+use crate::CausalNode;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// Stands in for an id that was never seen, so that unknown ids can enter a
+/// traversal: it is no node, and no edge points at it.
+pub const UNSEEN: u32 = u32::MAX;
+
+#[derive(Default)]
+pub struct NodeStore {
+    ids: Interner,
+    slots: Slots,
+    journal: Option<Journal>,
+}
+
+/// What the slots held when `begin_journal` was called, for the slots changed since.
+struct Journal {
+    /// Old contents of each slot written to.
+    saved: HashMap<u32, Option<CausalNode>>,
+    /// Every slot, saved at the first write to all of them at once. Slots written
+    /// before that are in `saved`.
+    all: Option<Slots>,
+    slot_count: usize,
+    len: usize,
+}
+
+/// The nodes of a `NodeStore`, without its interner (see `NodeStore::checkpoint`).
+#[derive(Clone, Default)]
+pub struct Slots {
+    /// The node of each handle; None for ids that are only edge targets or were
+    /// removed. May be shorter than the interner, when the newest ids are not nodes.
+    nodes: Vec<Option<CausalNode>>,
+    len: usize,
+}
+
+impl NodeStore {
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.slots.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.len == 0
+    }
+
+    /// Allocated node slots.
+    pub fn capacity(&self) -> usize {
+        self.slots.nodes.capacity()
+    }
+
+    /// Reserves room for `additional` more nodes.
+    pub fn reserve(&mut self, additional: usize) {
+        self.ids.reserve(additional);
+        self.slots.nodes.reserve(additional);
+    }
+
+    pub fn ids(&self) -> &Interner {
+        &self.ids
+    }
+
+    pub fn ids_mut(&mut self) -> &mut Interner {
+        &mut self.ids
+    }
+
+    /// The handle of `id`, assigning one if it is new.
+    pub fn intern(&mut self, id: &str) -> u32 {
+        self.ids.intern(id)
+    }
+
+    /// The handle of `id`, if it was ever seen.
+    pub fn handle(&self, id: &str) -> Option<u32> {
+        self.ids.get(id)
+    }
+
+    /// The handle of `id`, or `UNSEEN`.
+    pub fn lookup(&self, id: &str) -> u32 {
+        self.handle(id).unwrap_or(UNSEEN)
+    }
+
+    /// The id of a handle issued by this store.
+    pub fn name(&self, handle: u32) -> &String {
+        &self.ids.ids()[handle as usize]
+    }
+
+    /// The ids of `handles`, collected.
+    pub fn names<B: FromIterator<String>>(&self, handles: impl IntoIterator<Item = u32>) -> B {
+        handles.into_iter().map(|h| self.name(h).clone()).collect()
+    }
+
+    /// The ids of `node`'s edge targets, in edge order.
+    pub fn targets<'a>(&'a self, node: &'a CausalNode) -> impl Iterator<Item = &'a String> {
+        node.dependents.iter().map(|&h| self.name(h))
+    }
+
+    pub fn node(&self, handle: u32) -> Option<&CausalNode> {
+        self.slots.nodes.get(handle as usize)?.as_ref()
+    }
+
+    pub fn node_mut(&mut self, handle: u32) -> Option<&mut CausalNode> {
+        self.save(handle);
+        self.slots.nodes.get_mut(handle as usize)?.as_mut()
+    }
+
+    /// True if `handle` is a node (not just an edge target).
+    pub fn contains(&self, handle: u32) -> bool {
+        self.node(handle).is_some()
+    }
+
+    pub fn contains_key(&self, id: &str) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&CausalNode> {
+        self.node(self.handle(id)?)
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut CausalNode> {
+        let h = self.handle(id)?;
+        self.node_mut(h)
+    }
+
+    /// Stores `node` under `handle`, returning the node it replaces.
+    pub fn insert_at(&mut self, handle: u32, node: CausalNode) -> Option<CausalNode> {
+        self.save(handle);
+        let i = handle as usize;
+        if i >= self.slots.nodes.len() {
+            self.slots.nodes.resize_with(i + 1, || None);
+        }
+        let old = self.slots.nodes[i].replace(node);
+        if old.is_none() {
+            self.slots.len += 1;
+        }
+        old
+    }
+
+    pub fn insert(&mut self, id: &str, node: CausalNode) -> Option<CausalNode> {
+        let h = self.intern(id);
+        self.insert_at(h, node)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<CausalNode> {
+        let h = self.handle(id)?;
+        self.save(h);
+        let old = self.slots.nodes.get_mut(h as usize)?.take();
+        if old.is_some() {
+            self.slots.len -= 1;
+        }
+        old
+    }
+
+    /// Node `id`, created without edges if it does not exist.
+    pub fn get_or_insert(&mut self, id: &str) -> &mut CausalNode {
+        let h = self.intern(id);
+        self.get_or_insert_at(h)
+    }
+
+    /// `get_or_insert` by handle.
+    pub fn get_or_insert_at(&mut self, handle: u32) -> &mut CausalNode {
+        if !self.contains(handle) {
+            self.insert_at(handle, CausalNode::new());
+        }
+        self.node_mut(handle).expect("node was just inserted")
+    }
+
+    /// True if `from` is a node with an edge to `to`.
+    pub fn has_edge(&self, from: &str, to: &str) -> bool {
+        self.edge_index(from, to).is_some()
+    }
+
+    /// Position of the edge `from -> to` in `from`'s edge lists.
+    pub fn edge_index(&self, from: &str, to: &str) -> Option<usize> {
+        self.get(from)?.edge_index(self.handle(to)?)
+    }
+
+    /// Nodes with their handles, in handle order.
+    pub fn handles(&self) -> impl Iterator<Item = (u32, &CausalNode)> {
+        self.slots
+            .nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(h, slot)| Some((h as u32, slot.as_ref()?)))
+    }
+
+    /// Nodes with their ids, in handle order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &CausalNode)> {
+        self.handles().map(|(h, node)| (self.name(h), node))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.iter().map(|(id, _)| id)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &CausalNode> {
+        self.slots.nodes.iter().flatten()
+    }
+
+    pub fn par_values_mut(&mut self) -> impl ParallelIterator<Item = &mut CausalNode> {
+        self.save_all();
+        self.slots.nodes.par_iter_mut().flatten()
+    }
+
+    /// `par_values_mut` with each node's handle.
+    pub fn par_handles_mut(&mut self) -> impl ParallelIterator<Item = (u32, &mut CausalNode)> {
+        self.save_all();
+        self.slots
+            .nodes
+            .par_iter_mut()
+            .enumerate()
+            .filter_map(|(h, slot)| Some((h as u32, slot.as_mut()?)))
+    }
+
+    /// Node ids, sorted.
+    pub fn sorted_keys(&self) -> Vec<&String> {
+        let mut ids: Vec<&String> = self.keys().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Nodes with their ids, sorted by id.
+    pub fn sorted_nodes(&self) -> Vec<(&String, &CausalNode)> {
+        let mut nodes: Vec<(&String, &CausalNode)> = self.iter().collect();
+        nodes.sort_unstable_by_key(|&(id, _)| id);
+        nodes
+    }
+
+    /// A copy of the nodes, to undo a failed batch with `restore`.
+    pub fn checkpoint(&self) -> Slots {
+        self.slots.clone()
+    }
+
+    /// Puts back the nodes of a `checkpoint`. The interner is kept, so handles issued
+    /// since stay valid.
+    pub fn restore(&mut self, slots: Slots) {
+        self.slots = slots;
+    }
+
+    /// Starts saving the old contents of every slot written to, for
+    /// `rollback_journal`. A journal already open is replaced.
+    pub fn begin_journal(&mut self) {
+        self.journal = Some(Journal {
+            saved: HashMap::new(),
+            all: None,
+            slot_count: self.slots.nodes.len(),
+            len: self.slots.len,
+        });
+    }
+
+    /// Keeps every change since `begin_journal` and stops saving.
+    pub fn commit_journal(&mut self) {
+        self.journal = None;
+    }
+
+    /// Puts back the slots as they were at `begin_journal`. Ids interned since stay,
+    /// as with `restore`. Without an open journal, nothing changes.
+    pub fn rollback_journal(&mut self) {
+        let Some(journal) = self.journal.take() else {
+            return;
+        };
+        if let Some(all) = journal.all {
+            self.slots = all;
+        }
+        for (h, old) in journal.saved {
+            if let Some(slot) = self.slots.nodes.get_mut(h as usize) {
+                *slot = old;
+            }
+        }
+        self.slots.nodes.truncate(journal.slot_count);
+        self.slots.len = journal.len;
+    }
+
+    /// Saves slot `handle` into the open journal before its first write.
+    fn save(&mut self, handle: u32) {
+        let Some(journal) = self.journal.as_mut() else {
+            return;
+        };
+        if journal.all.is_none() {
+            let slots = &self.slots.nodes;
+            journal
+                .saved
+                .entry(handle)
+                .or_insert_with(|| slots.get(handle as usize).cloned().flatten());
+        }
+    }
+
+    /// Saves every slot into the open journal, before a write to all of them.
+    fn save_all(&mut self) {
+        if let Some(journal) = self.journal.as_mut() {
+            if journal.all.is_none() {
+                journal.all = Some(self.slots.clone());
+            }
+        }
+    }
+}
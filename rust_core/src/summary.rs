@@ -0,0 +1,140 @@
+use crate::algorithms::{self, IndexedGraph};
+use crate::GraphWalker;
+use pyo3::exceptions::PyKeyError;
+use pyo3::prelude::*;
+use std::collections::HashSet;
+
+/// Dependents shown per entry before eliding the rest.
+const MAX_DEPS_PER_LINE: usize = 10;
+const MAX_CENTRAL: usize = 20;
+const MAX_CYCLES: usize = 5;
+const MAX_COMMUNITIES: usize = 5;
+const MEMBERS_PER_COMMUNITY: usize = 8;
+
+/// Accumulates whole lines until the character budget is reached.
+struct Budget {
+    out: String,
+    used: usize,
+    max_chars: usize,
+    full: bool,
+}
+
+impl Budget {
+    /// Appends `line` if it fits; once a line does not fit, nothing more is added.
+    fn push(&mut self, line: String) -> bool {
+        if self.full {
+            return false;
+        }
+        let cost = line.chars().count() + usize::from(!self.out.is_empty());
+        if self.used + cost > self.max_chars {
+            self.full = true;
+            return false;
+        }
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.out.push_str(&line);
+        self.used += cost;
+        true
+    }
+}
+
+fn entry_line(walker: &GraphWalker, id: &str) -> String {
+    let mut deps: Vec<&String> = walker
+        .nodes
+        .get(id)
+        .map(|n| walker.nodes.targets(n).collect())
+        .unwrap_or_default();
+    deps.sort();
+    deps.dedup();
+    let shown: Vec<&str> = deps
+        .iter()
+        .take(MAX_DEPS_PER_LINE)
+        .map(|s| s.as_str())
+        .collect();
+    let mut list = shown.join(", ");
+    if deps.len() > MAX_DEPS_PER_LINE {
+        list.push_str(&format!(", +{} more", deps.len() - MAX_DEPS_PER_LINE));
+    }
+    format!("{} -> [{}]", id, list)
+}
+
+fn member_list(graph: &IndexedGraph, members: &[usize]) -> String {
+    let shown: Vec<&str> = members
+        .iter()
+        .take(MEMBERS_PER_COMMUNITY)
+        .map(|&i| graph.ids[i].as_str())
+        .collect();
+    let mut list = shown.join(", ");
+    if members.len() > MEMBERS_PER_COMMUNITY {
+        list.push_str(&format!(
+            ", +{} more",
+            members.len() - MEMBERS_PER_COMMUNITY
+        ));
+    }
+    list
+}
+
+pub fn summarize(walker: &GraphWalker, max_chars: usize, focus: &[String]) -> PyResult<String> {
+    if let Some(missing) = focus.iter().find(|id| !walker.nodes.contains_key(id)) {
+        return Err(PyKeyError::new_err(missing.clone()));
+    }
+    let graph = IndexedGraph::new(&walker.nodes);
+    let ranks = walker.indexed_pagerank_cached(&graph);
+    let components = algorithms::weak_components(&graph);
+    let mut budget = Budget {
+        out: String::new(),
+        used: 0,
+        max_chars,
+        full: false,
+    };
+
+    // Focus entries go first so they survive even a tiny budget.
+    let mut listed: HashSet<&str> = HashSet::new();
+    for id in focus {
+        if listed.insert(id) {
+            budget.push(entry_line(walker, id));
+        }
+    }
+
+    budget.push(format!(
+        "# graph: {} nodes, {} edges, {} components",
+        graph.len(),
+        walker.edge_count_total(),
+        components.len()
+    ));
+
+    let mut by_rank: Vec<usize> = (0..graph.len()).collect();
+    by_rank.sort_by(|&a, &b| ranks[b].total_cmp(&ranks[a]).then(a.cmp(&b)));
+    budget.push("# central".to_string());
+    for i in by_rank.into_iter().take(MAX_CENTRAL) {
+        let id = graph.ids[i].as_str();
+        if listed.insert(id) {
+            budget.push(entry_line(walker, id));
+        }
+    }
+
+    let mut cycles: Vec<Vec<usize>> = algorithms::tarjan_scc(&graph.adj)
+        .into_iter()
+        .filter(|c| c.len() > 1)
+        .collect();
+    cycles.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+    for cycle in cycles.iter().take(MAX_CYCLES) {
+        budget.push(format!(
+            "# cycle group ({} nodes): {}",
+            cycle.len(),
+            member_list(&graph, cycle)
+        ));
+    }
+
+    for (n, component) in components.iter().take(MAX_COMMUNITIES).enumerate() {
+        budget.push(format!(
+            "# community {} ({} nodes): {}",
+            n + 1,
+            component.len(),
+            member_list(&graph, component)
+        ));
+    }
+
+    Ok(budget.out)
+}
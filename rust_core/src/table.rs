@@ -0,0 +1,50 @@
+//! Per-node metric columns for `GraphWalker.metrics_table`, aligned to the sorted
+//! node ids of `IndexedGraph`.
+
+use crate::algorithms::{self, IndexedGraph};
+use pyo3::prelude::*;
+
+pub const SUPPORTED: [&str; 6] = [
+    "pagerank",
+    "degree",
+    "in_degree",
+    "out_degree",
+    "core_number",
+    "community",
+];
+
+pub enum Column {
+    Float(Vec<f64>),
+    Int(Vec<i64>),
+}
+
+impl Column {
+    pub fn to_py(&self, py: Python) -> PyObject {
+        match self {
+            Column::Float(values) => values.to_object(py),
+            Column::Int(values) => values.to_object(py),
+        }
+    }
+}
+
+/// Computes the column `name`, which must be one of `SUPPORTED`. `pagerank` and
+/// `degree` are computed by the caller, which has the cached ranks and the mode.
+pub fn compute(graph: &IndexedGraph, name: &str) -> Column {
+    let ints = |values: Vec<usize>| Column::Int(values.into_iter().map(|v| v as i64).collect());
+    match name {
+        "in_degree" => ints(graph.reverse().iter().map(Vec::len).collect()),
+        // Dangling targets count, matching the stored dependents.
+        "out_degree" => ints(graph.out_degree.clone()),
+        "core_number" => ints(algorithms::core_numbers(graph)),
+        "community" => {
+            let mut community = vec![0; graph.len()];
+            for (c, members) in algorithms::weak_components(graph).iter().enumerate() {
+                for &v in members {
+                    community[v] = c;
+                }
+            }
+            ints(community)
+        }
+        _ => unreachable!("unsupported metric column '{}'", name),
+    }
+}
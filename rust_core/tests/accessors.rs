@@ -0,0 +1,80 @@
+//! The read API: `has_node`, `get_dependents`, `node_ids`, `node_count`,
+//! `edge_count` with and without `unique`, the container protocol, and how
+//! missing, dangling and tombstoned ids answer.
+
+mod common;
+
+const SETUP: &str = r#"
+def graph():
+    w = rc.GraphWalker()
+    w.add_node("b", [])
+    w.add_node("a", ["b", "b", "c"])
+    return w
+
+def missing(call, id):
+    try:
+        call()
+    except rc.NodeNotFoundError as e:
+        assert e.args == (id,), e.args
+    else:
+        raise AssertionError("no NodeNotFoundError")
+"#;
+
+#[test]
+fn nodes_and_edges_are_readable() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+assert w.has_node("a") and w.has_node("b")
+assert w.get_dependents("a") == ["b", "b", "c"] and w.get_dependents("b") == []
+assert w.node_ids() == ["a", "b"] and w.node_count() == len(w) == 2
+# Every dependents entry, or each distinct (from, to) once.
+assert w.edge_count() == 3 and w.edge_count(unique=True) == 2
+assert "a" in w and "c" not in w and 1 not in w
+
+empty = rc.GraphWalker()
+assert (empty.node_ids(), empty.node_count(), empty.edge_count(), empty.edge_count(unique=True)) == ([], 0, 0, 0)
+"#,
+    );
+}
+
+#[test]
+fn missing_nodes_raise_key_errors() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+assert issubclass(rc.NodeNotFoundError, KeyError)
+# "c" is only an edge target, so it is not a node.
+assert not w.has_node("c")
+missing(lambda: w.get_dependents("c"), "c")
+missing(lambda: w.get_dependents_labeled("nowhere"), "nowhere")
+try:
+    w.get_dependents(5)
+except TypeError:
+    pass
+else:
+    raise AssertionError("no TypeError")
+
+# A tombstoned node is gone; edges into it are still counted.
+w.tombstone_node("b")
+assert not w.has_node("b") and w.node_count() == 1 and w.edge_count() == 3
+missing(lambda: w.get_dependents("b"), "b")
+"#,
+    );
+}
+
+#[test]
+fn lookups_pass_through_the_normalizer() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(normalize="lowercase")
+w.add_node("A", ["B"])
+assert w.has_node("a") and w.has_node("A") and "A" in w
+assert w.get_dependents("A") == w.get_dependents("a") == ["b"]
+assert w.node_ids() == ["a"]
+"#,
+    );
+}
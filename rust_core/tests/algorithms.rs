@@ -0,0 +1,168 @@
+//! Graph algorithms checked against known results.
+
+mod common;
+
+#[test]
+fn pagerank_matches_the_reference_ranks() {
+    // Ranks of the original all-pairs scan, 50 iterations at damping 0.85, on a
+    // graph without dangling nodes (where the two formulations agree).
+    common::run(
+        r#"
+w = rc.GraphWalker()
+for id, deps in {"a": ["b", "c"], "b": ["c"], "c": ["a"], "d": ["c", "a"], "e": ["d"]}.items():
+    w.add_node(id, deps)
+expected = {
+    "a": 0.36423403052731335,
+    "b": 0.1847994629726097,
+    "c": 0.3654665065000772,
+    "d": 0.05550000000000001,
+    "e": 0.030000000000000006,
+}
+ranks = w.calculate_pagerank(50, 0.85)
+assert ranks.keys() == expected.keys(), ranks
+for id, rank in expected.items():
+    assert abs(ranks[id] - rank) < 1e-12, (id, ranks[id], rank)
+# Served from the reverse index, which a mutation rebuilds.
+w.add_node("e", ["d"])
+assert w.calculate_pagerank(50, 0.85) == ranks
+"#,
+    );
+}
+
+#[test]
+fn max_flow_of_the_textbook_network_is_23() {
+    // The flow network of CLRS, figure 26.1.
+    common::run(
+        r#"
+capacities = {
+    ("s", "v1"): 16, ("s", "v2"): 13, ("v1", "v3"): 12, ("v2", "v1"): 4,
+    ("v2", "v4"): 14, ("v3", "v2"): 9, ("v3", "t"): 20, ("v4", "v3"): 7, ("v4", "t"): 4,
+}
+w = rc.GraphWalker()
+for frm, to in capacities:
+    w.add_edge(frm, to)
+w.add_node("t", [])
+assert w.max_flow("s", "t", capacities=capacities) == 23
+cut = w.min_cut("s", "t", capacities=capacities)
+assert sum(capacities[e] for e in cut) == 23, cut
+assert cut == {("v1", "v3"), ("v4", "v3"), ("v4", "t")}, cut
+# Unit capacities: two edges leave "s".
+assert w.max_flow("s", "t") == 2
+"#,
+    );
+}
+
+#[test]
+fn max_flow_between_disconnected_nodes_is_0() {
+    common::run(
+        r#"
+w = rc.GraphWalker()
+w.add_node("s", ["a"])
+w.add_node("a", [])
+w.add_node("t", ["b"])
+w.add_node("b", [])
+assert w.max_flow("s", "t") == 0 and w.max_flow("t", "s") == 0
+assert w.min_cut("s", "t") == set()
+assert w.max_flow("s", "s") == 0
+"#,
+    );
+}
+
+#[test]
+fn cycle_detection_survives_a_500k_node_chain() {
+    // Deep enough to overflow the stack of a recursive DFS.
+    common::run(
+        r#"
+N = 500_000
+ids = ["n%06d" % i for i in range(N)]
+indices = list(range(1, N)) + [N - 3]
+w = rc.GraphWalker.from_csr(list(range(N + 1)), indices, ids)
+assert w.detect_cycles() == [ids[-3:]], w.detect_cycles()
+"#,
+    );
+}
+
+#[test]
+fn pagerank_keeps_the_rank_of_leaves_after_every_iteration() {
+    // Most nodes are leaves, and "x" is only an edge target; their rank is spread
+    // over every node instead of leaking.
+    common::run(
+        r#"
+w = rc.GraphWalker()
+w.add_node("root", ["a", "b", "c"])
+w.add_node("a", ["l1", "l2", "l3"])
+w.add_node("b", ["l3", "x"])
+w.add_node("c", ["a"])
+for leaf in ("l1", "l2", "l3", "l4", "l5"):
+    w.add_node(leaf, [])
+for iterations in range(1, 41):
+    ranks = w.calculate_pagerank(iterations, 0.85)
+    assert len(ranks) == 9, ranks
+    total = sum(ranks.values())
+    assert abs(total - 1.0) < 1e-9, (iterations, total)
+    assert all(r > 0 for r in ranks.values()), ranks
+# Isolated leaves receive only the teleport and redistributed leaf rank.
+assert ranks["l4"] == ranks["l5"] < ranks["l1"]
+"#,
+    );
+}
+
+#[test]
+fn personalized_pagerank_favours_the_seed_cluster() {
+    // Two complete clusters joined by one edge each way.
+    common::run(
+        r#"
+w = rc.GraphWalker()
+A = ["a%d" % i for i in range(6)]
+B = ["b%d" % i for i in range(6)]
+for cluster in (A, B):
+    for id in cluster:
+        w.add_node(id, [other for other in cluster if other != id])
+w.add_edge("a5", "b0")
+w.add_edge("b5", "a0")
+for seeds in (["a0"], ["a1", "a4"], ["a5"]):
+    ranks = w.calculate_personalized_pagerank(seeds, 50, 0.85)
+    assert abs(sum(ranks.values()) - 1.0) < 1e-9, ranks
+    assert min(ranks[id] for id in A) > max(ranks[id] for id in B), (seeds, ranks)
+ranks = w.calculate_personalized_pagerank(["b2"], 50, 0.85)
+assert min(ranks[id] for id in B) > max(ranks[id] for id in A), ranks
+
+try:
+    w.calculate_personalized_pagerank(["a0", "missing"], 50, 0.85)
+except rc.NodeNotFoundError as e:
+    assert e.args == ("missing",), e.args
+else:
+    raise AssertionError("no NodeNotFoundError")
+try:
+    w.calculate_personalized_pagerank([], 50, 0.85)
+except ValueError as e:
+    assert not isinstance(e, rc.NodeNotFoundError) and "seed" in str(e), e
+else:
+    raise AssertionError("no ValueError")
+"#,
+    );
+}
+
+#[test]
+fn diameter_of_a_known_graph_is_4() {
+    common::run(
+        r#"
+w = rc.GraphWalker()
+# The longest shortest path is a -> b -> c -> d -> e; the side edges x -> c
+# and a -> y do not shorten it.
+for frm, to in [("a", "b"), ("b", "c"), ("c", "d"), ("d", "e"), ("x", "c"), ("a", "y")]:
+    w.add_edge(frm, to)
+assert w.find_diameter() == 4
+w.add_edge("b", "d")
+assert w.find_diameter() == 3
+
+sampled = w.find_diameter(sample=2, seed=1)
+assert set(sampled) == {"diameter", "approximate", "sources"}, sampled
+assert sampled["approximate"] is True and sampled["sources"] == 2, sampled
+assert sampled["diameter"] <= 3
+every = w.find_diameter(sample=100)
+assert every == {"diameter": 3, "approximate": False, "sources": len(w.node_ids())}, every
+assert rc.GraphWalker().find_diameter() == 0
+"#,
+    );
+}
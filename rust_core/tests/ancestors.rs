@@ -0,0 +1,72 @@
+//! `find_ancestors`: every node that reaches the target, termination on cycles and
+//! self-loops, the start left out, the index following edge changes, and unknown
+//! ids.
+
+mod common;
+
+const SETUP: &str = r#"
+def cyclic():
+    # s -> a -> t -> s is a cycle; b, c, d is a second way into t.
+    w = rc.GraphWalker()
+    w.add_node("s", ["a", "b"])
+    w.add_node("a", ["t"])
+    w.add_node("b", ["c"])
+    w.add_node("c", ["d"])
+    w.add_node("d", ["t"])
+    w.add_node("t", ["u", "s"])
+    return w
+"#;
+
+#[test]
+fn ancestors_are_found_through_cycles() {
+    common::run_with(
+        SETUP,
+        r#"
+w = cyclic()
+ancestors = w.find_ancestors("t")
+assert type(ancestors) is set
+# On the cycle the start reaches itself, but it is never its own ancestor.
+assert ancestors == {"s", "a", "b", "c", "d"}, ancestors
+assert w.find_ancestors("s") == {"t", "a", "b", "c", "d"}
+# An edge target that is not a node has ancestors too.
+assert w.find_ancestors("u") == {"s", "a", "b", "c", "d", "t"}
+
+# The inverse of propagation: x reaches y exactly when x is an ancestor of y.
+for x in w.node_ids():
+    for y in w.propagate_invalidation(x) - {x}:
+        assert x in w.find_ancestors(y), (x, y)
+
+loop = rc.GraphWalker()
+loop.add_node("x", ["x"])
+loop.add_node("y", ["x"])
+assert loop.find_ancestors("x") == {"y"} and loop.find_ancestors("y") == set()
+"#,
+    );
+}
+
+#[test]
+fn the_reverse_index_follows_mutations() {
+    common::run_with(
+        SETUP,
+        r#"
+w = cyclic()
+w.add_edge("q", "d")
+assert w.find_ancestors("t") == {"s", "a", "b", "c", "d", "q"}
+w.remove_edge("q", "d")
+assert w.find_ancestors("t") == {"s", "a", "b", "c", "d"}
+w.add_node("t", [])
+assert w.find_ancestors("s") == set()
+w.remove_node("a")
+assert w.find_ancestors("t") == {"s", "b", "c", "d"}
+
+# "u" is no longer referenced at all, so it is as unknown as an id never seen.
+for id in ("u", "nope", ""):
+    try:
+        w.find_ancestors(id)
+    except rc.NodeNotFoundError:
+        pass
+    else:
+        raise AssertionError("no NodeNotFoundError for %r" % id)
+"#,
+    );
+}
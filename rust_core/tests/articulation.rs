@@ -0,0 +1,95 @@
+//! `articulation_points` and `bridges`: two triangles joined at one node, several
+//! components, direction and parallel edges ignored, a deep chain, and agreement
+//! with removing each node or edge by brute force on random graphs.
+
+mod common;
+
+const SETUP: &str = r#"
+import random
+
+def build(edges, nodes=()):
+    w = rc.GraphWalker()
+    for id in nodes:
+        w.add_node(id, [])
+    for a, b in edges:
+        w.add_edge(a, b)
+    return w
+
+def undirected(w):
+    ids = set(w.node_ids())
+    near = {id: set() for id in ids}
+    for v in ids:
+        for d in w.get_dependents(v):
+            if d in ids and d != v:
+                near[v].add(d)
+                near[d].add(v)
+    return near
+
+def components(near, gone_node=None, gone_edge=None):
+    seen, count = set(), 0
+    for s in near:
+        if s == gone_node or s in seen:
+            continue
+        count += 1
+        stack = [s]
+        seen.add(s)
+        while stack:
+            v = stack.pop()
+            for d in near[v]:
+                if d == gone_node or d in seen or {v, d} == gone_edge:
+                    continue
+                seen.add(d)
+                stack.append(d)
+    return count
+
+def brute(w):
+    near = undirected(w)
+    base = components(near)
+    points = sorted(v for v in near if components(near, gone_node=v) > base - (not near[v]))
+    pairs = sorted({tuple(sorted((a, b))) for a in near for b in near[a]})
+    return points, [p for p in pairs if components(near, gone_edge=set(p)) > base]
+"#;
+
+#[test]
+fn two_triangles_share_one_cut_vertex() {
+    common::run_with(
+        SETUP,
+        r#"
+bowtie = build([("a", "b"), ("b", "c"), ("c", "a"), ("c", "d"), ("d", "e"), ("e", "c")])
+assert bowtie.articulation_points() == ["c"] and bowtie.bridges() == []
+# A pendant f, a separate x-y pair, an isolated node and an edge to a non-node.
+g = build([("a", "b"), ("b", "c"), ("c", "a"), ("c", "d"), ("d", "e"), ("e", "c"), ("c", "f"), ("x", "y"), ("d", "ghost")],
+          nodes=["f", "y", "lone"])
+assert g.articulation_points() == ["c"]
+assert g.bridges() == [("c", "f"), ("x", "y")]
+# Both directions, a parallel edge and a self-loop all collapse into one edge.
+path = build([("a", "b"), ("b", "a"), ("b", "c"), ("c", "b"), ("b", "c"), ("b", "b")])
+assert path.articulation_points() == ["b"] and path.bridges() == [("a", "b"), ("b", "c")]
+assert rc.GraphWalker().articulation_points() == [] and rc.GraphWalker().bridges() == []
+
+# A long chain: every inner node cuts it, and every edge is a bridge.
+n = 200000
+chain = build([("c%06d" % i, "c%06d" % (i + 1)) for i in range(n - 1)], nodes=["c%06d" % (n - 1)])
+points = chain.articulation_points()
+assert len(points) == n - 2 and points[0] == "c000001" and points[-1] == "c%06d" % (n - 2)
+assert len(chain.bridges()) == n - 1
+"#,
+    );
+}
+
+#[test]
+fn random_graphs_match_brute_force() {
+    common::run_with(
+        SETUP,
+        r#"
+for seed in range(8):
+    r = random.Random(seed)
+    n = 25
+    w = build([("n%d" % r.randrange(n), "n%d" % r.randrange(n)) for _ in range(30)],
+              nodes=["n%d" % i for i in range(n)])
+    points, bridges = brute(w)
+    assert w.articulation_points() == points, (seed, w.articulation_points(), points)
+    assert w.bridges() == bridges, (seed, w.bridges(), bridges)
+"#,
+    );
+}
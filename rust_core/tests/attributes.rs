@@ -0,0 +1,162 @@
+//! Node attributes: set with `add_node` and `set_attribute`, kept across a
+//! re-add, saved in snapshots, and pruning `propagate_invalidation` with
+//! `attribute_filter`. `set_attrs_bulk` and `get_attrs_bulk`: values stay aligned
+//! with their ids, and unknown ids raise or are skipped depending on `strict`.
+
+mod common;
+
+const SETUP: &str = r#"
+def graph(**kwargs):
+    w = rc.GraphWalker(**kwargs)
+    for i in range(20):
+        w.add_node("n%d" % i, [])
+    return w
+
+def teams():
+    # a feeds b (ads) and c (search); b feeds d (search), c feeds e (no team) and e feeds f.
+    w = rc.GraphWalker()
+    w.add_node("a", ["b", "c"], attributes={"team": "search"})
+    w.add_node("b", ["d"], attributes={"team": "ads"})
+    w.add_node("c", ["e", "d"], attributes={"team": "search"})
+    w.add_node("d", [], attributes={"team": "search", "tier": "gold"})
+    w.add_node("e", ["f"])
+    return w
+
+def raises(error, call, fragment):
+    try:
+        call()
+    except error as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+"#;
+
+#[test]
+fn attributes_are_set_kept_and_saved() {
+    common::run_with(
+        SETUP,
+        r#"
+import os, tempfile
+w = teams()
+assert w.get_attributes("a") == {"team": "search"} and w.get_attributes("e") == {}
+w.set_attribute("e", "team", "infra")
+w.set_attribute("a", "team", "ads")
+assert w.get_attributes("e") == {"team": "infra"} and w.get_attributes("a") == {"team": "ads"}
+# Re-adding a node keeps its attributes and merges new ones.
+w.add_node("a", ["c"])
+assert w.get_attributes("a") == {"team": "ads"}
+w.add_node("a", ["c"], attributes={"kind": "derived"})
+assert w.get_attributes("a") == {"team": "ads", "kind": "derived"}
+raises(rc.NodeNotFoundError, lambda: w.set_attribute("zz", "k", "v"), "zz")
+raises(rc.NodeNotFoundError, lambda: w.get_attributes("zz"), "zz")
+
+loaded = rc.GraphWalker.from_json(w.to_json())
+assert all(loaded.get_attributes(id) == w.get_attributes(id) for id in w.node_ids())
+path = os.path.join(tempfile.mkdtemp(), "snapshot.json")
+w.save(path)
+assert rc.GraphWalker.load(path).get_attributes("d") == {"team": "search", "tier": "gold"}
+"#,
+    );
+}
+
+#[test]
+fn filtered_propagation_stops_at_non_matching_nodes() {
+    common::run_with(
+        SETUP,
+        r#"
+w = teams()
+assert w.propagate_invalidation("a") == {"b", "c", "d", "e", "f"}
+# b (ads) and e (no team) are left out and not expanded; d is still reached via c.
+assert w.propagate_invalidation("a", attribute_filter={"team": "search"}) == {"c", "d"}
+# Every pair must match.
+assert w.propagate_invalidation("a", attribute_filter={"team": "search", "tier": "gold"}) == set()
+assert w.propagate_invalidation("c", attribute_filter={"team": "search", "tier": "gold"}) == {"d"}
+# The start node itself is not filtered.
+assert w.propagate_invalidation("b", attribute_filter={"team": "search"}) == {"d"}
+# f is not a node and has no attributes, so it never matches.
+w.set_attribute("e", "team", "search")
+assert w.propagate_invalidation("a", attribute_filter={"team": "search"}) == {"c", "d", "e"}
+# An empty filter matches every node, but still not f.
+assert w.propagate_invalidation("a", attribute_filter={}) == w.propagate_invalidation("a") - {"f"}
+"#,
+    );
+}
+
+#[test]
+fn values_are_aligned_with_their_ids() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+ids = ["n%d" % ((i * 7) % 20) for i in range(20)]
+assert w.set_attrs_bulk(ids, "score", ["v" + id for id in ids]) == 0
+assert w.get_attrs_bulk(ids[::-1], "score") == ["v" + id for id in ids[::-1]]
+assert all(w.get_attributes(id) == {"score": "v" + id} for id in ids)
+# A repeated id takes its last value.
+w.set_attrs_bulk(["n1", "n2", "n1"], "score", ["a", "b", "c"])
+assert w.get_attrs_bulk(["n1", "n2", "n1"], "score") == ["c", "b", "c"]
+# Another key leaves "score" alone.
+w.set_attrs_bulk(["n1"], "team", ["ads"])
+assert w.get_attributes("n1") == {"score": "c", "team": "ads"}
+
+# Absent keys and unknown ids read as None, in place.
+assert w.get_attrs_bulk(["n1", "missing", "n3"], "team") == ["ads", None, None]
+assert w.get_attrs_bulk([], "score") == [] and w.set_attrs_bulk([], "score", []) == 0
+raises(ValueError, lambda: w.set_attrs_bulk(["n1", "n2"], "score", ["x"]), "same length (2 != 1)")
+assert w.get_attrs_bulk(["n1"], "score") == ["c"]
+"#,
+    );
+}
+
+#[test]
+fn strict_raises_on_unknown_ids_and_skip_counts_them() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph(record_events=True)
+missing = ["x%d" % i for i in range(12)]
+ids = ["n0"] + missing + ["n1"]
+values = [str(i) for i in range(len(ids))]
+events = len(w.event_log())
+raises(KeyError, lambda: w.set_attrs_bulk(ids, "score", values), "12 unknown node id(s), first: x0, x1, x2, x3, x4, x5, x6, x7, x8, x9'")
+# A rejected call sets nothing and logs nothing.
+assert w.get_attrs_bulk(["n0", "n1"], "score") == [None, None]
+assert len(w.event_log()) == events
+
+assert w.set_attrs_bulk(ids, "score", values, strict=False) == 12
+assert w.get_attrs_bulk(["n0", "n1"], "score") == ["0", "13"]
+assert w.node_count() == 20, "skipped ids are not created"
+
+# The logged event replays to the same attributes.
+copy = rc.GraphWalker()
+copy.replay_events(w.event_log())
+assert copy.get_attrs_bulk(["n0", "n1", "n2"], "score") == ["0", "13", None]
+"#,
+    );
+}
+
+#[test]
+fn ids_are_normalized_and_frozen_walkers_refuse() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(normalize="lowercase")
+w.add_node("A", [])
+w.add_node("b", [])
+assert w.set_attrs_bulk(["a", "B"], "team", ["search", "ads"]) == 0
+assert w.get_attrs_bulk(["A", "b"], "team") == ["search", "ads"]
+
+w.freeze()
+raises(rc.FrozenGraphError, lambda: w.set_attrs_bulk(["a"], "team", ["x"], strict=False), "frozen")
+assert w.get_attrs_bulk(["a"], "team") == ["search"]
+w.unfreeze()
+try:
+    import numpy
+except ImportError:
+    raises(ImportError, lambda: w.set_numeric_attrs_bulk(["a"], "score", [1.0]), "numpy")
+else:
+    assert w.set_numeric_attrs_bulk(["a", "x"], "score", numpy.array([0.5, 2.0]), strict=False) == 1
+    assert w.get_attrs_bulk(["a"], "score") == ["0.5"]
+"#,
+    );
+}
@@ -0,0 +1,116 @@
+//! `calculate_betweenness`: hand-computed values on a path, a star and a diamond,
+//! agreement with a Python Brandes on random graphs, parallel edges and self-loops
+//! adding no paths, and the old out-degree kept as `calculate_degree_centrality`.
+
+mod common;
+
+const SETUP: &str = r#"
+import random
+from collections import deque
+
+def build(edges, nodes=()):
+    w = rc.GraphWalker()
+    for id in nodes:
+        w.add_node(id, [])
+    for a, b in edges:
+        w.add_edge(a, b)
+    return w
+
+def brandes(w):
+    ids = w.node_ids()
+    succ = {v: sorted({d for d in w.get_dependents(v) if w.has_node(d) and d != v}) for v in ids}
+    score = dict.fromkeys(ids, 0.0)
+    for s in ids:
+        order, preds = [], {v: [] for v in ids}
+        sigma, dist = dict.fromkeys(ids, 0), dict.fromkeys(ids, -1)
+        sigma[s], dist[s] = 1, 0
+        queue = deque([s])
+        while queue:
+            v = queue.popleft()
+            order.append(v)
+            for x in succ[v]:
+                if dist[x] < 0:
+                    dist[x] = dist[v] + 1
+                    queue.append(x)
+                if dist[x] == dist[v] + 1:
+                    sigma[x] += sigma[v]
+                    preds[x].append(v)
+        delta = dict.fromkeys(ids, 0.0)
+        for x in reversed(order):
+            for v in preds[x]:
+                delta[v] += sigma[v] / sigma[x] * (1 + delta[x])
+            if x != s:
+                score[x] += delta[x]
+    n = len(ids)
+    scale = 1.0 / ((n - 1) * (n - 2)) if n > 2 else 0.0
+    return {v: c * scale for v, c in score.items()}
+
+def close(a, b):
+    assert a.keys() == b.keys(), (a, b)
+    for k in a:
+        assert abs(a[k] - b[k]) < 1e-12, (k, a[k], b[k])
+"#;
+
+#[test]
+fn small_graphs_match_hand_computed_values() {
+    common::run_with(
+        SETUP,
+        r#"
+# a -> b -> c -> d: b carries a->c and a->d, c carries a->d and b->d; 3 * 2 ordered pairs.
+path = build([("a", "b"), ("b", "c"), ("c", "d")], nodes=["d"])
+assert path.calculate_betweenness() == {"a": 0.0, "b": 2 / 6, "c": 2 / 6, "d": 0.0}
+
+# Every leaf-to-leaf path goes through the hub.
+star = build([("hub", l) for l in "xyz"] + [(l, "hub") for l in "xyz"])
+assert star.calculate_betweenness() == {"hub": 1.0, "x": 0.0, "y": 0.0, "z": 0.0}
+# Only outward: the hub carries nothing.
+assert build([("hub", l) for l in "xyz"], nodes="xyz").calculate_betweenness()["hub"] == 0.0
+
+# Two shortest s -> t paths share the credit.
+diamond = build([("s", "a"), ("s", "b"), ("a", "t"), ("b", "t")], nodes=["t"])
+close(diamond.calculate_betweenness(), {"s": 0.0, "a": 0.5 / 6, "b": 0.5 / 6, "t": 0.0})
+
+# Parallel edges and self-loops add no paths; dangling targets are not nodes.
+noisy = build([("a", "b"), ("a", "b"), ("b", "b"), ("b", "c"), ("c", "d"), ("c", "ghost")], nodes=["d"])
+noisy.add_node("a", ["b", "b"])
+close(noisy.calculate_betweenness(), path.calculate_betweenness())
+
+assert rc.GraphWalker().calculate_betweenness() == {}
+assert build([("a", "b")], nodes=["b"]).calculate_betweenness() == {"a": 0.0, "b": 0.0}
+"#,
+    );
+}
+
+#[test]
+fn random_graphs_match_a_reference_implementation() {
+    common::run_with(
+        SETUP,
+        r#"
+for seed in range(6):
+    r = random.Random(seed)
+    n = 30
+    w = build([("n%d" % r.randrange(n), "n%d" % r.randrange(n)) for _ in range(70)],
+              nodes=["n%d" % i for i in range(n)])
+    close(w.calculate_betweenness(), brandes(w))
+"#,
+    );
+}
+
+#[test]
+fn degree_centrality_keeps_the_old_heuristic() {
+    common::run_with(
+        SETUP,
+        r#"
+path = build([("a", "b"), ("b", "c"), ("c", "d")], nodes=["d"])
+assert path.calculate_degree_centrality() == {"a": 1.0, "b": 1.0, "c": 1.0, "d": 0.0}
+assert path.calculate_degree_centrality() != path.calculate_betweenness()
+
+# Cached until the next mutation.
+first = path.calculate_betweenness()
+computed = path.computation_stats()["computed"]
+assert path.calculate_betweenness() == first and path.computation_stats()["computed"] == computed
+path.add_edge("a", "c")
+assert path.calculate_betweenness()["b"] < first["b"]
+"#,
+    );
+}
@@ -0,0 +1,121 @@
+//! `add_nodes_bulk`: dict and pair-list input, created/updated counts, replace
+//! versus merge, the logged events matching individual calls, atomic rejection,
+//! and a million entries in linear time.
+
+mod common;
+
+const SETUP: &str = r#"
+def chain(n):
+    return [("n%d" % i, ["n%d" % (i + 1), "n%d" % (i // 2)]) for i in range(n)]
+"#;
+
+#[test]
+fn replace_and_merge_report_created_and_updated() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b"], weights=[0.5])
+w.set_attribute("a", "team", "search")
+assert w.add_nodes_bulk({"a": ["c"], "d": ["a"], "e": []}) == {"created": 2, "updated": 1}
+# Replaced as add_node would: new dependents, attributes kept.
+assert w.get_dependents("a") == ["c"] and w.get_attributes("a") == {"team": "search"}
+assert w.get_dependents("d") == ["a"] and w.node_count() == 3
+
+assert w.add_nodes_bulk([("a", ["b", "c", "b"]), ("f", ["a"])], merge=True) == {"created": 1, "updated": 1}
+# Merged: new dependents appended once, the existing edge untouched.
+assert w.get_dependents("a") == ["c", "b"]
+w.set_edge_weight("a", "c", 0.25)
+w.add_nodes_bulk([("a", ["c"])], merge=True)
+assert w.get_edge("a", "c")[0] == 0.25 and w.get_dependents("a") == ["c", "b"]
+
+assert w.add_nodes_bulk([]) == {"created": 0, "updated": 0} and w.add_nodes_bulk({}) == {"created": 0, "updated": 0}
+for bad in ([("a", "b")], {"a": "b"}, 5):
+    try:
+        w.add_nodes_bulk(bad)
+    except TypeError:
+        pass
+    else:
+        raise AssertionError("no TypeError for %r" % (bad,))
+assert w.get_dependents("a") == ["c", "b"]
+"#,
+    );
+}
+
+#[test]
+fn events_match_the_equivalent_individual_calls() {
+    common::run_with(
+        SETUP,
+        r#"
+bulk, single = rc.GraphWalker(record_events=True), rc.GraphWalker(record_events=True)
+bulk.add_nodes_bulk({"z": ["y"], "a": [], "m": ["a", "z"]})
+for id, deps in [("z", ["y"]), ("a", []), ("m", ["a", "z"])]:
+    single.add_node(id, deps)
+# Dict order is kept.
+assert bulk.event_log() == single.event_log()
+assert [e["id"] for e in bulk.event_log()] == ["z", "a", "m"]
+
+bulk.add_nodes_bulk([("m", ["y", "a", "y"])], merge=True)
+assert bulk.event_log()[3:] == [{"op": "add_edge", "from": "m", "to": "y"}]
+assert bulk.get_dependents("m") == ["a", "z", "y"]
+replica = rc.GraphWalker()
+replica.replay_events(bulk.event_log())
+assert replica.content_digest(structural=True) == bulk.content_digest(structural=True)
+
+# A tombstoned id added in bulk is live again.
+bulk.tombstone_node("a")
+bulk.add_nodes_bulk([("a", [])])
+assert bulk.has_node("a") and bulk.tombstones() == []
+"#,
+    );
+}
+
+#[test]
+fn checked_walkers_reject_the_whole_batch() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(enforce_dag=True)
+w.add_node("a", ["b"])
+before = w.content_digest()
+try:
+    w.add_nodes_bulk([("x", ["y"]), ("b", ["a"])])
+except rc.CycleError as e:
+    assert "b -> a would create cycle" in str(e), str(e)
+else:
+    raise AssertionError("no CycleError")
+assert w.content_digest() == before and w.node_ids() == ["a"]
+assert w.add_nodes_bulk([("x", ["y"]), ("b", ["c"])]) == {"created": 2, "updated": 0}
+
+# The checked path gives the same graph as the parallel one.
+plain, checked = rc.GraphWalker(), rc.GraphWalker(cycle_check_steps=1000)
+plain.add_nodes_bulk(chain(1000))
+checked.add_nodes_bulk(chain(1000))
+assert plain.content_digest(structural=True) == checked.content_digest(structural=True)
+"#,
+    );
+}
+
+#[test]
+fn a_million_entries_insert_in_linear_time() {
+    common::run_with(
+        SETUP,
+        r#"
+def timed(n):
+    w, entries = rc.GraphWalker(), chain(n)
+    start = time.perf_counter()
+    assert w.add_nodes_bulk(entries) == {"created": n, "updated": 0}
+    elapsed = time.perf_counter() - start
+    assert w.node_count() == n and w.edge_count() == 2 * n
+    return w, elapsed
+
+_, small = timed(250000)
+big, large = timed(1000000)
+# Four times the entries: about four times the time, far from sixteen.
+assert large < 8 * small, (small, large)
+assert big.get_dependents("n999999") == ["n1000000", "n499999"]
+assert big.add_nodes_bulk(chain(500000), merge=True) == {"created": 0, "updated": 500000}
+assert big.edge_count() == 2000000
+"#,
+    );
+}
@@ -0,0 +1,95 @@
+//! `calculate_eigenvector_centrality` and `calculate_katz_centrality`: stars,
+//! chains and cycles against hand-computed scores, edge weights, and the
+//! `ConvergenceError` and divergence `ValueError` paths.
+
+mod common;
+
+const SETUP: &str = r#"
+import math
+
+def build(edges, nodes=(), weights=None):
+    w = rc.GraphWalker()
+    for i, (a, b) in enumerate(edges):
+        w.add_edge(a, b, weight=weights[i] if weights else None)
+    for id in nodes:
+        w.add_node(id, [])
+    return w
+
+def unit(scores):
+    # Raw scores scaled to unit L2 norm, as both methods return them.
+    norm = math.sqrt(sum(v * v for v in scores.values()))
+    return {k: v / norm for k, v in scores.items()}
+
+def close(found, expected, eps=1e-5):
+    return found.keys() == expected.keys() and all(abs(found[k] - expected[k]) < eps for k in expected)
+
+out_star = lambda **kw: build([("h", x) for x in "abcd"], nodes="abcd", **kw)
+in_star = lambda: build([(x, "h") for x in "abcd"], nodes="h")
+chain = lambda: build([("a", "b"), ("b", "c"), ("c", "d")], nodes="d")
+"#;
+
+#[test]
+fn katz_counts_attenuated_walks() {
+    common::run_with(
+        SETUP,
+        r#"
+# With alpha 0.1 and beta 1, a node scores 1 plus 0.1 per walk of length 1, 0.01 per length 2, ...
+assert close(out_star().calculate_katz_centrality(), unit({"h": 1.0, **{x: 1.1 for x in "abcd"}}))
+assert close(in_star().calculate_katz_centrality(), unit({"h": 1.4, **{x: 1.0 for x in "abcd"}}))
+ranks = chain().calculate_katz_centrality()
+assert close(ranks, unit({"a": 1.0, "b": 1.1, "c": 1.11, "d": 1.111}))
+assert sorted(ranks, key=ranks.get) == ["a", "b", "c", "d"]
+assert close(chain().calculate_katz_centrality(alpha=0.5, beta=2.0), unit({"a": 2.0, "b": 3.0, "c": 3.5, "d": 3.75}))
+# A weight scales the walk through that edge.
+heavy = out_star(weights=[3.0, 1.0, 1.0, 1.0]).calculate_katz_centrality()
+assert close(heavy, unit({"h": 1.0, "a": 1.3, "b": 1.1, "c": 1.1, "d": 1.1}))
+assert rc.GraphWalker().calculate_katz_centrality() == {}
+"#,
+    );
+}
+
+#[test]
+fn eigenvector_centrality_on_stars_cycles_and_chains() {
+    common::run_with(
+        SETUP,
+        r#"
+both = build([("h", x) for x in "abcd"] + [(x, "h") for x in "abcd"])
+assert close(both.calculate_eigenvector_centrality(), {"h": 1 / math.sqrt(2), **{x: 1 / math.sqrt(8) for x in "abcd"}})
+assert close(build([("a", "b"), ("b", "a")]).calculate_eigenvector_centrality(), {"a": 1 / math.sqrt(2), "b": 1 / math.sqrt(2)})
+# On a DAG the mass drains to the sinks, slowly.
+ranks = chain().calculate_eigenvector_centrality(max_iter=1000)
+assert sorted(ranks, key=ranks.get) == ["a", "b", "c", "d"] and ranks["d"] > 0.9999
+hub = in_star().calculate_eigenvector_centrality(max_iter=1000)
+assert hub["h"] > 0.9999 and all(hub[x] == hub["a"] for x in "bcd")
+assert rc.GraphWalker().calculate_eigenvector_centrality() == {}
+"#,
+    );
+}
+
+#[test]
+fn failures_raise_descriptive_errors() {
+    common::run_with(
+        SETUP,
+        r#"
+assert issubclass(rc.ConvergenceError, RuntimeError)
+cycle = build([("a", "b"), ("b", "a")])
+cases = [
+    (lambda: chain().calculate_eigenvector_centrality(), rc.ConvergenceError,
+     "eigenvector centrality did not converge within 100 iterations"),
+    (lambda: chain().calculate_katz_centrality(max_iter=2), rc.ConvergenceError,
+     "Katz centrality did not converge within 2 iterations"),
+    # The two-cycle has spectral radius 1.
+    (lambda: cycle.calculate_katz_centrality(alpha=1.5), ValueError,
+     "Katz centrality diverges for alpha=1.5; alpha must be below 1 / spectral radius"),
+]
+for call, error, message in cases:
+    try:
+        call()
+    except error as e:
+        assert str(e) == message, str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+assert close(cycle.calculate_katz_centrality(alpha=0.9), {"a": 1 / math.sqrt(2), "b": 1 / math.sqrt(2)})
+"#,
+    );
+}
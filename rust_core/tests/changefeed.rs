@@ -0,0 +1,142 @@
+//! Changefeed files: rotation into indexed segments, reading from a sequence
+//! number across them, resuming after a restart, and consumers and
+//! `propagate_invalidation` writing to one.
+
+mod common;
+
+const SETUP: &str = r#"
+import os, tempfile
+
+def feed():
+    """A fresh changefeed path in its own directory, so segments are easy to list."""
+    return os.path.join(tempfile.mkdtemp(), "feed.jsonl")
+
+def lines(path):
+    with open(path) as f:
+        return [json.loads(line) for line in f if line.strip()]
+
+broker = rc.MemoryBroker()
+"#;
+
+#[test]
+fn rotation_splits_the_feed_into_indexed_segments() {
+    common::run_with(
+        SETUP,
+        r#"
+path = feed()
+seqs = [rc.append_changefeed(path, "s%d" % i, ["b%d" % i, "a%d" % i], max_bytes=200) for i in range(10)]
+assert seqs == list(range(1, 11))
+index = lines(path + ".index")
+assert len(index) >= 3, index
+# Segments are contiguous, each within max_bytes, and named after their first seq.
+expected_first = 1
+for record in index:
+    assert record["first_seq"] == expected_first and record["last_seq"] >= record["first_seq"]
+    assert record["file"] == "feed.jsonl.%d" % record["first_seq"]
+    segment = os.path.join(os.path.dirname(path), record["file"])
+    assert os.path.getsize(segment) <= 200
+    assert [e["seq"] for e in lines(segment)] == list(range(record["first_seq"], record["last_seq"] + 1))
+    expected_first = record["last_seq"] + 1
+assert [e["seq"] for e in lines(path)] == list(range(expected_first, 11))
+
+entries = rc.read_changefeed(path)
+assert [e["seq"] for e in entries] == seqs
+assert entries[3]["start_id"] == "s3" and entries[3]["affected"] == ["a3", "b3"]
+assert set(entries[0]) == {"seq", "ts", "start_id", "affected"}
+assert all(isinstance(e["ts"], float) for e in entries)
+
+# The policy sticks to the path: later appends keep rotating.
+rc.append_changefeed(path, "later", [])
+rc.append_changefeed(path, "later", [])
+assert os.path.getsize(path) <= 200
+"#,
+    );
+}
+
+#[test]
+fn reads_resume_after_a_sequence_number() {
+    common::run_with(
+        SETUP,
+        r#"
+path = feed()
+for i in range(8):
+    rc.append_changefeed(path, "s%d" % i, [], max_bytes=150)
+for since in range(0, 10):
+    assert [e["seq"] for e in rc.read_changefeed(path, since_seq=since)] == list(range(since + 1, 9))
+# A reader that polls with its last seq sees each new entry once.
+seen, last = [], 8
+for i in range(5):
+    rc.append_changefeed(path, "more", [])
+    new = rc.read_changefeed(path, since_seq=last)
+    seen.extend(e["seq"] for e in new)
+    last = new[-1]["seq"]
+assert seen == list(range(9, 14))
+assert rc.read_changefeed(feed()) == []
+"#,
+    );
+}
+
+#[test]
+fn a_restarted_writer_continues_the_sequence() {
+    common::run_with(
+        SETUP,
+        r#"
+# A feed left by an earlier process: one rotated segment and an active file.
+path = feed()
+directory = os.path.dirname(path)
+with open(os.path.join(directory, "feed.jsonl.1"), "w") as f:
+    for seq in (1, 2):
+        f.write(json.dumps({"seq": seq, "ts": 1.0, "start_id": "old", "affected": []}) + "\n")
+with open(path + ".index", "w") as f:
+    f.write(json.dumps({"first_seq": 1, "last_seq": 2, "file": "feed.jsonl.1"}) + "\n")
+with open(path, "w") as f:
+    f.write(json.dumps({"seq": 3, "ts": 1.0, "start_id": "old", "affected": []}) + "\n")
+assert rc.append_changefeed(path, "new", ["x"]) == 4
+assert [(e["seq"], e["start_id"]) for e in rc.read_changefeed(path)] == [(1, "old"), (2, "old"), (3, "old"), (4, "new")]
+
+# A crash between writing the index and renaming the active file leaves its
+# entries in both; they are read once.
+path = feed()
+with open(path, "w") as f:
+    f.write(json.dumps({"seq": 1, "ts": 1.0, "start_id": "a", "affected": []}) + "\n")
+with open(path + ".index", "w") as f:
+    f.write(json.dumps({"first_seq": 1, "last_seq": 1, "file": "feed.jsonl.1"}) + "\n")
+with open(path + ".1", "w") as f:
+    f.write(json.dumps({"seq": 1, "ts": 1.0, "start_id": "a", "affected": []}) + "\n")
+assert [e["seq"] for e in rc.read_changefeed(path)] == [1]
+assert rc.append_changefeed(path, "b", []) == 2
+"#,
+    );
+}
+
+#[test]
+fn propagations_and_consumers_write_to_the_feed() {
+    common::run_with(
+        SETUP,
+        r#"
+path = feed()
+w = rc.GraphWalker()
+w.add_node("a", ["b", "c"])
+w.add_node("b", ["d"])
+assert w.propagate_invalidation("a", changefeed=path) == {"b", "c", "d"}
+assert w.propagate_invalidation("b") == {"d"}, "without changefeed nothing is written"
+assert [(e["start_id"], e["affected"]) for e in rc.read_changefeed(path)] == [("a", ["b", "c", "d"])]
+
+shared = rc.SharedGraphWalker()
+handle = rc.start_kafka_consumer(broker, "facts", walker=shared, changefeed=path)
+try:
+    broker.send("facts", json.dumps({"fact_id": "x", "dependents": ["y", "z"]}))
+    broker.send("facts", json.dumps({"fact_id": "y", "dependents": ["w"]}))
+    for start in ("x", "unknown"):
+        broker.send("facts", json.dumps({"fact_id": start, "op": "invalidate"}))
+    wait_until(lambda: handle.status()["messages"] == 4)
+finally:
+    handle.stop()
+entries = rc.read_changefeed(path, since_seq=1)
+assert [(e["seq"], e["start_id"], e["affected"]) for e in entries] == [
+    (2, "x", ["w", "y", "z"]),
+    (3, "unknown", []),
+]
+"#,
+    );
+}
@@ -0,0 +1,80 @@
+//! `find_cliques` and `max_clique_size`: two triangles sharing an edge, agreement
+//! with brute force on random graphs, the `max_cliques` limit, and a long sparse
+//! chain that must not recurse per node.
+
+mod common;
+
+const SETUP: &str = r#"
+import itertools, random
+
+def build(edges, nodes=()):
+    w = rc.GraphWalker()
+    for id in nodes:
+        w.add_node(id, [])
+    for a, b in edges:
+        w.add_edge(a, b)
+    return w
+
+def brute(w):
+    ids = w.node_ids()
+    linked = {(v, d) for v in ids for d in w.get_dependents(v) if w.has_node(d) and d != v}
+    linked |= {(d, v) for v, d in linked}
+    cliques = [set(c) for k in range(1, len(ids) + 1) for c in itertools.combinations(ids, k)
+               if all((x, y) in linked for x, y in itertools.combinations(c, 2))]
+    maximal = [sorted(c) for c in cliques if not any(c < other for other in cliques)]
+    return sorted(maximal, key=lambda c: (-len(c), c))
+"#;
+
+#[test]
+fn overlapping_triangles_give_two_cliques() {
+    common::run_with(
+        SETUP,
+        r#"
+w = build([("a", "b"), ("b", "c"), ("a", "c"), ("b", "d"), ("c", "d")], nodes=["d", "lone"])
+assert w.find_cliques() == [["a", "b", "c"], ["b", "c", "d"], ["lone"]]
+assert w.max_clique_size() == 3
+# Directions, reverse and parallel edges and self-loops do not matter.
+w.add_edge("c", "b")
+w.add_edge("a", "a")
+w.add_edge("d", "b")
+assert w.find_cliques() == [["a", "b", "c"], ["b", "c", "d"], ["lone"]]
+w.add_edge("a", "d")
+assert w.find_cliques() == [["a", "b", "c", "d"], ["lone"]] and w.max_clique_size() == 4
+assert rc.GraphWalker().find_cliques() == [] and rc.GraphWalker().max_clique_size() == 0
+
+for seed in range(5):
+    r = random.Random(seed)
+    g = build([("n%d" % r.randrange(12), "n%d" % r.randrange(12)) for _ in range(30)],
+              nodes=["n%d" % i for i in range(12)])
+    expected = brute(g)
+    assert g.find_cliques() == expected, seed
+    assert g.max_clique_size() == len(expected[0])
+"#,
+    );
+}
+
+#[test]
+fn the_limit_stops_enumeration() {
+    common::run_with(
+        SETUP,
+        r#"
+w = build([("a", "b"), ("b", "c"), ("a", "c"), ("b", "d"), ("c", "d")], nodes=["d", "lone"])
+assert w.find_cliques(max_cliques=3) == w.find_cliques()
+for limit in (0, 2):
+    try:
+        w.find_cliques(max_cliques=limit)
+    except rc.ResourceLimitError as e:
+        assert e.args == ("max_cliques limit of %d exceeded" % limit, limit, limit), e.args
+    else:
+        raise AssertionError("no ResourceLimitError for %d" % limit)
+
+# 50k nodes in a chain: every edge is a maximal clique, and nothing overflows.
+n = 50000
+chain = rc.GraphWalker()
+chain.add_nodes_bulk([("n%06d" % i, ["n%06d" % (i + 1)]) for i in range(n - 1)] + [("n%06d" % (n - 1), [])])
+cliques = chain.find_cliques()
+assert len(cliques) == n - 1 and cliques[0] == ["n000000", "n000001"]
+assert chain.max_clique_size() == 2
+"#,
+    );
+}
@@ -0,0 +1,77 @@
+//! `calculate_closeness`: exact Wasserman–Faust scores on a directed path and on a
+//! disconnected graph, nodes that reach nothing scoring zero, and agreement with a
+//! Python BFS on random graphs.
+
+mod common;
+
+const SETUP: &str = r#"
+import random
+from collections import deque
+
+def reference(w):
+    ids = w.node_ids()
+    n = len(ids)
+    scores = {}
+    for s in ids:
+        dist, queue = {s: 0}, deque([s])
+        while queue:
+            v = queue.popleft()
+            for x in w.get_dependents(v):
+                if w.has_node(x) and x not in dist:
+                    dist[x] = dist[v] + 1
+                    queue.append(x)
+        reached, total = len(dist) - 1, sum(dist.values())
+        scores[s] = (reached / (n - 1)) * (reached / total) if reached else 0.0
+    return scores
+
+def close(a, b):
+    assert a.keys() == b.keys(), (a, b)
+    for k in a:
+        assert abs(a[k] - b[k]) < 1e-12, (k, a[k], b[k])
+"#;
+
+#[test]
+fn a_path_ranks_its_head_highest() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+for a, b in [("a", "b"), ("b", "c"), ("c", "d")]:
+    w.add_node(a, [b])
+w.add_node("d", [])
+scores = w.calculate_closeness()
+# a reaches all 3 at total distance 6; b reaches 2 of 3 at 3; c 1 of 3 at 1.
+close(scores, {"a": 3 / 3 * (3 / 6), "b": 2 / 3 * (2 / 3), "c": 1 / 3 * (1 / 1), "d": 0.0})
+assert scores["a"] == max(scores.values()) and scores["d"] == 0.0
+assert scores["a"] > scores["b"] > scores["c"] > scores["d"]
+"#,
+    );
+}
+
+#[test]
+fn disconnected_graphs_are_scaled_by_reach() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b"])
+w.add_node("b", [])
+w.add_node("x", ["y", "z"])
+w.add_node("y", [])
+w.add_node("z", [])
+w.add_node("lone", [])
+w.add_node("g", ["ghost"])
+# Seven nodes; "ghost" is only an edge target and is neither scored nor reached.
+scores = w.calculate_closeness()
+close(scores, {"a": 1 / 6, "b": 0.0, "x": 2 / 6, "y": 0.0, "z": 0.0, "lone": 0.0, "g": 0.0})
+assert rc.GraphWalker().calculate_closeness() == {}
+
+for seed in range(5):
+    r = random.Random(seed)
+    g = rc.GraphWalker()
+    for i in range(40):
+        g.add_node("n%d" % i, ["n%d" % r.randrange(45) for _ in range(r.randrange(3))])
+    close(g.calculate_closeness(), reference(g))
+"#,
+    );
+}
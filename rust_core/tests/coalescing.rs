@@ -0,0 +1,93 @@
+//! Coalesced computations: identical `calculate_pagerank` calls from concurrent
+//! Python threads running one computation (counted in PageRank iterations),
+//! `inflight_computations` while it runs, and `coalesce=False` opting out.
+
+mod common;
+
+const SETUP: &str = r#"
+import random, threading
+
+def graph(n=20000):
+    r = random.Random(1)
+    w = rc.GraphWalker()
+    for i in range(n):
+        w.add_node("n%d" % i, ["n%d" % r.randrange(n) for _ in range(5)])
+    return w
+
+def concurrently(count, call):
+    results, start = [], threading.Barrier(count)
+    def worker():
+        start.wait()
+        results.append(call())
+    threads = [threading.Thread(target=worker) for _ in range(count)]
+    for t in threads:
+        t.start()
+    for t in threads:
+        t.join()
+    assert len(results) == count
+    return results
+"#;
+
+#[test]
+fn identical_concurrent_calls_share_one_run() {
+    common::run_serial(
+        SETUP,
+        r#"
+rc.configure({"coalesce": True})
+w = graph()
+before = w.computation_stats()
+assert before["pagerank_iterations"] == 0 and w.inflight_computations() == []
+
+watched = set()
+def watch():
+    deadline = time.monotonic() + 5
+    while not watched and time.monotonic() < deadline:
+        watched.update(key for key in w.inflight_computations() if key.startswith("pagerank:"))
+watcher = threading.Thread(target=watch)
+watcher.start()
+results = concurrently(8, lambda: w.calculate_pagerank(100, 0.85))
+watcher.join()
+assert all(r == results[0] for r in results)
+deterministic = "true" if rc.get_config()["deterministic"] else "false"
+assert watched == {"pagerank:100:0.85:None:%s@%d" % (deterministic, w.version())}, watched
+assert w.inflight_computations() == []
+
+stats = w.computation_stats()
+assert stats["pagerank_iterations"] == 100, stats
+assert stats["coalesced"] + stats["hits"] - before["coalesced"] - before["hits"] == 7, stats
+assert stats["coalesced"] >= 1, stats
+
+# Different parameters and a new version are separate computations.
+concurrently(4, lambda: w.calculate_pagerank(50, 0.85))
+assert w.computation_stats()["pagerank_iterations"] == 150
+w.add_edge("n0", "n1")
+concurrently(4, lambda: w.calculate_pagerank(50, 0.85))
+assert w.computation_stats()["pagerank_iterations"] == 200
+"#,
+    );
+}
+
+#[test]
+fn coalescing_can_be_turned_off() {
+    common::run_serial(
+        SETUP,
+        r#"
+w = graph(5000)
+# Warm the shared adjacency so that only PageRank itself is computed below.
+w.calculate_pagerank(1, 0.85)
+rc.configure({"coalesce": False})
+try:
+    before = w.computation_stats()
+    results = concurrently(4, lambda: w.calculate_pagerank(40, 0.85))
+    stats = w.computation_stats()
+    runs = stats["computed"] - before["computed"]
+    # Callers that missed the cache each ran their own 40 iterations; none waited.
+    assert stats["coalesced"] == before["coalesced"], stats
+    assert stats["pagerank_iterations"] - before["pagerank_iterations"] == 40 * runs, stats
+    assert 2 <= runs <= 4, stats
+    assert all(r == results[0] for r in results)
+finally:
+    rc.configure({"coalesce": True})
+"#,
+    );
+}
@@ -0,0 +1,134 @@
+//! `parallel_cold_start` from a `MemoryBroker`, compared with replaying the
+//! topic into a walker one message at a time.
+
+mod common;
+
+const SETUP: &str = r#"
+import random
+
+PARTITIONS = 4
+broker = rc.MemoryBroker()
+rng = random.Random(7)
+sent = []
+
+def send(key, state, partition, timestamp):
+    payload = None if state is None else json.dumps(state)
+    offset = broker.send("facts", payload, key=key, partition=partition, timestamp=timestamp)
+    sent.append((key, state, partition, offset, timestamp))
+
+for t in range(2000):
+    key = "k%03d" % rng.randrange(300)
+    partition = sum(map(ord, key)) % PARTITIONS
+    if rng.random() < 0.1:
+        send(key, None, partition, t)
+    else:
+        deps = ["k%03d" % rng.randrange(300) for _ in range(rng.randrange(4))]
+        send(key, {"dependents": deps, "attributes": {"t": str(t)}}, partition, t)
+# Repartitioned keys: the later timestamp wins whatever the partition.
+send("moved", {"dependents": ["old"]}, 0, 5000)
+send("moved", {"dependents": ["new"]}, 3, 5001)
+send("back", {"dependents": ["new"]}, 3, 5002)
+send("back", {"dependents": ["old"]}, 1, 5003)
+
+def replay():
+    """The walker a single reader applying every message in order would build."""
+    latest = {}
+    for key, state, partition, offset, timestamp in sent:
+        held = latest.get(key)
+        if held is None or (held[1] == partition) or (timestamp, partition) > (held[2], held[1]):
+            latest[key] = (state, partition, timestamp)
+    w = rc.GraphWalker()
+    for key in sorted(latest):
+        state = latest[key][0]
+        if state is not None:
+            w.add_node(key, state.get("dependents", []), attributes=state.get("attributes"))
+    return w
+
+def contents(w):
+    return {id: (w.get_dependents(id), w.get_attributes(id)) for id in w.node_ids()}
+"#;
+
+#[test]
+fn parallel_loads_match_a_single_threaded_replay() {
+    common::run_with(
+        SETUP,
+        r#"
+expected = contents(replay())
+assert expected["moved"][0] == ["new"] and expected["back"][0] == ["old"]
+for workers in (1, 2, 4, 8):
+    w, ends = rc.GraphWalker.parallel_cold_start(broker, "facts", workers=workers)
+    assert contents(w) == expected, workers
+    assert ends == {p: sum(1 for m in sent if m[2] == p) for p in range(PARTITIONS)}, ends
+"#,
+    );
+}
+
+#[test]
+fn ids_are_normalized_once() {
+    common::run_with(
+        SETUP,
+        r#"
+calls = []
+def upper(id):
+    calls.append(id)
+    return id.upper()
+
+expected = contents(replay())
+ids = set(expected) | {d for deps, _ in expected.values() for d in deps}
+w, _ = rc.GraphWalker.parallel_cold_start(broker, "facts", workers=1, normalize=upper)
+assert sorted(calls) == sorted(ids), (len(calls), len(ids))
+# With several workers an id met by two of them is normalized by each, but
+# always from its original form.
+calls.clear()
+w, _ = rc.GraphWalker.parallel_cold_start(broker, "facts", workers=4, normalize=upper)
+assert set(calls) == ids
+assert set(w.node_ids()) == {id.upper() for id in expected}
+assert w.original_id("MOVED") == "moved"
+"#,
+    );
+}
+
+#[test]
+fn progress_reports_every_worker() {
+    common::run_with(
+        SETUP,
+        r#"
+reports = []
+rc.GraphWalker.parallel_cold_start(
+    broker, "facts", workers=2, progress=lambda read, total, workers: reports.append((read, total, workers)))
+read, total, workers = reports[-1]
+assert read == total == len(sent), (read, total)
+assert sorted(p for w in workers for p in w["partitions"]) == list(range(PARTITIONS))
+try:
+    rc.GraphWalker.parallel_cold_start(broker, "missing")
+except RuntimeError as e:
+    assert "topic not found" in str(e), e
+else:
+    raise AssertionError("no RuntimeError")
+"#,
+    );
+}
+
+#[test]
+fn from_compacted_topic_keeps_the_last_value_and_drops_tombstones() {
+    common::run_with(
+        SETUP,
+        r#"
+compacted = rc.MemoryBroker()
+compacted.send("facts", json.dumps({"dependents": ["b"]}), key="a")
+compacted.send("facts", json.dumps({"dependents": ["c"], "attributes": {"v": "2"}}), key="a")
+compacted.send("facts", json.dumps({"dependents": []}), key="gone")
+compacted.send("facts", None, key="gone")
+compacted.send("facts", json.dumps({"dependents": ["a"]}), key="x", partition=1)
+w, ends = rc.GraphWalker.from_compacted_topic(compacted, "facts")
+assert ends == {0: 4, 1: 1}, ends
+assert w.get_dependents("a") == ["c"] and w.get_attributes("a") == {"v": "2"}
+assert not w.has_node("gone")
+assert w.get_dependents("x") == ["a"]
+
+w, ends = rc.GraphWalker.from_compacted_topic(broker, "facts")
+assert contents(w) == contents(replay())
+assert ends == rc.GraphWalker.parallel_cold_start(broker, "facts", workers=4)[1]
+"#,
+    );
+}
@@ -0,0 +1,82 @@
+//! Runs Python snippets against `rust_core` in an embedded interpreter, so the
+//! tests exercise the module exactly as Python callers see it.
+
+// Each test binary uses its own subset of these helpers.
+#![allow(dead_code)]
+
+use concord_rust_core::rust_core;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::sync::{Mutex, Once};
+
+static INIT: Once = Once::new();
+
+fn init() {
+    INIT.call_once(|| {
+        pyo3::append_to_inittab!(rust_core);
+        pyo3::prepare_freethreaded_python();
+    });
+}
+
+/// Python helpers every snippet can use.
+const HELPERS: &str = r#"
+import json, time
+
+def wait_until(condition, timeout=5.0):
+    deadline = time.monotonic() + timeout
+    while not condition():
+        if time.monotonic() > deadline:
+            raise AssertionError("timed out waiting")
+        time.sleep(0.005)
+
+def read_topic(broker, topic, count, timeout=5.0):
+    """The JSON payloads of the first `count` messages on `topic`."""
+    seen = []
+    handle = rc.start_kafka_consumer(
+        broker, topic, on_message=lambda m: seen.append(json.loads(m["payload"]))
+    )
+    try:
+        wait_until(lambda: len(seen) >= count, timeout)
+    finally:
+        handle.stop()
+    return seen[:count]
+"#;
+
+/// Runs `code` with `rust_core` imported as `rc` and `HELPERS` defined, failing
+/// the test with the Python traceback if it raises.
+pub fn run(code: &str) {
+    init();
+    Python::with_gil(|py| {
+        let globals = PyDict::new(py);
+        let prelude = "import rust_core as rc\n";
+        let code = format!("{}{}{}", prelude, HELPERS, code);
+        if let Err(e) = py.run(&code, Some(globals), None) {
+            e.print(py);
+            panic!("Python raised {}", e);
+        }
+    });
+}
+
+/// Runs the file's `setup` snippet and then `code`, in one namespace.
+pub fn run_with(setup: &str, code: &str) {
+    run(&format!("{}{}", setup, code));
+}
+
+/// Process-wide settings such as `set_default_limits` are shared by every test in
+/// a binary, so the snippets changing them take turns.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// `run_with`, one snippet at a time among the callers of `run_serial`.
+pub fn run_serial(setup: &str, code: &str) {
+    let _turn = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    run_with(setup, code);
+}
+
+/// Calls `f` with the GIL held and `rust_core` imported.
+pub fn with_rc<R>(f: impl FnOnce(Python, &PyModule) -> R) -> R {
+    init();
+    Python::with_gil(|py| {
+        let rc = py.import("rust_core").expect("rust_core imports");
+        f(py, rc)
+    })
+}
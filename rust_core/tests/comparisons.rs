@@ -0,0 +1,87 @@
+//! `propagate_and_compare`: exact deltas between runs from the same node as the
+//! graph changes, and the baseline resets caused by eviction, staleness and
+//! `clear_comparisons`.
+
+mod common;
+
+const SETUP: &str = r#"
+def graph():
+    w = rc.GraphWalker()
+    w.add_node("a", ["b"])
+    w.add_node("b", ["c"])
+    w.add_node("c", [])
+    return w
+
+def delta(result):
+    return (result["added"], result["removed"], result["unchanged"], result["baseline_reset"])
+"#;
+
+#[test]
+fn deltas_follow_the_mutations_between_runs() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+first = w.propagate_and_compare("a", include_current=True)
+assert delta(first) == ([], [], 0, True), first
+assert first["previous_version"] is None and first["current"] == ["b", "c"]
+
+w.add_edge("b", "d")
+w.add_edge("d", "e")
+w.remove_edge("b", "c")
+second = w.propagate_and_compare("a", include_current=True)
+assert delta(second) == (["d", "e"], ["c"], 1, False), second
+assert second["previous_version"] == first["version"] and second["version"] == w.version()
+assert second["current"] == ["b", "d", "e"]
+assert "current" not in w.propagate_and_compare("a")
+
+# No change: everything is unchanged.
+assert delta(w.propagate_and_compare("a")) == ([], [], 3, False)
+
+# Each start node keeps its own baseline.
+assert delta(w.propagate_and_compare("d")) == ([], [], 0, True)
+w.add_node("a", ["c"])
+w.add_edge("e", "f")
+assert delta(w.propagate_and_compare("a")) == (["c"], ["b", "d", "e"], 0, False)
+assert delta(w.propagate_and_compare("d")) == (["f"], [], 1, False)
+
+try:
+    w.propagate_and_compare("missing")
+except rc.NodeNotFoundError:
+    pass
+else:
+    raise AssertionError("no NodeNotFoundError")
+"#,
+    );
+}
+
+#[test]
+fn evicted_stale_and_cleared_baselines_reset() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+w.configure_comparisons(capacity=2)
+for start in ("a", "b", "c"):
+    w.propagate_and_compare(start)
+# "a" was the least recently used of three.
+assert delta(w.propagate_and_compare("c")) == ([], [], 0, False)
+assert delta(w.propagate_and_compare("a")) == ([], [], 0, True)
+assert delta(w.propagate_and_compare("c")) == ([], [], 0, False)
+assert delta(w.propagate_and_compare("b")) == ([], [], 0, True)
+
+w.configure_comparisons(capacity=2, max_staleness=1)
+w.propagate_and_compare("a")
+w.add_edge("c", "x")
+assert delta(w.propagate_and_compare("a")) == (["x"], [], 2, False)
+w.add_edge("x", "y")
+w.add_edge("y", "z")
+stale = w.propagate_and_compare("a")
+assert delta(stale) == ([], [], 0, True) and stale["previous_version"] is None, stale
+assert delta(w.propagate_and_compare("a")) == ([], [], 5, False)
+
+w.clear_comparisons()
+assert delta(w.propagate_and_compare("a")) == ([], [], 0, True)
+"#,
+    );
+}
@@ -0,0 +1,198 @@
+//! Incrementally maintained weakly connected components, checked against a
+//! from-scratch computation after every query of a random mutation sequence.
+
+mod common;
+
+use proptest::prelude::*;
+use proptest::test_runner::{Config, RngAlgorithm, TestRng, TestRunner};
+use pyo3::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone, Debug)]
+enum Op {
+    AddNode(String, Vec<String>),
+    AddEdge(String, String),
+    RemoveNode(String),
+    RemoveEdge(String, String),
+    TombstoneNode(String),
+    DecayWeights,
+    PruneEdgesBelow,
+    SplitNode(String, Vec<String>),
+    Query,
+}
+
+fn id() -> impl Strategy<Value = String> {
+    (0..10u8).prop_map(|i| format!("n{}", i))
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let ids = || prop::collection::vec(id(), 0..4);
+    prop_oneof![
+        4 => (id(), ids()).prop_map(|(a, d)| Op::AddNode(a, d)),
+        5 => (id(), id()).prop_map(|(a, b)| Op::AddEdge(a, b)),
+        2 => id().prop_map(Op::RemoveNode),
+        2 => (id(), id()).prop_map(|(a, b)| Op::RemoveEdge(a, b)),
+        1 => id().prop_map(Op::TombstoneNode),
+        1 => Just(Op::DecayWeights),
+        1 => Just(Op::PruneEdgesBelow),
+        1 => (id(), ids()).prop_map(|(a, o)| Op::SplitNode(a, o)),
+        4 => Just(Op::Query),
+    ]
+}
+
+/// Components of the current graph as the walker stores it, from scratch: nodes
+/// joined by edges between two nodes, in `weakly_connected_components` order.
+fn from_scratch(w: &PyAny) -> Vec<Vec<String>> {
+    let ids: Vec<String> = w.call_method0("node_ids").unwrap().extract().unwrap();
+    let index: BTreeMap<&str, usize> = ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (id.as_str(), i))
+        .collect();
+    let mut parent: Vec<usize> = (0..ids.len()).collect();
+    fn find(parent: &mut [usize], mut x: usize) -> usize {
+        while parent[x] != x {
+            x = parent[x];
+        }
+        x
+    }
+    for (i, id) in ids.iter().enumerate() {
+        let deps: Vec<String> = w
+            .call_method1("get_dependents", (id,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        for dep in deps {
+            if let Some(&j) = index.get(dep.as_str()) {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+    let mut groups: BTreeMap<usize, BTreeSet<String>> = BTreeMap::new();
+    for (i, id) in ids.iter().enumerate() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().insert(id.clone());
+    }
+    let mut out: Vec<Vec<String>> = groups
+        .into_values()
+        .map(|g| g.into_iter().collect())
+        .collect();
+    out.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].cmp(&b[0])));
+    out
+}
+
+fn counters(w: &PyAny) -> (u64, u64) {
+    let stats: BTreeMap<String, u64> = w
+        .call_method0("computation_stats")
+        .unwrap()
+        .extract()
+        .unwrap();
+    (stats["components_incremental"], stats["components_full"])
+}
+
+fn stale(w: &PyAny) -> bool {
+    w.call_method0("components_stale")
+        .unwrap()
+        .extract()
+        .unwrap()
+}
+
+/// Applies `op`, returning whether the graph changed in a way the tracker can only
+/// follow by recomputing everything.
+fn apply(w: &PyAny, op: &Op) -> bool {
+    let result = match op {
+        Op::AddNode(a, d) => w.call_method1("add_node", (a, d.clone())),
+        Op::AddEdge(a, b) => w.call_method1("add_edge", (a, b)),
+        Op::RemoveNode(a) => w.call_method1("remove_node", (a,)),
+        Op::RemoveEdge(a, b) => w.call_method1("remove_edge", (a, b)),
+        Op::TombstoneNode(a) => w.call_method1("tombstone_node", (a,)),
+        Op::DecayWeights => w.call_method1("decay_weights", (0.5,)),
+        Op::PruneEdgesBelow => w.call_method1("prune_edges_below", (0.3,)),
+        Op::SplitNode(a, o) => {
+            let parts = vec![(format!("{}s", a), Vec::<String>::new(), o.clone())];
+            w.call_method1("split_node", (a, parts))
+        }
+        Op::Query => unreachable!(),
+    };
+    // Rejected calls (unknown ids, bad split parts) leave the graph as it was.
+    result.is_ok() && matches!(op, Op::PruneEdgesBelow | Op::SplitNode(..))
+}
+
+fn run_sequence(ops: &[Op]) {
+    common::with_rc(|_, rc| {
+        let w = rc.getattr("GraphWalker").unwrap().call0().unwrap();
+        assert!(stale(w), "a walker never queried is stale");
+        let mut needs_full = true;
+        for op in ops {
+            if !matches!(op, Op::Query) {
+                needs_full |= apply(w, op);
+                continue;
+            }
+            let was_stale = stale(w);
+            let before = counters(w);
+            let components: Vec<Vec<String>> = w
+                .call_method0("weakly_connected_components")
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(components, from_scratch(w), "after {:?}", ops);
+            assert!(!stale(w));
+            let (incremental, full) = counters(w);
+            let syncs = (incremental - before.0) + (full - before.1);
+            assert_eq!(syncs, u64::from(was_stale), "one sync per stale query");
+            if needs_full {
+                assert_eq!(full, before.1 + 1, "after {:?}", ops);
+            }
+            needs_full = false;
+        }
+    });
+}
+
+#[test]
+fn incremental_components_match_a_full_recomputation() {
+    let config = Config {
+        cases: 256,
+        failure_persistence: None,
+        ..Config::default()
+    };
+    let rng = TestRng::from_seed(RngAlgorithm::ChaCha, &[11; 32]);
+    let mut runner = TestRunner::new_with_rng(config, rng);
+    // Every sequence ends with a query, so its last mutations are checked too.
+    let strategy = prop::collection::vec(op(), 1..60).prop_map(|mut ops| {
+        ops.push(Op::Query);
+        ops
+    });
+    runner
+        .run(&strategy, |ops| {
+            run_sequence(&ops);
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn edge_additions_are_applied_incrementally() {
+    common::run(
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b"])
+w.add_node("b", [])
+w.add_node("c", [])
+assert w.weakly_connected_components() == [["a", "b"], ["c"]]
+assert not w.components_stale()
+w.add_edge("c", "a")
+assert w.components_stale()
+assert w.weakly_connected_components() == [["a", "b", "c"]]
+w.remove_edge("c", "a")
+assert w.weakly_connected_components() == [["a", "b"], ["c"]]
+stats = w.computation_stats()
+assert (stats["components_full"], stats["components_incremental"]) == (1, 2), stats
+w.decay_weights(0.5)
+assert not w.components_stale()
+w.prune_edges_below(0.75)
+assert w.weakly_connected_components() == [["a"], ["b"], ["c"]]
+assert w.computation_stats()["components_full"] == 2
+"#,
+    );
+}
@@ -0,0 +1,118 @@
+//! `configure` / `get_config`: options round-trip, the dedicated pool has exactly
+//! the configured number of `concord-worker-*` threads, and rejected options change
+//! nothing. Configuration is process-wide, so every snippet runs serially and puts
+//! the defaults back.
+
+mod common;
+
+const SETUP: &str = r#"
+import os
+
+DEFAULTS = {
+    "threads": None,
+    "deterministic": False,
+    "coalesce": True,
+    "limits": {"max_results": None, "max_visited": None},
+    "log_level": "info",
+}
+
+def workers():
+    # Linux truncates thread names to 15 bytes: "concord-worker-".
+    count = 0
+    for task in os.listdir("/proc/self/task"):
+        try:
+            with open("/proc/self/task/%s/comm" % task) as f:
+                count += f.read().startswith("concord-worker")
+        except FileNotFoundError:
+            pass
+    return count
+
+def options():
+    config = rc.get_config()
+    del config["effective_threads"]
+    return config
+
+def raises(error, call, fragment):
+    try:
+        call()
+    except error as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+"#;
+
+#[test]
+fn options_round_trip() {
+    common::run_serial(
+        SETUP,
+        r#"
+assert options() == DEFAULTS, options()
+wanted = {
+    "threads": 2,
+    "deterministic": True,
+    "coalesce": False,
+    "limits": {"max_results": 10, "max_visited": 1000},
+    "log_level": "warn",
+}
+rc.configure(wanted)
+assert options() == wanted, options()
+assert rc.get_default_limits() == wanted["limits"]
+# get_config's output (without effective_threads) is valid configure input.
+rc.configure(options())
+assert options() == wanted
+# Keys left out are unchanged.
+rc.configure({"log_level": "debug"})
+assert options() == {**wanted, "log_level": "debug"}
+rc.configure(DEFAULTS)
+assert options() == DEFAULTS
+"#,
+    );
+}
+
+#[test]
+fn the_pool_has_the_configured_thread_count() {
+    common::run_serial(
+        SETUP,
+        r#"
+for n in (3, 1, 2):
+    rc.configure({"threads": n})
+    assert rc.get_config()["effective_threads"] == n
+    # A replaced pool's threads exit once it is dropped.
+    wait_until(lambda: workers() == n)
+    w = rc.GraphWalker()
+    for i in range(200):
+        w.add_node("n%d" % i, ["n%d" % ((i * 7 + 1) % 200)])
+    # Parallel calls run on that pool rather than starting threads of their own.
+    assert len(w.impact_scores()) == 200
+    assert workers() == n
+
+rc.configure({"threads": None})
+assert rc.get_config()["threads"] is None
+wait_until(lambda: workers() == 0)
+"#,
+    );
+}
+
+#[test]
+fn rejected_options_change_nothing() {
+    common::run_serial(
+        SETUP,
+        r#"
+rc.configure({"threads": 2, "log_level": "warn"})
+before = options()
+bad = [
+    (ValueError, {"deterministic": True, "threads": 0}, "threads must be positive"),
+    (ValueError, {"deterministic": True, "colour": "red"}, "unknown option 'colour'"),
+    (ValueError, {"coalesce": False, "log_level": "loud"}, "unknown log level 'loud'"),
+    (ValueError, {"threads": 4, "limits": {"bogus": 1}}, "unknown limit 'bogus'"),
+    (OverflowError, {"threads": 4, "limits": {"max_results": -1}}, ""),
+    (TypeError, {"threads": "four"}, ""),
+]
+for error, option, fragment in bad:
+    raises(error, lambda: rc.configure(option), fragment)
+    assert options() == before, (option, options(), before)
+wait_until(lambda: workers() == 2)
+rc.configure(DEFAULTS)
+"#,
+    );
+}
@@ -0,0 +1,177 @@
+//! `start_kafka_consumer` on a `MemoryBroker`: applying messages to a shared
+//! walker, Python callbacks and their routing by topic, and at-least-once commits.
+
+mod common;
+
+const SETUP: &str = r#"
+broker = rc.MemoryBroker()
+
+def fact(id, dependents=(), op="add"):
+    return json.dumps({"fact_id": id, "dependents": list(dependents), "op": op})
+"#;
+
+#[test]
+fn a_consumer_grows_the_walker_and_logs_invalidations() {
+    common::run_with(
+        SETUP,
+        r#"
+shared = rc.SharedGraphWalker(log_size=2)
+handle = rc.start_kafka_consumer(broker, "facts", walker=shared)
+try:
+    broker.send("facts", fact("a", ["b"]))
+    wait_until(lambda: shared.node_count() == 1)
+    broker.send("facts", fact("b", ["c"]))
+    wait_until(lambda: shared.node_count() == 2)
+    assert shared.get_dependents("a") == ["b"]
+    for start in ("a", "b", "unknown"):
+        broker.send("facts", fact(start, op="invalidate"))
+    wait_until(lambda: handle.status()["messages"] == 5)
+finally:
+    handle.stop()
+log = shared.recent_invalidations()
+assert [(e["fact_id"], e["invalidated"]) for e in log] == [("b", ["c"]), ("unknown", [])], log
+assert log[0]["topic"] == "facts" and log[0]["offset"] == 3, log[0]
+"#,
+    );
+}
+
+#[test]
+fn malformed_messages_are_counted_and_skipped() {
+    common::run_with(
+        SETUP,
+        r#"
+shared = rc.SharedGraphWalker()
+for payload in ["not json", json.dumps({"op": "add"}), json.dumps({"fact_id": "a", "op": "explode"})]:
+    broker.send("facts", payload)
+broker.send("facts", fact("ok", ["x"]))
+handle = rc.start_kafka_consumer(broker, "facts", walker=shared)
+wait_until(lambda: shared.has_node("ok"))
+handle.stop()
+status = handle.status()
+assert status["malformed"] == 3 and status["messages"] == 4, status
+assert status["state"] == "stopped", status
+assert shared.node_count() == 1
+"#,
+    );
+}
+
+#[test]
+fn callbacks_see_each_message_and_survive_exceptions() {
+    common::run_with(
+        SETUP,
+        r#"
+seen, errors = [], []
+shared = rc.SharedGraphWalker()
+def on_message(message):
+    # Calling back into rust_core from the consumer thread must not deadlock.
+    seen.append((message, shared.node_count()))
+    if message["key"] == b"bad":
+        raise ValueError("rejected")
+
+broker.send("facts", b"one", key="k1", partition=2, timestamp=1234, headers={"trace": "t1", "empty": None})
+broker.send("facts", "two", key="bad")
+broker.send("facts", None, key="k3")
+handle = rc.start_kafka_consumer(
+    broker, "facts", on_message=on_message, on_error=lambda e, m: errors.append((e, m)))
+wait_until(lambda: len(seen) == 3)
+handle.stop()
+
+first = seen[0][0]
+assert (first["topic"], first["partition"], first["offset"]) == ("facts", 2, 0), first
+assert (first["key"], first["payload"], first["timestamp"]) == (b"k1", b"one", 1234), first
+assert sorted(first["headers"]) == [("empty", None), ("trace", b"t1")], first["headers"]
+assert seen[2][0]["payload"] is None and seen[2][0]["offset"] == 1
+assert len(errors) == 1, errors
+exception, message = errors[0]
+assert isinstance(exception, ValueError) and str(exception) == "rejected"
+assert message["key"] == b"bad"
+assert handle.status()["errors"] == 1 and handle.status()["state"] == "stopped"
+"#,
+    );
+}
+
+#[test]
+fn a_failed_message_holds_its_partition_until_a_restart_redelivers_it() {
+    common::run_with(
+        SETUP,
+        r#"
+config = rc.ConsumerConfig(group_id="sink", enable_auto_commit=False, auto_commit_after_callback=True)
+seen = []
+def failing(message):
+    seen.append((message["partition"], message["offset"]))
+    if message["payload"] == b"bad":
+        raise RuntimeError("not yet")
+
+for payload in ["ok", "bad", "after"]:
+    broker.send("facts", payload)
+for payload in ["x", "y"]:
+    broker.send("facts", payload, partition=1)
+handle = rc.start_kafka_consumer(broker, "facts", on_message=failing, config=config)
+wait_until(lambda: len(seen) == 5)
+# The second partition keeps committing after the first is held back.
+wait_until(lambda: broker.committed("facts", "sink") == {0: 1, 1: 2})
+handle.stop()
+assert broker.committed("facts") == {}, "other groups are unaffected"
+
+redelivered = []
+handle = rc.start_kafka_consumer(
+    broker, "facts", on_message=lambda m: redelivered.append((m["partition"], m["offset"])), config=config)
+wait_until(lambda: broker.committed("facts", "sink") == {0: 3, 1: 2})
+handle.stop()
+assert redelivered == [(0, 1), (0, 2)], redelivered
+"#,
+    );
+}
+
+#[test]
+fn manual_commits_stop_before_the_first_failure() {
+    common::run_with(
+        SETUP,
+        r#"
+config = rc.ConsumerConfig(group_id="sink", enable_auto_commit=False)
+shared = rc.SharedGraphWalker(rc.GraphWalker(enforce_dag=True))
+broker.send("facts", fact("a", ["b"]))
+broker.send("facts", fact("b", ["a"]))
+broker.send("facts", fact("c"))
+handle = rc.start_kafka_consumer(broker, "facts", walker=shared, config=config)
+wait_until(lambda: shared.has_node("c"))
+assert broker.committed("facts", "sink") == {}, "nothing is committed before commit()"
+# The rejected cycle holds the offset at the message that closed it.
+assert handle.commit() == {"facts": {0: 1}}
+assert handle.commit() == {}
+handle.stop()
+assert broker.committed("facts", "sink") == {0: 1}
+"#,
+    );
+}
+
+#[test]
+fn handlers_are_routed_by_topic() {
+    common::run_with(
+        SETUP,
+        r#"
+shared = rc.SharedGraphWalker()
+facts, audit = [], []
+handlers = {"facts": lambda m: facts.append(m["payload"]), "audit": lambda m: audit.append(m["topic"])}
+broker.send("facts", fact("a", ["b"]))
+broker.send("audit", fact("x"), partition=1)
+broker.send("other", fact("o"))
+broker.send("facts", fact("c"))
+handle = rc.start_kafka_consumer(broker, ["facts", "audit", "other"], walker=shared, on_message=handlers)
+wait_until(lambda: handle.status()["messages"] == 4)
+handle.stop()
+assert facts == [fact("a", ["b"]).encode(), fact("c").encode()], facts
+assert audit == ["audit"], audit
+# A topic without a handler is still applied to the walker.
+assert sorted(shared.snapshot().node_ids()) == ["a", "c", "o", "x"]
+assert handle.status()["topic"] == "facts,audit,other", handle.status()
+
+try:
+    rc.start_kafka_consumer(broker, "facts", on_message={"facts": print, "audit": print})
+except ValueError as e:
+    assert "'audit'" in str(e), e
+else:
+    raise AssertionError("no ValueError")
+"#,
+    );
+}
@@ -0,0 +1,91 @@
+//! `ConsumerConfig`: defaults that match the settings used before it existed,
+//! arguments and free-form properties in `resolved()`, invalid settings raising
+//! at construction, and two groups each receiving every message.
+
+mod common;
+
+const SETUP: &str = r#"
+DEFAULTS = {
+    "auto.offset.reset": "latest",
+    "enable.auto.commit": "true",
+    "enable.partition.eof": "false",
+    "group.id": "rust_causal_core",
+    "session.timeout.ms": "6000",
+    "statistics.interval.ms": "5000",
+}
+
+def invalid(kwargs, fragment):
+    try:
+        rc.ConsumerConfig(**kwargs)
+    except ValueError as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("accepted %r" % kwargs)
+"#;
+
+#[test]
+fn resolved_settings_follow_the_arguments() {
+    common::run_with(
+        SETUP,
+        r#"
+assert rc.ConsumerConfig().resolved() == DEFAULTS
+config = rc.ConsumerConfig(group_id="g2", auto_offset_reset="earliest", enable_auto_commit=False,
+                           session_timeout_ms=10000, max_poll_interval_ms=60000, statistics_interval_ms=0)
+# Zero turns the statistics report off, so its property is left out.
+assert config.resolved() == {
+    "auto.offset.reset": "earliest",
+    "enable.auto.commit": "false",
+    "enable.partition.eof": "false",
+    "group.id": "g2",
+    "max.poll.interval.ms": "60000",
+    "session.timeout.ms": "10000",
+}, config.resolved()
+assert repr(config) == 'ConsumerConfig(group_id="g2", auto_offset_reset="earliest", enable_auto_commit=False)'
+# Properties pass straight through, and are applied last.
+extra = rc.ConsumerConfig(group_id="g2", properties={"fetch.min.bytes": "10", "group.id": "g3"}).resolved()
+assert extra == DEFAULTS | {"fetch.min.bytes": "10", "group.id": "g3"}, extra
+assert "bootstrap.servers" not in extra
+"#,
+    );
+}
+
+#[test]
+fn invalid_settings_raise_at_construction() {
+    common::run_with(
+        SETUP,
+        r#"
+invalid({"properties": {"no.such.prop": "1"}}, 'No such configuration property: "no.such.prop"')
+invalid({"properties": {"session.timeout.ms": "abc"}}, 'Invalid value for configuration property "session.timeout.ms"')
+invalid({"properties": {"bogus": "x"}}, "invalid consumer setting")
+invalid({"auto_offset_reset": "middle"}, "auto_offset_reset must be 'earliest' or 'latest', got 'middle'")
+try:
+    rc.ConsumerConfig(session_timeout_ms=-5)
+except OverflowError:
+    pass
+else:
+    raise AssertionError("accepted a negative timeout")
+"#,
+    );
+}
+
+#[test]
+fn each_group_receives_every_message() {
+    common::run_with(
+        SETUP,
+        r#"
+broker = rc.MemoryBroker()
+seen = {"a": [], "b": []}
+handles = [rc.start_kafka_consumer(broker, "facts", on_message=lambda m, g=g: seen[g].append(m["offset"]),
+                                   config=rc.ConsumerConfig(group_id="group-" + g))
+           for g in seen]
+try:
+    for i in range(5):
+        broker.send("facts", "m%d" % i)
+    wait_until(lambda: len(seen["a"]) == len(seen["b"]) == 5)
+finally:
+    for handle in handles:
+        handle.stop()
+assert seen == {"a": [0, 1, 2, 3, 4], "b": [0, 1, 2, 3, 4]}
+"#,
+    );
+}
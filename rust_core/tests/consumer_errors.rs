@@ -0,0 +1,58 @@
+//! Consumer errors: unroutable, refused and malformed broker addresses raise
+//! `TimeoutError` within `connect_timeout_ms` instead of leaving a silent thread,
+//! and errors inside the loop surface through `last_error()` and `status()`.
+
+mod common;
+
+const SETUP: &str = r#"
+def fails_fast(brokers, timeout_ms=500):
+    start = time.monotonic()
+    try:
+        rc.start_kafka_consumer(brokers, "facts", connect_timeout_ms=timeout_ms)
+    except TimeoutError as e:
+        took = time.monotonic() - start
+        assert "brokers %r did not answer within %d ms" % (brokers, timeout_ms) in str(e), str(e)
+        # The bound, plus a little for librdkafka's client set-up and teardown.
+        assert timeout_ms / 1000 <= took < timeout_ms / 1000 + 2.0, took
+    else:
+        raise AssertionError("connected to %r" % brokers)
+"#;
+
+#[test]
+fn bad_brokers_raise_within_the_timeout() {
+    common::run_with(
+        SETUP,
+        r#"
+# A closed local port, a name that does not resolve, and an unroutable address.
+for brokers in ["127.0.0.1:1", "not a broker list,,", "10.255.255.1:9092"]:
+    fails_fast(brokers)
+fails_fast("127.0.0.1:1", timeout_ms=1200)
+"#,
+    );
+}
+
+#[test]
+fn loop_errors_surface_on_the_handle() {
+    common::run_with(
+        SETUP,
+        r#"
+broker = rc.MemoryBroker()
+shared = rc.SharedGraphWalker()
+handle = rc.start_kafka_consumer(broker, "facts", walker=shared)
+try:
+    assert handle.last_error() is None
+    broker.send("facts", "not json")
+    broker.send("facts", json.dumps({"fact_id": "a", "dependents": ["b"], "op": "add"}))
+    # The loop keeps going after the bad message.
+    wait_until(lambda: shared.node_count() == 1)
+    status = handle.status()
+    assert (status["messages"], status["malformed"], status["state"]) == (2, 1, "running"), status
+    assert handle.last_error() == status["last_error"]
+    assert handle.last_error().startswith("payload is not a valid message"), handle.last_error()
+    assert handle.metrics()["deserialization_errors"] == 1
+finally:
+    handle.stop()
+assert handle.last_error().startswith("payload is not a valid message")
+"#,
+    );
+}
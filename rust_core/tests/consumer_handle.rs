@@ -0,0 +1,69 @@
+//! `ConsumerHandle`: `stop`, `join` and `is_running` over a consumer's life, a
+//! second `stop` doing nothing, and prompt stops while the loop waits for a
+//! message, is paused by a frozen walker, or runs a callback.
+
+mod common;
+
+const SETUP: &str = r#"
+broker = rc.MemoryBroker()
+
+def fact(id, dependents=()):
+    return json.dumps({"fact_id": id, "dependents": list(dependents), "op": "add"})
+
+def timed(call):
+    start = time.monotonic()
+    result = call()
+    return result, time.monotonic() - start
+"#;
+
+#[test]
+fn stop_join_and_is_running_follow_the_thread() {
+    common::run_with(
+        SETUP,
+        r#"
+handle = rc.start_kafka_consumer(broker, "facts")
+assert handle.is_running() and handle.status()["state"] == "running"
+# join waits without asking the thread to stop; it times out on a running consumer.
+joined, took = timed(lambda: handle.join(0.2))
+assert joined is False and took >= 0.2 and handle.is_running()
+
+stopped, took = timed(handle.stop)
+assert stopped is True and took < 1.0, took
+assert not handle.is_running() and handle.status()["state"] == "stopped"
+assert repr(handle) == 'ConsumerHandle(brokers="memory", topic="facts", state="stopped")'
+# Stopping again is a no-op, and join returns at once.
+assert handle.stop() is True and handle.join() is True and handle.join(0.0) is True
+assert handle.last_error() is None
+"#,
+    );
+}
+
+#[test]
+fn a_paused_or_busy_consumer_stops_promptly() {
+    common::run_with(
+        SETUP,
+        r#"
+shared = rc.SharedGraphWalker()
+handle = rc.start_kafka_consumer(broker, "paused", walker=shared)
+shared.freeze("pause")
+broker.send("paused", fact("a", ["b"]))
+wait_until(lambda: handle.status()["messages"] == 1)
+time.sleep(0.1)
+assert shared.node_count() == 0
+stopped, took = timed(handle.stop)
+assert stopped and took < 1.0 and not handle.is_running(), took
+shared.unfreeze()
+
+# A callback may stop its own consumer; the loop ends after that message.
+seen = []
+def on_message(message):
+    seen.append(message["payload"])
+    handle.stop()
+handle = rc.start_kafka_consumer(broker, "busy", on_message=on_message)
+for payload in ("x", "y", "z"):
+    broker.send("busy", payload)
+wait_until(lambda: not handle.is_running())
+assert seen == [b"x"] and handle.status()["messages"] == 1
+"#,
+    );
+}
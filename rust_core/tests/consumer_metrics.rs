@@ -0,0 +1,105 @@
+//! `ConsumerHandle.metrics()` on a `MemoryBroker`: message, byte and error
+//! counts, partition lag, and `reset_metrics()`.
+
+mod common;
+
+const SETUP: &str = r#"
+broker = rc.MemoryBroker()
+shared = rc.SharedGraphWalker()
+
+def add(id, partition=0, timestamp=None):
+    payload = json.dumps({"fact_id": id, "dependents": []})
+    broker.send("facts", payload, partition=partition, timestamp=timestamp)
+    return len(payload)
+"#;
+
+#[test]
+fn messages_read_before_the_first_call_are_counted() {
+    common::run_with(
+        SETUP,
+        r#"
+sent = sum(add("f%d" % i, timestamp=1000 + i) for i in range(20))
+handle = rc.start_kafka_consumer(broker, "facts", walker=shared)
+wait_until(lambda: shared.node_count() == 20)
+m = handle.metrics()
+handle.stop()
+assert m["messages"] == 20 and m["bytes"] == sent, m
+assert m["last_message_timestamp"] == 1019, m
+assert m["deserialization_errors"] == 0 and m["processing_errors"] == 0, m
+"#,
+    );
+}
+
+#[test]
+fn malformed_and_rejected_messages_count_as_errors() {
+    common::run_with(
+        SETUP,
+        r#"
+def on_message(message):
+    if message["payload"] == b'{"fact_id": "boom", "dependents": []}':
+        raise RuntimeError("handler failed")
+
+add("a")
+broker.send("facts", "not json")
+add("boom")
+add("b")
+handle = rc.start_kafka_consumer(broker, "facts", walker=shared, on_message=on_message)
+wait_until(lambda: handle.metrics()["messages"] == 4)
+wait_until(lambda: shared.has_node("b"))
+m = handle.metrics()
+handle.stop()
+assert m["deserialization_errors"] == 1, m
+assert m["processing_errors"] == 1, m
+"#,
+    );
+}
+
+#[test]
+fn partitions_report_committed_offsets_and_lag() {
+    common::run_with(
+        SETUP,
+        r#"
+for i in range(5):
+    add("p0-%d" % i, partition=0)
+for i in range(2):
+    add("p1-%d" % i, partition=1)
+handle = rc.start_kafka_consumer(broker, "facts", walker=shared)
+wait_until(lambda: handle.metrics()["messages"] == 7)
+parts = handle.metrics()["partitions"]["facts"]
+assert parts[0] == {"committed": None, "high_watermark": 5, "lag": None}, parts
+
+assert handle.commit() == {"facts": {0: 5, 1: 2}}
+parts = handle.metrics()["partitions"]["facts"]
+assert parts[0] == {"committed": 5, "high_watermark": 5, "lag": 0}, parts
+assert parts[1] == {"committed": 2, "high_watermark": 2, "lag": 0}, parts
+
+handle.stop()
+for i in range(3):
+    add("late-%d" % i, partition=1)
+parts = handle.metrics()["partitions"]["facts"]
+assert parts[1] == {"committed": 2, "high_watermark": 5, "lag": 3}, parts
+"#,
+    );
+}
+
+#[test]
+fn reset_metrics_counts_from_the_reset() {
+    common::run_with(
+        SETUP,
+        r#"
+add("a")
+handle = rc.start_kafka_consumer(broker, "facts", walker=shared)
+wait_until(lambda: handle.metrics()["messages"] == 1)
+before = handle.metrics()["since"]
+handle.reset_metrics()
+m = handle.metrics()
+assert m["messages"] == 0 and m["bytes"] == 0, m
+assert m["since"] >= before
+size = add("b")
+wait_until(lambda: handle.metrics()["messages"] == 1)
+assert handle.metrics()["bytes"] == size
+handle.stop()
+assert handle.status()["messages"] == 2, handle.status()
+"#,
+    );
+}
@@ -0,0 +1,78 @@
+//! `to_csr` and `from_csr`: a small graph against hand-built arrays, the round
+//! trip, lists accepted without numpy, and `ValueError` for malformed arrays.
+
+mod common;
+
+const SETUP: &str = r#"
+try:
+    import numpy
+except ImportError:
+    numpy = None
+
+# Rows a, b, c: a -> c, b -> a twice and b -> c.
+INDPTR, INDICES, IDS = [0, 1, 4, 4], [2, 0, 0, 2], ["a", "b", "c"]
+
+def small():
+    w = rc.GraphWalker()
+    w.add_node("b", ["c", "a", "a"])
+    w.add_node("a", ["c"])
+    return w
+
+def edges(w):
+    return {id: sorted(w.get_dependents(id)) for id in w.node_ids()}
+"#;
+
+#[test]
+fn csr_matches_a_hand_built_graph() {
+    common::run_with(
+        SETUP,
+        r#"
+w = small()
+built = rc.GraphWalker.from_csr(INDPTR, INDICES, IDS)
+# "c" is only an edge target in w, but every row becomes a node.
+assert edges(built) == {"a": ["c"], "b": ["a", "a", "c"], "c": []}
+assert all(built.get_edge(id, d)[0] == 1.0 for id in IDS for d in built.get_dependents(id))
+if numpy is None:
+    try:
+        w.to_csr()
+    except ImportError:
+        pass
+    else:
+        raise AssertionError("no ImportError")
+else:
+    indptr, indices, ids = w.to_csr()
+    assert indptr.dtype == numpy.int64 and indices.dtype == numpy.int64
+    assert (list(indptr), list(indices), list(ids)) == (INDPTR, INDICES, IDS)
+    again = rc.GraphWalker.from_csr(indptr, indices, ids)
+    assert [list(x) for x in again.to_csr()] == [list(x) for x in (indptr, indices, ids)]
+    small32 = rc.GraphWalker.from_csr(indptr.astype(numpy.int32), indices.astype(numpy.int32), ids)
+    assert edges(small32) == edges(built)
+assert rc.GraphWalker.from_csr([0], [], []).node_ids() == []
+"#,
+    );
+}
+
+#[test]
+fn malformed_arrays_raise_value_error() {
+    common::run_with(
+        SETUP,
+        r#"
+cases = [
+    (([0, 1, 4], INDICES, IDS), "indptr has 3 entries, expected len(node_ids) + 1 = 4"),
+    (([0, 3, 1, 4], INDICES, IDS), "indptr must be non-decreasing from 0 to len(indices) = 4"),
+    (([0, 1, 4, 5], INDICES, IDS), "indptr must be non-decreasing from 0 to len(indices) = 4"),
+    (([1, 1, 4, 4], INDICES, IDS), "indptr must be non-decreasing from 0 to len(indices) = 4"),
+    ((INDPTR, [2, 0, 0, 7], IDS), "index 7 is out of range for 3 rows"),
+    ((INDPTR, [2, 0, -1, 2], IDS), "out of range"),
+    ((INDPTR, INDICES, ["a", "b", "a"]), "duplicate node id 'a'"),
+]
+for args, fragment in cases:
+    try:
+        rc.GraphWalker.from_csr(*args)
+    except ValueError as e:
+        assert fragment in str(e), (args, str(e))
+    else:
+        raise AssertionError("accepted %r" % (args,))
+"#,
+    );
+}
@@ -0,0 +1,69 @@
+//! `detect_cycles`: a figure-eight giving exactly two canonical cycles in every
+//! insertion order, back edges into the middle of the DFS path, self-loops and
+//! parallel edges, and the `max_cycles` limit.
+
+mod common;
+
+const SETUP: &str = r#"
+import itertools
+
+FIGURE_EIGHT = [("m", "a"), ("a", "b"), ("b", "m"), ("m", "x"), ("x", "y"), ("y", "m")]
+
+def build(edges):
+    w = rc.GraphWalker()
+    for a, b in edges:
+        w.add_edge(a, b)
+    return w
+
+def is_cycle(w, cycle):
+    return all(cycle[(i + 1) % len(cycle)] in w.get_dependents(v) for i, v in enumerate(cycle))
+"#;
+
+#[test]
+fn a_figure_eight_gives_two_canonical_cycles() {
+    common::run_with(
+        SETUP,
+        r#"
+expected = [["a", "b", "m"], ["m", "x", "y"]]
+# Every insertion order changes the DFS, never the report.
+for order in itertools.permutations(FIGURE_EIGHT):
+    w = build(order)
+    assert w.detect_cycles() == expected, order
+    assert all(is_cycle(w, c) for c in expected)
+
+# A back edge into the middle of the path: s and the path prefix are not in the cycle.
+assert build([("s", "t"), ("t", "u"), ("u", "v"), ("v", "t")]).detect_cycles() == [["t", "u", "v"]]
+assert build([("a", "a"), ("a", "b")]).detect_cycles() == [["a"]]
+assert build([("a", "b"), ("b", "a"), ("a", "b")]).detect_cycles() == [["a", "b"]]
+assert build([("a", "b"), ("b", "c")]).detect_cycles() == [] and rc.GraphWalker().detect_cycles() == []
+"#,
+    );
+}
+
+#[test]
+fn max_cycles_bounds_the_report() {
+    common::run_with(
+        SETUP,
+        r#"
+w = build(FIGURE_EIGHT)
+assert w.detect_cycles(max_cycles=2) == w.detect_cycles()
+for limit in (0, 1):
+    try:
+        w.detect_cycles(max_cycles=limit)
+    except rc.ResourceLimitError as e:
+        assert e.args == ("max_cycles limit of %d exceeded" % limit, limit, limit), e.args
+    else:
+        raise AssertionError("no ResourceLimitError for %d" % limit)
+
+# Many disjoint cycles: the limit trips long before all are built.
+many = build([(a, b) for i in range(1000) for a, b in [("p%d" % i, "q%d" % i), ("q%d" % i, "p%d" % i)]])
+assert len(many.detect_cycles()) == 1000
+try:
+    many.detect_cycles(max_cycles=10)
+except rc.ResourceLimitError:
+    pass
+else:
+    raise AssertionError("no ResourceLimitError")
+"#,
+    );
+}
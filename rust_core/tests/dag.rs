@@ -0,0 +1,146 @@
+//! `enforce_dag`: rejected edges name the cycle and change nothing, batches are
+//! all-or-nothing, and checks answered from the reachability index agree with the
+//! BFS.
+
+mod common;
+
+const SETUP: &str = r#"
+import random
+
+def chain(*ids, **kwargs):
+    w = rc.GraphWalker(enforce_dag=True, **kwargs)
+    for a, b in zip(ids, ids[1:]):
+        w.add_edge(a, b)
+    return w
+
+def rejected(call, cycle):
+    try:
+        call()
+    except rc.CycleError as e:
+        assert isinstance(e, ValueError)
+        assert str(e).endswith("would create cycle: " + " -> ".join(cycle)), str(e)
+    else:
+        raise AssertionError("no CycleError")
+"#;
+
+#[test]
+fn a_rejected_edge_names_the_cycle_and_changes_nothing() {
+    common::run_with(
+        SETUP,
+        r#"
+w = chain("a", "b", "c", record_events=True)
+before, events = w.to_json(), len(w.event_log())
+rejected(lambda: w.add_edge("c", "a"), ["c", "a", "b", "c"])
+rejected(lambda: w.add_node("c", ["x", "b"]), ["c", "b", "c"])
+rejected(lambda: w.touch_edge("b", "a", 1.0), ["b", "a", "b"])
+rejected(lambda: w.add_edge("a", "a"), ["a", "a"])
+assert w.to_json() == before and len(w.event_log()) == events
+assert not w.has_node("x")
+# Edges that close no cycle are still accepted.
+w.add_edge("a", "c")
+w.add_node("d", ["a"])
+assert w.get_dependents("a") == ["b", "c"]
+"#,
+    );
+}
+
+#[test]
+fn batches_are_all_or_nothing() {
+    common::run_with(
+        SETUP,
+        r#"
+w = chain("a", "b", "c", record_events=True)
+before = w.to_json()
+rejected(lambda: w.add_nodes_bulk([("x", ["a"]), ("y", ["x"]), ("c", ["y"])]),
+         ["c", "y", "x", "a", "b", "c"])
+assert w.to_json() == before and not w.has_node("x")
+
+other = rc.GraphWalker()
+other.add_node("n", ["a"])
+other.add_node("c", ["n"])
+try:
+    w.merge(other)
+except rc.CycleError:
+    pass
+else:
+    raise AssertionError("no CycleError")
+assert w.to_json() == before and not w.has_node("n")
+
+events = [{"op": "add_edge", "from": "c", "to": "d"}, {"op": "add_edge", "from": "d", "to": "a"}]
+try:
+    w.replay_events(events)
+except rc.CycleError:
+    pass
+else:
+    raise AssertionError("no CycleError")
+assert w.to_json() == before and not w.has_node("d")
+w.replay_events(events[:1])
+assert w.get_dependents("c") == ["d"]
+"#,
+    );
+}
+
+#[test]
+fn checks_from_a_fresh_index_agree_with_the_bfs() {
+    common::run_with(
+        SETUP,
+        r#"
+rng = random.Random(3)
+for case in range(30):
+    n = rng.randrange(2, 25)
+    order = ["v%d" % i for i in range(n)]
+    rng.shuffle(order)
+    edges = [(order[i], order[j]) for i in range(n) for j in range(i + 1, n) if rng.random() < 0.15]
+    plain, indexed = rc.GraphWalker(enforce_dag=True), rc.GraphWalker(enforce_dag=True)
+    for w in (plain, indexed):
+        for a, b in edges:
+            w.add_edge(a, b)
+    for _ in range(40):
+        a, b = rng.choice(order + ["new"]), rng.choice(order)
+        outcomes = []
+        for w in (plain, indexed):
+            if w is indexed:
+                indexed.build_reachability_index()
+            try:
+                w.add_edge(a, b)
+                outcomes.append("added")
+            except rc.CycleError as e:
+                outcomes.append(str(e))
+        assert outcomes[0] == outcomes[1], (case, a, b, outcomes)
+    assert {id: plain.get_dependents(id) for id in plain.node_ids()} == \
+        {id: indexed.get_dependents(id) for id in indexed.node_ids()}
+
+# The index leaves out the kept edges into tombstoned ids, which come back when
+# the id is re-added; the check must not.
+w = chain("a", "b", "c")
+assert w.tombstone_node("b")
+w.build_reachability_index()
+assert not w.is_reachable("a", "b")
+rejected(lambda: w.add_edge("b", "a"), ["b", "a", "b"])
+"#,
+    );
+}
+
+#[test]
+fn a_fresh_index_skips_the_bfs() {
+    common::run_with(
+        SETUP,
+        r#"
+N = 200_000
+w = rc.GraphWalker(enforce_dag=True)
+w.add_nodes_bulk([("n%d" % i, ["n%d" % (i + 1)]) for i in range(N)])
+
+def check(x):
+    start = time.perf_counter()
+    w.add_edge("n0", "n%d" % x)
+    return time.perf_counter() - start
+
+bfs = min(check(x) for x in range(2, 5))
+w.build_reachability_index()
+indexed = check(5)
+assert indexed * 20 < bfs, (indexed, bfs)
+w.build_reachability_index()
+rejected(lambda: w.add_edge("n%d" % N, "n%d" % (N - 2)), ["n%d" % N, "n%d" % (N - 2), "n%d" % (N - 1), "n%d" % N])
+"#,
+    );
+}
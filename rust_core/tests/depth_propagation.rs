@@ -0,0 +1,60 @@
+//! `propagate_invalidation_with_depth`: minimum BFS distances on a diamond with a
+//! long and a short side, each `max_depth` cut-off, cycles back to the start, and
+//! unknown starts.
+
+mod common;
+
+const SETUP: &str = r#"
+def diamond():
+    # s reaches t in 2 hops via a and in 4 via b, c, d; t leads back to s.
+    w = rc.GraphWalker()
+    w.add_node("s", ["a", "b"])
+    w.add_node("a", ["t"])
+    w.add_node("b", ["c"])
+    w.add_node("c", ["d"])
+    w.add_node("d", ["t"])
+    w.add_node("t", ["u", "s"])
+    return w
+"#;
+
+#[test]
+fn each_node_reports_its_shortest_distance() {
+    common::run_with(
+        SETUP,
+        r#"
+w = diamond()
+depths = w.propagate_invalidation_with_depth("s")
+assert depths == {"a": 1, "b": 1, "t": 2, "c": 2, "d": 3, "u": 3, "s": 3}, depths
+# The same nodes as the plain propagation.
+assert set(depths) == w.propagate_invalidation("s")
+assert w.propagate_invalidation_with_depth("d") == {"t": 1, "u": 2, "s": 2, "a": 3, "b": 3, "c": 4, "d": 5}
+"#,
+    );
+}
+
+#[test]
+fn max_depth_stops_the_expansion() {
+    common::run_with(
+        SETUP,
+        r#"
+w = diamond()
+assert w.propagate_invalidation_with_depth("s", max_depth=0) == {}
+assert w.propagate_invalidation_with_depth("s", max_depth=1) == {"a": 1, "b": 1}
+assert w.propagate_invalidation_with_depth("s", max_depth=2) == {"a": 1, "b": 1, "t": 2, "c": 2}
+full = w.propagate_invalidation_with_depth("s")
+for k in range(1, 6):
+    limited = w.propagate_invalidation_with_depth("s", max_depth=k)
+    assert limited == {id: d for id, d in full.items() if d <= k}, k
+
+loop = rc.GraphWalker()
+loop.add_node("x", ["x"])
+assert loop.propagate_invalidation_with_depth("x") == {"x": 1}
+try:
+    loop.propagate_invalidation_with_depth("nope")
+except rc.NodeNotFoundError:
+    pass
+else:
+    raise AssertionError("no NodeNotFoundError")
+"#,
+    );
+}
@@ -0,0 +1,106 @@
+//! `distance_distribution` and `effective_diameter`: the exact histogram on small
+//! graphs against a BFS in Python, sampled estimates within tolerance and stable
+//! under their seed, and the rejected arguments.
+
+mod common;
+
+const SETUP: &str = r#"
+import random
+from collections import Counter, deque
+
+def random_graph(n, out, seed):
+    r = random.Random(seed)
+    w = rc.GraphWalker()
+    for i in range(n):
+        w.add_node("n%d" % i, ["n%d" % r.randrange(n) for _ in range(out)])
+    return w
+
+def bfs_histogram(w):
+    counts = Counter()
+    for source in w.node_ids():
+        seen, queue = {source: 0}, deque([source])
+        while queue:
+            u = queue.popleft()
+            for v in w.get_dependents(u) if w.has_node(u) else []:
+                if v not in seen and w.has_node(v):
+                    seen[v] = seen[u] + 1
+                    queue.append(v)
+        counts.update(d for d in seen.values() if d > 0)
+    return dict(counts)
+"#;
+
+#[test]
+fn exact_histograms_count_every_reachable_pair() {
+    common::run_with(
+        SETUP,
+        r#"
+chain = rc.GraphWalker()
+for a, b in [("a", "b"), ("b", "c"), ("c", "d")]:
+    chain.add_node(a, [b])
+chain.add_node("d", [])
+assert chain.distance_distribution() == {1: 3, 2: 2, 3: 1}
+# 6 pairs: 90% of them is 5.4, reached 0.4 of the way from distance 2 to 3.
+assert abs(chain.effective_diameter() - 2.4) < 1e-12
+assert chain.effective_diameter(1.0) == 3.0 == chain.find_diameter()
+assert chain.effective_diameter(0.5) == 1.0
+
+# Dangling targets and a node reaching itself are not pairs.
+loop = rc.GraphWalker()
+loop.add_node("a", ["b", "x"])
+loop.add_node("b", ["a"])
+assert loop.distance_distribution() == {1: 2}
+assert rc.GraphWalker().distance_distribution() == {} and rc.GraphWalker().effective_diameter() == 0.0
+
+for seed in range(5):
+    w = random_graph(40, 2, seed)
+    assert w.distance_distribution() == bfs_histogram(w), seed
+"#,
+    );
+}
+
+#[test]
+fn sampled_histograms_are_close_and_repeatable() {
+    common::run_with(
+        SETUP,
+        r#"
+w = random_graph(600, 3, 7)
+exact = w.distance_distribution()
+exact_total = sum(exact.values())
+exact_diameter = w.effective_diameter()
+for seed in range(3):
+    sampled = w.distance_distribution(sample_sources=150, seed=seed)
+    assert abs(sum(sampled.values()) - exact_total) < 0.05 * exact_total, seed
+    for d, count in exact.items():
+        if count > exact_total // 100:
+            assert abs(sampled.get(d, 0) - count) < 0.1 * count, (seed, d)
+    assert abs(w.effective_diameter(sample_sources=150, seed=seed) - exact_diameter) < 0.1
+    assert w.distance_distribution(sample_sources=150, seed=seed) == sampled
+
+# Asking for at least every node is the exact run.
+assert w.distance_distribution(sample_sources=600) == w.distance_distribution(sample_sources=10000) == exact
+"#,
+    );
+}
+
+#[test]
+fn bad_arguments_are_rejected() {
+    common::run_with(
+        SETUP,
+        r#"
+w = random_graph(10, 2, 0)
+for call, fragment in [
+    (lambda: w.distance_distribution(sample_sources=0), "sample_sources must be positive"),
+    (lambda: w.effective_diameter(sample_sources=0), "sample_sources must be positive"),
+    (lambda: w.effective_diameter(0.0), "percentile must be in (0, 1], got 0"),
+    (lambda: w.effective_diameter(1.5), "percentile must be in (0, 1], got 1.5"),
+    (lambda: w.effective_diameter(float("nan")), "percentile must be in (0, 1]"),
+]:
+    try:
+        call()
+    except ValueError as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no ValueError")
+"#,
+    );
+}
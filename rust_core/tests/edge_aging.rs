@@ -0,0 +1,135 @@
+//! Edge weights and aging, and consumers touching the edges of re-observed facts
+//! instead of replacing them.
+
+mod common;
+
+const SETUP: &str = r#"
+broker = rc.MemoryBroker()
+
+def consume(shared, messages):
+    """Sends `messages` as `(payload, timestamp_ms)` pairs and waits until the
+    consumer has applied them."""
+    for payload, timestamp in messages:
+        broker.send("facts", json.dumps(payload), timestamp=timestamp)
+    handle = rc.start_kafka_consumer(broker, "facts", walker=shared)
+    try:
+        wait_until(lambda: handle.metrics()["messages"] == len(messages))
+    finally:
+        handle.stop()
+    assert handle.metrics()["processing_errors"] == 0, handle.metrics()
+    return shared.snapshot()
+"#;
+
+#[test]
+fn decay_prune_and_touch_keep_counts_and_reverse_index_consistent() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b", "c"])
+w.add_node("b", ["c"])
+w.decay_weights(0.5)
+later = time.time() + 3600
+w.touch_edge("a", "b", later)
+assert w.get_edge("a", "b") == (1.0, later)
+assert w.get_edge("a", "c")[0] == 0.5
+w.touch_edge("a", "b", 5.0)
+assert w.get_edge("a", "b") == (1.0, later), "timestamps never move back"
+assert w.prune_edges_below(0.75) == 2
+assert w.edge_count() == 1
+assert w.find_ancestors("b") == {"a"}
+w.touch_edge("b", "d", 1.0)
+assert w.edge_count() == 2 and w.find_ancestors("d") == {"a", "b"}
+"#,
+    );
+}
+
+#[test]
+fn a_non_finite_prune_threshold_is_rejected_before_it_is_logged() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(record_events=True)
+w.add_node("a", ["b", "c"])
+before = w.event_log()
+for threshold in [float("nan"), float("inf"), float("-inf")]:
+    try:
+        w.prune_edges_below(threshold)
+    except ValueError as e:
+        assert "finite" in str(e), str(e)
+    else:
+        raise AssertionError("no ValueError for %r" % threshold)
+assert w.edge_count() == 2 and w.event_log() == before
+assert w.prune_edges_below(1.5) == 2
+"#,
+    );
+}
+
+#[test]
+fn a_reobserved_fact_touches_its_edges_at_the_message_time() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(record_events=True)
+w.add_node("a", ["b", "c"])
+w.add_node("x", ["b"])
+w.decay_weights(0.5)
+seen_at = w.get_edge("x", "b")[1]
+shared = rc.SharedGraphWalker(w)
+later = 4_000_000_000_000
+g = consume(shared, [({"fact_id": "a", "dependents": ["b", "d"]}, later)])
+
+assert g.get_edge("a", "b") == (1.0, later / 1000), g.get_edge("a", "b")
+assert g.get_edge("a", "d") == (1.0, later / 1000)
+assert g.get_edge("a", "c") is None
+assert g.get_edge("x", "b") == (0.5, seen_at), "other facts keep their weights"
+ops = [e["op"] for e in g.event_log()][-3:]
+assert ops == ["touch_edge", "touch_edge", "remove_edge"], ops
+assert not g.has_node("c")
+assert g.find_ancestors("d") == {"a"}
+"#,
+    );
+}
+
+#[test]
+fn new_and_tombstoned_facts_are_still_added() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+w.add_node("t", ["u"])
+w.tombstone_node("t")
+shared = rc.SharedGraphWalker(w)
+g = consume(shared, [
+    ({"fact_id": "n", "dependents": ["m"], "attributes": {"k": "v"}}, None),
+    ({"fact_id": "t", "dependents": ["v"]}, None),
+])
+assert g.get_dependents("n") == ["m"]
+assert g.get_attributes("n") == {"k": "v"}
+assert g.get_dependents("t") == ["v"]
+assert g.tombstones() == []
+"#,
+    );
+}
+
+#[test]
+fn a_rejected_new_edge_leaves_existing_edges_touched_only() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(enforce_dag=True)
+w.add_node("a", ["b"])
+w.add_node("b", ["c"])
+shared = rc.SharedGraphWalker(w)
+broker.send("facts", json.dumps({"fact_id": "b", "dependents": ["c", "a"]}),
+            timestamp=4_000_000_000_000)
+handle = rc.start_kafka_consumer(broker, "facts", walker=shared)
+wait_until(lambda: handle.metrics()["processing_errors"] == 1)
+handle.stop()
+g = shared.snapshot()
+assert g.get_dependents("b") == ["c"]
+assert g.get_edge("b", "c") == (1.0, 4_000_000_000.0)
+assert g.get_edge("b", "a") is None
+"#,
+    );
+}
@@ -0,0 +1,170 @@
+//! `from_edge_file` and `append_edge_file` on `fixtures/edges_tricky.csv`: quoted
+//! fields, unicode, CRLF, blank lines and, on its last line, a row with no target.
+
+mod common;
+
+const SETUP: &str = r#"
+import gzip, os, tempfile
+
+FIXTURE = os.path.join(os.environ["CARGO_MANIFEST_DIR"], "tests", "fixtures", "edges_tricky.csv")
+with open(FIXTURE, "rb") as f:
+    TRICKY = f.read()
+# The fixture without its last row, which has no target.
+VALID = TRICKY[:TRICKY.rindex(b"no-target")]
+
+EDGES = {
+    ("plain", "b"): 4.0,
+    ("quoted, comma", 'say "hi"'): 1.5,
+    ("café", "日本語"): 1.0,
+    ("multi\r\nline", "b"): 3.0,
+    ("tab\tin\tid", "plain"): 0.25,
+}
+
+def temp(data, suffix=".csv"):
+    fd, path = tempfile.mkstemp(suffix=suffix)
+    with os.fdopen(fd, "wb") as f:
+        f.write(data)
+    return path
+
+def load(data, suffix=".csv", **kwargs):
+    path = temp(data, suffix)
+    try:
+        return rc.GraphWalker.from_edge_file(path, **dict({"weight_col": "weight"}, **kwargs))
+    finally:
+        os.remove(path)
+
+def edges(w):
+    return {(frm, to): w.get_edge(frm, to)[0] for frm in w.node_ids() for to in w.get_dependents(frm)}
+
+def raises(error, call, fragment):
+    try:
+        call()
+    except error as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+"#;
+
+#[test]
+fn tricky_rows_are_parsed() {
+    common::run_with(
+        SETUP,
+        r#"
+w = load(VALID)
+assert edges(w) == EDGES, edges(w)
+assert w.node_ids() == sorted({frm for frm, _ in EDGES})
+# Positions instead of names, and a header read as a row without has_header.
+w = load(VALID.replace("source,target,weight".encode(), b"plain,b,5"), has_header=False, weight_col=2)
+assert edges(w) == EDGES
+w = load(VALID.replace(b",", b";"), delimiter=";")
+assert edges(w) == {(f.replace(",", ";"), t): x for (f, t), x in EDGES.items()}
+"#,
+    );
+}
+
+#[test]
+fn parse_errors_name_the_line() {
+    common::run_with(
+        SETUP,
+        r#"
+raises(rc.SerializationError, lambda: rc.GraphWalker.from_edge_file(FIXTURE, weight_col="weight"),
+       FIXTURE + ":12: missing target (column 1 of 1)")
+for text, fragment in [
+    (VALID + b"a,,1\r\n", ":12: empty target"),
+    (VALID + b"a,b,heavy\r\n", ":12: weight 'heavy' is not a finite number"),
+    (VALID + b"a,b,inf\n", ":12: weight 'inf' is not a finite number"),
+    (VALID + b'"a"x,b\n', ":12: unexpected text after a closing quote"),
+    # An unterminated quote is reported where its record starts.
+    (VALID + b'a,"b\n\nc,d\n', ":12: unterminated quoted field"),
+    (VALID + b"\xff,b\n", ":12: source is not valid UTF-8"),
+]:
+    raises(rc.SerializationError, lambda: load(text), fragment)
+raises(ValueError, lambda: load(VALID, source_col="from"), "no column 'from'")
+raises(ValueError, lambda: load(VALID, has_header=False, source_col="source"), "has_header=True")
+raises(ValueError, lambda: load(VALID, delimiter='"'), "delimiter")
+raises(OSError, lambda: rc.GraphWalker.from_edge_file(FIXTURE + ".missing"), ".missing")
+assert load(b"").node_count() == 0
+"#,
+    );
+}
+
+#[test]
+fn gzip_input_is_detected_from_its_content() {
+    common::run_with(
+        SETUP,
+        r#"
+packed = gzip.compress(VALID)
+assert edges(load(packed, ".csv.gz")) == EDGES
+# Without the extension, and as two concatenated members.
+assert edges(load(packed, ".csv")) == EDGES
+half = VALID.index("café".encode())
+assert edges(load(gzip.compress(VALID[:half]) + gzip.compress(VALID[half:]))) == EDGES
+raises(rc.SerializationError, lambda: load(gzip.compress(TRICKY), ".gz"), ":12: missing target")
+raises(OSError, lambda: load(packed[:len(packed) // 2], ".gz"), "gzip data ends")
+"#,
+    );
+}
+
+#[test]
+fn append_adds_rows_as_edges() {
+    common::run_with(
+        SETUP,
+        r#"
+path = temp(VALID)
+try:
+    w = rc.GraphWalker(record_events=True)
+    w.add_edge("b", "c", 7.0)
+    assert w.append_edge_file(path, weight_col="weight") == 6
+    assert edges(w) == {**EDGES, ("b", "c"): 7.0}, edges(w)
+    # Each row is logged as an add_edge, so replaying the log rebuilds the graph.
+    assert [e["op"] for e in w.event_log()].count("add_edge") == 7
+    replayed = rc.GraphWalker()
+    replayed.replay_events(w.event_log())
+    assert edges(replayed) == edges(w)
+    # Without a weight column, existing edges keep their weight.
+    assert w.append_edge_file(path) == 6 and w.get_edge("plain", "b")[0] == 4.0
+    events = len(w.event_log())
+
+    # A malformed file changes nothing.
+    before = edges(w)
+    bad = temp(TRICKY)
+    raises(rc.SerializationError, lambda: w.append_edge_file(bad), ":12: missing target")
+    os.remove(bad)
+    assert edges(w) == before and len(w.event_log()) == events
+
+    # An edge closing a cycle rolls back the rows added before it.
+    dag = rc.GraphWalker(enforce_dag=True)
+    dag.add_edge("b", "multi\r\nline")
+    raises(rc.CycleError, lambda: dag.append_edge_file(path), "")
+    assert dag.node_ids() == ["b"] and dag.get_dependents("b") == ["multi\r\nline"]
+
+    w.freeze()
+    raises(rc.FrozenGraphError, lambda: w.append_edge_file(path), "")
+finally:
+    os.remove(path)
+"#,
+    );
+}
+
+#[test]
+fn a_million_edges_load_in_seconds() {
+    // Debug builds are several times slower than the release build the budget is for.
+    let budget = if cfg!(debug_assertions) { 20.0 } else { 3.0 };
+    common::run_with(
+        SETUP,
+        &format!(
+            r#"
+N = 1_000_000
+path = temp(("source,target\n" + "".join("n%d,n%d\n" % (i, (i * 7 + 1) % N) for i in range(N))).encode())
+try:
+    start = time.perf_counter()
+    w = rc.GraphWalker.from_edge_file(path)
+    elapsed = time.perf_counter() - start
+finally:
+    os.remove(path)
+assert w.edge_count() == N and w.node_count() == N
+assert elapsed < {budget}, elapsed
+"#
+        ),
+    );
+}
@@ -0,0 +1,55 @@
+//! `explain_propagation` traces on a small crafted graph.
+
+mod common;
+
+const SETUP: &str = r#"
+def graph():
+    """a -> b -> d -> f with a shortcut a -> c -> d, a cycle b -> a, and the
+    edge d -> e into a tombstone."""
+    w = rc.GraphWalker()
+    w.add_node("a", ["b", "c"])
+    w.add_node("b", ["d", "a"])
+    w.add_node("c", ["d"])
+    w.add_node("d", ["e", "f"])
+    w.add_node("e", [])
+    w.tombstone_node("e")
+    return w
+"#;
+
+#[test]
+fn explain_propagation_traces_each_level() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+result = w.explain_propagation("a", nodes_of_interest=["d", "f", "a", "e", "missing"])
+assert result["affected"] == sorted(w.propagate_invalidation("a")) == ["a", "b", "c", "d", "f"]
+trace = result["trace"]
+assert len(trace["ms"]) == trace["levels"] == 4 and all(ms >= 0 for ms in trace["ms"])
+# Level 2 expands [b, c]: b adds d and a, c revisits d. Level 3 expands [a, d]: a
+# revisits b and c, d hits the tombstone e and adds f.
+assert trace["frontier_sizes"] == [1, 2, 2, 1]
+assert trace["new_nodes"] == [2, 2, 1, 0]
+assert trace["revisits"] == [0, 1, 2, 0]
+assert trace["tombstone_hits"] == [0, 0, 1, 0]
+assert trace["paths"] == {
+    "d": ["a", "b", "d"],
+    "f": ["a", "b", "d", "f"],
+    "a": ["a", "b", "a"],
+    "e": None,
+    "missing": None,
+}
+assert json.loads(json.dumps(result)) == result
+
+w.add_node("f", [])
+leaf = w.explain_propagation("f")
+assert leaf["affected"] == [] and leaf["trace"]["frontier_sizes"] == [1] and leaf["trace"]["paths"] == {}
+try:
+    w.explain_propagation("missing")
+except rc.NodeNotFoundError as e:
+    assert e.args == ("missing",)
+else:
+    raise AssertionError("no NodeNotFoundError")
+"#,
+    );
+}
@@ -0,0 +1,108 @@
+//! `to_dot` and `to_graphml`: ids with quotes, backslashes, colons and newlines
+//! parse back intact, labels, weights and attributes are written, and `highlight`
+//! colors exactly the given nodes.
+
+mod common;
+
+const SETUP: &str = r#"
+import os, re, tempfile
+import xml.etree.ElementTree as ET
+
+def tricky():
+    w = rc.GraphWalker()
+    w.add_node('svc:"a"', ["b\\c", "x\ny"], weights=[2.0, 1.0])
+    w.add_edge_labeled("b\\c", "t", "caches")
+    w.set_attribute("b\\c", "team", "s&<a>")
+    return w
+
+TOKEN = re.compile(r'"((?:[^"\\]|\\.)*)"|(->|[{};\[\]=,])|([A-Za-z_][A-Za-z0-9_]*)|(\S)')
+
+def unquote(s):
+    return re.sub(r"\\(.)", lambda m: "\n" if m.group(1) == "n" else m.group(1), s)
+
+def parse_dot(text):
+    # Statements are nodes `"id" [attrs];` and edges `"a" -> "b" [attrs];`.
+    tokens = [m for m in TOKEN.finditer(text)]
+    assert not any(m.group(4) for m in tokens), "stray character"
+    values = [("str", unquote(m.group(1))) if m.group(1) is not None else ("sym", m.group(2) or m.group(3)) for m in tokens]
+    assert values[:3] == [("sym", "digraph"), ("sym", "concord"), ("sym", "{")] and values[-1] == ("sym", "}")
+    nodes, edges, i = {}, {}, 3
+    while values[i] != ("sym", "}"):
+        kind, first = values[i]
+        assert kind == "str", values[i]
+        i += 1
+        second = None
+        if values[i] == ("sym", "->"):
+            second = values[i + 1][1]
+            i += 2
+        attrs = {}
+        if values[i] == ("sym", "["):
+            i += 1
+            while values[i] != ("sym", "]"):
+                key, eq, value = values[i:i + 3]
+                assert eq == ("sym", "=")
+                attrs[key[1]] = value[1]
+                i += 3
+                if values[i] == ("sym", ","):
+                    i += 1
+            i += 1
+        assert values[i] == ("sym", ";"), values[i]
+        i += 1
+        if second is None:
+            nodes[first] = attrs
+        else:
+            edges[(first, second)] = attrs
+    return nodes, edges
+"#;
+
+#[test]
+fn dot_escapes_ids_and_parses_back() {
+    common::run_with(
+        SETUP,
+        r##"
+w = tricky()
+nodes, edges = parse_dot(w.to_dot())
+assert sorted(nodes) == sorted(['svc:"a"', "b\\c", "x\ny", "t"])
+assert all(attrs == {} for attrs in nodes.values())
+assert edges == {("b\\c", "t"): {"label": "caches"}, ('svc:"a"', "b\\c"): {}, ('svc:"a"', "x\ny"): {}}
+
+nodes, _ = parse_dot(w.to_dot(highlight=list(w.propagate_invalidation("b\\c"))))
+assert nodes["t"] == {"style": "filled", "fillcolor": "#ff6b6b"}
+assert [id for id, attrs in nodes.items() if attrs] == ["t"]
+
+p = os.path.join(tempfile.mkdtemp(), "g.dot")
+assert w.to_dot(p) is None
+assert open(p).read() == w.to_dot()
+assert parse_dot(rc.GraphWalker().to_dot()) == ({}, {})
+"##,
+    );
+}
+
+#[test]
+fn graphml_is_valid_xml_with_weights_and_attributes() {
+    common::run_with(
+        SETUP,
+        r##"
+w = tricky()
+p = os.path.join(tempfile.mkdtemp(), "g.graphml")
+w.to_graphml(p, highlight=["t", 'svc:"a"'])
+ns = {"g": "http://graphml.graphdrawing.org/xmlns"}
+root = ET.parse(p).getroot()
+keys = {k.get("id"): (k.get("for"), k.get("attr.name"), k.get("attr.type")) for k in root.findall("g:key", ns)}
+names = {name: id for id, (_, name, _) in keys.items()}
+assert keys[names["weight"]] == ("edge", "weight", "double") and keys[names["team"]] == ("node", "team", "string")
+graph = root.find("g:graph", ns)
+assert graph.get("edgedefault") == "directed"
+
+data = lambda el: {d.get("key"): d.text for d in el.findall("g:data", ns)}
+nodes = {n.get("id"): data(n) for n in graph.findall("g:node", ns)}
+assert sorted(nodes) == sorted(['svc:"a"', "b\\c", "x\ny", "t"])
+assert nodes["b\\c"] == {names["team"]: "s&<a>"}
+assert nodes["t"] == nodes['svc:"a"'] == {names["color"]: "#ff6b6b"} and nodes["x\ny"] == {}
+edges = {(e.get("source"), e.get("target")): data(e) for e in graph.findall("g:edge", ns)}
+assert edges[('svc:"a"', "b\\c")] == {names["weight"]: "2"}
+assert edges[("b\\c", "t")] == {names["weight"]: "1", names["label"]: "caches"}
+assert len(edges) == 3
+"##,
+    );
+}
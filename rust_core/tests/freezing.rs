@@ -0,0 +1,125 @@
+//! `freeze` / `unfreeze` and `submit_events`: events streamed in while frozen are
+//! buffered and applied in arrival order on `unfreeze`, or refused under "pause".
+
+mod common;
+
+const SETUP: &str = r#"
+broker = rc.MemoryBroker()
+
+def add(id, dependents):
+    return {"op": "add_node", "id": id, "dependents": dependents}
+
+def edge(op, frm, to):
+    return {"op": op, "from": frm, "to": to}
+
+def raises(error, call, fragment):
+    try:
+        call()
+    except error as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+
+def fact(id, dependents=()):
+    return json.dumps({"fact_id": id, "dependents": list(dependents)})
+"#;
+
+#[test]
+fn buffered_events_are_applied_in_order_on_unfreeze() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(record_events=True)
+w.add_node("a", ["b"])
+version = w.version()
+w.freeze()
+assert w.is_frozen()
+# Each batch only makes sense applied after the one before it.
+assert w.submit_events([add("a", ["c"]), edge("add_edge", "c", "d")]) == 0
+assert w.submit_events([edge("remove_edge", "a", "c"), add("c", ["a"])]) == 0
+assert w.submit_events([{"op": "set_attrs", "ids": ["c"], "key": "seen", "values": ["1"]}]) == 0
+
+# Nothing is applied yet, and reads still work.
+assert w.get_dependents("a") == ["b"] and w.node_ids() == ["a"] and w.version() == version
+assert w.propagate_invalidation("a") == {"b"}
+raises(rc.FrozenGraphError, lambda: w.add_edge("a", "z"), "unfreeze")
+raises(rc.FrozenGraphError, lambda: w.replay_events([add("z", [])]), "unfreeze")
+assert len(w.event_log()) == 1
+
+assert w.unfreeze() == {"frozen_version": version, "buffered": 5, "applied": 5, "failed": 0}
+assert not w.is_frozen()
+assert w.get_dependents("a") == [] and w.get_dependents("c") == ["a"]
+assert w.get_attributes("c") == {"seen": "1"}
+assert [e["op"] for e in w.event_log()] == ["add_node", "add_node", "add_edge", "remove_edge", "add_node", "set_attrs"]
+# Unfrozen, events apply at once.
+assert w.submit_events([edge("add_edge", "a", "e")]) == 1 and w.get_dependents("a") == ["e"]
+"#,
+    );
+}
+
+#[test]
+fn failed_buffered_events_are_counted_and_the_rest_applied() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(enforce_dag=True)
+w.add_edge("a", "b")
+w.freeze()
+w.submit_events([edge("add_edge", "b", "c"), edge("add_edge", "c", "a"), edge("add_edge", "c", "d")])
+result = w.unfreeze()
+assert (result["buffered"], result["applied"], result["failed"]) == (3, 2, 1), result
+assert w.get_dependents("b") == ["c"] and w.get_dependents("c") == ["d"]
+"#,
+    );
+}
+
+#[test]
+fn pause_refuses_events_and_freeze_errors_are_raised() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", [])
+w.freeze(consumer_policy="pause")
+raises(rc.FrozenGraphError, lambda: w.submit_events([add("b", [])]), "consumer paused")
+raises(ValueError, lambda: w.freeze(), "already frozen")
+assert w.unfreeze()["buffered"] == 0 and w.node_ids() == ["a"]
+raises(ValueError, w.unfreeze, "not frozen")
+raises(ValueError, lambda: w.freeze(consumer_policy="drop"), "unknown consumer policy 'drop'")
+assert not w.is_frozen()
+# Malformed events are rejected before anything is buffered.
+w.freeze()
+raises(ValueError, lambda: w.submit_events([add("b", []), {"op": "explode"}]), "event 1: unknown variant `explode`")
+assert w.unfreeze()["buffered"] == 0
+"#,
+    );
+}
+
+#[test]
+fn a_consumer_feeding_a_frozen_walker_catches_up_on_unfreeze() {
+    common::run_with(
+        SETUP,
+        r#"
+for policy in ["buffer", "pause"]:
+    topic = "facts-" + policy
+    shared = rc.SharedGraphWalker()
+    shared.freeze(consumer_policy=policy)
+    handle = rc.start_kafka_consumer(broker, topic, walker=shared)
+    try:
+        broker.send(topic, fact("a", ["b"]))
+        broker.send(topic, fact("b", ["c"]))
+        broker.send(topic, fact("a", ["c"]))
+        if policy == "buffer":
+            wait_until(lambda: handle.status()["messages"] == 3)
+        else:
+            time.sleep(0.2)
+        assert shared.node_count() == 0, policy
+        shared.unfreeze()
+        wait_until(lambda: shared.node_count() == 2 and shared.get_dependents("a") == ["c"])
+        assert shared.get_dependents("b") == ["c"]
+        wait_until(lambda: handle.status()["messages"] == 3)
+    finally:
+        handle.stop()
+"#,
+    );
+}
@@ -0,0 +1,292 @@
+//! Arbitrary operation sequences against `GraphWalker`. Any Python exception is
+//! an acceptable answer to a bad call; a Rust panic (PanicException) is not, and
+//! fails the test as it resumes unwinding here.
+//!
+//! `cargo test fuzz_smoke` runs a fixed-seed budget suitable for CI. Set
+//! `CONCORD_FUZZ_CASES` to run more cases, e.g. for a longer local session.
+
+mod common;
+
+use proptest::prelude::*;
+use proptest::test_runner::{Config, RngAlgorithm, TestRng, TestRunner};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyTuple};
+
+#[derive(Clone, Debug)]
+enum Op {
+    AddNode(String, Vec<String>),
+    AddEdge(String, String, Option<f64>),
+    RemoveNode(String),
+    RemoveEdge(String, String),
+    TouchEdge(String, String, f64),
+    DecayWeights(f64),
+    PruneEdgesBelow(f64),
+    SetAttribute(String, String, String),
+    SplitNode(String, String, Vec<String>, Vec<String>, bool),
+    TombstoneNode(String),
+    Propagate(String),
+    Query(&'static str, String, String),
+    Whole(&'static str),
+    EgoGraph(String, usize),
+    Subgraph(Vec<String>),
+    Transaction(bool),
+    Freeze(bool),
+    RoundTrip(&'static str),
+}
+
+/// Ids drawn mostly from a small pool, so operations meet each other's nodes,
+/// with the occasional arbitrary string.
+fn id() -> impl Strategy<Value = String> {
+    prop_oneof![
+        6 => (0..6u8).prop_map(|i| format!("n{}", i)),
+        1 => Just("N0".to_string()),
+        1 => "\\PC{0,6}",
+    ]
+}
+
+fn weight() -> impl Strategy<Value = f64> {
+    prop_oneof![
+        4 => -2.0..4.0f64,
+        1 => Just(f64::NAN),
+        1 => Just(f64::INFINITY),
+        1 => Just(0.0),
+    ]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let ids = || prop::collection::vec(id(), 0..4);
+    prop_oneof![
+        4 => (id(), ids()).prop_map(|(a, d)| Op::AddNode(a, d)),
+        4 => (id(), id(), prop::option::of(weight())).prop_map(|(a, b, w)| Op::AddEdge(a, b, w)),
+        1 => id().prop_map(Op::RemoveNode),
+        1 => (id(), id()).prop_map(|(a, b)| Op::RemoveEdge(a, b)),
+        1 => (id(), id(), weight()).prop_map(|(a, b, t)| Op::TouchEdge(a, b, t)),
+        1 => weight().prop_map(Op::DecayWeights),
+        1 => weight().prop_map(Op::PruneEdgesBelow),
+        1 => (id(), "[a-c]{0,2}", "\\PC{0,4}").prop_map(|(a, k, v)| Op::SetAttribute(a, k, v)),
+        1 => (id(), id(), ids(), ids(), any::<bool>())
+            .prop_map(|(a, p, i, o, k)| Op::SplitNode(a, p, i, o, k)),
+        1 => id().prop_map(Op::TombstoneNode),
+        2 => id().prop_map(Op::Propagate),
+        3 => (
+            prop::sample::select(vec![
+                "shortest_path",
+                "shortest_path_length",
+                "all_shortest_paths",
+                "max_flow",
+                "min_cut",
+                "is_reachable",
+            ]),
+            id(),
+            id()
+        )
+            .prop_map(|(m, a, b)| Op::Query(m, a, b)),
+        3 => prop::sample::select(vec![
+            "detect_cycles",
+            "topological_sort",
+            "topological_layers",
+            "find_diameter",
+            "calculate_pagerank",
+            "calculate_betweenness",
+            "find_cliques",
+            "bridges",
+            "articulation_points",
+            "strongly_connected_components",
+            "weakly_connected_components",
+            "detect_communities",
+            "k_core_decomposition",
+            "count_triangles",
+            "validate",
+            "content_digest",
+            "to_csr",
+        ])
+        .prop_map(Op::Whole),
+        1 => (id(), 0..3usize).prop_map(|(c, r)| Op::EgoGraph(c, r)),
+        1 => ids().prop_map(Op::Subgraph),
+        1 => any::<bool>().prop_map(Op::Transaction),
+        1 => any::<bool>().prop_map(Op::Freeze),
+        1 => prop::sample::select(vec!["json", "pickle", "csr"]).prop_map(Op::RoundTrip),
+    ]
+}
+
+#[derive(Clone, Debug)]
+struct Options {
+    enforce_dag: bool,
+    record_events: bool,
+    track_provenance: bool,
+    track_heat: bool,
+    undirected: bool,
+    lowercase: bool,
+}
+
+fn options() -> impl Strategy<Value = Options> {
+    (any::<[bool; 6]>()).prop_map(|b| Options {
+        enforce_dag: b[0],
+        record_events: b[1],
+        track_provenance: b[2],
+        track_heat: b[3],
+        undirected: b[4],
+        lowercase: b[5],
+    })
+}
+
+/// Applies `op` to `w`, returning the walker to continue with: a round trip
+/// continues with the copy.
+fn apply<'py>(py: Python<'py>, w: &'py PyAny, op: &Op) -> &'py PyAny {
+    let result = match op {
+        Op::AddNode(a, d) => w.call_method1("add_node", (a, d.clone())),
+        Op::AddEdge(a, b, wt) => w.call_method1("add_edge", (a, b, *wt)),
+        Op::RemoveNode(a) => w.call_method1("remove_node", (a,)),
+        Op::RemoveEdge(a, b) => w.call_method1("remove_edge", (a, b)),
+        Op::TouchEdge(a, b, t) => w.call_method1("touch_edge", (a, b, *t)),
+        Op::DecayWeights(f) => w.call_method1("decay_weights", (*f,)),
+        Op::PruneEdgesBelow(t) => w.call_method1("prune_edges_below", (*t,)),
+        Op::SetAttribute(a, k, v) => w.call_method1("set_attribute", (a, k, v)),
+        Op::SplitNode(a, p, i, o, keep) => {
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("keep_original", *keep).unwrap();
+            let parts = vec![(p.clone(), i.clone(), o.clone())];
+            w.call_method("split_node", (a, parts), Some(kwargs))
+        }
+        Op::TombstoneNode(a) => w.call_method1("tombstone_node", (a,)),
+        Op::Propagate(a) => w.call_method1("propagate_invalidation", (a,)),
+        Op::Query(m, a, b) => w.call_method1(*m, (a, b)),
+        Op::Whole(m) => w.call_method0(*m),
+        Op::EgoGraph(c, r) => w.call_method1("ego_graph", (c, *r)),
+        Op::Subgraph(ids) => w.call_method1("subgraph", (ids.clone(), false)),
+        Op::Transaction(commit) => w.call_method0("begin_transaction").and_then(|_| {
+            w.call_method1("add_node", ("tx", vec!["n0"]))?;
+            w.call_method0(if *commit { "commit" } else { "rollback" })
+        }),
+        Op::Freeze(frozen) => w.call_method0(if *frozen { "freeze" } else { "unfreeze" }),
+        Op::RoundTrip(kind) => {
+            let copied = match *kind {
+                "json" => w
+                    .call_method0("to_json")
+                    .and_then(|json| w.get_type().call_method1("from_json", (json,))),
+                "pickle" => py
+                    .import("pickle")
+                    .and_then(|p| p.call_method1("loads", (p.call_method1("dumps", (w,))?,))),
+                _ => w.call_method0("to_csr").and_then(|csr| {
+                    w.get_type()
+                        .call_method1("from_csr", csr.downcast::<PyTuple>()?)
+                }),
+            };
+            match copied {
+                Ok(copied) => return copied,
+                Err(e) => Err(e),
+            }
+        }
+    };
+    if let Err(e) = result {
+        assert_not_panic(py, &e);
+    }
+    w
+}
+
+fn assert_not_panic(py: Python, e: &PyErr) {
+    let panic = py.get_type::<pyo3::panic::PanicException>();
+    assert!(!e.get_type(py).is(panic), "Rust panic: {}", e);
+}
+
+/// A walker with `options`, or None if they do not go together.
+fn walker<'py>(py: Python<'py>, rc: &'py PyModule, options: &Options) -> Option<&'py PyAny> {
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("enforce_dag", options.enforce_dag).unwrap();
+    kwargs
+        .set_item("record_events", options.record_events)
+        .unwrap();
+    kwargs
+        .set_item("track_provenance", options.track_provenance)
+        .unwrap();
+    kwargs.set_item("track_heat", options.track_heat).unwrap();
+    if options.undirected {
+        kwargs.set_item("mode", "undirected_multi").unwrap();
+    }
+    if options.lowercase {
+        kwargs
+            .set_item("normalize", PyList::new(py, ["trim", "lowercase"]))
+            .unwrap();
+    }
+    match rc.getattr("GraphWalker").unwrap().call((), Some(kwargs)) {
+        Ok(w) => Some(w),
+        Err(e) => {
+            assert_not_panic(py, &e);
+            None
+        }
+    }
+}
+
+fn run_sequence(options: &Options, ops: &[Op]) {
+    common::with_rc(|py, rc| {
+        let Some(mut w) = walker(py, rc, options) else {
+            return;
+        };
+        for op in ops {
+            w = apply(py, w, op);
+        }
+    });
+}
+
+fn run_cases(cases: u32) {
+    let config = Config {
+        cases,
+        failure_persistence: None,
+        ..Config::default()
+    };
+    // A fixed seed keeps the CI subset deterministic.
+    let rng = TestRng::from_seed(RngAlgorithm::ChaCha, &[7; 32]);
+    let mut runner = TestRunner::new_with_rng(config, rng);
+    let strategy = (options(), prop::collection::vec(op(), 1..40));
+    runner
+        .run(&strategy, |(options, ops)| {
+            run_sequence(&options, &ops);
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn fuzz_smoke_operation_sequences() {
+    let cases = std::env::var("CONCORD_FUZZ_CASES")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(256);
+    run_cases(cases);
+}
+
+#[test]
+fn fuzz_smoke_every_operation_on_an_empty_walker() {
+    let ops: Vec<Op> = ["", "n0"]
+        .iter()
+        .flat_map(|a| {
+            let a = a.to_string();
+            vec![
+                Op::RemoveNode(a.clone()),
+                Op::RemoveEdge(a.clone(), a.clone()),
+                Op::TouchEdge(a.clone(), a.clone(), 1.0),
+                Op::SplitNode(a.clone(), "p".into(), vec![a.clone()], vec![], false),
+                Op::TombstoneNode(a.clone()),
+                Op::Propagate(a.clone()),
+                Op::Query("all_shortest_paths", a.clone(), a.clone()),
+                Op::Query("max_flow", a.clone(), a.clone()),
+                Op::Query("min_cut", a.clone(), a.clone()),
+                Op::EgoGraph(a.clone(), 2),
+                Op::Whole("find_diameter"),
+                Op::Whole("to_csr"),
+                Op::RoundTrip("csr"),
+            ]
+        })
+        .collect();
+    for undirected in [false, true] {
+        let options = Options {
+            enforce_dag: false,
+            record_events: true,
+            track_provenance: true,
+            track_heat: true,
+            undirected,
+            lowercase: false,
+        };
+        run_sequence(&options, &ops);
+    }
+}
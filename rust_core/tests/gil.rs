@@ -0,0 +1,47 @@
+//! Long-running algorithms release the GIL: a Python thread keeps counting while
+//! they run.
+
+mod common;
+
+#[test]
+fn pagerank_lets_other_python_threads_run() {
+    common::run(
+        r#"
+import random, threading
+
+N = 20_000
+rng = random.Random(1)
+indptr, indices = [0], []
+for _ in range(N):
+    indices.extend(rng.randrange(N) for _ in range(5))
+    indptr.append(len(indices))
+w = rc.GraphWalker.from_csr(indptr, indices, ["n%d" % i for i in range(N)])
+
+# The spinner notes the time every 1000 steps. With the GIL held, it could only
+# run in the switch interval (5 ms) just before or after the call.
+ticks, running = [], True
+def spin():
+    step = 0
+    while running:
+        step += 1
+        if step % 1000 == 0:
+            ticks.append(time.monotonic())
+spinner = threading.Thread(target=spin)
+spinner.start()
+try:
+    wait_until(lambda: ticks)
+    # More iterations (a fresh computation each) until the call is long enough to tell.
+    for iterations in (20, 80, 320, 1280):
+        start = time.monotonic()
+        w.calculate_pagerank(iterations, 0.85)
+        end = time.monotonic()
+        if end - start > 0.2:
+            break
+finally:
+    running = False
+    spinner.join()
+during = [t for t in ticks if start + 0.02 < t < end - 0.02]
+assert len(during) > 10, (len(during), end - start)
+"#,
+    );
+}
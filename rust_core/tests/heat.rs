@@ -0,0 +1,120 @@
+//! `track_heat`: exact hit counts after a series of propagations, from Python
+//! threads and from a consumer, and counters staying out of digests, snapshots and
+//! equality.
+
+mod common;
+
+const SETUP: &str = r#"
+import copy, threading
+
+def graph(**kwargs):
+    w = rc.GraphWalker(track_heat=True, **kwargs)
+    w.add_node("a", ["b", "c"])
+    w.add_node("b", ["c"])
+    w.add_node("c", ["d"])
+    w.add_node("e", ["d"])
+    return w
+"#;
+
+#[test]
+fn propagations_count_each_reached_node() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+assert w.hot_nodes(10) == []
+w.propagate_invalidation("a")                 # b c d
+w.propagate_invalidation("b")                 # c d
+w.propagate_invalidation("e")                 # d
+w.propagate_invalidation_with_depth("c")      # d
+w.propagate_invalidation_with_parents("a")    # b c d
+w.propagate_invalidation_attributed(["e"])    # d
+assert w.hot_nodes(10) == [("d", 6), ("c", 3), ("b", 2)], w.hot_nodes(10)
+# Ties are broken by id, and k caps the list.
+w.propagate_invalidation("b")
+assert w.hot_nodes(2) == [("d", 7), ("c", 4)]
+assert w.hot_nodes(0) == []
+
+# Explained runs are not counted.
+w.explain_propagation("a")
+w.explain_invalidation("a", "d")
+assert w.hot_nodes(10) == [("d", 7), ("c", 4), ("b", 2)]
+
+w.reset_heat()
+assert w.hot_nodes(10) == []
+w.propagate_invalidation("e")
+assert w.hot_nodes(10) == [("d", 1)]
+
+# Without the option nothing is counted.
+plain = rc.GraphWalker()
+plain.add_node("a", ["b"])
+plain.propagate_invalidation("a")
+assert plain.hot_nodes(10) == []
+"#,
+    );
+}
+
+#[test]
+fn concurrent_propagations_lose_no_counts() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+for i in range(200):
+    w.add_node("n%d" % i, ["d"])
+THREADS, CALLS = 8, 250
+
+def worker(t):
+    for i in range(CALLS):
+        w.propagate_invalidation("a" if i % 2 else "n%d" % ((t * CALLS + i) % 200))
+
+threads = [threading.Thread(target=worker, args=(t,)) for t in range(THREADS)]
+for t in threads:
+    t.start()
+for t in threads:
+    t.join()
+half = THREADS * CALLS // 2
+assert w.hot_nodes(3) == [("d", 2 * half), ("b", half), ("c", half)], w.hot_nodes(3)
+"#,
+    );
+}
+
+#[test]
+fn consumer_propagations_count_on_the_shared_walker() {
+    common::run_with(
+        SETUP,
+        r#"
+broker = rc.MemoryBroker()
+shared = rc.SharedGraphWalker(graph())
+for start in ("a", "a", "e"):
+    broker.send("facts", json.dumps({"fact_id": start, "op": "invalidate"}))
+handle = rc.start_kafka_consumer(broker, "facts", walker=shared)
+try:
+    wait_until(lambda: handle.status()["messages"] == 3)
+finally:
+    handle.stop()
+shared.propagate_invalidation("b")
+assert shared.hot_nodes(10) == [("d", 4), ("c", 3), ("b", 2)], shared.hot_nodes(10)
+"#,
+    );
+}
+
+#[test]
+fn counters_are_not_graph_content() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+cold = copy.copy(w)
+digest, structural, snapshot = w.content_digest(), w.content_digest(structural=True), w.to_json()
+for start in ("a", "b", "e"):
+    w.propagate_invalidation(start)
+assert w.hot_nodes(1) == [("d", 3)]
+assert w.content_digest() == digest and w.content_digest(structural=True) == structural
+assert w.to_json() == snapshot
+assert w.content_digest() == cold.content_digest()
+# A loaded copy starts with fresh counters.
+assert rc.GraphWalker.from_json(w.to_json()).hot_nodes(1) == []
+"#,
+    );
+}
@@ -0,0 +1,64 @@
+//! `history_metrics` over a scripted event log, checked at every checkpoint.
+
+mod common;
+
+const SETUP: &str = r#"
+def scripted():
+    w = rc.GraphWalker(record_events=True)
+    w.add_node("a", ["b"])      # 1
+    w.add_node("b", ["a"])      # 2: one component, the cycle a <-> b
+    w.add_node("c", [])         # 3
+    w.add_edge("c", "d")        # 4: c is a second component; d is not a node
+    w.remove_node("c")          # 5
+    w.add_edge("d", "a")        # 6: d joins a and b
+    w.add_edge("b", "d")        # 7: d joins the cycle
+    return w
+
+def raises(error, call, fragment):
+    try:
+        call()
+    except error as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+"#;
+
+#[test]
+fn metrics_are_sampled_every_interval_and_at_the_end() {
+    common::run_with(
+        SETUP,
+        r#"
+w = scripted()
+assert w.history_metrics(2) == [
+    {"events": 2, "nodes": 2, "edges": 2, "avg_degree": 1.0, "components": 1},
+    {"events": 4, "nodes": 3, "edges": 3, "avg_degree": 1.0, "components": 2},
+    {"events": 6, "nodes": 3, "edges": 3, "avg_degree": 1.0, "components": 1},
+    {"events": 7, "nodes": 3, "edges": 4, "avg_degree": 4 / 3, "components": 1},
+]
+assert w.history_metrics(3, metrics=["largest_scc", "nodes"]) == [
+    {"events": 3, "largest_scc": 2, "nodes": 3},
+    {"events": 6, "largest_scc": 2, "nodes": 3},
+    {"events": 7, "largest_scc": 3, "nodes": 3},
+]
+# An interval past the end samples only the end state, once.
+assert w.history_metrics(100, metrics=["edges"]) == [{"events": 7, "edges": 4}]
+assert w.history_metrics(7, metrics=["edges"]) == [{"events": 7, "edges": 4}]
+# Sampling does not touch the walker or its log.
+assert len(w.event_log()) == 7 and w.node_ids() == ["a", "b", "d"]
+"#,
+    );
+}
+
+#[test]
+fn bad_arguments_raise() {
+    common::run_with(
+        SETUP,
+        r#"
+w = scripted()
+raises(ValueError, lambda: w.history_metrics(0), "interval_events must be positive")
+raises(ValueError, lambda: w.history_metrics(2, metrics=["diameter"]), "unknown metric 'diameter'")
+raises(ValueError, lambda: rc.GraphWalker().history_metrics(1), "record_events=True")
+assert rc.GraphWalker(record_events=True).history_metrics(5) == []
+"#,
+    );
+}
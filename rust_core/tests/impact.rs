@@ -0,0 +1,100 @@
+//! `impact_scores` against one `propagate_invalidation` call per node, on random
+//! graphs with cycles, dangling dependents and tombstones.
+
+mod common;
+
+use proptest::prelude::*;
+use proptest::test_runner::{Config, RngAlgorithm, TestRng, TestRunner};
+use std::collections::{BTreeSet, HashMap};
+
+/// Dependents of the nodes `n0..`, among the nodes and the ids `x0..x2` that are
+/// not nodes, and the nodes to tombstone.
+fn graph() -> impl Strategy<Value = (Vec<Vec<String>>, Vec<usize>)> {
+    (1..20usize).prop_flat_map(|n| {
+        let dep = prop_oneof![
+            6 => (0..n).prop_map(|i| format!("n{}", i)),
+            1 => (0..3usize).prop_map(|i| format!("x{}", i)),
+        ];
+        (
+            prop::collection::vec(prop::collection::vec(dep, 0..4), n),
+            prop::collection::vec(0..n, 0..3),
+        )
+    })
+}
+
+fn check_scores(dependents: &[Vec<String>], tombstoned: &[usize]) {
+    common::with_rc(|_, rc| {
+        let w = rc.getattr("GraphWalker").unwrap().call0().unwrap();
+        for (i, deps) in dependents.iter().enumerate() {
+            w.call_method1("add_node", (format!("n{}", i), deps.clone()))
+                .unwrap();
+        }
+        for i in tombstoned {
+            w.call_method1("tombstone_node", (format!("n{}", i),))
+                .unwrap();
+        }
+        let scores: HashMap<String, usize> =
+            w.call_method0("impact_scores").unwrap().extract().unwrap();
+        let ids: Vec<String> = w.call_method0("node_ids").unwrap().extract().unwrap();
+        assert_eq!(
+            scores.keys().cloned().collect::<BTreeSet<_>>(),
+            ids.iter().cloned().collect::<BTreeSet<_>>()
+        );
+        for id in &ids {
+            let reached: BTreeSet<String> = w
+                .call_method1("propagate_invalidation", (id,))
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(scores[id], reached.len(), "{} in {:?}", id, dependents);
+        }
+
+        let mut ordered: Vec<(&String, usize)> = scores.iter().map(|(id, &s)| (id, s)).collect();
+        ordered.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        for k in 0..=ids.len() + 1 {
+            let top: HashMap<String, usize> = w
+                .call_method1("impact_scores", (k,))
+                .unwrap()
+                .extract()
+                .unwrap();
+            let prefix: HashMap<String, usize> = ordered
+                .iter()
+                .take(k)
+                .map(|&(id, s)| (id.clone(), s))
+                .collect();
+            assert_eq!(top, prefix, "top_k={}", k);
+        }
+    });
+}
+
+#[test]
+fn impact_scores_equal_single_propagations() {
+    let config = Config {
+        cases: 256,
+        failure_persistence: None,
+        ..Config::default()
+    };
+    let rng = TestRng::from_seed(RngAlgorithm::ChaCha, &[13; 32]);
+    TestRunner::new_with_rng(config, rng)
+        .run(&graph(), |(dependents, tombstoned)| {
+            check_scores(&dependents, &tombstoned);
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn top_k_breaks_ties_by_id() {
+    common::run(
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b"])
+w.add_node("b", ["a"])
+w.add_node("c", ["d"])
+w.add_node("d", [])
+assert w.impact_scores() == {"a": 2, "b": 2, "c": 1, "d": 0}
+assert w.impact_scores(top_k=3) == {"a": 2, "b": 2, "c": 1}
+assert w.impact_scores(top_k=0) == {}
+"#,
+    );
+}
@@ -0,0 +1,98 @@
+//! `cycle_check_steps`: a new edge closing a cycle within the budget is reported
+//! by `new_cycles()`, one that closes none is forgotten, and one whose search runs
+//! out of budget waits in `pending_cycle_edges()` for `verify_pending_cycles()`.
+
+mod common;
+
+const SETUP: &str = r#"
+def chain(w, prefix, n):
+    for i in range(n):
+        w.add_edge("%s%d" % (prefix, i), "%s%d" % (prefix, i + 1))
+"#;
+
+#[test]
+fn a_cycle_within_the_budget_is_confirmed() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(cycle_check_steps=3)
+chain(w, "n", 2)
+assert w.new_cycles() == [] and w.pending_cycle_edges() == []
+w.add_edge("n2", "n0")
+w.add_node("z", ["n1"])
+w.add_edge("n1", "z")
+# Reported as detect_cycles reports them: rotated to start at the smallest id.
+assert w.new_cycles() == [["n0", "n1", "n2"], ["n1", "z"]], w.new_cycles()
+assert all(c in w.detect_cycles() for c in w.new_cycles())
+assert w.pending_cycle_edges() == []
+assert w.new_cycles(clear=True) == [["n0", "n1", "n2"], ["n1", "z"]]
+assert w.new_cycles() == []
+# Re-adding an edge that exists is not a new edge.
+w.add_edge("n2", "n0")
+assert w.new_cycles() == []
+"#,
+    );
+}
+
+#[test]
+fn an_edge_closing_no_cycle_is_not_recorded() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(cycle_check_steps=3)
+chain(w, "n", 5)
+# n5 has no dependents, so the search from it ends at once whatever the chain length.
+w.add_edge("n0", "n5")
+w.add_edge("x", "n4")
+w.add_node("y", ["n3", "x"])
+assert w.new_cycles() == [] and w.pending_cycle_edges() == []
+assert w.verify_pending_cycles() == 0
+
+# Without the option nothing is checked.
+plain = rc.GraphWalker()
+plain.add_edge("a", "b")
+plain.add_edge("b", "a")
+assert plain.new_cycles() == [] and plain.pending_cycle_edges() == []
+assert plain.detect_cycles() == [["a", "b"]]
+
+# Under enforce_dag the edge is rejected instead.
+dag = rc.GraphWalker(cycle_check_steps=3, enforce_dag=True)
+dag.add_edge("a", "b")
+try:
+    dag.add_edge("b", "a")
+except rc.CycleError:
+    pass
+else:
+    raise AssertionError("no CycleError")
+assert dag.new_cycles() == [] and dag.pending_cycle_edges() == []
+"#,
+    );
+}
+
+#[test]
+fn an_exhausted_search_is_verified_later() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(cycle_check_steps=3)
+chain(w, "p", 10)
+w.add_edge("p10", "p0")
+assert w.new_cycles() == []
+assert w.pending_cycle_edges() == [("p10", "p0")]
+
+# A long chain that leads nowhere also exhausts the budget.
+chain(w, "q", 10)
+w.add_edge("r", "q0")
+# An edge removed before verification is skipped.
+chain(w, "s", 10)
+w.add_edge("s10", "s0")
+w.remove_edge("s10", "s0")
+assert w.pending_cycle_edges() == [("p10", "p0"), ("r", "q0"), ("s10", "s0")]
+
+assert w.verify_pending_cycles() == 1
+assert w.pending_cycle_edges() == []
+assert w.new_cycles() == [["p%d" % i for i in range(11)]] == w.detect_cycles()
+assert w.verify_pending_cycles() == 0
+"#,
+    );
+}
@@ -0,0 +1,150 @@
+//! `start_introspection_server`: the JSON shape of each endpoint fetched with a
+//! blocking client, what a publish changes, refused methods and a second start,
+//! and shutdown. One server runs per process, so the snippets take turns.
+
+mod common;
+
+const SETUP: &str = r#"
+import urllib.error, urllib.parse, urllib.request
+
+def fetch(server, path, method="GET"):
+    request = urllib.request.Request("http://127.0.0.1:%d%s" % (server.port, path), method=method)
+    try:
+        with urllib.request.urlopen(request, timeout=5) as response:
+            return response.status, response.headers, response.read().decode()
+    except urllib.error.HTTPError as e:
+        return e.code, e.headers, e.read().decode()
+
+def fetch_json(server, path):
+    status, headers, body = fetch(server, path)
+    assert headers["Content-Type"] == "application/json", headers
+    return status, json.loads(body)
+
+def graph():
+    w = rc.GraphWalker()
+    w.add_node("a", ["b", "c"], attributes={"team": "search"})
+    w.add_edge("a", "c", weight=0.5)
+    w.add_edge_labeled("b", "c", "feeds")
+    w.add_node("d/e f", ["a"])
+    w.tombstone_node("b")
+    return w
+"#;
+
+#[test]
+fn endpoints_answer_with_their_schemas() {
+    common::run_serial(
+        SETUP,
+        r##"
+w = graph()
+server = rc.start_introspection_server(0, walker=w)
+try:
+    status, stats = fetch_json(server, "/stats")
+    assert status == 200
+    assert set(stats) == {"nodes", "edges", "tombstones", "version", "frozen", "published_at"}, stats
+    assert (stats["nodes"], stats["edges"], stats["tombstones"]) == (2, 3, 1)
+    assert stats["version"] == w.version() and stats["frozen"] is False
+    assert abs(stats["published_at"] - time.time()) < 60
+
+    NODE_KEYS = {"id", "dependents", "weights", "labels", "in_degree", "attributes", "tombstoned"}
+    status, node = fetch_json(server, "/node/a")
+    assert status == 200 and set(node) == NODE_KEYS, node
+    assert node == {"id": "a", "dependents": ["b", "c"], "weights": [1.0, 0.5], "labels": [None, None],
+                    "in_degree": 1, "attributes": {"team": "search"}, "tombstoned": False}, node
+    status, node = fetch_json(server, "/node/" + urllib.parse.quote("d/e f", safe=""))
+    assert status == 200 and node["id"] == "d/e f" and node["dependents"] == ["a"], node
+    status, node = fetch_json(server, "/node/b")
+    assert status == 200 and node == {"id": "b", "dependents": [], "weights": [], "labels": [],
+                                      "in_degree": 1, "attributes": {}, "tombstoned": True}, node
+    for path, code in [("/node/missing", 404), ("/node/%zz", 400), ("/nowhere", 404)]:
+        status, body = fetch_json(server, path)
+        assert status == code and set(body) == {"error"}, (path, status, body)
+
+    status, headers, text = fetch(server, "/metrics")
+    assert status == 200 and headers["Content-Type"].startswith("text/plain"), headers
+    samples = dict(line.rsplit(" ", 1) for line in text.splitlines() if not line.startswith("#"))
+    assert samples["concord_graph_nodes"] == "2" and samples["concord_graph_edges"] == "3", samples
+    assert samples["concord_graph_version"] == str(w.version())
+    for line in text.splitlines():
+        if line.startswith("# TYPE "):
+            assert line.split()[3] in ("gauge", "counter"), line
+finally:
+    server.shutdown()
+"##,
+    );
+}
+
+#[test]
+fn consumers_are_listed_with_their_counters() {
+    common::run_serial(
+        SETUP,
+        r#"
+broker = rc.MemoryBroker()
+broker.send("facts", "not json")
+broker.send("facts", json.dumps({"fact_id": "a", "dependents": ["b"], "op": "add"}))
+handle = rc.start_kafka_consumer(broker, "facts", walker=rc.SharedGraphWalker())
+server = rc.start_introspection_server(0)
+try:
+    wait_until(lambda: handle.status()["messages"] == 2)
+    status, consumers = fetch_json(server, "/consumers")
+    assert status == 200
+    mine = [c for c in consumers if c["topic"] == "facts" and c["state"] == "running"]
+    assert len(mine) == 1, consumers
+    assert set(mine[0]) == {"topic", "brokers", "state", "messages", "errors", "malformed", "last_error"}
+    assert (mine[0]["brokers"], mine[0]["messages"], mine[0]["errors"], mine[0]["malformed"]) == ("memory", 2, 0, 1)
+    assert isinstance(mine[0]["last_error"], str)
+    text = fetch(server, "/metrics")[2]
+    assert 'concord_consumer_up{topic="facts"} 1' in text
+    assert 'concord_consumer_malformed_total{topic="facts"} 1' in text
+    # Without a published graph the graph endpoints have nothing to answer.
+    assert fetch_json(server, "/stats")[0] == 503
+    assert fetch_json(server, "/node/a")[0] == 503
+finally:
+    handle.stop()
+    server.shutdown()
+"#,
+    );
+}
+
+#[test]
+fn the_server_is_read_only_single_and_stoppable() {
+    common::run_serial(
+        SETUP,
+        r#"
+w = graph()
+server = rc.start_introspection_server(0, walker=w)
+try:
+    assert server.is_running()
+    for method in ("POST", "PUT", "DELETE", "PATCH"):
+        status, headers, body = fetch(server, "/stats", method)
+        assert status == 405 and headers["Allow"] == "GET, HEAD", (method, status)
+        assert "read-only" in json.loads(body)["error"]
+    status, headers, body = fetch(server, "/stats", "HEAD")
+    assert status == 200 and body == ""
+    try:
+        rc.start_introspection_server(0)
+    except RuntimeError as e:
+        assert "already running on port %d" % server.port in str(e), str(e)
+    else:
+        raise AssertionError("no RuntimeError")
+
+    # The view only changes when published.
+    w.add_node("new", [])
+    assert fetch_json(server, "/stats")[1]["nodes"] == 2
+    assert fetch_json(server, "/node/new")[0] == 404
+    server.publish(w)
+    assert fetch_json(server, "/stats")[1]["nodes"] == 3
+    assert fetch_json(server, "/node/new")[0] == 200
+finally:
+    server.shutdown()
+assert not server.is_running()
+try:
+    fetch(server, "/stats")
+except urllib.error.URLError:
+    pass
+else:
+    raise AssertionError("server still answering")
+# Once stopped, another may start.
+rc.start_introspection_server(0).shutdown()
+"#,
+    );
+}
@@ -0,0 +1,97 @@
+//! `mark_invalid` and `clear_invalid`: an invalidation stops at ids invalidated
+//! earlier and walks again only past the ids a clear or a new edge reopened, as
+//! the `invalid_expanded` and `invalid_reopened` counters show.
+
+mod common;
+
+const SETUP: &str = r#"
+def graph():
+    """The chain a -> b -> c -> d, and a separate region below x of 1000 ids."""
+    w = rc.GraphWalker()
+    for frm, to in [("a", "b"), ("b", "c"), ("c", "d")]:
+        w.add_node(frm, [to])
+    w.add_node("d", [])
+    w.add_node("x", ["r0"])
+    for i in range(1000):
+        w.add_node("r%d" % i, ["r%d" % (i + 1)] if i < 999 else [])
+    return w
+
+def counters(w):
+    stats = w.computation_stats()
+    return stats["invalid_expanded"], stats["invalid_reopened"]
+"#;
+
+#[test]
+fn repeated_invalidations_stop_at_invalid_ids() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+assert w.mark_invalid("x") == sorted("r%d" % i for i in range(1000))
+assert counters(w) == (1001, 0)
+assert w.mark_invalid("a") == ["b", "c", "d"]
+assert counters(w) == (1005, 0), "only the chain is walked"
+# From an invalid id nothing is walked; from a valid one, only up to its
+# invalid dependents.
+assert w.mark_invalid("b") == [] and w.mark_invalid("r500") == []
+assert counters(w) == (1005, 0)
+assert w.mark_invalid("a") == [] and w.mark_invalid("x") == []
+assert counters(w) == (1007, 0)
+assert w.invalid_count() == 1003 and not w.is_invalid("a")
+"#,
+    );
+}
+
+#[test]
+fn a_partial_clear_reopens_only_the_ids_above_it() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+w.mark_invalid("x")
+w.mark_invalid("a")
+start = counters(w)
+assert w.clear_invalid(["c", "missing"]) == 1
+# b, above c, is reopened; d, below it, and the region below x stay closed.
+assert counters(w) == (start[0], start[1] + 1)
+assert w.mark_invalid("a") == ["c"]
+assert counters(w) == (start[0] + 3, start[1] + 1), "a, b and c are walked"
+assert w.mark_invalid("x") == []
+assert counters(w) == (start[0] + 4, start[1] + 1)
+
+# Clearing in the middle of the long region re-marks just what was cleared.
+w.clear_invalid(["r%d" % i for i in range(600, 610)])
+assert counters(w)[1] == start[1] + 1 + 600
+assert w.mark_invalid("x") == sorted("r%d" % i for i in range(600, 610))
+assert w.invalid_count() == 1003
+"#,
+    );
+}
+
+#[test]
+fn a_new_edge_reopens_the_invalid_ids_above_it() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+w.mark_invalid("x")
+w.mark_invalid("a")
+start = counters(w)
+w.add_edge("d", "e")
+w.add_edge("q", "r0")
+# d, c and b above it are reopened, and so is r0, though nothing invalid is
+# above it.
+assert w.mark_invalid("b") == ["e"]
+assert counters(w) == (start[0] + 3, start[1] + 4)
+assert w.mark_invalid("a") == []
+assert counters(w) == (start[0] + 4, start[1] + 4)
+
+# Removals reopen nothing, and clearing everything keeps the counters.
+w.remove_edge("c", "d")
+assert w.mark_invalid("a") == [] and counters(w) == (start[0] + 5, start[1] + 4)
+assert w.clear_invalid() == 1004 and w.invalid_count() == 0
+assert counters(w) == (start[0] + 5, start[1] + 4)
+assert w.mark_invalid("a") == ["b", "c"]
+"#,
+    );
+}
@@ -0,0 +1,106 @@
+//! `explain_invalidation` and `propagate_invalidation_with_parents`: both paths
+//! through a diamond, parents that rebuild shortest paths, cycles that cannot make
+//! the enumeration endless, and the empty answer when nothing is invalidated.
+
+mod common;
+
+const SETUP: &str = r#"
+import random
+
+def build(edges):
+    w = rc.GraphWalker()
+    for a, b in edges:
+        w.add_edge(a, b)
+    return w
+
+def diamond():
+    return build([("s", "a"), ("s", "b"), ("a", "t"), ("b", "t"), ("t", "u")])
+
+def ladder(n):
+    # n diamonds in a row: 2 ** n paths from l0 to ln.
+    return build([(a, b) for i in range(n) for m in "xy"
+                  for a, b in [("l%d" % i, "m%d%s" % (i, m)), ("m%d%s" % (i, m), "l%d" % (i + 1))]])
+"#;
+
+#[test]
+fn a_diamond_has_two_explanations() {
+    common::run_with(
+        SETUP,
+        r#"
+w = diamond()
+assert w.explain_invalidation("s", "t") == [["s", "a", "t"], ["s", "b", "t"]]
+assert w.explain_invalidation("s", "u") == [["s", "a", "t", "u"], ["s", "b", "t", "u"]]
+assert w.explain_invalidation("s", "u", max_paths=1) == [["s", "a", "t", "u"]]
+# Not invalidated: no explanation.
+assert w.explain_invalidation("t", "s") == [] and w.explain_invalidation("a", "b") == []
+
+parents = w.propagate_invalidation_with_parents("s")
+assert parents == {"a": ["s"], "b": ["s"], "t": ["a", "b"], "u": ["t"]}
+assert set(parents) == w.propagate_invalidation("s")
+for call in (lambda: w.explain_invalidation("zz", "t"), lambda: w.explain_invalidation("s", "zz"),
+             lambda: w.propagate_invalidation_with_parents("u")):
+    try:
+        call()
+    except rc.NodeNotFoundError:
+        pass
+    else:
+        raise AssertionError("no NodeNotFoundError")
+assert w.propagate_invalidation_with_parents("zz", strict=False) == {}
+"#,
+    );
+}
+
+#[test]
+fn cycles_and_path_explosions_stay_bounded() {
+    common::run_with(
+        SETUP,
+        r#"
+c = build([("a", "b"), ("b", "c"), ("c", "a"), ("c", "d"), ("b", "d")])
+# Shortest first; no path repeats a node, except a start explaining itself.
+assert c.explain_invalidation("a", "d", max_paths=10) == [["a", "b", "d"], ["a", "b", "c", "d"]]
+assert c.explain_invalidation("a", "a", max_paths=10) == [["a", "b", "c", "a"]]
+assert c.propagate_invalidation_with_parents("a") == {"a": ["c"], "b": ["a"], "c": ["b"], "d": ["b"]}
+
+w = ladder(20)
+paths = w.explain_invalidation("l0", "l20", max_paths=3)
+assert len(paths) == 3 and paths == sorted(paths) and all(len(p) == 41 for p in paths)
+try:
+    w.explain_invalidation("l0", "l20", max_paths=10000, max_visited=100)
+except rc.ResourceLimitError as e:
+    assert e.args[1] == 101, e.args
+else:
+    raise AssertionError("no ResourceLimitError")
+# Tombstoned ids are never crossed.
+w.tombstone_node("m5x", 1.0)
+around = w.explain_invalidation("l0", "l6", max_paths=64)
+assert len(around) == 32 and not any("m5x" in p for p in around)
+"#,
+    );
+}
+
+#[test]
+fn parents_rebuild_shortest_paths() {
+    common::run_with(
+        SETUP,
+        r#"
+for seed in range(5):
+    r = random.Random(seed)
+    w = build([("n%d" % r.randrange(30), "n%d" % r.randrange(30)) for _ in range(70)])
+    for s in w.node_ids()[:6]:
+        parents = w.propagate_invalidation_with_parents(s)
+        assert set(parents) == w.propagate_invalidation(s)
+        for target, via in parents.items():
+            assert via == sorted(via) and all(target in w.get_dependents(p) for p in via)
+            if target == s:
+                continue
+            length = w.shortest_path_length(s, target)
+            # Walk one parent back at a time; each hop is one step closer to s.
+            hops, v = 0, target
+            while v != s:
+                v, hops = parents[v][0], hops + 1
+            assert hops == length, (seed, s, target)
+            first = w.explain_invalidation(s, target, max_paths=1)[0]
+            assert len(first) == length + 1
+"#,
+    );
+}
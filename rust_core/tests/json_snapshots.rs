@@ -0,0 +1,97 @@
+//! `to_json`, `from_json`, `save` and `load`: unicode ids, empty dependents and
+//! weights round-trip, malformed input raises `SerializationError` instead of
+//! panicking, and 100k nodes round-trip through a file.
+
+mod common;
+
+const SETUP: &str = r#"
+import os, tempfile
+
+def same(a, b):
+    ids = sorted(a.node_ids())
+    return ids == sorted(b.node_ids()) and all(
+        a.get_dependents(id) == b.get_dependents(id)
+        and [a.get_edge(id, d)[0] for d in a.get_dependents(id)] == [b.get_edge(id, d)[0] for d in b.get_dependents(id)]
+        for id in ids)
+
+def path():
+    return os.path.join(tempfile.mkdtemp(), "graph.json")
+"#;
+
+#[test]
+fn unicode_ids_and_empty_dependents_round_trip() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+w.add_node("café", ["日本", "emoji-🚀"], weights=[2.5, 1.0])
+w.add_node("日本", [])
+w.add_node("lone", [])
+w.add_node('quoted "id"\n', ["café"])
+data = w.to_json()
+assert json.loads(data)["version"] == 2
+loaded = rc.GraphWalker.from_json(data)
+assert same(w, loaded) and loaded.get_dependents("lone") == [] and not loaded.has_node("emoji-🚀")
+assert loaded.to_json() == data
+p = path()
+w.save(p)
+assert same(w, rc.GraphWalker.load(p))
+assert same(rc.GraphWalker(), rc.GraphWalker.from_json(rc.GraphWalker().to_json()))
+"#,
+    );
+}
+
+#[test]
+fn malformed_input_raises_serialization_error() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b"])
+wrong_version = json.loads(w.to_json())
+wrong_version["version"] = 99
+cases = [
+    ("", "malformed snapshot: EOF while parsing"),
+    ("not json", "malformed snapshot"),
+    ("[]", "malformed snapshot"),
+    ('{"version": 2}', "missing field `checksum`"),
+    (json.dumps(wrong_version), "unsupported snapshot version 99 (expected 2)"),
+    (w.to_json()[:-5], "malformed snapshot"),
+]
+assert issubclass(rc.SerializationError, ValueError)
+for data, fragment in cases:
+    try:
+        rc.GraphWalker.from_json(data)
+    except rc.SerializationError as e:
+        assert fragment in str(e), (data, str(e))
+    else:
+        raise AssertionError("loaded %r" % data)
+try:
+    rc.GraphWalker.load(os.path.join(tempfile.mkdtemp(), "missing.json"))
+except OSError:
+    pass
+else:
+    raise AssertionError("loaded a missing file")
+"#,
+    );
+}
+
+#[test]
+fn a_hundred_thousand_nodes_round_trip() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+w.add_nodes_bulk([("n%d" % i, ["n%d" % ((i * 7 + k) % 100000) for k in range(1, 3)]) for i in range(100000)])
+p = path()
+start = time.time()
+w.save(p)
+loaded = rc.GraphWalker.load(p)
+# A loose bound for debug builds; only a gross slowdown would trip it.
+assert time.time() - start < 60
+assert loaded.node_count() == 100000 and loaded.edge_count() == 200000
+assert loaded.content_digest() == w.content_digest()
+assert loaded.get_dependents("n99999") == w.get_dependents("n99999")
+"#,
+    );
+}
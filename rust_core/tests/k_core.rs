@@ -0,0 +1,82 @@
+//! `k_core_decomposition` and `core_numbers`: a chain attached to a clique, where
+//! the old out-degree filter and the true k-core differ, agreement with Python
+//! peeling on random graphs, and `core_numbers` matching every `k`.
+
+mod common;
+
+const SETUP: &str = r#"
+import itertools, random
+
+def clique_with_chain():
+    # a-b-c-d all connected (each pair once, one direction), then d -> e -> f -> g.
+    w = rc.GraphWalker()
+    for x, y in itertools.combinations("abcd", 2):
+        w.add_edge(x, y)
+    for x, y in [("d", "e"), ("e", "f"), ("f", "g")]:
+        w.add_edge(x, y)
+    w.add_node("g", [])
+    return w
+
+def peel(w, k):
+    neighbors = {v: set() for v in w.node_ids()}
+    for v in w.node_ids():
+        for d in w.get_dependents(v):
+            if d in neighbors and d != v:
+                neighbors[v].add(d)
+                neighbors[d].add(v)
+    alive = set(neighbors)
+    changed = True
+    while changed:
+        changed = False
+        for v in list(alive):
+            if len(neighbors[v] & alive) < k:
+                alive.discard(v)
+                changed = True
+    return sorted(alive)
+"#;
+
+#[test]
+fn peeling_differs_from_the_degree_filter() {
+    common::run_with(
+        SETUP,
+        r#"
+w = clique_with_chain()
+naive = sorted(id for id in w.node_ids() if w.out_degree(id) >= 2)
+assert naive == ["a", "b"]
+# c and d are held in the 3-core by their incoming edges; the chain peels away.
+assert w.k_core_decomposition(2) == w.k_core_decomposition(3) == ["a", "b", "c", "d"]
+assert w.k_core_decomposition(1) == w.k_core_decomposition(0) == ["a", "b", "c", "d", "e", "f", "g"]
+assert w.k_core_decomposition(4) == []
+assert w.core_numbers() == {"a": 3, "b": 3, "c": 3, "d": 3, "e": 1, "f": 1, "g": 1}
+
+# A hub with enough out-degree but only leaves around it is not in the 2-core.
+hub = rc.GraphWalker()
+hub.add_node("hub", ["x", "y", "z"])
+for leaf in "xyz":
+    hub.add_node(leaf, [])
+assert hub.out_degree("hub") == 3 and hub.k_core_decomposition(2) == []
+assert hub.core_numbers() == {"hub": 1, "x": 1, "y": 1, "z": 1}
+assert rc.GraphWalker().core_numbers() == {} and rc.GraphWalker().k_core_decomposition(1) == []
+"#,
+    );
+}
+
+#[test]
+fn core_numbers_agree_with_peeling_for_every_k() {
+    common::run_with(
+        SETUP,
+        r#"
+for seed in range(6):
+    r = random.Random(seed)
+    w = rc.GraphWalker()
+    n = 40
+    for i in range(n):
+        w.add_node("n%d" % i, ["n%d" % r.randrange(n) for _ in range(r.randrange(5))])
+    cores = w.core_numbers()
+    for k in range(max(cores.values()) + 2):
+        expected = peel(w, k)
+        assert w.k_core_decomposition(k) == expected, (seed, k)
+        assert sorted(id for id, c in cores.items() if c >= k) == expected, (seed, k)
+"#,
+    );
+}
@@ -0,0 +1,71 @@
+//! Edge labels: `add_edge_labeled` and `get_dependents_labeled`, a mixed-label
+//! graph whose blast radius shrinks under `follow_labels`, unlabeled edges left
+//! out of any filter, and labels reset by `add_node` but kept in snapshots.
+
+mod common;
+
+const SETUP: &str = r#"
+def mixed():
+    # src derives a, caches b, references c and has an unlabeled edge to u; each
+    # of a, b and c has one further dependent under a different label.
+    w = rc.GraphWalker()
+    for a, b, label in [("src", "a", "derives_from"), ("src", "b", "caches"), ("src", "c", "references"),
+                        ("a", "a2", "references"), ("b", "b2", "derives_from"), ("c", "c2", "derives_from")]:
+        w.add_edge_labeled(a, b, label)
+    w.add_edge("src", "u")
+    return w
+
+def raises(error, call, fragment):
+    try:
+        call()
+    except error as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+"#;
+
+#[test]
+fn follow_labels_shrinks_the_blast_radius() {
+    common::run_with(
+        SETUP,
+        r#"
+w = mixed()
+assert w.get_dependents_labeled("src") == [("a", "derives_from"), ("b", "caches"), ("c", "references"), ("u", None)]
+assert w.get_dependents("src") == ["a", "b", "c", "u"]
+# No filter: every edge, labeled or not.
+assert w.propagate_invalidation("src") == {"a", "a2", "b", "b2", "c", "c2", "u"}
+# c is only referenced, so neither it nor c2 behind it is reached; u is unlabeled.
+assert w.propagate_invalidation("src", follow_labels=["derives_from", "caches"]) == {"a", "b", "b2"}
+assert w.propagate_invalidation("src", follow_labels=["references"]) == {"c"}
+assert w.propagate_invalidation("src", follow_labels=[]) == set()
+assert w.propagate_invalidation("c", follow_labels=["derives_from"]) == {"c2"}
+"#,
+    );
+}
+
+#[test]
+fn labels_are_set_cleared_and_saved() {
+    common::run_with(
+        SETUP,
+        r#"
+w = mixed()
+w.add_edge_labeled("src", "c", None)
+w.add_edge_labeled("src", "u", "caches")
+assert w.get_dependents_labeled("src") == [("a", "derives_from"), ("b", "caches"), ("c", None), ("u", "caches")]
+assert w.propagate_invalidation("src", follow_labels=["caches"]) == {"b", "u"}
+
+loaded = rc.GraphWalker.from_json(w.to_json())
+assert all(loaded.get_dependents_labeled(id) == w.get_dependents_labeled(id) for id in w.node_ids())
+assert loaded.content_digest() == w.content_digest()
+w.add_edge_labeled("a", "a2", "caches")
+assert loaded.content_digest() != w.content_digest()
+
+# Replacing a node resets its labels, as it resets weights.
+w.add_node("src", ["a", "b"])
+assert w.get_dependents_labeled("src") == [("a", None), ("b", None)]
+raises(rc.NodeNotFoundError, lambda: w.get_dependents_labeled("zz"), "zz")
+m = rc.GraphWalker(mode="undirected_multi")
+raises(rc.ModeError, lambda: m.add_edge_labeled("x", "y", "caches"), "only defined for directed graphs")
+"#,
+    );
+}
@@ -0,0 +1,102 @@
+//! `max_results` / `max_visited` on the path, reachability and neighborhood
+//! queries, driven into their caps on a hub graph: `hub` feeds `SPOKES` spokes
+//! that all feed `sink`.
+
+mod common;
+
+const SETUP: &str = r#"
+SPOKES = 200
+w = rc.GraphWalker()
+w.add_node("hub", ["s%03d" % i for i in range(SPOKES)])
+for i in range(SPOKES):
+    w.add_node("s%03d" % i, ["sink"])
+w.add_node("sink", [])
+
+def raises_limit(call):
+    try:
+        call()
+    except rc.ResourceLimitError as e:
+        message, visited, results = e.args
+        assert isinstance(message, str) and visited >= 0 and results >= 0, e.args
+        return e.args
+    raise AssertionError("no ResourceLimitError")
+"#;
+
+#[test]
+fn all_shortest_paths_caps_results_and_visits() {
+    common::run_serial(
+        SETUP,
+        r#"
+assert len(w.all_shortest_paths("hub", "sink")) == SPOKES
+message, visited, results = raises_limit(
+    lambda: w.all_shortest_paths("hub", "sink", max_results=10))
+assert message.startswith("max_results limit of 10"), message
+assert results == 11, results
+message, _, _ = raises_limit(
+    lambda: w.all_shortest_paths("hub", "sink", max_visited=50))
+assert message.startswith("max_visited limit of 50"), message
+# `limit` stops before the cap is reached.
+assert len(w.all_shortest_paths("hub", "sink", limit=10, max_results=10)) == 10
+"#,
+    );
+}
+
+#[test]
+fn all_shortest_paths_limit_keeps_the_first_sorted_paths() {
+    // Walking back from the target would meet s-b-c-t before s-a-d-t.
+    common::run_serial(
+        SETUP,
+        r#"
+g = rc.GraphWalker()
+g.add_node("s", ["b", "a"])
+g.add_node("a", ["d"])
+g.add_node("b", ["c"])
+g.add_node("c", ["t"])
+g.add_node("d", ["t"])
+every = g.all_shortest_paths("s", "t")
+assert every == [["s", "a", "d", "t"], ["s", "b", "c", "t"]], every
+assert g.all_shortest_paths("s", "t", limit=1) == every[:1]
+
+first = w.all_shortest_paths("hub", "sink", limit=5)
+assert first == sorted(w.all_shortest_paths("hub", "sink"))[:5], first
+"#,
+    );
+}
+
+#[test]
+fn reachable_count_and_ego_graph_honor_caps() {
+    common::run_serial(
+        SETUP,
+        r#"
+assert w.reachable_count("hub") == SPOKES + 1
+_, visited, results = raises_limit(lambda: w.reachable_count("hub", max_results=SPOKES))
+assert (visited, results) == (SPOKES + 1, SPOKES + 1)
+raises_limit(lambda: w.reachable_count("hub", max_visited=10))
+assert w.reachable_count("s000", max_results=1) == 1
+
+assert len(w.ego_graph("hub", 2).node_ids()) == SPOKES + 2
+raises_limit(lambda: w.ego_graph("hub", 2, max_results=100))
+raises_limit(lambda: w.ego_graph("sink", 2, direction="in", max_visited=SPOKES))
+assert len(w.ego_graph("s000", 1, max_results=3).node_ids()) == 3
+"#,
+    );
+}
+
+#[test]
+fn module_defaults_apply_when_no_limit_is_passed() {
+    common::run_serial(
+        SETUP,
+        r#"
+rc.set_default_limits({"max_results": 50})
+try:
+    raises_limit(lambda: w.all_shortest_paths("hub", "sink"))
+    raises_limit(lambda: w.reachable_count("hub"))
+    raises_limit(lambda: w.ego_graph("hub", 1))
+    raises_limit(lambda: w.find_ancestors("sink"))
+    # An explicit argument wins over the default.
+    assert len(w.all_shortest_paths("hub", "sink", max_results=SPOKES)) == SPOKES
+finally:
+    rc.set_default_limits({"max_results": None})
+"#,
+    );
+}
@@ -0,0 +1,85 @@
+//! `predict_links`: hand-computed scores for each metric, an edge removed from a
+//! clique coming back as the top prediction, existing edges and self pairs left
+//! out, and agreement with a brute-force ranking on random graphs.
+
+mod common;
+
+const SETUP: &str = r#"
+import itertools, math, random
+
+def build(edges, nodes=()):
+    w = rc.GraphWalker()
+    for id in nodes:
+        w.add_node(id, [])
+    for a, b in edges:
+        w.add_edge(a, b)
+    return w
+
+def brute_common_neighbors(w, k):
+    ids = w.node_ids()
+    near = {id: set() for id in ids}
+    for v in ids:
+        for d in w.get_dependents(v):
+            if d in near and d != v:
+                near[v].add(d)
+                near[d].add(v)
+    scored = [(a, b, float(len(near[a] & near[b]))) for a, b in itertools.combinations(ids, 2)
+              if b not in near[a] and near[a] & near[b]]
+    return sorted(scored, key=lambda t: (-t[2], t[0], t[1]))[:k]
+"#;
+
+#[test]
+fn each_metric_scores_the_missing_edge() {
+    common::run_with(
+        SETUP,
+        r#"
+# K4 on a, b, c, d without a-d, plus a pendant e off d.
+w = build([("a", "b"), ("a", "c"), ("b", "c"), ("b", "d"), ("c", "d"), ("d", "e")], nodes=["e"])
+assert w.predict_links(5) == w.predict_links(5, metric="common_neighbors") == [("a", "d", 2.0), ("b", "e", 1.0), ("c", "e", 1.0)]
+# b, c and d all have degree 3.
+aa = w.predict_links(5, metric="adamic_adar")
+assert [pair[:2] for pair in aa] == [("a", "d"), ("b", "e"), ("c", "e")]
+assert all(abs(s - e) < 1e-12 for (_, _, s), e in zip(aa, [2 / math.log(3), 1 / math.log(3), 1 / math.log(3)]))
+assert w.predict_links(5, metric="preferential_attachment") == [("a", "d", 6.0), ("b", "e", 3.0), ("c", "e", 3.0)]
+assert w.predict_links(1) == [("a", "d", 2.0)] and w.predict_links(0) == []
+# Direction is ignored: reversing every edge changes nothing.
+reverse = [(b, a) for a, b in [("a", "b"), ("a", "c"), ("b", "c"), ("b", "d"), ("c", "d"), ("d", "e")]]
+assert build(reverse, nodes="abcde").predict_links(5) == w.predict_links(5)
+# "e" as a bare edge target is not a node, so it is never paired.
+assert build([("a", "b"), ("a", "c"), ("b", "c"), ("b", "d"), ("c", "d"), ("d", "e")]).predict_links(5) == [("a", "d", 2.0)]
+w.tombstone_node("d", 1.0)
+assert w.predict_links(5) == []
+try:
+    w.predict_links(3, metric="katz")
+except ValueError as e:
+    assert "unknown link prediction metric 'katz'" in str(e), str(e)
+else:
+    raise AssertionError("no ValueError")
+"#,
+    );
+}
+
+#[test]
+fn a_removed_clique_edge_comes_back_first() {
+    common::run_with(
+        SETUP,
+        r#"
+for seed in range(4):
+    r = random.Random(seed)
+    clique = ["k%d" % i for i in range(8)]
+    removed = tuple(sorted(r.sample(clique, 2)))
+    edges = [(a, b) for a, b in itertools.combinations(clique, 2) if (a, b) != removed]
+    # Sparse noise around the clique, with one noise node tied to each member.
+    edges += [("n%d" % r.randrange(40), "n%d" % r.randrange(40)) for _ in range(60)]
+    edges += [(c, "n%d" % i) for i, c in enumerate(clique)]
+    w = build(edges, nodes=["n%d" % i for i in range(40)])
+    for metric in ["common_neighbors", "adamic_adar"]:
+        top = w.predict_links(3, metric=metric)
+        assert top[0][:2] == removed, (seed, metric, top)
+    predicted = w.predict_links(50)
+    assert predicted == brute_common_neighbors(w, 50), seed
+    existing = {tuple(sorted((v, d))) for v in w.node_ids() for d in w.get_dependents(v)}
+    assert all(a < b and (a, b) not in existing for a, b, _ in predicted)
+"#,
+    );
+}
@@ -0,0 +1,30 @@
+//! `stress_test` and the lock counters it reports through `lock_stats()`.
+
+mod common;
+
+#[test]
+fn stress_test_counts_lock_use_and_reads_scale_with_readers() {
+    // One test, because every stress_test run resets the process-wide counters.
+    common::run(
+        r#"
+import os
+
+result = rc.stress_test(2, 1, 200, nodes=10_000)
+stats = rc.lock_stats()
+assert result["read_ops"] > 0 and result["write_ops"] > 0, result
+assert stats["read_acquisitions"] == result["read_ops"], (result, stats)
+assert stats["write_acquisitions"] == result["write_ops"], (result, stats)
+assert stats["max_write_wait_ns"] <= stats["write_wait_ns"], stats
+rc.reset_lock_stats()
+assert set(rc.lock_stats().values()) == {0}, rc.lock_stats()
+
+cpus = len(os.sched_getaffinity(0))
+if cpus < 4:
+    print("reader scaling needs 4 CPUs, found %d; not checked" % cpus)
+else:
+    single = rc.stress_test(1, 0, 1000, nodes=500_000)["read_ops_per_sec"]
+    four = rc.stress_test(4, 0, 1000, nodes=500_000)["read_ops_per_sec"]
+    assert four > 2 * single, (single, four)
+"#,
+    );
+}
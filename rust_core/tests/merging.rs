@@ -0,0 +1,111 @@
+//! `merge` and `GraphWalker.union`: overlapping nodes keep every edge, duplicate
+//! edges are stored once, conflicts go last-wins or through `on_conflict`,
+//! disjoint graphs just combine, and mismatched or self merges are refused.
+
+mod common;
+
+const SETUP: &str = r#"
+import copy, random
+
+def shards():
+    # Both have x -> y with different weights, y -> z with different labels, and
+    # x's "t" attribute with different values.
+    a = rc.GraphWalker()
+    a.add_node("x", ["y", "z"], weights=[1.0, 2.0], attributes={"t": "1", "k": "a"})
+    a.add_edge_labeled("y", "z", "caches")
+    b = rc.GraphWalker()
+    b.add_node("x", ["y", "w"], weights=[3.0, 1.0], attributes={"t": "2", "n": "b"})
+    b.add_edge_labeled("y", "z", "refs")
+    b.add_node("new", [])
+    return a, b
+
+def labeled(w):
+    return {id: w.get_dependents_labeled(id) for id in w.node_ids()}
+"#;
+
+#[test]
+fn overlapping_walkers_merge_last_wins() {
+    common::run_with(
+        SETUP,
+        r#"
+a, b = shards()
+c = copy.deepcopy(a)
+assert c.merge(b) == {"nodes_added": 1, "edges_added": 1, "conflicts": 3}
+assert labeled(c) == {"new": [], "x": [("y", None), ("z", None), ("w", None)], "y": [("z", "refs")]}
+assert c.get_edge("x", "y")[0] == 3.0 and c.get_edge("x", "z")[0] == 2.0
+assert c.get_attributes("x") == {"t": "2", "k": "a", "n": "b"}
+# Merging the same graph again finds every edge already present.
+assert c.merge(b) == {"nodes_added": 0, "edges_added": 0, "conflicts": 0}
+assert c.get_dependents("x") == ["y", "z", "w"]
+assert rc.GraphWalker.union([a, a]).get_dependents("x") == ["y", "z"]
+# union applies the same rule left to right.
+assert rc.GraphWalker.union([a, b]).content_digest(structural=True) == c.content_digest(structural=True)
+assert labeled(rc.GraphWalker.union([b, a]))["y"] == [("z", "caches")]
+"#,
+    );
+}
+
+#[test]
+fn on_conflict_picks_the_kept_value() {
+    common::run_with(
+        SETUP,
+        r#"
+a, b = shards()
+calls = []
+def keep(kind, key, ours, theirs):
+    calls.append((kind, key, ours, theirs))
+    return max(ours, theirs) if kind == "weight" else ours
+c = copy.deepcopy(a)
+assert c.merge(b, on_conflict=keep)["conflicts"] == 3
+assert sorted(calls) == [("attribute", ("x", "t"), "1", "2"), ("label", ("y", "z"), "caches", "refs"),
+                         ("weight", ("x", "y"), 1.0, 3.0)]
+assert c.get_edge("x", "y")[0] == 3.0 and c.get_attributes("x")["t"] == "1"
+assert c.get_dependents_labeled("y") == [("z", "caches")]
+
+# A missing label or attribute never conflicts with a present one.
+p, q = rc.GraphWalker(), rc.GraphWalker()
+p.add_node("m", ["n"], attributes={"a": "1"})
+q.add_edge_labeled("m", "n", "caches")
+q.set_attribute("m", "b", "2")
+assert p.merge(q, on_conflict=keep)["conflicts"] == 0 and len(calls) == 3
+assert p.get_dependents_labeled("m") == [("n", "caches")] and p.get_attributes("m") == {"a": "1", "b": "2"}
+"#,
+    );
+}
+
+#[test]
+fn disjoint_shards_union_into_one_graph() {
+    common::run_with(
+        SETUP,
+        r#"
+r = random.Random(3)
+edges = [("n%d" % r.randrange(200), "n%d" % r.randrange(200)) for _ in range(600)]
+whole = rc.GraphWalker()
+parts = [rc.GraphWalker() for _ in range(4)]
+for i, (frm, to) in enumerate(edges):
+    whole.add_edge(frm, to)
+    # A repeated edge often lands in another shard; the union stores it once.
+    parts[i % 4].add_edge(frm, to)
+u = rc.GraphWalker.union(parts)
+assert u.node_ids() == whole.node_ids()
+assert all(sorted(u.get_dependents(id)) == sorted(set(whole.get_dependents(id))) for id in whole.node_ids())
+assert rc.GraphWalker.union([]).node_ids() == []
+
+x, y = rc.GraphWalker(), rc.GraphWalker()
+x.add_node("a", ["b"])
+y.add_node("q", ["r"])
+assert x.merge(y) == {"nodes_added": 1, "edges_added": 1, "conflicts": 0}
+assert labeled(x) == {"a": [("b", None)], "q": [("r", None)]}
+
+for other, error, message in [(x, ValueError, "cannot merge a walker into itself"),
+                              (rc.GraphWalker(mode="undirected_multi"), rc.ModeError,
+                               "cannot merge a 'undirected_multi' walker into a 'directed' walker")]:
+    try:
+        x.merge(other)
+    except error as e:
+        assert str(e) == message, str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+"#,
+    );
+}
@@ -0,0 +1,116 @@
+//! `metrics_table`: every column aligned with the `id` column and equal to the
+//! metric computed on its own, cached columns until the next mutation, and the
+//! rejected names and formats.
+
+mod common;
+
+const SETUP: &str = r#"
+ALL = ["pagerank", "degree", "in_degree", "out_degree", "core_number", "community"]
+
+def graph():
+    w = rc.GraphWalker()
+    w.add_node("a", ["b", "c"])
+    w.add_node("b", ["c"])
+    w.add_node("c", ["a"])
+    w.add_node("d", ["e"])
+    w.add_edge("x", "y")
+    w.add_edge("x", "d")
+    w.add_node("lone", [])
+    return w
+
+def counters(w):
+    stats = w.computation_stats()
+    return stats["computed"], stats["hits"]
+"#;
+
+#[test]
+fn columns_align_with_individually_computed_metrics() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+table = w.metrics_table(ALL)
+assert list(table) == ["id"] + ALL
+ids = table["id"]
+assert ids == sorted(w.node_ids()) == ["a", "b", "c", "d", "lone", "x"]
+assert all(len(column) == len(ids) for column in table.values())
+row = {id: {m: table[m][i] for m in ALL} for i, id in enumerate(ids)}
+
+ranks = w.calculate_pagerank(20, 0.85)
+cores = w.core_numbers()
+community = {id: i for i, members in enumerate(w.weakly_connected_components()) for id in members}
+for id in ids:
+    assert abs(row[id]["pagerank"] - ranks[id]) < 1e-12, id
+    assert row[id]["in_degree"] == w.in_degree(id)
+    assert row[id]["out_degree"] == w.out_degree(id)
+    assert row[id]["degree"] == w.in_degree(id) + w.out_degree(id)
+    assert row[id]["core_number"] == cores[id]
+    assert row[id]["community"] == community[id]
+assert [row["a"][m] for m in ("in_degree", "out_degree", "core_number", "community")] == [1, 2, 2, 0]
+assert row["d"]["in_degree"] == 1 and row["lone"]["degree"] == 0
+
+# Any subset, in the order asked, with the same values.
+subset = w.metrics_table(["community", "in_degree"])
+assert list(subset) == ["id", "community", "in_degree"]
+assert subset["in_degree"] == table["in_degree"] and subset["community"] == table["community"]
+assert rc.GraphWalker().metrics_table(ALL) == {"id": [], **{m: [] for m in ALL}}
+"#,
+    );
+}
+
+#[test]
+fn columns_are_cached_until_the_next_mutation() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+first = w.metrics_table(["pagerank", "in_degree"])
+computed, hits = counters(w)
+assert w.metrics_table(["pagerank", "in_degree"]) == first
+assert counters(w)[0] == computed and counters(w)[1] > hits
+w.metrics_table(["in_degree"])
+assert counters(w)[0] == computed
+
+w.add_edge("lone", "a")
+after = w.metrics_table(["pagerank", "in_degree"])
+assert counters(w)[0] > computed
+assert after["in_degree"][0] == first["in_degree"][0] + 1
+assert after["pagerank"] != first["pagerank"]
+"#,
+    );
+}
+
+#[test]
+fn unknown_metrics_and_formats_are_rejected() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+for call, fragment in [
+    (lambda: w.metrics_table(["pagerank", "bogus"]), "unknown metric 'bogus' (supported: pagerank, degree, in_degree, out_degree, core_number, community)"),
+    (lambda: w.metrics_table(["pagerank"], format="csv"), "format must be 'dict' or 'arrow', got 'csv'"),
+]:
+    try:
+        call()
+    except ValueError as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no ValueError")
+
+# The arrow format needs pyarrow; without it the import error surfaces.
+try:
+    import pyarrow
+except ImportError:
+    try:
+        w.metrics_table(["pagerank"], format="arrow")
+    except ImportError:
+        pass
+    else:
+        raise AssertionError("no ImportError")
+else:
+    arrow = w.metrics_table(ALL, format="arrow")
+    assert arrow.column_names == ["id"] + ALL
+    assert arrow.to_pydict() == w.metrics_table(ALL)
+"#,
+    );
+}
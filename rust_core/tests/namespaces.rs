@@ -0,0 +1,98 @@
+//! `namespace_by_topic`: the same id arriving on two topics becomes two nodes,
+//! each prefixed with its topic, an already-qualified id links the namespaces, and
+//! the module helpers take such ids apart.
+
+mod common;
+
+const SETUP: &str = r#"
+broker = rc.MemoryBroker()
+
+def fact(id, dependents=(), op="add"):
+    return json.dumps({"fact_id": id, "dependents": list(dependents), "op": op})
+
+def consume(messages, count, **options):
+    """A walker fed from the start of both topics until `count` messages are read."""
+    shared = rc.SharedGraphWalker()
+    for topic, payload in messages:
+        broker.send(topic, payload)
+    handle = rc.start_kafka_consumer(broker, ["orders", "billing"], walker=shared, **options)
+    try:
+        wait_until(lambda: handle.status()["messages"] == count)
+    finally:
+        handle.stop()
+    return shared
+"#;
+
+#[test]
+fn identical_ids_from_two_topics_stay_distinct() {
+    common::run_with(
+        SETUP,
+        r#"
+shared = consume(
+    [("orders", fact("user-1", ["order-9"])), ("billing", fact("user-1", ["invoice-3"]))],
+    2,
+    namespace_by_topic=True,
+)
+g = shared.snapshot()
+assert g.node_ids() == ["billing::user-1", "orders::user-1"], g.node_ids()
+assert g.get_dependents("orders::user-1") == ["orders::order-9"]
+assert g.get_dependents("billing::user-1") == ["billing::invoice-3"]
+assert g.nodes_by_namespace() == {"billing": ["billing::user-1"], "orders": ["orders::user-1"]}
+
+# Without the option the second message replaces the first.
+plain = consume([], 2).snapshot()
+assert plain.node_ids() == ["user-1"] and plain.get_dependents("user-1") == ["invoice-3"]
+assert plain.nodes_by_namespace() == {"": ["user-1"]}
+"#,
+    );
+}
+
+#[test]
+fn qualified_ids_link_namespaces_and_propagation_crosses_them() {
+    common::run_with(
+        SETUP,
+        r#"
+shared = consume(
+    [
+        ("orders", fact("user-1", ["order-9"])),
+        ("billing", fact("orders::order-9", ["invoice-3"])),
+        ("orders", fact("user-1", op="invalidate")),
+    ],
+    3,
+    namespace_by_topic=True,
+)
+assert shared.get_dependents("orders::order-9") == ["billing::invoice-3"]
+assert shared.propagate_invalidation("orders::user-1") == {"orders::order-9", "billing::invoice-3"}
+log = shared.recent_invalidations()
+assert [(e["fact_id"], e["invalidated"]) for e in log] == [
+    ("orders::user-1", ["billing::invoice-3", "orders::order-9"])
+], log
+
+# A custom separator.
+broker = rc.MemoryBroker()
+slashed = consume([("billing", fact("user-1", ["orders/order-9"]))], 1, namespace_by_topic=True, separator="/")
+assert slashed.has_node("billing/user-1")
+assert slashed.get_dependents("billing/user-1") == ["orders/order-9"]
+assert slashed.snapshot().nodes_by_namespace(separator="/")["billing"] == ["billing/user-1"]
+"#,
+    );
+}
+
+#[test]
+fn helpers_build_and_split_namespaced_ids() {
+    common::run(
+        r#"
+assert rc.namespace_id("orders", "user-1") == "orders::user-1"
+assert rc.namespace_id("orders", "user-1", separator="/") == "orders/user-1"
+# The first separator ends the namespace.
+assert rc.namespace_of("orders::a::b") == "orders"
+assert rc.strip_namespace("orders::a::b") == "a::b"
+assert rc.namespace_of("plain") is None and rc.strip_namespace("plain") == "plain"
+assert rc.namespace_of("orders/a", separator="/") == "orders"
+assert rc.namespace_of("orders::a", separator="") is None
+for ns, id in [("orders", "user-1"), ("billing", "x::y")]:
+    full = rc.namespace_id(ns, id)
+    assert (rc.namespace_of(full), rc.strip_namespace(full)) == (ns, id)
+"#,
+    );
+}
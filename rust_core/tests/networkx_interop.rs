@@ -0,0 +1,91 @@
+//! `to_networkx_edges` and `GraphWalker.from_edge_list`: weights, labels and
+//! attributes ride along, isolated nodes survive, plain `G.nodes` / `G.edges`
+//! work, and a full round trip through networkx gives back an equal graph.
+
+mod common;
+
+const SETUP: &str = r#"
+import random
+try:
+    import networkx as nx
+except ImportError:
+    nx = None
+
+def walker():
+    w = rc.GraphWalker()
+    w.add_node("a", ["b", "c"], weights=[2.0, 1.0], attributes={"team": "search"})
+    w.add_edge_labeled("b", "c", "caches")
+    w.add_node("c", [])
+    w.add_node("lone", [])
+    w.add_edge("c", "target")
+    return w
+
+def to_nx(w):
+    nodes, edges = w.to_networkx_edges()
+    G = nx.DiGraph()
+    G.add_nodes_from(nodes)
+    G.add_edges_from(edges)
+    return G
+"#;
+
+#[test]
+fn edges_and_nodes_carry_their_data() {
+    common::run_with(
+        SETUP,
+        r#"
+w = walker()
+nodes, edges = w.to_networkx_edges()
+assert nodes == [("a", {"team": "search"}), ("b", {}), ("c", {}), ("lone", {})]
+assert [(a, b) for a, b, _ in edges] == [("a", "b"), ("a", "c"), ("b", "c"), ("c", "target")]
+assert [d["weight"] for _, _, d in edges] == [2.0, 1.0, 1.0, 1.0]
+assert edges[2][2]["label"] == "caches" and "label" not in edges[0][2]
+assert all(d["observed_at"] == w.get_edge(a, b)[1] for a, b, d in edges)
+
+g = rc.GraphWalker.from_edge_list(nodes, edges)
+assert g.content_digest() == w.content_digest()
+# Plain ids and (from, to) or (from, to, weight) tuples work too; a repeat overwrites.
+h = rc.GraphWalker.from_edge_list(["iso", ("x", {"rank": 3})], [("x", "y"), ("x", "z", 2.5), ("x", "z", 4.0)])
+assert h.node_ids() == ["iso", "x"] and h.get_attributes("x") == {"rank": "3"}
+assert h.get_dependents("x") == ["y", "z"] and h.get_edge("x", "z")[0] == 4.0 and h.get_edge("x", "y")[0] == 1.0
+try:
+    rc.GraphWalker.from_edge_list(["a"], [("a",)])
+except ValueError as e:
+    assert "an edge is (from, to) or (from, to, data), got 1 elements" in str(e), str(e)
+else:
+    raise AssertionError("no ValueError")
+"#,
+    );
+}
+
+#[test]
+fn graphs_round_trip_through_networkx() {
+    common::run_with(
+        SETUP,
+        r#"
+if nx is not None:
+    w = walker()
+    G = to_nx(w)
+    # networkx creates the bare target as an attribute-less node.
+    assert sorted(G.nodes) == ["a", "b", "c", "lone", "target"] and G.nodes["a"] == {"team": "search"}
+    assert G.edges["a", "b"]["weight"] == 2.0 and G.edges["b", "c"]["label"] == "caches"
+    back = rc.GraphWalker.from_edge_list(list(G.nodes(data=True)), list(G.edges(data=True)))
+    assert nx.utils.graphs_equal(to_nx(back), G)
+    assert back.node_ids() == ["a", "b", "c", "lone", "target"]
+    assert back.get_dependents_labeled("b") == [("c", "caches")] and back.get_edge("a", "b") == w.get_edge("a", "b")
+
+    for seed in range(4):
+        r = random.Random(seed)
+        R = nx.gnm_random_graph(40, 90, seed=seed, directed=True)
+        R = nx.relabel_nodes(R, {i: "n%d" % i for i in R})
+        for a, b in R.edges:
+            R.edges[a, b]["weight"] = r.choice([0.5, 1.0, 3.0])
+        for v in r.sample(list(R.nodes), 5):
+            R.nodes[v]["kind"] = r.choice(["x", "y"])
+        g = rc.GraphWalker.from_edge_list(list(R.nodes(data=True)), list(R.edges(data=True)))
+        assert g.node_count() == 40 and g.edge_count() == 90
+        out = to_nx(g)
+        assert sorted(out.nodes(data=True)) == sorted(R.nodes(data=True)), seed
+        assert sorted((a, b, d["weight"]) for a, b, d in out.edges(data=True)) == sorted(R.edges(data="weight")), seed
+"#,
+    );
+}
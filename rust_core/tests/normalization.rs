@@ -0,0 +1,144 @@
+//! Id normalization at every entry point: queries look ids up as the walker stores
+//! them, the bulk constructors take `normalize=`, and consumers find facts through
+//! the shared walker's normalizer.
+
+mod common;
+
+const SETUP: &str = r#"
+import os, tempfile
+
+STEPS = ["trim", "lowercase"]
+
+def graph():
+    w = rc.GraphWalker(normalize=STEPS)
+    w.add_node("Fact-A", ["Fact-B", "Fact-C"], attributes={"team": "search"})
+    w.add_node("Fact-B", ["Fact-C"])
+    w.add_node("Fact-C", [])
+    return w
+"#;
+
+#[test]
+fn queries_find_nodes_by_mixed_case_ids() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+assert w.has_node("FACT-A") and "FACT-A " in w and w.has_node(" fact-a")
+assert w.get_dependents("FACT-A") == ["fact-b", "fact-c"]
+assert w["FACT-A"] == ["fact-b", "fact-c"]
+assert w.get_attributes("FACT-A") == {"team": "search"}
+assert w.get_attrs_bulk(["FACT-A", "Fact-B"], "team") == ["search", None]
+assert w.get_edge("FACT-A", "FACT-B") is not None
+assert w.out_degree("FACT-A") == 2 and w.in_degree("FACT-B") == 1
+assert w.propagate_invalidation("FACT-A") == {"fact-b", "fact-c"}
+assert w.propagate_invalidation_multi(["FACT-A", "FACT-B"]) == {"fact-b", "fact-c"}
+assert w.find_ancestors("FACT-C") == {"fact-a", "fact-b"}
+assert w.is_reachable("FACT-A", "FACT-C") and w.reachable_count("FACT-A") == 2
+assert w.shortest_path("FACT-A", "FACT-C") == ["fact-a", "fact-c"]
+assert w.all_shortest_paths("FACT-A", " Fact-C ") == [["fact-a", "fact-c"]]
+assert w.shortest_path_lengths(["FACT-A"], ["FACT-C"]) == {("fact-a", "fact-c"): 1}
+assert w.ego_graph("FACT-B", 1, direction="in").node_ids() == ["fact-a", "fact-b"]
+assert sorted(w.subgraph(["FACT-A", "FACT-B"]).node_ids()) == ["fact-a", "fact-b"]
+assert w.calculate_jaccard_similarity("FACT-A", "FACT-B") == 0.5
+assert w.max_flow("FACT-A", "FACT-C", capacities={("FACT-A", "FACT-C"): 3}) == 4
+assert w.mark_invalid("FACT-B") == ["fact-c"] and w.is_invalid("FACT-C")
+assert w.clear_invalid(["FACT-C"]) == 1
+# Lookups record no original form for ids that are not in the graph.
+assert not w.has_node("GHOST") and w.original_id("ghost") is None
+assert w.original_id("fact-a") == "Fact-A"
+"#,
+    );
+}
+
+#[test]
+fn mixed_case_ids_remove_the_node_they_find() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+assert w.remove_node("FACT-A")
+assert not w.has_node("FACT-A") and not w.has_node("fact-a")
+w.tombstone_node("FACT-B")
+assert not w.has_node("Fact-B") and [id for id, _ in w.tombstones()] == ["fact-b"]
+try:
+    w.get_dependents("FACT-A")
+except rc.NodeNotFoundError:
+    pass
+else:
+    raise AssertionError("no NodeNotFoundError")
+"#,
+    );
+}
+
+#[test]
+fn bulk_constructors_take_a_normalizer() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker.from_edge_list(["A", ("a", {"k": "v"})], [("A", "B"), ("a", "C")], normalize=STEPS)
+assert w.node_ids() == ["a"] and w.get_dependents("A") == ["b", "c"]
+assert w.get_attributes("a") == {"k": "v"} and w.original_id("a") == "A"
+assert w.normalize("X ") == "x"
+
+w = rc.GraphWalker.from_csr([0, 1, 1], [1], ["X", "Y"], normalize="lowercase")
+assert w.node_ids() == ["x", "y"] and w.get_dependents("X") == ["y"]
+
+fd, path = tempfile.mkstemp(suffix=".csv")
+with os.fdopen(fd, "w") as f:
+    f.write("source,target,weight\nA,B,2\n a,C,1\n")
+try:
+    w = rc.GraphWalker.from_edge_file(path, weight_col="weight", normalize=STEPS)
+finally:
+    os.remove(path)
+assert w.get_dependents("A") == ["b", "c"] and w.get_edge("a", "b")[0] == 2.0
+
+raw = rc.GraphWalker(enforce_dag=True)
+raw.add_node("P", ["Q"])
+raw.add_node("Gone", [])
+raw.tombstone_node("Gone", 5.0)
+raw.mark_invalid("P")
+assert raw.get_invalid() == {"Q"}
+assert "p" not in rc.GraphWalker.from_json(raw.to_json())
+w = rc.GraphWalker.from_json(raw.to_json(), normalize="lowercase")
+assert w.get_dependents("P") == ["q"] and w.is_dag_enforced()
+assert w.tombstones() == [("gone", 5.0)], w.tombstones()
+assert w.get_invalid() == {"q"}, w.get_invalid()
+fd, path = tempfile.mkstemp(suffix=".json")
+os.close(fd)
+try:
+    raw.save_snapshot(path)
+    w = rc.GraphWalker.load_snapshot(path, normalize="lowercase")
+finally:
+    os.remove(path)
+assert w.node_ids() == ["p"] and w.original_id("p") == "P"
+"#,
+    );
+}
+
+#[test]
+fn consumers_look_facts_up_through_the_walker_normalizer() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(normalize=STEPS, record_events=True)
+w.add_node("fact-a", ["fact-b"])
+shared = rc.SharedGraphWalker(w)
+broker = rc.MemoryBroker()
+broker.send("facts", json.dumps({"fact_id": "FACT-A", "dependents": ["Fact-B"]}),
+            timestamp=4_000_000_000_000)
+broker.send("facts", json.dumps({"fact_id": "FACT-A", "op": "invalidate"}))
+handle = rc.start_kafka_consumer(broker, "facts", walker=shared)
+wait_until(lambda: len(shared.recent_invalidations()) == 1)
+handle.stop()
+assert handle.metrics()["processing_errors"] == 0, handle.metrics()
+
+entry = shared.recent_invalidations()[0]
+assert entry["fact_id"] == "fact-a" and entry["invalidated"] == ["fact-b"], entry
+assert shared.has_node("FACT-A") and shared.get_dependents("FACT-A") == ["fact-b"]
+assert shared.propagate_invalidation("FACT-A") == {"fact-b"}
+g = shared.snapshot()
+assert g.event_log()[-1]["op"] == "touch_edge", g.event_log()[-1]
+assert g.get_edge("FACT-A", "FACT-B") == (1.0, 4_000_000_000.0)
+"#,
+    );
+}
@@ -0,0 +1,79 @@
+//! `calculate_pagerank(tolerance=...)`: early termination well before the cap,
+//! `pagerank_convergence` reporting iterations and delta, the cap still holding,
+//! empty and single-node graphs, and rejected tolerances. Deterministic mode is
+//! process-wide, so the snippets take turns.
+
+mod common;
+
+const SETUP: &str = r#"
+def graph():
+    w = rc.GraphWalker()
+    w.add_node("a", ["b"])
+    w.add_node("b", ["c"])
+    w.add_node("c", ["a"])
+    w.add_node("d", ["a"])
+    return w
+"#;
+
+#[test]
+fn iteration_stops_once_the_ranks_settle() {
+    common::run_serial(
+        SETUP,
+        r#"
+w = graph()
+ranks = w.calculate_pagerank(1000, 0.85, tolerance=1e-9)
+run = w.pagerank_convergence(1000, 0.85, tolerance=1e-9)
+assert run["converged"] and run["delta"] < 1e-9, run
+assert 10 < run["iterations"] < 200, run
+# Only the iterations reported were run, not the cap.
+assert w.computation_stats()["pagerank_iterations"] == run["iterations"]
+
+full = w.calculate_pagerank(1000, 0.85)
+uncapped = w.pagerank_convergence(1000, 0.85)
+assert uncapped["iterations"] == 1000 and not uncapped["converged"], uncapped
+assert sum(abs(ranks[id] - full[id]) for id in full) < 1e-8
+assert abs(sum(ranks.values()) - 1.0) < 1e-9
+
+# The cap wins when the tolerance is out of reach.
+capped = w.pagerank_convergence(5, 0.85, 1e-12)
+assert capped["iterations"] == 5 and not capped["converged"] and capped["delta"] > 1e-12, capped
+# A looser tolerance stops sooner.
+assert w.pagerank_convergence(1000, 0.85, 1e-3)["iterations"] < run["iterations"]
+"#,
+    );
+}
+
+#[test]
+fn tiny_graphs_and_bad_tolerances() {
+    common::run_serial(
+        SETUP,
+        r#"
+empty = rc.GraphWalker()
+assert empty.calculate_pagerank(100, 0.85, 1e-6) == {}
+assert empty.pagerank_convergence(100, 0.85, 1e-6) == {"iterations": 0, "delta": None, "converged": True}
+
+single = rc.GraphWalker()
+single.add_node("x", [])
+assert single.calculate_pagerank(100, 0.85, 1e-6) == {"x": 1.0}
+assert single.pagerank_convergence(100, 0.85, 1e-6) == {"iterations": 1, "delta": 0.0, "converged": True}
+
+w = graph()
+for tolerance in (-1.0, float("nan")):
+    for call in (w.calculate_pagerank, w.pagerank_convergence):
+        try:
+            call(10, 0.85, tolerance)
+        except ValueError as e:
+            assert "tolerance must be a non-negative number" in str(e), str(e)
+        else:
+            raise AssertionError("no ValueError for %r" % tolerance)
+
+# Deterministic mode stops after the same number of iterations.
+expected = w.pagerank_convergence(1000, 0.85, 1e-9)["iterations"]
+rc.configure({"deterministic": True})
+try:
+    assert graph().pagerank_convergence(1000, 0.85, 1e-9)["iterations"] == expected
+finally:
+    rc.configure({"deterministic": False})
+"#,
+    );
+}
@@ -0,0 +1,105 @@
+//! Paged results: windows and cursor pages reassemble the full result, and
+//! cursors refuse to continue once the graph has changed.
+
+mod common;
+
+const SETUP: &str = r#"
+def graph():
+    w = rc.GraphWalker()
+    for i in range(300):
+        w.add_node("n%03d" % i, ["n%03d" % ((i * 7 + k) % 300) for k in range(1, 4)])
+        if i % 10 == 0:
+            w.set_edge_weight("n%03d" % i, "n%03d" % ((i * 7 + 1) % 300), 0.5)
+    return w
+
+def drain(cursor, n):
+    rows = []
+    while page := cursor.next_page(n):
+        assert len(page) <= n
+        rows.extend(page)
+    return rows
+
+def stale(call):
+    try:
+        call()
+    except rc.StaleCursorError as e:
+        return str(e)
+    raise AssertionError("no StaleCursorError")
+"#;
+
+#[test]
+fn pages_reassemble_the_full_result() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+ids = w.nodes()
+assert ids == sorted(w.node_ids()) and len(ids) == 300
+edges = w.edges()
+assert edges == sorted(edges, key=lambda e: (e[0], e[1])) and len(edges) == 900
+assert ("n000", "n001", 0.5) in edges and ("n001", "n008", 1.0) in edges
+reached = w.propagate_invalidation_sorted("n000")
+assert reached == sorted(w.propagate_invalidation("n000"))
+
+for n in [1, 7, 300, 1000]:
+    assert drain(w.nodes_cursor(), n) == ids
+    assert drain(w.edges_cursor(), n) == edges
+    assert drain(w.propagate_cursor("n000"), n) == reached
+    windows = [w.edges(offset=o, limit=n) for o in range(0, 900, n)]
+    assert [e for window in windows for e in window] == edges
+assert w.nodes(offset=295) == ids[295:] and w.nodes(offset=400, limit=5) == []
+assert w.propagate_invalidation_sorted("n000", offset=10, limit=3) == reached[10:13]
+
+cursor = w.edges_cursor()
+assert (cursor.total(), cursor.remaining(), cursor.version()) == (900, 900, w.version())
+assert cursor.next_page(0) == [] and cursor.remaining() == 900
+assert cursor.next_page(899) == edges[:899] and cursor.remaining() == 1
+assert cursor.next_page(5) == edges[899:] and cursor.next_page(5) == [] and cursor.remaining() == 0
+assert list(iter(w)) == ids
+"#,
+    );
+}
+
+#[test]
+fn a_mutation_makes_cursors_stale() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+cursor = w.nodes_cursor()
+first = cursor.next_page(100)
+# Queries leave the cursor usable.
+w.propagate_invalidation("n000")
+w.calculate_pagerank(5, 0.85)
+assert cursor.next_page(100) == w.nodes()[100:200]
+
+w.add_edge("n000", "n299")
+message = stale(lambda: cursor.next_page(100))
+assert "200 of 300 rows read" in message, message
+assert cursor.remaining() == 100, "a refused page does not advance"
+# A cursor created after the mutation reads the new graph.
+fresh = w.nodes_cursor()
+assert drain(fresh, 64) == w.nodes()
+
+for mutate in [lambda: w.set_attribute("n001", "team", "ads"),
+               lambda: w.remove_edge("n000", "n299"),
+               lambda: w.tombstone_node("n002"),
+               lambda: w.decay_weights(0.5)]:
+    cursor = w.edges_cursor()
+    propagation = w.propagate_cursor("n000")
+    nodes = iter(w)
+    next(nodes)
+    mutate()
+    stale(lambda: cursor.next_page(1))
+    stale(lambda: propagation.next_page(1))
+    stale(lambda: next(nodes))
+
+# A rolled-back transaction still moves the version.
+cursor = w.nodes_cursor()
+w.begin_transaction()
+w.add_node("extra", [])
+w.rollback()
+stale(lambda: cursor.next_page(1))
+"#,
+    );
+}
@@ -0,0 +1,95 @@
+//! `shortest_path_lengths` and `distance_matrix`: asymmetric distances on a
+//! directed chain, requested targets only, -1 for unreachable cells, the
+//! `max_pairs` guard, and agreement with `shortest_path_length` on random graphs.
+
+mod common;
+
+const SETUP: &str = r#"
+import random
+
+def chain(n=5):
+    w = rc.GraphWalker()
+    for i in range(n - 1):
+        w.add_edge("c%d" % i, "c%d" % (i + 1))
+    w.add_node("c%d" % (n - 1), [])
+    return w
+
+def limit(call, message, args):
+    try:
+        call()
+    except rc.ResourceLimitError as e:
+        assert e.args == (message,) + args, e.args
+    else:
+        raise AssertionError("no ResourceLimitError")
+"#;
+
+#[test]
+fn chain_distances_are_directed() {
+    common::run_with(
+        SETUP,
+        r#"
+w = chain()
+lengths = w.shortest_path_lengths(["c0", "c4"])
+assert lengths == {("c0", "c%d" % i): i for i in range(5)} | {("c4", "c4"): 0}
+assert ("c4", "c0") not in lengths
+assert w.shortest_path_lengths(["c1", "c3"], targets=["c0", "c4", "c1"]) == {("c1", "c4"): 3, ("c1", "c1"): 0, ("c3", "c4"): 1}
+assert w.distance_matrix(["c0", "c2", "c4"]) == [[0, 2, 4], [-1, 0, 2], [-1, -1, 0]]
+assert w.distance_matrix([]) == [] and w.shortest_path_lengths([]) == {}
+
+# Targets may be edge targets; sources and matrix ids must be nodes.
+w.add_edge("c4", "tail")
+assert w.shortest_path_lengths(["c2"], targets=["tail"]) == {("c2", "tail"): 3}
+assert w.shortest_path_lengths(["c3"])[("c3", "tail")] == 2
+for call in (lambda: w.shortest_path_lengths(["tail"]), lambda: w.shortest_path_lengths(["c0"], targets=["zz"]),
+             lambda: w.distance_matrix(["c0", "tail"])):
+    try:
+        call()
+    except rc.NodeNotFoundError:
+        pass
+    else:
+        raise AssertionError("no NodeNotFoundError")
+"#,
+    );
+}
+
+#[test]
+fn max_pairs_bounds_the_output() {
+    common::run_with(
+        SETUP,
+        r#"
+w = chain()
+limit(lambda: w.distance_matrix(["c0", "c1"], max_pairs=3), "distance_matrix of 2 ids has 4 cells, more than max_pairs=3", (3, 4))
+assert len(w.distance_matrix(["c0", "c1"], max_pairs=4)) == 2
+limit(lambda: w.shortest_path_lengths(["c0", "c1"], targets=["c2"], max_pairs=1),
+      "shortest_path_lengths found more than max_pairs=1 pairs; pass fewer sources at a time", (1, 2))
+assert len(w.shortest_path_lengths(["c0", "c1"], targets=["c2"], max_pairs=2)) == 2
+assert len(w.shortest_path_lengths(["c0"], max_pairs=None)) == 5
+"#,
+    );
+}
+
+#[test]
+fn random_graphs_match_single_pair_lengths() {
+    common::run_with(
+        SETUP,
+        r#"
+for seed in range(4):
+    r = random.Random(seed)
+    w = rc.GraphWalker()
+    ids = ["n%d" % i for i in range(30)]
+    for id in ids:
+        w.add_node(id, [])
+    for _ in range(60):
+        w.add_edge(r.choice(ids), r.choice(ids))
+    sources = r.sample(ids, 8)
+    lengths = w.shortest_path_lengths(sources)
+    matrix = w.distance_matrix(sources)
+    for i, s in enumerate(sources):
+        for j, t in enumerate(sources):
+            expected = 0 if s == t else w.shortest_path_length(s, t)
+            assert lengths.get((s, t)) == expected, (seed, s, t)
+            assert matrix[i][j] == (-1 if expected is None else expected)
+    assert all(d == (0 if s == t else w.shortest_path_length(s, t)) for (s, t), d in lengths.items())
+"#,
+    );
+}
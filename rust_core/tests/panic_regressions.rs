@@ -0,0 +1,145 @@
+//! Calls that used to panic across the FFI boundary and now raise a Python
+//! exception (or succeed) instead.
+
+mod common;
+
+#[test]
+fn split_node_on_a_missing_id_raises_key_error() {
+    common::run(
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b"])
+before = w.to_json()
+try:
+    w.split_node("missing", [("p", [], [])])
+except KeyError:
+    pass
+else:
+    raise AssertionError("no KeyError")
+assert w.to_json() == before
+"#,
+    );
+}
+
+#[test]
+fn find_diameter_and_paths_with_edge_targets_that_are_not_nodes() {
+    // "c" and "d" only appear as edge targets, so the BFS and the path search
+    // meet ids without a node of their own.
+    common::run(
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b", "c"])
+w.add_node("b", ["d"])
+assert w.find_diameter() == 2
+assert w.find_diameter(sample=1)["diameter"] >= 1
+assert w.shortest_path("a", "d") == ["a", "b", "d"]
+assert w.shortest_path("b", "a") is None
+"#,
+    );
+}
+
+#[test]
+fn detect_cycles_on_a_500k_node_chain_does_not_overflow_the_stack() {
+    common::run(
+        r#"
+n = 500_000
+w = rc.GraphWalker()
+w.add_nodes_bulk([(str(i), [str(i + 1)]) for i in range(n)])
+assert w.detect_cycles() == []
+w.add_edge(str(n), "0")
+assert len(w.detect_cycles()) == 1
+"#,
+    );
+}
+
+#[test]
+fn consumer_start_failures_raise() {
+    common::run(
+        r#"
+try:
+    rc.start_kafka_consumer("127.0.0.1:1", "facts", connect_timeout_ms=300)
+except TimeoutError:
+    pass
+else:
+    raise AssertionError("no TimeoutError")
+
+try:
+    rc.start_kafka_consumer(rc.MemoryBroker(), [])
+except ValueError:
+    pass
+else:
+    raise AssertionError("no ValueError")
+"#,
+    );
+}
+
+#[test]
+fn array_apis_without_numpy_raise_import_error() {
+    common::run(
+        r#"
+try:
+    import numpy
+except ImportError:
+    numpy = None
+
+w = rc.GraphWalker()
+w.add_node("a", ["b"])
+if numpy is None:
+    for call in (
+        lambda: w.to_csr(),
+        lambda: w.propagate_invalidation_ids("a"),
+        lambda: w.detect_communities_ids(),
+        lambda: w.set_numeric_attrs_bulk(["a"], "score", [1.0]),
+    ):
+        try:
+            call()
+        except ImportError:
+            pass
+        else:
+            raise AssertionError("no ImportError")
+    # Plain sequences need no numpy.
+    c = rc.GraphWalker.from_csr([0, 1, 1], [1], ["a", "b"])
+    assert c.get_dependents("a") == ["b"]
+else:
+    indptr, indices, ids = w.to_csr()
+    c = rc.GraphWalker.from_csr(indptr, indices, ids)
+    assert c.get_dependents("a") == ["b"]
+"#,
+    );
+}
+
+#[test]
+fn node_lookups_on_ids_that_are_not_nodes() {
+    // "t" is only an edge target and "gone" was removed, so every lookup by id or
+    // handle below meets ids without a node of their own.
+    common::run(
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b", "t"], attributes={"team": "x"})
+w.add_node("b", ["t"])
+w.add_node("gone", ["a"])
+w.remove_node("gone")
+assert w.get_edge("t", "a") is None and w.get_edge("missing", "a") is None and w.get_edge("gone", "a") is None
+assert w.get_edge("a", "t")[0] == 1.0
+assert w.validate_schema() == []
+w.add_edge_labeled("b", "t", "feeds")
+assert w.get_dependents_labeled("b") == [("t", "feeds")]
+
+w.propagate_and_compare("a", include_current=True)
+assert w.record_outcome("a", ["b"])["adjusted"] == 2
+
+sub = w.subgraph(["a", "t", "missing"], strict=False)
+assert sub.node_ids() == ["a"] and sub.get_dependents("a") == []
+copy = rc.GraphWalker()
+copy.add_node("a", [], attributes={"team": "y"})
+copy.merge(w, on_conflict=lambda kind, key, ours, theirs: ours)
+assert copy.get_attributes("a") == {"team": "y"} and copy.get_dependents("b") == ["t"]
+
+assert w.content_digest(False) == rc.GraphWalker.from_json(w.to_json()).content_digest(False)
+u = rc.GraphWalker(mode="undirected_multi")
+u.add_node("a", ["a", "dangling"])
+assert u.metrics_table(["degree"])["degree"] == [3.0, 1.0]
+assert w.validate(10).dangling == [("a", "t"), ("b", "t")]
+"#,
+    );
+}
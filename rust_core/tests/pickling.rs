@@ -0,0 +1,120 @@
+//! Pickle, copy and deepcopy keep every constructor setting and the event log.
+
+mod common;
+
+const SETUP: &str = r#"
+import copy, multiprocessing, operator, pickle
+
+def configured():
+    w = rc.GraphWalker(
+        record_events=True,
+        track_provenance=True,
+        cycle_check_steps=100,
+        track_heat=True,
+        normalize=["trim", str.casefold],
+    )
+    w.add_node(" Fact-A", ["FACT-B"])
+    w.add_edge("fact-b", "fact-c", 2.0)
+    w.set_attribute("FACT-A", "kind", "source")
+    return w
+
+def check(original, restored):
+    assert restored.to_json() == original.to_json()
+    assert restored.event_log() == original.event_log()
+    assert restored.normalize(" FACT-C") == "fact-c"
+    assert restored.edge_provenance("fact-a", "fact-b")["source"] == "api"
+    restored.propagate_invalidation("fact-a")
+    assert restored.hot_nodes(1) == [("fact-b", 1)], restored.hot_nodes(1)
+    # The incremental cycle check still runs.
+    restored.add_edge("FACT-C", "fact-a")
+    assert restored.new_cycles()
+    assert len(restored.event_log()) == len(original.event_log()) + 1
+    # The copy is independent.
+    assert original.edge_count() == 2
+"#;
+
+#[test]
+fn pickle_round_trip_keeps_settings() {
+    common::run_with(
+        SETUP,
+        r#"
+w = configured()
+check(w, pickle.loads(pickle.dumps(w)))
+"#,
+    );
+}
+
+#[test]
+fn copy_and_deepcopy_keep_settings() {
+    common::run_with(
+        SETUP,
+        r#"
+w = configured()
+check(w, copy.copy(w))
+check(w, copy.deepcopy(w))
+check(w, copy.deepcopy([w])[0])
+"#,
+    );
+}
+
+#[test]
+fn unconfigured_walkers_stay_unconfigured() {
+    common::run_with(
+        SETUP,
+        r#"
+w = pickle.loads(pickle.dumps(rc.GraphWalker()))
+w.add_node("A", ["b"])
+assert w.normalize("A") == "A"
+try:
+    w.event_log()
+except ValueError:
+    pass
+else:
+    raise AssertionError("events recorded")
+assert w.edge_provenance("A", "b") is None
+"#,
+    );
+}
+
+#[test]
+fn old_pickle_state_still_loads() {
+    common::run_with(
+        SETUP,
+        r#"
+w = configured()
+data, settings = w.__getstate__()
+restored = rc.GraphWalker()
+restored.__setstate__(data)
+assert restored.to_json() == w.to_json()
+assert restored.normalize("A") == "A"
+"#,
+    );
+}
+
+#[test]
+fn shared_walker_snapshot_keeps_settings() {
+    common::run_with(
+        SETUP,
+        r#"
+w = configured()
+expected = copy.copy(w)
+shared = rc.SharedGraphWalker(w)
+check(expected, shared.snapshot())
+"#,
+    );
+}
+
+#[test]
+fn walkers_cross_a_multiprocessing_boundary() {
+    common::run_with(
+        SETUP,
+        r#"
+w = configured()
+with multiprocessing.get_context("fork").Pool(2) as pool:
+    normalized = pool.map(operator.methodcaller("normalize", " FACT-Z"), [w, w])
+    returned = pool.map(copy.copy, [w])
+assert normalized == ["fact-z", "fact-z"]
+check(w, returned[0])
+"#,
+    );
+}
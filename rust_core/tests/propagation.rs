@@ -0,0 +1,202 @@
+//! `propagate_invalidation`, which runs on `concord_core::propagate_with`, and
+//! its multi-source variants.
+
+mod common;
+
+use proptest::prelude::*;
+use proptest::test_runner::{Config, RngAlgorithm, TestRng, TestRunner};
+use pyo3::types::IntoPyDict;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[test]
+fn unknown_start_raises_node_not_found_unless_lenient() {
+    common::run(
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b"])
+try:
+    w.propagate_invalidation("b")
+except rc.NodeNotFoundError as e:
+    assert e.args == ("b",)
+else:
+    raise AssertionError("no NodeNotFoundError")
+assert w.propagate_invalidation("b", strict=False) == set()
+"#,
+    );
+}
+
+#[test]
+fn limit_errors_match_concord_core() {
+    common::run(
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b", "c"])
+w.add_node("b", ["d"])
+try:
+    w.propagate_invalidation("a", max_results=1)
+except rc.ResourceLimitError as e:
+    assert e.args == (
+        "max_results limit of 1 exceeded after visiting 1 nodes with 2 results",
+        1,
+        2,
+    ), e.args
+else:
+    raise AssertionError("no ResourceLimitError")
+assert w.propagate_invalidation("a") == {"b", "c", "d"}
+"#,
+    );
+}
+
+#[test]
+fn parents_collect_every_edge_from_the_previous_level() {
+    common::run(
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b", "c"])
+w.add_node("b", ["d"])
+w.add_node("c", ["d"])
+w.add_node("d", ["a"])
+w.tombstone_node("c")
+parents = w.propagate_invalidation_with_parents("a")
+assert parents == {"a": ["d"], "b": ["a"], "d": ["b"]}, parents
+"#,
+    );
+}
+
+#[test]
+fn removing_a_chain_node_stops_invalidation_there() {
+    common::run(
+        r#"
+w = rc.GraphWalker()
+for frm, to in [("a", "b"), ("b", "c"), ("c", "d")]:
+    w.add_node(frm, [to])
+assert w.propagate_invalidation("a") == {"b", "c", "d"}
+assert w.remove_node("b")
+assert not w.remove_node("b"), "nothing left to delete"
+# "a" still lists "b"; the dangling reference is reached but not crossed.
+assert w.get_dependents("a") == ["b"]
+assert w.propagate_invalidation("a") == {"b"}
+assert w.propagate_invalidation("c") == {"d"}
+
+w.add_node("x", ["c"])
+assert w.remove_node("c", prune_edges=True)
+assert w.get_dependents("x") == [] and w.propagate_invalidation("x") == set()
+assert w.remove_edge("a", "b") and not w.remove_edge("a", "b")
+assert w.propagate_invalidation("a") == set()
+"#,
+    );
+}
+
+/// A random graph: nodes `n0..`, each with dependents among the nodes and the ids
+/// `x0..x2` that are not nodes, some nodes tombstoned, and distinct start ids.
+#[derive(Clone, Debug)]
+struct Case {
+    dependents: Vec<Vec<String>>,
+    tombstoned: Vec<usize>,
+    starts: Vec<String>,
+}
+
+fn case() -> impl Strategy<Value = Case> {
+    (1..14usize).prop_flat_map(|n| {
+        let dep = prop_oneof![
+            6 => (0..n).prop_map(|i| format!("n{}", i)),
+            1 => (0..3usize).prop_map(|i| format!("x{}", i)),
+        ];
+        (
+            prop::collection::vec(prop::collection::vec(dep, 0..4), n),
+            prop::collection::vec(0..n, 0..2),
+            prop::sample::subsequence((0..n).collect::<Vec<_>>(), 0..=n.min(4)),
+            any::<prop::sample::Index>(),
+        )
+            .prop_map(|(dependents, tombstoned, starts, shift)| {
+                // Start ids in a shuffled order, so attribution order is checked.
+                let mut starts: Vec<String> = starts.iter().map(|i| format!("n{}", i)).collect();
+                if !starts.is_empty() {
+                    let k = shift.index(starts.len());
+                    starts.rotate_left(k);
+                }
+                Case {
+                    dependents,
+                    tombstoned,
+                    starts,
+                }
+            })
+    })
+}
+
+fn check_multi(case: &Case) {
+    common::with_rc(|_, rc| {
+        let w = rc.getattr("GraphWalker").unwrap().call0().unwrap();
+        for (i, deps) in case.dependents.iter().enumerate() {
+            w.call_method1("add_node", (format!("n{}", i), deps.clone()))
+                .unwrap();
+        }
+        for i in &case.tombstoned {
+            w.call_method1("tombstone_node", (format!("n{}", i),))
+                .unwrap();
+        }
+        // `strict=False`: a tombstoned start reaches nothing, as it does alone.
+        let kwargs = [("strict", false)].into_py_dict(w.py());
+        let single: Vec<BTreeSet<String>> = case
+            .starts
+            .iter()
+            .map(|s| {
+                w.call_method("propagate_invalidation", (s,), Some(kwargs))
+                    .unwrap()
+                    .extract()
+                    .unwrap()
+            })
+            .collect();
+        let union: BTreeSet<String> = single.iter().flatten().cloned().collect();
+        let multi: BTreeSet<String> = w
+            .call_method(
+                "propagate_invalidation_multi",
+                (case.starts.clone(),),
+                Some(kwargs),
+            )
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(multi, union, "{:?}", case);
+
+        let attributed: BTreeMap<String, Vec<String>> = w
+            .call_method(
+                "propagate_invalidation_attributed",
+                (case.starts.clone(),),
+                Some(kwargs),
+            )
+            .unwrap()
+            .extract()
+            .unwrap();
+        let expected: BTreeMap<String, Vec<String>> = union
+            .iter()
+            .map(|id| {
+                let sources = case
+                    .starts
+                    .iter()
+                    .zip(&single)
+                    .filter(|(_, reached)| reached.contains(id))
+                    .map(|(s, _)| s.clone())
+                    .collect();
+                (id.clone(), sources)
+            })
+            .collect();
+        assert_eq!(attributed, expected, "{:?}", case);
+    });
+}
+
+#[test]
+fn multi_source_results_match_single_source_calls() {
+    let config = Config {
+        cases: 256,
+        failure_persistence: None,
+        ..Config::default()
+    };
+    let rng = TestRng::from_seed(RngAlgorithm::ChaCha, &[5; 32]);
+    TestRunner::new_with_rng(config, rng)
+        .run(&case(), |case| {
+            check_multi(&case);
+            Ok(())
+        })
+        .unwrap();
+}
@@ -0,0 +1,110 @@
+//! Consumers publishing invalidations through a `CausalProducer` on a
+//! `MemoryBroker`, with and without an `InvalidationPublisher`.
+
+mod common;
+
+const SETUP: &str = r#"
+broker = rc.MemoryBroker()
+shared = rc.SharedGraphWalker()
+shared.add_node("a", ["b"])
+
+def start(publisher=None):
+    return rc.start_kafka_consumer(
+        broker, "facts", walker=shared, producer=rc.CausalProducer(broker),
+        output_topic="out", publisher=publisher,
+    )
+
+def invalidate(id, times):
+    for _ in range(times):
+        broker.send("facts", json.dumps({"fact_id": id, "op": "invalidate"}))
+"#;
+
+#[test]
+fn every_invalidation_is_published_without_a_publisher() {
+    common::run_with(
+        SETUP,
+        r#"
+handle = start()
+invalidate("a", 2)
+out = read_topic(broker, "out", 2)
+handle.stop()
+assert [m["invalidated"] for m in out] == [["b"], ["b"]], out
+assert all("suppressed" not in m for m in out)
+"#,
+    );
+}
+
+#[test]
+fn held_back_invalidations_are_sent_once_their_window_passes() {
+    common::run_with(
+        SETUP,
+        r#"
+publisher = rc.InvalidationPublisher(min_interval=0.5, dedup=False)
+handle = start(publisher)
+invalidate("a", 2)
+wait_until(lambda: publisher.metrics()["rate_limited"] == 1)
+assert broker.message_count("out") == 1
+# The consumer's timer sends the held-back set without another message.
+wait_until(lambda: broker.message_count("out") == 2, timeout=3.0)
+handle.stop()
+out = read_topic(broker, "out", 2)
+assert [m["suppressed"] for m in out] == [0, 1], out
+assert publisher.metrics() == {"published": 2, "rate_limited": 1, "deduplicated": 0}
+"#,
+    );
+}
+
+#[test]
+fn stopping_sends_what_is_still_held_back() {
+    common::run_with(
+        SETUP,
+        r#"
+publisher = rc.InvalidationPublisher(min_interval=3600.0, dedup=False)
+handle = start(publisher)
+invalidate("a", 3)
+wait_until(lambda: publisher.metrics()["rate_limited"] == 2)
+assert broker.message_count("out") == 1
+assert handle.stop()
+assert broker.message_count("out") == 2
+out = read_topic(broker, "out", 2)
+assert [m["suppressed"] for m in out] == [0, 2], out
+assert handle.metrics()["processing_errors"] == 0
+"#,
+    );
+}
+
+#[test]
+fn deduplicated_flushes_send_nothing() {
+    common::run_with(
+        SETUP,
+        r#"
+publisher = rc.InvalidationPublisher(min_interval=3600.0)
+handle = start(publisher)
+invalidate("a", 2)
+wait_until(lambda: publisher.metrics()["rate_limited"] == 1)
+handle.stop()
+# The held-back set equals the one already sent.
+assert broker.message_count("out") == 1
+assert publisher.metrics()["deduplicated"] == 1
+"#,
+    );
+}
+
+#[test]
+fn failed_deliveries_are_counted_by_the_producer() {
+    common::run_with(
+        SETUP,
+        r#"
+producer = rc.CausalProducer(broker)
+broker.fail_sends(1)
+handle = rc.start_kafka_consumer(
+    broker, "facts", walker=shared, producer=producer, output_topic="out"
+)
+invalidate("a", 2)
+wait_until(lambda: broker.message_count("out") == 1)
+handle.stop()
+report = producer.flush()
+assert (report["delivered"], report["failed"]) == (1, 1), report
+"#,
+    );
+}
@@ -0,0 +1,81 @@
+//! The Python protocols on `GraphWalker`: `len`, `in`, `g[id]`, `repr`, and
+//! `iter(g)` as a lazy `NodeIter` that raises `StaleCursorError` once the graph
+//! changes under it, and never skips or repeats an id.
+
+mod common;
+
+const SETUP: &str = r#"
+def graph():
+    w = rc.GraphWalker()
+    w.add_node("b", ["a", "c"])
+    w.add_node("a", ["b"])
+    return w
+"#;
+
+#[test]
+fn mapping_protocol_matches_the_read_api() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+assert len(w) == w.node_count() == 2 and len(rc.GraphWalker()) == 0
+assert "a" in w and "c" not in w and 3 not in w and None not in w
+assert w["b"] == w.get_dependents("b") == ["a", "c"]
+try:
+    w["c"]
+except KeyError as e:
+    assert isinstance(e, rc.NodeNotFoundError) and e.args == ("c",)
+else:
+    raise AssertionError("no KeyError")
+assert repr(w) == "GraphWalker(nodes=2, edges=3)"
+w.add_node("z", [])
+assert repr(w) == "GraphWalker(nodes=3, edges=3)" and len(w) == 3
+"#,
+    );
+}
+
+#[test]
+fn iteration_is_lazy_sorted_and_refuses_stale_graphs() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+it = iter(w)
+assert type(it).__name__ == "NodeIter" and iter(it) is it
+assert list(it) == ["a", "b"] == w.node_ids()
+try:
+    next(it)
+except StopIteration:
+    pass
+else:
+    raise AssertionError("iterator did not stop")
+assert [id for id in w] == ["a", "b"] and dict((id, w[id]) for id in w) == {"a": ["b"], "b": ["a", "c"]}
+
+# Reads in the loop are fine; a mutation fails the next step.
+it = iter(w)
+assert next(it) == "a"
+w.get_dependents("a")
+w.propagate_invalidation("a")
+assert next(it) == "b"
+for mutate in (lambda: w.add_node("z", []), lambda: w.add_edge("a", "c"), lambda: w.set_attribute("a", "k", "v")):
+    it = iter(w)
+    next(it)
+    mutate()
+    try:
+        next(it)
+    except rc.StaleCursorError as e:
+        assert isinstance(e, RuntimeError) and "graph changed during iteration" in str(e), str(e)
+    else:
+        raise AssertionError("no StaleCursorError")
+
+# A big graph is walked in sorted order, one id at a time.
+big = rc.GraphWalker()
+big.add_nodes_bulk([("n%06d" % i, []) for i in range(50000)])
+count, last = 0, ""
+for id in big:
+    assert id > last
+    count, last = count + 1, id
+assert count == 50000
+"#,
+    );
+}
@@ -0,0 +1,97 @@
+//! `generate_random_walks` and `write_random_walks`: every step follows an edge,
+//! walks stop at sinks, a fixed seed reproduces them, `p` and `q` bias the
+//! second step as node2vec does, and the file holds the same walks.
+
+mod common;
+
+const SETUP: &str = r#"
+import os, tempfile
+
+def graph():
+    # h fans out to a, b and c; a and b lead back to h, c leads to a sink.
+    w = rc.GraphWalker()
+    w.add_node("h", ["a", "b", "c"])
+    w.add_node("a", ["h", "b"])
+    w.add_node("b", ["h"])
+    w.add_node("c", ["sink", "ghost"])
+    w.add_node("sink", [])
+    return w
+
+def raises(call, fragment):
+    try:
+        call()
+    except ValueError as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no ValueError")
+"#;
+
+#[test]
+fn walks_follow_edges_and_stop_at_sinks() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+walks = w.generate_random_walks(4, 6, seed=1)
+ids = w.node_ids()
+# Rounds over the nodes in id order.
+assert len(walks) == 4 * len(ids) and [walk[0] for walk in walks] == ids * 4
+for walk in walks:
+    assert 1 <= len(walk) <= 6
+    assert all(b in w.get_dependents(a) for a, b in zip(walk, walk[1:])), walk
+    # "ghost" is only an edge target, so no walk enters it; short walks end at the sink.
+    assert "ghost" not in walk and (len(walk) == 6 or walk[-1] == "sink"), walk
+assert all(walk == ["sink"] for walk in walks if walk[0] == "sink")
+assert all(walk == ["c", "sink"] for walk in walks if walk[0] == "c")
+assert len(w.generate_random_walks(2, 3)) == 2 * len(ids) and w.generate_random_walks(0, 3, seed=1) == []
+
+w.tombstone_node("b", 1.0)
+assert not any("b" in walk for walk in w.generate_random_walks(10, 8, seed=3))
+raises(lambda: w.generate_random_walks(1, 0), "walk_length must be at least 1")
+raises(lambda: w.generate_random_walks(1, 3, p=0.0), "p must be positive and finite, got 0")
+raises(lambda: w.generate_random_walks(1, 3, q=float("inf")), "q must be positive and finite")
+"#,
+    );
+}
+
+#[test]
+fn a_fixed_seed_reproduces_the_walks() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+walks = w.generate_random_walks(5, 8, 0.5, 2.0, seed=42)
+assert walks == w.generate_random_walks(5, 8, 0.5, 2.0, seed=42)
+assert walks != w.generate_random_walks(5, 8, 0.5, 2.0, seed=43)
+# Walk i depends on the seed and i alone, so more walks per node keep the first rounds.
+assert w.generate_random_walks(6, 8, 0.5, 2.0, seed=42)[:len(walks)] == walks
+
+path = os.path.join(tempfile.mkdtemp(), "walks.txt")
+assert w.write_random_walks(path, 5, 8, 0.5, 2.0, seed=42) == len(walks)
+assert open(path).read().splitlines() == [" ".join(walk) for walk in walks]
+spaced = rc.GraphWalker()
+spaced.add_node("a b", [])
+raises(lambda: spaced.write_random_walks(path, 1, 2), 'node id "a b" contains whitespace')
+"#,
+    );
+}
+
+#[test]
+fn p_and_q_bias_the_second_step() {
+    common::run_with(
+        SETUP,
+        r#"
+# From x the walk must go to y. There it returns to x with weight 1/p, or moves
+# on to z, which x has no edge to, with weight 1/q.
+w = rc.GraphWalker()
+w.add_node("x", ["y"])
+w.add_node("y", ["x", "z"])
+w.add_node("z", [])
+for p, q in [(1.0, 1.0), (0.25, 4.0), (4.0, 0.25)]:
+    walks = [walk for walk in w.generate_random_walks(3000, 3, p, q, seed=9) if walk[0] == "x"]
+    returned = sum(walk[2] == "x" for walk in walks) / len(walks)
+    expected = (1 / p) / (1 / p + 1 / q)
+    assert abs(returned - expected) < 0.04, (p, q, returned, expected)
+"#,
+    );
+}
@@ -0,0 +1,109 @@
+//! `is_reachable`, `reachable_count` and `build_reachability_index`: indexed
+//! answers against a Python BFS on random graphs with cycles and tombstones, and
+//! rebuilds after every kind of mutation so no answer is ever stale.
+
+mod common;
+
+const SETUP: &str = r#"
+import random
+from collections import deque
+
+def bfs(w, start, dead=()):
+    # What propagate_invalidation reaches: at least one edge, targets included.
+    seen, queue = set(), deque([start])
+    while queue:
+        v = queue.popleft()
+        for d in (w.get_dependents(v) if w.has_node(v) else []):
+            if d not in seen and d not in dead:
+                seen.add(d)
+                queue.append(d)
+    return seen
+
+def random_graph(seed, n=60, m=120):
+    r = random.Random(seed)
+    w = rc.GraphWalker()
+    for _ in range(m):
+        w.add_edge("n%d" % r.randrange(n), "n%d" % r.randrange(n + 10))
+    return w, r
+"#;
+
+#[test]
+fn a_small_graph_answers_like_propagation() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+for a, b in [("a", "b"), ("b", "c"), ("c", "a"), ("c", "d"), ("d", "t")]:
+    w.add_edge(a, b)
+w.add_node("lone", [])
+info = w.build_reachability_index()
+assert set(info) == {"components", "intervals", "memory_bytes"} and info["components"] == 4
+# a reaches itself round the cycle; lone has no edge, so not even itself.
+assert w.is_reachable("a", "a") and w.is_reachable("a", "t") and not w.is_reachable("d", "a")
+assert not w.is_reachable("lone", "lone") and not w.is_reachable("a", "nope")
+assert w.reachable_count("a") == len(w.propagate_invalidation("a")) == 5 and w.reachable_count("lone") == 0
+for call in (lambda: w.is_reachable("t", "a"), lambda: w.reachable_count("nope")):
+    try:
+        call()
+    except rc.NodeNotFoundError:
+        pass
+    else:
+        raise AssertionError("no NodeNotFoundError")
+"#,
+    );
+}
+
+#[test]
+fn random_graphs_match_a_bfs() {
+    common::run_with(
+        SETUP,
+        r#"
+for seed in range(6):
+    w, r = random_graph(seed)
+    if seed % 2:
+        w.build_reachability_index()
+    targets = ["n%d" % i for i in range(70)]
+    for s in w.node_ids():
+        expected = bfs(w, s)
+        assert w.reachable_count(s) == len(expected), (seed, s)
+        assert all(w.is_reachable(s, t) == (t in expected) for t in targets), (seed, s)
+
+    # Tombstoned ids are skipped, as by propagation.
+    dead = set(r.sample(w.node_ids(), 5))
+    for id in dead:
+        w.tombstone_node(id, 1.0)
+    for s in w.node_ids():
+        expected = bfs(w, s, dead)
+        assert expected == w.propagate_invalidation(s), (seed, s)
+        assert w.reachable_count(s) == len(expected) and all(w.is_reachable(s, t) == (t in expected) for t in targets)
+"#,
+    );
+}
+
+#[test]
+fn mutations_make_the_index_rebuild() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b"])
+w.add_node("b", ["c"])
+w.add_node("c", [])
+w.build_reachability_index()
+assert not w.is_reachable("c", "a") and w.reachable_count("a") == 2
+steps = [
+    (lambda: w.add_edge("c", "a"), lambda: w.is_reachable("c", "a") and w.reachable_count("a") == 3),
+    (lambda: w.add_node("c", ["d"]), lambda: not w.is_reachable("c", "a") and w.reachable_count("a") == 3),
+    (lambda: w.tombstone_node("b", 1.0), lambda: w.reachable_count("a") == 0),
+    (lambda: w.add_node("b", ["d", "e"]), lambda: w.reachable_count("a") == 3),
+    (lambda: w.add_nodes_bulk([("e", ["a"])]), lambda: w.is_reachable("e", "e") and w.reachable_count("b") == 4),
+]
+for mutate, holds in steps:
+    w.build_reachability_index()
+    mutate()
+    assert holds()
+    for s in w.node_ids():
+        assert w.reachable_count(s) == len(w.propagate_invalidation(s))
+"#,
+    );
+}
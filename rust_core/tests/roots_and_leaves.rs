@@ -0,0 +1,61 @@
+//! `find_roots`, `find_leaves`, `find_isolated` and `find_phantom_nodes`: edge
+//! targets that were never added are reported only as phantoms, leave that list
+//! once added, and tombstoned ids are not phantoms.
+
+mod common;
+
+const SETUP: &str = r#"
+def graph():
+    # p1 and p2 are only edge targets, as when their add_node has not arrived yet.
+    w = rc.GraphWalker()
+    w.add_node("a", ["b", "p1"])
+    w.add_node("b", ["p2", "p1"])
+    w.add_node("loop", ["loop"])
+    w.add_node("lone", [])
+    return w
+
+def report(w):
+    return w.find_roots(), w.find_leaves(), w.find_isolated(), w.find_phantom_nodes()
+"#;
+
+#[test]
+fn phantom_targets_are_reported_separately() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+# An edge to a phantom still counts as outgoing; a self-loop is both in and out.
+assert report(w) == (["a", "lone"], ["lone"], ["lone"], ["p1", "p2"])
+w.add_node("p1", [])
+assert report(w) == (["a", "lone"], ["lone", "p1"], ["lone"], ["p2"])
+# Arriving with edges of its own, p2 is an ordinary node too.
+w.add_node("p2", ["lone"])
+assert report(w) == (["a"], ["lone", "p1"], [], [])
+assert report(rc.GraphWalker()) == ([], [], [], [])
+"#,
+    );
+}
+
+#[test]
+fn tombstoned_ids_are_not_phantoms() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+w.add_node("gone", ["b"])
+w.add_edge("a", "gone")
+w.tombstone_node("gone", 1.0)
+# gone keeps its in-edge from a, but it is a deleted fact, not a phantom.
+assert report(w) == (["a", "lone"], ["lone"], ["lone"], ["p1", "p2"])
+assert w.get_dependents("a") == ["b", "p1", "gone"]
+
+# Phantoms follow ingestion order: edges first, nodes later.
+v = rc.GraphWalker()
+for frm, to in [("x", "y"), ("y", "z"), ("z", "w")]:
+    v.add_edge(frm, to)
+assert v.find_phantom_nodes() == ["w"] and v.find_leaves() == [] and v.find_roots() == ["x"]
+v.add_node("w", [])
+assert v.find_phantom_nodes() == [] and v.find_leaves() == ["w"]
+"#,
+    );
+}
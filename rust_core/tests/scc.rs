@@ -0,0 +1,73 @@
+//! `strongly_connected_components` and `condensation`: two 3-cycles joined by a
+//! bridge, agreement with mutual reachability on random graphs, a long chain
+//! without recursion, and the condensed walker being a DAG.
+
+mod common;
+
+const SETUP: &str = r#"
+import random
+
+def bridged():
+    # a, b, c and d, e, f are 3-cycles; c -> d joins them.
+    w = rc.GraphWalker()
+    for a, b in [("a", "b"), ("b", "c"), ("c", "a"), ("d", "e"), ("e", "f"), ("f", "d"), ("c", "d")]:
+        w.add_edge(a, b)
+    return w
+
+def mutual(w):
+    reach = {v: w.propagate_invalidation(v) | {v} for v in w.node_ids()}
+    groups = {tuple(sorted(u for u in reach[v] if w.has_node(u) and v in reach[u])) for v in reach}
+    return sorted(map(list, groups))
+"#;
+
+#[test]
+fn components_are_sorted_and_deterministic() {
+    common::run_with(
+        SETUP,
+        r#"
+w = bridged()
+w.add_node("lone", [])
+w.add_edge("f", "ghost")
+# Sorted members, components by smallest member; "ghost" is not a node.
+assert w.strongly_connected_components() == [["a", "b", "c"], ["d", "e", "f"], ["lone"]]
+assert rc.GraphWalker().strongly_connected_components() == []
+
+for seed in range(6):
+    r = random.Random(seed)
+    g = rc.GraphWalker()
+    for i in range(30):
+        g.add_node("n%d" % i, ["n%d" % r.randrange(30) for _ in range(r.randrange(3))])
+    assert g.strongly_connected_components() == mutual(g), seed
+
+n = 200000
+chain = rc.GraphWalker()
+chain.add_nodes_bulk([("n%d" % i, ["n%d" % (i + 1)]) for i in range(n)] + [("n%d" % n, ["n0"])])
+assert len(chain.strongly_connected_components()) == 1
+"#,
+    );
+}
+
+#[test]
+fn the_condensation_is_a_dag() {
+    common::run_with(
+        SETUP,
+        r#"
+w = bridged()
+w.add_edge("b", "e")
+w.add_edge("a", "f")
+w.add_edge("d", "gone")
+w.add_node("lone", [])
+before = w.content_digest()
+c = w.condensation()
+assert type(c) is rc.GraphWalker
+assert c.node_ids() == ["a", "d", "lone"]
+# Three edges join the components; one condensed edge stands for all of them.
+assert c.get_dependents("a") == ["d"] and c.get_dependents("d") == [] and c.edge_count() == 1
+assert [c.get_attributes(id) for id in c.node_ids()] == [{"scc_size": "3"}, {"scc_size": "3"}, {"scc_size": "1"}]
+assert c.detect_cycles() == [] and c.topological_sort() == ["a", "lone", "d"]
+# The source walker is untouched.
+assert w.content_digest() == before
+assert rc.GraphWalker().condensation().node_count() == 0
+"#,
+    );
+}
@@ -0,0 +1,132 @@
+//! Schema constraints: `enforce_schema` rejecting violations on ingest,
+//! `validate_schema` auditing a graph built without it, and the schema surviving
+//! a snapshot round-trip.
+
+mod common;
+
+const SETUP: &str = r#"
+import os, tempfile
+
+def declare(w):
+    w.define_node_kind("job", "job:*")
+    w.define_node_kind("dataset", "ds:*")
+    w.allow_edge("job", "dataset")
+    w.allow_edge("dataset", "dataset", "copy")
+
+def raises(error, call, fragment):
+    try:
+        call()
+    except error as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+"#;
+
+#[test]
+fn enforcement_rejects_violations_on_ingest() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(enforce_schema=True)
+declare(w)
+w.add_edge("job:a", "ds:x")
+w.add_node("job:b", ["ds:y"])
+w.add_edge_labeled("ds:x", "ds:y", "copy")
+# Edges touching an id with no kind are not constrained.
+w.add_edge("ds:x", "report")
+before = w.to_json()
+
+rule = "no allow_edge rule for dataset -> job"
+raises(rc.SchemaError, lambda: w.add_edge("ds:x", "job:a"), rule)
+raises(rc.SchemaError, lambda: w.add_node("ds:z", ["job:b"]), rule)
+raises(rc.SchemaError, lambda: w.touch_edge("ds:y", "job:b", 5.0), rule)
+raises(rc.SchemaError, lambda: w.add_edge("ds:y", "ds:x"), "dataset -> dataset")
+raises(rc.SchemaError, lambda: w.set_attribute("job:a", "kind", "dataset"), "dataset -> dataset")
+raises(
+    rc.SchemaError,
+    lambda: w.split_node("job:a", [("job:a1", [], []), ("ds:a2", [], ["ds:x"])]),
+    "dataset -> dataset",
+)
+# A labeled rule only admits its label, and relabeling is checked too.
+raises(rc.SchemaError, lambda: w.add_edge_labeled("ds:y", "ds:x", "move"), "dataset -> dataset [move]")
+raises(rc.SchemaError, lambda: w.add_edge_labeled("ds:x", "ds:y", None), "dataset -> dataset")
+# add_edge on an existing edge keeps its label, so the edge still passes.
+w.add_edge("ds:x", "ds:y")
+assert issubclass(rc.SchemaError, ValueError)
+assert w.to_json() == before
+assert w.validate_schema() == []
+
+# replay_events is all-or-nothing, and checks a replayed labeled edge with its label.
+source = rc.GraphWalker(record_events=True)
+source.add_edge("job:c", "ds:q")
+source.add_edge_labeled("ds:q", "ds:r", "copy")
+source.add_edge("ds:q", "job:c")
+target = rc.GraphWalker(enforce_schema=True)
+declare(target)
+raises(rc.SchemaError, lambda: target.replay_events(source.event_log()), "ds:q -> job:c")
+assert target.node_count() == 0 and target.edge_count() == 0
+target.replay_events(source.event_log()[:3])
+assert target.get_dependents("job:c") == ["ds:q"] and target.get_dependents("ds:q") == ["ds:r"]
+"#,
+    );
+}
+
+#[test]
+fn validate_schema_audits_an_existing_graph() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+w.add_edge("job:a", "ds:x")
+w.add_edge("ds:x", "job:a")
+w.add_edge("ds:x", "ds:y")
+w.add_edge_labeled("ds:y", "ds:z", "copy")
+w.add_node("n", ["ds:z"], attributes={"kind": "dataset"})
+w.add_edge("ds:z", "other")
+assert w.validate_schema() == []
+declare(w)
+violations = sorted(w.validate_schema(), key=lambda v: (v["from"], v["to"]))
+assert violations == [
+    {"from": "ds:x", "to": "ds:y", "from_kind": "dataset", "to_kind": "dataset",
+     "reason": "no allow_edge rule for dataset -> dataset"},
+    {"from": "ds:x", "to": "job:a", "from_kind": "dataset", "to_kind": "job",
+     "reason": "no allow_edge rule for dataset -> job"},
+    {"from": "n", "to": "ds:z", "from_kind": "dataset", "to_kind": "dataset",
+     "reason": "no allow_edge rule for dataset -> dataset"},
+], violations
+# A new rule resolves the violations it covers.
+w.allow_edge("dataset", "job")
+assert sorted(v["to"] for v in w.validate_schema()) == ["ds:y", "ds:z"]
+"#,
+    );
+}
+
+#[test]
+fn the_schema_round_trips_through_snapshots() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(enforce_schema=True)
+declare(w)
+w.add_edge("job:a", "ds:x")
+w.add_edge_labeled("ds:x", "ds:y", "copy")
+path = os.path.join(tempfile.mkdtemp(), "schema.json")
+w.save(path)
+for loaded in (rc.GraphWalker.load(path), rc.GraphWalker.from_json(w.to_json())):
+    assert json.loads(loaded.to_json())["schema"] == json.loads(w.to_json())["schema"]
+    raises(rc.SchemaError, lambda: loaded.add_edge("ds:y", "job:a"), "dataset -> job")
+    loaded.add_edge("job:b", "ds:y")
+    assert loaded.validate_schema() == []
+
+# The rules come back without enforcement when it was off.
+audit = rc.GraphWalker()
+declare(audit)
+audit.add_edge("ds:x", "job:a")
+loaded = rc.GraphWalker.from_json(audit.to_json())
+assert len(loaded.validate_schema()) == 1
+loaded.add_edge("ds:y", "job:a")
+assert len(loaded.validate_schema()) == 2
+os.remove(path)
+"#,
+    );
+}
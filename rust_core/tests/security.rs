@@ -0,0 +1,89 @@
+//! `SecurityConfig`: the librdkafka properties it resolves to, and the settings
+//! it rejects before anything connects.
+
+mod common;
+
+const SETUP: &str = r#"
+import atexit, os, tempfile
+
+fd, CA = tempfile.mkstemp(suffix=".pem")
+os.close(fd)
+atexit.register(os.remove, CA)
+
+def rejected(fragment, build):
+    try:
+        build()
+    except ValueError as e:
+        assert fragment in str(e), (fragment, str(e))
+        assert "hunter2" not in str(e), str(e)
+    else:
+        raise AssertionError("no ValueError for " + fragment)
+"#;
+
+#[test]
+fn settings_resolve_to_the_librdkafka_properties() {
+    common::run_with(
+        SETUP,
+        r#"
+s = rc.SecurityConfig(
+    security_protocol="SASL_SSL", sasl_mechanism="scram-sha-512",
+    sasl_username="concord", sasl_password="hunter2", ssl_ca_location=CA)
+assert s.resolved() == {
+    "security.protocol": "sasl_ssl",
+    "sasl.mechanism": "SCRAM-SHA-512",
+    "sasl.username": "concord",
+    "sasl.password": "***",
+    "ssl.ca.location": CA,
+}, s.resolved()
+assert "hunter2" not in repr(s), repr(s)
+
+s = rc.SecurityConfig(security_protocol="ssl", ssl_ca_location=CA,
+                      ssl_certificate_location=CA, ssl_key_location=CA)
+assert s.resolved() == {
+    "security.protocol": "ssl",
+    "ssl.ca.location": CA,
+    "ssl.certificate.location": CA,
+    "ssl.key.location": CA,
+}, s.resolved()
+assert rc.SecurityConfig().resolved() == {"security.protocol": "plaintext"}
+
+plain = rc.SecurityConfig(security_protocol="sasl_plaintext", sasl_mechanism="PLAIN",
+                          sasl_username="concord", sasl_password="hunter2")
+resolved = rc.ConsumerConfig(group_id="g", security=plain).resolved()
+assert resolved["security.protocol"] == "sasl_plaintext" and resolved["sasl.password"] == "***", resolved
+assert resolved["group.id"] == "g", resolved
+"#,
+    );
+}
+
+#[test]
+fn mismatched_settings_raise_value_error() {
+    common::run_with(
+        SETUP,
+        r#"
+S = rc.SecurityConfig
+rejected("security_protocol must be", lambda: S(security_protocol="kerberos"))
+rejected("needs a sasl_mechanism", lambda: S(security_protocol="sasl_plaintext"))
+rejected("sasl_mechanism must be", lambda: S(
+    security_protocol="sasl_plaintext", sasl_mechanism="GSSAPI", sasl_username="u", sasl_password="hunter2"))
+rejected("needs both sasl_username and sasl_password", lambda: S(
+    security_protocol="sasl_ssl", sasl_mechanism="PLAIN", sasl_username="u"))
+rejected("needs both sasl_username and sasl_password", lambda: S(
+    security_protocol="sasl_ssl", sasl_mechanism="PLAIN", sasl_username="u", sasl_password=""))
+rejected("SASL settings need", lambda: S(security_protocol="ssl", sasl_password="hunter2"))
+rejected("TLS settings need", lambda: S(security_protocol="sasl_plaintext", sasl_mechanism="PLAIN",
+                                        sasl_username="u", sasl_password="hunter2", ssl_ca_location=CA))
+rejected("is not a file", lambda: S(security_protocol="ssl", ssl_ca_location=CA + ".missing"))
+rejected("go together", lambda: S(security_protocol="ssl", ssl_certificate_location=CA))
+rejected("go together", lambda: S(security_protocol="ssl", ssl_key_location=CA))
+
+plain = S(security_protocol="sasl_plaintext", sasl_mechanism="PLAIN",
+          sasl_username="u", sasl_password="hunter2")
+for key in ["security.protocol", "sasl.mechanisms", "sasl.password", "ssl.key.location"]:
+    rejected("'%s' is set by security" % key, lambda: rc.ConsumerConfig(
+        security=plain, properties={key: "x"}))
+    rejected("'%s' is set by security" % key, lambda: rc.CausalProducer(
+        "localhost:9092", config={key: "x"}, security=plain))
+"#,
+    );
+}
@@ -0,0 +1,105 @@
+//! `shortest_path`, `shortest_path_length` and `all_shortest_paths`: the path
+//! itself with both endpoints, several minimal explanations, unreachable and
+//! unknown endpoints, and agreement with a Python BFS on random graphs.
+
+mod common;
+
+const SETUP: &str = r#"
+import itertools, random
+from collections import deque
+
+def build(edges):
+    w = rc.GraphWalker()
+    for a, b in edges:
+        w.add_edge(a, b)
+    return w
+
+def two_ways():
+    # a reaches d through b or c, then e; a longer way round runs through x, y, z.
+    return build([("a", "b"), ("b", "d"), ("a", "c"), ("c", "d"), ("d", "e"),
+                  ("a", "x"), ("x", "y"), ("y", "z"), ("z", "e")])
+
+def bfs_length(w, s, t):
+    dist, queue = {s: 0}, deque([s])
+    while queue:
+        v = queue.popleft()
+        for d in (w.get_dependents(v) if w.has_node(v) else []):
+            if d not in dist:
+                dist[d] = dist[v] + 1
+                queue.append(d)
+    return dist.get(t)
+
+def valid(w, path):
+    return all(b in w.get_dependents(a) for a, b in zip(path, path[1:]))
+"#;
+
+#[test]
+fn paths_include_both_endpoints() {
+    common::run_with(
+        SETUP,
+        r#"
+w = two_ways()
+assert w.shortest_path("a", "e") == ["a", "b", "d", "e"]
+assert w.shortest_path_length("a", "e") == 3
+assert w.all_shortest_paths("a", "e") == [["a", "b", "d", "e"], ["a", "c", "d", "e"]]
+assert w.all_shortest_paths("a", "e", limit=1) == [["a", "b", "d", "e"]]
+assert w.shortest_path("a", "z") == ["a", "x", "y", "z"]
+# A node reaches itself by the empty path.
+assert (w.shortest_path("a", "a"), w.shortest_path_length("a", "a"), w.all_shortest_paths("a", "a")) == (["a"], 0, [["a"]])
+# Unreachable: None, and no paths.
+assert (w.shortest_path("d", "a"), w.shortest_path_length("d", "a"), w.all_shortest_paths("d", "a")) == (None, None, [])
+assert w.find_diameter() == 3
+"#,
+    );
+}
+
+#[test]
+fn unknown_endpoints_raise_key_errors() {
+    common::run_with(
+        SETUP,
+        r#"
+w = two_ways()
+# "e" is an edge target: a valid destination, but not a source.
+calls = [
+    (lambda: w.shortest_path("nope", "a"), "nope"),
+    (lambda: w.shortest_path("a", "nope"), "nope"),
+    (lambda: w.shortest_path("e", "a"), "e"),
+    (lambda: w.shortest_path_length("zz", "a"), "zz"),
+    (lambda: w.all_shortest_paths("a", "q"), "q"),
+]
+for call, id in calls:
+    try:
+        call()
+    except KeyError as e:
+        assert isinstance(e, rc.NodeNotFoundError) and e.args == (id,), e.args
+    else:
+        raise AssertionError("no KeyError for %r" % id)
+"#,
+    );
+}
+
+#[test]
+fn random_graphs_match_a_bfs() {
+    common::run_with(
+        SETUP,
+        r#"
+for seed in range(4):
+    r = random.Random(seed)
+    w = build([("n%d" % r.randrange(25), "n%d" % r.randrange(25)) for _ in range(50)])
+    for s, t in itertools.product(w.node_ids(), ["n%d" % i for i in range(25)]):
+        if not (w.has_node(t) or any(t in w.get_dependents(v) for v in w.node_ids())):
+            continue
+        expected = bfs_length(w, s, t)
+        assert w.shortest_path_length(s, t) == expected, (seed, s, t)
+        path = w.shortest_path(s, t)
+        if expected is None:
+            assert path is None and w.all_shortest_paths(s, t) == []
+            continue
+        assert path[0] == s and path[-1] == t and len(path) == expected + 1 and valid(w, path)
+        every = w.all_shortest_paths(s, t)
+        assert every == sorted(every) and path in every
+        assert all(len(p) == expected + 1 and valid(w, p) for p in every)
+        assert len(set(map(tuple, every))) == len(every)
+"#,
+    );
+}
@@ -0,0 +1,162 @@
+//! `most_similar` on graphs crafted so that each method has one clear nearest
+//! neighbour, hand-computed scores for every neighbourhood metric,
+//! `similarity_matrix`, and the ties and errors around them.
+
+mod common;
+
+const SETUP: &str = r#"
+import math
+
+def graph(edges):
+    w = rc.GraphWalker()
+    for frm, to in edges:
+        w.add_edge(frm, to)
+    for _, to in edges:
+        if to not in w:
+            w.add_node(to, [])
+    return w
+
+def close(got, expected):
+    assert [id for id, _ in got] == [id for id, _ in expected], got
+    assert all(abs(a[1] - b[1]) < 1e-12 for a, b in zip(got, expected)), got
+"#;
+
+#[test]
+fn jaccard_prefers_the_largest_shared_share() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph([("q", "x1"), ("q", "x2"), ("q", "x3"),
+           ("near", "x1"), ("near", "x2"), ("near", "x3"), ("near", "x4"),
+           ("far", "x1"), ("far", "y")])
+close(w.most_similar("q", 5, direction="out"), [("near", 0.75), ("far", 0.25)])
+close(w.most_similar("q", 1, method="jaccard", direction="out"), [("near", 0.75)])
+# Along sources nothing is shared with q, which has none.
+assert w.most_similar("q", 5, direction="in") == []
+"#,
+    );
+}
+
+#[test]
+fn adamic_adar_weights_rare_neighbours_above_hubs() {
+    common::run_with(
+        SETUP,
+        r#"
+fillers = [("f%d" % i, "hub") for i in range(10)]
+w = graph([("q", "hub"), ("q", "rare"), ("p1", "hub"), ("p2", "rare")] + fillers)
+# Jaccard cannot tell p1 and p2 apart; Adamic-Adar prefers the neighbour with two
+# sources over the one with twelve.
+assert w.most_similar("q", 2, direction="out")[0][1] == 0.5
+close(w.most_similar("q", 1, method="adamic_adar", direction="out"), [("p2", 1 / math.log(2))])
+ranked = w.most_similar("q", 20, method="adamic_adar", direction="out")
+assert len(ranked) == 12 and ranked[1] == ("f0", 1 / math.log(12)) and ranked[-1][0] == "p1"
+"#,
+    );
+}
+
+#[test]
+fn simrank_1hop_compares_sources() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph([("s1", "q"), ("s2", "q"), ("s1", "near"), ("s2", "near"),
+           ("s1", "far"), ("s3", "far")])
+close(w.most_similar("q", 5, method="simrank_1hop"), [("near", 0.8 * 2 / 4), ("far", 0.8 / 4)])
+
+# Two nodes without sources score zero against each other, however alike their
+# dependents are: the method looks only at sources.
+w = graph([("u", "z"), ("v", "z")])
+assert w.most_similar("u", 5, method="simrank_1hop") == []
+assert w.most_similar("u", 5, method="jaccard") == [("v", 1.0)]
+"#,
+    );
+}
+
+#[test]
+fn co_invalidation_compares_propagation_sets() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph([("q", "a"), ("a", "b"), ("b", "c"), ("near", "a"), ("far", "a"), ("far", "x")])
+close(w.most_similar("q", 5, method="co_invalidation"),
+      [("near", 1.0), ("far", 0.75), ("a", 2 / 3), ("b", 1 / 3)])
+close(w.most_similar("q", 1, method="co_invalidation"), [("near", 1.0)])
+"#,
+    );
+}
+
+#[test]
+fn neighbourhood_metrics_match_hand_computed_scores() {
+    common::run_with(
+        SETUP,
+        r#"
+# q has 4 dependents; a shares 2 of its 2, b shares 3 of its 9. x and y have three
+# sources, z two.
+w = graph([("q", t) for t in ["x", "y", "z", "u"]] + [("a", "x"), ("a", "y")] +
+          [("b", t) for t in ["x", "y", "z", "v", "s", "t", "r", "o", "p"]] + [("c", "w")])
+close(w.most_similar("q", 5, method="jaccard", direction="out"), [("a", 2 / 4), ("b", 3 / 10)])
+close(w.most_similar("q", 5, method="overlap", direction="out"), [("a", 2 / 2), ("b", 3 / 4)])
+close(w.most_similar("q", 5, method="cosine", direction="out"), [("a", 2 / math.sqrt(8)), ("b", 3 / math.sqrt(36))])
+close(w.most_similar("q", 5, method="adamic_adar", direction="out"),
+      [("b", 2 / math.log(3) + 1 / math.log(2)), ("a", 2 / math.log(3))])
+# c shares nothing and is left out; its own ranking is empty.
+assert w.most_similar("c", 5, method="cosine", direction="out") == []
+
+# Equal scores go by id, whatever the insertion order.
+t = graph([("q", "x"), ("m2", "x"), ("m1", "x"), ("m3", "x")])
+for method in ["jaccard", "overlap", "cosine"]:
+    close(t.most_similar("q", 2, method=method, direction="out"), [("m1", 1.0), ("m2", 1.0)])
+"#,
+    );
+}
+
+#[test]
+fn similarity_matrix_scores_every_pair_in_order() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph([("q", "x"), ("q", "y"), ("a", "x"), ("c", "w")])
+m = w.similarity_matrix(["q", "a", "c"], method="cosine", direction="out")
+h = 1 / math.sqrt(2)
+expected = [[1.0, h, 0.0], [h, 1.0, 0.0], [0.0, 0.0, 1.0]]
+assert all(abs(x - y) < 1e-12 for row, want in zip(m, expected) for x, y in zip(row, want)), m
+# Rows follow the given order, and each entry matches most_similar.
+flipped = w.similarity_matrix(["a", "q"], direction="out")
+assert flipped == [[1.0, 0.5], [0.5, 1.0]]
+assert w.most_similar("q", 1, direction="out") == [("a", flipped[1][0])]
+assert w.similarity_matrix([]) == []
+try:
+    w.similarity_matrix(["q", "zz"])
+except rc.NodeNotFoundError as e:
+    assert e.args == ("zz",)
+else:
+    raise AssertionError("no NodeNotFoundError")
+"#,
+    );
+}
+
+#[test]
+fn unknown_ids_and_methods_raise() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph([("a", "b")])
+for call in [lambda: w.most_similar("missing", 3),
+             lambda: w.most_similar("missing", 3, method="simrank_1hop")]:
+    try:
+        call()
+    except rc.NodeNotFoundError as e:
+        assert e.args == ("missing",)
+    else:
+        raise AssertionError("no NodeNotFoundError")
+for kwargs in [{"method": "euclidean"}, {"direction": "sideways"}]:
+    try:
+        w.most_similar("a", 3, **kwargs)
+    except ValueError:
+        pass
+    else:
+        raise AssertionError("no ValueError for %r" % kwargs)
+assert w.most_similar("a", 0) == []
+"#,
+    );
+}
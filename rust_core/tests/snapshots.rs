@@ -0,0 +1,175 @@
+//! Snapshot checksums and `content_digest`.
+
+mod common;
+
+const SETUP: &str = r#"
+import os, struct, tempfile
+
+def build(order, ts=100.0):
+    w = rc.GraphWalker(track_provenance=True, normalize="lowercase")
+    # "A" is seen first either way, so both keep it as the original id.
+    w.touch_edge("A", "b", ts)
+    edges = [("a", "b", 2.0), ("a", "c", 1.0), ("b", "c", 0.5), ("c", "d", 1.0)]
+    for frm, to, weight in (edges if order else edges[::-1]):
+        # A touch creates the edge observed at `ts` (add_edge would use the clock).
+        w.touch_edge(frm, to, ts)
+        w.set_edge_weight(frm, to, weight)
+    w.touch_edge("b", "d", ts)
+    w.add_edge_labeled("b", "d", "feeds")
+    attrs = [("a", "team", "search"), ("a", "tier", "1"), ("c", "team", "ads")]
+    for id, key, value in (attrs if order else attrs[::-1]):
+        w.set_attribute(id, key, value)
+    w.add_node("e", [])
+    w.tombstone_node("e", ts)
+    w.mark_invalid("c")
+    return w
+
+def save(w, **kwargs):
+    fd, path = tempfile.mkstemp(suffix=".json")
+    os.close(fd)
+    w.save_snapshot(path, **kwargs)
+    with open(path, "rb") as f:
+        data = f.read()
+    os.remove(path)
+    return data
+
+def load(data):
+    fd, path = tempfile.mkstemp(suffix=".json")
+    with os.fdopen(fd, "wb") as f:
+        f.write(data)
+    try:
+        return rc.GraphWalker.load_snapshot(path)
+    finally:
+        os.remove(path)
+"#;
+
+#[test]
+fn digest_does_not_depend_on_insertion_order() {
+    common::run_with(
+        SETUP,
+        r#"
+assert build(True).content_digest() == build(False).content_digest()
+assert build(True).content_digest(structural=True) == build(False, ts=5.0).content_digest(structural=True)
+assert build(True).content_digest() != build(False, ts=5.0).content_digest()
+"#,
+    );
+}
+
+#[test]
+fn digest_covers_every_persisted_field() {
+    common::run_with(
+        SETUP,
+        r#"
+base = build(True).content_digest()
+changes = {
+    "weight": lambda w: w.set_edge_weight("a", "b", 3.0),
+    "label": lambda w: w.add_edge_labeled("b", "d", "reads"),
+    "unlabel": lambda w: w.add_edge_labeled("b", "d", None),
+    "attribute": lambda w: w.set_attribute("a", "team", "ads"),
+    "observed_at": lambda w: w.touch_edge("a", "b", 101.0),
+    "tombstone": lambda w: w.tombstone_node("c", 100.0),
+    "invalid": lambda w: w.mark_invalid("a"),
+    "cleared": lambda w: w.clear_invalid(),
+}
+for name, change in changes.items():
+    w = build(True)
+    change(w)
+    assert w.content_digest() != base, name
+"#,
+    );
+}
+
+#[test]
+fn snapshots_round_trip_with_their_digest() {
+    common::run_with(
+        SETUP,
+        r#"
+w = build(True)
+for kwargs in ({}, {"include_provenance": True, "include_original_ids": True}):
+    loaded = load(save(w, **kwargs))
+    assert loaded.content_digest(structural=True) == w.content_digest(structural=True)
+    assert loaded.get_invalid() == w.get_invalid()
+    assert loaded.tombstones() == w.tombstones()
+assert load(save(w, include_provenance=True, include_original_ids=True)).content_digest() == w.content_digest()
+"#,
+    );
+}
+
+#[test]
+fn corrupted_field_values_fail_the_checksum() {
+    common::run_with(
+        SETUP,
+        r#"
+data = save(build(True), include_provenance=True, include_original_ids=True)
+corruptions = [
+    (b'"weight":2.0', b'"weight":2.5'),
+    (b'"observed_at":100.0', b'"observed_at":200.0'),
+    (b'"label":"feeds"', b'"label":"feedz"'),
+    (b'"team":"search"', b'"team":"seerch"'),
+    (b'"e":100.0', b'"e":900.0'),
+    (b'"invalid":["d"]', b'"invalid":["c"]'),
+    (b'"source":"api"', b'"source":"apx"'),
+]
+for old, new in corruptions:
+    assert old in data, old
+    try:
+        load(data.replace(old, new, 1))
+    except rc.SerializationError as e:
+        message = str(e)
+        assert "checksum mismatch" in message and "expected" in message and "actual" in message, message
+    else:
+        raise AssertionError(f"{old!r} corrupted silently")
+"#,
+    );
+}
+
+#[test]
+fn no_single_byte_corruption_loads_a_different_graph() {
+    common::run_with(
+        SETUP,
+        r#"
+w = build(True)
+data = save(w, include_provenance=True, include_original_ids=True)
+digest = w.content_digest()
+for i in range(len(data)):
+    c = data[i:i + 1]
+    corrupted = data[:i] + (b"y" if c == b"x" else b"x") + data[i + 1:]
+    try:
+        loaded = load(corrupted)
+    except rc.SerializationError:
+        continue
+    # Only corruptions the format ignores, such as an unknown key, may load.
+    assert loaded.content_digest() == digest, (i, corrupted[max(0, i - 20):i + 20])
+"#,
+    );
+}
+
+#[test]
+fn version_1_snapshots_still_load() {
+    common::run_with(
+        SETUP,
+        r#"
+def s(text):
+    b = text.encode()
+    return struct.pack("<Q", len(b)) + b
+
+import hashlib
+body = s("a") + struct.pack("<Q", 0) + struct.pack("<Q", 1) + s("b") + struct.pack("<d", 1.0)
+checksum = "sha256:" + hashlib.sha256(body).hexdigest()
+v1 = json.dumps({
+    "version": 1,
+    "checksum": checksum,
+    "nodes": [{"id": "a", "edges": [{"to": "b", "weight": 1.0, "observed_at": 7.0}]}],
+})
+w = rc.GraphWalker.from_json(v1)
+assert w.get_dependents("a") == ["b"]
+assert json.loads(w.to_json())["version"] == 2
+try:
+    rc.GraphWalker.from_json(v1.replace('"weight": 1.0', '"weight": 2.0'))
+except rc.SerializationError:
+    pass
+else:
+    raise AssertionError("corrupted v1 snapshot loaded")
+"#,
+    );
+}
@@ -0,0 +1,84 @@
+//! `minimum_spanning_tree`: a weighted square with a diagonal leaving out the
+//! heaviest edges, a forest over several components, deterministic ties, and the
+//! total weight matching a Python Kruskal on random graphs.
+
+mod common;
+
+const SETUP: &str = r#"
+import random
+
+def build(edges, nodes=()):
+    w = rc.GraphWalker()
+    for a, b, c in edges:
+        w.add_edge(a, b, weight=c)
+    for id in nodes:
+        w.add_node(id, [])
+    return w
+
+def kruskal(w):
+    ids = set(w.node_ids())
+    best = {}
+    for v in ids:
+        for d in w.get_dependents(v):
+            if d in ids and d != v:
+                pair = tuple(sorted((v, d)))
+                best[pair] = min(best.get(pair, float("inf")), w.get_edge(v, d)[0])
+    parent = {v: v for v in ids}
+    def find(x):
+        while parent[x] != x:
+            x = parent[x]
+        return x
+    total, count = 0.0, 0
+    for (a, b), c in sorted(best.items(), key=lambda item: (item[1], item[0])):
+        ra, rb = find(a), find(b)
+        if ra != rb:
+            parent[ra] = rb
+            total += c
+            count += 1
+    return total, count
+"#;
+
+#[test]
+fn the_heaviest_edges_are_left_out() {
+    common::run_with(
+        SETUP,
+        r#"
+square = build([("a", "b", 1.0), ("b", "c", 2.0), ("c", "d", 3.0), ("d", "a", 4.0), ("a", "c", 5.0)])
+assert square.minimum_spanning_tree() == (6.0, [("a", "b"), ("b", "c"), ("c", "d")])
+
+# One tree per component; both directions of x-y count once, at the lower weight.
+two = build([("a", "b", 1.0), ("b", "c", 1.0), ("c", "a", 1.0), ("x", "y", 2.0), ("y", "x", 0.5),
+             ("p", "q", 1.0), ("q", "ghost", 0.1)], nodes=["lone"])
+assert two.minimum_spanning_tree() == (3.5, [("a", "b"), ("a", "c"), ("p", "q"), ("x", "y")])
+# Equal weights break ties by the id pair, so the same forest comes back every time.
+assert build([("c", "a", 1.0), ("b", "c", 1.0), ("a", "b", 1.0)]).minimum_spanning_tree() == (2.0, [("a", "b"), ("a", "c")])
+assert rc.GraphWalker().minimum_spanning_tree() == (0.0, [])
+"#,
+    );
+}
+
+#[test]
+fn random_forests_match_kruskal() {
+    common::run_with(
+        SETUP,
+        r#"
+for seed in range(6):
+    r = random.Random(seed)
+    w = build([("n%d" % r.randrange(30), "n%d" % r.randrange(30), r.choice([0.5, 1.0, 2.0, 3.0, 7.5])) for _ in range(50)])
+    total, edges = w.minimum_spanning_tree()
+    expected_total, expected_count = kruskal(w)
+    assert abs(total - expected_total) < 1e-12 and len(edges) == expected_count, seed
+    assert edges == sorted(edges) and all(a < b for a, b in edges)
+    # A forest: no edge joins two nodes already connected.
+    parent = {}
+    def find(x):
+        parent.setdefault(x, x)
+        while parent[x] != x:
+            x = parent[x]
+        return x
+    for a, b in edges:
+        assert find(a) != find(b), (seed, a, b)
+        parent[find(a)] = find(b)
+"#,
+    );
+}
@@ -0,0 +1,85 @@
+//! `split_node` on a hub: each part takes its share of the hub's edges, and
+//! propagation, ancestors and counts change exactly as the split says.
+
+mod common;
+
+const SETUP: &str = r#"
+def hub():
+    """s1, s2, s3 -> hub -> t1, t2, t3."""
+    w = rc.GraphWalker()
+    for s in ["s1", "s2", "s3"]:
+        w.add_node(s, ["hub"])
+    w.add_node("hub", ["t1", "t2", "t3"], attributes={"team": "core"})
+    for t in ["t1", "t2", "t3"]:
+        w.add_node(t, [])
+    w.set_edge_weight("hub", "t1", 0.5)
+    w.add_edge_labeled("hub", "t1", "feeds")
+    return w
+
+def edge(w, frm, to):
+    """The exported form of the edge `frm -> to`, without its timestamp."""
+    node = next(n for n in json.loads(w.to_json())["nodes"] if n["id"] == frm)
+    found = next(e for e in node["edges"] if e["to"] == to)
+    found.pop("observed_at")
+    return found
+
+def raises(error, call, fragment):
+    try:
+        call()
+    except error as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+"#;
+
+#[test]
+fn splitting_a_hub_divides_its_blast_radius() {
+    common::run_with(
+        SETUP,
+        r#"
+w = hub()
+assert w.propagate_invalidation("s1") == {"hub", "t1", "t2", "t3"}
+w.split_node("hub", [("hub_a", ["s1"], ["t1"]), ("hub_b", ["s2", "s3"], ["t2"])])
+assert "hub" not in w and w.node_count() == 8
+assert w.propagate_invalidation("s1") == {"hub_a", "t1"}
+assert w.propagate_invalidation("s2") == w.propagate_invalidation("s3") == {"hub_b", "t2"}
+# The unassigned edge hub -> t3 is dropped with the hub.
+assert w.find_ancestors("t3") == set()
+assert w.find_ancestors("t1") == {"s1", "hub_a"} and w.find_ancestors("t2") == {"s2", "s3", "hub_b"}
+assert w.edge_count() == 5
+assert w.get_dependents("s1") == ["hub_a"] and w.get_dependents("hub_b") == ["t2"]
+
+# Weights and labels travel with their edges; attributes are copied.
+assert edge(w, "hub_a", "t1") == {"to": "t1", "weight": 0.5, "label": "feeds"}
+assert w.get_attributes("hub_a") == w.get_attributes("hub_b") == {"team": "core"}
+"#,
+    );
+}
+
+#[test]
+fn kept_originals_keep_unassigned_edges_and_strict_rejects_them() {
+    common::run_with(
+        SETUP,
+        r#"
+w = hub()
+w.split_node("hub", [("hub_a", ["s1"], ["t1", "t2"])], keep_original=True, copy_attributes=False)
+assert w.get_dependents("hub") == ["t3"] and w.get_dependents("hub_a") == ["t1", "t2"]
+assert w.propagate_invalidation("s1") == {"hub_a", "t1", "t2"}
+assert w.propagate_invalidation("s2") == {"hub", "t3"}
+assert w.get_attributes("hub_a") == {} and w.get_attributes("hub") == {"team": "core"}
+
+# An edge listed on several parts goes to each.
+w = hub()
+w.split_node("hub", [("x", ["s1", "s2", "s3"], ["t1"]), ("y", ["s1"], ["t1", "t2", "t3"])])
+assert w.propagate_invalidation("s1") == {"x", "y", "t1", "t2", "t3"}
+assert w.find_ancestors("t1") == {"s1", "s2", "s3", "x", "y"}
+
+w = hub()
+before = w.to_json()
+raises(ValueError, lambda: w.split_node("hub", [("hub_a", ["s1"], ["t1"])], strict=True),
+       "leaves edges unassigned: hub -> t2, hub -> t3, s2 -> hub, s3 -> hub")
+assert w.to_json() == before
+raises(KeyError, lambda: w.split_node("missing", [("x", [], [])]), "missing")
+"#,
+    );
+}
@@ -0,0 +1,79 @@
+//! `stable_order` depends only on graph content: equal graphs built in different
+//! ways order their nodes the same.
+
+mod common;
+
+const SETUP: &str = r#"
+import os, random, tempfile
+
+NODES = [("a", ["b"]), ("c", ["b"]), ("b", ["d"]), ("d", []), ("x", ["y"]), ("y", []), ("z", [])]
+
+def by_nodes(entries):
+    w = rc.GraphWalker()
+    for id, dependents in entries:
+        w.add_node(id, dependents)
+    return w
+"#;
+
+#[test]
+fn components_come_largest_first_then_by_pagerank() {
+    common::run_with(
+        SETUP,
+        r#"
+w = by_nodes(NODES)
+# {a, b, c, d} first, with the sink d and then b ranked highest and a, c tied;
+# then {x, y}; then z.
+assert w.stable_order() == ["d", "b", "a", "c", "y", "x", "z"]
+stats = w.computation_stats()
+assert w.stable_order() == w.stable_order()
+assert w.computation_stats()["hits"] >= stats["hits"] + 2, "cached until the next mutation"
+
+fd, path = tempfile.mkstemp(suffix=".json")
+os.close(fd)
+try:
+    w.save_snapshot(path, include_order=True)
+    with open(path) as f:
+        nodes = json.load(f)["nodes"]
+finally:
+    os.remove(path)
+assert sorted((n["order"], n["id"]) for n in nodes) == list(enumerate(w.stable_order()))
+"#,
+    );
+}
+
+#[test]
+fn equal_graphs_built_differently_share_an_order() {
+    common::run_with(
+        SETUP,
+        r#"
+expected = by_nodes(NODES).stable_order()
+assert by_nodes(NODES[::-1]).stable_order() == expected
+
+w = rc.GraphWalker()
+for id, dependents in NODES:
+    for dep in dependents:
+        w.add_edge(id, dep)
+for id in ["d", "y", "z", "extra"]:
+    w.add_node(id, [])
+w.remove_node("extra")
+assert w.stable_order() == expected
+assert rc.GraphWalker.from_json(w.to_json()).stable_order() == expected
+
+# The same on a larger graph with many pagerank ties, added in two shuffled orders.
+rng = random.Random(5)
+edges = sorted({("n%d" % rng.randrange(150), "n%d" % rng.randrange(150)) for _ in range(300)})
+orders = []
+for seed in [1, 2]:
+    shuffled = edges[:]
+    random.Random(seed).shuffle(shuffled)
+    g = rc.GraphWalker()
+    for frm, to in shuffled:
+        g.add_edge(frm, to)
+    for i in range(150):
+        if "n%d" % i not in g:
+            g.add_node("n%d" % i, [])
+    orders.append(g.stable_order())
+assert orders[0] == orders[1] and sorted(orders[0]) == sorted("n%d" % i for i in range(150))
+"#,
+    );
+}
@@ -0,0 +1,107 @@
+//! `subgraph` and `ego_graph`: edges crossing the boundary are pruned, weights,
+//! labels and attributes are copied, unknown ids raise or are skipped, each
+//! `direction` follows its own edges, and the result is independent of its parent.
+
+mod common;
+
+const SETUP: &str = r#"
+def graph():
+    # p -> a -> b -> c -> a, with a -> x weighted and c -> ghost, an edge target only.
+    w = rc.GraphWalker()
+    w.add_node("a", ["b", "x"], weights=[2.0, 1.0], attributes={"team": "search"})
+    w.add_node("b", ["c"])
+    w.add_node("c", ["a", "ghost"])
+    w.add_node("x", [])
+    w.add_edge_labeled("p", "a", "caches")
+    return w
+
+def edges(w):
+    return {id: w.get_dependents(id) for id in w.node_ids()}
+"#;
+
+#[test]
+fn subgraph_prunes_edges_at_the_boundary() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+s = w.subgraph(["a", "b", "p"])
+assert edges(s) == {"a": ["b"], "b": [], "p": ["a"]}
+assert s.get_edge("a", "b")[0] == 2.0 and s.get_dependents_labeled("p") == [("a", "caches")]
+assert s.get_attributes("a") == {"team": "search"}
+assert edges(w.subgraph(["c", "a"])) == {"a": [], "c": ["a"]}
+assert w.subgraph([]).node_ids() == []
+
+try:
+    w.subgraph(["a", "nope"])
+except rc.NodeNotFoundError as e:
+    assert e.args == ("nope",)
+else:
+    raise AssertionError("no NodeNotFoundError")
+# "ghost" is not a node either, so it is skipped like "nope".
+assert edges(w.subgraph(["a", "nope", "ghost", "x"], strict=False)) == {"a": ["x"], "x": []}
+"#,
+    );
+}
+
+#[test]
+fn ego_graphs_follow_the_chosen_direction() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+assert edges(w.ego_graph("a", 1, direction="out")) == {"a": ["b", "x"], "b": [], "x": []}
+assert edges(w.ego_graph("a", 1, direction="in")) == {"a": [], "c": ["a"], "p": ["a"]}
+assert w.ego_graph("a", 1).node_ids() == ["a", "b", "c", "p", "x"]
+assert w.ego_graph("a", 0).node_ids() == ["a"]
+assert w.ego_graph("b", 1, direction="out").node_ids() == ["b", "c"]
+assert w.ego_graph("b", 2, direction="out").node_ids() == ["a", "b", "c"]
+
+# A hop may pass through an edge target that is not a node; the target itself is left out.
+v = rc.GraphWalker()
+v.add_edge("a", "t")
+v.add_edge("z", "t")
+assert v.ego_graph("a", 1).node_ids() == ["a"]
+assert edges(v.ego_graph("a", 2)) == {"a": [], "z": []}
+
+try:
+    w.ego_graph("a", 1, direction="up")
+except ValueError as e:
+    assert str(e) == "unknown direction 'up' (expected out, in or both)", str(e)
+else:
+    raise AssertionError("no ValueError")
+try:
+    w.ego_graph("zz", 1)
+except rc.NodeNotFoundError:
+    pass
+else:
+    raise AssertionError("no NodeNotFoundError")
+"#,
+    );
+}
+
+#[test]
+fn mutating_the_result_leaves_the_parent_alone() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+before = (edges(w), w.content_digest())
+for s in (w.subgraph(["a", "b"]), w.ego_graph("a", 2)):
+    s.set_edge_weight("a", "b", 7.0)
+    s.add_node("a", ["q"])
+    s.set_attribute("b", "team", "ads")
+    s.add_edge("b", "a", weight=5.0)
+    s.tombstone_node("b", 1.0)
+    assert edges(w) == before[0] and w.content_digest() == before[1]
+    assert w.get_attributes("a") == {"team": "search"} and w.get_attributes("b") == {}
+# And the other way round.
+s = w.subgraph(["a", "b"])
+w.set_attribute("a", "team", "ads")
+w.set_edge_weight("a", "b", 9.0)
+w.add_node("b", ["a"])
+assert s.get_attributes("a") == {"team": "search"} and s.get_edge("a", "b")[0] == 2.0
+assert s.get_dependents("b") == []
+"#,
+    );
+}
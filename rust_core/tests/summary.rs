@@ -0,0 +1,79 @@
+//! `degree_distribution`, `in_degree`, `out_degree` and `summary`: an empty
+//! graph, a single node and a small known graph, the printed report, and the DAG
+//! flag flipping on a cycle.
+
+mod common;
+
+const SETUP: &str = r#"
+def known():
+    # a -> b -> c and a -> c, d -> x with x not a node, and e isolated.
+    w = rc.GraphWalker()
+    w.add_node("a", ["b", "c"])
+    w.add_node("b", ["c"])
+    w.add_node("c", [])
+    w.add_node("d", ["x"])
+    w.add_node("e", [])
+    return w
+
+def figures(s):
+    return (s.node_count, s.edge_count, s.density, s.weakly_connected_components, s.isolated_nodes,
+            s.max_in_degree, s.max_in_degree_nodes, s.max_out_degree, s.max_out_degree_nodes, s.is_dag)
+"#;
+
+#[test]
+fn degrees_count_stored_and_incoming_edges() {
+    common::run_with(
+        SETUP,
+        r#"
+w = known()
+assert w.degree_distribution() == {0: 2, 1: 2, 2: 1}
+assert [w.in_degree(id) for id in "abcde"] == [0, 1, 2, 0, 0]
+# The edge to x counts as stored on d, but x has no in-degree of its own.
+assert [w.out_degree(id) for id in "abcde"] == [2, 1, 0, 1, 0]
+for call in (lambda: w.in_degree("x"), lambda: w.out_degree("zz")):
+    try:
+        call()
+    except rc.NodeNotFoundError:
+        pass
+    else:
+        raise AssertionError("no NodeNotFoundError")
+assert rc.GraphWalker().degree_distribution() == {}
+"#,
+    );
+}
+
+#[test]
+fn summaries_of_empty_single_and_known_graphs() {
+    common::run_with(
+        SETUP,
+        r#"
+assert figures(rc.GraphWalker().summary()) == (0, 0, 0.0, 0, 0, 0, [], 0, [], True)
+solo = rc.GraphWalker()
+solo.add_node("solo", [])
+assert figures(solo.summary()) == (1, 0, 0.0, 1, 1, 0, ["solo"], 0, ["solo"], True)
+
+w = known()
+# {a, b, c}, {d} and {e}; density is 4 edges over 5 * 4 ordered pairs.
+assert figures(w.summary()) == (5, 4, 0.2, 3, 1, 2, ["c"], 2, ["a"], True)
+assert repr(w.summary()) == "\n".join([
+    "GraphSummary",
+    "  nodes: 5",
+    "  edges: 4",
+    "  density: 0.200000",
+    "  weakly connected components: 3",
+    "  isolated nodes: 1",
+    "  max in-degree: 2 (c)",
+    "  max out-degree: 2 (a)",
+    "  DAG: yes",
+])
+assert repr(rc.GraphWalker().summary()).splitlines()[6:] == ["  max in-degree: 0", "  max out-degree: 0", "  DAG: yes"]
+
+w.add_edge("c", "a")
+w.add_edge("e", "d")
+s = w.summary()
+assert not s.is_dag and "  DAG: no" in repr(s)
+assert (s.weakly_connected_components, s.isolated_nodes, s.max_out_degree_nodes) == (2, 0, ["a"])
+assert s.max_in_degree_nodes == ["c"] and w.degree_distribution() == {1: 4, 2: 1}
+"#,
+    );
+}
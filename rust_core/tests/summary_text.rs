@@ -0,0 +1,71 @@
+//! `summarize_text`: the character budget is respected at line boundaries, focus
+//! nodes come first, and every line is a `node -> [deps]` entry or a `#` header.
+
+mod common;
+
+const SETUP: &str = r##"
+import re
+
+def graph(order=1):
+    w = rc.GraphWalker()
+    entries = [("n%d" % i, ["n%d" % ((i + 1) % 30), "n%d" % ((i * 3) % 30)]) for i in range(30)]
+    entries += [("café", ["n1"]), ("solo", ["ünïcode"])]
+    for id, dependents in entries[::order]:
+        w.add_node(id, dependents)
+    return w
+
+ENTRY = re.compile(r"^(\S+) -> \[((\S+, )*\S+)?\]$")
+
+def check_lines(text):
+    for line in text.splitlines():
+        assert line.startswith("# ") or ENTRY.match(line), repr(line)
+    return text.splitlines()
+"##;
+
+#[test]
+fn the_budget_is_respected_at_line_boundaries() {
+    common::run_with(
+        SETUP,
+        r##"
+w = graph()
+full = w.summarize_text(100_000)
+lines = check_lines(full)
+assert lines[0] == "# graph: 32 nodes, 62 edges, 2 components", lines[0]
+assert "# central" in lines and any(l.startswith("# cycle group (30 nodes)") for l in lines)
+assert any(l.startswith("# community") for l in lines)
+for budget in list(range(0, 200)) + list(range(200, len(full) + 50, 37)):
+    text = w.summarize_text(budget)
+    assert len(text) <= budget, (budget, len(text))
+    # The longest run of whole lines from the full summary that fits.
+    kept = check_lines(text)
+    assert kept == lines[:len(kept)], budget
+    if len(kept) < len(lines):
+        assert len("\n".join(lines[:len(kept) + 1])) > budget, budget
+assert w.summarize_text(0) == ""
+"##,
+    );
+}
+
+#[test]
+fn focus_nodes_come_first_and_output_is_deterministic() {
+    common::run_with(
+        SETUP,
+        r##"
+w = graph()
+text = w.summarize_text(60, focus=["solo", "café"])
+assert check_lines(text)[:2] == ["solo -> [ünïcode]", "café -> [n1]"], text
+# Even a budget that only fits them keeps the focus lines.
+assert w.summarize_text(len("solo -> [ünïcode]\ncafé -> [n1]"), focus=["solo", "café"]).splitlines() == ["solo -> [ünïcode]", "café -> [n1]"]
+assert "solo" not in w.summarize_text(300), "solo is neither central nor focused"
+
+for budget, focus in [(100_000, None), (500, None), (400, ["n7", "solo"])]:
+    assert graph().summarize_text(budget, focus) == graph(-1).summarize_text(budget, focus) == w.summarize_text(budget, focus)
+try:
+    w.summarize_text(100, focus=["missing"])
+except KeyError as e:
+    assert "missing" in str(e)
+else:
+    raise AssertionError("no KeyError")
+"##,
+    );
+}
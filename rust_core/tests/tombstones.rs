@@ -0,0 +1,103 @@
+//! `tombstone_node`: tombstoned ids drop out of traversal and analysis, are
+//! exported separately, and come back with their incoming edges when re-added.
+
+mod common;
+
+const SETUP: &str = r#"
+def chain():
+    """a -> b -> c, and d -> c."""
+    w = rc.GraphWalker()
+    w.add_node("a", ["b"])
+    w.add_node("b", ["c"])
+    w.add_node("c", [])
+    w.add_node("d", ["c"])
+    return w
+"#;
+
+#[test]
+fn propagation_and_analysis_ignore_tombstoned_nodes() {
+    common::run_with(
+        SETUP,
+        r#"
+w = chain()
+assert w.tombstone_node("b", 5.0)
+assert not w.tombstone_node("b") and not w.tombstone_node("missing")
+assert w.tombstones() == [("b", 5.0)]
+assert w.propagate_invalidation("a") == set(), "the edge into b is not followed"
+assert w.propagate_invalidation("d") == {"c"}
+assert w.find_ancestors("c") == {"d"}
+assert w.node_ids() == ["a", "c", "d"] and "b" not in w
+assert set(w.calculate_pagerank(20, 0.85)) == {"a", "c", "d"}
+# The edge into b stays, waiting for b to come back.
+assert w.get_dependents("a") == ["b"] and w.edge_count() == 2
+"#,
+    );
+}
+
+#[test]
+fn re_adding_a_tombstoned_id_clears_it_and_restores_its_edges() {
+    common::run_with(
+        SETUP,
+        r#"
+w = chain()
+w.tombstone_node("b", 5.0)
+w.add_node("b", ["c", "e"])
+assert w.tombstones() == []
+assert w.propagate_invalidation("a") == {"b", "c", "e"}
+assert w.find_ancestors("c") == {"a", "b", "d"}
+
+# An edge to the tombstoned id also brings it back.
+w.tombstone_node("c", 6.0)
+assert w.propagate_invalidation("a") == {"b", "e"}
+w.add_edge("c", "x")
+assert w.tombstones() == [] and w.propagate_invalidation("a") == {"b", "c", "e", "x"}
+"#,
+    );
+}
+
+#[test]
+fn tombstones_are_purged_by_age_and_exported_separately() {
+    common::run_with(
+        SETUP,
+        r#"
+w = chain()
+for id, ts in [("b", 10.0), ("a", 20.0), ("d", 30.0)]:
+    w.tombstone_node(id, ts)
+assert w.tombstones() == [("a", 20.0), ("b", 10.0), ("d", 30.0)]
+
+exported = json.loads(w.to_json())
+assert exported["tombstones"] == {"a": 20.0, "b": 10.0, "d": 30.0}
+assert [n["id"] for n in exported["nodes"]] == ["c"]
+copy = rc.GraphWalker.from_json(w.to_json())
+assert copy.tombstones() == w.tombstones() and copy.node_ids() == ["c"]
+
+assert w.purge_tombstones(20.0) == 1 and w.tombstones() == [("a", 20.0), ("d", 30.0)]
+assert w.purge_tombstones(100.0) == 2 and w.tombstones() == []
+assert json.loads(w.to_json())["tombstones"] == {}
+"#,
+    );
+}
+
+#[test]
+fn consumers_tombstone_deleted_facts_when_asked() {
+    common::run_with(
+        SETUP,
+        r#"
+broker = rc.MemoryBroker()
+for flag in [True, False]:
+    topic = "facts-%s" % flag
+    shared = rc.SharedGraphWalker()
+    handle = rc.start_kafka_consumer(broker, topic, walker=shared, tombstone_deletes=flag)
+    try:
+        broker.send(topic, json.dumps({"fact_id": "a", "dependents": ["b"]}))
+        broker.send(topic, json.dumps({"fact_id": "b", "dependents": []}))
+        broker.send(topic, json.dumps({"fact_id": "b", "op": "delete"}))
+        wait_until(lambda: handle.status()["messages"] == 3)
+    finally:
+        handle.stop()
+    snapshot = shared.snapshot()
+    assert snapshot.node_ids() == ["a"]
+    assert [id for id, _ in snapshot.tombstones()] == (["b"] if flag else [])
+"#,
+    );
+}
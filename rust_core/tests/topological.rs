@@ -0,0 +1,100 @@
+//! `topological_sort`, `topological_layers` and `propagate_invalidation_ordered`:
+//! a diamond DAG, longest-path layering, every edge respected on random DAGs, id
+//! tie-breaking, and `CycleError` naming a cycle.
+
+mod common;
+
+const SETUP: &str = r#"
+import random
+
+def build(edges, nodes=()):
+    w = rc.GraphWalker()
+    for id in nodes:
+        w.add_node(id, [])
+    for a, b in edges:
+        w.add_edge(a, b)
+    return w
+
+def respects(w, order):
+    position = {id: i for i, id in enumerate(order)}
+    return all(position[v] < position[d] for v in order for d in w.get_dependents(v) if d in position)
+"#;
+
+#[test]
+fn a_diamond_sorts_into_layers() {
+    common::run_with(
+        SETUP,
+        r#"
+w = build([("a", "b"), ("a", "c"), ("b", "d"), ("c", "d")], nodes=["d"])
+assert w.topological_layers() == [["a"], ["b", "c"], ["d"]]
+assert w.topological_sort() == ["a", "b", "c", "d"]
+
+# A shortcut edge does not pull t forward: its longest path has two edges.
+s = build([("s", "a"), ("s", "b"), ("a", "t"), ("b", "t"), ("s", "t"), ("t", "u")], nodes=["lone"])
+assert s.topological_layers() == [["lone", "s"], ["a", "b"], ["t"]]
+# Flattened layers; "u" is not a node, so the whole-graph methods leave it out.
+assert s.topological_sort() == ["lone", "s", "a", "b", "t"]
+assert s.propagate_invalidation_ordered("s") == ["a", "b", "t", "u"]
+assert s.propagate_invalidation_ordered("a") == ["t", "u"]
+
+# Ties go by id within a layer, not by the order Kahn's queue would pop them.
+ties = build([("a", "b")], nodes=["b", "c"])
+assert ties.topological_sort() == ["a", "c", "b"]
+assert rc.GraphWalker().topological_sort() == [] and rc.GraphWalker().topological_layers() == []
+"#,
+    );
+}
+
+#[test]
+fn random_dags_respect_every_edge() {
+    common::run_with(
+        SETUP,
+        r#"
+for seed in range(6):
+    r = random.Random(seed)
+    names = ["n%02d" % i for i in range(40)]
+    r.shuffle(names)
+    # Edges only go forward in the shuffled rank, so the graph is acyclic.
+    edges = [(names[i], names[j]) for i, j in (sorted(r.sample(range(40), 2)) for _ in range(80))]
+    w = build(edges, nodes=names)
+    order = w.topological_sort()
+    assert sorted(order) == sorted(names) and respects(w, order), seed
+    layers = w.topological_layers()
+    assert [id for layer in layers for id in layer] == order
+    assert all(layer == sorted(layer) for layer in layers)
+    depth = {id: k for k, layer in enumerate(layers) for id in layer}
+    for v in names:
+        for d in w.get_dependents(v):
+            assert depth[d] > depth[v]
+        if depth[v]:
+            assert any(depth[p] == depth[v] - 1 and v in w.get_dependents(p) for p in names), v
+    for start in names[:5]:
+        ordered = w.propagate_invalidation_ordered(start)
+        assert set(ordered) == w.propagate_invalidation(start) and respects(w, ordered)
+"#,
+    );
+}
+
+#[test]
+fn cycles_raise_cycle_error() {
+    common::run_with(
+        SETUP,
+        r#"
+w = build([("p", "q"), ("q", "r"), ("r", "q"), ("x", "p")])
+assert issubclass(rc.CycleError, ValueError)
+for call in (w.topological_sort, w.topological_layers, lambda: w.propagate_invalidation_ordered("x")):
+    try:
+        call()
+    except rc.CycleError as e:
+        assert str(e) == "graph has a cycle: q -> r -> q", str(e)
+    else:
+        raise AssertionError("no CycleError")
+try:
+    w.propagate_invalidation_ordered("nope")
+except rc.NodeNotFoundError:
+    pass
+else:
+    raise AssertionError("no NodeNotFoundError")
+"#,
+    );
+}
@@ -0,0 +1,132 @@
+//! `begin_transaction` / `commit` / `rollback`: a rolled-back transaction leaves
+//! every count and query as it was before it began.
+
+mod common;
+
+const SETUP: &str = r#"
+def graph(**kwargs):
+    w = rc.GraphWalker(record_events=True, track_provenance=True, **kwargs)
+    w.add_node("a", ["b", "c"], attributes={"team": "search"})
+    w.add_node("b", ["c"])
+    w.add_node("c", [])
+    w.add_edge("b", "d", weight=0.5)
+    w.tombstone_node("c")
+    return w
+
+def state(w):
+    return {
+        "nodes": w.node_count(),
+        "edges": w.edge_count(),
+        "ids": w.node_ids(),
+        "dependents": {id: w.get_dependents(id) for id in w.node_ids()},
+        "edge": w.get_edge("b", "d"),
+        "attributes": w.get_attributes("a"),
+        "propagation": w.propagate_invalidation("a"),
+        "ancestors": w.find_ancestors("d"),
+        "tombstones": [id for id, _ in w.tombstones()],
+        "events": len(w.event_log()),
+        "json": w.to_json(),
+    }
+
+def raises(error, call, fragment):
+    try:
+        call()
+    except error as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+"#;
+
+#[test]
+fn rollback_restores_the_state_before_the_transaction() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+before = state(w)
+w.begin_transaction()
+assert w.in_transaction()
+w.add_node("e", ["a", "f"])
+w.add_edge("d", "g")
+w.add_edge("b", "d", weight=2.0)
+w.add_node("b", ["a"])
+w.remove_edge("a", "b")
+w.set_attribute("a", "team", "ads")
+w.add_node("c", ["a"])
+w.remove_node("d")
+w.decay_weights(0.5)
+assert w.node_count() != before["nodes"] and w.get_attributes("a") == {"team": "ads"}
+w.rollback()
+assert not w.in_transaction()
+assert state(w) == before, (state(w), before)
+"#,
+    );
+}
+
+#[test]
+fn commit_keeps_the_mutations() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+w.begin_transaction()
+w.add_node("e", ["a"])
+w.add_edge("d", "g")
+w.commit()
+assert not w.in_transaction()
+assert w.get_dependents("e") == ["a"] and w.get_dependents("d") == ["g"]
+assert w.find_ancestors("g") == {"a", "b", "d", "e"}
+# A later transaction rolls back to the committed state.
+after = state(w)
+w.begin_transaction()
+w.remove_node("e")
+w.rollback()
+assert state(w) == after
+"#,
+    );
+}
+
+#[test]
+fn rollback_without_a_transaction_and_nesting_raise() {
+    common::run_with(
+        SETUP,
+        r#"
+w = graph()
+before = state(w)
+raises(ValueError, w.rollback, "no transaction")
+raises(ValueError, w.commit, "no transaction")
+w.begin_transaction()
+w.add_edge("a", "z")
+raises(ValueError, w.begin_transaction, "already open")
+assert w.in_transaction() and w.get_dependents("a") == ["b", "c", "z"]
+w.rollback()
+assert state(w) == before
+
+# A rollback refused while frozen leaves the transaction open.
+w.begin_transaction()
+w.add_edge("a", "z")
+w.freeze()
+raises(rc.FrozenGraphError, w.rollback, "")
+assert w.in_transaction()
+w.unfreeze()
+w.rollback()
+assert state(w) == before
+"#,
+    );
+}
+
+#[test]
+fn a_rolled_back_dag_walker_accepts_the_edges_it_undid() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(enforce_dag=True)
+w.add_edge("a", "b")
+w.begin_transaction()
+w.add_edge("b", "c")
+w.rollback()
+w.add_edge("c", "a")
+assert w.get_dependents("c") == ["a"]
+"#,
+    );
+}
@@ -0,0 +1,80 @@
+//! `count_triangles` over the undirected projection, the directed variants
+//! `count_directed_cycles_of_length_3` and `count_transitive_triads`, and the local
+//! and global clustering coefficients, against hand counts and brute force.
+
+mod common;
+
+const SETUP: &str = r#"
+import itertools, random
+
+def build(edges, nodes=()):
+    w = rc.GraphWalker()
+    for id in nodes:
+        w.add_node(id, [])
+    for a, b in edges:
+        w.add_edge(a, b)
+    return w
+
+def counts(w):
+    return (w.count_triangles(), w.count_directed_cycles_of_length_3(), w.count_transitive_triads())
+
+def brute(w):
+    ids = w.node_ids()
+    arcs = {(v, d) for v in ids for d in w.get_dependents(v) if w.has_node(d) and d != v}
+    linked = lambda x, y: (x, y) in arcs or (y, x) in arcs
+    triangles = cycles = triads = 0
+    for x, y, z in itertools.combinations(ids, 3):
+        triangles += linked(x, y) and linked(y, z) and linked(x, z)
+    for x, y, z in itertools.permutations(ids, 3):
+        # Each directed 3-cycle appears once per rotation.
+        cycles += ((x, y) in arcs and (y, z) in arcs and (z, x) in arcs)
+        triads += ((x, y) in arcs and (y, z) in arcs and (x, z) in arcs)
+    return (triangles, cycles // 3, triads)
+"#;
+
+#[test]
+fn undirected_and_directed_counts() {
+    common::run_with(
+        SETUP,
+        r#"
+assert counts(build([("a", "b"), ("b", "c"), ("a", "c")], nodes="c")) == (1, 0, 1)
+assert counts(build(itertools.combinations("abcd", 2), nodes="d")) == (4, 0, 4)
+# A directed 3-cycle is a cycle, not a transitive triad, and one triangle.
+assert counts(build([("a", "b"), ("b", "c"), ("c", "a")])) == (1, 1, 0)
+# Reverse and parallel edges and self-loops change nothing undirected.
+noisy = build([("a", "b"), ("b", "c"), ("c", "a"), ("a", "c"), ("b", "a"), ("a", "b"), ("a", "a")])
+assert counts(noisy) == (1, 1, 3)
+assert counts(rc.GraphWalker()) == (0, 0, 0)
+
+for seed in range(5):
+    r = random.Random(seed)
+    w = build([("n%d" % r.randrange(15), "n%d" % r.randrange(15)) for _ in range(45)],
+              nodes=["n%d" % i for i in range(15)])
+    assert counts(w) == brute(w), (seed, counts(w), brute(w))
+"#,
+    );
+}
+
+#[test]
+fn clustering_coefficients() {
+    common::run_with(
+        SETUP,
+        r#"
+# h-x-y is a triangle and z hangs off h: h closes 1 of its 3 neighbor pairs.
+w = build([("h", "x"), ("h", "y"), ("x", "y"), ("h", "z")], nodes="yz")
+assert abs(w.clustering_coefficient("h") - 1 / 3) < 1e-12
+assert w.clustering_coefficient("x") == w.clustering_coefficient("y") == 1.0
+assert w.clustering_coefficient("z") == 0.0
+# 3 * triangles / connected triples = 3 * 1 / (3 + 1 + 1).
+assert abs(w.global_clustering_coefficient() - 0.6) < 1e-12
+assert build(itertools.combinations("abcd", 2), nodes="d").global_clustering_coefficient() == 1.0
+assert rc.GraphWalker().global_clustering_coefficient() == 0.0
+try:
+    w.clustering_coefficient("nope")
+except rc.NodeNotFoundError:
+    pass
+else:
+    raise AssertionError("no NodeNotFoundError")
+"#,
+    );
+}
@@ -0,0 +1,127 @@
+//! `mode="undirected_multi"`: one event stream fed to a directed and an undirected
+//! walker, the documented differences in counts, traversal, triangles, similarity
+//! and export, and the directed-only calls raising `ModeError`.
+
+mod common;
+
+const SETUP: &str = r#"
+import os, tempfile
+
+STREAM = [("a", "b"), ("b", "a"), ("a", "b"), ("b", "c"), ("c", "a"), ("d", "d")]
+
+def both():
+    directed, undirected = rc.GraphWalker(), rc.GraphWalker(mode="undirected_multi")
+    for w in (directed, undirected):
+        for a, b in STREAM:
+            w.add_edge(a, b)
+    return directed, undirected
+
+def graphml(w):
+    path = os.path.join(tempfile.mkdtemp(), "g.graphml")
+    w.to_graphml(path)
+    with open(path) as f:
+        return f.read()
+
+def raises(error, call, fragment):
+    try:
+        call()
+    except error as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+"#;
+
+#[test]
+fn the_same_stream_reads_as_pairs_with_counts() {
+    common::run_with(
+        SETUP,
+        r#"
+directed, undirected = both()
+assert (directed.mode(), undirected.mode()) == ("directed", "undirected_multi")
+
+# Directed: five parallel-or-not edges; undirected: four pairs.
+assert directed.edge_count() == 5 and undirected.edge_count() == 4
+assert directed.get_edge("a", "b")[0] == 1.0
+assert undirected.get_edge("a", "b")[0] == undirected.get_edge("b", "a")[0] == 3.0
+assert undirected.get_edge("c", "b")[0] == 1.0 and directed.get_edge("c", "b") is None
+assert undirected.get_edge("d", "d")[0] == 1.0 and undirected.get_dependents("d") == ["d"]
+
+# Traversal follows both directions of a pair.
+assert sorted(directed.get_dependents("c")) == ["a"]
+assert sorted(undirected.get_dependents("c")) == ["a", "b"]
+assert sorted(undirected.get_dependents("a")) == ["b", "c"]
+
+# Triangles with multiplicity: 3 * 1 * 1 for a-b-c.
+assert directed.count_triangles() == 1 and undirected.count_triangles() == 3
+assert directed.calculate_jaccard_similarity("a", "c") == 0.0
+assert abs(undirected.calculate_jaccard_similarity("a", "c") - 0.2) < 1e-12
+
+# remove_edge drops the whole pair, whichever way round it is named.
+undirected.remove_edge("c", "a")
+assert undirected.get_edge("a", "c") is None and undirected.get_edge("c", "a") is None
+assert undirected.edge_count() == 3
+"#,
+    );
+}
+
+#[test]
+fn exports_and_snapshots_keep_the_mode() {
+    common::run_with(
+        SETUP,
+        r#"
+directed, undirected = both()
+loaded = rc.GraphWalker.from_json(undirected.to_json())
+assert loaded.mode() == "undirected_multi"
+assert loaded.content_digest() == undirected.content_digest() != directed.content_digest()
+assert rc.GraphWalker.from_json(directed.to_json()).mode() == "directed"
+
+dot = undirected.to_dot()
+assert dot.startswith("graph concord {") and "->" not in dot, dot
+edges = [line.strip() for line in dot.splitlines() if "--" in line]
+assert edges == ['"a" -- "b" [weight=3];', '"a" -- "c" [weight=1];',
+                 '"b" -- "c" [weight=1];', '"d" -- "d" [weight=1];'], edges
+assert directed.to_dot().startswith("digraph concord {") and '"b" -> "a";' in directed.to_dot()
+
+text = graphml(undirected)
+assert 'edgedefault="undirected"' in text
+assert text.count("<edge ") == 4 and '<edge source="b" target="a">' not in text
+assert '<edge source="a" target="b">\n      <data key="weight">3</data>' in text, text
+assert 'edgedefault="directed"' in graphml(directed) and graphml(directed).count("<edge ") == 5
+
+raises(rc.ModeError, lambda: undirected.merge(directed), "cannot merge a 'directed' walker into a 'undirected_multi' walker")
+raises(ValueError, lambda: rc.GraphWalker(mode="bogus"), "unknown mode 'bogus' (expected 'directed' or 'undirected_multi')")
+"#,
+    );
+}
+
+#[test]
+fn directed_only_operations_raise_mode_error() {
+    common::run_with(
+        SETUP,
+        r#"
+directed, undirected = both()
+assert issubclass(rc.ModeError, ValueError)
+calls = {
+    "detect_cycles": lambda w: w.detect_cycles(),
+    "topological_sort": lambda w: w.topological_sort(),
+    "topological_layers": lambda w: w.topological_layers(),
+    "propagate_invalidation_ordered": lambda w: w.propagate_invalidation_ordered("a"),
+    "strongly_connected_components": lambda w: w.strongly_connected_components(),
+    "condensation": lambda w: w.condensation(),
+    "split_node": lambda w: w.split_node("c", [("c1", ["a"], [])]),
+    "add_edge with a weight": lambda w: w.add_edge("a", "c", weight=2.0),
+    "add_edge_labeled": lambda w: w.add_edge_labeled("a", "c", "feeds"),
+}
+for name, call in calls.items():
+    raises(rc.ModeError, lambda: call(undirected), name + " is only defined for directed graphs (this walker is 'undirected_multi')")
+for option in ({"enforce_dag": True}, {"cycle_check_steps": 5}):
+    raises(rc.ModeError, lambda: rc.GraphWalker(mode="undirected_multi", **option), list(option)[0] + " is only defined for directed graphs")
+assert undirected.edge_count() == 4 and undirected.get_edge("a", "c")[0] == 1.0
+
+# The directed walker answers the same calls: SCCs see the a-b-c cycle.
+assert sorted(map(sorted, directed.strongly_connected_components())) == [["a", "b", "c"], ["d"]]
+assert len(directed.detect_cycles()) > 0
+raises(ValueError, directed.topological_sort, "cycle")
+"#,
+    );
+}
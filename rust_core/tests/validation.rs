@@ -0,0 +1,81 @@
+//! `validate` and `repair`: dangling references, self-loops, duplicate edges and
+//! the empty id are each detected with capped lists and full counts, repaired only
+//! when selected, and a repair replays from the event log.
+
+mod common;
+
+const SETUP: &str = r#"
+def broken():
+    w = rc.GraphWalker(record_events=True)
+    w.add_node("a", ["b", "b", "a", "ghost", ""])
+    w.add_node("b", ["a", "b"])
+    w.add_node("", ["a"])
+    # An edge to a tombstoned id is not dangling.
+    w.add_node("t", [])
+    w.add_edge("a", "t")
+    w.tombstone_node("t", 1.0)
+    return w
+
+def issues(r):
+    return {"dangling": (r.dangling, r.dangling_count), "self_loops": (r.self_loops, r.self_loop_count),
+            "duplicate_edges": (r.duplicate_edges, r.duplicate_edge_count), "empty_ids": (r.empty_ids, r.empty_id_count)}
+"#;
+
+#[test]
+fn each_pathology_is_detected() {
+    common::run_with(
+        SETUP,
+        r#"
+w = broken()
+r = w.validate()
+assert issues(r) == {
+    "dangling": ([("a", "ghost")], 1),
+    "self_loops": ([("a", "a"), ("b", "b")], 2),
+    "duplicate_edges": ([("a", "b")], 1),
+    "empty_ids": ([("", "a"), ("a", "")], 2),
+}
+assert r.empty_id_node and not r.is_valid
+text = repr(r)
+assert text.startswith("ValidationReport (issues found)") and '    "a" -> "ghost"' in text
+assert "the empty id is a node" in text
+# The lists are capped; the counts are not.
+capped = w.validate(limit=1)
+assert capped.self_loops == [("a", "a")] and capped.self_loop_count == 2
+assert capped.empty_ids == [("", "a")] and capped.empty_id_count == 2
+
+clean = rc.GraphWalker()
+clean.add_node("x", ["y"])
+clean.add_node("y", [])
+assert clean.validate().is_valid and not clean.validate().empty_id_node
+assert all(count == 0 for _, count in issues(clean.validate()).values())
+"#,
+    );
+}
+
+#[test]
+fn repair_fixes_only_what_is_selected() {
+    common::run_with(
+        SETUP,
+        r#"
+w = broken()
+assert w.repair() == {"dangling": 0, "self_loops": 0, "duplicate_edges": 0}
+assert w.get_dependents("a") == ["b", "b", "a", "ghost", "", "t"]
+assert w.repair(drop_dangling=True) == {"dangling": 1, "self_loops": 0, "duplicate_edges": 0}
+assert w.get_dependents("a") == ["b", "b", "a", "", "t"]
+r = w.validate()
+assert r.dangling_count == 0 and r.self_loop_count == 2 and r.duplicate_edge_count == 1
+
+assert w.repair(dedupe_edges=True, drop_self_loops=True) == {"dangling": 0, "self_loops": 2, "duplicate_edges": 1}
+assert w.get_dependents("a") == ["b", "", "t"] and w.get_dependents("b") == ["a"]
+assert w.repair(True, True, True) == {"dangling": 0, "self_loops": 0, "duplicate_edges": 0}
+# Edges touching the empty id are never removed.
+r = w.validate()
+assert r.empty_id_count == 2 and not r.is_valid
+assert (r.dangling_count, r.self_loop_count, r.duplicate_edge_count) == (0, 0, 0)
+
+replayed = rc.GraphWalker()
+replayed.replay_events(w.event_log())
+assert all(replayed.get_dependents(id) == w.get_dependents(id) for id in w.node_ids())
+"#,
+    );
+}
@@ -0,0 +1,95 @@
+//! `record_outcome`: first-hop weights converging toward observed outcomes, the
+//! logged `set_weights` events, the rejected calls, and `weight_stats`.
+
+mod common;
+
+const SETUP: &str = r#"
+def weight(w, a, b):
+    return w.get_edge(a, b)[0]
+
+def raises(error, call, fragment):
+    try:
+        call()
+    except error as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+"#;
+
+#[test]
+fn weights_converge_toward_observed_outcomes() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker(record_events=True)
+w.add_node("a", ["always", "never", "half"], weights=[0.5, 0.5, 0.5])
+w.add_node("always", ["deep"])
+w.propagate_and_compare("a")
+
+first = w.record_outcome("a", ["always", "deep", "surprise"])
+assert first == {"adjusted": 3, "unpredicted": ["surprise"]}, first
+# One step of 0.1 toward 1.0 or 0.0; only first-hop edges move.
+assert abs(weight(w, "a", "always") - 0.55) < 1e-12
+assert abs(weight(w, "a", "never") - 0.45) < 1e-12
+assert abs(weight(w, "a", "half") - 0.45) < 1e-12
+assert weight(w, "always", "deep") == 1.0
+
+for i in range(200):
+    changed = ["always"] + (["half"] if i % 2 else [])
+    w.record_outcome("a", changed, learning_rate=0.2)
+    for id in ("always", "never", "half"):
+        assert 0.0 <= weight(w, "a", id) <= 1.0
+assert weight(w, "a", "always") > 0.99
+assert weight(w, "a", "never") < 0.01
+assert 0.3 < weight(w, "a", "half") < 0.7
+
+# The new weights are logged, so replay reproduces them without the prediction.
+replica = rc.GraphWalker()
+replica.replay_events(w.event_log())
+for id in ("always", "never", "half"):
+    assert weight(replica, "a", id) == weight(w, "a", id)
+
+# learning_rate=0 changes nothing; 1 jumps straight to the outcome.
+before = weight(w, "a", "half")
+w.record_outcome("a", [], learning_rate=0.0)
+assert weight(w, "a", "half") == before
+w.record_outcome("a", ["never"], learning_rate=1.0)
+assert (weight(w, "a", "always"), weight(w, "a", "never"), weight(w, "a", "half")) == (0.0, 1.0, 0.0)
+"#,
+    );
+}
+
+#[test]
+fn outcomes_need_a_stored_prediction_and_a_valid_rate() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+w.add_node("a", ["b"])
+raises(ValueError, lambda: w.record_outcome("a", ["b"]), "call propagate_and_compare first")
+w.propagate_and_compare("a")
+for rate in (-0.1, 1.5, float("nan")):
+    raises(ValueError, lambda: w.record_outcome("a", ["b"], learning_rate=rate), "learning_rate must be in [0, 1]")
+assert weight(w, "a", "b") == 1.0
+w.clear_comparisons()
+raises(ValueError, lambda: w.record_outcome("a", ["b"]), "no stored propagation result for 'a'")
+"#,
+    );
+}
+
+#[test]
+fn weight_stats_summarize_the_distribution() {
+    common::run_with(
+        SETUP,
+        r#"
+empty = rc.GraphWalker().weight_stats()
+assert empty == {"edges": 0, "min": None, "max": None, "mean": None, "p50": None, "p90": None,
+                 "histogram": [0] * 10}, empty
+w = rc.GraphWalker()
+w.add_node("a", ["b", "c", "d", "e", "f"], weights=[0.0, 0.25, 0.5, 0.75, 1.0])
+stats = w.weight_stats()
+assert stats == {"edges": 5, "min": 0.0, "max": 1.0, "mean": 0.5, "p50": 0.5, "p90": 1.0,
+                 "histogram": [1, 0, 1, 0, 0, 1, 0, 1, 0, 1]}, stats
+"#,
+    );
+}
@@ -0,0 +1,126 @@
+//! Edge weights: storing and changing them, weighted versus unweighted PageRank on
+//! a small asymmetric graph, unweighted calls unchanged, and Dijkstra in
+//! `shortest_path_weighted` against a Python reference.
+
+mod common;
+
+const SETUP: &str = r#"
+import heapq, random
+
+def fan(weights=None):
+    w = rc.GraphWalker()
+    w.add_node("a", ["b", "c"], weights=weights)
+    w.add_node("b", [])
+    w.add_node("c", [])
+    return w
+
+def dijkstra(edges, s, t):
+    best, heap = {s: 0.0}, [(0.0, s)]
+    while heap:
+        cost, v = heapq.heappop(heap)
+        if v == t:
+            return cost
+        if cost > best[v]:
+            continue
+        for a, b, c in edges:
+            if a == v and cost + c < best.get(b, float("inf")):
+                best[b] = cost + c
+                heapq.heappush(heap, (cost + c, b))
+    return None
+
+def raises(error, call, fragment):
+    try:
+        call()
+    except error as e:
+        assert fragment in str(e), str(e)
+    else:
+        raise AssertionError("no " + error.__name__)
+"#;
+
+#[test]
+fn weights_are_stored_and_validated() {
+    common::run_with(
+        SETUP,
+        r#"
+w = fan([3.0, 1.0])
+assert w.get_edge("a", "b")[0] == 3.0 and w.get_edge("a", "c")[0] == 1.0
+assert fan().get_edge("a", "b")[0] == 1.0
+w.add_edge("b", "c", weight=0.5)
+w.set_edge_weight("a", "b", 2.0)
+assert w.get_edge("a", "b")[0] == 2.0 and w.get_edge("b", "c")[0] == 0.5
+# add_edge without a weight leaves an existing edge's weight alone; with one, sets it.
+w.add_edge("b", "c")
+assert w.get_edge("b", "c")[0] == 0.5 and w.get_dependents("b") == ["c"]
+w.add_edge("b", "c", weight=4.0)
+assert w.get_edge("b", "c")[0] == 4.0 and w.get_dependents("b") == ["c"]
+
+raises(ValueError, lambda: w.add_node("q", ["r"], weights=[1.0, 2.0]), "got 2 weights for 1 dependents")
+raises(ValueError, lambda: w.add_edge("a", "b", weight=float("nan")), "edge weight must be finite, got NaN")
+raises(ValueError, lambda: w.set_edge_weight("a", "b", float("inf")), "edge weight must be finite, got inf")
+raises(KeyError, lambda: w.set_edge_weight("a", "zz", 1.0), "no edge a -> zz")
+assert w.get_edge("a", "b")[0] == 2.0 and not w.has_node("q")
+"#,
+    );
+}
+
+#[test]
+fn pagerank_follows_the_weights() {
+    common::run_with(
+        SETUP,
+        r#"
+weighted = fan([3.0, 1.0]).calculate_pagerank(100, 0.85)
+plain = fan().calculate_pagerank(100, 0.85)
+# Unweighted, b and c split a's rank evenly; weighted, b gets three quarters.
+assert abs(plain["b"] - plain["c"]) < 1e-12
+assert weighted["b"] > plain["b"] > weighted["c"]
+assert abs(weighted["a"] - plain["a"]) < 1e-12
+share = 0.85 * weighted["a"]
+assert abs((weighted["b"] - weighted["c"]) - share * (0.75 - 0.25)) < 1e-9
+assert abs(sum(weighted.values()) - 1.0) < 1e-9
+# Explicit weights of 1.0 are the unweighted call.
+assert fan([1.0, 1.0]).calculate_pagerank(100, 0.85) == plain
+# Scaling every weight changes nothing.
+scaled = fan([30.0, 10.0]).calculate_pagerank(100, 0.85)
+assert all(abs(scaled[k] - weighted[k]) < 1e-12 for k in weighted)
+"#,
+    );
+}
+
+#[test]
+fn dijkstra_finds_the_cheapest_path() {
+    common::run_with(
+        SETUP,
+        r#"
+w = rc.GraphWalker()
+for a, b, c in [("s", "a", 1.0), ("a", "t", 1.0), ("s", "t", 5.0), ("s", "b", 0.5), ("b", "t", 3.0)]:
+    w.add_edge(a, b, weight=c)
+# Fewest hops and lowest cost disagree.
+assert w.shortest_path("s", "t") == ["s", "t"]
+assert w.shortest_path_weighted("s", "t") == (2.0, ["s", "a", "t"])
+assert w.shortest_path_weighted("s", "s") == (0.0, ["s"])
+assert w.shortest_path_weighted("a", "b") is None
+raises(KeyError, lambda: w.shortest_path_weighted("s", "nope"), "nope")
+w.add_edge("a", "n", weight=-2.0)
+w.add_edge("n", "t")
+raises(ValueError, lambda: w.shortest_path_weighted("s", "t"), "edge a -> n has negative weight -2")
+
+for seed in range(5):
+    r = random.Random(seed)
+    edges = [("n%d" % r.randrange(15), "n%d" % r.randrange(15), r.choice([0.0, 0.5, 1.0, 2.5, 4.0])) for _ in range(40)]
+    g = rc.GraphWalker()
+    for a, b, c in edges:
+        g.add_edge(a, b, weight=c)
+    # An edge added again takes the later weight; the reference sees what was stored.
+    stored = [(a, b, g.get_edge(a, b)[0]) for a, b, _ in edges]
+    for s in g.node_ids():
+        for t in g.node_ids():
+            found, expected = g.shortest_path_weighted(s, t), dijkstra(stored, s, t)
+            if expected is None:
+                assert found is None, (seed, s, t)
+                continue
+            cost, path = found
+            assert abs(cost - expected) < 1e-12 and path[0] == s and path[-1] == t, (seed, s, t)
+            assert abs(sum(g.get_edge(a, b)[0] for a, b in zip(path, path[1:])) - cost) < 1e-12
+"#,
+    );
+}